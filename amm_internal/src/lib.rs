@@ -2,207 +2,2214 @@
 
 extern crate alloc;
 
+use alloc::borrow::Cow;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::format;
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+/// The category of an [`AmmError`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AmmErrorKind {
+  /// The data is not well-formed for its format.
+  Syntax,
+  /// A value has the wrong type or is out of range.
+  InvalidValue,
+  /// A required field is missing.
+  MissingField,
+  /// A type tag or variant index does not name any known variant.
+  UnknownVariant,
+  /// The data ended before a complete value could be read.
+  UnexpectedEnd,
+  /// The data was written by an unsupported version of its format.
+  UnsupportedVersion,
+  /// The data is not in a recognized or supported storage format.
+  UnsupportedFormat,
+  /// Reading or writing the underlying storage failed.
+  Io,
+  /// Any other failure reported by a storage backend.
+  Other,
+}
+
+/// An error raised while loading or deserializing an AMM document.
+///
+/// Errors record the path of the value that failed within the document, such
+/// as `parts[2].sections[0].content[14]`, along with its byte offset whenever
+/// the position is known.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AmmError {
+  pub kind: AmmErrorKind,
+  pub message: String,
+  pub path: String,
+  pub offset: Option<usize>,
+}
+
+impl AmmError {
+  #[must_use]
+  pub fn new(kind: AmmErrorKind, message: impl Into<String>) -> Self {
+    Self {
+      kind,
+      message: message.into(),
+      path: String::new(),
+      offset: None,
+    }
+  }
+
+  /// Sets the byte offset at which the error occurred.
+  #[must_use]
+  pub fn at(mut self, offset: usize) -> Self {
+    self.offset = Some(offset);
+    self
+  }
+
+  /// Shifts the offset of an error raised while deserializing `inner`, which
+  /// must be a subslice of `outer`, so that it is relative to `outer` instead.
+  #[must_use]
+  pub fn within(mut self, outer: &str, inner: &str) -> Self {
+    let base = (inner.as_ptr() as usize).saturating_sub(outer.as_ptr() as usize);
+    self.offset = Some(base + self.offset.unwrap_or(0));
+    self
+  }
+
+  /// Prepends the named field to the path of the error.
+  #[must_use]
+  pub fn in_field(mut self, name: &str) -> Self {
+    if !self.path.is_empty() && !self.path.starts_with('[') {
+      self.path.insert(0, '.');
+    }
+    self.path.insert_str(0, name);
+    self
+  }
+
+  /// Prepends the index of a collection item to the path of the error.
+  #[must_use]
+  pub fn in_index(mut self, index: usize) -> Self {
+    if !self.path.is_empty() && !self.path.starts_with('[') {
+      self.path.insert(0, '.');
+    }
+    self.path.insert_str(0, &format!("[{index}]"));
+    self
+  }
+
+  /// Returns the one-based line and column of the error within the
+  /// `document` it was raised for, if its offset is known.
+  #[must_use]
+  pub fn line_column(&self, document: &str) -> Option<(usize, usize)> {
+    let preceding = document.as_bytes().get(..self.offset?)?;
+    let line_start = preceding
+      .iter()
+      .rposition(|byte| *byte == b'\n')
+      .map_or(0, |idx| idx + 1);
+    let column =
+      core::str::from_utf8(&preceding[line_start..]).map_or(preceding.len() - line_start, |line| line.chars().count());
+    Some((preceding.iter().filter(|byte| **byte == b'\n').count() + 1, column + 1))
+  }
+}
+
+impl core::fmt::Display for AmmError {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    write!(f, "{}", self.message)?;
+    if !self.path.is_empty() {
+      write!(f, " at {}", self.path)?;
+    }
+    match self.offset {
+      Some(offset) => write!(f, " (byte {offset})"),
+      None => Ok(()),
+    }
+  }
+}
+
+impl From<String> for AmmError {
+  fn from(message: String) -> Self {
+    Self::new(AmmErrorKind::Other, message)
+  }
+}
+
+impl From<&str> for AmmError {
+  fn from(message: &str) -> Self {
+    Self::new(AmmErrorKind::Other, message)
+  }
+}
+
+impl From<AmmError> for String {
+  fn from(error: AmmError) -> Self {
+    error.to_string()
+  }
+}
+
+/// Layout options for serialized JSON.
+///
+/// The default options produce compact single-line output. Pretty-printed
+/// output places every array item and object member on its own line, while
+/// canonical output sorts object keys and normalises numbers so that equal
+/// values always serialize to identical text.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct JsonOptions {
+  /// The number of spaces per level of indentation, or zero for compact output.
+  pub indent: usize,
+  /// Whether object keys are sorted and numbers normalised.
+  pub canonical: bool,
+  level: usize,
+}
+
+impl JsonOptions {
+  #[must_use]
+  pub fn compact() -> Self {
+    Self::default()
+  }
+
+  #[must_use]
+  pub fn pretty(indent: usize) -> Self {
+    Self {
+      indent,
+      ..Self::default()
+    }
+  }
+
+  #[must_use]
+  pub fn canonical(mut self, canonical: bool) -> Self {
+    self.canonical = canonical;
+    self
+  }
+
+  /// Returns the options for values nested one level deeper than this one.
+  #[must_use]
+  pub fn nested(&self) -> Self {
+    Self {
+      level: self.level + 1,
+      ..*self
+    }
+  }
+
+  /// Writes the line break and indentation preceding a value at the current
+  /// level, which is empty for compact output.
+  ///
+  /// # Errors
+  /// Returns an error if writing to `out` fails.
+  pub fn write_line_break(&self, out: &mut dyn Write) -> fmt::Result {
+    if self.indent > 0 {
+      write!(out, "\n{:width$}", "", width = self.indent * self.level)?;
+    }
+    Ok(())
+  }
+
+  /// Writes an array holding `items` at the current level.
+  ///
+  /// # Errors
+  /// Returns an error if writing to `out` fails.
+  pub fn write_array<'a, T: JsonSerializer + ?Sized + 'a>(
+    &self,
+    out: &mut dyn Write,
+    items: impl IntoIterator<Item = &'a T>,
+  ) -> fmt::Result {
+    let nested = self.nested();
+    let mut empty = true;
+    out.write_char('[')?;
+    for item in items {
+      if !empty {
+        out.write_char(',')?;
+      }
+      nested.write_line_break(out)?;
+      item.write_json(out, &nested)?;
+      empty = false;
+    }
+    if !empty {
+      self.write_line_break(out)?;
+    }
+    out.write_char(']')
+  }
+
+  /// Writes an object holding `members` at the current level, given their
+  /// unescaped keys, sorting them by key first for canonical output.
+  ///
+  /// # Errors
+  /// Returns an error if writing to `out` fails.
+  pub fn write_object(&self, out: &mut dyn Write, members: &mut [(&str, &dyn JsonSerializer)]) -> fmt::Result {
+    if self.canonical {
+      members.sort_by_key(|(key, _)| *key);
+    }
+    let nested = self.nested();
+    out.write_char('{')?;
+    for (idx, (key, value)) in members.iter().enumerate() {
+      if idx > 0 {
+        out.write_char(',')?;
+      }
+      nested.write_line_break(out)?;
+      write_json_string(out, key)?;
+      out.write_str(if self.indent == 0 { ":" } else { ": " })?;
+      value.write_json(out, &nested)?;
+    }
+    if !members.is_empty() {
+      self.write_line_break(out)?;
+    }
+    out.write_char('}')
+  }
+}
 
 pub trait JsonSerializer {
-  fn serialize_json(&self) -> String;
+  /// Serializes this value as compact JSON.
+  fn serialize_json(&self) -> String {
+    self.serialize_json_with(&JsonOptions::default())
+  }
+
+  /// Serializes this value as JSON laid out according to `options`.
+  fn serialize_json_with(&self, options: &JsonOptions) -> String {
+    let mut json = String::new();
+    // Writing into a string cannot fail
+    let _ = self.write_json(&mut json, options);
+    json
+  }
+
+  /// Streams this value as JSON laid out according to `options` into `out`,
+  /// without building any intermediate strings.
+  ///
+  /// # Errors
+  /// Returns an error if writing to `out` fails.
+  fn write_json(&self, out: &mut dyn Write, options: &JsonOptions) -> fmt::Result;
+}
+
+pub trait JsonDeserializer {
+  /// Deserializes a value from a complete JSON document, which may only be
+  /// surrounded by whitespace.
+  ///
+  /// # Errors
+  /// Returns an error describing the kind and position of the first value
+  /// that could not be deserialized, relative to the start of `json`.
+  fn deserialize_json(json: &str) -> Result<Self, AmmError>
+  where
+    Self: Sized,
+  {
+    let mut reader = JsonReader::new(json);
+    let value = Self::read_json(&mut reader)?;
+    reader.finish()?;
+    Ok(value)
+  }
+
+  /// Reads the next value from `reader` in a single pass, leaving the reader
+  /// positioned right after it.
+  ///
+  /// # Errors
+  /// Returns an error describing the kind and position of the first value
+  /// that could not be deserialized.
+  fn read_json(reader: &mut JsonReader) -> Result<Self, AmmError>
+  where
+    Self: Sized;
+}
+
+macro_rules! impl_json_scalar {
+  ($($scalar:ty),*) => {$(
+    impl JsonSerializer for $scalar {
+      fn write_json(&self, out: &mut dyn Write, _options: &JsonOptions) -> fmt::Result {
+        write!(out, "{self}")
+      }
+    }
+
+    impl JsonDeserializer for $scalar {
+      fn read_json(reader: &mut JsonReader) -> Result<Self, AmmError> {
+        let token = reader.read_scalar()?;
+        token.parse::<$scalar>().map_err(|err| {
+          AmmError::new(AmmErrorKind::InvalidValue, format!("Invalid value \"{token}\": {err}"))
+            .at(reader.position() - token.len())
+        })
+      }
+    }
+  )*};
+}
+
+impl_json_scalar!(bool, u8, u16, u32, usize, i8, i16, i32, isize);
+
+impl JsonSerializer for String {
+  fn write_json(&self, out: &mut dyn Write, _options: &JsonOptions) -> fmt::Result {
+    write_json_string(out, self)
+  }
+}
+
+impl JsonSerializer for &str {
+  fn write_json(&self, out: &mut dyn Write, _options: &JsonOptions) -> fmt::Result {
+    write_json_string(out, self)
+  }
+}
+
+impl JsonDeserializer for String {
+  fn read_json(reader: &mut JsonReader) -> Result<Self, AmmError> {
+    reader.read_string().map(Cow::into_owned)
+  }
+}
+
+/// Missing optional values are written as an empty string, which is read
+/// back as `None` along with `null`.
+impl<T: JsonSerializer> JsonSerializer for Option<T> {
+  fn write_json(&self, out: &mut dyn Write, options: &JsonOptions) -> fmt::Result {
+    match self {
+      Some(value) => value.write_json(out, options),
+      None => out.write_str("\"\""),
+    }
+  }
+}
+
+impl<T: JsonDeserializer> JsonDeserializer for Option<T> {
+  fn read_json(reader: &mut JsonReader) -> Result<Self, AmmError> {
+    if reader.next_is_null() || reader.next_is_empty_string() {
+      reader.skip_value().map(|()| None)
+    } else {
+      T::read_json(reader).map(Some)
+    }
+  }
+}
+
+impl<T: JsonSerializer> JsonSerializer for Vec<T> {
+  fn write_json(&self, out: &mut dyn Write, options: &JsonOptions) -> fmt::Result {
+    options.write_array(out, self)
+  }
+}
+
+impl<T: JsonDeserializer> JsonDeserializer for Vec<T> {
+  fn read_json(reader: &mut JsonReader) -> Result<Self, AmmError> {
+    let mut items = Vec::new();
+    reader.begin_array()?;
+    while reader.next_item()? {
+      items.push(T::read_json(reader).map_err(|err| err.in_index(items.len()))?);
+    }
+    Ok(items)
+  }
+}
+
+impl<T: JsonSerializer> JsonSerializer for BTreeSet<T> {
+  fn write_json(&self, out: &mut dyn Write, options: &JsonOptions) -> fmt::Result {
+    options.write_array(out, self)
+  }
+}
+
+impl<T: JsonDeserializer + Ord> JsonDeserializer for BTreeSet<T> {
+  fn read_json(reader: &mut JsonReader) -> Result<Self, AmmError> {
+    let mut items = BTreeSet::new();
+    reader.begin_array()?;
+    while reader.next_item()? {
+      items.insert(T::read_json(reader).map_err(|err| err.in_index(items.len()))?);
+    }
+    Ok(items)
+  }
+}
+
+impl<V: JsonSerializer> JsonSerializer for BTreeMap<String, V> {
+  fn write_json(&self, out: &mut dyn Write, options: &JsonOptions) -> fmt::Result {
+    let mut members = self
+      .iter()
+      .map(|(key, value)| (key.as_str(), value as &dyn JsonSerializer))
+      .collect::<Vec<_>>();
+    options.write_object(out, &mut members)
+  }
+}
+
+impl<V: JsonDeserializer> JsonDeserializer for BTreeMap<String, V> {
+  fn read_json(reader: &mut JsonReader) -> Result<Self, AmmError> {
+    let mut items = BTreeMap::new();
+    reader.begin_object()?;
+    while let Some(key) = reader.next_key()? {
+      let value = V::read_json(reader).map_err(|err| err.in_field(&key))?;
+      items.insert(key.into_owned(), value);
+    }
+    Ok(items)
+  }
+}
+
+const MAX_JSON_DEPTH: usize = 256;
+
+/// A generic JSON value, used for documents whose layout is not described by
+/// a Rust type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<JsonValue>),
+  Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+  /// Parses a complete JSON document into a generic value.
+  ///
+  /// # Errors
+  /// Returns a syntax error at the byte offset at which the document is not
+  /// valid JSON.
+  pub fn parse(json: &str) -> Result<Self, AmmError> {
+    Self::deserialize_json(json)
+  }
+  /// Returns the value of the given `key` if this value is an object containing it.
+  #[must_use]
+  pub fn get(&self, key: &str) -> Option<&JsonValue> {
+    match self {
+      Self::Object(members) => members.iter().find_map(|(name, value)| (name == key).then_some(value)),
+      _ => None,
+    }
+  }
+
+  #[must_use]
+  pub fn as_bool(&self) -> Option<bool> {
+    match self {
+      Self::Bool(value) => Some(*value),
+      _ => None,
+    }
+  }
+
+  #[must_use]
+  pub fn as_f64(&self) -> Option<f64> {
+    match self {
+      Self::Number(value) => Some(*value),
+      _ => None,
+    }
+  }
+
+  /// Returns the value as an integer if it is a number without a fractional part.
+  #[must_use]
+  pub fn as_i64(&self) -> Option<i64> {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    match self {
+      Self::Number(value) if *value == (*value as i64) as f64 => Some(*value as i64),
+      _ => None,
+    }
+  }
+
+  #[must_use]
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Self::String(value) => Some(value),
+      _ => None,
+    }
+  }
+
+  #[must_use]
+  pub fn as_array(&self) -> Option<&[JsonValue]> {
+    match self {
+      Self::Array(values) => Some(values),
+      _ => None,
+    }
+  }
+}
+
+impl JsonSerializer for JsonValue {
+  fn write_json(&self, out: &mut dyn Write, options: &JsonOptions) -> fmt::Result {
+    match self {
+      Self::Null => out.write_str("null"),
+      Self::Bool(value) => write!(out, "{value}"),
+      // Negative zero is the only number whose shortest representation is not unique
+      Self::Number(value) if options.canonical && *value == 0.0 => out.write_char('0'),
+      Self::Number(value) if value.is_finite() => write!(out, "{value}"),
+      Self::Number(_) => out.write_str("null"),
+      Self::String(value) => write_json_string(out, value),
+      Self::Array(values) => options.write_array(out, values),
+      Self::Object(members) => {
+        let mut members = members
+          .iter()
+          .map(|(name, value)| (name.as_str(), value as &dyn JsonSerializer))
+          .collect::<Vec<_>>();
+        options.write_object(out, &mut members)
+      }
+    }
+  }
+}
+
+impl JsonDeserializer for JsonValue {
+  fn read_json(reader: &mut JsonReader) -> Result<Self, AmmError> {
+    match reader.peek() {
+      Some(b'{') => {
+        let mut members = Vec::new();
+        reader.begin_object()?;
+        while let Some(key) = reader.next_key()? {
+          members.push((key.into_owned(), Self::read_json(reader)?));
+        }
+        Ok(Self::Object(members))
+      }
+      Some(b'[') => {
+        let mut values = Vec::new();
+        reader.begin_array()?;
+        while reader.next_item()? {
+          values.push(Self::read_json(reader)?);
+        }
+        Ok(Self::Array(values))
+      }
+      Some(b'"') => reader.read_string().map(|value| Self::String(value.into_owned())),
+      _ => {
+        let token = reader.read_scalar()?;
+        match token {
+          "null" => Ok(Self::Null),
+          "true" => Ok(Self::Bool(true)),
+          "false" => Ok(Self::Bool(false)),
+          _ => match token.parse::<f64>() {
+            Ok(number) if is_json_number(token.as_bytes()) => Ok(Self::Number(number)),
+            _ => Err(
+              AmmError::new(AmmErrorKind::Syntax, "Invalid JSON: invalid value").at(reader.position() - token.len()),
+            ),
+          },
+        }
+      }
+    }
+  }
+}
+
+/// Returns whether `token` follows the JSON number grammar, which is stricter
+/// than the formats accepted when parsing Rust numbers.
+fn is_json_number(token: &[u8]) -> bool {
+  let digits = |idx: &mut usize| {
+    let first = *idx;
+    while token.get(*idx).is_some_and(u8::is_ascii_digit) {
+      *idx += 1;
+    }
+    *idx > first
+  };
+  let mut idx = usize::from(token.first() == Some(&b'-'));
+  let leading_zero = token.get(idx) == Some(&b'0');
+  let start = idx;
+  if !digits(&mut idx) || (leading_zero && idx - start > 1) {
+    return false;
+  }
+  if token.get(idx) == Some(&b'.') {
+    idx += 1;
+    if !digits(&mut idx) {
+      return false;
+    }
+  }
+  if matches!(token.get(idx), Some(b'e' | b'E')) {
+    idx += 1;
+    if matches!(token.get(idx), Some(b'+' | b'-')) {
+      idx += 1;
+    }
+    if !digits(&mut idx) {
+      return false;
+    }
+  }
+  idx == token.len()
+}
+
+/// Writes `text` as a quoted JSON string, escaping any characters that may not
+/// appear verbatim.
+fn write_json_string(out: &mut dyn Write, text: &str) -> fmt::Result {
+  out.write_char('"')?;
+  let mut start = 0;
+  for (idx, ch) in text.char_indices() {
+    let escape = match ch {
+      '"' => "\\\"",
+      '\\' => "\\\\",
+      '\n' => "\\n",
+      '\r' => "\\r",
+      '\t' => "\\t",
+      '\u{8}' => "\\b",
+      '\u{c}' => "\\f",
+      ch if u32::from(ch) < 0x20 => "",
+      _ => continue,
+    };
+    out.write_str(&text[start..idx])?;
+    if escape.is_empty() {
+      write!(out, "\\u{:04x}", u32::from(ch))?;
+    } else {
+      out.write_str(escape)?;
+    }
+    start = idx + ch.len_utf8();
+  }
+  out.write_str(&text[start..])?;
+  out.write_char('"')
+}
+
+/// A cursor that reads JSON values in a single forward pass over a document.
+///
+/// Strings without escape sequences are borrowed from the document, and every
+/// error is reported at its byte offset within it.
+pub struct JsonReader<'a> {
+  data: &'a [u8],
+  position: usize,
+  depth: usize,
+}
+
+impl<'a> JsonReader<'a> {
+  #[must_use]
+  pub fn new(json: &'a str) -> Self {
+    Self {
+      data: json.as_bytes(),
+      position: 0,
+      depth: 0,
+    }
+  }
+
+  /// Returns the byte offset of the reader within the document.
+  #[must_use]
+  pub fn position(&self) -> usize {
+    self.position
+  }
+
+  /// Skips any whitespace and returns the byte offset at which the next value starts.
+  pub fn value_start(&mut self) -> usize {
+    self.skip_whitespace();
+    self.position
+  }
+
+  fn error(&self, message: &str) -> AmmError {
+    let kind = if self.position < self.data.len() {
+      AmmErrorKind::Syntax
+    } else {
+      AmmErrorKind::UnexpectedEnd
+    };
+    AmmError::new(kind, format!("Invalid JSON: {message}")).at(self.position)
+  }
+
+  fn skip_whitespace(&mut self) {
+    while matches!(self.data.get(self.position), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+      self.position += 1;
+    }
+  }
+
+  fn peek(&mut self) -> Option<u8> {
+    self.skip_whitespace();
+    self.data.get(self.position).copied()
+  }
+
+  fn expect(&mut self, byte: u8) -> Result<(), AmmError> {
+    if self.peek() == Some(byte) {
+      self.position += 1;
+      Ok(())
+    } else {
+      Err(self.error(&format!("expected '{}'", char::from(byte))))
+    }
+  }
+
+  /// Returns whether the previous token is the opening bracket of the current
+  /// array or object, so that no separator may precede the next value.
+  fn after_opening(&self, bracket: u8) -> bool {
+    self.data[..self.position]
+      .iter()
+      .rev()
+      .find(|byte| !matches!(byte, b' ' | b'\t' | b'\n' | b'\r'))
+      == Some(&bracket)
+  }
+
+  /// Returns whether the next value is a JSON `null`.
+  pub fn next_is_null(&mut self) -> bool {
+    self.peek() == Some(b'n')
+      && self.data[self.position..].starts_with(b"null")
+      && self
+        .data
+        .get(self.position + 4)
+        .is_none_or(|byte| matches!(byte, b',' | b']' | b'}' | b' ' | b'\t' | b'\n' | b'\r'))
+  }
+
+  /// Returns whether the next value is an object.
+  pub fn next_is_object(&mut self) -> bool {
+    self.peek() == Some(b'{')
+  }
+
+  fn next_is_empty_string(&mut self) -> bool {
+    self.peek() == Some(b'"') && self.data.get(self.position + 1) == Some(&b'"')
+  }
+
+  /// Consumes the opening brace of an object, after which its members are
+  /// read with [`JsonReader::next_key`].
+  ///
+  /// # Errors
+  /// Returns an error if the next value is not an object or is nested too deeply.
+  pub fn begin_object(&mut self) -> Result<(), AmmError> {
+    self.expect(b'{')?;
+    self.depth += 1;
+    if self.depth > MAX_JSON_DEPTH {
+      return Err(self.error("nesting is too deep"));
+    }
+    Ok(())
+  }
+
+  /// Returns the key of the next member of the current object, leaving the
+  /// reader positioned at its value, or `None` after consuming the closing
+  /// brace of the object.
+  ///
+  /// # Errors
+  /// Returns an error if the object is malformed.
+  pub fn next_key(&mut self) -> Result<Option<Cow<'a, str>>, AmmError> {
+    let first = self.after_opening(b'{');
+    match self.peek() {
+      Some(b'}') => {
+        self.position += 1;
+        self.depth = self.depth.saturating_sub(1);
+        return Ok(None);
+      }
+      Some(b',') if !first => self.position += 1,
+      _ if !first => return Err(self.error("expected ',' or '}'")),
+      _ => (),
+    }
+    if self.peek() != Some(b'"') {
+      return Err(self.error("expected an object key"));
+    }
+    let key = self.read_string()?;
+    self.expect(b':')?;
+    Ok(Some(key))
+  }
+
+  /// Consumes the opening bracket of an array, after which its items are
+  /// read while [`JsonReader::next_item`] returns `true`.
+  ///
+  /// # Errors
+  /// Returns an error if the next value is not an array or is nested too deeply.
+  pub fn begin_array(&mut self) -> Result<(), AmmError> {
+    self.expect(b'[')?;
+    self.depth += 1;
+    if self.depth > MAX_JSON_DEPTH {
+      return Err(self.error("nesting is too deep"));
+    }
+    Ok(())
+  }
+
+  /// Returns whether another item follows in the current array, leaving the
+  /// reader positioned at it, or consumes the closing bracket of the array.
+  ///
+  /// # Errors
+  /// Returns an error if the array is malformed.
+  pub fn next_item(&mut self) -> Result<bool, AmmError> {
+    let first = self.after_opening(b'[');
+    match self.peek() {
+      Some(b']') => {
+        self.position += 1;
+        self.depth = self.depth.saturating_sub(1);
+        return Ok(false);
+      }
+      Some(b',') if !first => self.position += 1,
+      _ if !first => return Err(self.error("expected ',' or ']'")),
+      _ => (),
+    }
+    match self.peek() {
+      Some(b']') | None => Err(self.error("expected a value")),
+      _ => Ok(true),
+    }
+  }
+
+  /// Reads the next value as a string, borrowing it from the document unless
+  /// it contains escape sequences.
+  ///
+  /// # Errors
+  /// Returns an error if the next value is not a valid string.
+  pub fn read_string(&mut self) -> Result<Cow<'a, str>, AmmError> {
+    if self.peek() != Some(b'"') {
+      return Err(self.error("expected a string"));
+    }
+    self.position += 1;
+    let start = self.position;
+    while self
+      .data
+      .get(self.position)
+      .is_some_and(|byte| *byte != b'"' && *byte != b'\\' && *byte >= 0x20)
+    {
+      self.position += 1;
+    }
+    let text = if self.data.get(self.position) == Some(&b'"') {
+      let data: &'a [u8] = self.data;
+      Cow::Borrowed(core::str::from_utf8(&data[start..self.position]).map_err(|_| self.error("invalid UTF-8"))?)
+    } else {
+      self.position = start;
+      Cow::Owned(self.parse_characters()?)
+    };
+    if self.data.get(self.position) == Some(&b'"') {
+      self.position += 1;
+      Ok(text)
+    } else {
+      Err(self.error("unterminated string"))
+    }
+  }
+
+  /// Reads the next number, boolean or `null` as its unparsed text.
+  ///
+  /// # Errors
+  /// Returns an error if no such value follows.
+  pub fn read_scalar(&mut self) -> Result<&'a str, AmmError> {
+    self.skip_whitespace();
+    let start = self.position;
+    while self
+      .data
+      .get(self.position)
+      .is_some_and(|byte| !matches!(byte, b',' | b']' | b'}' | b':' | b' ' | b'\t' | b'\n' | b'\r'))
+    {
+      self.position += 1;
+    }
+    if self.position == start {
+      return Err(self.error("expected a value"));
+    }
+    let data: &'a [u8] = self.data;
+    core::str::from_utf8(&data[start..self.position]).map_err(|_| self.error("invalid UTF-8"))
+  }
+
+  /// Skips over the next value, including any values nested within it.
+  ///
+  /// # Errors
+  /// Returns an error if the value is unterminated.
+  pub fn skip_value(&mut self) -> Result<(), AmmError> {
+    match self.peek() {
+      Some(b'"') => self.read_string().map(drop),
+      Some(b'{' | b'[') => {
+        let mut depth = 0_usize;
+        loop {
+          match self.data.get(self.position) {
+            Some(b'"') => {
+              self.read_string()?;
+              continue;
+            }
+            Some(b'{' | b'[') => depth += 1,
+            Some(b'}' | b']') => {
+              depth -= 1;
+              if depth == 0 {
+                self.position += 1;
+                return Ok(());
+              }
+            }
+            Some(_) => (),
+            None => return Err(self.error("unterminated value")),
+          }
+          self.position += 1;
+        }
+      }
+      _ => self.read_scalar().map(drop),
+    }
+  }
+
+  /// Returns the `_type` tag of the object that follows without consuming it.
+  ///
+  /// The tag is found immediately when it is the first member of the object,
+  /// as written by the AMM serializers.
+  ///
+  /// # Errors
+  /// Returns an error if the object is malformed before its tag is found.
+  pub fn peek_type(&mut self) -> Result<Option<Cow<'a, str>>, AmmError> {
+    self.peek_tag("_type")
+  }
+
+  /// Returns the string stored under `key` in the object that follows
+  /// without consuming it, for types tagged under a key other than `_type`.
+  ///
+  /// # Errors
+  /// Returns an error if the object is malformed before its tag is found.
+  pub fn peek_tag(&mut self, key: &str) -> Result<Option<Cow<'a, str>>, AmmError> {
+    let (position, depth) = (self.position, self.depth);
+    let mut tag = None;
+    self.begin_object()?;
+    while let Some(member) = self.next_key()? {
+      if member == key {
+        tag = Some(self.read_string()?);
+        break;
+      }
+      self.skip_value()?;
+    }
+    (self.position, self.depth) = (position, depth);
+    Ok(tag)
+  }
+
+  /// Checks that nothing but whitespace follows the values read so far.
+  ///
+  /// # Errors
+  /// Returns an error if any other characters remain.
+  pub fn finish(&mut self) -> Result<(), AmmError> {
+    match self.peek() {
+      Some(_) => Err(self.error("unexpected trailing characters")),
+      None => Ok(()),
+    }
+  }
+
+  fn parse_hex(&mut self) -> Result<u32, AmmError> {
+    let hex = self
+      .data
+      .get(self.position..self.position + 4)
+      .and_then(|hex| core::str::from_utf8(hex).ok())
+      .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+      .ok_or_else(|| self.error("invalid unicode escape"))?;
+    self.position += 4;
+    Ok(hex)
+  }
+
+  /// Decodes characters and escape sequences up to the next unescaped quote
+  /// or the end of the data, leaving the position at that quote.
+  fn parse_characters(&mut self) -> Result<String, AmmError> {
+    let mut text = String::new();
+    loop {
+      let start = self.position;
+      while self
+        .data
+        .get(self.position)
+        .is_some_and(|byte| *byte != b'"' && *byte != b'\\' && *byte >= 0x20)
+      {
+        self.position += 1;
+      }
+      text.push_str(core::str::from_utf8(&self.data[start..self.position]).map_err(|_| self.error("invalid UTF-8"))?);
+      match self.data.get(self.position) {
+        Some(b'"') | None => return Ok(text),
+        Some(b'\\') => {
+          self.position += 1;
+          let escape = self.data.get(self.position).copied();
+          self.position += 1;
+          match escape {
+            Some(b'"') => text.push('"'),
+            Some(b'\\') => text.push('\\'),
+            Some(b'/') => text.push('/'),
+            Some(b'b') => text.push('\u{8}'),
+            Some(b'f') => text.push('\u{c}'),
+            Some(b'n') => text.push('\n'),
+            Some(b'r') => text.push('\r'),
+            Some(b't') => text.push('\t'),
+            Some(b'u') => {
+              let mut code = self.parse_hex()?;
+              if (0xd800..0xdc00).contains(&code) {
+                // Characters outside the basic multilingual plane are escaped as surrogate pairs
+                if !self.data[self.position..].starts_with(b"\\u") {
+                  return Err(self.error("expected a low surrogate"));
+                }
+                self.position += 2;
+                let low = self.parse_hex()?;
+                if !(0xdc00..0xe000).contains(&low) {
+                  return Err(self.error("invalid surrogate pair"));
+                }
+                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+              }
+              text.push(char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))?);
+            }
+            _ => return Err(self.error("invalid escape sequence")),
+          }
+        }
+        Some(_) => return Err(self.error("unescaped control character in string")),
+      }
+    }
+  }
+}
+
+pub trait BinarySerializer {
+  /// Appends the compact binary encoding of this value to `output`.
+  fn serialize_binary(&self, output: &mut Vec<u8>);
+}
+
+pub trait BinaryDeserializer {
+  /// Decodes a value from the start of `data`, advancing it past the bytes
+  /// that were consumed.
+  ///
+  /// # Errors
+  /// Returns an error if `data` ends early or does not contain a valid
+  /// encoding of the value, leaving `data` at the position of the failure.
+  fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError>
+  where
+    Self: Sized;
+}
+
+fn read_binary_bytes<'a>(data: &mut &'a [u8], length: usize) -> Result<&'a [u8], AmmError> {
+  if data.len() < length {
+    return Err(AmmError::new(
+      AmmErrorKind::UnexpectedEnd,
+      "Unexpected end of binary data",
+    ));
+  }
+  let (bytes, rest) = data.split_at(length);
+  *data = rest;
+  Ok(bytes)
+}
+
+impl BinarySerializer for u64 {
+  fn serialize_binary(&self, output: &mut Vec<u8>) {
+    // Integers are stored as little-endian base-128 varints
+    let mut value = *self;
+    while value >= 0x80 {
+      output.push((value & 0x7f) as u8 | 0x80);
+      value >>= 7;
+    }
+    output.push(value as u8);
+  }
+}
+
+impl BinaryDeserializer for u64 {
+  fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+      let byte = read_binary_bytes(data, 1)?[0];
+      if (shift == 63 && byte > 1) || (shift > 0 && byte == 0) {
+        return Err(AmmError::new(
+          AmmErrorKind::InvalidValue,
+          "Invalid or non-canonical binary integer",
+        ));
+      }
+      value |= u64::from(byte & 0x7f) << shift;
+      if byte & 0x80 == 0 {
+        return Ok(value);
+      }
+    }
+    Err(AmmError::new(AmmErrorKind::InvalidValue, "Binary integer is too long"))
+  }
 }
 
-pub trait JsonDeserializer {
-  /// TODO
-  ///
-  /// # Errors
-  /// TODO
-  fn deserialize_json(json: &str) -> Result<Self, String>
-  where
-    Self: Sized;
+macro_rules! impl_binary_unsigned {
+  ($($int:ty),*) => {$(
+    impl BinarySerializer for $int {
+      fn serialize_binary(&self, output: &mut Vec<u8>) {
+        (*self as u64).serialize_binary(output);
+      }
+    }
+
+    impl BinaryDeserializer for $int {
+      fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+        let value = u64::deserialize_binary(data)?;
+        Self::try_from(value)
+          .map_err(|_| AmmError::new(AmmErrorKind::InvalidValue, format!("Binary integer {value} is out of range")))
+      }
+    }
+  )*};
 }
 
-impl JsonSerializer for bool {
-  fn serialize_json(&self) -> String {
-    self.to_string()
+macro_rules! impl_binary_signed {
+  ($($int:ty),*) => {$(
+    impl BinarySerializer for $int {
+      fn serialize_binary(&self, output: &mut Vec<u8>) {
+        // Signed integers are zigzag-encoded so that small magnitudes stay short
+        let value = *self as i64;
+        (((value << 1) ^ (value >> 63)) as u64).serialize_binary(output);
+      }
+    }
+
+    impl BinaryDeserializer for $int {
+      fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+        let value = u64::deserialize_binary(data)?;
+        let value = (value >> 1) as i64 ^ -((value & 1) as i64);
+        Self::try_from(value)
+          .map_err(|_| AmmError::new(AmmErrorKind::InvalidValue, format!("Binary integer {value} is out of range")))
+      }
+    }
+  )*};
+}
+
+impl_binary_unsigned!(u16, u32, usize);
+impl_binary_signed!(i8, i16, i32, i64, isize);
+
+impl BinarySerializer for u8 {
+  fn serialize_binary(&self, output: &mut Vec<u8>) {
+    output.push(*self);
   }
 }
 
-impl JsonSerializer for u8 {
-  fn serialize_json(&self) -> String {
-    self.to_string()
+impl BinaryDeserializer for u8 {
+  fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+    Ok(read_binary_bytes(data, 1)?[0])
   }
 }
 
-impl JsonSerializer for u16 {
-  fn serialize_json(&self) -> String {
-    self.to_string()
+impl BinarySerializer for bool {
+  fn serialize_binary(&self, output: &mut Vec<u8>) {
+    output.push(u8::from(*self));
   }
 }
 
-impl JsonSerializer for u32 {
-  fn serialize_json(&self) -> String {
-    self.to_string()
+impl BinaryDeserializer for bool {
+  fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+    match u8::deserialize_binary(data)? {
+      0 => Ok(false),
+      1 => Ok(true),
+      value => Err(AmmError::new(
+        AmmErrorKind::InvalidValue,
+        format!("Invalid binary boolean value {value}"),
+      )),
+    }
   }
 }
 
-impl JsonSerializer for usize {
-  fn serialize_json(&self) -> String {
-    self.to_string()
+impl BinarySerializer for String {
+  fn serialize_binary(&self, output: &mut Vec<u8>) {
+    self.len().serialize_binary(output);
+    output.extend_from_slice(self.as_bytes());
   }
 }
 
-impl JsonSerializer for i8 {
-  fn serialize_json(&self) -> String {
-    self.to_string()
+impl BinaryDeserializer for String {
+  fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+    let length = usize::deserialize_binary(data)?;
+    let bytes = read_binary_bytes(data, length)?;
+    core::str::from_utf8(bytes)
+      .map(String::from)
+      .map_err(|err| AmmError::new(AmmErrorKind::InvalidValue, err.to_string()))
   }
 }
 
-impl JsonSerializer for i16 {
-  fn serialize_json(&self) -> String {
-    self.to_string()
+impl<T: BinarySerializer> BinarySerializer for Option<T> {
+  fn serialize_binary(&self, output: &mut Vec<u8>) {
+    match self {
+      Some(value) => {
+        output.push(1);
+        value.serialize_binary(output);
+      }
+      None => output.push(0),
+    }
   }
 }
 
-impl JsonSerializer for i32 {
-  fn serialize_json(&self) -> String {
-    self.to_string()
+impl<T: BinaryDeserializer> BinaryDeserializer for Option<T> {
+  fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+    match bool::deserialize_binary(data)? {
+      true => T::deserialize_binary(data).map(Some),
+      false => Ok(None),
+    }
   }
 }
 
-impl JsonSerializer for isize {
-  fn serialize_json(&self) -> String {
-    self.to_string()
+impl<T: BinarySerializer> BinarySerializer for Vec<T> {
+  fn serialize_binary(&self, output: &mut Vec<u8>) {
+    self.len().serialize_binary(output);
+    self.iter().for_each(|item| item.serialize_binary(output));
   }
 }
 
-impl JsonSerializer for String {
-  fn serialize_json(&self) -> String {
-    format!("\"{self}\"")
+impl<T: BinaryDeserializer> BinaryDeserializer for Vec<T> {
+  fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+    // Never trust the item count for preallocation beyond what the data could hold
+    let length = usize::deserialize_binary(data)?;
+    let mut items = Vec::with_capacity(length.min(data.len()));
+    for idx in 0..length {
+      items.push(T::deserialize_binary(data).map_err(|err| err.in_index(idx))?);
+    }
+    Ok(items)
+  }
+}
+
+impl<T: BinarySerializer> BinarySerializer for BTreeSet<T> {
+  fn serialize_binary(&self, output: &mut Vec<u8>) {
+    self.len().serialize_binary(output);
+    self.iter().for_each(|item| item.serialize_binary(output));
+  }
+}
+
+impl<T: BinaryDeserializer + Ord> BinaryDeserializer for BTreeSet<T> {
+  fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+    let length = usize::deserialize_binary(data)?;
+    let mut items = BTreeSet::new();
+    for idx in 0..length {
+      items.insert(T::deserialize_binary(data).map_err(|err| err.in_index(idx))?);
+    }
+    Ok(items)
+  }
+}
+
+impl<K: BinarySerializer, V: BinarySerializer> BinarySerializer for BTreeMap<K, V> {
+  fn serialize_binary(&self, output: &mut Vec<u8>) {
+    self.len().serialize_binary(output);
+    for (key, value) in self {
+      key.serialize_binary(output);
+      value.serialize_binary(output);
+    }
+  }
+}
+
+impl<K: BinaryDeserializer + Ord, V: BinaryDeserializer> BinaryDeserializer for BTreeMap<K, V> {
+  fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+    let length = usize::deserialize_binary(data)?;
+    let mut items = BTreeMap::new();
+    for _ in 0..length {
+      let key = K::deserialize_binary(data)?;
+      items.insert(key, V::deserialize_binary(data)?);
+    }
+    Ok(items)
+  }
+}
+
+/// Describes the JSON layout of a type as a [JSON Schema](https://json-schema.org)
+/// (draft 2020-12).
+pub trait JsonSchema {
+  /// Returns the schema of the JSON layout of this type, adding the
+  /// definitions of all named types it refers to to `definitions`.
+  fn json_schema(definitions: &mut BTreeMap<String, JsonValue>) -> JsonValue;
+
+  /// Returns a complete JSON Schema document describing this type, holding
+  /// the definitions of all named types it refers to.
+  #[must_use]
+  fn json_schema_document() -> JsonValue
+  where
+    Self: Sized,
+  {
+    let mut definitions = BTreeMap::new();
+    let schema = Self::json_schema(&mut definitions);
+    json_schema::document(schema, definitions)
   }
 }
 
-impl JsonDeserializer for bool {
-  fn deserialize_json(json: &str) -> Result<Self, String> {
-    json.parse::<bool>().map_err(|err| err.to_string())
+/// Builders for the fragments returned by [`JsonSchema`] implementations.
+pub mod json_schema {
+  use super::{BTreeMap, JsonValue, String, Vec};
+  use alloc::vec;
+
+  fn text(value: &str) -> JsonValue {
+    JsonValue::String(String::from(value))
+  }
+
+  fn members<'a>(members: impl IntoIterator<Item = (&'a str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(
+      members
+        .into_iter()
+        .map(|(key, value)| (String::from(key), value))
+        .collect(),
+    )
+  }
+
+  /// Returns a schema accepting any JSON value of the given `type`.
+  #[must_use]
+  pub fn of_type(r#type: &str) -> JsonValue {
+    members([("type", text(r#type))])
+  }
+
+  /// Returns a schema accepting integers between `minimum` and `maximum`.
+  #[must_use]
+  pub fn integer(minimum: Option<i64>, maximum: Option<u64>) -> JsonValue {
+    #[allow(clippy::cast_precision_loss)]
+    let bounds = [
+      minimum.map(|minimum| ("minimum", JsonValue::Number(minimum as f64))),
+      maximum.map(|maximum| ("maximum", JsonValue::Number(maximum as f64))),
+    ];
+    members(
+      [("type", text("integer"))]
+        .into_iter()
+        .chain(bounds.into_iter().flatten()),
+    )
+  }
+
+  /// Returns a schema accepting only the string `value`.
+  #[must_use]
+  pub fn constant(value: &str) -> JsonValue {
+    members([("const", text(value))])
+  }
+
+  /// Returns a schema accepting any of the strings in `values`.
+  #[must_use]
+  pub fn string_enum(values: &[&str]) -> JsonValue {
+    members([
+      ("type", text("string")),
+      (
+        "enum",
+        JsonValue::Array(values.iter().map(|value| text(value)).collect()),
+      ),
+    ])
+  }
+
+  /// Returns a schema accepting strings that match the regular expression `pattern`.
+  #[must_use]
+  pub fn string_pattern(pattern: &str) -> JsonValue {
+    members([("type", text("string")), ("pattern", text(pattern))])
+  }
+
+  /// Returns a schema accepting arrays whose items all match `items`.
+  #[must_use]
+  pub fn array(items: JsonValue, unique: bool) -> JsonValue {
+    let unique = unique.then_some(("uniqueItems", JsonValue::Bool(true)));
+    members([("type", text("array")), ("items", items)].into_iter().chain(unique))
+  }
+
+  /// Returns a schema accepting arrays whose leading items match `items` in
+  /// order, of which the first `required` must be present.
+  #[must_use]
+  pub fn tuple(items: impl IntoIterator<Item = JsonValue>, required: usize) -> JsonValue {
+    #[allow(clippy::cast_precision_loss)]
+    members([
+      ("type", text("array")),
+      ("prefixItems", JsonValue::Array(items.into_iter().collect())),
+      ("minItems", JsonValue::Number(required as f64)),
+    ])
+  }
+
+  /// Returns a schema accepting objects whose members all match `values`.
+  #[must_use]
+  pub fn map(values: JsonValue) -> JsonValue {
+    members([("type", text("object")), ("additionalProperties", values)])
+  }
+
+  /// Returns a schema accepting objects with the given `properties`, of which
+  /// those listed in `required` must be present.
+  #[must_use]
+  pub fn object<'a>(
+    properties: impl IntoIterator<Item = (&'a str, JsonValue)>,
+    required: impl IntoIterator<Item = &'a str>,
+  ) -> JsonValue {
+    let properties: Vec<_> = properties.into_iter().collect();
+    let required = required.into_iter().map(text).collect();
+    members([
+      ("type", text("object")),
+      ("properties", members(properties)),
+      ("required", JsonValue::Array(required)),
+    ])
+  }
+
+  /// Returns a schema accepting values that match any of the given `schemas`.
+  #[must_use]
+  pub fn any_of(schemas: impl IntoIterator<Item = JsonValue>) -> JsonValue {
+    let mut schemas: Vec<_> = schemas.into_iter().collect();
+    match schemas.len() {
+      1 => schemas.remove(0),
+      _ => members([("anyOf", JsonValue::Array(schemas))]),
+    }
+  }
+
+  /// Returns a schema accepting values that match all of the given `schemas`.
+  #[must_use]
+  pub fn all_of(schemas: impl IntoIterator<Item = JsonValue>) -> JsonValue {
+    members([("allOf", JsonValue::Array(schemas.into_iter().collect()))])
   }
+
+  /// Returns a reference to the definition of the named type `name`, adding
+  /// the schema returned by `schema` to `definitions` if it is not yet defined.
+  ///
+  /// Recursive types refer back to their own definition while it is being built.
+  pub fn define(
+    definitions: &mut BTreeMap<String, JsonValue>,
+    name: &str,
+    schema: impl FnOnce(&mut BTreeMap<String, JsonValue>) -> JsonValue,
+  ) -> JsonValue {
+    if !definitions.contains_key(name) {
+      definitions.insert(String::from(name), JsonValue::Null);
+      let schema = schema(definitions);
+      definitions.insert(String::from(name), schema);
+    }
+    members([("$ref", JsonValue::String(String::from("#/$defs/") + name))])
+  }
+
+  /// Combines a root `schema` and the `definitions` it refers to into a
+  /// complete JSON Schema document.
+  #[must_use]
+  pub fn document(schema: JsonValue, definitions: BTreeMap<String, JsonValue>) -> JsonValue {
+    let mut document = vec![(
+      String::from("$schema"),
+      text("https://json-schema.org/draft/2020-12/schema"),
+    )];
+    match schema {
+      JsonValue::Object(members) => document.extend(members),
+      schema => document.push((String::from("allOf"), JsonValue::Array(vec![schema]))),
+    }
+    if !definitions.is_empty() {
+      document.push((
+        String::from("$defs"),
+        JsonValue::Object(definitions.into_iter().collect()),
+      ));
+    }
+    JsonValue::Object(document)
+  }
+}
+
+macro_rules! impl_json_schema_integer {
+  ($($int_type:ty: $minimum:expr, $maximum:expr;)*) => {$(
+    impl JsonSchema for $int_type {
+      fn json_schema(_definitions: &mut BTreeMap<String, JsonValue>) -> JsonValue {
+        json_schema::integer($minimum, $maximum)
+      }
+    }
+  )*};
+}
+
+impl_json_schema_integer! {
+  u8: Some(0), Some(u8::MAX.into());
+  u16: Some(0), Some(u16::MAX.into());
+  u32: Some(0), Some(u32::MAX.into());
+  usize: Some(0), None;
+  i8: Some(i8::MIN.into()), Some(i8::MAX as u64);
+  i16: Some(i16::MIN.into()), Some(i16::MAX as u64);
+  i32: Some(i32::MIN.into()), Some(i32::MAX as u64);
+  isize: None, None;
 }
 
-impl JsonDeserializer for u8 {
-  fn deserialize_json(json: &str) -> Result<Self, String> {
-    json.parse::<u8>().map_err(|err| err.to_string())
+impl JsonSchema for bool {
+  fn json_schema(_definitions: &mut BTreeMap<String, JsonValue>) -> JsonValue {
+    json_schema::of_type("boolean")
   }
 }
 
-impl JsonDeserializer for u16 {
-  fn deserialize_json(json: &str) -> Result<Self, String> {
-    json.parse::<u16>().map_err(|err| err.to_string())
+impl JsonSchema for String {
+  fn json_schema(_definitions: &mut BTreeMap<String, JsonValue>) -> JsonValue {
+    json_schema::of_type("string")
   }
 }
 
-impl JsonDeserializer for u32 {
-  fn deserialize_json(json: &str) -> Result<Self, String> {
-    json.parse::<u32>().map_err(|err| err.to_string())
+impl JsonSchema for &str {
+  fn json_schema(_definitions: &mut BTreeMap<String, JsonValue>) -> JsonValue {
+    json_schema::of_type("string")
   }
 }
 
-impl JsonDeserializer for usize {
-  fn deserialize_json(json: &str) -> Result<Self, String> {
-    json.parse::<usize>().map_err(|err| err.to_string())
+impl<T: JsonSchema> JsonSchema for Option<T> {
+  fn json_schema(definitions: &mut BTreeMap<String, JsonValue>) -> JsonValue {
+    // Missing values are written as empty strings, while null is accepted when reading
+    json_schema::any_of([
+      T::json_schema(definitions),
+      json_schema::constant(""),
+      json_schema::of_type("null"),
+    ])
   }
 }
 
-impl JsonDeserializer for i8 {
-  fn deserialize_json(json: &str) -> Result<Self, String> {
-    json.parse::<i8>().map_err(|err| err.to_string())
+impl<T: JsonSchema> JsonSchema for Vec<T> {
+  fn json_schema(definitions: &mut BTreeMap<String, JsonValue>) -> JsonValue {
+    json_schema::array(T::json_schema(definitions), false)
   }
 }
 
-impl JsonDeserializer for i16 {
-  fn deserialize_json(json: &str) -> Result<Self, String> {
-    json.parse::<i16>().map_err(|err| err.to_string())
+impl<T: JsonSchema> JsonSchema for BTreeSet<T> {
+  fn json_schema(definitions: &mut BTreeMap<String, JsonValue>) -> JsonValue {
+    json_schema::array(T::json_schema(definitions), true)
   }
 }
 
-impl JsonDeserializer for i32 {
-  fn deserialize_json(json: &str) -> Result<Self, String> {
-    json.parse::<i32>().map_err(|err| err.to_string())
+impl<V: JsonSchema> JsonSchema for BTreeMap<String, V> {
+  fn json_schema(definitions: &mut BTreeMap<String, JsonValue>) -> JsonValue {
+    json_schema::map(V::json_schema(definitions))
   }
 }
 
-impl JsonDeserializer for isize {
-  fn deserialize_json(json: &str) -> Result<Self, String> {
-    json.parse::<isize>().map_err(|err| err.to_string())
+impl JsonSchema for JsonValue {
+  fn json_schema(_definitions: &mut BTreeMap<String, JsonValue>) -> JsonValue {
+    JsonValue::Object(Vec::new())
   }
 }
 
-impl JsonDeserializer for String {
-  fn deserialize_json(json: &str) -> Result<Self, String> {
-    Ok(json.to_string())
+/// Support for [serde](https://serde.rs), which represents AMM values in
+/// their own JSON layout in human-readable formats and in their binary
+/// encoding in compact formats.
+#[cfg(feature = "serde")]
+pub mod serde_support {
+  use super::{
+    AmmError, AmmErrorKind, BinaryDeserializer, BinarySerializer, JsonDeserializer, JsonSerializer, JsonValue,
+  };
+  use alloc::{string::String, vec::Vec};
+  use core::fmt;
+  use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+  use serde::ser::{self, Serialize, SerializeMap, Serializer};
+
+  pub use serde;
+
+  /// The data of an AMM value read through serde.
+  pub enum SerdeData {
+    /// A JSON document read from a human-readable format.
+    Json(String),
+    /// Binary-encoded data read from a compact format.
+    Binary(Vec<u8>),
+  }
+
+  impl SerdeData {
+    /// Reads the data of an AMM value from `deserializer`, as a JSON document
+    /// if its format is human readable or as bytes otherwise.
+    ///
+    /// # Errors
+    /// Returns an error if the input is neither a JSON-like value nor bytes.
+    pub fn read<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+      if deserializer.is_human_readable() {
+        JsonValue::deserialize(deserializer).map(|value| Self::Json(value.serialize_json()))
+      } else {
+        deserializer.deserialize_byte_buf(BytesVisitor).map(Self::Binary)
+      }
+    }
+  }
+
+  /// Writes a JSON document through `serializer` as the equivalent nested
+  /// serde maps, sequences and values.
+  ///
+  /// # Errors
+  /// Returns an error if `json` is not valid JSON or the serializer fails.
+  pub fn serialize_json_document<S: Serializer>(json: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    JsonValue::parse(json)
+      .map_err(ser::Error::custom)?
+      .serialize(serializer)
+  }
+
+  /// Serializes an AMM value in its JSON layout if the format of `serializer`
+  /// is human readable, or as its binary encoding otherwise.
+  ///
+  /// # Errors
+  /// Returns an error if the serializer fails.
+  pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    T: JsonSerializer + BinarySerializer,
+    S: Serializer,
+  {
+    if serializer.is_human_readable() {
+      serialize_json_document(&value.serialize_json(), serializer)
+    } else {
+      let mut output = Vec::new();
+      value.serialize_binary(&mut output);
+      serializer.serialize_bytes(&output)
+    }
+  }
+
+  /// Deserializes an AMM value written by [`serialize`].
+  ///
+  /// # Errors
+  /// Returns an error if the input does not hold a valid value.
+  pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+  where
+    T: JsonDeserializer + BinaryDeserializer,
+    D: Deserializer<'de>,
+  {
+    match SerdeData::read(deserializer)? {
+      SerdeData::Json(json) => T::deserialize_json(&json),
+      SerdeData::Binary(data) => {
+        let mut data = data.as_slice();
+        T::deserialize_binary(&mut data).and_then(|value| match data {
+          [] => Ok(value),
+          _ => Err(AmmError::new(
+            AmmErrorKind::Syntax,
+            "Unexpected trailing data in binary AMM value",
+          )),
+        })
+      }
+    }
+    .map_err(de::Error::custom)
+  }
+
+  impl Serialize for JsonValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+      match self {
+        Self::Null => serializer.serialize_unit(),
+        Self::Bool(value) => serializer.serialize_bool(*value),
+        Self::Number(value) => match self.as_i64() {
+          Some(integer) => serializer.serialize_i64(integer),
+          None if value.is_finite() => serializer.serialize_f64(*value),
+          None => serializer.serialize_unit(),
+        },
+        Self::String(value) => serializer.serialize_str(value),
+        Self::Array(values) => serializer.collect_seq(values),
+        Self::Object(members) => {
+          let mut map = serializer.serialize_map(Some(members.len()))?;
+          for (key, value) in members {
+            map.serialize_entry(key, value)?;
+          }
+          map.end()
+        }
+      }
+    }
+  }
+
+  impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+      deserializer.deserialize_any(JsonValueVisitor)
+    }
+  }
+
+  struct JsonValueVisitor;
+
+  #[allow(clippy::cast_precision_loss)]
+  impl<'de> Visitor<'de> for JsonValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+      formatter.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<JsonValue, E> {
+      Ok(JsonValue::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<JsonValue, E> {
+      Ok(JsonValue::Number(value as f64))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<JsonValue, E> {
+      Ok(JsonValue::Number(value as f64))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<JsonValue, E> {
+      Ok(JsonValue::Number(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<JsonValue, E> {
+      Ok(JsonValue::String(String::from(value)))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<JsonValue, E> {
+      Ok(JsonValue::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+      Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<JsonValue, E> {
+      Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+      JsonValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+      let mut values = Vec::new();
+      while let Some(value) = seq.next_element()? {
+        values.push(value);
+      }
+      Ok(JsonValue::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+      let mut members = Vec::new();
+      while let Some(member) = map.next_entry()? {
+        members.push(member);
+      }
+      Ok(JsonValue::Object(members))
+    }
+  }
+
+  struct BytesVisitor;
+
+  impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+      formatter.write_str("binary AMM data")
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Vec<u8>, E> {
+      Ok(value.to_vec())
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Vec<u8>, E> {
+      Ok(value)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+      let mut data = Vec::new();
+      while let Some(byte) = seq.next_element()? {
+        data.push(byte);
+      }
+      Ok(data)
+    }
   }
 }
 
+/// Implements `serde::Serialize` and `serde::Deserialize` for types that
+/// implement both the AMM JSON and binary serialization traits, using the
+/// layout described in [`serde_support`].
+#[cfg(feature = "serde")]
+#[macro_export]
+macro_rules! impl_serde {
+  ($($amm_type:ty),* $(,)?) => {$(
+    impl $crate::serde_support::serde::Serialize for $amm_type {
+      fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+      where
+        S: $crate::serde_support::serde::Serializer,
+      {
+        $crate::serde_support::serialize(self, serializer)
+      }
+    }
+
+    impl<'de> $crate::serde_support::serde::Deserialize<'de> for $amm_type {
+      fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+      where
+        D: $crate::serde_support::serde::Deserializer<'de>,
+      {
+        $crate::serde_support::deserialize(deserializer)
+      }
+    }
+  )*};
+}
+
 pub mod amm_prelude {
+  pub use super::json_schema;
+  pub use super::AmmError;
+  pub use super::AmmErrorKind;
+  pub use super::BinaryDeserializer;
+  pub use super::BinarySerializer;
   pub use super::JsonDeserializer;
+  pub use super::JsonOptions;
+  pub use super::JsonReader;
+  pub use super::JsonSchema;
   pub use super::JsonSerializer;
+  pub use super::JsonValue;
   pub use alloc::collections::{BTreeMap, BTreeSet};
   pub use alloc::string::{String, ToString};
   pub use alloc::vec::Vec;
 
+  /// Returns the byte index of the closing quote of a JSON string whose
+  /// contents start at `start`, or the length of the data if it is unterminated.
+  fn json_string_end(data: &[u8], mut idx: usize) -> usize {
+    while idx < data.len() {
+      match data[idx] {
+        b'\\' => idx += 2,
+        b'"' => return idx,
+        _ => idx += 1,
+      }
+    }
+    data.len()
+  }
+
+  /// Returns the `_type` tag of the JSON object whose members are contained in `data`.
+  #[deprecated(since = "0.4.0", note = "use `JsonReader::peek_type` instead")]
   #[must_use]
+  #[allow(deprecated)]
   pub fn json_get_type(data: &str) -> &str {
-    if let Some((_, type_str)) = data.split_once("\"_type\":\"") {
-      type_str.split_once('"').unwrap_or_default().0
-    } else {
-      ""
+    let (mut data, mut key) = json_next_key(data);
+    while !key.is_empty() {
+      let value;
+      (data, value) = json_next_value(data);
+      if key == "_type" {
+        return value;
+      }
+      (data, key) = json_next_key(data);
     }
+    ""
   }
 
+  /// Returns the data following the next object key in `data` along with the
+  /// key itself, still escaped.
+  #[deprecated(since = "0.4.0", note = "use `JsonReader::next_key` instead")]
   #[must_use]
   pub fn json_next_key(data: &str) -> (&str, &str) {
-    let mut key_start = 0;
-    for (idx, ch) in data.chars().enumerate() {
-      if ch == '"' {
-        if key_start > 0 {
-          return (&data[(idx + 1)..], &data[key_start..idx]);
+    let bytes = data.as_bytes();
+    match bytes.iter().position(|byte| *byte == b'"') {
+      Some(start) => {
+        let end = json_string_end(bytes, start + 1);
+        if end < bytes.len() {
+          (&data[(end + 1)..], &data[(start + 1)..end])
+        } else {
+          ("", "")
         }
-        key_start = idx + 1;
       }
+      None => ("", ""),
     }
-    ("", "")
   }
 
+  /// Returns whether the next value in `data` is a JSON `null`.
+  #[deprecated(since = "0.4.0", note = "use `JsonReader::next_is_null` instead")]
+  #[must_use]
+  pub fn json_next_is_null(data: &str) -> bool {
+    let value = data.trim_start_matches([' ', '\t', '\n', '\r', ':']);
+    value.strip_prefix("null").is_some_and(|rest| {
+      rest
+        .bytes()
+        .next()
+        .is_none_or(|byte| matches!(byte, b',' | b']' | b'}' | b' ' | b'\t' | b'\n' | b'\r'))
+    })
+  }
+
+  /// Returns the data following the next value in `data` along with the value
+  /// itself, with the quotes of a string or the brackets of an array or object
+  /// removed.
+  #[deprecated(
+    since = "0.4.0",
+    note = "use `JsonReader::read_string`, `JsonReader::read_scalar` or `JsonReader::skip_value` instead"
+  )]
   #[must_use]
   pub fn json_next_value(data: &str) -> (&str, &str) {
-    let (mut value_start, mut num_openers, mut in_value) = (0, 0, false);
-    for (idx, ch) in data.chars().enumerate() {
-      if in_value {
-        match ch {
-          ',' | '"' => {
-            if num_openers == 0 {
-              return (&data[(idx + 1)..], &data[value_start..idx]);
-            }
-          }
-          '[' | '{' => {
-            num_openers += 1;
-          }
-          ']' | '}' => {
-            num_openers -= 1;
-            if num_openers == 0 {
-              return (&data[(idx + 1)..], &data[value_start..idx]);
+    let bytes = data.as_bytes();
+    let Some(start) = bytes
+      .iter()
+      .position(|byte| !matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | b':' | b',' | b']' | b'}'))
+    else {
+      return ("", "");
+    };
+    match bytes[start] {
+      b'"' => {
+        let end = json_string_end(bytes, start + 1);
+        (&data[(end + 1).min(bytes.len())..], &data[(start + 1)..end])
+      }
+      b'[' | b'{' => {
+        let (mut idx, mut depth) = (start, 0_usize);
+        while idx < bytes.len() {
+          match bytes[idx] {
+            b'"' => idx = json_string_end(bytes, idx + 1),
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => {
+              depth -= 1;
+              if depth == 0 {
+                return (&data[(idx + 1)..], &data[(start + 1)..idx]);
+              }
             }
+            _ => (),
           }
-          _ => (),
+          idx += 1;
         }
-      } else if ch != ' ' && ch != ':' && ch != ']' && ch != '}' && ch != ',' {
-        value_start = if ch == '"' || ch == '[' || ch == '{' {
-          if ch == '[' || ch == '{' {
-            num_openers += 1;
-          }
-          idx + 1
-        } else {
-          idx
-        };
-        in_value = true;
+        ("", &data[(start + 1)..])
+      }
+      _ => {
+        let end = bytes[start..]
+          .iter()
+          .position(|byte| matches!(byte, b',' | b']' | b'}' | b' ' | b'\t' | b'\n' | b'\r'))
+          .map_or(bytes.len(), |length| start + length);
+        (&data[end..], &data[start..end])
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use alloc::vec;
+  use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
+
+  #[test]
+  fn test_json_value() {
+    let json = r#" {"name": "A \"B\"\n\u00e9\ud83c\udfb5", "values": [1, -2.5, 3e2, true, null], "empty": {}} "#;
+    let value = JsonValue::parse(json).unwrap();
+    assert_eq!(
+      value.get("name").and_then(JsonValue::as_str),
+      Some("A \"B\"\n\u{e9}\u{1f3b5}")
+    );
+    let values = value.get("values").and_then(JsonValue::as_array).unwrap();
+    assert_eq!(values[0].as_i64(), Some(1));
+    assert_eq!(values[1].as_f64(), Some(-2.5));
+    assert_eq!(values[2].as_i64(), Some(300));
+    assert_eq!(values[3].as_bool(), Some(true));
+    assert_eq!(values[4], JsonValue::Null);
+    assert_eq!(value.get("empty"), Some(&JsonValue::Object(vec![])));
+    assert_eq!(JsonValue::parse(&value.serialize_json()), Ok(value));
+    for invalid in ["", "[1,]", "{\"a\" 1}", "01", "\"\\x\"", "\"a", "[1] 2", "\"\t\""] {
+      assert!(JsonValue::parse(invalid).is_err(), "{invalid}");
+    }
+  }
+
+  #[test]
+  fn test_json_options() {
+    let value = JsonValue::parse(r#"{"b": [1, -0, 2.50], "a": {}, "c": {"y": [], "x": "z"}}"#).unwrap();
+    assert_eq!(
+      value.serialize_json(),
+      r#"{"b":[1,-0,2.5],"a":{},"c":{"y":[],"x":"z"}}"#
+    );
+    assert_eq!(
+      value.serialize_json_with(&JsonOptions::compact().canonical(true)),
+      r#"{"a":{},"b":[1,0,2.5],"c":{"x":"z","y":[]}}"#
+    );
+    let pretty = value.serialize_json_with(&JsonOptions::pretty(2));
+    assert_eq!(
+      pretty,
+      "{\n  \"b\": [\n    1,\n    -0,\n    2.5\n  ],\n  \"a\": {},\n  \"c\": {\n    \"y\": [],\n    \"x\": \"z\"\n  }\n}"
+    );
+    assert_eq!(JsonValue::parse(&pretty), Ok(value));
+  }
+
+  #[test]
+  fn test_json_streaming() {
+    struct Counter(usize);
+    impl Write for Counter {
+      fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.0 += text.len();
+        Ok(())
+      }
+    }
+    struct Failing;
+    impl Write for Failing {
+      fn write_str(&mut self, _: &str) -> fmt::Result {
+        Err(fmt::Error)
       }
     }
-    ("", &data[value_start..])
+
+    let value = BTreeMap::from([(String::from("notes"), vec![Some(60_u8), None, Some(64)])]);
+    let mut counter = Counter(0);
+    assert!(value.write_json(&mut counter, &JsonOptions::pretty(2)).is_ok());
+    assert_eq!(counter.0, value.serialize_json_with(&JsonOptions::pretty(2)).len());
+    assert_eq!(value.serialize_json(), r#"{"notes":[60,"",64]}"#);
+    assert!(value.write_json(&mut Failing, &JsonOptions::default()).is_err());
+    assert_eq!(
+      BTreeMap::<String, Vec<Option<u8>>>::deserialize_json(&value.serialize_json()),
+      Ok(value)
+    );
+
+    let json = r#" {"plain": "text", "escaped": "a\"b", "list": [1, [2, {"x": "]"}]], "_type": "Tag"} "#;
+    let mut reader = JsonReader::new(json);
+    assert_eq!(reader.peek_type(), Ok(Some(Cow::Borrowed("Tag"))));
+    assert_eq!(reader.position(), 0);
+    assert!(reader.begin_object().is_ok());
+    assert_eq!(reader.next_key(), Ok(Some(Cow::Borrowed("plain"))));
+    assert!(matches!(reader.read_string(), Ok(Cow::Borrowed("text"))));
+    assert_eq!(reader.next_key(), Ok(Some(Cow::Borrowed("escaped"))));
+    assert!(matches!(reader.read_string(), Ok(Cow::Owned(text)) if text == "a\"b"));
+    assert_eq!(reader.next_key(), Ok(Some(Cow::Borrowed("list"))));
+    assert!(reader.skip_value().is_ok());
+    assert_eq!(reader.next_key(), Ok(Some(Cow::Borrowed("_type"))));
+    assert!(reader.skip_value().is_ok());
+    assert_eq!(reader.next_key(), Ok(None));
+    assert!(reader.finish().is_ok());
+    let error = Vec::<u8>::deserialize_json("[1, 2").unwrap_err();
+    assert_eq!((error.kind, error.offset), (AmmErrorKind::UnexpectedEnd, Some(5)));
+  }
+
+  #[test]
+  fn test_error_context() {
+    let error = JsonValue::parse("[1,\n x]").unwrap_err();
+    assert_eq!((error.kind, error.offset), (AmmErrorKind::Syntax, Some(5)));
+    assert_eq!(error.line_column("[1,\n x]"), Some((2, 2)));
+    let json = "\"notes\": [1, 2, 300]";
+    let error = u8::deserialize_json(&json[16..19]).unwrap_err();
+    let error = error
+      .within(json, &json[16..19])
+      .in_index(2)
+      .in_field("notes")
+      .in_field("staff")
+      .in_index(0);
+    assert_eq!(error.path, "[0].staff.notes[2]");
+    assert_eq!(error.offset, Some(16));
+    assert_eq!(
+      error.to_string(),
+      "Invalid value \"300\": number too large to fit in target type at [0].staff.notes[2] (byte 16)"
+    );
+    let error = Vec::<u16>::deserialize_binary(&mut &[0x02, 0x01, 0x80][..]).unwrap_err();
+    assert_eq!((error.kind, error.path.as_str()), (AmmErrorKind::UnexpectedEnd, "[1]"));
+  }
+
+  #[test]
+  #[allow(deprecated)]
+  fn test_json_scanning() {
+    use amm_prelude::{json_get_type, json_next_is_null, json_next_key, json_next_value};
+    let text = String::from("Quote \" backslash \\ tab \t \u{1} caf\u{e9}");
+    let escaped = text.serialize_json();
+    assert_eq!(escaped, "\"Quote \\\" backslash \\\\ tab \\t \\u0001 caf\u{e9}\"");
+    assert_eq!(String::deserialize_json(&escaped), Ok(text));
+    assert_eq!(
+      String::deserialize_json(r#""\ud83c\udfb5 \/""#),
+      Ok(String::from("\u{1f3b5} /"))
+    );
+    assert!(String::deserialize_json(r#""a"b""#).is_err());
+    assert!(String::deserialize_json(r#""\q""#).is_err());
+
+    let object = " \"_type\" :\t\"Note\",\n \"text\": \"a \\\"}]\\\" b\", \"list\" : [1, {\"x\": \"]\"}], \"none\": null, \"n\": 5 ";
+    assert_eq!(json_get_type(object), "Note");
+    let (data, key) = json_next_key(object);
+    assert_eq!(key, "_type");
+    let (data, value) = json_next_value(data);
+    assert_eq!(value, "Note");
+    let (data, key) = json_next_key(data);
+    let (data, value) = json_next_value(data);
+    assert_eq!((key, value), ("text", r#"a \"}]\" b"#));
+    let (data, key) = json_next_key(data);
+    let (data, value) = json_next_value(data);
+    assert_eq!((key, value), ("list", r#"1, {"x": "]"}"#));
+    let (data, key) = json_next_key(data);
+    assert!(key == "none" && json_next_is_null(data));
+    let (data, _) = json_next_value(data);
+    let (data, key) = json_next_key(data);
+    assert!(key == "n" && !json_next_is_null(data));
+    assert_eq!(json_next_value(data).1, "5");
+    assert_eq!(json_get_type("\"name\":\"_type\",\"other\":{\"_type\":\"Inner\"}"), "");
+  }
+
+  #[test]
+  fn test_binary_encoding() {
+    let mut output = Vec::new();
+    300_u16.serialize_binary(&mut output);
+    (-3_i8).serialize_binary(&mut output);
+    String::from("A \u{e9}").serialize_binary(&mut output);
+    Some(vec![true, false]).serialize_binary(&mut output);
+    BTreeMap::from([(String::from("key"), 7_usize)]).serialize_binary(&mut output);
+    assert_eq!(
+      output,
+      [0xac, 0x02, 0x05, 0x04, b'A', b' ', 0xc3, 0xa9, 0x01, 0x02, 0x01, 0x00, 0x01, 0x03, b'k', b'e', b'y', 0x07]
+    );
+    let mut data = output.as_slice();
+    assert_eq!(u16::deserialize_binary(&mut data), Ok(300));
+    assert_eq!(i8::deserialize_binary(&mut data), Ok(-3));
+    assert_eq!(String::deserialize_binary(&mut data).as_deref(), Ok("A \u{e9}"));
+    assert_eq!(
+      Option::<Vec<bool>>::deserialize_binary(&mut data),
+      Ok(Some(vec![true, false]))
+    );
+    let map = BTreeMap::<String, usize>::deserialize_binary(&mut data).unwrap();
+    assert_eq!(map.get("key"), Some(&7));
+    assert!(data.is_empty());
+    for invalid in [&[0x80][..], &[0x80, 0x00], &[0xff; 11]] {
+      assert!(u64::deserialize_binary(&mut &invalid[..]).is_err());
+    }
+    assert!(bool::deserialize_binary(&mut &[0x02][..]).is_err());
+    assert!(u16::deserialize_binary(&mut &[0xff, 0xff, 0x04][..]).is_err());
+    assert!(String::deserialize_binary(&mut &[0x02, 0xff, 0xfe][..]).is_err());
+  }
+
+  #[derive(
+    Debug, Default, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+  )]
+  struct Label(String);
+
+  #[derive(Debug, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
+  struct Range(u16, #[amm(default)] u16, #[amm(skip)] bool);
+
+  #[derive(
+    Debug, Default, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+  )]
+  #[amm(tag = "kind", rename = "Entry")]
+  struct Entry<T> {
+    #[amm(rename = "id")]
+    identifier: String,
+    value: T,
+    #[amm(skip)]
+    cached: usize,
+  }
+
+  #[derive(Debug, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
+  #[amm(tag = "kind")]
+  enum Shape {
+    #[amm(rename = "dot")]
+    Point,
+    Circle {
+      radius: u16,
+      #[amm(default)]
+      filled: bool,
+      #[amm(skip)]
+      hits: usize,
+    },
+    Level(u8),
+    #[amm(rename = "Entry")]
+    Labeled(Entry<Label>),
+  }
+
+  #[test]
+  fn test_derive_attributes() {
+    let entry = Entry {
+      identifier: String::from("a"),
+      value: Label(String::from("x")),
+      cached: 3,
+    };
+    let json = entry.serialize_json();
+    assert_eq!(json, r#"{"kind":"Entry","id":"a","value":"x"}"#);
+    let parsed = Entry::<Label>::deserialize_json(&json).unwrap();
+    assert_eq!((parsed.identifier.as_str(), parsed.cached), ("a", 0));
+
+    assert_eq!(Range(3, 5, true).serialize_json(), "[3,5]");
+    assert_eq!(Range::deserialize_json("[3]"), Ok(Range(3, 0, false)));
+    assert_eq!(Range::deserialize_json("[3, 5, [7]]"), Ok(Range(3, 5, false)));
+    let err = Range::deserialize_json(" []").unwrap_err();
+    assert_eq!((err.kind, err.offset), (AmmErrorKind::MissingField, Some(1)));
+    assert_eq!(Range::deserialize_json(r#"[3, "5"]"#).unwrap_err().path, "[1]");
+
+    let shapes = vec![
+      Shape::Point,
+      Shape::Circle {
+        radius: 2,
+        filled: true,
+        hits: 4,
+      },
+      Shape::Level(4),
+      Shape::Labeled(entry),
+    ];
+    let json = shapes.serialize_json();
+    assert_eq!(
+      json,
+      r#"["dot",{"kind":"Circle","radius":2,"filled":true},"Level-4",{"kind":"Entry","id":"a","value":"x"}]"#
+    );
+    let parsed = Vec::<Shape>::deserialize_json(&json).unwrap();
+    assert_eq!(
+      parsed[1],
+      Shape::Circle {
+        radius: 2,
+        filled: true,
+        hits: 0
+      }
+    );
+    assert_eq!(parsed[2], Shape::Level(4));
+    assert_eq!(
+      Shape::deserialize_json(r#"{"radius": 7, "kind": "Circle"}"#),
+      Ok(Shape::Circle {
+        radius: 7,
+        filled: false,
+        hits: 0
+      })
+    );
+    let err = Shape::deserialize_json(r#"{"kind": "Circle"}"#).unwrap_err();
+    assert_eq!(
+      (err.kind, err.message.as_str()),
+      (AmmErrorKind::MissingField, r#"Missing AMM enum field: "radius""#)
+    );
+    assert_eq!(
+      Shape::deserialize_json(r#""Point""#).unwrap_err().kind,
+      AmmErrorKind::UnknownVariant
+    );
+
+    let mut output = Vec::new();
+    shapes.serialize_binary(&mut output);
+    Range(3, 5, true).serialize_binary(&mut output);
+    let mut data = output.as_slice();
+    assert_eq!(Vec::<Shape>::deserialize_binary(&mut data).unwrap()[1], parsed[1]);
+    assert_eq!(Range::deserialize_binary(&mut data), Ok(Range(3, 5, false)));
+    assert!(data.is_empty());
+  }
+
+  #[derive(Debug, PartialEq, JsonDeserialize, JsonSchema, JsonSerialize)]
+  struct Track {
+    name: String,
+    #[amm(default)]
+    volume: u8,
+    #[amm(skip)]
+    muted: bool,
+    comment: Option<String>,
+  }
+
+  #[test]
+  fn test_derive_named_defaults() {
+    let track = Track {
+      name: String::from("Bass"),
+      volume: 7,
+      muted: true,
+      comment: None,
+    };
+    let json = track.serialize_json();
+    assert_eq!(json, r#"{"_type":"Track","name":"Bass","volume":7,"comment":""}"#);
+    assert_eq!(Track::deserialize_json(&json), Ok(Track { muted: false, ..track }));
+    assert_eq!(
+      Track::deserialize_json(r#"{"_type":"Track","name":"Lead"}"#),
+      Ok(Track {
+        name: String::from("Lead"),
+        volume: 0,
+        muted: false,
+        comment: None
+      })
+    );
+    let err = Track::deserialize_json(r#" {"_type":"Track","volume":3}"#).unwrap_err();
+    assert_eq!(
+      (err.kind, err.message.as_str(), err.offset),
+      (
+        AmmErrorKind::MissingField,
+        r#"Missing AMM struct field: "name""#,
+        Some(1)
+      )
+    );
+    assert_eq!(
+      Track::deserialize_json(r#"{"name":null}"#).unwrap_err().kind,
+      AmmErrorKind::MissingField
+    );
+
+    let mut definitions = BTreeMap::new();
+    Track::json_schema(&mut definitions);
+    assert_eq!(
+      definitions["Track"].get("required").map(JsonSerializer::serialize_json),
+      Some(String::from(r#"["_type","name"]"#))
+    );
+  }
+
+  #[allow(dead_code)]
+  #[derive(JsonSchema)]
+  struct Tree {
+    children: Vec<Tree>,
+    label: Option<Label>,
+  }
+
+  #[test]
+  fn test_derive_json_schema() {
+    let mut definitions = BTreeMap::new();
+    let schema = Vec::<Shape>::json_schema(&mut definitions).serialize_json();
+    assert_eq!(schema, r##"{"type":"array","items":{"$ref":"#/$defs/Shape"}}"##);
+    assert_eq!(definitions.keys().collect::<Vec<_>>(), ["Label", "Shape"]);
+    assert_eq!(definitions["Label"].serialize_json(), r#"{"type":"string"}"#);
+    let shape = &definitions["Shape"];
+    let variants = shape.get("anyOf").and_then(JsonValue::as_array).unwrap();
+    assert_eq!(
+      variants.iter().map(JsonSerializer::serialize_json).collect::<Vec<_>>(),
+      [
+        r#"{"type":"string","enum":["dot"]}"#,
+        r#"{"type":"object","properties":{"kind":{"const":"Circle"},"radius":{"type":"integer","minimum":0,"maximum":65535},"filled":{"type":"boolean"}},"required":["kind","radius"]}"#,
+        r#"{"type":"string","pattern":"^Level-[0-9]+$"}"#,
+        r##"{"type":"object","properties":{"kind":{"const":"Entry"},"id":{"type":"string"},"value":{"$ref":"#/$defs/Label"}},"required":["kind","id","value"]}"##,
+      ]
+    );
+
+    assert_eq!(
+      Range::json_schema(&mut definitions).serialize_json(),
+      r##"{"$ref":"#/$defs/Range"}"##
+    );
+    assert_eq!(
+      definitions["Range"].serialize_json(),
+      r#"{"type":"array","prefixItems":[{"type":"integer","minimum":0,"maximum":65535},{"type":"integer","minimum":0,"maximum":65535}],"minItems":1}"#
+    );
+
+    let document = Tree::json_schema_document().serialize_json();
+    assert_eq!(
+      document,
+      concat!(
+        r##"{"$schema":"https://json-schema.org/draft/2020-12/schema","$ref":"#/$defs/Tree","$defs":{"##,
+        r##""Label":{"type":"string"},"##,
+        r##""Tree":{"type":"object","properties":{"_type":{"const":"Tree"},"children":{"type":"array","items":{"$ref":"#/$defs/Tree"}},"##,
+        r##""label":{"anyOf":[{"$ref":"#/$defs/Label"},{"const":""},{"type":"null"}]}},"required":["_type","children"]}}}"##
+      )
+    );
   }
 }
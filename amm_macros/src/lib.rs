@@ -2,408 +2,833 @@
 
 extern crate alloc;
 
-use alloc::vec::Vec;
+use alloc::{
+  format,
+  string::{String, ToString},
+  vec::Vec,
+};
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
+use syn::ext::IdentExt;
 
-fn serialize_enum_json(enum_type: &syn::Ident, data: &syn::DataEnum) -> TokenStream {
+/// Options set through `#[amm(...)]` attributes on a type, variant or field.
+#[derive(Default)]
+struct Attributes {
+  rename: Option<String>,
+  tag: Option<String>,
+  skip: bool,
+  default: bool,
+}
+
+impl Attributes {
+  /// Parses all `#[amm(...)]` attributes, rejecting any option that is not
+  /// in `allowed` for the kind of item described by `position`.
+  fn parse(attrs: &[syn::Attribute], allowed: &[&str], position: &str) -> syn::Result<Self> {
+    let mut parsed = Self::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("amm")) {
+      attr.parse_nested_meta(|meta| {
+        let name = meta.path.get_ident().map(ToString::to_string).unwrap_or_default();
+        if !allowed.contains(&name.as_str()) {
+          return Err(meta.error(if allowed.is_empty() {
+            format!("AMM attributes are not supported on {position}")
+          } else {
+            format!(
+              "Unsupported AMM attribute on {position}, expected one of: {}",
+              allowed.join(", ")
+            )
+          }));
+        }
+        match name.as_str() {
+          "rename" => parsed.rename = Some(meta.value()?.parse::<syn::LitStr>()?.value()),
+          "tag" => parsed.tag = Some(meta.value()?.parse::<syn::LitStr>()?.value()),
+          "skip" => parsed.skip = true,
+          _ => parsed.default = true,
+        }
+        Ok(())
+      })?;
+    }
+    Ok(parsed)
+  }
+}
+
+/// A struct or variant field along with its AMM attributes.
+struct Field<'a> {
+  /// The expression used to access the field on `self`.
+  member: syn::Member,
+  /// The variable the field is bound to when matching or deserializing.
+  binding: syn::Ident,
+  /// The JSON object key of the field.
+  key: String,
+  ty: &'a syn::Type,
+  is_option: bool,
+  attrs: Attributes,
+}
+
+fn parse_fields<'a>(fields: &'a syn::Fields, allowed: &[&str], position: &str) -> syn::Result<Vec<Field<'a>>> {
+  fields
+    .iter()
+    .enumerate()
+    .map(|(idx, field)| {
+      let attrs = Attributes::parse(&field.attrs, allowed, position)?;
+      let (member, binding) = match &field.ident {
+        Some(ident) => (syn::Member::Named(ident.clone()), ident.clone()),
+        None => (syn::Member::Unnamed(syn::Index::from(idx)), format_ident!("el{idx}")),
+      };
+      Ok(Field {
+        member,
+        key: attrs.rename.clone().unwrap_or_else(|| binding.to_string()),
+        binding,
+        ty: &field.ty,
+        is_option: type_is(&field.ty, "Option"),
+        attrs,
+      })
+    })
+    .collect()
+}
+
+/// Returns whether the outermost path segment of `ty` is named `name`.
+fn type_is(ty: &syn::Type, name: &str) -> bool {
+  match ty {
+    syn::Type::Path(type_path) => type_path
+      .path
+      .segments
+      .first()
+      .is_some_and(|segment| segment.ident == name),
+    _ => false,
+  }
+}
+
+/// Returns whether `fields` belong to a single-field tuple struct, which is
+/// serialized transparently as its only value.
+fn is_newtype(fields: &syn::Fields) -> bool {
+  matches!(fields, syn::Fields::Unnamed(unnamed_fields) if unnamed_fields.unnamed.len() == 1)
+}
+
+fn parse_struct_fields(fields: &syn::Fields) -> syn::Result<Vec<Field<'_>>> {
+  match fields {
+    syn::Fields::Named(_) => parse_fields(fields, &["rename", "skip", "default"], "AMM struct fields"),
+    _ if is_newtype(fields) => parse_fields(fields, &[], "single-field AMM tuple structs"),
+    _ => parse_fields(fields, &["skip", "default"], "AMM tuple struct fields"),
+  }
+}
+
+fn parse_variant_fields(variant: &syn::Variant) -> syn::Result<Vec<Field<'_>>> {
+  match &variant.fields {
+    syn::Fields::Named(_) => parse_fields(
+      &variant.fields,
+      &["rename", "skip", "default"],
+      "AMM enum variant fields",
+    ),
+    _ => parse_fields(&variant.fields, &[], "AMM enum tuple variants"),
+  }
+}
+
+/// Returns the AMM attributes supported on a struct with the given fields.
+fn struct_attributes(fields: &syn::Fields) -> &'static [&'static str] {
+  match fields {
+    syn::Fields::Named(_) => &["rename", "tag"],
+    _ => &[],
+  }
+}
+
+/// Returns the name identifying an enum variant in serialized JSON.
+fn variant_name(variant: &syn::Variant) -> syn::Result<String> {
+  let attrs = Attributes::parse(&variant.attrs, &["rename"], "AMM enum variants")?;
+  Ok(attrs.rename.unwrap_or_else(|| variant.ident.to_string()))
+}
+
+/// Builds the `impl` header of `trait_name` for the derived type, requiring
+/// every type parameter to implement the same trait along with any `extra`
+/// predicate.
+fn impl_header(
+  ast: &syn::DeriveInput,
+  trait_name: &proc_macro2::TokenStream,
+  extra: Option<proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+  let name = &ast.ident;
+  let (impl_generics, type_generics, where_clause) = ast.generics.split_for_impl();
+  let mut predicates: Vec<proc_macro2::TokenStream> = where_clause
+    .map(|clause| {
+      clause
+        .predicates
+        .iter()
+        .map(|predicate| quote! { #predicate })
+        .collect()
+    })
+    .unwrap_or_default();
+  predicates.extend(ast.generics.type_params().map(|param| {
+    let param = &param.ident;
+    quote! { #param: #trait_name }
+  }));
+  predicates.extend(extra);
+  if predicates.is_empty() {
+    quote! { impl #impl_generics #trait_name for #name #type_generics }
+  } else {
+    quote! { impl #impl_generics #trait_name for #name #type_generics where #(#predicates),* }
+  }
+}
+
+fn single_field_error(variant: &syn::Variant) -> syn::Error {
+  syn::Error::new_spanned(variant, "AMM enum tuple variants must contain exactly one field")
+}
+
+/// Generates the variables, match arms and initializers that read the named
+/// `fields` of a struct or enum variant from the members of a JSON object.
+///
+/// Fields marked as default or holding an `Option` may be missing from the
+/// object, while any other missing field is reported as `missing` followed by
+/// its key.
+fn read_named_fields(
+  fields: &[Field<'_>],
+  missing: &str,
+) -> (
+  Vec<syn::Ident>,
+  Vec<proc_macro2::TokenStream>,
+  Vec<proc_macro2::TokenStream>,
+) {
+  let (mut variables, mut keys, mut values) = (Vec::new(), Vec::new(), Vec::new());
+  for Field {
+    binding,
+    key,
+    is_option,
+    attrs,
+    ..
+  } in fields
+  {
+    let variable = format_ident!("field_{}", binding.unraw());
+    values.push(if attrs.skip {
+      quote! { #binding: Default::default() }
+    } else if *is_option {
+      quote! { #binding: #variable.flatten() }
+    } else if attrs.default {
+      quote! { #binding: #variable.unwrap_or_default() }
+    } else {
+      let message = format!("{missing}\"{key}\"");
+      quote! { #binding: #variable.ok_or_else(|| AmmError::new(AmmErrorKind::MissingField, #message).at(start))? }
+    });
+    if !attrs.skip {
+      keys.push(
+        quote! { #key => #variable = Some(JsonDeserializer::read_json(reader).map_err(|err| err.in_field(#key))?) },
+      );
+      variables.push(variable);
+    }
+  }
+  (variables, keys, values)
+}
+
+/// Returns whether a named field must be present in serialized JSON.
+fn is_required(field: &Field<'_>) -> bool {
+  !field.attrs.skip && !field.attrs.default && !field.is_option
+}
+
+fn serialize_enum_json(ast: &syn::DeriveInput, data: &syn::DataEnum) -> syn::Result<proc_macro2::TokenStream> {
   // Add a match arm for all possible enum variants based on their type
+  let container = Attributes::parse(&ast.attrs, &["tag"], "AMM enums")?;
+  let tag = container.tag.unwrap_or_else(|| String::from("_type"));
   let mut enum_arms: Vec<proc_macro2::TokenStream> = Vec::new();
   for variant in &data.variants {
     let variant_type = &variant.ident;
+    let variant_type_string = variant_name(variant)?;
+    let fields = parse_variant_fields(variant)?;
     match &variant.fields {
-      syn::Fields::Named(named_fields) => {
-        let (mut fields, mut values) = (Vec::new(), Vec::new());
-        let mut key = alloc::format!("{{{{\"_type\":\"{variant_type}\",");
-        for (idx, field) in named_fields.named.iter().enumerate() {
-          let field_name = field.ident.as_ref().unwrap();
-          match &field.ty {
-            syn::Type::Path(type_path) => {
-              fields.push(field_name);
-              key += alloc::format!(
-                "\"{field_name}\":{{}}{}",
-                if idx + 1 < named_fields.named.len() { "," } else { "}}" }
-              )
-              .as_str();
-              match &type_path.path.segments.first().unwrap().ident {
-                field_type if field_type == "Vec" => {
-                  values.push(quote! { format!("[{}]", #field_name.iter().map(|el| el.serialize_json()).collect::<Vec<_>>().join(",")) });
-                }
-                field_type if field_type == "Option" => {
-                  values.push(quote! { #field_name.map(|el| el.serialize_json()).unwrap_or(String::from("\"\"")) });
-                }
-                _ => values.push(quote! { #field_name.serialize_json() }),
-              }
-            }
-            _ => panic!("Unknown AMM Enum field type"),
-          }
-        }
-        enum_arms.push(quote! { #enum_type::#variant_type { #(#fields),* } => format!(#key, #(#values),*) });
+      syn::Fields::Named(_) => {
+        let rest = fields.iter().any(|field| field.attrs.skip).then(|| quote! { .. });
+        let fields: Vec<_> = fields.iter().filter(|field| !field.attrs.skip).collect();
+        let bindings = fields.iter().map(|field| &field.binding);
+        let members = fields
+          .iter()
+          .map(|Field { binding, key, .. }| quote! { (#key, #binding as &dyn JsonSerializer) });
+        enum_arms.push(quote! { Self::#variant_type { #(#bindings,)* #rest } => {
+          options.write_object(out, &mut [(#tag, &#variant_type_string as &dyn JsonSerializer), #(#members),*])
+        }});
       }
-      syn::Fields::Unnamed(unnamed_fields) => match &unnamed_fields.unnamed.first().unwrap().ty {
-        syn::Type::Path(type_path) => {
-          let field_details = type_path.path.segments.first().unwrap();
-          match &field_details.ident {
-            field_type if field_type == "u8" => {
-              let variant_type_string = alloc::format!("\"{variant_type}");
-              enum_arms.push(quote! { #enum_type::#variant_type(el) => #variant_type_string.to_string() + "-" + el.serialize_json().as_str() + "\"" });
-            }
-            _ => {
-              enum_arms.push(quote! { #enum_type::#variant_type(el) => el.serialize_json() });
-            }
-          }
+      syn::Fields::Unnamed(unnamed_fields) => match unnamed_fields.unnamed.first() {
+        Some(field) if fields.len() == 1 && type_is(&field.ty, "u8") => {
+          enum_arms.push(quote! { Self::#variant_type(el) => write!(out, "\"{}-{}\"", #variant_type_string, el) });
+        }
+        _ if fields.len() == 1 => {
+          enum_arms.push(quote! { Self::#variant_type(el) => el.write_json(out, options) });
         }
-        _ => panic!("Unknown AMM Enum field type"),
+        _ => return Err(single_field_error(variant)),
       },
       syn::Fields::Unit => {
-        let variant_type_string = alloc::format!("\"{variant_type}\"");
-        enum_arms.push(quote! { #enum_type::#variant_type => #variant_type_string.to_string() });
+        let variant_type_string = format!("\"{variant_type_string}\"");
+        enum_arms.push(quote! { Self::#variant_type => out.write_str(#variant_type_string) });
       }
     }
   }
 
   // Generate the actual serialization function
-  TokenStream::from(quote! {
-    impl JsonSerializer for #enum_type {
-      fn serialize_json(&self) -> String {
+  let header = impl_header(ast, &quote! { JsonSerializer }, None);
+  Ok(quote! {
+    #header {
+      fn write_json(&self, out: &mut dyn core::fmt::Write, options: &JsonOptions) -> core::fmt::Result {
         match self { #(#enum_arms),* }
       }
     }
   })
 }
 
-fn deserialize_enum_json(enum_type: &syn::Ident, data: &syn::DataEnum) -> TokenStream {
+fn deserialize_enum_json(ast: &syn::DeriveInput, data: &syn::DataEnum) -> syn::Result<proc_macro2::TokenStream> {
   // Add a match arm for all possible enum variants based on their type
+  let container = Attributes::parse(&ast.attrs, &["tag"], "AMM enums")?;
+  let tag = container.tag.unwrap_or_else(|| String::from("_type"));
+  let unknown_variant = format!("Unknown {} variant: ", ast.ident);
   let mut enum_arms: Vec<proc_macro2::TokenStream> = Vec::new();
   let mut unit_enum_arms: Vec<proc_macro2::TokenStream> = Vec::new();
   for variant in &data.variants {
     let variant_type = &variant.ident;
-    let variant_type_string = alloc::format!("{variant_type}");
+    let variant_type_string = variant_name(variant)?;
+    let fields = parse_variant_fields(variant)?;
     match &variant.fields {
-      syn::Fields::Named(named_fields) => {
-        let mut fields = Vec::new();
-        for field in &named_fields.named {
-          let field_name = field.ident.as_ref().unwrap();
-          let field_name_string = alloc::format!("{field_name}");
-          match &field.ty {
-            syn::Type::Path(type_path) => {
-              let field_details = type_path.path.segments.first().unwrap();
-              match &field_details.ident {
-                field_type if field_type == "Vec" => {
-                  if let syn::PathArguments::AngleBracketed(details) = &field_details.arguments {
-                    if let syn::GenericArgument::Type(syn::Type::Path(vec_path)) = details.args.first().unwrap() {
-                      let content_type = &vec_path.path.segments.first().unwrap().ident;
-                      fields.push(quote! { #field_name: struct_fields.get(#field_name_string).ok_or(format!("Missing AMM enum field: \"{}\"", #field_name_string))?.split(',').map(|x| #content_type::deserialize_json(x).unwrap_or_default()).collect() });
-                    }
-                  }
-                }
-                field_type if field_type == "Option" => {
-                  if let syn::PathArguments::AngleBracketed(details) = &field_details.arguments {
-                    if let syn::GenericArgument::Type(syn::Type::Path(option_path)) = details.args.first().unwrap() {
-                      let content_type = &option_path.path.segments.first().unwrap().ident;
-                      fields.push(quote! {
-                        #field_name: match struct_fields.get(#field_name_string) {
-                          Some(value) => { if value.is_empty() { None } else { Some(#content_type::deserialize_json(value).unwrap_or_default()) } },
-                          None => None,
-                        }
-                      });
-                    }
-                  }
-                }
-                _ => {
-                  fields.push(quote! { #field_name: #type_path::deserialize_json(struct_fields.get(#field_name_string).ok_or(format!("Missing AMM enum field: \"{}\"", #field_name_string))?)? });
-                }
-              }
-            }
-            _ => panic!("Unknown AMM Enum field type"),
-          }
-        }
+      syn::Fields::Named(_) => {
+        let (variables, keys, values) = read_named_fields(&fields, "Missing AMM enum field: ");
         enum_arms.push(quote! {
           #variant_type_string => {
-            let mut value;
-            let mut struct_fields = BTreeMap::new();
-            let (mut data, mut key) = json_next_key(json);
-            while !key.is_empty() {
-              (data, value) = json_next_value(data);
-              struct_fields.insert(key, value);
-              (data, key) = json_next_key(data);
+            #(let mut #variables = None;)*
+            reader.begin_object()?;
+            while let Some(key) = reader.next_key()? {
+              // Null values are treated the same as missing fields
+              if reader.next_is_null() {
+                reader.skip_value()?;
+                continue;
+              }
+              match &*key {
+                #(#keys,)*
+                _ => reader.skip_value()?,
+              }
             }
-            Self::#variant_type { #(#fields),* }
+            Self::#variant_type { #(#values),* }
           }
         });
       }
-      syn::Fields::Unnamed(unnamed_fields) => match &unnamed_fields.unnamed.first().unwrap().ty {
-        syn::Type::Path(type_path) => {
-          let field_details = type_path.path.segments.first().unwrap();
-          match &field_details.ident {
-            field_type if field_type == "u8" => {
-              let variant_type_string_dash = variant_type_string.clone() + "-";
-              unit_enum_arms.push(quote! { x if x.contains(#variant_type_string_dash) => {
-                  match json.find('-') {
-                    Some(idx) => Self::#variant_type(#type_path::deserialize_json(&json[idx+1..])?),
-                    None => Self::#variant_type(1),
-                  }
-                }
-              });
-            }
-            _ => {
-              enum_arms
-                .push(quote! { #variant_type_string => Self::#variant_type(#variant_type::deserialize_json(json)?) });
+      syn::Fields::Unnamed(unnamed_fields) => match unnamed_fields.unnamed.first() {
+        Some(field) if fields.len() == 1 && type_is(&field.ty, "u8") => {
+          let variant_type_string_dash = variant_type_string + "-";
+          let length = variant_type_string_dash.len();
+          unit_enum_arms.push(quote! { x if x.starts_with(#variant_type_string_dash) => {
+              Self::#variant_type(JsonDeserializer::deserialize_json(&x[#length..]).map_err(|err| err.at(start))?)
             }
-          }
+          });
         }
-        _ => panic!("Unknown AMM Enum field type"),
+        _ if fields.len() == 1 => {
+          enum_arms.push(quote! { #variant_type_string => Self::#variant_type(JsonDeserializer::read_json(reader)?) });
+        }
+        _ => return Err(single_field_error(variant)),
       },
       syn::Fields::Unit => unit_enum_arms.push(quote! { #variant_type_string => Self::#variant_type }),
     }
   }
-  unit_enum_arms.push(quote! { _ => Err(alloc::format!("Unknown enum field: {}", json))? });
+  unit_enum_arms.push(quote! { _ => Err(AmmError::new(AmmErrorKind::UnknownVariant, String::from(#unknown_variant) + &*value).at(start))? });
 
-  // Generate the actual deserialization function
-  if enum_arms.is_empty() {
-    TokenStream::from(quote! {
-      impl JsonDeserializer for #enum_type {
-        fn deserialize_json(json: &str) -> Result<Self, String> {
-          Ok(match json { #(#unit_enum_arms),* })
-        }
+  // Generate the actual deserialization function, reading tagged objects only when the enum has such variants
+  let read_unit = quote! {
+    let value = reader.read_string()?;
+    Ok(match &*value { #(#unit_enum_arms),* })
+  };
+  let read_variant = if enum_arms.is_empty() {
+    read_unit
+  } else {
+    quote! {
+      if reader.next_is_object() {
+        let tag = reader.peek_tag(#tag)?.unwrap_or_default();
+        Ok(match &*tag {
+          #(#enum_arms,)*
+          _ => Err(AmmError::new(AmmErrorKind::UnknownVariant, String::from(#unknown_variant) + &*tag).at(start))?,
+        })
+      } else {
+        #read_unit
       }
-    })
+    }
+  };
+  let header = impl_header(ast, &quote! { JsonDeserializer }, None);
+  Ok(quote! {
+    #header {
+      fn read_json(reader: &mut JsonReader) -> Result<Self, AmmError> {
+        let start = reader.value_start();
+        #read_variant
+      }
+    }
+  })
+}
+
+fn serialize_struct_json(ast: &syn::DeriveInput, fields: &syn::Fields) -> syn::Result<proc_macro2::TokenStream> {
+  let container = Attributes::parse(&ast.attrs, struct_attributes(fields), "AMM structs")?;
+  let parsed_fields = parse_struct_fields(fields)?;
+  let members: Vec<_> = parsed_fields.iter().filter(|field| !field.attrs.skip).collect();
+  let body = if let syn::Fields::Named(_) = fields {
+    // Every field is written as an object member following the type tag
+    let tag = container.tag.unwrap_or_else(|| String::from("_type"));
+    let struct_type_string = container.rename.unwrap_or_else(|| ast.ident.to_string());
+    let members = members
+      .iter()
+      .map(|Field { member, key, .. }| quote! { (#key, &self.#member as &dyn JsonSerializer) });
+    quote! { options.write_object(out, &mut [(#tag, &#struct_type_string as &dyn JsonSerializer), #(#members),*]) }
+  } else if is_newtype(fields) {
+    // Single-field tuple structs are written as their only value
+    quote! { self.0.write_json(out, options) }
+  } else if members.is_empty() {
+    quote! { out.write_str("[]") }
   } else {
-    TokenStream::from(quote! {
-      impl JsonDeserializer for #enum_type {
-        fn deserialize_json(json: &str) -> Result<Self, String> {
-          Ok(match json_get_type(json) {
-            #(#enum_arms),*,
-            _ => match json { #(#unit_enum_arms),* },
-          })
-        }
+    // Other tuple structs are written as an array of their values
+    let members = members.iter().map(|field| &field.member);
+    quote! { options.write_array(out, [#(&self.#members as &dyn JsonSerializer),*]) }
+  };
+
+  // Generate the actual serialization function
+  let header = impl_header(ast, &quote! { JsonSerializer }, None);
+  Ok(quote! {
+    #header {
+      fn write_json(&self, out: &mut dyn core::fmt::Write, options: &JsonOptions) -> core::fmt::Result {
+        #body
       }
-    })
-  }
+    }
+  })
 }
 
-fn serialize_struct_json(struct_type: &syn::Ident, fields: &syn::FieldsNamed) -> TokenStream {
-  // Serialize each struct field based on its type
-  let struct_type_string = alloc::format!("{struct_type}");
-  let mut serialized_fields: Vec<proc_macro2::TokenStream> = Vec::new();
-  for (idx, field) in fields.named.iter().enumerate() {
-    let field_name = field.ident.as_ref().unwrap();
-    match &field.ty {
-      syn::Type::Path(type_path) => {
-        let field_details = type_path.path.segments.first().unwrap();
-        match &field_details.ident {
-          field_type if field_type == "Vec" || field_type == "BTreeSet" => {
-            let key = alloc::format!(
-              "\"{}\":[{{}}]{}",
-              format_ident!("{field_name}"),
-              if idx + 1 < fields.named.len() { "," } else { "" }
-            );
-            serialized_fields.push(quote! { format!(#key, self.#field_name.iter().map(|el| el.serialize_json()).collect::<Vec<_>>().join(",")).as_str() });
-          }
-          field_type if field_type == "Option" => {
-            let key = alloc::format!(
-              "\"{}\":{{}}{}",
-              format_ident!("{field_name}"),
-              if idx + 1 < fields.named.len() { "," } else { "" }
-            );
-            serialized_fields.push(quote! { format!(#key, self.#field_name.as_ref().map(|el| el.serialize_json()).unwrap_or(String::from("\"\""))).as_str() });
-          }
-          field_type if field_type == "BTreeMap" => {
-            let key = alloc::format!(
-              "\"{}\":{{{{{{}}}}}}{}",
-              format_ident!("{field_name}"),
-              if idx + 1 < fields.named.len() { "," } else { "" }
-            );
-            serialized_fields.push(quote! { format!(#key, self.#field_name.iter().map(|(k, v)| format!("\"{}\":\"{}\"", k, v)).collect::<Vec<_>>().join(",")).as_str() });
-          }
-          _ => {
-            let key = alloc::format!(
-              "\"{}\":{{}}{}",
-              format_ident!("{field_name}"),
-              if idx + 1 < fields.named.len() { "," } else { "" }
-            );
-            serialized_fields.push(quote! { format!(#key, self.#field_name.serialize_json()).as_str() });
-          }
+fn deserialize_struct_json(ast: &syn::DeriveInput, fields: &syn::Fields) -> syn::Result<proc_macro2::TokenStream> {
+  Attributes::parse(&ast.attrs, struct_attributes(fields), "AMM structs")?;
+  let parsed_fields = parse_struct_fields(fields)?;
+  let body = if let syn::Fields::Named(_) = fields {
+    // Deserialize each struct field by key, failing on missing fields that have no default value
+    let (variables, keys, values) = read_named_fields(&parsed_fields, "Missing AMM struct field: ");
+    let start = parsed_fields
+      .iter()
+      .any(is_required)
+      .then(|| quote! { let start = reader.value_start(); });
+    quote! {
+      #start
+      #(let mut #variables = None;)*
+      reader.begin_object()?;
+      while let Some(key) = reader.next_key()? {
+        // Null values are treated the same as missing fields
+        if reader.next_is_null() {
+          reader.skip_value()?;
+          continue;
+        }
+        match &*key {
+          #(#keys,)*
+          _ => reader.skip_value()?,
         }
       }
-      _ => panic!("Unknown AMM Struct field type"),
+      Ok(Self { #(#values),* })
+    }
+  } else if is_newtype(fields) {
+    quote! { Ok(Self(JsonDeserializer::read_json(reader)?)) }
+  } else {
+    // Tuple struct values are read in order, ignoring any surplus array items
+    let (mut reads, mut values) = (Vec::new(), Vec::new());
+    for Field { binding, attrs, .. } in &parsed_fields {
+      if attrs.skip {
+        values.push(quote! { Default::default() });
+        continue;
+      }
+      let idx = reads.len();
+      reads.push(quote! {
+        let #binding = if open && reader.next_item()? {
+          Some(JsonDeserializer::read_json(reader).map_err(|err| err.in_index(#idx))?)
+        } else {
+          open = false;
+          None
+        };
+      });
+      values.push(if attrs.default {
+        quote! { #binding.unwrap_or_default() }
+      } else {
+        let message = format!("Missing AMM tuple struct value: {idx}");
+        quote! { #binding.ok_or_else(|| AmmError::new(AmmErrorKind::MissingField, #message).at(start))? }
+      });
+    }
+    let start = parsed_fields
+      .iter()
+      .any(|field| !field.attrs.skip && !field.attrs.default)
+      .then(|| quote! { let start = reader.value_start(); });
+    let open = (!reads.is_empty()).then(|| quote! { let mut open = true; });
+    let surplus = if reads.is_empty() {
+      quote! { reader.next_item()? }
+    } else {
+      quote! { open && reader.next_item()? }
+    };
+    quote! {
+      #start
+      reader.begin_array()?;
+      #open
+      #(#reads)*
+      while #surplus {
+        reader.skip_value()?;
+      }
+      Ok(Self(#(#values),*))
+    }
+  };
+
+  // Generate the actual deserialization function
+  let header = impl_header(ast, &quote! { JsonDeserializer }, None);
+  Ok(quote! {
+    #header {
+      fn read_json(reader: &mut JsonReader) -> Result<Self, AmmError> {
+        #body
+      }
+    }
+  })
+}
+
+fn serialize_enum_binary(ast: &syn::DeriveInput, data: &syn::DataEnum) -> syn::Result<proc_macro2::TokenStream> {
+  // Each variant is written as its index followed by its fields in declaration order
+  Attributes::parse(&ast.attrs, &["tag"], "AMM enums")?;
+  let mut enum_arms: Vec<proc_macro2::TokenStream> = Vec::new();
+  for (idx, variant) in data.variants.iter().enumerate() {
+    let variant_type = &variant.ident;
+    variant_name(variant)?;
+    let fields = parse_variant_fields(variant)?;
+    let bindings: Vec<_> = fields
+      .iter()
+      .filter(|field| !field.attrs.skip)
+      .map(|field| &field.binding)
+      .collect();
+    match &variant.fields {
+      syn::Fields::Named(_) => {
+        let rest = fields.iter().any(|field| field.attrs.skip).then(|| quote! { .. });
+        enum_arms.push(quote! { Self::#variant_type { #(#bindings,)* #rest } => {
+          #idx.serialize_binary(output);
+          #(#bindings.serialize_binary(output);)*
+        }});
+      }
+      syn::Fields::Unnamed(_) => {
+        enum_arms.push(quote! { Self::#variant_type(#(#bindings),*) => {
+          #idx.serialize_binary(output);
+          #(#bindings.serialize_binary(output);)*
+        }});
+      }
+      syn::Fields::Unit => enum_arms.push(quote! { Self::#variant_type => #idx.serialize_binary(output) }),
     }
   }
 
   // Generate the actual serialization function
-  TokenStream::from(quote! {
-    impl JsonSerializer for #struct_type {
-      fn serialize_json(&self) -> String {
-        String::from("{\"_type\":\"") + #struct_type_string + "\"," + #(#serialized_fields)+* + "}"
+  let header = impl_header(ast, &quote! { BinarySerializer }, None);
+  Ok(quote! {
+    #header {
+      fn serialize_binary(&self, output: &mut Vec<u8>) {
+        match self { #(#enum_arms),* }
       }
     }
   })
 }
 
-fn deserialize_struct_json(struct_type: &syn::Ident, fields: &syn::FieldsNamed) -> TokenStream {
-  // Deserialize each struct field based on its type
-  let mut serialized_fields: Vec<proc_macro2::TokenStream> = Vec::new();
-  for field in &fields.named {
-    let field_name = field.ident.as_ref().unwrap();
-    let field_name_string = alloc::format!("{field_name}");
-    match &field.ty {
-      syn::Type::Path(type_path) => {
-        let field_details = type_path.path.segments.first().unwrap();
-        match &field_details.ident {
-          field_type if field_type == "Vec" => {
-            if let syn::PathArguments::AngleBracketed(details) = &field_details.arguments {
-              if let syn::GenericArgument::Type(syn::Type::Path(vec_path)) = details.args.first().unwrap() {
-                let content_type = &vec_path.path.segments.first().unwrap().ident;
-                serialized_fields.push(quote! { #field_name_string => {
-                  let mut subdata = value;
-                  (subdata, value) = json_next_value(subdata);
-                  while !value.is_empty() {
-                    parsed.#field_name.push(#content_type::deserialize_json(value)?);
-                    (subdata, value) = json_next_value(subdata);
-                  }
-                }});
-              }
-            }
-          }
-          field_type if field_type == "BTreeSet" => {
-            if let syn::PathArguments::AngleBracketed(details) = &field_details.arguments {
-              if let syn::GenericArgument::Type(syn::Type::Path(vec_path)) = details.args.first().unwrap() {
-                let content_type = &vec_path.path.segments.first().unwrap().ident;
-                serialized_fields.push(quote! { #field_name_string => {
-                  let mut subdata = value;
-                  (subdata, value) = json_next_value(subdata);
-                  while !value.is_empty() {
-                    parsed.#field_name.insert(#content_type::deserialize_json(value)?);
-                    (subdata, value) = json_next_value(subdata);
-                  }
-                }});
-              }
-            }
-          }
-          field_type if field_type == "Option" => {
-            if let syn::PathArguments::AngleBracketed(details) = &field_details.arguments {
-              if let syn::GenericArgument::Type(syn::Type::Path(option_path)) = details.args.first().unwrap() {
-                let content_type = &option_path.path.segments.first().unwrap().ident;
-                serialized_fields.push(
-                  quote! { #field_name_string => parsed.#field_name = if value.is_empty() { None } else { Some(#content_type::deserialize_json(value)?) } },
-                );
-              }
+fn deserialize_enum_binary(ast: &syn::DeriveInput, data: &syn::DataEnum) -> syn::Result<proc_macro2::TokenStream> {
+  // Read the variant index and decode the fields of the matching variant
+  Attributes::parse(&ast.attrs, &["tag"], "AMM enums")?;
+  let mut enum_arms: Vec<proc_macro2::TokenStream> = Vec::new();
+  for (idx, variant) in data.variants.iter().enumerate() {
+    let variant_type = &variant.ident;
+    variant_name(variant)?;
+    let fields = parse_variant_fields(variant)?;
+    match &variant.fields {
+      syn::Fields::Named(_) => {
+        let values = fields.iter().map(
+          |Field {
+             binding, key, attrs, ..
+           }| {
+            if attrs.skip {
+              quote! { #binding: Default::default() }
+            } else {
+              quote! { #binding: BinaryDeserializer::deserialize_binary(data).map_err(|err| err.in_field(#key))? }
             }
-          }
-          field_type if field_type == "BTreeMap" => {
-            serialized_fields.push(quote! { #field_name_string => {
-              let mut subdata = value;
-              (subdata, key) = json_next_key(subdata);
-              while !key.is_empty() {
-                (subdata, value) = json_next_value(subdata);
-                parsed.#field_name.insert(String::deserialize_json(key)?, String::deserialize_json(value)?);
-                (subdata, key) = json_next_key(subdata);
-              }
-            }});
-          }
-          field_type => {
-            serialized_fields
-              .push(quote! { #field_name_string => parsed.#field_name = #field_type::deserialize_json(value)? });
-          }
-        }
+          },
+        );
+        enum_arms.push(quote! { #idx => Self::#variant_type { #(#values),* } });
       }
-      _ => panic!("Unknown AMM Struct field type"),
+      syn::Fields::Unnamed(_) => {
+        let values = fields
+          .iter()
+          .map(|_| quote! { BinaryDeserializer::deserialize_binary(data)? });
+        enum_arms.push(quote! { #idx => Self::#variant_type(#(#values),*) });
+      }
+      syn::Fields::Unit => enum_arms.push(quote! { #idx => Self::#variant_type }),
     }
   }
+  let unknown_variant = format!("Unknown {} variant index: ", ast.ident);
 
   // Generate the actual deserialization function
-  TokenStream::from(quote! {
-    impl JsonDeserializer for #struct_type {
-      fn deserialize_json(json: &str) -> Result<Self, String> {
-        let mut value;
-        let mut parsed = Self::default();
-        let (mut data, mut key) = json_next_key(json);
-        while !key.is_empty() {
-          (data, value) = json_next_value(data);
-          match key {
-            #(#serialized_fields),*,
-            _ => (),
-          }
-          (data, key) = json_next_key(data);
+  let header = impl_header(ast, &quote! { BinaryDeserializer }, None);
+  Ok(quote! {
+    #header {
+      fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+        Ok(match usize::deserialize_binary(data)? {
+          #(#enum_arms),*,
+          idx => Err(AmmError::new(AmmErrorKind::UnknownVariant, String::from(#unknown_variant) + &idx.to_string()))?,
+        })
+      }
+    }
+  })
+}
+
+fn serialize_struct_binary(ast: &syn::DeriveInput, fields: &syn::Fields) -> syn::Result<proc_macro2::TokenStream> {
+  // Struct fields are written back-to-back in declaration order
+  Attributes::parse(&ast.attrs, struct_attributes(fields), "AMM structs")?;
+  let fields = parse_struct_fields(fields)?;
+  let members = fields
+    .iter()
+    .filter(|field| !field.attrs.skip)
+    .map(|field| &field.member);
+  let header = impl_header(ast, &quote! { BinarySerializer }, None);
+  Ok(quote! {
+    #header {
+      fn serialize_binary(&self, output: &mut Vec<u8>) {
+        #(self.#members.serialize_binary(output);)*
+      }
+    }
+  })
+}
+
+fn deserialize_struct_binary(ast: &syn::DeriveInput, fields: &syn::Fields) -> syn::Result<proc_macro2::TokenStream> {
+  Attributes::parse(&ast.attrs, struct_attributes(fields), "AMM structs")?;
+  let values = parse_struct_fields(fields)?
+    .into_iter()
+    .map(|Field { member, key, attrs, .. }| {
+      let value = match &member {
+        _ if attrs.skip => quote! { Default::default() },
+        syn::Member::Named(_) => {
+          quote! { BinaryDeserializer::deserialize_binary(data).map_err(|err| err.in_field(#key))? }
+        }
+        syn::Member::Unnamed(idx) => {
+          let idx = idx.index as usize;
+          quote! { BinaryDeserializer::deserialize_binary(data).map_err(|err| err.in_index(#idx))? }
         }
-        Ok(parsed)
+      };
+      quote! { #member: #value }
+    });
+  let header = impl_header(ast, &quote! { BinaryDeserializer }, None);
+  Ok(quote! {
+    #header {
+      fn deserialize_binary(data: &mut &[u8]) -> Result<Self, AmmError> {
+        Ok(Self { #(#values),* })
       }
     }
   })
 }
 
-#[allow(clippy::missing_panics_doc)]
-#[proc_macro_derive(JsonSerialize)]
-pub fn json_serialize(tokens: TokenStream) -> TokenStream {
-  if let Ok(ast) = syn::parse::<syn::DeriveInput>(tokens) {
-    match &ast.data {
-      syn::Data::Struct(data) => match &data.fields {
-        syn::Fields::Named(named_fields) => serialize_struct_json(&ast.ident, named_fields),
-        _ => panic!("Unit and tuple structs are not supported in AMM objects"),
+fn json_schema_enum(ast: &syn::DeriveInput, data: &syn::DataEnum) -> syn::Result<proc_macro2::TokenStream> {
+  // Collect the schemas of all variants, describing every unit variant with a single string enumeration
+  let container = Attributes::parse(&ast.attrs, &["tag"], "AMM enums")?;
+  let tag = container.tag.unwrap_or_else(|| String::from("_type"));
+  let (mut unit_variants, mut schemas) = (Vec::new(), Vec::new());
+  for variant in &data.variants {
+    let variant_type_string = variant_name(variant)?;
+    let fields = parse_variant_fields(variant)?;
+    match &variant.fields {
+      syn::Fields::Named(_) => {
+        let properties = fields
+          .iter()
+          .filter(|field| !field.attrs.skip)
+          .map(|Field { key, ty, .. }| quote! { (#key, <#ty as JsonSchema>::json_schema(definitions)) });
+        let required = fields.iter().filter(|field| is_required(field)).map(|field| &field.key);
+        schemas.push(quote! {
+          json_schema::object(
+            [(#tag, json_schema::constant(#variant_type_string)), #(#properties),*],
+            [#tag, #(#required),*],
+          )
+        });
+      }
+      syn::Fields::Unnamed(unnamed_fields) => match unnamed_fields.unnamed.first() {
+        Some(field) if fields.len() == 1 && type_is(&field.ty, "u8") => {
+          let pattern = format!("^{}-[0-9]+$", escape_pattern(&variant_type_string));
+          schemas.push(quote! { json_schema::string_pattern(#pattern) });
+        }
+        Some(field) if fields.len() == 1 => {
+          let ty = &field.ty;
+          schemas.push(quote! { <#ty as JsonSchema>::json_schema(definitions) });
+        }
+        _ => return Err(single_field_error(variant)),
       },
-      syn::Data::Enum(data) => serialize_enum_json(&ast.ident, data),
-      syn::Data::Union(_) => panic!("Union types are not supported in AMM objects"),
+      syn::Fields::Unit => unit_variants.push(variant_type_string),
     }
-  } else {
-    panic!("Invalid input for AMM object serialization");
   }
+  if !unit_variants.is_empty() {
+    schemas.insert(0, quote! { json_schema::string_enum(&[#(#unit_variants),*]) });
+  }
+  Ok(json_schema_impl(ast, quote! { json_schema::any_of([#(#schemas),*]) }))
 }
 
-#[allow(clippy::missing_panics_doc)]
-#[proc_macro_derive(JsonDeserialize)]
-pub fn json_deserialize(tokens: TokenStream) -> TokenStream {
-  if let Ok(ast) = syn::parse::<syn::DeriveInput>(tokens) {
-    match &ast.data {
-      syn::Data::Struct(data) => match &data.fields {
-        syn::Fields::Named(named_fields) => deserialize_struct_json(&ast.ident, named_fields),
-        _ => panic!("Unit and tuple structs are not supported in AMM objects"),
-      },
-      syn::Data::Enum(data) => deserialize_enum_json(&ast.ident, data),
-      syn::Data::Union(_) => panic!("Union types are not supported in AMM objects"),
+fn json_schema_struct(ast: &syn::DeriveInput, fields: &syn::Fields) -> syn::Result<proc_macro2::TokenStream> {
+  let container = Attributes::parse(&ast.attrs, struct_attributes(fields), "AMM structs")?;
+  let parsed_fields = parse_struct_fields(fields)?;
+  let members: Vec<_> = parsed_fields.iter().filter(|field| !field.attrs.skip).collect();
+  let schema = if let syn::Fields::Named(_) = fields {
+    let tag = container.tag.unwrap_or_else(|| String::from("_type"));
+    let struct_type_string = container.rename.unwrap_or_else(|| ast.ident.to_string());
+    let properties = members
+      .iter()
+      .map(|Field { key, ty, .. }| quote! { (#key, <#ty as JsonSchema>::json_schema(definitions)) });
+    let required = members
+      .iter()
+      .filter(|field| is_required(field))
+      .map(|field| &field.key);
+    quote! {
+      json_schema::object(
+        [(#tag, json_schema::constant(#struct_type_string)), #(#properties),*],
+        [#tag, #(#required),*],
+      )
+    }
+  } else if is_newtype(fields) {
+    let ty = members.iter().map(|field| field.ty);
+    quote! { #(<#ty as JsonSchema>::json_schema(definitions))* }
+  } else {
+    // Values marked as default may be missing from the end of the array
+    let items = members.iter().map(|field| field.ty);
+    let required = members
+      .iter()
+      .rposition(|field| !field.attrs.default)
+      .map_or(0, |idx| idx + 1);
+    quote! { json_schema::tuple([#(<#items as JsonSchema>::json_schema(definitions)),*], #required) }
+  };
+  Ok(json_schema_impl(ast, schema))
+}
+
+/// Escapes all characters of `name` that have a special meaning in regular expressions.
+fn escape_pattern(name: &str) -> String {
+  name.chars().fold(String::new(), |mut pattern, c| {
+    if !c.is_alphanumeric() && c != '_' {
+      pattern.push('\\');
     }
+    pattern.push(c);
+    pattern
+  })
+}
+
+/// Generates the `JsonSchema` implementation returning `schema`, which is
+/// stored as a named definition unless the type is generic.
+fn json_schema_impl(ast: &syn::DeriveInput, schema: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+  let header = impl_header(ast, &quote! { JsonSchema }, None);
+  let body = if ast.generics.type_params().next().is_some() {
+    schema
   } else {
-    panic!("Invalid input for AMM object deserialization");
+    let name = ast.ident.to_string();
+    quote! { json_schema::define(definitions, #name, |definitions| #schema) }
+  };
+  quote! {
+    #header {
+      fn json_schema(definitions: &mut BTreeMap<String, JsonValue>) -> JsonValue {
+        #body
+      }
+    }
   }
 }
 
-#[allow(clippy::missing_panics_doc)]
+/// Expands a derive macro using the generator matching the shape of the
+/// input type, reporting unsupported input as a compile error.
+fn expand(
+  tokens: TokenStream,
+  structs: fn(&syn::DeriveInput, &syn::Fields) -> syn::Result<proc_macro2::TokenStream>,
+  enums: fn(&syn::DeriveInput, &syn::DataEnum) -> syn::Result<proc_macro2::TokenStream>,
+) -> TokenStream {
+  let expanded = syn::parse::<syn::DeriveInput>(tokens).and_then(|ast| match &ast.data {
+    syn::Data::Struct(data) if matches!(data.fields, syn::Fields::Unit) => Err(syn::Error::new_spanned(
+      &ast.ident,
+      "Unit structs are not supported in AMM objects",
+    )),
+    syn::Data::Struct(data) => structs(&ast, &data.fields),
+    syn::Data::Enum(data) => enums(&ast, data),
+    syn::Data::Union(_) => Err(syn::Error::new_spanned(
+      &ast.ident,
+      "Union types are not supported in AMM objects",
+    )),
+  });
+  TokenStream::from(expanded.unwrap_or_else(syn::Error::into_compile_error))
+}
+
+/// Derives `JsonSerializer` for a struct or enum.
+///
+/// Structs with named fields are written as objects tagged with their type
+/// name under the `_type` key, tuple structs as arrays of their values, and
+/// single-field tuple structs as their only value. Enum variants are written
+/// as their name, as `"Name-N"` when holding a single `u8`, as their only
+/// value when holding any other single value, or as tagged objects when
+/// they have named fields.
+///
+/// The layout can be adjusted with `#[amm(...)]` attributes:
+/// - `tag = "key"` on a struct or enum replaces the `_type` tag key
+/// - `rename = "name"` on a struct, variant or named field replaces its name
+/// - `skip` on a field leaves it out, so it takes its default value when read
+/// - `default` on a field allows it to be missing, in which case it takes its
+///   default value
+///
+/// Type parameters are required to implement the derived trait themselves.
+#[proc_macro_derive(JsonSerialize, attributes(amm))]
+pub fn json_serialize(tokens: TokenStream) -> TokenStream {
+  expand(tokens, serialize_struct_json, serialize_enum_json)
+}
+
+/// Derives `JsonDeserializer` for a struct or enum, reading the layout
+/// written by [`JsonSerialize`](derive@JsonSerialize).
+///
+/// Fields missing from the document are an error unless they hold an `Option`
+/// or are marked `#[amm(default)]`.
+#[proc_macro_derive(JsonDeserialize, attributes(amm))]
+pub fn json_deserialize(tokens: TokenStream) -> TokenStream {
+  expand(tokens, deserialize_struct_json, deserialize_enum_json)
+}
+
+/// Derives `BinarySerializer` for a struct or enum, writing its fields in
+/// declaration order and leaving out those marked `#[amm(skip)]`.
+#[proc_macro_derive(BinarySerialize, attributes(amm))]
+pub fn binary_serialize(tokens: TokenStream) -> TokenStream {
+  expand(tokens, serialize_struct_binary, serialize_enum_binary)
+}
+
+/// Derives `BinaryDeserializer` for a struct or enum, reading the layout
+/// written by [`BinarySerialize`](derive@BinarySerialize).
+#[proc_macro_derive(BinaryDeserialize, attributes(amm))]
+pub fn binary_deserialize(tokens: TokenStream) -> TokenStream {
+  expand(tokens, deserialize_struct_binary, deserialize_enum_binary)
+}
+
+/// Derives `JsonSchema` for a struct or enum, describing the layout written
+/// by [`JsonSerialize`](derive@JsonSerialize) as a JSON Schema fragment.
+///
+/// Non-generic types are added to the schema definitions under their Rust
+/// type name and referred to from wherever they are used.
+#[proc_macro_derive(JsonSchema, attributes(amm))]
+pub fn json_schema(tokens: TokenStream) -> TokenStream {
+  expand(tokens, json_schema_struct, json_schema_enum)
+}
+
 #[proc_macro_derive(ModOrder)]
 pub fn modification_order(tokens: TokenStream) -> TokenStream {
-  if let Ok(ast) = syn::parse::<syn::DeriveInput>(tokens) {
-    match &ast.data {
-      syn::Data::Enum(data) => {
-        let enum_type = &ast.ident;
-        let mut enum_arms: Vec<proc_macro2::TokenStream> = Vec::new();
-        for (enum_value, variant) in data.variants.iter().enumerate() {
-          let variant_type = &variant.ident;
-          match &variant.fields {
-            syn::Fields::Named(_) => enum_arms.push(quote! { Self::#variant_type { .. } => #enum_value }),
-            _ => enum_arms.push(quote! { Self::#variant_type => #enum_value }),
-          }
+  let expanded = syn::parse::<syn::DeriveInput>(tokens).and_then(|ast| match &ast.data {
+    syn::Data::Enum(data) => {
+      let enum_type = &ast.ident;
+      let mut enum_arms: Vec<proc_macro2::TokenStream> = Vec::new();
+      for (enum_value, variant) in data.variants.iter().enumerate() {
+        let variant_type = &variant.ident;
+        match &variant.fields {
+          syn::Fields::Named(_) => enum_arms.push(quote! { Self::#variant_type { .. } => #enum_value }),
+          _ => enum_arms.push(quote! { Self::#variant_type => #enum_value }),
         }
-        TokenStream::from(quote! {
-          impl #enum_type {
-            fn get_unique_value(&self) -> usize {
-              match self { #(#enum_arms),* }
-            }
+      }
+      Ok(quote! {
+        impl #enum_type {
+          fn get_unique_value(&self) -> usize {
+            match self { #(#enum_arms),* }
           }
+        }
 
-          impl Ord for #enum_type {
-            fn cmp(&self, other: &Self) -> core::cmp::Ordering {
-              self.get_unique_value().cmp(&other.get_unique_value())
-            }
+        impl Ord for #enum_type {
+          fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+            self.get_unique_value().cmp(&other.get_unique_value())
           }
+        }
 
-          impl PartialOrd for #enum_type {
-            fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
-              Some(self.cmp(other))
-            }
+        impl PartialOrd for #enum_type {
+          fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+            Some(self.cmp(other))
           }
-        })
-      }
-      _ => panic!("Only enums are supported for AMM modification ordering"),
+        }
+      })
     }
-  } else {
-    panic!("Invalid input for AMM modification ordering");
-  }
+    _ => Err(syn::Error::new_spanned(
+      &ast.ident,
+      "Only enums are supported for AMM modification ordering",
+    )),
+  });
+  TokenStream::from(expanded.unwrap_or_else(syn::Error::into_compile_error))
 }
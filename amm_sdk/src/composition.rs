@@ -3,9 +3,11 @@ use crate::note::Note;
 use crate::structure::{Chord, MultiVoice, Part, Phrase, Section, Staff};
 use crate::temporal::{place_and_merge_part_timeslice, PartTimeslice};
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 
-#[derive(Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Debug, Default, Eq, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+)]
 pub struct Composition {
   title: String,
   copyright: Option<String>,
@@ -1,5 +1,5 @@
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
@@ -7,7 +7,19 @@ use wasm_bindgen::prelude::*;
 ///
 /// Note that the same symbol can be used for different clef types.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum ClefSymbol {
   /// ![G Clef](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/clef-G.png)
   ///
@@ -28,7 +40,19 @@ pub enum ClefSymbol {
 ///
 /// A clef is used to determine the pitches for the notes on a staff.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum ClefType {
   /// Designates that pitch G4 is located on the second line from the bottom of the staff.
   #[default]
@@ -53,7 +77,19 @@ pub enum ClefType {
 
 /// Represents a clef which is used to determine the pitches for the notes on a staff.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub struct Clef {
   /// The symbol used to designate the clef.
   pub symbol: ClefSymbol,
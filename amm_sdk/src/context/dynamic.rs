@@ -1,8 +1,51 @@
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
+
+/// Represents the loudness envelope of a dynamic marking.
+///
+/// The `attack` level applies to the onset of the affected note(s), while the
+/// `sustain` level applies to the remainder of the marking's duration. Both levels
+/// are specified as relative loudness in the range `[0.0, 1.0]`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct DynamicEnvelope {
+  /// The relative loudness at the onset of a note.
+  pub attack: f32,
+  /// The relative loudness after the onset of a note.
+  pub sustain: f32,
+}
+
+impl DynamicEnvelope {
+  /// Creates a new dynamic envelope with the given attack and sustain levels.
+  #[must_use]
+  pub const fn new(attack: f32, sustain: f32) -> Self {
+    Self { attack, sustain }
+  }
+
+  /// Creates a new dynamic envelope with identical attack and sustain levels.
+  #[must_use]
+  pub const fn constant(level: f32) -> Self {
+    Self {
+      attack: level,
+      sustain: level,
+    }
+  }
+}
 
 /// Represents a dynamic marking in music notation.
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum Dynamic {
   /// ![Forte](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/f.png)
   ///
@@ -12,6 +55,16 @@ pub enum Dynamic {
   /// The magnitude of the notated forte is specified by the `u8` value.
   /// For example, `Forte(3)` represents a dynamic marking of `fff`.
   Forte(u8),
+  /// ![Forte Piano](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/fp.png)
+  ///
+  /// A `forte-piano` dynamic marking indicates that corresponding music should be
+  /// attacked loudly and then immediately played softly.
+  FortePiano,
+  /// ![Forzando](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/fz.png)
+  ///
+  /// A `forzando` dynamic marking indicates that the corresponding note should be
+  /// played with a sudden, forceful accent.
+  Forzando,
   #[default]
   /// ![Mezzo Forte](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/mf.png)
   ///
@@ -23,6 +76,11 @@ pub enum Dynamic {
   /// A `mezzo-piano` dynamic marking indicates that corresponding music should be played
   /// only slightly softer than average.
   MezzoPiano,
+  /// ![Niente](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/n.png)
+  ///
+  /// A `niente` dynamic marking indicates that corresponding music should fade
+  /// into (or emerge from) complete silence.
+  Niente,
   /// ![Piano](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/p.png)
   ///
   /// A `piano` dynamic marking indicates that corresponding music should be played
@@ -31,17 +89,131 @@ pub enum Dynamic {
   /// The magnitude of the notated piano is specified by the `u8` value.
   /// For example, `Piano(3)` represents a dynamic marking of `ppp`.
   Piano(u8),
+  /// ![Piano Forte](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/pf.png)
+  ///
+  /// A `piano-forte` dynamic marking indicates that corresponding music should be
+  /// attacked softly and then immediately played loudly.
+  PianoForte,
+  /// ![Rinforzando](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/rf.png)
+  ///
+  /// A `rinforzando` dynamic marking indicates that the corresponding notes should be
+  /// reinforced with a sustained emphasis.
+  Rinforzando,
+  /// ![Rinforzato](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/rfz.png)
+  ///
+  /// A `rinforzato` dynamic marking indicates that the corresponding notes should be
+  /// reinforced with a strong, sustained emphasis.
+  Rinforzato,
+  /// ![Sforzando](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/sf.png)
+  ///
+  /// A `sforzando` dynamic marking indicates that the corresponding note should be
+  /// played with a sudden, strong accent.
+  ///
+  /// The magnitude of the notated sforzando is specified by the `u8` value.
+  /// For example, `Sforzando(2)` represents a dynamic marking of `sff`.
+  Sforzando(u8),
+  /// ![Sforzando Piano](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/sfp.png)
+  ///
+  /// A `sforzando-piano` dynamic marking indicates that the corresponding note should be
+  /// played with a sudden, strong accent and then immediately played softly.
+  ///
+  /// The magnitude of the subsequent piano is specified by the `u8` value.
+  /// For example, `SforzandoPiano(2)` represents a dynamic marking of `sfpp`.
+  SforzandoPiano(u8),
+  /// ![Sforzato](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/sfz.png)
+  ///
+  /// A `sforzato` dynamic marking indicates that the corresponding note should be
+  /// played with a sudden, very strong accent.
+  ///
+  /// The magnitude of the notated sforzato is specified by the `u8` value.
+  /// For example, `Sforzato(2)` represents a dynamic marking of `sffz`.
+  Sforzato(u8),
+  /// ![Sforzato Piano](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/sfzp.png)
+  ///
+  /// A `sforzato-piano` dynamic marking indicates that the corresponding note should be
+  /// played with a sudden, very strong accent and then immediately played softly.
+  SforzatoPiano,
 }
 
 impl Dynamic {
-  /// Returns the relative loudness of the dynamic marking in the range `[0.0, 1.0]`.
+  /// Creates a new dynamic marking from its textual representation (e.g., `"ppp"`,
+  /// `"sfz"`, or `"niente"`).
+  ///
+  /// Returns `None` if the text does not correspond to a known dynamic marking.
   #[must_use]
-  pub fn value(&self) -> f32 {
+  pub fn from_text(text: &str) -> Option<Self> {
+    let text = text.trim().trim_end_matches('.');
+    let count = |letter: char, text: &str| {
+      if !text.is_empty() && text.chars().all(|ch| ch == letter) {
+        u8::try_from(text.len()).ok()
+      } else {
+        None
+      }
+    };
+    match text {
+      "n" | "niente" => Some(Self::Niente),
+      "mp" => Some(Self::MezzoPiano),
+      "mf" => Some(Self::MezzoForte),
+      "fp" => Some(Self::FortePiano),
+      "pf" => Some(Self::PianoForte),
+      "fz" => Some(Self::Forzando),
+      "rf" => Some(Self::Rinforzando),
+      "rfz" => Some(Self::Rinforzato),
+      "sfzp" => Some(Self::SforzatoPiano),
+      _ => {
+        if let Some(magnitude) = count('p', text) {
+          Some(Self::Piano(magnitude))
+        } else if let Some(magnitude) = count('f', text) {
+          Some(Self::Forte(magnitude))
+        } else if let Some(sforzando) = text.strip_prefix('s') {
+          if let Some(magnitude) = sforzando.strip_suffix('z').and_then(|text| count('f', text)) {
+            Some(Self::Sforzato(magnitude))
+          } else if let Some(magnitude) = count('f', sforzando) {
+            Some(Self::Sforzando(magnitude))
+          } else {
+            sforzando
+              .strip_prefix('f')
+              .and_then(|text| count('p', text))
+              .map(Self::SforzandoPiano)
+          }
+        } else {
+          None
+        }
+      }
+    }
+  }
+
+  /// Returns whether the dynamic marking only applies to the attack of the
+  /// note(s) on which it appears, rather than to all subsequent music.
+  #[must_use]
+  pub const fn is_accent(&self) -> bool {
+    matches!(
+      self,
+      Self::Forzando | Self::Rinforzando | Self::Rinforzato | Self::Sforzando(_) | Self::Sforzato(_)
+    )
+  }
+
+  /// Returns the relative loudness envelope of the dynamic marking, with all
+  /// levels in the range `[0.0, 1.0]`.
+  #[must_use]
+  pub fn value(&self) -> DynamicEnvelope {
+    let piano = |magnitude: u8| (0.5 - (0.1 * f32::from(magnitude))).max(0.05);
+    let forte = |magnitude: u8| (0.5 + (0.1 * f32::from(magnitude))).min(1.0);
     match *self {
-      Self::Piano(magnitude) => (0.5 - (0.1 * f32::from(magnitude))).max(0.05),
-      Self::MezzoPiano => 0.45,
-      Self::MezzoForte => 0.55,
-      Self::Forte(magnitude) => (0.5 + (0.1 * f32::from(magnitude))).min(1.0),
+      Self::Piano(magnitude) => DynamicEnvelope::constant(piano(magnitude)),
+      Self::MezzoPiano => DynamicEnvelope::constant(0.45),
+      Self::MezzoForte => DynamicEnvelope::constant(0.55),
+      Self::Forte(magnitude) => DynamicEnvelope::constant(forte(magnitude)),
+      Self::Niente => DynamicEnvelope::constant(0.0),
+      Self::FortePiano => DynamicEnvelope::new(forte(1), piano(1)),
+      Self::PianoForte => DynamicEnvelope::new(piano(1), forte(1)),
+      Self::Forzando => DynamicEnvelope::new(0.8, forte(1)),
+      Self::Rinforzando => DynamicEnvelope::new(0.7, 0.7),
+      Self::Rinforzato => DynamicEnvelope::new(0.75, 0.7),
+      Self::Sforzando(magnitude) => DynamicEnvelope::new((0.7 + (0.1 * f32::from(magnitude))).min(1.0), forte(1)),
+      Self::Sforzato(magnitude) => DynamicEnvelope::new((0.75 + (0.1 * f32::from(magnitude))).min(1.0), forte(1)),
+      Self::SforzandoPiano(magnitude) => DynamicEnvelope::new(0.8, piano(magnitude)),
+      Self::SforzatoPiano => DynamicEnvelope::new(0.85, piano(1)),
     }
   }
 }
@@ -54,6 +226,55 @@ impl core::fmt::Display for Dynamic {
       Self::MezzoPiano => write!(f, "mp"),
       Self::MezzoForte => write!(f, "mf"),
       Self::Forte(magnitude) => write!(f, "{}", "f".repeat(usize::from(magnitude))),
+      Self::FortePiano => write!(f, "fp"),
+      Self::Forzando => write!(f, "fz"),
+      Self::Niente => write!(f, "n"),
+      Self::PianoForte => write!(f, "pf"),
+      Self::Rinforzando => write!(f, "rf"),
+      Self::Rinforzato => write!(f, "rfz"),
+      Self::Sforzando(magnitude) => write!(f, "s{}", "f".repeat(usize::from(magnitude))),
+      Self::SforzandoPiano(magnitude) => write!(f, "sf{}", "p".repeat(usize::from(magnitude))),
+      Self::Sforzato(magnitude) => write!(f, "s{}z", "f".repeat(usize::from(magnitude))),
+      Self::SforzatoPiano => write!(f, "sfzp"),
     }
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_from_text() {
+    for dynamic in [
+      Dynamic::Forte(3),
+      Dynamic::FortePiano,
+      Dynamic::Forzando,
+      Dynamic::MezzoForte,
+      Dynamic::MezzoPiano,
+      Dynamic::Niente,
+      Dynamic::Piano(2),
+      Dynamic::PianoForte,
+      Dynamic::Rinforzando,
+      Dynamic::Rinforzato,
+      Dynamic::Sforzando(1),
+      Dynamic::SforzandoPiano(2),
+      Dynamic::Sforzato(2),
+      Dynamic::SforzatoPiano,
+    ] {
+      assert_eq!(Dynamic::from_text(&dynamic.to_string()), Some(dynamic));
+    }
+    assert_eq!(Dynamic::from_text("niente"), Some(Dynamic::Niente));
+    assert_eq!(Dynamic::from_text("dolce"), None);
+    assert_eq!(Dynamic::from_text(""), None);
+  }
+
+  #[test]
+  fn test_envelope() {
+    assert_eq!(Dynamic::MezzoForte.value(), DynamicEnvelope::constant(0.55));
+    let envelope = Dynamic::SforzandoPiano(1).value();
+    assert!(envelope.attack > Dynamic::Forte(2).value().attack);
+    assert!(envelope.sustain < Dynamic::MezzoPiano.value().sustain);
+    assert!(Dynamic::PianoForte.value().attack < Dynamic::PianoForte.value().sustain);
+  }
+}
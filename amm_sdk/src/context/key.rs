@@ -1,6 +1,6 @@
 use crate::note::{Accidental, PitchName};
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
@@ -38,7 +38,19 @@ const FIFTHS_E_FLAT_MINOR: i8 = -6;
 
 /// Represents the relative intervals between notes in a musical scale.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum KeyMode {
   /// Represents the following note intervals in semitones,
   /// starting from the root note of the corresponding key:
@@ -56,7 +68,19 @@ pub enum KeyMode {
 /// Represents the key signature of a musical piece, not taking
 /// into account its mode (i.e., major, minor, etc.).
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum KeySignature {
   /// The key of A is defined by a scale with a root note (tonic) of A.
   A,
@@ -100,7 +124,19 @@ pub enum KeySignature {
 /// Represents the key of a musical piece, including both its
 /// mode (i.e., major, minor, etc.) and its signature (defining root note).
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub struct Key {
   /// The mode of the key (i.e., major, minor, etc.).
   pub mode: KeyMode,
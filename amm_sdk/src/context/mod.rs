@@ -8,12 +8,32 @@ mod key;
 mod tempo;
 mod tempo_suggestion;
 mod time_signature;
+mod tuning;
 
 pub(crate) use id::generate_id;
+pub(crate) use tempo_suggestion::normalize_tempo_text;
 
 pub use clef::{Clef, ClefSymbol, ClefType};
-pub use dynamic::Dynamic;
+pub use dynamic::{Dynamic, DynamicEnvelope};
 pub use key::{Key, KeyMode, KeySignature};
 pub use tempo::Tempo;
 pub use tempo_suggestion::{TempoMarking, TempoSuggestion};
 pub use time_signature::{TimeSignature, TimeSignatureType};
+pub use tuning::Tuning;
+
+#[cfg(feature = "serde")]
+amm_internal::impl_serde!(
+  Clef,
+  ClefSymbol,
+  ClefType,
+  Dynamic,
+  Key,
+  KeyMode,
+  KeySignature,
+  Tempo,
+  TempoMarking,
+  TempoSuggestion,
+  TimeSignature,
+  TimeSignatureType,
+  Tuning
+);
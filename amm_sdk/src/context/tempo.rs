@@ -1,12 +1,14 @@
 use crate::note::{Duration, DurationType};
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
 /// Represents an explicit tempo marking in music notation.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy, Clone, Debug, Eq, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+)]
 pub struct Tempo {
   /// The base note which represents a single "beat" in the tempo.
   pub base_note: Duration,
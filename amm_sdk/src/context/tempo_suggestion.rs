@@ -1,11 +1,23 @@
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
 /// Represents a text-based tempo marking in music notation.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum TempoMarking {
   /// Very, very slowly.
   Larghissimo,
@@ -52,9 +64,112 @@ pub enum TempoMarking {
   Prestissimo,
 }
 
+const TEMPO_MARKING_WORDS: [(&str, TempoMarking); 44] = [
+  ("allegro vivace", TempoMarking::AllegroVivace),
+  ("allegro moderato", TempoMarking::AllegroModerato),
+  ("andante moderato", TempoMarking::AndanteModerato),
+  ("marcia moderato", TempoMarking::MarciaModerato),
+  ("alla marcia", TempoMarking::MarciaModerato),
+  ("sehr langsam", TempoMarking::Largo),
+  ("sehr schnell", TempoMarking::Presto),
+  ("tres lent", TempoMarking::Largo),
+  ("tres vif", TempoMarking::Presto),
+  ("tres vite", TempoMarking::Presto),
+  ("larghissimo", TempoMarking::Larghissimo),
+  ("grave", TempoMarking::Grave),
+  ("largo", TempoMarking::Largo),
+  ("lento", TempoMarking::Lento),
+  ("larghetto", TempoMarking::Larghetto),
+  ("adagio", TempoMarking::Adagio),
+  ("adagietto", TempoMarking::Adagietto),
+  ("andante", TempoMarking::Andante),
+  ("andantino", TempoMarking::Andantino),
+  ("moderato", TempoMarking::Moderato),
+  ("allegretto", TempoMarking::Allegretto),
+  ("allegro", TempoMarking::Allegro),
+  ("vivace", TempoMarking::Vivace),
+  ("vivo", TempoMarking::Vivace),
+  ("vivacissimo", TempoMarking::Vivacissimo),
+  ("allegrissimo", TempoMarking::Allegrissimo),
+  ("presto", TempoMarking::Presto),
+  ("prestissimo", TempoMarking::Prestissimo),
+  ("langsam", TempoMarking::Lento),
+  ("gehend", TempoMarking::Andante),
+  ("massig", TempoMarking::Moderato),
+  ("bewegt", TempoMarking::Allegretto),
+  ("schnell", TempoMarking::Allegro),
+  ("rasch", TempoMarking::Allegro),
+  ("lebhaft", TempoMarking::Vivace),
+  ("lent", TempoMarking::Lento),
+  ("lentement", TempoMarking::Lento),
+  ("allant", TempoMarking::Andantino),
+  ("modere", TempoMarking::Moderato),
+  ("moderement", TempoMarking::Moderato),
+  ("anime", TempoMarking::Allegro),
+  ("vite", TempoMarking::Allegro),
+  ("rapide", TempoMarking::Allegro),
+  ("vif", TempoMarking::Vivace),
+];
+
+/// Normalizes free-form tempo text for word matching by lowercasing it,
+/// removing diacritics and punctuation, and surrounding every word
+/// (including the first and last) with a single space.
+pub(crate) fn normalize_tempo_text(text: &str) -> String {
+  let mut normalized = String::from(" ");
+  for ch in text.chars().flat_map(char::to_lowercase) {
+    match ch {
+      'à' | 'á' | 'â' | 'ä' => normalized.push('a'),
+      'è' | 'é' | 'ê' | 'ë' => normalized.push('e'),
+      'ì' | 'í' | 'î' | 'ï' => normalized.push('i'),
+      'ò' | 'ó' | 'ô' | 'ö' => normalized.push('o'),
+      'ù' | 'ú' | 'û' | 'ü' => normalized.push('u'),
+      'ç' => normalized.push('c'),
+      'ß' => normalized.push_str("ss"),
+      ch if ch.is_alphanumeric() => normalized.push(ch),
+      _ => {
+        if !normalized.ends_with(' ') {
+          normalized.push(' ');
+        }
+      }
+    }
+  }
+  if !normalized.ends_with(' ') {
+    normalized.push(' ');
+  }
+  normalized
+}
+
+impl TempoMarking {
+  /// Creates a new tempo marking from a textual tempo indication written in
+  /// Italian, German, or French (e.g., `"Allegro ma non troppo"`, `"Langsam"`,
+  /// or `"Très vif"`).
+  ///
+  /// Returns `None` if the text does not contain a known tempo marking.
+  #[must_use]
+  pub fn from_text(text: &str) -> Option<Self> {
+    let text = normalize_tempo_text(text);
+    TEMPO_MARKING_WORDS
+      .iter()
+      .find(|(words, _)| text.contains(&format!(" {words} ")))
+      .map(|(_, marking)| *marking)
+  }
+}
+
 /// Represents a text-based tempo suggestion in music notation.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub struct TempoSuggestion {
   pub marking: TempoMarking,
 }
@@ -213,3 +328,25 @@ impl core::fmt::Display for TempoSuggestion {
     write!(f, "{}", self.marking)
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_from_text() {
+    assert_eq!(
+      TempoMarking::from_text("Allegro ma non troppo"),
+      Some(TempoMarking::Allegro)
+    );
+    assert_eq!(TempoMarking::from_text("Allegretto"), Some(TempoMarking::Allegretto));
+    assert_eq!(
+      TempoMarking::from_text("Allegro vivace"),
+      Some(TempoMarking::AllegroVivace)
+    );
+    assert_eq!(TempoMarking::from_text("Sehr langsam"), Some(TempoMarking::Largo));
+    assert_eq!(TempoMarking::from_text("Mäßig"), Some(TempoMarking::Moderato));
+    assert_eq!(TempoMarking::from_text("Très vif"), Some(TempoMarking::Presto));
+    assert_eq!(TempoMarking::from_text("dolce"), None);
+  }
+}
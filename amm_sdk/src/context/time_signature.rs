@@ -1,11 +1,23 @@
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
 /// Represents a type of time signature marking, whether explicit or implicit.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum TimeSignatureType {
   /// ![Common Time](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/time-symbol-common.png)
   ///
@@ -32,7 +44,19 @@ pub enum TimeSignatureType {
 /// Some `signature` types are implicit (e.g., `CommonTime` = `4/4`,
 /// `CutTime` = `2/2`), while others require an explicit `numerator` and `denominator`.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub struct TimeSignature {
   /// The type of time signature marking, whether explicit or implicit.
   pub signature: TimeSignatureType,
@@ -0,0 +1,264 @@
+use super::Key;
+use crate::modification::NoteModificationType;
+use crate::note::Note;
+use amm_internal::amm_prelude::*;
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
+
+const DEFAULT_NUM_FRETS: u8 = 24;
+const MAX_FRET_SPAN: u8 = 4;
+
+/// Represents the open-string tuning of a fretted instrument,
+/// along with the placement of a capo (if any).
+///
+/// Strings are numbered starting from 1 for the highest-pitched
+/// string, matching the convention used in tablature notation.
+/// All fret numbers are counted from the capo, such that fret 0
+/// always denotes an open (or capoed) string.
+#[derive(
+  Clone, Debug, Eq, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+)]
+pub struct Tuning {
+  /// The MIDI numbers of the open strings, ordered from string 1 upward.
+  pub strings: Vec<u8>,
+  /// The fret at which a capo is placed, or 0 if no capo is used.
+  pub capo: u8,
+  /// The total number of frets on the instrument's neck.
+  pub num_frets: u8,
+}
+
+impl Tuning {
+  /// Creates a new tuning from the given open-string MIDI numbers,
+  /// ordered from string 1 (highest-pitched) upward, and capo placement.
+  #[must_use]
+  pub fn new(strings: &[u8], capo: u8) -> Self {
+    Self {
+      strings: strings.to_vec(),
+      capo,
+      num_frets: DEFAULT_NUM_FRETS,
+    }
+  }
+
+  /// Creates a standard six-string guitar tuning (E-A-D-G-B-E).
+  #[must_use]
+  pub fn standard_guitar() -> Self {
+    Self::new(&[64, 59, 55, 50, 45, 40], 0)
+  }
+
+  /// Creates a standard four-string bass guitar tuning (E-A-D-G).
+  #[must_use]
+  pub fn standard_bass() -> Self {
+    Self::new(&[43, 38, 33, 28], 0)
+  }
+
+  /// Creates a standard re-entrant ukulele tuning (G-C-E-A).
+  #[must_use]
+  pub fn standard_ukulele() -> Self {
+    Self::new(&[69, 64, 60, 67], 0)
+  }
+
+  /// Returns the number of strings in the tuning.
+  #[must_use]
+  pub fn num_strings(&self) -> usize {
+    self.strings.len()
+  }
+
+  /// Returns the MIDI number sounded by the given string and fret, or `None`
+  /// if the position does not exist on the instrument.
+  #[must_use]
+  pub fn midi_number(&self, string: u8, fret: u8) -> Option<u8> {
+    if string == 0 || fret > self.num_frets.saturating_sub(self.capo) {
+      None
+    } else {
+      self
+        .strings
+        .get(usize::from(string - 1))
+        .and_then(|open| open.checked_add(self.capo))
+        .and_then(|open| open.checked_add(fret))
+    }
+  }
+
+  /// Returns all `(string, fret)` positions at which the given MIDI number
+  /// can be played, ordered by string number.
+  #[must_use]
+  #[allow(clippy::cast_possible_truncation)]
+  pub fn fret_positions(&self, midi_number: u8) -> Vec<(u8, u8)> {
+    let max_fret = self.num_frets.saturating_sub(self.capo);
+    self
+      .strings
+      .iter()
+      .enumerate()
+      .filter_map(|(idx, open)| {
+        let open = open.saturating_add(self.capo);
+        if midi_number >= open && midi_number - open <= max_fret {
+          Some((idx as u8 + 1, midi_number - open))
+        } else {
+          None
+        }
+      })
+      .collect()
+  }
+
+  /// Chooses a playable `(string, fret)` position for each of the given
+  /// simultaneously sounding MIDI numbers, returned in the same order.
+  ///
+  /// Each note is placed on a distinct string, and the fretted notes must
+  /// fit within a comfortable hand span. When multiple solutions exist,
+  /// the one closest to the optional `hand_position` (the lowest fretted
+  /// fret of the previous position) is preferred, favoring lower positions
+  /// on the neck otherwise.
+  ///
+  /// Returns `None` if the notes cannot be played together.
+  #[must_use]
+  pub fn assign_positions(&self, midi_numbers: &[u8], hand_position: Option<u8>) -> Option<Vec<(u8, u8)>> {
+    let candidates = midi_numbers
+      .iter()
+      .map(|midi_number| self.fret_positions(*midi_number))
+      .collect::<Vec<_>>();
+    if midi_numbers.len() > self.strings.len() || candidates.iter().any(Vec::is_empty) {
+      None
+    } else {
+      let mut best_solution = None;
+      Self::search_positions(
+        &candidates,
+        hand_position,
+        &mut Vec::with_capacity(candidates.len()),
+        &mut best_solution,
+      );
+      best_solution.map(|(_, positions)| positions)
+    }
+  }
+
+  fn search_positions(
+    candidates: &[Vec<(u8, u8)>],
+    hand_position: Option<u8>,
+    current: &mut Vec<(u8, u8)>,
+    best_solution: &mut Option<(u32, Vec<(u8, u8)>)>,
+  ) {
+    if current.len() == candidates.len() {
+      if let Some(cost) = Self::position_cost(current, hand_position) {
+        if best_solution.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+          *best_solution = Some((cost, current.clone()));
+        }
+      }
+    } else {
+      for position in &candidates[current.len()] {
+        if current.iter().all(|(string, _)| *string != position.0) {
+          current.push(*position);
+          Self::search_positions(candidates, hand_position, current, best_solution);
+          current.pop();
+        }
+      }
+    }
+  }
+
+  fn position_cost(positions: &[(u8, u8)], hand_position: Option<u8>) -> Option<u32> {
+    let fretted = positions.iter().filter(|(_, fret)| *fret > 0).map(|(_, fret)| *fret);
+    match (fretted.clone().min(), fretted.max()) {
+      (Some(lowest), Some(highest)) if highest - lowest <= MAX_FRET_SPAN => Some(
+        2 * u32::from(highest - lowest)
+          + hand_position.map_or(u32::from(lowest), |hand_position| {
+            u32::from(lowest.abs_diff(hand_position))
+          }),
+      ),
+      (Some(_), Some(_)) => None,
+      _ => Some(0),
+    }
+  }
+
+  /// Assigns fret positions to the given simultaneously sounding notes,
+  /// updating the current `hand_position` accordingly.
+  ///
+  /// Notes which already specify a fret position are left untouched, as
+  /// is the entire group if any of its notes already specify one.
+  pub(crate) fn assign_note_positions(&self, notes: Vec<&mut Note>, key: Key, hand_position: &mut Option<u8>) {
+    let existing_frets = notes
+      .iter()
+      .filter_map(|note| {
+        note
+          .iter_modifications()
+          .find_map(|modification| match modification.r#type {
+            NoteModificationType::FretPosition { fret, .. } => Some(fret),
+            _ => None,
+          })
+      })
+      .collect::<Vec<_>>();
+    if existing_frets.is_empty() {
+      let mut notes = notes.into_iter().filter(|note| !note.is_rest()).collect::<Vec<_>>();
+      let midi_numbers = notes.iter().map(|note| note.midi_number(Some(key))).collect::<Vec<_>>();
+      if let Some(positions) = self.assign_positions(&midi_numbers, *hand_position) {
+        for (note, (string, fret)) in notes.iter_mut().zip(positions.iter()) {
+          note.add_modification(NoteModificationType::FretPosition {
+            string: *string,
+            fret: *fret,
+          });
+        }
+        if let Some(lowest) = positions.iter().map(|(_, fret)| *fret).filter(|fret| *fret > 0).min() {
+          *hand_position = Some(lowest);
+        }
+      }
+    } else if let Some(lowest) = existing_frets.into_iter().filter(|fret| *fret > 0).min() {
+      *hand_position = Some(lowest);
+    }
+  }
+}
+
+impl Default for Tuning {
+  fn default() -> Self {
+    Self {
+      strings: Vec::new(),
+      capo: 0,
+      num_frets: DEFAULT_NUM_FRETS,
+    }
+  }
+}
+
+#[cfg(feature = "print")]
+impl core::fmt::Display for Tuning {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    let strings = self
+      .strings
+      .iter()
+      .map(ToString::to_string)
+      .collect::<Vec<String>>()
+      .join("-");
+    write!(
+      f,
+      "Tuning: [{strings}]{}",
+      if self.capo > 0 {
+        format!(" with Capo at Fret {}", self.capo)
+      } else {
+        String::new()
+      }
+    )
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_fret_positions() {
+    let tuning = Tuning::standard_guitar();
+    assert_eq!(
+      tuning.fret_positions(64),
+      vec![(1, 0), (2, 5), (3, 9), (4, 14), (5, 19), (6, 24)]
+    );
+    assert_eq!(tuning.fret_positions(39), vec![]);
+    assert_eq!(tuning.midi_number(6, 3), Some(43));
+    let capoed = Tuning::new(&tuning.strings, 2);
+    assert_eq!(capoed.fret_positions(42).first(), Some(&(6, 0)));
+    assert_eq!(capoed.fret_positions(41), vec![]);
+  }
+
+  #[test]
+  fn test_assign_positions() {
+    let tuning = Tuning::standard_guitar();
+    assert_eq!(
+      tuning.assign_positions(&[48, 52, 55, 60, 64], None),
+      Some(vec![(5, 3), (4, 2), (3, 0), (2, 1), (1, 0)])
+    );
+    assert_eq!(tuning.assign_positions(&[69], Some(12)), Some(vec![(2, 10)]));
+    assert_eq!(tuning.assign_positions(&[40, 41], None), None);
+  }
+}
@@ -1,10 +1,23 @@
 use super::note::NoteModificationType;
 use crate::context::{generate_id, Dynamic};
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize, ModOrder};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize, ModOrder};
 
 /// Represents a type of modification to a chord.
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ModOrder, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone,
+  Copy,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  ModOrder,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum ChordModificationType {
   /// ![Accent](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/accent.png)
   #[default]
@@ -68,7 +81,7 @@ pub enum ChordModificationType {
 }
 
 /// Represents a modification to a chord.
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct ChordModification {
   /// The unique identifier for this modification.
   id: usize,
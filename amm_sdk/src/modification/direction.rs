@@ -1,12 +1,66 @@
 use crate::context::{generate_id, Clef, Dynamic, Key, TimeSignature};
 use crate::temporal::Timeslice;
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize, ModOrder};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize, ModOrder};
+
+/// Represents the placement of a textual direction relative to its staff.
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
+pub enum TextPlacement {
+  /// The text is placed above the staff.
+  #[default]
+  Above,
+  /// The text is placed below the staff.
+  Below,
+}
+
+/// Represents optional font and placement hints for a textual direction.
+///
+/// These hints describe how the text was originally engraved and carry
+/// no musical meaning of their own.
+#[derive(
+  Clone, Debug, Default, Eq, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+)]
+pub struct TextStyle {
+  /// The preferred font family, if any.
+  pub font_family: Option<String>,
+  /// The preferred font size in points, if any.
+  pub font_size: Option<u8>,
+  /// Whether the text is rendered in a bold weight.
+  pub bold: bool,
+  /// Whether the text is rendered in an italic style.
+  pub italic: bool,
+  /// The placement of the text relative to its staff, if specified.
+  pub placement: Option<TextPlacement>,
+}
 
 /// Represents a type of contextual direction which changes the global
 /// state of the music being played starting at the point that the
 /// direction is encountered.
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, ModOrder, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  ModOrder,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum DirectionType {
   /// ![Accordion Registration High](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/accordion-high.png)
   ///
@@ -23,10 +77,16 @@ pub enum DirectionType {
   ClefChange { clef: Clef },
   /// Represents a change in dynamic level for the current staff.
   Dynamic { dynamic: Dynamic },
+  /// Represents expressive performance text, such as *dolce* or *pizz.*
+  Expression { text: String, style: TextStyle },
   /// Represents a change in key for the current staff.
   KeyChange { key: Key },
+  /// ![Rehearsal Mark](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/rehearsal.png)
+  Rehearsal { mark: String, style: TextStyle },
   /// ![String Mute](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/string-mute.png)
   StringMute { on: bool },
+  /// Represents free-form text with no specific musical meaning, such as *Solo*.
+  Text { text: String, style: TextStyle },
   /// Represents a change in time signature for the current staff.
   TimeSignatureChange { time_signature: TimeSignature },
 }
@@ -34,7 +94,7 @@ pub enum DirectionType {
 /// Represents a contextual direction which changes the global state of
 /// the music being played starting at the point that the direction is
 /// encountered.
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct Direction {
   /// The unique identifier for this direction.
   id: usize,
@@ -71,7 +131,7 @@ impl Clone for Direction {
   fn clone(&self) -> Self {
     Self {
       id: generate_id(),
-      r#type: self.r#type,
+      r#type: self.r#type.clone(),
     }
   }
 }
@@ -113,8 +173,11 @@ impl core::fmt::Display for DirectionType {
       Self::Caesura => write!(f, "Caesura"),
       Self::ClefChange { clef } => write!(f, "Clef: {clef}"),
       Self::Dynamic { dynamic } => write!(f, "Dynamic: {dynamic}"),
+      Self::Expression { text, .. } => write!(f, "Expression: {text}"),
       Self::KeyChange { key } => write!(f, "Key: {key}"),
+      Self::Rehearsal { mark, .. } => write!(f, "Rehearsal Mark: {mark}"),
       Self::StringMute { on } => write!(f, "String Mute: {}", if *on { "on" } else { "off" }),
+      Self::Text { text, .. } => write!(f, "Text: {text}"),
       Self::TimeSignatureChange { time_signature } => write!(f, "Time Signature: {time_signature}"),
     }
   }
@@ -8,7 +8,26 @@ mod phrase;
 mod section;
 
 pub use chord::{ChordModification, ChordModificationType};
-pub use direction::{Direction, DirectionType};
-pub use note::{HandbellTechnique, NoteModification, NoteModificationType};
+pub use direction::{Direction, DirectionType, TextPlacement, TextStyle};
+pub use note::{HandbellTechnique, NoteModification, NoteModificationType, PluckingFinger};
 pub use phrase::{PedalType, PhraseModification, PhraseModificationType};
 pub use section::{SectionModification, SectionModificationType};
+
+#[cfg(feature = "serde")]
+amm_internal::impl_serde!(
+  ChordModification,
+  ChordModificationType,
+  Direction,
+  DirectionType,
+  HandbellTechnique,
+  NoteModification,
+  NoteModificationType,
+  PedalType,
+  PhraseModification,
+  PhraseModificationType,
+  PluckingFinger,
+  SectionModification,
+  SectionModificationType,
+  TextPlacement,
+  TextStyle
+);
@@ -1,10 +1,22 @@
 use super::chord::ChordModificationType;
 use crate::context::{generate_id, Dynamic};
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize, ModOrder};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize, ModOrder};
 
 /// Represents a technique used in handbell playing.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, ModOrder, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Eq,
+  PartialEq,
+  ModOrder,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum HandbellTechnique {
   /// <span class="smufl">&#xE81F;</span>
   Belltree,
@@ -32,12 +44,61 @@ pub enum HandbellTechnique {
   Swing,
 }
 
+/// Represents a finger of the plucking hand used in guitar notation.
+#[derive(
+  Copy,
+  Clone,
+  Debug,
+  Eq,
+  PartialEq,
+  ModOrder,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
+pub enum PluckingFinger {
+  /// Pulgar, notated as *p*.
+  Thumb,
+  /// Índice, notated as *i*.
+  Index,
+  /// Medio, notated as *m*.
+  Middle,
+  /// Anular, notated as *a*.
+  Ring,
+  /// Chiquito, notated as *c*.
+  Little,
+}
+
 /// Represents a type of modification to a note.
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ModOrder, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone,
+  Copy,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  ModOrder,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum NoteModificationType {
   /// ![Accent](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/accent.png)
   #[default]
   Accent,
+  /// ![Bend](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/bend-shape-curved.png)
+  ///
+  /// Represents a string bend by the given number of semitones, optionally
+  /// pre-bent before the note is sounded and/or released afterward.
+  Bend {
+    semitones: i8,
+    pre_bend: bool,
+    release: bool,
+  },
   /// ![Brass Bend](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/brass-bend.png)
   BrassBend,
   /// ![Detached Legato](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/detached-legato.png)
@@ -54,10 +115,24 @@ pub enum NoteModificationType {
   Falloff,
   /// ![Fermata](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/fermata.png)
   Fermata,
+  /// Represents the finger used to play a note, along with an optional
+  /// substitution finger which replaces it while the note is held. Either
+  /// finger may be absent when the source only marks the other.
+  ///
+  /// Fingers are numbered from 1, where 1 denotes the thumb on keyboard
+  /// instruments and the index finger on string instruments.
+  Fingering {
+    finger: Option<u8>,
+    substitution: Option<u8>,
+  },
   /// ![Fingernails](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/fingernails.png)
   Fingernails,
   /// ![Flip](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/flip.png)
   Flip,
+  /// Represents the string and fret on which a note is played on a fretted
+  /// instrument, where string 1 is the highest-pitched string and the fret
+  /// is counted from the capo (if any).
+  FretPosition { string: u8, fret: u8 },
   /// ![Glissando Up](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/glissando.png)
   Glissando { from_current: bool, going_up: bool },
   /// ![Golpe](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/golpe.png)
@@ -70,12 +145,18 @@ pub enum NoteModificationType {
   HalfMuted,
   /// Represents a [`HandbellTechnique`] used in handbell playing.
   Handbell { technique: HandbellTechnique },
+  /// Represents a natural or artificial string harmonic.
+  Harmonic { natural: bool },
   /// Open: <span class="smufl">&#xE5EB;</span>
   ///
   /// Half: <span class="smufl">&#xE5E9;</span>
   ///
   /// Closed: <span class="smufl">&#xE5E8;</span>
   HarmonMute { open: bool, half: bool },
+  /// ![Hammer-On](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/hammer-on.png)
+  ///
+  /// Represents a hammer-on from this note to the next note on the same string.
+  HammerOn,
   /// ![Haydn](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/haydn.png)
   Haydn,
   /// ![Heel](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/heel.png)
@@ -98,8 +179,16 @@ pub enum NoteModificationType {
   Pizzicato,
   /// ![Plop](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/plop.png)
   Plop,
+  /// ![Pluck](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/pluck.png)
+  ///
+  /// Represents the [`PluckingFinger`] used to pluck a note.
+  Pluck { finger: PluckingFinger },
   /// ![Portamento Up](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/slide.png)
   Portamento { from_current: bool, going_up: bool },
+  /// ![Pull-Off](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/pull-off.png)
+  ///
+  /// Represents a pull-off from this note to the next note on the same string.
+  PullOff,
   /// ![Schleifer](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/schleifer.png)
   Schleifer,
   /// ![Scoop](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/scoop.png)
@@ -122,6 +211,9 @@ pub enum NoteModificationType {
   Stopped,
   /// ![Stress](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/stress.png)
   Stress,
+  /// Represents the string on which a note should be played, where
+  /// string 1 is the highest-pitched string of the instrument.
+  StringNumber { string: u8 },
   /// ![Tap](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/tap.png)
   Tap,
   /// ![Tenuto](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/tenuto.png)
@@ -151,7 +243,7 @@ pub enum NoteModificationType {
 }
 
 /// Represents a modification to a note.
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct NoteModification {
   /// The unique identifier for this modification.
   id: usize,
@@ -272,11 +364,38 @@ impl core::fmt::Display for HandbellTechnique {
   }
 }
 
+#[cfg(feature = "print")]
+impl core::fmt::Display for PluckingFinger {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    write!(
+      f,
+      "{}",
+      match self {
+        Self::Thumb => "p",
+        Self::Index => "i",
+        Self::Middle => "m",
+        Self::Ring => "a",
+        Self::Little => "c",
+      }
+    )
+  }
+}
+
 #[cfg(feature = "print")]
 impl core::fmt::Display for NoteModificationType {
   fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
     match self {
       Self::Accent => write!(f, "Accent"),
+      Self::Bend {
+        semitones,
+        pre_bend,
+        release,
+      } => write!(
+        f,
+        "Bend: {semitones} semitones{}{}",
+        if *pre_bend { " Pre-Bent" } else { "" },
+        if *release { " Released" } else { "" },
+      ),
       Self::BrassBend => write!(f, "Brass Bend"),
       Self::DetachedLegato => write!(f, "Detached Legato"),
       Self::Doit => write!(f, "Doit"),
@@ -285,8 +404,15 @@ impl core::fmt::Display for NoteModificationType {
       Self::Dynamic { dynamic } => write!(f, "Dynamic: {dynamic}"),
       Self::Falloff => write!(f, "Falloff"),
       Self::Fermata => write!(f, "Fermata"),
+      Self::Fingering { finger, substitution } => match (finger, substitution) {
+        (Some(finger), Some(substitution)) => write!(f, "Fingering: {finger}-{substitution}"),
+        (Some(finger), None) => write!(f, "Fingering: {finger}"),
+        (None, Some(substitution)) => write!(f, "Fingering: -{substitution}"),
+        (None, None) => write!(f, "Fingering"),
+      },
       Self::Fingernails => write!(f, "Fingernails"),
       Self::Flip => write!(f, "Flip"),
+      Self::FretPosition { string, fret } => write!(f, "String {string}, Fret {fret}"),
       Self::Glissando { from_current, going_up } => write!(
         f,
         "Glissando: {} {}",
@@ -301,12 +427,14 @@ impl core::fmt::Display for NoteModificationType {
       ),
       Self::HalfMuted => write!(f, "Half Muted"),
       Self::Handbell { technique } => write!(f, "Handbell: {technique}"),
+      Self::Harmonic { natural } => write!(f, "{} Harmonic", if *natural { "Natural" } else { "Artificial" }),
       Self::HarmonMute { open, half } => write!(
         f,
         "Harmon Mute: {}{}",
         if *half { "Half-" } else { "Fully " },
         if *open { "Open" } else { "Closed" },
       ),
+      Self::HammerOn => write!(f, "Hammer-On"),
       Self::Haydn => write!(f, "Haydn"),
       Self::Heel => write!(f, "Heel"),
       Self::Hole { open, half } => write!(
@@ -320,12 +448,14 @@ impl core::fmt::Display for NoteModificationType {
       Self::Open => write!(f, "Open"),
       Self::Pizzicato => write!(f, "Pizzicato"),
       Self::Plop => write!(f, "Plop"),
+      Self::Pluck { finger } => write!(f, "Pluck: {finger}"),
       Self::Portamento { from_current, going_up } => write!(
         f,
         "Portamento: {} {}",
         if *going_up { "Going Up" } else { "Going Down" },
         if *from_current { "From Note" } else { "To Note" },
       ),
+      Self::PullOff => write!(f, "Pull-Off"),
       Self::Schleifer => write!(f, "Schleifer"),
       Self::Scoop => write!(f, "Scoop"),
       Self::Sforzando => write!(f, "Sforzando"),
@@ -337,6 +467,7 @@ impl core::fmt::Display for NoteModificationType {
       Self::Staccatissimo => write!(f, "Staccatissimo"),
       Self::Stopped => write!(f, "Stopped"),
       Self::Stress => write!(f, "Stress"),
+      Self::StringNumber { string } => write!(f, "String {string}"),
       Self::Tap => write!(f, "Tap"),
       Self::Tenuto => write!(f, "Tenuto"),
       Self::ThumbPosition => write!(f, "Thumb Position"),
@@ -1,9 +1,22 @@
 use crate::context::{generate_id, Dynamic};
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize, ModOrder};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize, ModOrder};
 
 /// Represents a type of pedal used in piano playing.
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ModOrder, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone,
+  Copy,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  ModOrder,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum PedalType {
   /// ![Sustain](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/pedal.png)
   #[default]
@@ -15,7 +28,20 @@ pub enum PedalType {
 }
 
 /// Represents a type of modification to a phrase.
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ModOrder, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone,
+  Copy,
+  Debug,
+  Default,
+  Eq,
+  PartialEq,
+  ModOrder,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum PhraseModificationType {
   /// ![Crescendo](https://hedgetechllc.github.io/amm-sdk/amm_sdk/images/crescendo.png)
   ///
@@ -59,7 +85,7 @@ pub enum PhraseModificationType {
 }
 
 /// Represents a modification to a phrase.
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct PhraseModification {
   /// The unique identifier for this modification.
   id: usize,
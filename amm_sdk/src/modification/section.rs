@@ -1,14 +1,84 @@
-use crate::context::{generate_id, Tempo, TempoSuggestion};
+use crate::context::{generate_id, normalize_tempo_text, Tempo, TempoMarking, TempoSuggestion};
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize, ModOrder};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize, ModOrder};
+
+const TEMPO_RESET_WORDS: [(&str, SectionModificationType); 12] = [
+  ("tempo i", SectionModificationType::TempoPrimo),
+  ("tempo 1", SectionModificationType::TempoPrimo),
+  ("tempo primo", SectionModificationType::TempoPrimo),
+  ("tempo 1mo", SectionModificationType::TempoPrimo),
+  ("1er mouvement", SectionModificationType::TempoPrimo),
+  ("premier mouvement", SectionModificationType::TempoPrimo),
+  ("a tempo", SectionModificationType::ATempo),
+  ("im tempo", SectionModificationType::ATempo),
+  ("im zeitmass", SectionModificationType::ATempo),
+  ("au mouvement", SectionModificationType::ATempo),
+  ("come prima", SectionModificationType::ATempo),
+  ("wie vorher", SectionModificationType::ATempo),
+];
+
+const NEGATING_WORDS: [&str; 4] = ["senza", "non", "sans", "ohne"];
+
+const TEMPO_MODIFICATION_WORDS: [(&str, SectionModificationType); 35] = [
+  ("meno mosso", SectionModificationType::Ritenuto),
+  ("meno moto", SectionModificationType::Ritenuto),
+  ("moins vite", SectionModificationType::Ritenuto),
+  ("piu mosso", SectionModificationType::Accelerando),
+  ("piu moto", SectionModificationType::Accelerando),
+  ("plus vite", SectionModificationType::Accelerando),
+  ("en pressant", SectionModificationType::Accelerando),
+  ("en retenant", SectionModificationType::Rallentando),
+  ("en serrant", SectionModificationType::Stringendo),
+  ("accel", SectionModificationType::Accelerando),
+  ("accelerando", SectionModificationType::Accelerando),
+  ("pressez", SectionModificationType::Accelerando),
+  ("schneller", SectionModificationType::Accelerando),
+  ("eilend", SectionModificationType::Accelerando),
+  ("rall", SectionModificationType::Rallentando),
+  ("rallentando", SectionModificationType::Rallentando),
+  ("allarg", SectionModificationType::Rallentando),
+  ("allargando", SectionModificationType::Rallentando),
+  ("slentando", SectionModificationType::Rallentando),
+  ("cedez", SectionModificationType::Rallentando),
+  ("zuruckhalten", SectionModificationType::Rallentando),
+  ("zuruckhaltend", SectionModificationType::Rallentando),
+  ("rit", SectionModificationType::Ritardando),
+  ("ritard", SectionModificationType::Ritardando),
+  ("ritardando", SectionModificationType::Ritardando),
+  ("langsamer", SectionModificationType::Ritardando),
+  ("riten", SectionModificationType::Ritenuto),
+  ("ritenuto", SectionModificationType::Ritenuto),
+  ("retenu", SectionModificationType::Ritenuto),
+  ("zogernd", SectionModificationType::Ritenuto),
+  ("stringendo", SectionModificationType::Stringendo),
+  ("serrez", SectionModificationType::Stringendo),
+  ("drangend", SectionModificationType::Stringendo),
+  ("animando", SectionModificationType::Stringendo),
+  ("incalzando", SectionModificationType::Stringendo),
+];
 
 /// Represents a type of modification to a section.
-#[derive(Clone, Eq, Debug, Default, PartialEq, ModOrder, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone,
+  Eq,
+  Debug,
+  Default,
+  PartialEq,
+  ModOrder,
+  BinaryDeserialize,
+  BinarySerialize,
+  JsonDeserialize,
+  JsonSchema,
+  JsonSerialize,
+)]
 pub enum SectionModificationType {
   /// Represents a section with a quick tempo acceleration over
   /// a few notes or measures.
   #[default]
   Accelerando,
+  /// Represents a return to the prevailing tempo after a temporary
+  /// alteration such as a ritardando.
+  ATempo,
   /// Represents a section that should only be played during
   /// certain iterations.
   OnlyPlay { iterations: Vec<u8> },
@@ -36,10 +106,66 @@ pub enum SectionModificationType {
   TempoExplicit { tempo: Tempo },
   /// Represents a section with a suggested tempo change.
   TempoImplicit { tempo: TempoSuggestion },
+  /// Represents a return to the tempo at the start of the piece.
+  TempoPrimo,
+}
+
+impl SectionModificationType {
+  /// Creates a new tempo-related section modification from a textual
+  /// indication written in Italian, German, or French (e.g., `"rall."`,
+  /// `"poco più mosso"`, `"Langsam"`, or `"en pressant"`).
+  ///
+  /// Returns to earlier tempos (e.g., `"a tempo"` or `"Tempo I"`) are
+  /// recognized first, followed by gradual or immediate tempo alterations,
+  /// and finally general tempo markings which result in an implicit tempo
+  /// change. Alterations which are negated, as in `"senza rit."`, are ignored.
+  ///
+  /// Returns `None` if the text does not contain a known tempo indication.
+  #[must_use]
+  pub fn from_text(text: &str) -> Option<Self> {
+    let normalized = normalize_tempo_text(text);
+    let is_negated = |position: usize| {
+      let preceding = normalized[..position].trim_end();
+      NEGATING_WORDS.iter().any(|word| {
+        preceding
+          .strip_suffix(word)
+          .is_some_and(|preceding| preceding.is_empty() || preceding.ends_with(' '))
+      })
+    };
+    let find = |words: &[(&str, Self)]| {
+      words
+        .iter()
+        .find(|(words, _)| {
+          normalized
+            .match_indices(&format!(" {words} "))
+            .any(|(position, _)| !is_negated(position + 1))
+        })
+        .map(|(_, modification)| modification.clone())
+    };
+
+    // Abbreviated stringendo is only recognized with its period to avoid matching "string"
+    let is_abbreviated_stringendo = || {
+      let tokens = text.split_whitespace().map(str::to_lowercase).collect::<Vec<_>>();
+      tokens.iter().enumerate().any(|(idx, token)| {
+        token.trim_start_matches(|ch: char| !ch.is_alphanumeric()) == "string."
+          && !idx
+            .checked_sub(1)
+            .is_some_and(|previous| NEGATING_WORDS.contains(&tokens[previous].as_str()))
+      })
+    };
+    find(&TEMPO_RESET_WORDS)
+      .or_else(|| find(&TEMPO_MODIFICATION_WORDS))
+      .or_else(|| is_abbreviated_stringendo().then_some(Self::Stringendo))
+      .or_else(|| {
+        TempoMarking::from_text(text).map(|marking| Self::TempoImplicit {
+          tempo: TempoSuggestion::new(marking),
+        })
+      })
+  }
 }
 
 /// Represents a modification to a section.
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct SectionModification {
   /// The unique identifier for this modification.
   id: usize,
@@ -96,6 +222,7 @@ impl core::fmt::Display for SectionModificationType {
   fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
     match self {
       Self::Accelerando => write!(f, "Accelerando"),
+      Self::ATempo => write!(f, "A Tempo"),
       Self::OnlyPlay { iterations } => {
         let iterations = iterations
           .iter()
@@ -111,6 +238,7 @@ impl core::fmt::Display for SectionModificationType {
       Self::Stringendo => write!(f, "Stringendo"),
       Self::TempoExplicit { tempo } => write!(f, "Explicit Tempo: {tempo}"),
       Self::TempoImplicit { tempo } => write!(f, "Implicit Tempo: {tempo}"),
+      Self::TempoPrimo => write!(f, "Tempo Primo"),
     }
   }
 }
@@ -121,3 +249,55 @@ impl core::fmt::Display for SectionModification {
     write!(f, "{}", self.r#type)
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_from_text() {
+    assert_eq!(
+      SectionModificationType::from_text("rall."),
+      Some(SectionModificationType::Rallentando)
+    );
+    assert_eq!(
+      SectionModificationType::from_text("molto rit."),
+      Some(SectionModificationType::Ritardando)
+    );
+    assert_eq!(
+      SectionModificationType::from_text("poco più mosso"),
+      Some(SectionModificationType::Accelerando)
+    );
+    assert_eq!(
+      SectionModificationType::from_text("Zurückhaltend"),
+      Some(SectionModificationType::Rallentando)
+    );
+    assert_eq!(
+      SectionModificationType::from_text("Langsam"),
+      Some(SectionModificationType::TempoImplicit {
+        tempo: TempoSuggestion::new(TempoMarking::Lento)
+      })
+    );
+    assert_eq!(
+      SectionModificationType::from_text("a tempo"),
+      Some(SectionModificationType::ATempo)
+    );
+    assert_eq!(
+      SectionModificationType::from_text("Tempo I"),
+      Some(SectionModificationType::TempoPrimo)
+    );
+    assert_eq!(
+      SectionModificationType::from_text("string."),
+      Some(SectionModificationType::Stringendo)
+    );
+    assert_eq!(
+      SectionModificationType::from_text("rit. e dim."),
+      Some(SectionModificationType::Ritardando)
+    );
+    assert_eq!(SectionModificationType::from_text("Solo string"), None);
+    assert_eq!(SectionModificationType::from_text("on the G string"), None);
+    assert_eq!(SectionModificationType::from_text("senza rit."), None);
+    assert_eq!(SectionModificationType::from_text("non rit."), None);
+    assert_eq!(SectionModificationType::from_text("pizz."), None);
+  }
+}
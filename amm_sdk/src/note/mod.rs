@@ -9,3 +9,6 @@ pub use accidental::Accidental;
 pub use duration::{Duration, DurationType};
 pub use note::Note;
 pub use pitch::{Pitch, PitchName};
+
+#[cfg(feature = "serde")]
+amm_internal::impl_serde!(Accidental, Duration, DurationType, Note, Pitch, PitchName);
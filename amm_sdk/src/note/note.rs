@@ -3,7 +3,7 @@ use crate::context::{generate_id, Key, Tempo};
 use crate::modification::{NoteModification, NoteModificationType};
 use crate::temporal::Timeslice;
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
@@ -11,7 +11,7 @@ const A4_FREQUENCY_HZ: f32 = 440.0;
 const MIDI_NUMBER_A4: i8 = 69;
 
 /// Represents a note in a musical composition.
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct Note {
   /// The unique identifier of the note.
   pub id: usize,
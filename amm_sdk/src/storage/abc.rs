@@ -0,0 +1,2645 @@
+use super::musicxml::MusicXmlConverter;
+use super::util::{gcd, tempo_marking_text, Fraction};
+use super::xml::{add_notation, xml_element, xml_text_element, XmlElementExt};
+use super::{AmmError, Load, Store};
+use crate::context::{ClefType, Dynamic, Key, KeyMode, Tempo, TimeSignature, TimeSignatureType};
+use crate::modification::{
+  ChordModificationType, Direction, DirectionType, NoteModificationType, PhraseModificationType,
+  SectionModificationType, TextPlacement,
+};
+use crate::note::{Accidental, Note, PitchName};
+use crate::structure::{
+  Chord, ChordContent, MultiVoice, MultiVoiceContent, PartContent, Phrase, PhraseContent, Section, SectionContent,
+  Staff, StaffContent,
+};
+use crate::Composition;
+use alloc::{
+  collections::BTreeMap,
+  string::{String, ToString},
+  vec::Vec,
+};
+use musicxml::elements::ScorePartwise;
+use musicxml_internal::{ElementDeserializer, XmlElement};
+
+const VOICES_PER_STAFF: usize = 4;
+const MAX_MEASURE_RESTS: u64 = 999;
+const MAX_WHOLE_DIVISIONS: u64 = 15_360;
+const MEASURES_PER_LINE: usize = 4;
+const BODY_FIELDS: &str = "IKLMmNPQRrsTUVWwX+";
+const DECORATION_SYMBOLS: [char; 11] = ['.', '~', 'H', 'L', 'M', 'O', 'P', 'S', 'T', 'u', 'v'];
+const DYNAMICS: [&str; 16] = [
+  "pppp", "ppp", "pp", "p", "mp", "mf", "f", "ff", "fff", "ffff", "sfz", "sf", "sfp", "fp", "fz", "rfz",
+];
+const MAJOR_TONICS: [&str; 15] = [
+  "Cb", "Gb", "Db", "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#",
+];
+const MINOR_TONICS: [&str; 15] = [
+  "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#", "G#", "D#", "A#",
+];
+const METADATA_FIELDS: [(char, &str); 11] = [
+  ('A', "area"),
+  ('B', "book"),
+  ('D', "discography"),
+  ('F', "file_url"),
+  ('G', "group"),
+  ('H', "history"),
+  ('N', "notes"),
+  ('O', "origin"),
+  ('R', "rhythm"),
+  ('S', "source"),
+  ('Z', "transcription"),
+];
+const CREATOR_NOTES: [(&str, &str); 3] = [
+  ("lyricist", "Lyrics by "),
+  ("arranger", "Arranged by "),
+  ("publisher", "Published by "),
+];
+
+struct AbcNote {
+  pitch: Option<(char, i32, Option<&'static str>)>,
+  length: Fraction,
+  tie: bool,
+}
+
+enum AbcDecoration {
+  Notation(Option<&'static str>, XmlElement),
+  Direction(XmlElement),
+}
+
+enum AbcItem {
+  Element(XmlElement),
+  Timed {
+    element: XmlElement,
+    written: Fraction,
+    actual: Fraction,
+  },
+  Backup(Fraction),
+}
+
+#[derive(Default)]
+struct AbcMeasure {
+  items: Vec<AbcItem>,
+  forward_repeat: bool,
+  backward_repeat: bool,
+  ending_start: Option<String>,
+  ending_stop: Option<(String, bool)>,
+  bar_style: Option<&'static str>,
+}
+
+impl AbcMeasure {
+  fn has_content(&self) -> bool {
+    self.items.iter().any(|item| matches!(item, AbcItem::Timed { .. }))
+  }
+}
+
+struct AbcContext {
+  key: XmlElement,
+  time: XmlElement,
+  clef: XmlElement,
+  unit: Fraction,
+  measure_length: Option<Fraction>,
+  compound: bool,
+}
+
+impl AbcContext {
+  fn copy(&self) -> Self {
+    Self {
+      key: self.key.deep_copy(),
+      time: self.time.deep_copy(),
+      clef: self.clef.deep_copy(),
+      unit: self.unit,
+      measure_length: self.measure_length,
+      compound: self.compound,
+    }
+  }
+}
+
+impl Default for AbcContext {
+  fn default() -> Self {
+    Self {
+      key: AbcConverter::key_element(0, false),
+      time: xml_element("time", &[], vec![xml_element("senza-misura", &[], Vec::new())]),
+      clef: xml_element(
+        "clef",
+        &[],
+        vec![xml_text_element("sign", "G"), xml_text_element("line", 2)],
+      ),
+      unit: Fraction::new(1, 8),
+      measure_length: None,
+      compound: false,
+    }
+  }
+}
+
+struct AbcVoice {
+  name: Option<String>,
+  context: AbcContext,
+  initial: Option<AbcContext>,
+  measures: Vec<AbcMeasure>,
+  current: AbcMeasure,
+  position: Fraction,
+  extent: Fraction,
+  overlay: usize,
+  last_event: Vec<usize>,
+  tied_pitches: Vec<(char, i32)>,
+  notations: Vec<(Option<&'static str>, XmlElement)>,
+  pending_slurs: usize,
+  open_slurs: usize,
+  tuplet: Option<(u64, u64, u64, bool)>,
+  broken_rhythm: Option<Fraction>,
+  grace: Option<bool>,
+  open_ending: Option<String>,
+}
+
+impl AbcVoice {
+  fn new(context: AbcContext) -> Self {
+    Self {
+      name: None,
+      context,
+      initial: None,
+      measures: Vec::new(),
+      current: AbcMeasure::default(),
+      position: Fraction::ZERO,
+      extent: Fraction::ZERO,
+      overlay: 1,
+      last_event: Vec::new(),
+      tied_pitches: Vec::new(),
+      notations: Vec::new(),
+      pending_slurs: 0,
+      open_slurs: 0,
+      tuplet: None,
+      broken_rhythm: None,
+      grace: None,
+      open_ending: None,
+    }
+  }
+
+  fn change_context(&mut self, key: Option<XmlElement>, time: Option<XmlElement>, clef: Option<XmlElement>) {
+    // Changes before the first note of the voice simply redefine its initial context
+    let mut attributes = Vec::new();
+    if let Some(key) = key {
+      self.context.key = key.deep_copy();
+      attributes.push(key);
+    }
+    if let Some(time) = time {
+      self.context.time = time.deep_copy();
+      attributes.push(time);
+    }
+    if let Some(clef) = clef {
+      self.context.clef = clef.deep_copy();
+      attributes.push(clef);
+    }
+    if self.initial.is_some() && !attributes.is_empty() {
+      self
+        .current
+        .items
+        .push(AbcItem::Element(xml_element("attributes", &[], attributes)));
+    }
+  }
+
+  fn push_direction(&mut self, direction: XmlElement) {
+    self.current.items.push(AbcItem::Element(direction));
+  }
+
+  fn note_element(&self, note: &AbcNote, in_chord: bool, invisible: bool) -> XmlElement {
+    let voice = xml_text_element("voice", self.overlay);
+    match note.pitch {
+      None if invisible => xml_element("forward", &[], vec![voice]),
+      None => xml_element("note", &[], vec![xml_element("rest", &[], Vec::new()), voice]),
+      Some((step, octave, accidental)) => {
+        let mut elements = Vec::new();
+        if in_chord {
+          elements.push(xml_element("chord", &[], Vec::new()));
+        }
+        elements.push(xml_element(
+          "pitch",
+          &[],
+          vec![xml_text_element("step", step), xml_text_element("octave", octave)],
+        ));
+        elements.push(voice);
+        if let Some(accidental) = accidental {
+          elements.push(xml_text_element("accidental", accidental));
+        }
+        xml_element("note", &[], elements)
+      }
+    }
+  }
+
+  fn add_tie(note: &mut XmlElement, tie_type: &str) {
+    note
+      .elements
+      .push(xml_element("tie", &[("type", tie_type)], Vec::new()));
+    add_notation(note, None, xml_element("tied", &[("type", tie_type)], Vec::new()));
+  }
+
+  fn push_event(&mut self, notes: &[AbcNote], multiplier: Fraction, invisible: bool) {
+    let Some(first) = notes.first() else {
+      return;
+    };
+    let mut written = first.length * multiplier * self.context.unit;
+    if let Some(acciaccatura) = self.grace {
+      for note in notes.iter().filter(|note| note.pitch.is_some()) {
+        let mut element = self.note_element(note, false, false);
+        element.elements.insert(
+          0,
+          xml_element(
+            "grace",
+            if acciaccatura { &[("slash", "yes")] } else { &[] },
+            Vec::new(),
+          ),
+        );
+        if let Some((note_type, dots)) = written.note_type() {
+          element.elements.push(xml_text_element("type", note_type));
+          element
+            .elements
+            .extend((0..dots).map(|_| xml_element("dot", &[], Vec::new())));
+        }
+        self.current.items.push(AbcItem::Element(element));
+      }
+      return;
+    }
+
+    // Apply any pending broken rhythm and tuplet ratio to the notated length
+    if let Some(factor) = self.broken_rhythm.take() {
+      written = written * factor;
+    }
+    let mut actual = written;
+    let mut time_modification = None;
+    let mut tuplet_types = Vec::new();
+    if let Some((actual_notes, normal_notes, remaining, started)) = self.tuplet.as_mut() {
+      actual = written * Fraction::new(*normal_notes, *actual_notes);
+      time_modification = Some(xml_element(
+        "time-modification",
+        &[],
+        vec![
+          xml_text_element("actual-notes", *actual_notes),
+          xml_text_element("normal-notes", *normal_notes),
+        ],
+      ));
+      if !*started {
+        *started = true;
+        tuplet_types.push("start");
+      }
+      *remaining -= 1;
+      if *remaining == 0 {
+        tuplet_types.push("stop");
+        self.tuplet = None;
+      }
+    }
+
+    // Create one MusicXML note for every note in the event
+    if self.initial.is_none() {
+      self.initial = Some(self.context.copy());
+    }
+    let tied_pitches = core::mem::take(&mut self.tied_pitches);
+    self.last_event.clear();
+    for (idx, note) in notes.iter().enumerate() {
+      let mut element = self.note_element(note, idx > 0, invisible);
+      if element.name == "note" {
+        element
+          .elements
+          .extend(time_modification.as_ref().map(XmlElementExt::deep_copy));
+        if let Some((step, octave, _)) = note.pitch {
+          if tied_pitches.contains(&(step, octave)) {
+            Self::add_tie(&mut element, "stop");
+          }
+          if note.tie {
+            Self::add_tie(&mut element, "start");
+            self.tied_pitches.push((step, octave));
+          }
+        }
+        if idx == 0 {
+          for (group, notation) in self.notations.drain(..) {
+            add_notation(&mut element, group, notation);
+          }
+          for _ in 0..self.pending_slurs {
+            self.open_slurs += 1;
+            add_notation(
+              &mut element,
+              None,
+              xml_element(
+                "slur",
+                &[("type", "start"), ("number", &self.open_slurs.to_string())],
+                Vec::new(),
+              ),
+            );
+          }
+          self.pending_slurs = 0;
+          for tuplet_type in &tuplet_types {
+            add_notation(
+              &mut element,
+              None,
+              xml_element("tuplet", &[("type", tuplet_type)], Vec::new()),
+            );
+          }
+        }
+      }
+      self.last_event.push(self.current.items.len());
+      self.current.items.push(AbcItem::Timed {
+        element,
+        written,
+        actual,
+      });
+    }
+    self.notations.clear();
+    self.position = self.position + actual;
+    self.extent = self.extent.max(self.position);
+  }
+
+  fn push_measure_rests(&mut self, count: u64, invisible: bool) {
+    let length = self.context.measure_length.unwrap_or(Fraction::ONE) / self.context.unit;
+    for idx in 0..count {
+      if idx > 0 {
+        self.bar(false, false, None, None);
+      }
+      let rest = AbcNote {
+        pitch: None,
+        length,
+        tie: false,
+      };
+      self.push_event(&[rest], Fraction::ONE, invisible);
+      if let Some(AbcItem::Timed { element, .. }) = self.current.items.last_mut() {
+        if let Some(rest) = element.elements.iter_mut().find(|child| child.name == "rest") {
+          rest.attributes.push((String::from("measure"), String::from("yes")));
+        }
+      }
+    }
+  }
+
+  fn end_slur(&mut self) {
+    if self.open_slurs == 0 {
+      return;
+    }
+    if let Some(AbcItem::Timed { element, .. }) =
+      self.last_event.first().and_then(|idx| self.current.items.get_mut(*idx))
+    {
+      if element.name == "note" {
+        add_notation(
+          element,
+          None,
+          xml_element(
+            "slur",
+            &[("type", "stop"), ("number", &self.open_slurs.to_string())],
+            Vec::new(),
+          ),
+        );
+        self.open_slurs -= 1;
+      }
+    }
+  }
+
+  fn apply_broken_rhythm(&mut self, previous: Fraction, next: Fraction) {
+    let mut change = None;
+    for idx in &self.last_event {
+      if let Some(AbcItem::Timed { written, actual, .. }) = self.current.items.get_mut(*idx) {
+        change.get_or_insert((*actual, *actual * previous));
+        *written = *written * previous;
+        *actual = *actual * previous;
+      }
+    }
+    if let Some((before, after)) = change {
+      self.position = (self.position + after) - before;
+      self.extent = self.extent.max(self.position);
+    }
+    self.broken_rhythm = Some(next);
+  }
+
+  fn overlay(&mut self) {
+    self.current.items.push(AbcItem::Backup(self.position));
+    self.position = Fraction::ZERO;
+    self.overlay = (self.overlay + 1).min(VOICES_PER_STAFF);
+    self.last_event.clear();
+    self.tuplet = None;
+    self.broken_rhythm = None;
+  }
+
+  fn bar(&mut self, end_repeat: bool, start_repeat: bool, style: Option<&'static str>, ending: Option<String>) {
+    // Any open ending is closed by repeats, double bars, or the start of another ending
+    let ending_stop = if end_repeat || start_repeat || style.is_some() || ending.is_some() {
+      self.open_ending.take().map(|numbers| (numbers, !end_repeat))
+    } else {
+      None
+    };
+    if self.current.has_content() {
+      if self.overlay > 1 && self.position < self.extent {
+        let remaining = self.extent - self.position;
+        self.current.items.push(AbcItem::Timed {
+          element: xml_element("forward", &[], vec![xml_text_element("voice", self.overlay)]),
+          written: remaining,
+          actual: remaining,
+        });
+      }
+      let mut measure = core::mem::take(&mut self.current);
+      measure.backward_repeat = end_repeat;
+      measure.ending_stop = ending_stop;
+      measure.bar_style = style;
+      self.measures.push(measure);
+    } else if let Some(previous) = self.measures.last_mut() {
+      previous.backward_repeat |= end_repeat;
+      if ending_stop.is_some() {
+        previous.ending_stop = ending_stop;
+      }
+      if style.is_some() {
+        previous.bar_style = style;
+      }
+    }
+    self.current.forward_repeat |= start_repeat;
+    if let Some(numbers) = ending {
+      self.current.ending_start = Some(numbers.clone());
+      self.open_ending = Some(numbers);
+    }
+    self.position = Fraction::ZERO;
+    self.extent = Fraction::ZERO;
+    self.overlay = 1;
+    self.last_event.clear();
+    self.tuplet = None;
+  }
+}
+
+#[derive(Default)]
+struct AbcReader {
+  fields: Vec<(char, String)>,
+  copyright: Option<String>,
+  context: AbcContext,
+  unit_specified: bool,
+  tempo: Option<XmlElement>,
+  voice_definitions: Vec<String>,
+  decorations: BTreeMap<char, String>,
+  voices: Vec<(String, AbcVoice)>,
+  current_voice: usize,
+}
+
+impl AbcReader {
+  fn parse(text: &str) -> Result<Self, String> {
+    // Only the first tune in the data is read, which ends at the first empty line after its header
+    let mut reader = Self::default();
+    let start = text.lines().position(|line| line.starts_with("X:")).unwrap_or(0);
+    let mut in_header = true;
+    let mut found_reference = false;
+    for line in text.lines().skip(start) {
+      if line.trim().is_empty() {
+        if in_header {
+          continue;
+        }
+        break;
+      } else if let Some(directive) = line.strip_prefix("%%") {
+        if let Some(copyright) = directive.strip_prefix("abc-copyright") {
+          reader.copyright = Some(String::from(copyright.trim()));
+        }
+        continue;
+      }
+      let line = AbcConverter::strip_comment(line);
+      match AbcConverter::split_field_line(line, in_header) {
+        Some(('X', _)) if found_reference => break,
+        Some((letter, value)) if in_header => {
+          found_reference |= letter == 'X';
+          reader.header_field(letter, value);
+          if letter == 'K' {
+            in_header = false;
+            reader.end_header();
+          }
+        }
+        Some((letter, value)) => reader.body_field(letter, value),
+        None if !in_header => reader.music_line(line)?,
+        None => (),
+      }
+    }
+    if in_header {
+      return Err(String::from("Data does not contain an ABC tune...missing K: field"));
+    }
+    for (_, voice) in &mut reader.voices {
+      voice.bar(false, false, Some("light-heavy"), None);
+    }
+    Ok(reader)
+  }
+
+  fn voice(&mut self) -> &mut AbcVoice {
+    if self.voices.is_empty() {
+      self
+        .voices
+        .push((String::from("1"), AbcVoice::new(self.context.copy())));
+      self.current_voice = 0;
+    }
+    &mut self.voices[self.current_voice].1
+  }
+
+  fn select_voice(&mut self, definition: &str) {
+    let tokens = AbcConverter::split_field(definition);
+    let Some(id) = tokens.first() else {
+      return;
+    };
+    self.current_voice = self
+      .voices
+      .iter()
+      .position(|(voice_id, _)| voice_id == id)
+      .unwrap_or_else(|| {
+        self.voices.push((id.clone(), AbcVoice::new(self.context.copy())));
+        self.voices.len() - 1
+      });
+    for token in &tokens[1..] {
+      match token.split_once('=') {
+        Some(("name" | "nm", name)) => self.voice().name = Some(String::from(name.trim_matches('"'))),
+        Some(("clef", clef)) => {
+          if let Some(clef) = AbcConverter::clef_element(clef) {
+            self.voice().change_context(None, None, Some(clef));
+          }
+        }
+        Some(_) => (),
+        None => {
+          if let Some(clef) = AbcConverter::clef_element(token) {
+            self.voice().change_context(None, None, Some(clef));
+          }
+        }
+      }
+    }
+  }
+
+  fn define_decoration(&mut self, definition: &str) {
+    if let Some((symbol, decoration)) = definition.split_once('=') {
+      let mut symbol = symbol.trim().chars();
+      if let (Some(symbol), None) = (symbol.next(), symbol.next()) {
+        let decoration = decoration.trim().trim_matches(|ch| ch == '!' || ch == '+');
+        self.decorations.insert(symbol, String::from(decoration));
+      }
+    }
+  }
+
+  fn header_field(&mut self, letter: char, value: &str) {
+    match letter {
+      'M' => {
+        if let Some((time, measure_length, compound)) = AbcConverter::parse_meter(value) {
+          self.context.time = time;
+          self.context.measure_length = measure_length;
+          self.context.compound = compound;
+        }
+      }
+      'L' => {
+        if let Some(unit) = Fraction::parse(value).filter(|unit| unit.numerator > 0) {
+          self.context.unit = unit;
+          self.unit_specified = true;
+        }
+      }
+      'Q' => self.tempo = AbcConverter::parse_tempo(value, self.context.unit),
+      'K' => {
+        let (key, clef) = AbcConverter::parse_key(value);
+        if let Some(key) = key {
+          self.context.key = key;
+        }
+        if let Some(clef) = clef {
+          self.context.clef = clef;
+        }
+      }
+      'V' => self.voice_definitions.push(String::from(value)),
+      'U' => self.define_decoration(value),
+      '+' => {
+        if let Some((_, last)) = self.fields.last_mut() {
+          last.push(' ');
+          last.push_str(value);
+        }
+      }
+      'I' | 'P' | 'W' | 'w' | 'm' | 'r' | 's' => (),
+      letter => self.fields.push((letter, String::from(value))),
+    }
+  }
+
+  fn end_header(&mut self) {
+    if !self.unit_specified
+      && self
+        .context
+        .measure_length
+        .is_some_and(|length| length < Fraction::new(3, 4))
+    {
+      self.context.unit = Fraction::new(1, 16);
+    }
+    for definition in core::mem::take(&mut self.voice_definitions) {
+      self.select_voice(&definition);
+    }
+    self.current_voice = 0;
+  }
+
+  fn body_field(&mut self, letter: char, value: &str) {
+    match letter {
+      'K' => {
+        let (key, clef) = AbcConverter::parse_key(value);
+        self.voice().change_context(key, None, clef);
+      }
+      'M' => {
+        if let Some((time, measure_length, compound)) = AbcConverter::parse_meter(value) {
+          let voice = self.voice();
+          voice.context.measure_length = measure_length;
+          voice.context.compound = compound;
+          voice.change_context(None, Some(time), None);
+        }
+      }
+      'L' => {
+        if let Some(unit) = Fraction::parse(value).filter(|unit| unit.numerator > 0) {
+          self.voice().context.unit = unit;
+        }
+      }
+      'Q' => {
+        let unit = self.voice().context.unit;
+        if let Some(direction) = AbcConverter::parse_tempo(value, unit) {
+          self.voice().push_direction(direction);
+        }
+      }
+      'V' => self.select_voice(value),
+      'P' if !value.is_empty() => self.voice().push_direction(AbcConverter::direction(
+        vec![xml_text_element("rehearsal", value)],
+        None,
+        Some("above"),
+      )),
+      'T' if !value.is_empty() => self.voice().push_direction(AbcConverter::direction(
+        vec![xml_text_element("words", value)],
+        None,
+        Some("above"),
+      )),
+      'U' => self.define_decoration(value),
+      _ => (),
+    }
+  }
+
+  fn annotation(&mut self, text: &str) {
+    // Annotations without a placement prefix are chord symbols, which have no AMM equivalent
+    let (placement, text) = match text.chars().next() {
+      Some('^') => (Some("above"), &text[1..]),
+      Some('_') => (Some("below"), &text[1..]),
+      Some('<' | '>' | '@') => (None, &text[1..]),
+      _ => return,
+    };
+    if !text.trim().is_empty() {
+      self.voice().push_direction(AbcConverter::direction(
+        vec![xml_text_element("words", text.trim())],
+        None,
+        placement,
+      ));
+    }
+  }
+
+  fn decoration(&mut self, name: &str) {
+    let mut symbol = name.chars();
+    let name = match (symbol.next(), symbol.next()) {
+      (Some(symbol), None) => self
+        .decorations
+        .get(&symbol)
+        .cloned()
+        .unwrap_or_else(|| String::from(name)),
+      _ => String::from(name),
+    };
+    match AbcConverter::transcode_decoration(&name) {
+      Some(AbcDecoration::Notation(group, notation)) => self.voice().notations.push((group, notation)),
+      Some(AbcDecoration::Direction(direction)) => self.voice().push_direction(direction),
+      None => (),
+    }
+  }
+
+  fn tuplet(&mut self, chars: &[char], idx: &mut usize) {
+    let actual_notes = AbcConverter::read_number(chars, idx)
+      .unwrap_or(3)
+      .clamp(1, u64::from(u8::MAX));
+    let read_part = |idx: &mut usize| {
+      if chars.get(*idx) == Some(&':') {
+        *idx += 1;
+        AbcConverter::read_number(chars, idx)
+      } else {
+        None
+      }
+    };
+    let normal_notes = read_part(idx);
+    let num_notes = read_part(idx);
+    let voice = self.voice();
+    let normal_notes = normal_notes
+      .unwrap_or(match actual_notes {
+        2 | 4 | 8 => 3,
+        3 | 6 => 2,
+        _ if voice.context.compound => 3,
+        _ => 2,
+      })
+      .clamp(1, u64::from(u8::MAX));
+    voice.tuplet = Some((
+      actual_notes,
+      normal_notes,
+      num_notes.unwrap_or(actual_notes).max(1),
+      false,
+    ));
+  }
+
+  fn bar_line(&mut self, chars: &[char], idx: &mut usize) {
+    let start = *idx;
+    while let Some(&ch) = chars.get(*idx) {
+      let continues = match ch {
+        '|' | ':' => true,
+        '[' => *idx == start && chars.get(*idx + 1) == Some(&'|'),
+        ']' => chars[start..*idx].contains(&'|'),
+        _ => false,
+      };
+      if !continues {
+        break;
+      }
+      *idx += 1;
+    }
+    let token = chars[start..*idx].iter().collect::<String>();
+    let ending = if chars.get(*idx).is_some_and(char::is_ascii_digit) {
+      AbcConverter::read_ending(chars, idx)
+    } else if chars.get(*idx) == Some(&'[') && chars.get(*idx + 1).is_some_and(char::is_ascii_digit) {
+      *idx += 1;
+      AbcConverter::read_ending(chars, idx)
+    } else {
+      None
+    };
+    if token.contains('|') || token == "::" || ending.is_some() {
+      let end_repeat = token.starts_with(':');
+      let start_repeat = token.ends_with(':');
+      let style = if end_repeat || token.contains("|]") {
+        Some("light-heavy")
+      } else if token.contains("||") {
+        Some("light-light")
+      } else if token.starts_with("[|") {
+        Some("heavy-light")
+      } else {
+        None
+      };
+      self.voice().bar(end_repeat, start_repeat, style, ending);
+    }
+  }
+
+  fn chord(&mut self, chars: &[char], idx: &mut usize) -> Result<(), String> {
+    *idx += 1;
+    let mut notes = Vec::new();
+    loop {
+      let Some(&ch) = chars.get(*idx) else {
+        return Err(String::from("Unterminated chord...missing closing ']'"));
+      };
+      match ch {
+        ']' => break,
+        '^' | '_' | '=' | 'A'..='G' | 'a'..='g' => notes.extend(AbcConverter::read_note(chars, idx)),
+        '!' | '+' => {
+          if let Some(name) = AbcConverter::read_delimited(chars, idx) {
+            self.decoration(&name);
+          }
+        }
+        '"' => {
+          if let Some(text) = AbcConverter::read_delimited(chars, idx) {
+            self.annotation(&text);
+          }
+        }
+        ch if DECORATION_SYMBOLS.contains(&ch) => {
+          self.decoration(&ch.to_string());
+          *idx += 1;
+        }
+        _ => *idx += 1,
+      }
+    }
+    *idx += 1;
+    let length = AbcConverter::read_length(chars, idx);
+    if chars.get(*idx) == Some(&'-') {
+      *idx += 1;
+      notes.iter_mut().for_each(|note| note.tie = true);
+    }
+    self.voice().push_event(&notes, length, false);
+    Ok(())
+  }
+
+  fn music_line(&mut self, line: &str) -> Result<(), String> {
+    let chars = line.chars().collect::<Vec<_>>();
+    let mut idx = 0;
+    while let Some(&ch) = chars.get(idx) {
+      match ch {
+        '"' => {
+          if let Some(text) = AbcConverter::read_delimited(&chars, &mut idx) {
+            self.annotation(&text);
+          }
+        }
+        '!' | '+' => {
+          if let Some(name) = AbcConverter::read_delimited(&chars, &mut idx) {
+            self.decoration(&name);
+          }
+        }
+        '.' if chars.get(idx + 1) == Some(&'|') => idx += 1,
+        '{' => {
+          let acciaccatura = chars.get(idx + 1) == Some(&'/');
+          self.voice().grace = Some(acciaccatura);
+          idx += if acciaccatura { 2 } else { 1 };
+        }
+        '}' => {
+          self.voice().grace = None;
+          idx += 1;
+        }
+        '(' if chars.get(idx + 1).is_some_and(char::is_ascii_digit) => {
+          idx += 1;
+          self.tuplet(&chars, &mut idx);
+        }
+        '(' => {
+          self.voice().pending_slurs += 1;
+          idx += 1;
+        }
+        ')' => {
+          self.voice().end_slur();
+          idx += 1;
+        }
+        '[' if chars.get(idx + 1).is_some_and(char::is_ascii_digit) => {
+          idx += 1;
+          let ending = AbcConverter::read_ending(&chars, &mut idx);
+          self.voice().bar(false, false, None, ending);
+        }
+        '[' if chars.get(idx + 1) == Some(&'|') => self.bar_line(&chars, &mut idx),
+        '[' if chars.get(idx + 1).is_some_and(char::is_ascii_alphabetic) && chars.get(idx + 2) == Some(&':') => {
+          let end = chars[idx..]
+            .iter()
+            .position(|ch| *ch == ']')
+            .map_or(chars.len(), |end| idx + end);
+          let value = chars[idx + 3..end].iter().collect::<String>();
+          self.body_field(chars[idx + 1], value.trim());
+          idx = end + 1;
+        }
+        '[' => self.chord(&chars, &mut idx)?,
+        '|' | ':' => self.bar_line(&chars, &mut idx),
+        '&' => {
+          self.voice().overlay();
+          idx += 1;
+        }
+        '>' | '<' => {
+          let count = chars[idx..].iter().take_while(|next| **next == ch).count();
+          idx += count;
+          let shift = count.min(3);
+          let (longer, shorter) = (
+            Fraction::new((1 << (shift + 1)) - 1, 1 << shift),
+            Fraction::new(1, 1 << shift),
+          );
+          if ch == '>' {
+            self.voice().apply_broken_rhythm(longer, shorter);
+          } else {
+            self.voice().apply_broken_rhythm(shorter, longer);
+          }
+        }
+        '^' | '_' | '=' | 'A'..='G' | 'a'..='g' => {
+          if let Some(note) = AbcConverter::read_note(&chars, &mut idx) {
+            self.voice().push_event(&[note], Fraction::ONE, false);
+          }
+        }
+        'z' | 'x' => {
+          idx += 1;
+          let rest = AbcNote {
+            pitch: None,
+            length: AbcConverter::read_length(&chars, &mut idx),
+            tie: false,
+          };
+          self.voice().push_event(&[rest], Fraction::ONE, ch == 'x');
+        }
+        'Z' | 'X' => {
+          idx += 1;
+          let count = AbcConverter::read_number(&chars, &mut idx).unwrap_or(1);
+          self
+            .voice()
+            .push_measure_rests(count.clamp(1, MAX_MEASURE_RESTS), ch == 'X');
+        }
+        ch if DECORATION_SYMBOLS.contains(&ch) || self.decorations.contains_key(&ch) => {
+          self.decoration(&ch.to_string());
+          idx += 1;
+        }
+        _ => idx += 1,
+      }
+    }
+    Ok(())
+  }
+
+  fn transcode_metadata(&self) -> Vec<XmlElement> {
+    let titles = self
+      .fields
+      .iter()
+      .filter(|(letter, _)| *letter == 'T')
+      .map(|(_, title)| title.as_str())
+      .collect::<Vec<_>>();
+    let mut metadata = Vec::new();
+    if let Some(title) = titles.first() {
+      metadata.push(xml_element("work", &[], vec![xml_text_element("work-title", title)]));
+    }
+    if let Some(movement_title) = titles.get(1) {
+      metadata.push(xml_text_element("movement-title", movement_title));
+    }
+    let mut identification = self
+      .fields
+      .iter()
+      .filter_map(|(letter, value)| match letter {
+        'C' => Some(("composer", value.as_str())),
+        'N' => CREATOR_NOTES
+          .iter()
+          .find_map(|(creator, prefix)| value.strip_prefix(prefix).map(|name| (*creator, name))),
+        _ => None,
+      })
+      .map(|(creator, name)| XmlElement {
+        text: String::from(name),
+        ..xml_element("creator", &[("type", creator)], Vec::new())
+      })
+      .collect::<Vec<_>>();
+    if let Some(copyright) = &self.copyright {
+      identification.push(xml_text_element("rights", copyright));
+    }
+    if !identification.is_empty() {
+      metadata.push(xml_element("identification", &[], identification));
+    }
+    metadata
+  }
+
+  fn transcode_extra_metadata(&self) -> BTreeMap<&'static str, String> {
+    let mut metadata: BTreeMap<&'static str, String> = BTreeMap::new();
+    for (letter, value) in &self.fields {
+      let key = if *letter == 'X' {
+        Some("reference_number")
+      } else if *letter == 'N' && CREATOR_NOTES.iter().any(|(_, prefix)| value.starts_with(prefix)) {
+        None
+      } else {
+        METADATA_FIELDS
+          .iter()
+          .find(|(field, _)| field == letter)
+          .map(|(_, key)| *key)
+      };
+      if let Some(key) = key.filter(|_| !value.is_empty()) {
+        metadata
+          .entry(key)
+          .and_modify(|existing| {
+            existing.push('\n');
+            existing.push_str(value);
+          })
+          .or_insert_with(|| value.clone());
+      }
+    }
+    metadata
+  }
+
+  fn transcode_item(item: &AbcItem, staff_number: usize, whole_divisions: u64) -> XmlElement {
+    let (element, durations) = match item {
+      AbcItem::Element(element) => (element.deep_copy(), None),
+      AbcItem::Timed {
+        element,
+        written,
+        actual,
+      } => (element.deep_copy(), Some((*written, *actual))),
+      AbcItem::Backup(duration) => {
+        return xml_element(
+          "backup",
+          &[],
+          vec![xml_text_element("duration", duration.to_divisions(whole_divisions))],
+        )
+      }
+    };
+    let mut element = element;
+    match element.name.as_str() {
+      "note" | "forward" => {
+        if let Some(voice) = element.elements.iter_mut().find(|child| child.name == "voice") {
+          let number = voice.text.parse().unwrap_or(1) + (staff_number - 1) * VOICES_PER_STAFF;
+          voice.text = number.to_string();
+        }
+        element.elements.push(xml_text_element("staff", staff_number));
+      }
+      "direction" => element.elements.push(xml_text_element("staff", staff_number)),
+      "attributes" => {
+        for child in &mut element.elements {
+          if staff_number > 1 || child.name == "clef" {
+            child
+              .attributes
+              .push((String::from("number"), staff_number.to_string()));
+          }
+        }
+      }
+      _ => (),
+    }
+    if let Some((written, actual)) = durations {
+      element
+        .elements
+        .push(xml_text_element("duration", actual.to_divisions(whole_divisions)));
+      let is_measure_rest = element
+        .child("rest")
+        .is_some_and(|rest| rest.attribute("measure") == Some("yes"));
+      if element.name == "note" && !is_measure_rest {
+        if let Some((note_type, dots)) = written.note_type() {
+          element.elements.push(xml_text_element("type", note_type));
+          element
+            .elements
+            .extend((0..dots).map(|_| xml_element("dot", &[], Vec::new())));
+        }
+      }
+    }
+    element
+  }
+
+  fn transcode_part(&self, staves: &[&AbcVoice], whole_divisions: u64) -> Vec<XmlElement> {
+    let num_measures = staves
+      .iter()
+      .map(|voice| voice.measures.len())
+      .max()
+      .unwrap_or_default();
+    (0..num_measures)
+      .map(|measure_idx| {
+        // Add the initial context to the first measure and any left barline
+        let mut elements = Vec::new();
+        if measure_idx == 0 {
+          let initial = staves[0].initial.as_ref().unwrap_or(&staves[0].context);
+          let mut attributes = vec![
+            xml_text_element("divisions", whole_divisions / 4),
+            initial.key.deep_copy(),
+            initial.time.deep_copy(),
+          ];
+          if staves.len() > 1 {
+            attributes.push(xml_text_element("staves", staves.len()));
+          }
+          for (idx, voice) in staves.iter().enumerate() {
+            let mut clef = voice.initial.as_ref().unwrap_or(&voice.context).clef.deep_copy();
+            clef.attributes.push((String::from("number"), (idx + 1).to_string()));
+            attributes.push(clef);
+          }
+          elements.push(xml_element("attributes", &[], attributes));
+          if let Some(tempo) = &self.tempo {
+            elements.push(Self::transcode_item(
+              &AbcItem::Element(tempo.deep_copy()),
+              1,
+              whole_divisions,
+            ));
+          }
+        }
+        let barlines = staves.iter().find_map(|voice| voice.measures.get(measure_idx));
+        if let Some(barlines) = barlines.filter(|measure| measure.forward_repeat || measure.ending_start.is_some()) {
+          let mut barline = Vec::new();
+          if barlines.forward_repeat {
+            barline.push(xml_text_element("bar-style", "heavy-light"));
+          }
+          if let Some(numbers) = &barlines.ending_start {
+            barline.push(xml_element(
+              "ending",
+              &[("number", numbers), ("type", "start")],
+              Vec::new(),
+            ));
+          }
+          if barlines.forward_repeat {
+            barline.push(xml_element("repeat", &[("direction", "forward")], Vec::new()));
+          }
+          elements.push(xml_element("barline", &[("location", "left")], barline));
+        }
+
+        // Transcode the contents of every staff in sequence
+        let mut cursor = Fraction::ZERO;
+        for (idx, voice) in staves.iter().enumerate() {
+          let Some(measure) = voice.measures.get(measure_idx) else {
+            continue;
+          };
+          if cursor > Fraction::ZERO {
+            elements.push(Self::transcode_item(&AbcItem::Backup(cursor), idx + 1, whole_divisions));
+            cursor = Fraction::ZERO;
+          }
+          for item in &measure.items {
+            match item {
+              AbcItem::Timed { element, actual, .. } if !element.has_child("chord") => cursor = cursor + *actual,
+              AbcItem::Backup(duration) => cursor = cursor - *duration,
+              _ => (),
+            }
+            elements.push(Self::transcode_item(item, idx + 1, whole_divisions));
+          }
+        }
+
+        // Complete the measure with any right barline
+        if let Some(barlines) = barlines
+          .filter(|measure| measure.backward_repeat || measure.ending_stop.is_some() || measure.bar_style.is_some())
+        {
+          let mut barline = Vec::new();
+          if let Some(style) = barlines.bar_style {
+            barline.push(xml_text_element("bar-style", style));
+          }
+          if let Some((numbers, discontinue)) = &barlines.ending_stop {
+            barline.push(xml_element(
+              "ending",
+              &[
+                ("number", numbers),
+                ("type", if *discontinue { "discontinue" } else { "stop" }),
+              ],
+              Vec::new(),
+            ));
+          }
+          if barlines.backward_repeat {
+            barline.push(xml_element("repeat", &[("direction", "backward")], Vec::new()));
+          }
+          elements.push(xml_element("barline", &[("location", "right")], barline));
+        }
+        xml_element("measure", &[("number", &(measure_idx + 1).to_string())], elements)
+      })
+      .collect()
+  }
+
+  fn transcode_score(&self) -> Result<XmlElement, String> {
+    // Voices sharing the same name are treated as the staves of a single part
+    let mut parts: Vec<(String, Vec<&AbcVoice>)> = Vec::new();
+    for (id, voice) in self.voices.iter().filter(|(_, voice)| !voice.measures.is_empty()) {
+      let name = voice.name.clone().unwrap_or_else(|| format!("Voice {id}"));
+      match parts.iter_mut().find(|(part_name, _)| *part_name == name) {
+        Some((_, staves)) => staves.push(voice),
+        None => parts.push((name, vec![voice])),
+      }
+    }
+    if parts.is_empty() {
+      return Err(String::from("No music found in the ABC tune"));
+    }
+
+    // Choose a number of divisions able to represent every duration exactly
+    let whole_divisions = parts
+      .iter()
+      .flat_map(|(_, staves)| staves.iter())
+      .flat_map(|voice| voice.measures.iter())
+      .flat_map(|measure| measure.items.iter())
+      .try_fold(4, |divisions: u64, item| match item {
+        AbcItem::Timed { actual: duration, .. } | AbcItem::Backup(duration) => {
+          let divisions = divisions / gcd(divisions, duration.denominator);
+          divisions
+            .checked_mul(duration.denominator)
+            .filter(|divisions| *divisions <= MAX_WHOLE_DIVISIONS)
+        }
+        AbcItem::Element(_) => Some(divisions),
+      })
+      .ok_or("Note durations in the ABC tune are too fine to be represented")?;
+
+    // Transcode the tune into an equivalent partwise MusicXML document
+    let mut contents = self.transcode_metadata();
+    contents.push(xml_element(
+      "part-list",
+      &[],
+      parts
+        .iter()
+        .enumerate()
+        .map(|(idx, (name, _))| {
+          xml_element(
+            "score-part",
+            &[("id", &format!("P{}", idx + 1))],
+            vec![xml_text_element("part-name", name)],
+          )
+        })
+        .collect(),
+    ));
+    for (idx, (_, staves)) in parts.iter().enumerate() {
+      contents.push(xml_element(
+        "part",
+        &[("id", &format!("P{}", idx + 1))],
+        self.transcode_part(staves, whole_divisions),
+      ));
+    }
+    Ok(xml_element("score-partwise", &[("version", "4.0")], contents))
+  }
+}
+
+struct AbcWriter {
+  output: String,
+  unit: Fraction,
+  meter: String,
+  measure_length: Option<Fraction>,
+  key: Key,
+  tempo: Tempo,
+  clef: ClefType,
+  position: Fraction,
+  measures_on_line: usize,
+  pending_bar: bool,
+  pending_start_repeat: bool,
+  pending_end_repeat: bool,
+  pending_ending: Option<String>,
+  pending_fields: Vec<String>,
+  prefix: String,
+  in_grace: bool,
+  origin: Fraction,
+  elapsed: Fraction,
+  overlay_bars: Option<Vec<usize>>,
+  nested_voices: Vec<AbcWriter>,
+  pending_overlays: Vec<String>,
+  pending_parts: Vec<String>,
+}
+
+impl AbcWriter {
+  fn new(composition: &Composition, unit: Fraction) -> Self {
+    let (meter, measure_length) = AbcConverter::meter_text(composition.get_starting_time_signature());
+    Self {
+      output: String::new(),
+      unit,
+      meter,
+      measure_length,
+      key: *composition.get_starting_key(),
+      tempo: *composition.get_tempo(),
+      clef: ClefType::Treble,
+      position: Fraction::ZERO,
+      measures_on_line: 0,
+      pending_bar: false,
+      pending_start_repeat: false,
+      pending_end_repeat: false,
+      pending_ending: None,
+      pending_fields: Vec::new(),
+      prefix: String::new(),
+      in_grace: false,
+      origin: Fraction::ZERO,
+      elapsed: Fraction::ZERO,
+      overlay_bars: None,
+      nested_voices: Vec::new(),
+      pending_overlays: Vec::new(),
+      pending_parts: Vec::new(),
+    }
+  }
+
+  fn overlay_writer(&self, position: Fraction) -> Self {
+    Self {
+      output: String::new(),
+      unit: self.unit,
+      meter: self.meter.clone(),
+      measure_length: self.measure_length,
+      key: self.key,
+      tempo: self.tempo,
+      clef: self.clef,
+      position,
+      measures_on_line: 0,
+      pending_bar: false,
+      pending_start_repeat: false,
+      pending_end_repeat: false,
+      pending_ending: None,
+      pending_fields: Vec::new(),
+      prefix: String::new(),
+      in_grace: false,
+      origin: self.origin,
+      elapsed: Fraction::ZERO,
+      overlay_bars: Some(Vec::new()),
+      nested_voices: Vec::new(),
+      pending_overlays: Vec::new(),
+      pending_parts: Vec::new(),
+    }
+  }
+
+  fn separate(&mut self) {
+    if !self.output.is_empty() && !self.output.ends_with([' ', '\n']) {
+      self.output.push(' ');
+    }
+  }
+
+  fn flush_bar(&mut self) {
+    if let Some(bars) = &mut self.overlay_bars {
+      // Overlay voices only record where their measures end so that they can be interleaved afterwards
+      if core::mem::take(&mut self.pending_bar) {
+        bars.push(self.output.len());
+      }
+      return;
+    }
+    let bar = match (self.pending_end_repeat, self.pending_start_repeat) {
+      (true, true) => Some("::"),
+      (true, false) => Some(":|"),
+      (false, true) => Some("|:"),
+      (false, false) => self.pending_bar.then_some("|"),
+    };
+    let ending = self.pending_ending.take();
+    if bar.is_some() || ending.is_some() {
+      self.flush_overlays();
+      let bar = bar.unwrap_or("|");
+      self.separate();
+      self.output.push_str(bar);
+      if let Some(ending) = ending {
+        if !bar.ends_with('|') {
+          self.output.push_str(" [");
+        }
+        self.output.push_str(&ending);
+      }
+      if self.pending_bar {
+        self.measures_on_line += 1;
+        if self.measures_on_line >= MEASURES_PER_LINE {
+          self.output.push('\n');
+          self.measures_on_line = 0;
+        }
+      }
+      self.pending_fields.append(&mut self.pending_parts);
+    }
+    self.pending_bar = false;
+    self.pending_start_repeat = false;
+    self.pending_end_repeat = false;
+    for field in core::mem::take(&mut self.pending_fields) {
+      self.separate();
+      self.output.push_str(&format!("[{field}]"));
+    }
+  }
+
+  fn flush_overlays(&mut self) {
+    for overlay in core::mem::take(&mut self.pending_overlays) {
+      self.separate();
+      self.output.push_str("& ");
+      self.output.push_str(&overlay);
+    }
+  }
+
+  fn begin(&mut self) {
+    if self.in_grace {
+      self.output.push('}');
+      self.in_grace = false;
+    } else {
+      self.flush_bar();
+      self.separate();
+    }
+    let prefix = core::mem::take(&mut self.prefix);
+    self.output.push_str(&prefix);
+  }
+
+  fn close_grace(&mut self) {
+    if self.in_grace {
+      self.output.push('}');
+      self.in_grace = false;
+    }
+  }
+
+  fn advance(&mut self, duration: Fraction) {
+    self.position = self.position + duration;
+    self.elapsed = self.elapsed + duration;
+    if let Some(length) = self.measure_length.filter(|length| length.numerator > 0) {
+      while self.position >= length {
+        self.position = self.position - length;
+        self.pending_bar = true;
+      }
+    }
+  }
+
+  fn pad(&mut self, mut duration: Fraction) {
+    // Invisible rests are split at barlines so that every measure remains complete
+    while duration > Fraction::ZERO {
+      let chunk = self
+        .measure_length
+        .filter(|length| length.numerator > 0)
+        .map_or(duration, |length| duration.min(length - self.position));
+      self.begin();
+      self.output.push('x');
+      self.output.push_str(&AbcConverter::length_text(chunk, self.unit));
+      self.advance(chunk);
+      duration = duration - chunk;
+    }
+  }
+
+  fn write_note(&mut self, note: &Note, ratio: Fraction) {
+    let duration = Fraction::from_duration(&note.duration);
+    let grace = note
+      .iter_modifications()
+      .find_map(|modification| match modification.r#type {
+        NoteModificationType::Grace { acciaccatura } => Some(acciaccatura),
+        _ => None,
+      });
+    if let Some(acciaccatura) = grace {
+      if !self.in_grace {
+        self.begin();
+        self.output.push_str(if acciaccatura { "{/" } else { "{" });
+        self.in_grace = true;
+      }
+      self.output.push_str(&AbcConverter::pitch_text(note));
+      self.output.push_str(&AbcConverter::length_text(duration, self.unit));
+    } else {
+      self.begin();
+      for modification in note.iter_modifications() {
+        self
+          .output
+          .push_str(AbcConverter::note_decoration(&modification.r#type).unwrap_or_default());
+      }
+      if note.is_rest() {
+        self.output.push('z');
+      } else {
+        self.output.push_str(&AbcConverter::pitch_text(note));
+      }
+      self.output.push_str(&AbcConverter::length_text(duration, self.unit));
+      if note
+        .iter_modifications()
+        .any(|modification| modification.r#type == NoteModificationType::Tie)
+      {
+        self.output.push('-');
+      }
+      self.advance(duration * ratio);
+    }
+  }
+
+  fn write_chord(&mut self, chord: &Chord, ratio: Fraction) {
+    // Grace notes cannot appear within an ABC chord, so they are written just before it
+    let (grace_notes, notes): (Vec<_>, Vec<_>) = chord
+      .iter()
+      .map(|ChordContent::Note(note)| note)
+      .filter(|note| !note.is_rest())
+      .partition(|note| note.is_grace_note());
+    for note in grace_notes {
+      self.write_note(note, ratio);
+    }
+    let Some(first) = notes.first() else {
+      return;
+    };
+    let duration = Fraction::from_duration(&first.duration);
+    self.begin();
+    for modification in chord.iter_modifications() {
+      self
+        .output
+        .push_str(AbcConverter::chord_decoration(&modification.r#type).unwrap_or_default());
+    }
+    self.output.push('[');
+    for note in &notes {
+      self.output.push_str(&AbcConverter::pitch_text(note));
+      self.output.push_str(&AbcConverter::length_text(
+        Fraction::from_duration(&note.duration),
+        self.unit,
+      ));
+      if note
+        .iter_modifications()
+        .any(|modification| modification.r#type == NoteModificationType::Tie)
+      {
+        self.output.push('-');
+      }
+    }
+    self.output.push(']');
+    if chord
+      .iter_modifications()
+      .any(|modification| modification.r#type == ChordModificationType::Tie)
+    {
+      self.output.push('-');
+    }
+    self.advance(duration * ratio);
+  }
+
+  fn count_events(items: core::slice::Iter<'_, PhraseContent>) -> usize {
+    items
+      .map(|item| match item {
+        PhraseContent::Note(note) => usize::from(!note.is_grace_note()),
+        PhraseContent::Chord(_) => 1,
+        PhraseContent::Phrase(phrase) => Self::count_events(phrase.iter()),
+        PhraseContent::MultiVoice(multivoice) => multivoice
+          .iter()
+          .next()
+          .map_or(0, |MultiVoiceContent::Phrase(phrase)| Self::count_events(phrase.iter())),
+      })
+      .sum()
+  }
+
+  fn write_phrase(&mut self, phrase: &Phrase, mut ratio: Fraction) {
+    if phrase.is_empty() {
+      return;
+    }
+    let mut closing = String::new();
+    let mut trailing = String::new();
+    for modification in phrase.iter_modifications() {
+      match &modification.r#type {
+        PhraseModificationType::Tuplet { num_beats, into_beats } => {
+          let count = Self::count_events(phrase.iter());
+          self.prefix.push_str(&format!("({num_beats}:{into_beats}:{count}"));
+          ratio = ratio * Fraction::new(u64::from(*into_beats), u64::from(*num_beats));
+        }
+        PhraseModificationType::Legato => {
+          self.prefix.push('(');
+          closing.push(')');
+        }
+        PhraseModificationType::Crescendo { .. } => {
+          self.prefix.push_str("!<(!");
+          trailing.push_str("!<)!");
+        }
+        PhraseModificationType::Decrescendo { .. } => {
+          self.prefix.push_str("!>(!");
+          trailing.push_str("!>)!");
+        }
+        PhraseModificationType::OctaveShift { num_octaves } => {
+          let marker = match num_octaves {
+            1 => "8va",
+            -1 => "8vb",
+            2 => "15ma",
+            -2 => "15mb",
+            _ => continue,
+          };
+          self.prefix.push_str(&format!("!{marker}(!"));
+          trailing.push_str(&format!("!{marker})!"));
+        }
+        PhraseModificationType::Pedal { .. } => {
+          self.prefix.push_str("!ped!");
+          trailing.push_str("!ped-up!");
+        }
+        _ => (),
+      }
+    }
+    for item in phrase.iter() {
+      match item {
+        PhraseContent::Note(note) => self.write_note(note, ratio),
+        PhraseContent::Chord(chord) => self.write_chord(chord, ratio),
+        PhraseContent::Phrase(phrase) => self.write_phrase(phrase, ratio),
+        PhraseContent::MultiVoice(multivoice) => self.write_multivoice(multivoice, ratio),
+      }
+    }
+    self.close_grace();
+    self.output.push_str(&closing);
+    self.prefix.push_str(&trailing);
+  }
+
+  fn write_multivoice(&mut self, multivoice: &MultiVoice, ratio: Fraction) {
+    let phrases = multivoice
+      .iter()
+      .map(|MultiVoiceContent::Phrase(phrase)| phrase)
+      .filter(|phrase| Self::count_events(phrase.iter()) > 0)
+      .collect::<Vec<_>>();
+    if self.overlay_bars.is_some() {
+      // ABC overlays cannot be nested, so nested voices become additional voices of the outermost overlay
+      let start = self.elapsed;
+      let mut end = start;
+      for phrase in phrases.iter().skip(1) {
+        let mut voice = self.overlay_writer(Fraction::ZERO);
+        voice.pad(self.origin + start);
+        voice.elapsed = start;
+        voice.write_phrase(phrase, ratio);
+        end = end.max(voice.elapsed);
+        self.nested_voices.push(voice);
+      }
+      if let Some(phrase) = phrases.first() {
+        self.write_phrase(phrase, ratio);
+      }
+      self.pad(end - self.elapsed.min(end));
+      return;
+    }
+
+    // ABC overlays cannot cross barlines, so each voice is written separately, padded with invisible
+    // rests to cover the same span, and then interleaved measure by measure
+    self.close_grace();
+    self.flush_bar();
+    let start = self.position;
+    self.origin = start;
+    let mut voices = Vec::new();
+    for (idx, phrase) in phrases.iter().enumerate() {
+      let mut voice = if idx == 0 {
+        let mut voice = self.overlay_writer(start);
+        voice.prefix = core::mem::take(&mut self.prefix);
+        voice
+      } else {
+        let mut voice = self.overlay_writer(Fraction::ZERO);
+        voice.pad(start);
+        voice.elapsed = Fraction::ZERO;
+        voice
+      };
+      voice.write_phrase(phrase, ratio);
+      voices.push(voice);
+    }
+    let mut voice_idx = 0;
+    while let Some(voice) = voices.get_mut(voice_idx) {
+      let mut nested_voices = core::mem::take(&mut voice.nested_voices);
+      voices.append(&mut nested_voices);
+      voice_idx += 1;
+    }
+    let duration = voices.iter().map(|voice| voice.elapsed).max().unwrap_or(Fraction::ZERO);
+    let segments = voices
+      .iter_mut()
+      .map(|voice| {
+        voice.pad(duration - voice.elapsed);
+        voice.close_grace();
+        voice.flush_bar();
+        self.prefix.push_str(&voice.prefix);
+        let mut segments = Vec::new();
+        let mut segment_start = 0;
+        for &segment_end in voice.overlay_bars.iter().flatten() {
+          segments.push(voice.output[segment_start..segment_end].trim());
+          segment_start = segment_end;
+        }
+        segments.push(voice.output[segment_start..].trim());
+        segments
+      })
+      .collect::<Vec<_>>();
+
+    // The first voice continues the current measure, while the others are overlaid just before each barline
+    let num_segments = segments.iter().map(Vec::len).max().unwrap_or_default();
+    for segment_idx in 0..num_segments {
+      let mut parts = segments
+        .iter()
+        .filter_map(|voice| voice.get(segment_idx).copied())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+      let is_padding = |part: &&str| part.split_whitespace().all(|token| token.starts_with('x'));
+      if segment_idx > 0 {
+        if parts.is_empty() {
+          continue;
+        }
+        self.pending_bar = true;
+        if let Some(idx) = parts.iter().position(|part| !is_padding(part)) {
+          let main = parts.remove(idx);
+          parts.insert(0, main);
+        }
+      }
+      if let Some((main, overlays)) = parts.split_first() {
+        self.flush_bar();
+        self.separate();
+        self.output.push_str(main);
+        self.pending_overlays.extend(
+          overlays
+            .iter()
+            .filter(|part| !is_padding(part))
+            .map(|part| String::from(*part)),
+        );
+      }
+    }
+    self.position = start;
+    self.advance(duration);
+    self.pending_bar = num_segments > 1
+      && segments
+        .iter()
+        .all(|voice| voice.get(num_segments - 1).is_none_or(|part| part.is_empty()));
+  }
+
+  fn write_direction(&mut self, direction: &Direction) {
+    match &direction.r#type {
+      DirectionType::Dynamic { dynamic } => {
+        if let Some(dynamic) = AbcConverter::dynamic_text(dynamic) {
+          self.prefix.push_str(&format!("!{dynamic}!"));
+        }
+      }
+      DirectionType::KeyChange { key } => {
+        if *key != self.key {
+          self.key = *key;
+          self.pending_fields.push(format!("K:{}", AbcConverter::key_text(key)));
+        }
+      }
+      DirectionType::TimeSignatureChange { time_signature } => {
+        let (meter, measure_length) = AbcConverter::meter_text(time_signature);
+        if meter != self.meter {
+          self.pending_fields.push(format!("M:{meter}"));
+          self.meter = meter;
+          self.measure_length = measure_length;
+        }
+      }
+      DirectionType::ClefChange { clef } => {
+        if clef.clef_type != self.clef {
+          self.clef = clef.clef_type;
+          self.pending_fields.push(format!(
+            "K:{} clef={}",
+            AbcConverter::key_text(&self.key),
+            AbcConverter::clef_text(clef.clef_type)
+          ));
+        }
+      }
+      DirectionType::Rehearsal { mark, .. } => {
+        // Parts always begin at a barline, so marks placed within a measure are moved to the next one
+        if self.position == Fraction::ZERO {
+          self.pending_fields.push(format!("P:{mark}"));
+        } else {
+          self.pending_parts.push(format!("P:{mark}"));
+        }
+      }
+      DirectionType::Text { text, style } | DirectionType::Expression { text, style } => {
+        let placement = if style.placement == Some(TextPlacement::Below) {
+          '_'
+        } else {
+          '^'
+        };
+        self
+          .prefix
+          .push_str(&format!("\"{placement}{}\"", text.replace('"', "'")));
+      }
+      DirectionType::BreathMark => self.prefix.push_str("!breath!"),
+      DirectionType::Caesura => self.prefix.push_str("!caesura!"),
+      _ => (),
+    }
+  }
+
+  fn write_staff(&mut self, staff: &Staff) {
+    for item in staff.iter() {
+      match item {
+        StaffContent::Note(note) => self.write_note(note, Fraction::ONE),
+        StaffContent::Chord(chord) => self.write_chord(chord, Fraction::ONE),
+        StaffContent::Phrase(phrase) => self.write_phrase(phrase, Fraction::ONE),
+        StaffContent::MultiVoice(multivoice) => self.write_multivoice(multivoice, Fraction::ONE),
+        StaffContent::Direction(direction) => self.write_direction(direction),
+      }
+    }
+  }
+
+  fn write_section(&mut self, section: &Section, staff_name: &str) {
+    let mut repeat = false;
+    for modification in section.iter_modifications() {
+      match &modification.r#type {
+        SectionModificationType::Repeat { .. } => repeat = true,
+        SectionModificationType::OnlyPlay { iterations } => {
+          self.pending_ending = Some(
+            iterations
+              .iter()
+              .map(|iteration| (u16::from(*iteration) + 1).to_string())
+              .collect::<Vec<_>>()
+              .join(","),
+          );
+        }
+        SectionModificationType::TempoExplicit { tempo } => {
+          if *tempo != self.tempo {
+            self.tempo = *tempo;
+            self
+              .pending_fields
+              .push(format!("Q:{}", AbcConverter::tempo_text(tempo)));
+          }
+        }
+        SectionModificationType::TempoImplicit { tempo } => {
+          self
+            .pending_fields
+            .push(format!("Q:\"{}\"", tempo_marking_text(tempo.marking)));
+        }
+        SectionModificationType::Accelerando => self.prefix.push_str("\"^accel.\""),
+        SectionModificationType::ATempo => self.prefix.push_str("\"^a tempo\""),
+        SectionModificationType::TempoPrimo => self.prefix.push_str("\"^Tempo I\""),
+        SectionModificationType::Rallentando => self.prefix.push_str("\"^rall.\""),
+        SectionModificationType::Ritardando => self.prefix.push_str("\"^rit.\""),
+        SectionModificationType::Ritenuto => self.prefix.push_str("\"^riten.\""),
+        SectionModificationType::Stringendo => self.prefix.push_str("\"^string.\""),
+      }
+    }
+    if repeat {
+      self.pending_start_repeat = true;
+    }
+    for content in section.iter() {
+      match content {
+        SectionContent::Staff(staff) if staff.get_name() == staff_name => self.write_staff(staff),
+        SectionContent::Section(section) => self.write_section(section, staff_name),
+        SectionContent::Staff(_) => (),
+      }
+    }
+    if repeat {
+      self.pending_end_repeat = true;
+    }
+  }
+
+  fn finish(mut self) -> String {
+    self.close_grace();
+    self.flush_overlays();
+    self.separate();
+    self.output.push_str(if self.pending_end_repeat { ":|" } else { "|]" });
+    self.output
+  }
+}
+
+/// Converter between AMM compositions and ABC notation.
+///
+/// Only the first tune in ABC data is imported. Each ABC voice becomes a
+/// staff, and voices sharing the same `name=` property are grouped into a
+/// single part. Lyrics and chord symbols have no AMM equivalent and are
+/// ignored.
+pub struct AbcConverter;
+
+impl AbcConverter {
+  fn strip_comment(line: &str) -> &str {
+    line
+      .char_indices()
+      .find(|(idx, ch)| *ch == '%' && !line[..*idx].ends_with('\\'))
+      .map_or(line, |(idx, _)| &line[..idx])
+  }
+
+  fn split_field_line(line: &str, in_header: bool) -> Option<(char, &str)> {
+    let mut chars = line.chars();
+    let letter = chars
+      .next()
+      .filter(|letter| (in_header && letter.is_ascii_alphabetic()) || BODY_FIELDS.contains(*letter))?;
+    (chars.next() == Some(':')).then(|| (letter, line[2..].trim()))
+  }
+
+  fn split_field(value: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for ch in value.chars() {
+      match ch {
+        '"' => {
+          quoted = !quoted;
+          token.push(ch);
+        }
+        ch if ch.is_whitespace() && !quoted => {
+          if !token.is_empty() {
+            tokens.push(core::mem::take(&mut token));
+          }
+        }
+        ch => token.push(ch),
+      }
+    }
+    if !token.is_empty() {
+      tokens.push(token);
+    }
+    tokens
+  }
+
+  fn read_number(chars: &[char], idx: &mut usize) -> Option<u64> {
+    let start = *idx;
+    while chars.get(*idx).is_some_and(char::is_ascii_digit) {
+      *idx += 1;
+    }
+    chars[start..*idx].iter().collect::<String>().parse().ok()
+  }
+
+  fn read_length(chars: &[char], idx: &mut usize) -> Fraction {
+    let numerator = Self::read_number(chars, idx).unwrap_or(1);
+    let mut denominator: u64 = 1;
+    while chars.get(*idx) == Some(&'/') {
+      *idx += 1;
+      denominator = denominator.saturating_mul(Self::read_number(chars, idx).unwrap_or(2).max(1));
+    }
+    Fraction::new(numerator, denominator)
+  }
+
+  fn read_delimited(chars: &[char], idx: &mut usize) -> Option<String> {
+    // Unterminated or multi-word decorations are most likely deprecated line-break symbols
+    let delimiter = chars[*idx];
+    let text = chars[*idx + 1..]
+      .iter()
+      .position(|ch| *ch == delimiter)
+      .map(|length| chars[*idx + 1..*idx + 1 + length].iter().collect::<String>())
+      .filter(|text| delimiter == '"' || !text.contains(|ch: char| ch.is_whitespace() || ch == '|'));
+    *idx += text.as_ref().map_or(1, |text| text.chars().count() + 2);
+    text
+  }
+
+  fn read_ending(chars: &[char], idx: &mut usize) -> Option<String> {
+    let mut numbers = Vec::new();
+    while let Some(first) = Self::read_number(chars, idx) {
+      let last = if chars.get(*idx) == Some(&'-') && chars.get(*idx + 1).is_some_and(char::is_ascii_digit) {
+        *idx += 1;
+        Self::read_number(chars, idx).unwrap_or(first)
+      } else {
+        first
+      };
+      numbers.extend((first..=last.min(first.saturating_add(16))).filter(|number| (1..=255).contains(number)));
+      if chars.get(*idx) == Some(&',') && chars.get(*idx + 1).is_some_and(char::is_ascii_digit) {
+        *idx += 1;
+      } else {
+        break;
+      }
+    }
+    (!numbers.is_empty()).then(|| numbers.iter().map(ToString::to_string).collect::<Vec<_>>().join(","))
+  }
+
+  fn read_note(chars: &[char], idx: &mut usize) -> Option<AbcNote> {
+    let start = *idx;
+    let accidental = match (chars.get(*idx), chars.get(*idx + 1)) {
+      (Some('^'), Some('^')) => Some("double-sharp"),
+      (Some('_'), Some('_')) => Some("flat-flat"),
+      (Some('^'), _) => Some("sharp"),
+      (Some('_'), _) => Some("flat"),
+      (Some('='), _) => Some("natural"),
+      _ => None,
+    };
+    if let Some(accidental) = accidental {
+      *idx += if accidental.len() > 7 { 2 } else { 1 };
+      // Microtonal accidentals are approximated by their nearest standard accidental
+      while chars.get(*idx).is_some_and(|ch| ch.is_ascii_digit() || *ch == '/') {
+        *idx += 1;
+      }
+    }
+    let Some(letter) = chars
+      .get(*idx)
+      .copied()
+      .filter(char::is_ascii_alphabetic)
+      .filter(|letter| matches!(letter, 'A'..='G' | 'a'..='g'))
+    else {
+      *idx = start + 1;
+      return None;
+    };
+    *idx += 1;
+    let mut octave = if letter.is_ascii_lowercase() { 5 } else { 4 };
+    while let Some(mark) = chars.get(*idx) {
+      match mark {
+        '\'' => octave += 1,
+        ',' => octave -= 1,
+        _ => break,
+      }
+      *idx += 1;
+    }
+    let length = Self::read_length(chars, idx);
+    let tie = chars.get(*idx) == Some(&'-');
+    if tie {
+      *idx += 1;
+    }
+    Some(AbcNote {
+      pitch: Some((letter.to_ascii_uppercase(), octave.clamp(0, 9), accidental)),
+      length,
+      tie,
+    })
+  }
+
+  fn key_element(fifths: i32, minor: bool) -> XmlElement {
+    xml_element(
+      "key",
+      &[],
+      vec![
+        xml_text_element("fifths", fifths.clamp(-7, 7)),
+        xml_text_element("mode", if minor { "minor" } else { "major" }),
+      ],
+    )
+  }
+
+  fn mode_offset(mode: &str) -> Option<(i32, bool)> {
+    // Modal keys retain their key signature but are otherwise treated as major keys
+    match mode.get(..3).unwrap_or(mode) {
+      "" | "maj" | "ion" => Some((0, false)),
+      "m" | "mi" | "min" | "aeo" => Some((-3, true)),
+      "mix" => Some((-1, false)),
+      "dor" => Some((-2, false)),
+      "phr" => Some((-4, false)),
+      "loc" => Some((-5, false)),
+      "lyd" => Some((1, false)),
+      _ => None,
+    }
+  }
+
+  fn clef_element(name: &str) -> Option<XmlElement> {
+    let name = name.trim_matches('"').to_ascii_lowercase();
+    let (name, octave_change) = [("+8", 1), ("-8", -1), ("+15", 2), ("-15", -2)]
+      .iter()
+      .find_map(|(suffix, octave_change)| name.strip_suffix(suffix).map(|name| (name, *octave_change)))
+      .unwrap_or((name.as_str(), 0));
+    let (sign, line) = match name {
+      "treble" | "g" | "g2" => ("G", 2),
+      "bass" | "bass4" | "f" | "f4" => ("F", 4),
+      "bass3" | "baritone" | "f3" => ("F", 3),
+      "bass5" | "subbass" | "f5" => ("F", 5),
+      "alto" | "alto3" | "c" | "c3" => ("C", 3),
+      "tenor" | "alto4" | "c4" => ("C", 4),
+      "alto1" | "soprano" | "c1" => ("C", 1),
+      "alto2" | "mezzosoprano" | "c2" => ("C", 2),
+      _ => return None,
+    };
+    let mut elements = vec![xml_text_element("sign", sign), xml_text_element("line", line)];
+    if octave_change != 0 {
+      elements.push(xml_text_element("clef-octave-change", octave_change));
+    }
+    Some(xml_element("clef", &[], elements))
+  }
+
+  fn parse_key(value: &str) -> (Option<XmlElement>, Option<XmlElement>) {
+    let tokens = Self::split_field(value);
+    let mut tokens = tokens.iter().map(String::as_str).peekable();
+    let key = match tokens.peek().copied() {
+      Some("none" | "HP") => {
+        tokens.next();
+        Some(Self::key_element(0, false))
+      }
+      Some("Hp") => {
+        tokens.next();
+        Some(Self::key_element(2, false))
+      }
+      Some(tonic) if tonic.starts_with(|letter: char| matches!(letter, 'A'..='G')) => {
+        tokens.next();
+        let mut fifths = match tonic.as_bytes()[0] {
+          b'F' => -1,
+          b'G' => 1,
+          b'D' => 2,
+          b'A' => 3,
+          b'E' => 4,
+          b'B' => 5,
+          _ => 0,
+        };
+        let mut mode = &tonic[1..];
+        if let Some(rest) = mode.strip_prefix('#') {
+          fifths += 7;
+          mode = rest;
+        } else if let Some(rest) = mode.strip_prefix('b') {
+          fifths -= 7;
+          mode = rest;
+        }
+        let mut mode = mode.to_ascii_lowercase();
+        if mode.is_empty() {
+          if let Some(next) = tokens
+            .peek()
+            .map(|token| token.to_ascii_lowercase())
+            .filter(|token| !token.is_empty() && Self::mode_offset(token).is_some())
+          {
+            mode = next;
+            tokens.next();
+          }
+        }
+        let (offset, minor) = Self::mode_offset(&mode).unwrap_or((0, false));
+        Some(Self::key_element(fifths + offset, minor))
+      }
+      _ => None,
+    };
+    let clef = tokens.find_map(|token| match token.split_once('=') {
+      Some(("clef", clef)) => Self::clef_element(clef),
+      Some(_) => None,
+      None => Self::clef_element(token),
+    });
+    (key, clef)
+  }
+
+  fn parse_meter(value: &str) -> Option<(XmlElement, Option<Fraction>, bool)> {
+    let time = |numerator: u64, denominator: u64, symbol: &[(&str, &str)]| {
+      xml_element(
+        "time",
+        symbol,
+        vec![
+          xml_text_element("beats", numerator),
+          xml_text_element("beat-type", denominator),
+        ],
+      )
+    };
+    match value.trim() {
+      "" | "none" => Some((
+        xml_element("time", &[], vec![xml_element("senza-misura", &[], Vec::new())]),
+        None,
+        false,
+      )),
+      "C" => Some((time(4, 4, &[("symbol", "common")]), Some(Fraction::ONE), false)),
+      "C|" => Some((time(2, 2, &[("symbol", "cut")]), Some(Fraction::ONE), false)),
+      meter => {
+        let (numerator, denominator) = meter.split_once('/')?;
+        let numerator = numerator
+          .trim()
+          .trim_matches(|ch| ch == '(' || ch == ')')
+          .split('+')
+          .map(|beats| beats.trim().parse::<u64>().ok())
+          .sum::<Option<u64>>()
+          .filter(|numerator| (1..=u64::from(u8::MAX)).contains(numerator))?;
+        let denominator = denominator
+          .trim()
+          .parse::<u64>()
+          .ok()
+          .filter(|denominator| (1..=u64::from(u8::MAX)).contains(denominator))?;
+        Some((
+          time(numerator, denominator, &[]),
+          Some(Fraction::new(numerator, denominator)),
+          numerator > 3 && numerator.is_multiple_of(3),
+        ))
+      }
+    }
+  }
+
+  #[allow(clippy::cast_precision_loss)]
+  fn parse_tempo(value: &str, unit: Fraction) -> Option<XmlElement> {
+    let mut text = Vec::new();
+    let mut remaining = String::new();
+    for (idx, part) in value.split('"').enumerate() {
+      if idx % 2 == 1 {
+        text.push(part.trim());
+      } else {
+        remaining.push_str(part);
+        remaining.push(' ');
+      }
+    }
+    let remaining = remaining.trim();
+    let tempo = match remaining.split_once('=') {
+      Some((beats, bpm)) => beats
+        .split_whitespace()
+        .map(|beat| {
+          if beat.starts_with(['C', 'L']) {
+            Some(unit)
+          } else {
+            Fraction::parse(beat)
+          }
+        })
+        .try_fold(Fraction::ZERO, |total, beat| beat.map(|beat| total + beat))
+        .filter(|beat| beat.numerator > 0)
+        .zip(bpm.trim().parse::<u16>().ok()),
+      None => remaining.parse::<u16>().ok().map(|bpm| (unit, bpm)),
+    };
+    let mut direction_types = text
+      .into_iter()
+      .filter(|text| !text.is_empty())
+      .map(|text| xml_text_element("words", text))
+      .collect::<Vec<_>>();
+    let mut sound = None;
+    if let Some((beat, bpm)) = tempo.filter(|(_, bpm)| *bpm > 0) {
+      if let Some((beat_unit, dots)) = beat.note_type() {
+        let mut metronome = vec![xml_text_element("beat-unit", beat_unit)];
+        metronome.extend((0..dots).map(|_| xml_element("beat-unit-dot", &[], Vec::new())));
+        metronome.push(xml_text_element("per-minute", bpm));
+        direction_types.push(xml_element("metronome", &[], metronome));
+      }
+      let quarters_per_minute = f64::from(bpm) * 4.0 * beat.numerator as f64 / beat.denominator as f64;
+      sound = Some(xml_element(
+        "sound",
+        &[("tempo", &quarters_per_minute.to_string())],
+        Vec::new(),
+      ));
+    }
+    (!direction_types.is_empty()).then(|| Self::direction(direction_types, sound, Some("above")))
+  }
+
+  fn direction(direction_types: Vec<XmlElement>, sound: Option<XmlElement>, placement: Option<&str>) -> XmlElement {
+    let mut elements = direction_types
+      .into_iter()
+      .map(|direction_type| xml_element("direction-type", &[], vec![direction_type]))
+      .collect::<Vec<_>>();
+    elements.extend(sound);
+    match placement {
+      Some(placement) => xml_element("direction", &[("placement", placement)], elements),
+      None => xml_element("direction", &[], elements),
+    }
+  }
+
+  fn transcode_decoration(name: &str) -> Option<AbcDecoration> {
+    let notation = |group: Option<&'static str>, name: &str| {
+      Some(AbcDecoration::Notation(group, xml_element(name, &[], Vec::new())))
+    };
+    let marker = |direction_type: XmlElement, sound: (&str, &str)| {
+      Some(AbcDecoration::Direction(Self::direction(
+        vec![direction_type],
+        Some(xml_element("sound", &[sound], Vec::new())),
+        Some("above"),
+      )))
+    };
+    let spanner = |name: &str, attributes: &[(&str, &str)], placement: &str| {
+      Some(AbcDecoration::Direction(Self::direction(
+        vec![xml_element(name, attributes, Vec::new())],
+        None,
+        Some(placement),
+      )))
+    };
+    match name {
+      "." | "staccato" => notation(Some("articulations"), "staccato"),
+      "L" | "accent" | ">" | "emphasis" => notation(Some("articulations"), "accent"),
+      "tenuto" => notation(Some("articulations"), "tenuto"),
+      "marcato" | "^" => notation(Some("articulations"), "strong-accent"),
+      "wedge" => notation(Some("articulations"), "staccatissimo"),
+      "breath" => notation(Some("articulations"), "breath-mark"),
+      "caesura" => notation(Some("articulations"), "caesura"),
+      "slide" => notation(Some("articulations"), "scoop"),
+      "H" | "fermata" => notation(None, "fermata"),
+      "invertedfermata" => Some(AbcDecoration::Notation(
+        None,
+        xml_element("fermata", &[("type", "inverted")], Vec::new()),
+      )),
+      "arpeggio" => notation(None, "arpeggiate"),
+      "T" | "trill" | "trill(" => notation(Some("ornaments"), "trill-mark"),
+      "M" | "lowermordent" | "mordent" => notation(Some("ornaments"), "mordent"),
+      "P" | "uppermordent" | "pralltriller" => notation(Some("ornaments"), "inverted-mordent"),
+      "~" | "roll" | "turn" | "turnx" => notation(Some("ornaments"), "turn"),
+      "invertedturn" | "invertedturnx" => notation(Some("ornaments"), "inverted-turn"),
+      "/" | "//" | "///" | "////" => Some(AbcDecoration::Notation(
+        Some("ornaments"),
+        XmlElement {
+          text: name.len().to_string(),
+          ..xml_element("tremolo", &[("type", "single")], Vec::new())
+        },
+      )),
+      "u" | "upbow" => notation(Some("technical"), "up-bow"),
+      "v" | "downbow" => notation(Some("technical"), "down-bow"),
+      "open" => notation(Some("technical"), "open-string"),
+      "thumb" => notation(Some("technical"), "thumb-position"),
+      "snap" => notation(Some("technical"), "snap-pizzicato"),
+      "+" | "plus" => notation(Some("technical"), "stopped"),
+      "0" | "1" | "2" | "3" | "4" | "5" => Some(AbcDecoration::Notation(
+        Some("technical"),
+        xml_text_element("fingering", name),
+      )),
+      "S" | "segno" => marker(xml_element("segno", &[], Vec::new()), ("segno", "segno")),
+      "O" | "coda" => marker(xml_element("coda", &[], Vec::new()), ("coda", "coda")),
+      "D.S." | "D.S.alcoda" | "D.S.alfine" | "dalsegno" => {
+        marker(xml_text_element("words", "D.S."), ("dalsegno", "segno"))
+      }
+      "D.C." | "D.C.alcoda" | "D.C.alfine" | "dacapo" => marker(xml_text_element("words", "D.C."), ("dacapo", "yes")),
+      "dacoda" => marker(xml_text_element("words", "To Coda"), ("tocoda", "coda")),
+      "fine" => marker(xml_text_element("words", "Fine"), ("fine", "yes")),
+      "<(" | "crescendo(" => spanner("wedge", &[("type", "crescendo")], "below"),
+      ">(" | "diminuendo(" | "decrescendo(" => spanner("wedge", &[("type", "diminuendo")], "below"),
+      "<)" | ">)" | "crescendo)" | "diminuendo)" | "decrescendo)" => spanner("wedge", &[("type", "stop")], "below"),
+      "8va(" => spanner("octave-shift", &[("type", "down"), ("size", "8")], "above"),
+      "8vb(" => spanner("octave-shift", &[("type", "up"), ("size", "8")], "below"),
+      "15ma(" => spanner("octave-shift", &[("type", "down"), ("size", "15")], "above"),
+      "15mb(" => spanner("octave-shift", &[("type", "up"), ("size", "15")], "below"),
+      "8va)" | "8vb)" => spanner("octave-shift", &[("type", "stop"), ("size", "8")], "above"),
+      "15ma)" | "15mb)" => spanner("octave-shift", &[("type", "stop"), ("size", "15")], "above"),
+      "ped" => spanner("pedal", &[("type", "start")], "below"),
+      "ped-up" => spanner("pedal", &[("type", "stop")], "below"),
+      dynamic if DYNAMICS.contains(&dynamic) => Some(AbcDecoration::Direction(Self::direction(
+        vec![xml_element(
+          "dynamics",
+          &[],
+          vec![xml_element(dynamic, &[], Vec::new())],
+        )],
+        None,
+        Some("below"),
+      ))),
+      _ => None,
+    }
+  }
+
+  fn load_from_abc(data: &[u8]) -> Result<Composition, String> {
+    let reader = AbcReader::parse(&String::from_utf8_lossy(data))?;
+    let mut composition =
+      MusicXmlConverter::load_from_musicxml(&ScorePartwise::deserialize(&reader.transcode_score()?)?)?;
+    for (key, value) in reader.transcode_extra_metadata() {
+      composition.add_metadata(key, &value);
+    }
+    Ok(composition)
+  }
+
+  fn fraction_text(fraction: Fraction) -> String {
+    format!("{}/{}", fraction.numerator, fraction.denominator)
+  }
+
+  fn length_text(duration: Fraction, unit: Fraction) -> String {
+    match duration / unit {
+      Fraction {
+        numerator: 1,
+        denominator: 1,
+      } => String::new(),
+      Fraction {
+        numerator,
+        denominator: 1,
+      } => numerator.to_string(),
+      Fraction {
+        numerator: 1,
+        denominator: 2,
+      } => String::from("/"),
+      Fraction {
+        numerator: 1,
+        denominator,
+      } => format!("/{denominator}"),
+      length => Self::fraction_text(length),
+    }
+  }
+
+  fn pitch_text(note: &Note) -> String {
+    let mut text = String::from(match note.accidental {
+      Accidental::Sharp => "^",
+      Accidental::DoubleSharp => "^^",
+      Accidental::Flat => "_",
+      Accidental::DoubleFlat => "__",
+      Accidental::Natural => "=",
+      Accidental::None => "",
+    });
+    let letter = match note.pitch.name {
+      PitchName::A => 'A',
+      PitchName::B => 'B',
+      PitchName::C => 'C',
+      PitchName::D => 'D',
+      PitchName::E => 'E',
+      PitchName::F => 'F',
+      PitchName::G => 'G',
+      PitchName::Rest => return String::from("z"),
+    };
+    if note.pitch.octave >= 5 {
+      text.push(letter.to_ascii_lowercase());
+      text.extend((5..note.pitch.octave).map(|_| '\''));
+    } else {
+      text.push(letter);
+      text.extend((note.pitch.octave..4).map(|_| ','));
+    }
+    text
+  }
+
+  fn key_text(key: &Key) -> String {
+    let idx = usize::try_from(key.fifths() + 7).unwrap_or_default().min(14);
+    match key.mode {
+      KeyMode::Major => String::from(MAJOR_TONICS[idx]),
+      KeyMode::Minor => format!("{}m", MINOR_TONICS[idx]),
+    }
+  }
+
+  fn meter_text(time_signature: &TimeSignature) -> (String, Option<Fraction>) {
+    match time_signature.signature {
+      TimeSignatureType::CommonTime => (String::from("C"), Some(Fraction::ONE)),
+      TimeSignatureType::CutTime => (String::from("C|"), Some(Fraction::ONE)),
+      TimeSignatureType::None => (String::from("none"), None),
+      TimeSignatureType::Explicit => (
+        format!("{}/{}", time_signature.numerator, time_signature.denominator),
+        Some(Fraction::new(
+          u64::from(time_signature.numerator),
+          u64::from(time_signature.denominator),
+        )),
+      ),
+    }
+  }
+
+  fn clef_text(clef_type: ClefType) -> &'static str {
+    match clef_type {
+      ClefType::Treble | ClefType::FrenchViolin => "treble",
+      ClefType::Bass => "bass",
+      ClefType::Baritone => "bass3",
+      ClefType::Subbass => "bass5",
+      ClefType::Alto => "alto",
+      ClefType::Tenor => "tenor",
+      ClefType::Soprano => "alto1",
+      ClefType::MezzoSoprano => "alto2",
+    }
+  }
+
+  fn tempo_text(tempo: &Tempo) -> String {
+    format!(
+      "{}={}",
+      Self::fraction_text(Fraction::from_duration(&tempo.base_note)),
+      tempo.beats_per_minute
+    )
+  }
+
+  fn dynamic_text(dynamic: &Dynamic) -> Option<&'static str> {
+    match dynamic {
+      Dynamic::Piano(1) => Some("p"),
+      Dynamic::Piano(2) => Some("pp"),
+      Dynamic::Piano(3) => Some("ppp"),
+      Dynamic::Piano(4) => Some("pppp"),
+      Dynamic::Forte(1) => Some("f"),
+      Dynamic::Forte(2) => Some("ff"),
+      Dynamic::Forte(3) => Some("fff"),
+      Dynamic::Forte(4) => Some("ffff"),
+      Dynamic::MezzoPiano => Some("mp"),
+      Dynamic::MezzoForte => Some("mf"),
+      Dynamic::FortePiano => Some("fp"),
+      Dynamic::Forzando => Some("fz"),
+      Dynamic::Rinforzato => Some("rfz"),
+      Dynamic::Sforzando(1) => Some("sf"),
+      Dynamic::SforzandoPiano(1) => Some("sfp"),
+      Dynamic::Sforzato(1) => Some("sfz"),
+      _ => None,
+    }
+  }
+
+  fn note_decoration(modification: &NoteModificationType) -> Option<&'static str> {
+    match modification {
+      NoteModificationType::Accent => Some("!accent!"),
+      NoteModificationType::DownBow => Some("!downbow!"),
+      NoteModificationType::Fermata => Some("!fermata!"),
+      NoteModificationType::Fingering { finger, substitution } => match finger.or(*substitution) {
+        Some(0) => Some("!0!"),
+        Some(1) => Some("!1!"),
+        Some(2) => Some("!2!"),
+        Some(3) => Some("!3!"),
+        Some(4) => Some("!4!"),
+        Some(5) => Some("!5!"),
+        _ => None,
+      },
+      NoteModificationType::Marcato => Some("!marcato!"),
+      NoteModificationType::Mordent { upper: true } => Some("!uppermordent!"),
+      NoteModificationType::Mordent { upper: false } => Some("!lowermordent!"),
+      NoteModificationType::Open => Some("!open!"),
+      NoteModificationType::Scoop => Some("!slide!"),
+      NoteModificationType::Sforzando => Some("!sfz!"),
+      NoteModificationType::Staccato => Some("."),
+      NoteModificationType::Staccatissimo => Some("!wedge!"),
+      NoteModificationType::Stopped => Some("!+!"),
+      NoteModificationType::Tenuto => Some("!tenuto!"),
+      NoteModificationType::ThumbPosition => Some("!thumb!"),
+      NoteModificationType::Tremolo { relative_speed } => match relative_speed {
+        1 => Some("!/!"),
+        2 => Some("!//!"),
+        3 => Some("!///!"),
+        4 => Some("!////!"),
+        _ => None,
+      },
+      NoteModificationType::Trill { .. } => Some("!trill!"),
+      NoteModificationType::Turn { upper: true, .. } => Some("!turn!"),
+      NoteModificationType::Turn { upper: false, .. } => Some("!invertedturn!"),
+      NoteModificationType::UpBow => Some("!upbow!"),
+      NoteModificationType::Dynamic { dynamic } => match Self::dynamic_text(dynamic) {
+        Some("p") => Some("!p!"),
+        Some("pp") => Some("!pp!"),
+        Some("ppp") => Some("!ppp!"),
+        Some("pppp") => Some("!pppp!"),
+        Some("f") => Some("!f!"),
+        Some("ff") => Some("!ff!"),
+        Some("fff") => Some("!fff!"),
+        Some("ffff") => Some("!ffff!"),
+        Some("mp") => Some("!mp!"),
+        Some("mf") => Some("!mf!"),
+        Some("fp") => Some("!fp!"),
+        Some("fz") => Some("!fz!"),
+        Some("rfz") => Some("!rfz!"),
+        Some("sf") => Some("!sf!"),
+        Some("sfp") => Some("!sfp!"),
+        Some("sfz") => Some("!sfz!"),
+        _ => None,
+      },
+      _ => None,
+    }
+  }
+
+  fn chord_decoration(modification: &ChordModificationType) -> Option<&'static str> {
+    match modification {
+      ChordModificationType::Accent => Some("!accent!"),
+      ChordModificationType::Arpeggiate => Some("!arpeggio!"),
+      ChordModificationType::DownBow => Some("!downbow!"),
+      ChordModificationType::Dynamic { dynamic } => {
+        Self::note_decoration(&NoteModificationType::Dynamic { dynamic: *dynamic })
+      }
+      ChordModificationType::Fermata => Some("!fermata!"),
+      ChordModificationType::Marcato => Some("!marcato!"),
+      ChordModificationType::Open => Some("!open!"),
+      ChordModificationType::Sforzando => Some("!sfz!"),
+      ChordModificationType::Staccato => Some("."),
+      ChordModificationType::Staccatissimo => Some("!wedge!"),
+      ChordModificationType::Tenuto => Some("!tenuto!"),
+      ChordModificationType::UpBow => Some("!upbow!"),
+      _ => None,
+    }
+  }
+
+  fn save_to_abc(composition: &Composition) -> String {
+    // Write the tune header
+    let metadata = composition.get_metadata();
+    let mut abc = format!(
+      "X:{}\nT:{}\n",
+      metadata.get("reference_number").map_or("1", String::as_str),
+      composition.get_title()
+    );
+    if let Some(movement_title) = metadata.get("movement_title") {
+      abc.push_str(&format!("T:{movement_title}\n"));
+    }
+    for composer in composition.get_composers() {
+      abc.push_str(&format!("C:{composer}\n"));
+    }
+    let creators = composition
+      .get_lyricists()
+      .iter()
+      .map(|name| (CREATOR_NOTES[0].1, name))
+      .chain(
+        composition
+          .get_arrangers()
+          .iter()
+          .map(|name| (CREATOR_NOTES[1].1, name)),
+      )
+      .chain(
+        composition
+          .get_publisher()
+          .iter()
+          .map(|name| (CREATOR_NOTES[2].1, name)),
+      );
+    for (prefix, name) in creators {
+      abc.push_str(&format!("N:{prefix}{name}\n"));
+    }
+    for (letter, key) in METADATA_FIELDS {
+      for line in metadata.get(key).iter().flat_map(|value| value.lines()) {
+        abc.push_str(&format!("{letter}:{line}\n"));
+      }
+    }
+    if let Some(copyright) = composition.get_copyright() {
+      abc.push_str(&format!("%%abc-copyright {copyright}\n"));
+    }
+    let unit = Fraction::new(1, 8);
+    abc.push_str(&format!(
+      "M:{}\nL:{}\nQ:{}\nK:{}\n",
+      Self::meter_text(composition.get_starting_time_signature()).0,
+      Self::fraction_text(unit),
+      Self::tempo_text(composition.get_tempo()),
+      Self::key_text(composition.get_starting_key())
+    ));
+
+    // Write every staff of every part as a separate voice
+    let mut voice_number = 0;
+    for part in composition.iter() {
+      for staff_name in part.get_staff_names() {
+        voice_number += 1;
+        let mut writer = AbcWriter::new(composition, unit);
+        for PartContent::Section(section) in part.iter() {
+          writer.write_section(section, &staff_name);
+        }
+        abc.push_str(&format!(
+          "V:{voice_number} name=\"{}\"\n{}\n",
+          part.get_name().replace('"', "'"),
+          writer.finish()
+        ));
+      }
+    }
+    abc
+  }
+}
+
+impl Load for AbcConverter {
+  // ABC tunes are transcoded into an equivalent MusicXML document which is
+  // then loaded by the MusicXML converter, so both formats share the same structure
+  fn load_bytes(data: &[u8]) -> Result<Composition, AmmError> {
+    AbcConverter::load_from_abc(data).map_err(AmmError::from)
+  }
+}
+
+impl Store for AbcConverter {
+  fn save_bytes(composition: &Composition) -> Result<Vec<u8>, AmmError> {
+    Ok(AbcConverter::save_to_abc(composition).into_bytes())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::note::{Duration, DurationType};
+  use crate::storage::Storage;
+
+  const TUNE: &str = "%abc-2.1\n\
+    X:42\n\
+    T:The Kesh\n\
+    T:Jig Version\n\
+    C:Trad.\n\
+    O:Ireland\n\
+    R:jig\n\
+    N:Arranged by A. Player\n\
+    %%abc-copyright Public Domain\n\
+    M:6/8\n\
+    L:1/8\n\
+    Q:3/8=120\n\
+    V:1 name=\"Whistle\"\n\
+    V:2 name=\"Guitar\" clef=bass\n\
+    K:G\n\
+    V:1\n\
+    |:\"^Lively\"!mf!GAG GAB|(3ABA (B~e)d2|{/g}[DGB]2d- d>ed|1 D3-D3:|2 DED (3DEDz|]\n\
+    V:2\n\
+    |:G,3 D,3|A,3 A,3 & C3 E3|G,3 x3|1 D,6:|2 Z|]\n\
+    \n\
+    X:43\n\
+    T:Ignored Second Tune\n\
+    K:C\n\
+    CDEF|\n";
+
+  #[test]
+  fn test_load_abc() {
+    let composition = AbcConverter::load_bytes(TUNE.as_bytes()).unwrap();
+    assert_eq!(composition.get_title(), "The Kesh");
+    assert_eq!(composition.get_composers(), ["Trad."]);
+    assert_eq!(composition.get_arrangers(), ["A. Player"]);
+    assert_eq!(composition.get_copyright().as_deref(), Some("Public Domain"));
+    assert_eq!(composition.get_metadata().get("reference_number").unwrap(), "42");
+    assert_eq!(composition.get_metadata().get("movement_title").unwrap(), "Jig Version");
+    assert_eq!(composition.get_metadata().get("origin").unwrap(), "Ireland");
+    assert_eq!(composition.get_metadata().get("rhythm").unwrap(), "jig");
+    assert_eq!(composition.get_part_names(), ["Whistle", "Guitar"]);
+    assert_eq!(
+      *composition.get_starting_key(),
+      Key::from_fifths(1, Some(KeyMode::Major))
+    );
+    assert_eq!(
+      *composition.get_starting_time_signature(),
+      TimeSignature::new_explicit(6, 8)
+    );
+    assert_eq!(composition.get_tempo().beats_per_minute, 120);
+    assert_eq!(
+      composition.get_tempo().base_note,
+      Duration::new(DurationType::Quarter, 1)
+    );
+    assert!(composition.iter_timeslices().count() > 0);
+    assert!(AbcConverter::load_bytes(b"X:1\nT:No Key\nCDEF|").is_err());
+    assert!(AbcConverter::load_bytes(b"X:1\nT:No Notes\nK:C\n").is_err());
+  }
+
+  #[test]
+  fn test_invalid_abc() {
+    assert!(AbcConverter::load_bytes(b"X:1\nK:C\n[ABC\n").is_err());
+    assert!(AbcConverter::load_bytes(b"X:1\nL:1/4294967295\nK:C\nCDEF|\n").is_err());
+  }
+
+  #[test]
+  fn test_export_import_example() {
+    let composition = Storage::MusicXML.load("examples/Hymn_to_Freedom.mxl").unwrap();
+    let abc = AbcConverter::save_to_abc(&composition);
+    let reader = AbcReader::parse(&abc).unwrap();
+    for (_, voice) in &reader.voices {
+      let measure_length = voice.context.measure_length.unwrap();
+      for measure in voice.measures.iter().filter(|measure| measure.has_content()) {
+        // Every voice within a measure, whether main or overlaid, must fit within the time signature
+        let mut voice_lengths = vec![Fraction::ZERO];
+        for item in &measure.items {
+          match item {
+            AbcItem::Timed { element, actual, .. } if !element.has_child("chord") => {
+              *voice_lengths.last_mut().unwrap() = *voice_lengths.last().unwrap() + *actual;
+            }
+            AbcItem::Backup(_) => voice_lengths.push(Fraction::ZERO),
+            _ => (),
+          }
+        }
+        assert_eq!(voice_lengths[0], measure_length);
+        assert!(voice_lengths.iter().all(|length| *length <= measure_length));
+      }
+    }
+    let reimported = AbcConverter::load_bytes(abc.as_bytes()).unwrap();
+    assert_eq!(reimported.get_part_names(), ["Piano"]);
+    let sounding_pitches = |composition: &Composition| {
+      let mut pitches = composition
+        .get_part_by_name("Piano")
+        .unwrap()
+        .iter_timeslices()
+        .flat_map(|timeslice| timeslice.content)
+        .filter(|content| !content.note.is_rest())
+        .map(|content| (content.note.pitch.octave, content.note.pitch.name as u8))
+        .collect::<Vec<_>>();
+      pitches.sort_unstable();
+      pitches
+    };
+    assert_eq!(sounding_pitches(&reimported), sounding_pitches(&composition));
+  }
+
+  #[test]
+  fn test_parse_abc_fields() {
+    let (key, _) = AbcConverter::parse_key("F#m");
+    assert_eq!(key.unwrap().child_text("fifths"), Some("3"));
+    let (key, clef) = AbcConverter::parse_key("A dorian clef=bass");
+    assert_eq!(key.unwrap().child_text("fifths"), Some("1"));
+    assert_eq!(clef.unwrap().child_text("sign"), Some("F"));
+    let (key, clef) = AbcConverter::parse_key("Bbmix treble-8");
+    assert_eq!(key.unwrap().child_text("fifths"), Some("-3"));
+    assert_eq!(clef.unwrap().child_text("clef-octave-change"), Some("-1"));
+    let (_, measure_length, compound) = AbcConverter::parse_meter("(2+3+2)/8").unwrap();
+    assert_eq!(measure_length, Some(Fraction::new(7, 8)));
+    assert!(!compound);
+    assert!(AbcConverter::parse_meter("C|").is_some());
+    let chars = "3/2 // 1-3,5".chars().collect::<Vec<_>>();
+    let mut idx = 0;
+    assert_eq!(AbcConverter::read_length(&chars, &mut idx), Fraction::new(3, 2));
+    idx += 1;
+    assert_eq!(AbcConverter::read_length(&chars, &mut idx), Fraction::new(1, 4));
+    idx += 1;
+    assert_eq!(AbcConverter::read_ending(&chars, &mut idx).as_deref(), Some("1,2,3,5"));
+    assert_eq!(Fraction::new(3, 8).note_type(), Some(("quarter", 1)));
+    assert_eq!(Fraction::new(5, 8).note_type(), None);
+  }
+
+  #[test]
+  fn test_save_abc() {
+    let composition = AbcConverter::load_bytes(TUNE.as_bytes()).unwrap();
+    let abc = AbcConverter::save_to_abc(&composition);
+    assert!(abc.starts_with("X:42\nT:The Kesh\nT:Jig Version\nC:Trad.\nN:Arranged by A. Player\n"));
+    assert!(abc.contains("M:6/8\nL:1/8\nQ:3/8=120\nK:G\n"));
+    assert!(abc.contains("V:1 name=\"Whistle\"\n"));
+    assert!(abc.contains("{/g}[D2G2B2] d- d3/2 e/ d |1 D3- D3 :|"));
+    let reloaded = AbcConverter::load_bytes(abc.as_bytes()).unwrap();
+    assert_eq!(reloaded.get_title(), composition.get_title());
+    assert_eq!(reloaded.get_part_names(), composition.get_part_names());
+    assert_eq!(reloaded.get_metadata(), composition.get_metadata());
+    assert_eq!(reloaded.get_starting_key(), composition.get_starting_key());
+    assert_eq!(reloaded.get_tempo(), composition.get_tempo());
+    assert_eq!(
+      reloaded.iter_timeslices().count(),
+      composition.iter_timeslices().count()
+    );
+  }
+}
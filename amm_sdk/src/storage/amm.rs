@@ -1,45 +1,311 @@
 use super::{Load, Store};
 use crate::Composition;
-use alloc::string::String;
-use amm_internal::{JsonDeserializer, JsonSerializer};
-use std::fs;
+use alloc::collections::BTreeMap;
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use amm_internal::amm_prelude::{json_schema, JsonReader, JsonSchema};
+#[cfg(feature = "serde")]
+use amm_internal::serde_support::{self, SerdeData};
+use amm_internal::{
+  AmmError, AmmErrorKind, BinaryDeserializer, BinarySerializer, JsonDeserializer, JsonOptions, JsonSerializer,
+  JsonValue,
+};
+use core::fmt;
 
+const AMM_FORMAT_VERSION: u32 = 2;
+const AMM_BINARY_MAGIC: &[u8; 4] = b"AMMB";
+const AMM_BINARY_VERSION: u64 = 1;
+
+/// Upgrades an AMM document from one version of the format to the next.
+type AmmMigration = fn(&str) -> Result<String, AmmError>;
+
+/// Native AMM JSON documents.
+///
+/// Every document starts with a `_version` field holding the version of the
+/// format it was written in. Documents written in an older version are
+/// upgraded one version at a time by the registered migrations before being
+/// loaded, while fields that are not part of the current model, such as those
+/// written by a newer version of the SDK, are ignored.
 pub struct AmmStorage;
 
+/// Writer that inserts the version header right after the opening brace of
+/// the document written through it.
+struct VersionHeader<'a> {
+  out: &'a mut dyn fmt::Write,
+  header: Option<String>,
+}
+
+impl fmt::Write for VersionHeader<'_> {
+  fn write_str(&mut self, text: &str) -> fmt::Result {
+    match self.header.take() {
+      Some(header) if !text.is_empty() => {
+        self.out.write_str(&text[..1])?;
+        self.out.write_str(&header)?;
+        self.out.write_str(&text[1..])
+      }
+      header => {
+        self.header = header;
+        self.out.write_str(text)
+      }
+    }
+  }
+}
+
+/// Compact binary encoding of an AMM composition.
+///
+/// The encoding starts with the `AMMB` magic bytes, followed by the format
+/// version and the length of the payload, each as a varint. The payload holds
+/// every field of the composition in declaration order, with integers stored
+/// as varints, enum variants as their index, and strings and collections
+/// prefixed by their length, so equal compositions always encode to the same
+/// bytes.
+pub struct AmmBinaryStorage;
+
 impl AmmStorage {
-  fn load_from_amm(data: &[u8]) -> Result<Composition, String> {
-    let json = core::str::from_utf8(data).map_err(|err| err.to_string())?;
-    Composition::deserialize_json(json)
+  /// Upgrades from each older version of the format to the next one, indexed
+  /// by the version being upgraded from.
+  const MIGRATIONS: [AmmMigration; AMM_FORMAT_VERSION as usize] = [Self::migrate_unversioned, Self::migrate_unescaped];
+
+  fn format_version(json: &str) -> Result<u32, AmmError> {
+    // Only the top-level fields are read so that nested objects cannot affect the result, and the search stops at
+    // the first malformed field since older documents may hold strings that are only repaired by their migration
+    let mut reader = JsonReader::new(json);
+    if reader.begin_object().is_err() {
+      return Ok(0);
+    }
+    while let Ok(Some(key)) = reader.next_key() {
+      if key == "_version" {
+        let value = reader.read_scalar().map_err(|err| err.in_field("_version"))?;
+        return value.parse().map_err(|_| {
+          AmmError::new(
+            AmmErrorKind::InvalidValue,
+            format!("Invalid AMM format version: {value}"),
+          )
+          .within(json, value)
+          .in_field("_version")
+        });
+      }
+      if reader.skip_value().is_err() {
+        break;
+      }
+    }
+    Ok(0)
+  }
+
+  fn with_version(json: &str, version: u32) -> Result<String, AmmError> {
+    let fields = json
+      .trim_start()
+      .strip_prefix('{')
+      .ok_or_else(|| AmmError::new(AmmErrorKind::Syntax, "AMM document is not a JSON object").at(0))?;
+    let fields = match fields.trim_start().strip_prefix("\"_version\":") {
+      Some(fields) => fields.split_once(',').map_or("}", |(_, fields)| fields),
+      None => fields,
+    };
+    Ok(format!("{{\"_version\":{version},{fields}"))
+  }
+
+  fn migrate_unversioned(json: &str) -> Result<String, AmmError> {
+    // Documents written before the format was versioned share the layout of version 1
+    Self::with_version(json, 1)
+  }
+
+  fn migrate_unescaped(json: &str) -> Result<String, AmmError> {
+    // Strings were written verbatim before version 2, so quotes within them were left unescaped
+    let (mut escaped, mut in_string) = (String::with_capacity(json.len()), false);
+    for (idx, ch) in json.char_indices() {
+      match ch {
+        '"' if in_string && !Self::closes_string(&json[(idx + 1)..]) => escaped.push_str("\\\""),
+        '"' => {
+          in_string = !in_string;
+          escaped.push(ch);
+        }
+        '\\' if in_string => escaped.push_str("\\\\"),
+        ch if in_string && u32::from(ch) < 0x20 => escaped.push_str(&format!("\\u{:04x}", u32::from(ch))),
+        ch => escaped.push(ch),
+      }
+    }
+    Self::with_version(&escaped, 2)
   }
 
-  fn save_to_amm(composition: &Composition) -> String {
-    composition.serialize_json()
+  /// Returns whether a quote followed by `rest` ends the string it appears in
+  /// within the compact layout written before version 2, where the closing
+  /// quote of a string is always followed by a separator and the next key or
+  /// value, or by the end of an array or object.
+  ///
+  /// Strings that themselves contain a quote followed by such a sequence, like
+  /// `a",1`, cannot be told apart from their end and are cut short.
+  fn closes_string(rest: &str) -> bool {
+    let mut chars = rest.chars();
+    match chars.next() {
+      None | Some(':' | ']' | '}') => true,
+      Some(',') => chars
+        .next()
+        .is_none_or(|next| matches!(next, '"' | '{' | '[' | '-' | '0'..='9' | 't' | 'f' | 'n')),
+      _ => false,
+    }
+  }
+
+  fn load_from_amm(data: &[u8]) -> Result<Composition, AmmError> {
+    let json = core::str::from_utf8(data)
+      .map_err(|err| AmmError::new(AmmErrorKind::Syntax, err.to_string()).at(err.valid_up_to()))?;
+    let mut json = Cow::Borrowed(json);
+    let mut version = Self::format_version(&json)?;
+    while let Some(migration) = Self::MIGRATIONS.get(version as usize) {
+      json = Cow::Owned(migration(&json)?);
+      version += 1;
+    }
+    // Offsets of documents upgraded by a migration refer to the upgraded document
+    Composition::deserialize_json(&json)
+  }
+
+  pub(super) fn save_to_amm(composition: &Composition, options: &JsonOptions) -> String {
+    let mut amm = String::new();
+    // Writing into a string cannot fail
+    let _ = Self::write_amm(&mut amm, composition, options);
+    amm
+  }
+
+  pub(super) fn write_amm(out: &mut dyn fmt::Write, composition: &Composition, options: &JsonOptions) -> fmt::Result {
+    // The version header always leads the document, even when the keys are sorted
+    let mut header = String::new();
+    let colon = if options.indent == 0 { ":" } else { ": " };
+    options.nested().write_line_break(&mut header)?;
+    header += &format!("\"_version\"{colon}{AMM_FORMAT_VERSION},");
+    composition.write_json(
+      &mut VersionHeader {
+        out,
+        header: Some(header),
+      },
+      options,
+    )
+  }
+  pub(super) fn json_schema() -> JsonValue {
+    // Documents hold every field of the composition along with the leading format version
+    let mut definitions = BTreeMap::new();
+    let composition = Composition::json_schema(&mut definitions);
+    let version = JsonValue::Object(vec![(
+      String::from("const"),
+      JsonValue::Number(f64::from(AMM_FORMAT_VERSION)),
+    )]);
+    json_schema::document(
+      json_schema::all_of([composition, json_schema::object([("_version", version)], ["_version"])]),
+      definitions,
+    )
   }
 }
 
-impl Load for AmmStorage {
-  fn load(path: &str) -> Result<Composition, String> {
-    let data = fs::read(path).map_err(|err| err.to_string())?;
-    AmmStorage::load_from_amm(data.as_slice())
+impl AmmBinaryStorage {
+  pub(crate) fn is_amm_binary(data: &[u8]) -> bool {
+    data.starts_with(AMM_BINARY_MAGIC)
+  }
+
+  fn load_from_amm_binary(data: &[u8]) -> Result<Composition, AmmError> {
+    let total = data.len();
+    let mut data = data
+      .strip_prefix(AMM_BINARY_MAGIC)
+      .ok_or_else(|| AmmError::new(AmmErrorKind::UnsupportedFormat, "Missing binary AMM header").at(0))?;
+    let version = u64::deserialize_binary(&mut data).map_err(|err| err.at(total - data.len()))?;
+    if version != AMM_BINARY_VERSION {
+      return Err(AmmError::new(
+        AmmErrorKind::UnsupportedVersion,
+        format!("Unsupported binary AMM version {version}"),
+      ));
+    }
+    let length = usize::deserialize_binary(&mut data).map_err(|err| err.at(total - data.len()))?;
+    if data.len() != length {
+      let kind = if data.len() < length {
+        AmmErrorKind::UnexpectedEnd
+      } else {
+        AmmErrorKind::Syntax
+      };
+      return Err(AmmError::new(
+        kind,
+        format!(
+          "Binary AMM payload contains {} bytes but its header specifies {length}",
+          data.len()
+        ),
+      ));
+    }
+    let composition = Composition::deserialize_binary(&mut data).map_err(|err| err.at(total - data.len()))?;
+    if data.is_empty() {
+      Ok(composition)
+    } else {
+      Err(AmmError::new(AmmErrorKind::Syntax, "Unexpected trailing data in binary AMM payload").at(total - data.len()))
+    }
+  }
+
+  fn save_to_amm_binary(composition: &Composition) -> Vec<u8> {
+    let mut payload = Vec::new();
+    composition.serialize_binary(&mut payload);
+    let mut amm = AMM_BINARY_MAGIC.to_vec();
+    AMM_BINARY_VERSION.serialize_binary(&mut amm);
+    payload.len().serialize_binary(&mut amm);
+    amm.extend(payload);
+    amm
   }
+}
 
-  fn load_data(data: Vec<u8>) -> Result<Composition, String> {
-    AmmStorage::load_from_amm(data.as_slice())
+impl Load for AmmStorage {
+  fn load_bytes(data: &[u8]) -> Result<Composition, AmmError> {
+    AmmStorage::load_from_amm(data)
   }
 }
 
 impl Store for AmmStorage {
-  fn save(path: &str, composition: &Composition) -> Result<usize, String> {
-    let amm = AmmStorage::save_to_amm(composition);
-    fs::write(path, amm.as_bytes()).map_err(|err| err.to_string())?;
-    Ok(amm.as_bytes().len())
+  fn save_bytes(composition: &Composition) -> Result<Vec<u8>, AmmError> {
+    Ok(AmmStorage::save_to_amm(composition, &JsonOptions::default()).into_bytes())
+  }
+}
+
+impl Load for AmmBinaryStorage {
+  fn load_bytes(data: &[u8]) -> Result<Composition, AmmError> {
+    AmmBinaryStorage::load_from_amm_binary(data)
+  }
+}
+
+impl Store for AmmBinaryStorage {
+  fn save_bytes(composition: &Composition) -> Result<Vec<u8>, AmmError> {
+    Ok(AmmBinaryStorage::save_to_amm_binary(composition))
+  }
+}
+
+/// Compositions are represented in serde as complete AMM documents, including
+/// their format version, so that documents written through serde can be
+/// loaded by [`AmmStorage`] and [`AmmBinaryStorage`] and vice versa.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Composition {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+      let amm = AmmStorage::save_to_amm(self, &JsonOptions::default());
+      serde_support::serialize_json_document(&amm, serializer)
+    } else {
+      serializer.serialize_bytes(&AmmBinaryStorage::save_to_amm_binary(self))
+    }
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Composition {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    match SerdeData::read(deserializer)? {
+      SerdeData::Json(amm) => AmmStorage::load_from_amm(amm.as_bytes()),
+      SerdeData::Binary(amm) => AmmBinaryStorage::load_from_amm_binary(&amm),
+    }
+    .map_err(serde::de::Error::custom)
   }
 }
 
 #[cfg(test)]
 mod test {
   use super::*;
-  use crate::{context::*, modification::*, note::*, storage::Storage};
+  use crate::{context::*, modification::*, note::*, storage::Storage, structure::PartContent};
+
+  /// Members of an empty composition other than its type tag, title and optional fields.
+  const EMPTY_FIELDS: &str = concat!(
+    r#""composers":[],"lyricists":[],"arrangers":[],"metadata":{},"parts":[],"#,
+    r#""tempo":{"_type":"Tempo","base_note":{"_type":"Duration","value":"Quarter","dots":0},"beats_per_minute":120},"#,
+    r#""starting_key":{"_type":"Key","mode":"Major","signature":"C"},"#,
+    r#""starting_time_signature":{"_type":"TimeSignature","signature":"CommonTime","numerator":0,"denominator":0}"#
+  );
 
   #[test]
   fn test_json_serialization_direct() {
@@ -60,6 +326,7 @@ mod test {
     composition.add_metadata("TestKey2", "TestValue2");
     {
       let part = composition.add_part("Guitar");
+      part.set_tuning(Some(Tuning::new(&[64, 59, 55, 50, 45, 38], 2)));
       let section = part.add_section("Intro");
       let subsection = section.add_section("Subsection");
       let staff = subsection.add_staff("Staff1");
@@ -109,6 +376,44 @@ mod test {
       staff.add_direction(DirectionType::Dynamic {
         dynamic: Dynamic::MezzoForte,
       });
+      staff.add_direction(DirectionType::Dynamic {
+        dynamic: Dynamic::FortePiano,
+      });
+      staff.add_direction(DirectionType::Dynamic {
+        dynamic: Dynamic::Forzando,
+      });
+      staff.add_direction(DirectionType::Dynamic {
+        dynamic: Dynamic::Niente,
+      });
+      staff.add_direction(DirectionType::Dynamic {
+        dynamic: Dynamic::PianoForte,
+      });
+      staff.add_direction(DirectionType::Dynamic {
+        dynamic: Dynamic::Rinforzando,
+      });
+      staff.add_direction(DirectionType::Dynamic {
+        dynamic: Dynamic::Rinforzato,
+      });
+      staff.add_direction(DirectionType::Dynamic {
+        dynamic: Dynamic::Sforzando(2),
+      });
+      staff.add_direction(DirectionType::Dynamic {
+        dynamic: Dynamic::SforzandoPiano(2),
+      });
+      staff.add_direction(DirectionType::Dynamic {
+        dynamic: Dynamic::Sforzato(1),
+      });
+      staff.add_direction(DirectionType::Dynamic {
+        dynamic: Dynamic::SforzatoPiano,
+      });
+      staff.add_direction(DirectionType::Expression {
+        text: String::from("dolce"),
+        style: TextStyle {
+          italic: true,
+          placement: Some(TextPlacement::Below),
+          ..TextStyle::default()
+        },
+      });
       staff.add_direction(DirectionType::KeyChange {
         key: Key::new(KeySignature::AFlat, KeyMode::Major),
       });
@@ -199,7 +504,20 @@ mod test {
       staff.add_direction(DirectionType::KeyChange {
         key: Key::new(KeySignature::GSharp, KeyMode::Minor),
       });
+      staff.add_direction(DirectionType::Rehearsal {
+        mark: String::from("B"),
+        style: TextStyle {
+          font_family: Some(String::from("Times New Roman")),
+          font_size: Some(14),
+          bold: true,
+          ..TextStyle::default()
+        },
+      });
       staff.add_direction(DirectionType::StringMute { on: true });
+      staff.add_direction(DirectionType::Text {
+        text: String::from("Solo"),
+        style: TextStyle::default(),
+      });
       staff.add_direction(DirectionType::TimeSignatureChange {
         time_signature: TimeSignature::new_explicit(3, 8),
       });
@@ -293,6 +611,11 @@ mod test {
         Some(Accidental::None),
       );
       note.add_modification(NoteModificationType::Accent);
+      note.add_modification(NoteModificationType::Bend {
+        semitones: 2,
+        pre_bend: false,
+        release: true,
+      });
       note.add_modification(NoteModificationType::BrassBend);
       note.add_modification(NoteModificationType::DetachedLegato);
       note.add_modification(NoteModificationType::Doit);
@@ -303,8 +626,13 @@ mod test {
       });
       note.add_modification(NoteModificationType::Falloff);
       note.add_modification(NoteModificationType::Fermata);
+      note.add_modification(NoteModificationType::Fingering {
+        finger: Some(3),
+        substitution: Some(4),
+      });
       note.add_modification(NoteModificationType::Fingernails);
       note.add_modification(NoteModificationType::Flip);
+      note.add_modification(NoteModificationType::FretPosition { string: 2, fret: 1 });
       note.add_modification(NoteModificationType::Glissando {
         from_current: false,
         going_up: true,
@@ -312,6 +640,7 @@ mod test {
       note.add_modification(NoteModificationType::Golpe);
       note.add_modification(NoteModificationType::Grace { acciaccatura: true });
       note.add_modification(NoteModificationType::HalfMuted);
+      note.add_modification(NoteModificationType::HammerOn);
       note.add_modification(NoteModificationType::Handbell {
         technique: HandbellTechnique::Belltree,
       });
@@ -348,6 +677,7 @@ mod test {
       note.add_modification(NoteModificationType::Handbell {
         technique: HandbellTechnique::Swing,
       });
+      note.add_modification(NoteModificationType::Harmonic { natural: false });
       note.add_modification(NoteModificationType::HarmonMute { open: true, half: true });
       note.add_modification(NoteModificationType::Haydn);
       note.add_modification(NoteModificationType::Heel);
@@ -360,10 +690,14 @@ mod test {
       note.add_modification(NoteModificationType::Open);
       note.add_modification(NoteModificationType::Pizzicato);
       note.add_modification(NoteModificationType::Plop);
+      note.add_modification(NoteModificationType::Pluck {
+        finger: PluckingFinger::Ring,
+      });
       note.add_modification(NoteModificationType::Portamento {
         from_current: true,
         going_up: false,
       });
+      note.add_modification(NoteModificationType::PullOff);
       note.add_modification(NoteModificationType::Schleifer);
       note.add_modification(NoteModificationType::Scoop);
       note.add_modification(NoteModificationType::Sforzando);
@@ -375,6 +709,7 @@ mod test {
       note.add_modification(NoteModificationType::Staccato);
       note.add_modification(NoteModificationType::Stopped);
       note.add_modification(NoteModificationType::Stress);
+      note.add_modification(NoteModificationType::StringNumber { string: 4 });
       note.add_modification(NoteModificationType::Tap);
       note.add_modification(NoteModificationType::Tenuto);
       note.add_modification(NoteModificationType::ThumbPosition);
@@ -494,6 +829,7 @@ mod test {
         into_beats: 2,
       });
       section.add_modification(SectionModificationType::Accelerando);
+      section.add_modification(SectionModificationType::ATempo);
       section.add_modification(SectionModificationType::OnlyPlay {
         iterations: vec![0, 1, 3],
       });
@@ -508,11 +844,16 @@ mod test {
       section.add_modification(SectionModificationType::TempoImplicit {
         tempo: TempoSuggestion::new(TempoMarking::Allegretto),
       });
+      section.add_modification(SectionModificationType::TempoPrimo);
     }
-    let serialized = composition.serialize_json();
-    match AmmStorage::load_data(serialized.as_bytes().to_vec()).as_ref() {
+    let binary = AmmBinaryStorage::save_to_amm_binary(&composition);
+    let loaded = AmmBinaryStorage::load_bytes(&binary).unwrap();
+    assert_eq!(composition, loaded);
+    assert_eq!(binary, AmmBinaryStorage::save_to_amm_binary(&loaded));
+    let serialized = AmmStorage::save_to_amm(&composition, &JsonOptions::default());
+    match AmmStorage::load_bytes(serialized.as_bytes()).as_ref() {
       Ok(loaded) => {
-        let reserialized = loaded.serialize_json();
+        let reserialized = AmmStorage::save_to_amm(loaded, &JsonOptions::default());
         assert_eq!(composition, *loaded);
         assert_eq!(serialized, reserialized);
       }
@@ -543,4 +884,334 @@ mod test {
       Err(error) => assert!(false, "{}", error),
     }
   }
+
+  #[test]
+  fn test_binary_serialization_fs() {
+    let composition = Storage::MusicXML
+      .load("examples/Grande Valse Brillante.musicxml")
+      .unwrap();
+    let size = Storage::AMMBinary
+      .save("../target/test_out.ammb", &composition)
+      .unwrap();
+    assert!(size < composition.serialize_json().len() / 4);
+    let loaded = Storage::AMMBinary.load("../target/test_out.ammb").unwrap();
+    assert_eq!(composition, loaded);
+    assert_eq!(composition.serialize_json(), loaded.serialize_json());
+
+    // Truncated, extended, and newer data must be rejected
+    let binary = AmmBinaryStorage::save_to_amm_binary(&composition);
+    assert!(AmmBinaryStorage::load_bytes(&binary[..binary.len() - 1]).is_err());
+    assert!(AmmBinaryStorage::load_bytes(&[binary.as_slice(), &[0]].concat()).is_err());
+    let mut newer = binary;
+    newer[4] = 2;
+    assert!(AmmBinaryStorage::load_bytes(&newer).is_err());
+  }
+
+  #[test]
+  fn test_json_versioning() {
+    let composition = Storage::MusicXML.load("examples/Hymn_to_Freedom.mxl").unwrap();
+    let amm = AmmStorage::save_to_amm(&composition, &JsonOptions::default());
+    assert!(amm.starts_with("{\"_version\":2,\"_type\":\"Composition\","));
+    assert_eq!(AmmStorage::format_version(&amm), Ok(AMM_FORMAT_VERSION));
+
+    // Unversioned documents are migrated to the current version
+    let unversioned = composition.serialize_json();
+    assert_eq!(AmmStorage::format_version(&unversioned), Ok(0));
+    assert_eq!(AmmStorage::load_bytes(unversioned.as_bytes()).unwrap(), composition);
+
+    // Fields unknown to the current model are ignored at every level
+    let extended = amm
+      .replacen(
+        "\"_version\":2,",
+        "\"_version\":3,\"editor\":{\"name\":\"Future\",\"tags\":[1,2]},",
+        1,
+      )
+      .replace(
+        "\"_type\":\"Note\",",
+        "\"_type\":\"Note\",\"velocity\":[64,{\"curve\":\"linear\"}],",
+      );
+    assert_eq!(AmmStorage::format_version(&extended), Ok(3));
+    assert_eq!(AmmStorage::load_bytes(extended.as_bytes()).unwrap(), composition);
+    assert!(AmmStorage::load_bytes(b"{\"_version\":\"one\"}").is_err());
+    assert_eq!(
+      AmmStorage::format_version(r#"{"title":"_version","tempo":{"_version":5}}"#),
+      Ok(0)
+    );
+  }
+
+  #[test]
+  fn test_json_escaping() {
+    let mut composition = Composition::new(
+      "Title with \"quotes\", a \\ backslash,\nand a new line",
+      None,
+      None,
+      None,
+    );
+    composition.set_copyright("\u{a9} Caf\u{e9} {Music} [2024]\t\u{1}");
+    composition.add_metadata("key \"one\"", "value: {1, 2}");
+    composition
+      .add_part("Part \"A\"")
+      .add_section("Section")
+      .add_staff("Staff");
+    let amm = AmmStorage::save_to_amm(&composition, &JsonOptions::default());
+    assert!(amm.contains(r#""title":"Title with \"quotes\", a \\ backslash,\nand a new line""#));
+    assert!(amm.contains(r#"[2024]\t\u0001""#));
+    assert_eq!(AmmStorage::load_bytes(amm.as_bytes()).unwrap(), composition);
+
+    // Documents from other producers may use any whitespace and escapes
+    let document = "{\n  \"_version\" : 2,\n  \"_type\" : \"Composition\",\n  \"title\" : \"Caf\\u00e9 \\ud83c\\udfb5 \\/\",\n  \"composers\" : [ \"A\" , \"B\" ],\n  \"lyricists\" : [ ],\n  \"arrangers\" : [ ],\n  \"copyright\" : null,\n  \"metadata\" : { \"key\" : \"value\" },\n  \"parts\" : [ ],\n  \"tempo\" : { \"_type\" : \"Tempo\", \"base_note\" : { \"_type\" : \"Duration\", \"value\" : \"Quarter\", \"dots\" : 1 }, \"beats_per_minute\" : 96 },\n  \"starting_key\" : { \"_type\" : \"Key\", \"mode\" : \"Major\", \"signature\" : \"C\" },\n  \"starting_time_signature\" : { \"_type\" : \"TimeSignature\", \"signature\" : \"CommonTime\", \"numerator\" : 4, \"denominator\" : 4 }\n}\n";
+    let loaded = AmmStorage::load_bytes(document.as_bytes()).unwrap();
+    assert_eq!(loaded.get_title(), "Caf\u{e9} \u{1f3b5} /");
+    assert_eq!(loaded.get_composers(), ["A", "B"]);
+    assert_eq!(*loaded.get_copyright(), None);
+    assert_eq!(loaded.get_metadata().get("key").map(String::as_str), Some("value"));
+    assert_eq!(loaded.get_tempo().beats_per_minute, 96);
+    assert_eq!(loaded.get_tempo().base_note.dots, 1);
+
+    // Strings were written verbatim before version 2
+    let legacy =
+      format!("{{\"_version\":1,\"_type\":\"Composition\",\"title\":\"C:\\Music\\Tune\nTwo\",{EMPTY_FIELDS}}}");
+    let loaded = AmmStorage::load_bytes(legacy.as_bytes()).unwrap();
+    assert_eq!(loaded.get_title(), "C:\\Music\\Tune\nTwo");
+    let legacy = format!(
+      "{{\"_version\":1,\"_type\":\"Composition\",\"title\":\"Sonata \"Il Pastor Fido\", No. 2\",\"copyright\":\"Edition \"Urtext\"\",{EMPTY_FIELDS}}}"
+    );
+    let loaded = AmmStorage::load_bytes(legacy.as_bytes()).unwrap();
+    assert_eq!(loaded.get_title(), "Sonata \"Il Pastor Fido\", No. 2");
+    assert_eq!(loaded.get_copyright().as_deref(), Some("Edition \"Urtext\""));
+  }
+
+  #[test]
+  fn test_json_formatting() {
+    let mut composition = Composition::new("Formatting", None, None, None);
+    composition.add_metadata("zeta", "last");
+    composition.add_metadata("alpha", "first");
+    let staff = composition.add_part("Part").add_section("Section").add_staff("Staff");
+    staff.add_note(
+      Pitch::new(PitchName::C, 4),
+      Duration::new(DurationType::Quarter, 0),
+      None,
+    );
+
+    let pretty = AmmStorage::save_to_amm(&composition, &JsonOptions::pretty(2));
+    assert!(pretty.starts_with("{\n  \"_version\": 2,\n  \"_type\": \"Composition\",\n  \"title\": \"Formatting\",\n"));
+    assert!(pretty.contains("\n  \"metadata\": {\n    \"alpha\": \"first\",\n    \"zeta\": \"last\"\n  },\n"));
+    assert!(pretty.contains("\n  \"composers\": [],\n"));
+    assert!(pretty.ends_with("\n  }\n}"));
+    assert_eq!(AmmStorage::load_bytes(pretty.as_bytes()).unwrap(), composition);
+
+    let canonical = AmmStorage::save_to_amm(&composition, &JsonOptions::compact().canonical(true));
+    assert!(canonical.starts_with("{\"_version\":2,\"_type\":\"Composition\",\"arrangers\":[],\"composers\":[],"));
+    assert!(canonical.contains("{\"_type\":\"Pitch\",\"name\":\"C\",\"octave\":4}"));
+    let loaded = AmmStorage::load_bytes(canonical.as_bytes()).unwrap();
+    assert_eq!(loaded, composition);
+    assert_eq!(
+      AmmStorage::save_to_amm(&loaded, &JsonOptions::compact().canonical(true)),
+      canonical
+    );
+    let compact = AmmStorage::save_to_amm(&composition, &JsonOptions::default());
+    assert_eq!(compact.len(), canonical.len());
+    assert!(!compact.contains('\n'));
+
+    let size = Storage::AMM
+      .save_with(
+        "../target/test_out_pretty.amm",
+        &composition,
+        &JsonOptions::pretty(4).canonical(true),
+      )
+      .unwrap();
+    assert!(size > pretty.len());
+    assert_eq!(Storage::AMM.load("../target/test_out_pretty.amm").unwrap(), composition);
+
+    // Type tags are usually read first, but may appear anywhere within their object
+    let document = format!(
+      r#"{{"_version":2,"parts":[{{"content":[{{"name":"Verse","id":2,"content":[],"modifications":[],"_type":"Section"}}],"name":"Vocals","id":1}}],"title":"Tags",{}}}"#,
+      EMPTY_FIELDS.replace(r#""parts":[],"#, r#""_type":"Composition","#)
+    );
+    let loaded = AmmStorage::load_bytes(document.as_bytes()).unwrap();
+    let part = loaded.iter().next().unwrap();
+    assert_eq!(part.get_name(), "Vocals");
+    assert!(matches!(part.iter().next(), Some(PartContent::Section(section)) if section.get_name() == "Verse"));
+  }
+
+  #[test]
+  fn test_deserialization_errors() {
+    let mut composition = Composition::new("Errors", None, None, None);
+    let staff = composition.add_part("Part").add_section("Section").add_staff("Staff");
+    staff.add_note(
+      Pitch::new(PitchName::C, 4),
+      Duration::new(DurationType::Quarter, 0),
+      None,
+    );
+    staff.add_note(
+      Pitch::new(PitchName::D, 4),
+      Duration::new(DurationType::Quarter, 0),
+      None,
+    );
+    let mut amm = AmmStorage::save_to_amm(&composition, &JsonOptions::default());
+    let offset = amm.rfind("\"octave\":4").unwrap() + 9;
+    amm.replace_range(offset..=offset, "400");
+    let error = AmmStorage::load_bytes(amm.as_bytes()).unwrap_err();
+    assert_eq!(error.kind, AmmErrorKind::InvalidValue);
+    assert_eq!(error.path, "parts[0].content[0].content[0].content[1].pitch.octave");
+    assert_eq!(error.offset, Some(offset));
+    assert_eq!(error.line_column(&amm), Some((1, offset + 1)));
+    let amm = amm.replacen("\"Quarter\"", "\"Whole-ish\"", 1);
+    let error = AmmStorage::load_bytes(amm.as_bytes()).unwrap_err();
+    assert_eq!(error.kind, AmmErrorKind::UnknownVariant);
+    assert_eq!(error.path, "parts[0].content[0].content[0].content[0].duration.value");
+
+    let document = "{\n  \"_version\": 2,\n  \"_type\": \"Composition\",\n  \"tempo\": {\"_type\": \"Tempo\", \"beats_per_minute\": -96}\n}";
+    let error = Storage::AMM.load_data(document.as_bytes().to_vec()).unwrap_err();
+    assert_eq!(error.path, "tempo.beats_per_minute");
+    assert_eq!(error.line_column(document), Some((4, 51)));
+    assert_eq!(
+      error.to_string(),
+      "Invalid value \"-96\": invalid digit found in string at tempo.beats_per_minute (byte 95)"
+    );
+    let error = AmmStorage::load_bytes(br#"{"_version":2,"title":"a\q"}"#).unwrap_err();
+    assert_eq!(
+      (error.kind, error.path.as_str(), error.offset),
+      (AmmErrorKind::Syntax, "title", Some(26))
+    );
+
+    let binary = AmmBinaryStorage::save_to_amm_binary(&composition);
+    let error = Storage::AMMBinary
+      .load_data(binary[..binary.len() - 2].to_vec())
+      .unwrap_err();
+    assert_eq!(error.kind, AmmErrorKind::UnexpectedEnd);
+    let mut payload = Vec::new();
+    composition.serialize_binary(&mut payload);
+    let error = Composition::deserialize_binary(&mut &payload[..payload.len() - 1]).unwrap_err();
+    assert_eq!(error.kind, AmmErrorKind::UnexpectedEnd);
+    assert_eq!(error.path, "starting_time_signature.denominator");
+    assert!(Storage::detect(b"{}").is_err_and(|error| error.kind == AmmErrorKind::UnsupportedFormat));
+  }
+
+  #[test]
+  fn test_json_schema() {
+    fn members(value: &JsonValue) -> &[(String, JsonValue)] {
+      match value {
+        JsonValue::Object(members) => members,
+        _ => &[],
+      }
+    }
+
+    fn keys(value: &JsonValue) -> Vec<&str> {
+      members(value).iter().map(|(key, _)| key.as_str()).collect()
+    }
+
+    fn check_objects(value: &JsonValue, definitions: &JsonValue, checked: &mut usize) {
+      match value {
+        JsonValue::Object(fields) => {
+          // Objects are tagged with the name of their struct or enum variant
+          if let Some(name) = value.get("_type").and_then(JsonValue::as_str) {
+            let schemas = members(definitions).iter().flat_map(|(_, schema)| {
+              let variants = schema.get("anyOf").and_then(JsonValue::as_array);
+              core::iter::once(schema).chain(variants.into_iter().flatten())
+            });
+            let tagged = |schema: &&JsonValue| {
+              let tag = schema.get("properties").and_then(|properties| properties.get("_type"));
+              tag.and_then(|tag| tag.get("const")).and_then(JsonValue::as_str) == Some(name)
+            };
+            assert!(
+              schemas
+                .filter(tagged)
+                .any(|schema| keys(schema.get("properties").unwrap()) == keys(value)),
+              "{name}"
+            );
+            *checked += 1;
+          }
+          fields
+            .iter()
+            .for_each(|(_, value)| check_objects(value, definitions, checked));
+        }
+        JsonValue::Array(items) => items.iter().for_each(|item| check_objects(item, definitions, checked)),
+        _ => {}
+      }
+    }
+
+    let mut composition = Composition::new("Schema", None, None, None);
+    composition.set_copyright("Copyright");
+    let staff = composition.add_part("Part").add_section("Section").add_staff("Staff");
+    staff.add_direction(DirectionType::Dynamic {
+      dynamic: Dynamic::Forte(2),
+    });
+    let chord = staff.add_chord();
+    chord.add_note(
+      Pitch::new(PitchName::C, 4),
+      Duration::new(DurationType::Quarter, 0),
+      None,
+    );
+    chord.add_modification(ChordModificationType::Accent);
+
+    let schema = JsonValue::parse(&crate::storage::amm_json_schema(&JsonOptions::pretty(2))).unwrap();
+    assert_eq!(
+      schema.get("$schema").and_then(JsonValue::as_str),
+      Some("https://json-schema.org/draft/2020-12/schema")
+    );
+    let definitions = schema.get("$defs").unwrap();
+    let amm = JsonValue::parse(&AmmStorage::save_to_amm(&composition, &JsonOptions::default())).unwrap();
+    let mut document = amm.clone();
+    if let JsonValue::Object(members) = &mut document {
+      members.retain(|(key, _)| key != "_version");
+    }
+    let mut checked = 0;
+    check_objects(&document, definitions, &mut checked);
+    assert!(checked > 5);
+
+    let version = schema.get("allOf").and_then(JsonValue::as_array).unwrap()[1].serialize_json();
+    assert_eq!(
+      version,
+      format!(
+        r#"{{"type":"object","properties":{{"_version":{{"const":{AMM_FORMAT_VERSION}}}}},"required":["_version"]}}"#
+      )
+    );
+    assert_eq!(
+      definitions.get("Pitch").unwrap().serialize_json(),
+      r##"{"type":"object","properties":{"_type":{"const":"Pitch"},"name":{"$ref":"#/$defs/PitchName"},"octave":{"type":"integer","minimum":0,"maximum":255}},"required":["_type","name","octave"]}"##
+    );
+    let dynamic = definitions
+      .get("Dynamic")
+      .unwrap()
+      .get("anyOf")
+      .unwrap()
+      .serialize_json();
+    assert!(dynamic.contains(r#"{"type":"string","pattern":"^Forte-[0-9]+$"}"#));
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_serde_interop() {
+    let mut composition = Composition::new("Serde \"Quoted\"\nTitle", None, None, None);
+    composition.set_copyright("C:\\Music");
+    let staff = composition.add_part("Part").add_section("Section").add_staff("Staff");
+    staff.add_note(
+      Pitch::new(PitchName::E, 5),
+      Duration::new(DurationType::Eighth, 1),
+      Some(Accidental::Flat),
+    );
+
+    let amm = AmmStorage::save_to_amm(&composition, &JsonOptions::default());
+    assert_eq!(serde_json::to_string(&composition).unwrap(), amm);
+    assert_eq!(serde_json::from_str::<Composition>(&amm).unwrap(), composition);
+    let value = serde_json::to_value(&composition).unwrap();
+    assert_eq!(value["_version"], AMM_FORMAT_VERSION);
+    assert_eq!(value["parts"][0]["_type"], "Part");
+    assert_eq!(serde_json::from_value::<Composition>(value).unwrap(), composition);
+
+    let pitch = Pitch::new(PitchName::E, 5);
+    assert_eq!(serde_json::to_string(&pitch).unwrap(), pitch.serialize_json());
+    assert_eq!(serde_json::to_string(&DurationType::Eighth).unwrap(), "\"Eighth\"");
+    assert_eq!(
+      serde_json::from_str::<Pitch>(r#"{"octave": 5, "name": "E"}"#).unwrap(),
+      pitch
+    );
+    assert!(serde_json::from_str::<Pitch>(r#"{"_type": "Pitch", "octave": 400}"#).is_err());
+
+    let binary = bincode::serialize(&composition).unwrap();
+    assert_eq!(bincode::deserialize::<Composition>(&binary).unwrap(), composition);
+    let binary = bincode::serialize(&pitch).unwrap();
+    assert_eq!(bincode::deserialize::<Pitch>(&binary).unwrap(), pitch);
+  }
 }
@@ -0,0 +1,143 @@
+use super::{Load, Store};
+use crate::Composition;
+use alloc::vec::Vec;
+use amm_internal::{AmmError, AmmErrorKind};
+
+/// Recognizes the raw data of a document in a custom storage format.
+pub type DetectFormat = fn(&[u8]) -> bool;
+
+type LoadFormat = fn(&[u8]) -> Result<Composition, AmmError>;
+type StoreFormat = fn(&Composition) -> Result<Vec<u8>, AmmError>;
+
+#[cfg(feature = "std")]
+static FORMATS: std::sync::RwLock<Vec<StorageFormat>> = std::sync::RwLock::new(Vec::new());
+
+/// Describes a storage format provided by the application.
+///
+/// Once registered with [`Storage::register`](super::Storage::register), the
+/// format is available as [`Storage::Custom`](super::Storage::Custom) under
+/// its name and takes part in format detection and extension lookups along
+/// with the built-in formats.
+#[derive(Copy, Clone, Debug)]
+pub struct StorageFormat {
+  name: &'static str,
+  extensions: &'static [&'static str],
+  detect: Option<DetectFormat>,
+  load: Option<LoadFormat>,
+  save: Option<StoreFormat>,
+}
+
+impl StorageFormat {
+  /// Creates a format with the given unique `name` and file `extensions`,
+  /// which supports neither import nor export until [`with_load`](Self::with_load)
+  /// or [`with_store`](Self::with_store) is called.
+  #[must_use]
+  pub const fn new(name: &'static str, extensions: &'static [&'static str]) -> Self {
+    Self {
+      name,
+      extensions,
+      detect: None,
+      load: None,
+      save: None,
+    }
+  }
+
+  /// Recognizes documents in this format with `detect` when the contents of
+  /// some data do not match any of the built-in formats.
+  #[must_use]
+  pub const fn with_detection(mut self, detect: DetectFormat) -> Self {
+    self.detect = Some(detect);
+    self
+  }
+
+  /// Imports documents in this format with the loader `F`.
+  #[must_use]
+  pub fn with_load<F: Load>(mut self) -> Self {
+    self.load = Some(F::load_bytes);
+    self
+  }
+
+  /// Exports documents in this format with the writer `F`.
+  #[must_use]
+  pub fn with_store<F: Store>(mut self) -> Self {
+    self.save = Some(F::save_bytes);
+    self
+  }
+
+  /// Returns the unique name of the format.
+  #[must_use]
+  pub const fn name(&self) -> &'static str {
+    self.name
+  }
+
+  /// Returns the file extensions associated with the format, without their
+  /// leading dots.
+  #[must_use]
+  pub const fn extensions(&self) -> &'static [&'static str] {
+    self.extensions
+  }
+
+  pub(super) fn load_bytes(&self, data: &[u8]) -> Result<Composition, AmmError> {
+    let load = self.load.ok_or_else(|| {
+      AmmError::new(
+        AmmErrorKind::UnsupportedFormat,
+        format!("Cannot import from {}", self.name),
+      )
+    })?;
+    load(data)
+  }
+
+  pub(super) fn save_bytes(&self, composition: &Composition) -> Result<Vec<u8>, AmmError> {
+    let save = self.save.ok_or_else(|| {
+      AmmError::new(
+        AmmErrorKind::UnsupportedFormat,
+        format!("Cannot export to {}", self.name),
+      )
+    })?;
+    save(composition)
+  }
+}
+
+/// Adds `format` to the registered formats, failing if a format with the
+/// same name has already been registered.
+#[cfg(feature = "std")]
+pub(super) fn register(format: StorageFormat) -> Result<(), AmmError> {
+  let mut formats = FORMATS.write().unwrap_or_else(std::sync::PoisonError::into_inner);
+  if formats.iter().any(|registered| registered.name == format.name) {
+    return Err(AmmError::new(
+      AmmErrorKind::InvalidValue,
+      format!("Storage format {} is already registered", format.name),
+    ));
+  }
+  formats.push(format);
+  Ok(())
+}
+
+/// Returns all registered formats in the order they were registered.
+pub(super) fn formats() -> Vec<StorageFormat> {
+  #[cfg(feature = "std")]
+  let formats = FORMATS
+    .read()
+    .unwrap_or_else(std::sync::PoisonError::into_inner)
+    .clone();
+  #[cfg(not(feature = "std"))]
+  let formats = Vec::new();
+  formats
+}
+
+/// Returns the registered format with the given `name`.
+pub(super) fn find(name: &str) -> Result<StorageFormat, AmmError> {
+  formats().into_iter().find(|format| format.name == name).ok_or_else(|| {
+    AmmError::new(
+      AmmErrorKind::UnsupportedFormat,
+      format!("Storage format {name} is not registered"),
+    )
+  })
+}
+
+/// Returns the first registered format whose detection hook recognizes `data`.
+pub(super) fn detect(data: &[u8]) -> Option<StorageFormat> {
+  formats()
+    .into_iter()
+    .find(|format| format.detect.is_some_and(|detect| detect(data)))
+}
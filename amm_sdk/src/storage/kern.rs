@@ -0,0 +1,1996 @@
+use super::musicxml::MusicXmlConverter;
+use super::util::{gcd, measure_starts, ContextChange, Fraction, StaffContext, MAX_DOTS};
+use super::xml::{add_notation, xml_element, xml_text_element, XmlElementExt};
+use super::{AmmError, Load, Store};
+use crate::context::{ClefType, Key, KeyMode, Tempo, TimeSignature, TimeSignatureType};
+use crate::modification::{
+  ChordModificationType, DirectionType, NoteModificationType, PhraseModificationType, SectionModificationType,
+};
+use crate::note::{Accidental, Note, Pitch, PitchName};
+use crate::structure::{
+  Chord, ChordContent, MultiVoice, MultiVoiceContent, PartContent, Phrase, PhraseContent, Section, SectionContent,
+  Staff, StaffContent,
+};
+use crate::Composition;
+use alloc::{
+  collections::{BTreeMap, BTreeSet},
+  string::{String, ToString},
+  vec::Vec,
+};
+use musicxml::elements::ScorePartwise;
+use musicxml_internal::{ElementDeserializer, XmlElement};
+
+const VOICES_PER_STAFF: usize = 4;
+const MAX_WHOLE_DIVISIONS: u64 = 15_360;
+const SHARPS: [char; 7] = ['f', 'c', 'g', 'd', 'a', 'e', 'b'];
+const MAJOR_TONICS: [&str; 15] = [
+  "C-", "G-", "D-", "A-", "E-", "B-", "F", "C", "G", "D", "A", "E", "B", "F#", "C#",
+];
+const MINOR_TONICS: [&str; 15] = [
+  "a-", "e-", "b-", "f", "c", "g", "d", "a", "e", "b", "f#", "c#", "g#", "d#", "a#",
+];
+const REFERENCE_CREATORS: [(&str, &str); 4] = [
+  ("COM", "composer"),
+  ("LYR", "lyricist"),
+  ("ARR", "arranger"),
+  ("PPR", "publisher"),
+];
+
+enum KernItem {
+  Element(XmlElement),
+  Timed {
+    element: XmlElement,
+    written: Fraction,
+    actual: Fraction,
+  },
+  Backup(Fraction),
+}
+
+#[derive(Default)]
+struct KernNote {
+  pitch: Option<(char, i32, i32)>,
+  explicit: bool,
+  tie_start: bool,
+  tie_stop: bool,
+  slur_starts: usize,
+  slur_stops: usize,
+  notations: Vec<(Option<&'static str>, &'static str)>,
+}
+
+struct KernEvent {
+  length: Option<Fraction>,
+  notes: Vec<KernNote>,
+  grace: Option<bool>,
+  invisible: bool,
+}
+
+struct KernTuplet {
+  ratio: (u64, u64),
+  elapsed: Fraction,
+  last: usize,
+}
+
+#[derive(Default)]
+struct KernMeasure {
+  forward_repeat: bool,
+  backward_repeat: bool,
+  bar_style: Option<&'static str>,
+}
+
+struct KernStaff {
+  part_tag: Option<String>,
+  name: Option<String>,
+  part: usize,
+  number: usize,
+  clef: XmlElement,
+  key: BTreeMap<char, i32>,
+  alterations: BTreeMap<(char, i32), i32>,
+  voices: Vec<Vec<(Fraction, KernItem)>>,
+  tuplets: Vec<Option<KernTuplet>>,
+  slurs: Vec<(usize, usize)>,
+}
+
+struct KernPart {
+  tag: Option<String>,
+  name: String,
+  staves: Vec<usize>,
+  measures: Vec<Vec<KernItem>>,
+}
+
+struct KernReader {
+  title: Option<String>,
+  movement_title: Option<String>,
+  creators: Vec<(&'static str, String)>,
+  rights: Option<String>,
+  columns: Vec<Option<usize>>,
+  ends: Vec<Fraction>,
+  staves: Vec<KernStaff>,
+  parts: Vec<KernPart>,
+  fifths: i32,
+  minor: bool,
+  initial_key: (i32, bool),
+  meter: Option<(String, u64)>,
+  symbol: Option<&'static str>,
+  tempo: Option<XmlElement>,
+  now: Fraction,
+  measure_start: Fraction,
+  measures: Vec<KernMeasure>,
+  current: KernMeasure,
+  started: bool,
+  has_data: bool,
+}
+
+impl KernReader {
+  fn parse(text: &str) -> Result<Self, String> {
+    let mut reader = Self {
+      title: None,
+      movement_title: None,
+      creators: Vec::new(),
+      rights: None,
+      columns: Vec::new(),
+      ends: Vec::new(),
+      staves: Vec::new(),
+      parts: Vec::new(),
+      fifths: 0,
+      minor: false,
+      initial_key: (0, false),
+      meter: None,
+      symbol: None,
+      tempo: None,
+      now: Fraction::ZERO,
+      measure_start: Fraction::ZERO,
+      measures: Vec::new(),
+      current: KernMeasure::default(),
+      started: false,
+      has_data: false,
+    };
+    let mut in_header = true;
+    for (line_idx, line) in text.lines().enumerate() {
+      let line = line.trim_end_matches('\r');
+      if line.is_empty() {
+        continue;
+      } else if let Some(record) = line.strip_prefix("!!!") {
+        reader.read_reference(record);
+        continue;
+      } else if line.starts_with('!') {
+        continue;
+      }
+      let tokens = line.split('\t').collect::<Vec<_>>();
+      let result = if in_header {
+        in_header = false;
+        reader.read_exclusive_interpretations(&tokens)
+      } else if reader.columns.is_empty() {
+        // Anything following the termination of every spine is not part of the score
+        break;
+      } else if tokens.len() != reader.columns.len() {
+        Err(format!(
+          "Expected {} spines but found {}",
+          reader.columns.len(),
+          tokens.len()
+        ))
+      } else if tokens[0].starts_with('*') {
+        reader.read_interpretations(&tokens)
+      } else if tokens[0].starts_with('=') {
+        reader.read_barline(tokens[0]);
+        Ok(())
+      } else {
+        reader.read_data(&tokens)
+      };
+      result.map_err(|err| format!("Line {}: {err}", line_idx + 1))?;
+    }
+    if in_header {
+      return Err(String::from("No **kern spines found in the Humdrum data"));
+    } else if reader.has_data {
+      reader.close_measure();
+    }
+    Ok(reader)
+  }
+
+  fn read_reference(&mut self, record: &str) {
+    let Some((key, value)) = record.split_once(':') else {
+      return;
+    };
+    let value = value.trim();
+    if value.is_empty() {
+      return;
+    }
+    match key.trim() {
+      "OTL" if self.title.is_none() => self.title = Some(String::from(value)),
+      "OMD" if self.movement_title.is_none() => self.movement_title = Some(String::from(value)),
+      "YEC" if self.rights.is_none() => self.rights = Some(String::from(value)),
+      key => {
+        if let Some((_, creator)) = REFERENCE_CREATORS.iter().find(|(reference, _)| *reference == key) {
+          self.creators.push((creator, String::from(value)));
+        }
+      }
+    }
+  }
+
+  fn read_exclusive_interpretations(&mut self, tokens: &[&str]) -> Result<(), String> {
+    if !tokens[0].starts_with("**") {
+      return Err(String::from(
+        "Expected exclusive interpretations to start the Humdrum data",
+      ));
+    }
+    for token in tokens {
+      if *token == "**kern" {
+        self.columns.push(Some(self.staves.len()));
+        self.staves.push(KernStaff {
+          part_tag: None,
+          name: None,
+          part: 0,
+          number: 1,
+          clef: KernConverter::clef_element("G", 2, 0),
+          key: BTreeMap::new(),
+          alterations: BTreeMap::new(),
+          voices: Vec::new(),
+          tuplets: Vec::new(),
+          slurs: Vec::new(),
+        });
+      } else {
+        self.columns.push(None);
+      }
+      self.ends.push(Fraction::ZERO);
+    }
+    if self.staves.is_empty() {
+      Err(String::from("No **kern spines found in the Humdrum data"))
+    } else {
+      Ok(())
+    }
+  }
+
+  fn manipulate_spines(&mut self, tokens: &[&str]) -> Result<(), String> {
+    let (mut columns, mut ends) = (Vec::new(), Vec::new());
+    let mut idx = 0;
+    while idx < tokens.len() {
+      match tokens[idx] {
+        "*^" => {
+          columns.extend([self.columns[idx]; 2]);
+          ends.extend([self.ends[idx]; 2]);
+        }
+        "*v" => {
+          // Adjacent merges within the same staff join into a single spine
+          let mut end = idx + 1;
+          while end < tokens.len() && tokens[end] == "*v" && self.columns[end] == self.columns[idx] {
+            end += 1;
+          }
+          columns.push(self.columns[idx]);
+          ends.push(self.ends[idx..end].iter().copied().max().unwrap_or(Fraction::ZERO));
+          idx = end;
+          continue;
+        }
+        "*x" => {
+          if tokens.get(idx + 1) != Some(&"*x") {
+            return Err(String::from("Spine exchanges must involve two adjacent spines"));
+          }
+          columns.extend([self.columns[idx + 1], self.columns[idx]]);
+          ends.extend([self.ends[idx + 1], self.ends[idx]]);
+          idx += 2;
+          continue;
+        }
+        "*+" => return Err(String::from("Adding spines with *+ is not supported")),
+        "*-" => (),
+        _ => {
+          columns.push(self.columns[idx]);
+          ends.push(self.ends[idx]);
+        }
+      }
+      idx += 1;
+    }
+    self.columns = columns;
+    self.ends = ends;
+    Ok(())
+  }
+
+  fn read_interpretations(&mut self, tokens: &[&str]) -> Result<(), String> {
+    if tokens
+      .iter()
+      .any(|token| matches!(*token, "*^" | "*v" | "*x" | "*+" | "*-"))
+    {
+      return self.manipulate_spines(tokens);
+    }
+
+    // Only the first spine of every staff determines its context
+    let (mut key_changed, mut time_changed, mut tempo) = (false, false, None);
+    for (idx, token) in tokens.iter().enumerate() {
+      let (Some(staff), Some(interpretation)) = (self.columns[idx], token.strip_prefix('*')) else {
+        continue;
+      };
+      if self.columns[..idx].contains(&Some(staff)) {
+        continue;
+      }
+      if let Some(signature) = interpretation
+        .strip_prefix("k[")
+        .and_then(|signature| signature.strip_suffix(']'))
+      {
+        let key = KernConverter::parse_key_signature(signature);
+        self.fifths = key.values().sum();
+        self.staves[staff].key = key;
+        key_changed = true;
+      } else if let Some(tonic) = interpretation.strip_suffix(':') {
+        if tonic.starts_with(|ch: char| matches!(ch.to_ascii_lowercase(), 'a'..='g'))
+          && tonic[1..].chars().all(|ch| matches!(ch, '#' | '-'))
+        {
+          self.minor = tonic.starts_with(|ch: char| ch.is_ascii_lowercase());
+          key_changed = true;
+        }
+      } else if let Some(beats_per_minute) = interpretation.strip_prefix("MM") {
+        tempo = beats_per_minute.trim().parse::<f64>().ok().filter(|bpm| *bpm > 0.0);
+      } else if let Some(meter) = interpretation.strip_prefix('M') {
+        if let Some((beats, beat_type)) = meter.split_once('/') {
+          let valid = beats
+            .split('+')
+            .all(|beats| beats.parse::<u64>().is_ok_and(|beats| beats > 0));
+          if let Some(beat_type) = beat_type
+            .parse::<u64>()
+            .ok()
+            .filter(|beat_type| *beat_type > 0 && valid)
+          {
+            self.meter = Some((String::from(beats), beat_type));
+            self.symbol = None;
+            time_changed = true;
+          }
+        }
+      } else if let Some(symbol) = interpretation
+        .strip_prefix("met(")
+        .and_then(|symbol| symbol.strip_suffix(')'))
+      {
+        let (symbol, beats, beat_type) = match symbol {
+          "c" => ("common", "4", 4),
+          "c|" => ("cut", "2", 2),
+          _ => continue,
+        };
+        if self.meter.is_none() {
+          self.meter = Some((String::from(beats), beat_type));
+        }
+        self.symbol = Some(symbol);
+        time_changed = true;
+      } else if let Some(clef) = interpretation.strip_prefix("clef") {
+        if let Some(mut clef) = KernConverter::parse_clef(clef) {
+          if self.started {
+            clef
+              .attributes
+              .push((String::from("number"), self.staves[staff].number.to_string()));
+            let item = KernItem::Element(xml_element("attributes", &[], vec![clef]));
+            self.push_item(staff, 0, item);
+          } else {
+            self.staves[staff].clef = clef;
+          }
+        }
+      } else if let Some(name) = interpretation.strip_prefix("I\"") {
+        self.staves[staff].name = Some(String::from(name.trim())).filter(|name| !name.is_empty());
+      } else if let Some(part) = interpretation.strip_prefix("part") {
+        self.staves[staff].part_tag = Some(String::from(part));
+      }
+    }
+
+    // Changes before the first data record simply redefine the initial context
+    let tempo = tempo.map(KernConverter::tempo_direction);
+    if !self.started {
+      self.tempo = tempo.or(self.tempo.take());
+      return Ok(());
+    }
+    for part in 0..self.parts.len() {
+      let staff = self.parts[part].staves[0];
+      if key_changed {
+        let key = KernConverter::key_element(self.fifths, self.minor);
+        self.push_item(staff, 0, KernItem::Element(xml_element("attributes", &[], vec![key])));
+      }
+      if time_changed {
+        let time = self.time_element();
+        self.push_item(staff, 0, KernItem::Element(xml_element("attributes", &[], vec![time])));
+      }
+      if let Some(tempo) = tempo.as_ref().filter(|_| part == 0) {
+        self.push_item(staff, 0, KernItem::Element(tempo.deep_copy()));
+      }
+    }
+    Ok(())
+  }
+
+  fn read_barline(&mut self, token: &str) {
+    if self.has_data {
+      self.current.backward_repeat = token.contains(":|");
+      if token.starts_with("==") {
+        self.current.bar_style = Some("light-heavy");
+      } else if token.contains("||") {
+        self.current.bar_style = Some("light-light");
+      }
+      self.close_measure();
+    }
+    if token.contains("|:") {
+      self.current.forward_repeat = true;
+    }
+  }
+
+  fn start(&mut self) {
+    // Spines are ordered from the lowest staff on the left to the highest staff on the right
+    for staff in (0..self.staves.len()).rev() {
+      let tag = self.staves[staff].part_tag.clone();
+      let part = tag
+        .as_ref()
+        .and_then(|tag| self.parts.iter().position(|part| part.tag.as_ref() == Some(tag)))
+        .unwrap_or_else(|| {
+          self.parts.push(KernPart {
+            tag,
+            name: String::new(),
+            staves: Vec::new(),
+            measures: Vec::new(),
+          });
+          self.parts.len() - 1
+        });
+      self.parts[part].staves.push(staff);
+      self.staves[staff].part = part;
+      self.staves[staff].number = self.parts[part].staves.len();
+    }
+    for (idx, part) in self.parts.iter_mut().enumerate() {
+      part.name = part
+        .staves
+        .iter()
+        .find_map(|staff| self.staves[*staff].name.clone())
+        .unwrap_or_else(|| format!("Part {}", idx + 1));
+    }
+    self.initial_key = (self.fifths, self.minor);
+    self.started = true;
+  }
+
+  fn push_item(&mut self, staff: usize, voice: usize, item: KernItem) -> usize {
+    let voices = &mut self.staves[staff].voices;
+    if voices.len() <= voice {
+      voices.resize_with(voice + 1, Vec::new);
+    }
+    voices[voice].push((self.now, item));
+    voices[voice].len() - 1
+  }
+
+  fn read_data(&mut self, tokens: &[&str]) -> Result<(), String> {
+    if !self.started {
+      self.start();
+    }
+    self.has_data = true;
+    let (now, mut events, mut grace_only) = (self.now, 0, true);
+    for (idx, token) in tokens.iter().enumerate() {
+      let Some(staff) = self.columns[idx].filter(|_| *token != ".") else {
+        continue;
+      };
+      let voice = self.columns[..idx]
+        .iter()
+        .filter(|column| **column == Some(staff))
+        .count();
+      let event = KernConverter::parse_event(token)?;
+      events += 1;
+      if let Some(duration) = self.push_event(staff, voice, &event) {
+        self.ends[idx] = now + duration;
+        grace_only = false;
+      }
+    }
+
+    // Records containing only grace notes take no time
+    if events == 0 || !grace_only {
+      self.now = self.ends.iter().copied().filter(|end| *end > now).min().unwrap_or(now);
+    }
+    Ok(())
+  }
+
+  fn close_tuplet(&mut self, staff: usize, voice: usize) {
+    let Some(Some(tuplet)) = self.staves[staff].tuplets.get_mut(voice).map(Option::take) else {
+      return;
+    };
+    if let Some((_, KernItem::Timed { element, .. })) = self.staves[staff].voices[voice].get_mut(tuplet.last) {
+      add_notation(element, None, xml_element("tuplet", &[("type", "stop")], Vec::new()));
+    }
+  }
+
+  fn note_element(&mut self, staff: usize, note: &KernNote, in_chord: bool, voice_number: usize) -> XmlElement {
+    let staff_number = self.staves[staff].number;
+    let Some((step, octave, alter)) = note.pitch else {
+      return xml_element(
+        "note",
+        &[],
+        vec![
+          xml_element("rest", &[], Vec::new()),
+          xml_text_element("voice", voice_number),
+          xml_text_element("staff", staff_number),
+        ],
+      );
+    };
+
+    // Kern spells out every sounding alteration, so written accidentals depend on the key and measure
+    let staff = &mut self.staves[staff];
+    let implied = staff
+      .alterations
+      .get(&(step, octave))
+      .or_else(|| staff.key.get(&step.to_ascii_lowercase()))
+      .copied()
+      .unwrap_or_default();
+    staff.alterations.insert((step, octave), alter);
+    let mut elements = Vec::new();
+    if in_chord {
+      elements.push(xml_element("chord", &[], Vec::new()));
+    }
+    let mut pitch = vec![xml_text_element("step", step)];
+    if alter != 0 {
+      pitch.push(xml_text_element("alter", alter));
+    }
+    pitch.push(xml_text_element("octave", octave.clamp(0, 9)));
+    elements.push(xml_element("pitch", &[], pitch));
+    elements.push(xml_text_element("voice", voice_number));
+    elements.push(xml_text_element("staff", staff_number));
+    if note.explicit || (alter != implied && !note.tie_stop) {
+      let accidental = match alter {
+        -2 => Some("flat-flat"),
+        -1 => Some("flat"),
+        0 => Some("natural"),
+        1 => Some("sharp"),
+        2 => Some("double-sharp"),
+        _ => None,
+      };
+      elements.extend(accidental.map(|accidental| xml_text_element("accidental", accidental)));
+    }
+    let mut element = xml_element("note", &[], elements);
+    for (tied, tie_type) in [(note.tie_stop, "stop"), (note.tie_start, "start")] {
+      if tied {
+        element
+          .elements
+          .push(xml_element("tie", &[("type", tie_type)], Vec::new()));
+        add_notation(
+          &mut element,
+          None,
+          xml_element("tied", &[("type", tie_type)], Vec::new()),
+        );
+      }
+    }
+    for (group, name) in &note.notations {
+      add_notation(&mut element, *group, xml_element(name, &[], Vec::new()));
+    }
+    element
+  }
+
+  fn add_slurs(&mut self, staff: usize, voice: usize, note: &KernNote, element: &mut XmlElement) {
+    let slurs = &mut self.staves[staff].slurs;
+    for _ in 0..note.slur_stops {
+      if let Some(idx) = slurs.iter().rposition(|(slur_voice, _)| *slur_voice == voice) {
+        let (_, number) = slurs.remove(idx);
+        add_notation(
+          element,
+          None,
+          xml_element("slur", &[("type", "stop"), ("number", &number.to_string())], Vec::new()),
+        );
+      }
+    }
+    for _ in 0..note.slur_starts {
+      let number = (1..)
+        .find(|number| slurs.iter().all(|(_, open)| open != number))
+        .unwrap_or(1);
+      slurs.push((voice, number));
+      add_notation(
+        element,
+        None,
+        xml_element(
+          "slur",
+          &[("type", "start"), ("number", &number.to_string())],
+          Vec::new(),
+        ),
+      );
+    }
+  }
+
+  fn push_event(&mut self, staff: usize, voice: usize, event: &KernEvent) -> Option<Fraction> {
+    let voice_number = (self.staves[staff].number - 1) * VOICES_PER_STAFF + voice.min(VOICES_PER_STAFF - 1) + 1;
+    if let Some(slash) = event.grace {
+      // Grace notes take no time and are written as eighth notes unless specified otherwise
+      let (written, _) = KernConverter::written_length(event.length.unwrap_or(Fraction::new(1, 8)));
+      for (idx, note) in event.notes.iter().filter(|note| note.pitch.is_some()).enumerate() {
+        let mut element = self.note_element(staff, note, idx > 0, voice_number);
+        element.elements.insert(
+          0,
+          xml_element("grace", if slash { &[("slash", "yes")] } else { &[] }, Vec::new()),
+        );
+        self.add_slurs(staff, voice, note, &mut element);
+        if let Some((note_type, dots)) = written.note_type() {
+          element.elements.push(xml_text_element("type", note_type));
+          element
+            .elements
+            .extend((0..dots).map(|_| xml_element("dot", &[], Vec::new())));
+        }
+        self.push_item(staff, voice, KernItem::Element(element));
+      }
+      return None;
+    }
+    let actual = event.length?;
+    let (written, ratio) = KernConverter::written_length(actual);
+    if self.staves[staff].tuplets.len() <= voice {
+      self.staves[staff].tuplets.resize_with(voice + 1, || None);
+    }
+    let open_ratio = self.staves[staff].tuplets[voice].as_ref().map(|tuplet| tuplet.ratio);
+    if open_ratio.is_some() && open_ratio != ratio {
+      self.close_tuplet(staff, voice);
+    }
+    if event.invisible && event.notes.iter().all(|note| note.pitch.is_none()) {
+      let element = xml_element(
+        "forward",
+        &[],
+        vec![
+          xml_text_element("voice", voice_number),
+          xml_text_element("staff", self.staves[staff].number),
+        ],
+      );
+      self.push_item(
+        staff,
+        voice,
+        KernItem::Timed {
+          element,
+          written: actual,
+          actual,
+        },
+      );
+      return Some(actual);
+    }
+
+    // Tuplets are grouped until their combined duration fits the binary hierarchy again
+    let mut tuplet_types = Vec::new();
+    if let Some(ratio) = ratio {
+      let tuplet = self.staves[staff].tuplets[voice].get_or_insert_with(|| {
+        tuplet_types.push("start");
+        KernTuplet {
+          ratio,
+          elapsed: Fraction::ZERO,
+          last: 0,
+        }
+      });
+      tuplet.elapsed = tuplet.elapsed + actual;
+      if tuplet.elapsed.denominator.is_power_of_two() {
+        tuplet_types.push("stop");
+        self.staves[staff].tuplets[voice] = None;
+      }
+    }
+    let mut first = None;
+    for (idx, note) in event.notes.iter().enumerate() {
+      let mut element = self.note_element(staff, note, idx > 0, voice_number);
+      if let Some((actual_notes, normal_notes)) = ratio {
+        element.elements.push(xml_element(
+          "time-modification",
+          &[],
+          vec![
+            xml_text_element("actual-notes", actual_notes),
+            xml_text_element("normal-notes", normal_notes),
+          ],
+        ));
+      }
+      self.add_slurs(staff, voice, note, &mut element);
+      if idx == 0 {
+        for tuplet_type in &tuplet_types {
+          add_notation(
+            &mut element,
+            None,
+            xml_element("tuplet", &[("type", tuplet_type)], Vec::new()),
+          );
+        }
+      }
+      let item = self.push_item(
+        staff,
+        voice,
+        KernItem::Timed {
+          element,
+          written,
+          actual,
+        },
+      );
+      first.get_or_insert(item);
+    }
+    if let (Some(tuplet), Some(first)) = (self.staves[staff].tuplets[voice].as_mut(), first) {
+      tuplet.last = first;
+    }
+    Some(actual)
+  }
+
+  fn close_measure(&mut self) {
+    let length = self.now - self.measure_start;
+    for staff in 0..self.staves.len() {
+      for voice in 0..self.staves[staff].tuplets.len() {
+        self.close_tuplet(staff, voice);
+      }
+      self.staves[staff].alterations.clear();
+    }
+
+    // Every voice of every staff is written in turn, backing up to the start of the measure between them
+    for part in 0..self.parts.len() {
+      let (mut items, mut cursor) = (Vec::new(), Fraction::ZERO);
+      for staff in self.parts[part].staves.clone() {
+        let number = self.staves[staff].number;
+        let voices = core::mem::take(&mut self.staves[staff].voices);
+        let forward = |voice: usize, duration: Fraction| KernItem::Timed {
+          element: xml_element(
+            "forward",
+            &[],
+            vec![
+              xml_text_element("voice", (number - 1) * VOICES_PER_STAFF + voice + 1),
+              xml_text_element("staff", number),
+            ],
+          ),
+          written: duration,
+          actual: duration,
+        };
+        if voices.iter().all(Vec::is_empty) && length > Fraction::ZERO {
+          if cursor > Fraction::ZERO {
+            items.push(KernItem::Backup(cursor));
+          }
+          items.push(forward(0, length));
+          cursor = length;
+          continue;
+        }
+        for (voice, events) in voices.into_iter().enumerate().filter(|(_, events)| !events.is_empty()) {
+          if cursor > Fraction::ZERO {
+            items.push(KernItem::Backup(cursor));
+            cursor = Fraction::ZERO;
+          }
+          for (onset, item) in events {
+            let offset = onset - self.measure_start;
+            if offset > cursor {
+              items.push(forward(voice.min(VOICES_PER_STAFF - 1), offset - cursor));
+              cursor = offset;
+            }
+            if let KernItem::Timed { element, actual, .. } = &item {
+              if !element.has_child("chord") {
+                cursor = cursor + *actual;
+              }
+            }
+            items.push(item);
+          }
+        }
+      }
+      self.parts[part].measures.push(items);
+    }
+    self.measures.push(core::mem::take(&mut self.current));
+    self.measure_start = self.now;
+    self.has_data = false;
+  }
+
+  fn time_element(&self) -> XmlElement {
+    match &self.meter {
+      Some((beats, beat_type)) => {
+        let elements = vec![
+          xml_text_element("beats", beats),
+          xml_text_element("beat-type", beat_type),
+        ];
+        match self.symbol {
+          Some(symbol) => xml_element("time", &[("symbol", symbol)], elements),
+          None => xml_element("time", &[], elements),
+        }
+      }
+      None => xml_element("time", &[], vec![xml_element("senza-misura", &[], Vec::new())]),
+    }
+  }
+
+  fn transcode_item(item: &KernItem, whole_divisions: u64) -> XmlElement {
+    match item {
+      KernItem::Element(element) => element.deep_copy(),
+      KernItem::Backup(duration) => xml_element(
+        "backup",
+        &[],
+        vec![xml_text_element("duration", duration.to_divisions(whole_divisions))],
+      ),
+      KernItem::Timed {
+        element,
+        written,
+        actual,
+      } => {
+        let mut element = element.deep_copy();
+        element
+          .elements
+          .push(xml_text_element("duration", actual.to_divisions(whole_divisions)));
+        if element.name == "note" {
+          if let Some((note_type, dots)) = written.note_type() {
+            element.elements.push(xml_text_element("type", note_type));
+            element
+              .elements
+              .extend((0..dots).map(|_| xml_element("dot", &[], Vec::new())));
+          }
+        }
+        element
+      }
+    }
+  }
+
+  fn transcode_part(&self, part_idx: usize, whole_divisions: u64) -> Vec<XmlElement> {
+    let part = &self.parts[part_idx];
+    part
+      .measures
+      .iter()
+      .enumerate()
+      .map(|(measure_idx, items)| {
+        let mut elements = Vec::new();
+        if measure_idx == 0 {
+          let mut attributes = vec![
+            xml_text_element("divisions", whole_divisions / 4),
+            KernConverter::key_element(self.initial_key.0, self.initial_key.1),
+            self.time_element(),
+          ];
+          if part.staves.len() > 1 {
+            attributes.push(xml_text_element("staves", part.staves.len()));
+          }
+          for (idx, staff) in part.staves.iter().enumerate() {
+            let mut clef = self.staves[*staff].clef.deep_copy();
+            clef.attributes.push((String::from("number"), (idx + 1).to_string()));
+            attributes.push(clef);
+          }
+          elements.push(xml_element("attributes", &[], attributes));
+          if let Some(tempo) = self.tempo.as_ref().filter(|_| part_idx == 0) {
+            elements.push(tempo.deep_copy());
+          }
+        }
+        let barlines = &self.measures[measure_idx];
+        if barlines.forward_repeat {
+          elements.push(xml_element(
+            "barline",
+            &[("location", "left")],
+            vec![
+              xml_text_element("bar-style", "heavy-light"),
+              xml_element("repeat", &[("direction", "forward")], Vec::new()),
+            ],
+          ));
+        }
+        elements.extend(items.iter().map(|item| Self::transcode_item(item, whole_divisions)));
+        if barlines.backward_repeat || barlines.bar_style.is_some() {
+          let mut barline = vec![xml_text_element(
+            "bar-style",
+            barlines.bar_style.unwrap_or("light-heavy"),
+          )];
+          if barlines.backward_repeat {
+            barline.push(xml_element("repeat", &[("direction", "backward")], Vec::new()));
+          }
+          elements.push(xml_element("barline", &[("location", "right")], barline));
+        }
+        xml_element("measure", &[("number", &(measure_idx + 1).to_string())], elements)
+      })
+      .collect()
+  }
+
+  fn transcode_score(mut self) -> Result<XmlElement, String> {
+    if self.parts.iter().all(|part| part.measures.is_empty()) {
+      return Err(String::from("No music found in the Humdrum data"));
+    }
+
+    // Choose a number of divisions able to represent every duration exactly
+    let whole_divisions = self
+      .parts
+      .iter()
+      .flat_map(|part| part.measures.iter())
+      .flat_map(|items| items.iter())
+      .try_fold(4, |divisions: u64, item| match item {
+        KernItem::Timed { actual: duration, .. } | KernItem::Backup(duration) => {
+          let divisions = divisions / gcd(divisions, duration.denominator);
+          divisions
+            .checked_mul(duration.denominator)
+            .filter(|divisions| *divisions <= MAX_WHOLE_DIVISIONS)
+        }
+        KernItem::Element(_) => Some(divisions),
+      })
+      .ok_or("Note durations in the Humdrum data are too fine to be represented")?;
+
+    // Transcode the score into an equivalent partwise MusicXML document
+    let mut contents = Vec::new();
+    if let Some(title) = self.title.take() {
+      contents.push(xml_element("work", &[], vec![xml_text_element("work-title", title)]));
+    }
+    if let Some(movement_title) = self.movement_title.take() {
+      contents.push(xml_text_element("movement-title", movement_title));
+    }
+    let mut identification = self
+      .creators
+      .iter()
+      .map(|(creator, name)| XmlElement {
+        text: name.clone(),
+        ..xml_element("creator", &[("type", creator)], Vec::new())
+      })
+      .collect::<Vec<_>>();
+    if let Some(rights) = self.rights.take() {
+      identification.push(xml_text_element("rights", rights));
+    }
+    if !identification.is_empty() {
+      contents.push(xml_element("identification", &[], identification));
+    }
+    contents.push(xml_element(
+      "part-list",
+      &[],
+      self
+        .parts
+        .iter()
+        .enumerate()
+        .map(|(idx, part)| {
+          xml_element(
+            "score-part",
+            &[("id", &format!("P{}", idx + 1))],
+            vec![xml_text_element("part-name", &part.name)],
+          )
+        })
+        .collect(),
+    ));
+    for idx in 0..self.parts.len() {
+      contents.push(xml_element(
+        "part",
+        &[("id", &format!("P{}", idx + 1))],
+        self.transcode_part(idx, whole_divisions),
+      ));
+    }
+    Ok(xml_element("score-partwise", &[("version", "4.0")], contents))
+  }
+}
+
+struct KernToken {
+  layer: usize,
+  onset: Fraction,
+  duration: Fraction,
+  text: String,
+}
+
+struct KernStaffWriter {
+  context: StaffContext,
+  tempo: Tempo,
+  onset: Fraction,
+  position: Fraction,
+  alterations: BTreeMap<(u8, usize), i32>,
+  layer: usize,
+  max_layer: usize,
+  tied: BTreeMap<usize, Vec<Pitch>>,
+  tokens: Vec<KernToken>,
+  clefs: Vec<(Fraction, ClefType)>,
+  changes: Vec<(Fraction, u8, String)>,
+  meters: Vec<(Fraction, Option<Fraction>)>,
+  repeats: Vec<(Fraction, Fraction)>,
+}
+
+impl KernStaffWriter {
+  fn new(composition: &Composition) -> Self {
+    let context = StaffContext::new(composition);
+    let mut writer = Self {
+      context,
+      tempo: *composition.get_tempo(),
+      onset: Fraction::ZERO,
+      position: Fraction::ZERO,
+      alterations: BTreeMap::new(),
+      layer: 1,
+      max_layer: 1,
+      tied: BTreeMap::new(),
+      tokens: Vec::new(),
+      clefs: vec![(Fraction::ZERO, ClefType::Treble)],
+      changes: Vec::new(),
+      meters: vec![(Fraction::ZERO, context.measure_length)],
+      repeats: Vec::new(),
+    };
+    writer.change(&[1, 2], KernConverter::key_interpretations(&context.key));
+    writer.change(&[3, 4], KernConverter::time_interpretations(&context.time_signature));
+    writer.change(&[5], vec![(5, KernConverter::tempo_interpretation(&writer.tempo))]);
+    writer
+  }
+
+  fn change(&mut self, orders: &[u8], interpretations: Vec<(u8, String)>) {
+    let onset = self.onset;
+    self
+      .changes
+      .retain(|(change_onset, order, _)| *change_onset != onset || !orders.contains(order));
+    self
+      .changes
+      .extend(interpretations.into_iter().map(|(order, text)| (onset, order, text)));
+  }
+
+  fn advance(&mut self, duration: Fraction) {
+    self.onset = self.onset + duration;
+    self.position = self.position + duration;
+    if let Some(length) = self.context.measure_length.filter(|length| length.numerator > 0) {
+      while self.position >= length {
+        self.position = self.position - length;
+        self.alterations.clear();
+      }
+    }
+  }
+
+  fn push_token(&mut self, text: String, duration: Fraction) {
+    self.tokens.push(KernToken {
+      layer: self.layer,
+      onset: self.onset,
+      duration,
+      text,
+    });
+    self.advance(duration);
+  }
+
+  fn note_text(&mut self, note: &Note, length: &str, tied: &[Pitch], tie: bool) -> String {
+    if note.is_rest() {
+      return format!("{length}r");
+    }
+
+    // Kern spells out the sounding alteration of every note
+    let key = (note.pitch.octave, note.pitch.name.index());
+    let alteration = match note.accidental {
+      Accidental::None => {
+        let key_accidental = self.context.key.accidentals()[note.pitch.name.index()];
+        self
+          .alterations
+          .get(&key)
+          .copied()
+          .unwrap_or_else(|| KernConverter::alteration(key_accidental))
+      }
+      accidental => {
+        let alteration = KernConverter::alteration(accidental);
+        self.alterations.insert(key, alteration);
+        alteration
+      }
+    };
+    let is_tied = tied.contains(&note.pitch);
+    let mut text = String::new();
+    if tie && !is_tied {
+      text.push('[');
+    }
+    text.push_str(length);
+    text.push_str(&KernConverter::pitch_text(note.pitch.name, note.pitch.octave));
+    match alteration {
+      alteration if alteration > 0 => text.push_str(&"#".repeat(alteration.unsigned_abs() as usize)),
+      alteration if alteration < 0 => text.push_str(&"-".repeat(alteration.unsigned_abs() as usize)),
+      _ if note.accidental == Accidental::Natural => text.push('n'),
+      _ => (),
+    }
+    match (is_tied, tie) {
+      (true, true) => text.push('_'),
+      (true, false) => text.push(']'),
+      _ => (),
+    }
+    text
+  }
+
+  fn write_note(&mut self, note: &Note, ratio: Fraction) {
+    let grace = note
+      .iter_modifications()
+      .find_map(|modification| match modification.r#type {
+        NoteModificationType::Grace { acciaccatura } => Some(acciaccatura),
+        _ => None,
+      });
+    let written = Fraction::from_duration(&note.duration);
+    let duration = if grace.is_some() {
+      Fraction::ZERO
+    } else {
+      written * ratio
+    };
+    let length = KernConverter::recip(if grace.is_some() { written } else { duration });
+    let tied = if grace.is_some() {
+      Vec::new()
+    } else {
+      self.tied.remove(&self.layer).unwrap_or_default()
+    };
+    let tie = !note.is_rest()
+      && note
+        .iter_modifications()
+        .any(|modification| modification.r#type == NoteModificationType::Tie);
+    let mut text = self.note_text(note, &length, &tied, tie);
+    if tie {
+      self.tied.insert(self.layer, vec![note.pitch]);
+    }
+    for modification in note.iter_modifications() {
+      text.push_str(KernConverter::note_signs(&modification.r#type));
+    }
+    if let Some(acciaccatura) = grace {
+      text.push(if acciaccatura { 'q' } else { 'Q' });
+    }
+    self.push_token(text, duration);
+  }
+
+  fn write_chord(&mut self, chord: &Chord, ratio: Fraction) {
+    // Grace notes are written just before the chord they belong to
+    let (grace_notes, notes): (Vec<_>, Vec<_>) = chord
+      .iter()
+      .map(|ChordContent::Note(note)| note)
+      .filter(|note| !note.is_rest())
+      .partition(|note| note.is_grace_note());
+    for note in grace_notes {
+      self.write_note(note, ratio);
+    }
+    let Some(first) = notes.first().copied() else {
+      return;
+    };
+    let duration = Fraction::from_duration(&first.duration) * ratio;
+    let length = KernConverter::recip(duration);
+    let chord_tie = chord
+      .iter_modifications()
+      .any(|modification| modification.r#type == ChordModificationType::Tie);
+    let tied = self.tied.remove(&self.layer).unwrap_or_default();
+    let (mut subtokens, mut tied_pitches) = (Vec::new(), Vec::new());
+    for note in notes {
+      let tie = chord_tie
+        || note
+          .iter_modifications()
+          .any(|modification| modification.r#type == NoteModificationType::Tie);
+      let mut text = self.note_text(note, &length, &tied, tie);
+      for modification in note.iter_modifications() {
+        text.push_str(KernConverter::note_signs(&modification.r#type));
+      }
+      if tie {
+        tied_pitches.push(note.pitch);
+      }
+      subtokens.push(text);
+    }
+    if !tied_pitches.is_empty() {
+      self.tied.insert(self.layer, tied_pitches);
+    }
+    for modification in chord.iter_modifications() {
+      subtokens[0].push_str(KernConverter::chord_signs(&modification.r#type));
+    }
+    self.push_token(subtokens.join(" "), duration);
+  }
+
+  fn write_phrase(&mut self, phrase: &Phrase, mut ratio: Fraction) {
+    let (layer, start) = (self.layer, self.tokens.len());
+    for modification in phrase.iter_modifications() {
+      if let PhraseModificationType::Tuplet { num_beats, into_beats } = modification.r#type {
+        if num_beats > 0 && into_beats > 0 {
+          ratio = ratio * Fraction::new(u64::from(into_beats), u64::from(num_beats));
+        }
+      }
+    }
+    for item in phrase.iter() {
+      match item {
+        PhraseContent::Note(note) => self.write_note(note, ratio),
+        PhraseContent::Chord(chord) => self.write_chord(chord, ratio),
+        PhraseContent::Phrase(phrase) => self.write_phrase(phrase, ratio),
+        PhraseContent::MultiVoice(multivoice) => self.write_multivoice(multivoice, ratio),
+      }
+    }
+
+    // Slurs run from the first to the last sounding event of the phrase within its own layer
+    let legato = phrase
+      .iter_modifications()
+      .any(|modification| modification.r#type == PhraseModificationType::Legato);
+    let events = (start..self.tokens.len())
+      .filter(|idx| self.tokens[*idx].layer == layer && self.tokens[*idx].duration > Fraction::ZERO)
+      .collect::<Vec<_>>();
+    if let (true, Some(first), Some(last)) = (legato, events.first(), events.last()) {
+      if first != last {
+        self.tokens[*first].text.insert(0, '(');
+        self.tokens[*last].text.push(')');
+      }
+    }
+  }
+
+  fn write_multivoice(&mut self, multivoice: &MultiVoice, ratio: Fraction) {
+    let phrases = multivoice
+      .iter()
+      .map(|MultiVoiceContent::Phrase(phrase)| phrase)
+      .filter(|phrase| !phrase.is_empty())
+      .collect::<Vec<_>>();
+    let (layer, max_layer, onset, position) = (self.layer, self.max_layer, self.onset, self.position);
+    let alterations = self.alterations.clone();
+
+    // Additional voices are written into sub-spines that no enclosing voice is using
+    let layers = (0..phrases.len())
+      .map(|idx| {
+        if idx == 0 {
+          layer
+        } else {
+          self.max_layer += 1;
+          self.max_layer
+        }
+      })
+      .collect::<Vec<_>>();
+    let (mut end, mut end_position, mut first_alterations) = (onset, position, None);
+    for (phrase, voice_layer) in phrases.into_iter().zip(layers) {
+      self.onset = onset;
+      self.position = position;
+      self.alterations.clone_from(&alterations);
+      self.layer = voice_layer;
+      self.write_phrase(phrase, ratio);
+      if self.onset > end {
+        end = self.onset;
+        end_position = self.position;
+      }
+      first_alterations.get_or_insert_with(|| self.alterations.clone());
+    }
+    self.layer = layer;
+    self.max_layer = max_layer;
+    self.onset = end;
+    self.position = end_position;
+    self.alterations = first_alterations.unwrap_or(alterations);
+  }
+
+  fn write_staff(&mut self, staff: &Staff) {
+    for item in staff.iter() {
+      match item {
+        StaffContent::Note(note) => self.write_note(note, Fraction::ONE),
+        StaffContent::Chord(chord) => self.write_chord(chord, Fraction::ONE),
+        StaffContent::Phrase(phrase) => self.write_phrase(phrase, Fraction::ONE),
+        StaffContent::MultiVoice(multivoice) => self.write_multivoice(multivoice, Fraction::ONE),
+        StaffContent::Direction(direction) => match &direction.r#type {
+          DirectionType::KeyChange { .. }
+          | DirectionType::TimeSignatureChange { .. }
+          | DirectionType::ClefChange { .. } => match self.context.apply(&direction.r#type) {
+            Some(ContextChange::Key(key)) => self.change(&[1, 2], KernConverter::key_interpretations(&key)),
+            Some(ContextChange::TimeSignature(time_signature)) => {
+              self.meters.push((self.onset, self.context.measure_length));
+              self.change(&[3, 4], KernConverter::time_interpretations(&time_signature));
+            }
+            Some(ContextChange::Clef(clef_type)) => {
+              let onset = self.onset;
+              self.clefs.retain(|(clef_onset, _)| *clef_onset != onset);
+              self.clefs.push((onset, clef_type));
+            }
+            None => (),
+          },
+          DirectionType::BreathMark => {
+            let layer = self.layer;
+            if let Some(token) = self
+              .tokens
+              .iter_mut()
+              .rev()
+              .find(|token| token.layer == layer && token.duration > Fraction::ZERO)
+            {
+              token.text.push(',');
+            }
+          }
+          _ => (),
+        },
+      }
+    }
+  }
+
+  fn write_section(&mut self, section: &Section, staff_name: &str) {
+    let start = self.onset;
+    for modification in section.iter_modifications() {
+      if let SectionModificationType::TempoExplicit { tempo } = &modification.r#type {
+        if *tempo != self.tempo {
+          self.tempo = *tempo;
+          self.change(&[5], vec![(5, KernConverter::tempo_interpretation(tempo))]);
+        }
+      }
+    }
+    for content in section.iter() {
+      match content {
+        SectionContent::Staff(staff) if staff.get_name() == staff_name => self.write_staff(staff),
+        SectionContent::Section(section) => self.write_section(section, staff_name),
+        SectionContent::Staff(_) => (),
+      }
+    }
+    if self.onset > start
+      && section
+        .iter_modifications()
+        .any(|modification| matches!(modification.r#type, SectionModificationType::Repeat { .. }))
+    {
+      self.repeats.push((start, self.onset));
+    }
+  }
+
+  fn end(&self) -> Fraction {
+    self
+      .tokens
+      .iter()
+      .map(|token| token.onset + token.duration)
+      .max()
+      .unwrap_or(Fraction::ZERO)
+  }
+}
+
+/// Converter between AMM compositions and Humdrum `**kern` data.
+///
+/// Humdrum data is imported by transcoding every `**kern` spine into a staff of
+/// an equivalent MusicXML document which is then loaded by the MusicXML
+/// converter. Spines sharing a `*part` interpretation belong to the same part,
+/// and spine splits become additional voices of their staff. Compositions are
+/// exported with one spine per staff, split into sub-spines wherever a staff
+/// contains more than one voice.
+pub struct KernConverter;
+
+impl KernConverter {
+  fn parse_recip(chars: &[char], idx: &mut usize) -> Option<Fraction> {
+    let digits = chars[*idx..].iter().take_while(|ch| ch.is_ascii_digit()).count();
+    let number = chars[*idx..*idx + digits].iter().collect::<String>();
+    *idx += digits;
+    let mut length = if number.chars().all(|ch| ch == '0') {
+      (digits <= 3).then(|| Fraction::new(1 << digits, 1))?
+    } else {
+      Fraction::new(1, number.parse::<u64>().ok()?)
+    };
+    if chars.get(*idx) == Some(&'%') {
+      let digits = chars[*idx + 1..].iter().take_while(|ch| ch.is_ascii_digit()).count();
+      let numerator = chars[*idx + 1..*idx + 1 + digits]
+        .iter()
+        .collect::<String>()
+        .parse::<u64>()
+        .ok()
+        .filter(|numerator| *numerator > 0)?;
+      length = length * Fraction::new(numerator, 1);
+      *idx += digits + 1;
+    }
+    let dots = chars[*idx..].iter().take_while(|ch| **ch == '.').count();
+    *idx += dots;
+    let dots = u32::try_from(dots).ok().filter(|dots| *dots <= MAX_DOTS)?;
+    Some(length * Fraction::new((1 << (dots + 1)) - 1, 1 << dots))
+  }
+
+  fn notation(sign: char) -> Option<(Option<&'static str>, &'static str)> {
+    match sign {
+      '\'' => Some((Some("articulations"), "staccato")),
+      '`' => Some((Some("articulations"), "staccatissimo")),
+      '~' => Some((Some("articulations"), "tenuto")),
+      '^' => Some((Some("articulations"), "accent")),
+      ',' => Some((Some("articulations"), "breath-mark")),
+      ';' => Some((None, "fermata")),
+      ':' => Some((None, "arpeggiate")),
+      'u' => Some((Some("technical"), "down-bow")),
+      'v' => Some((Some("technical"), "up-bow")),
+      'o' => Some((Some("technical"), "harmonic")),
+      'T' | 't' => Some((Some("ornaments"), "trill-mark")),
+      'M' | 'm' => Some((Some("ornaments"), "mordent")),
+      'W' | 'w' => Some((Some("ornaments"), "inverted-mordent")),
+      'S' => Some((Some("ornaments"), "turn")),
+      '$' => Some((Some("ornaments"), "inverted-turn")),
+      _ => None,
+    }
+  }
+
+  fn parse_event(token: &str) -> Result<KernEvent, String> {
+    let mut event = KernEvent {
+      length: None,
+      notes: Vec::new(),
+      grace: None,
+      invisible: false,
+    };
+    for subtoken in token.split(' ').filter(|subtoken| !subtoken.is_empty()) {
+      let chars = subtoken.chars().collect::<Vec<_>>();
+      let (mut note, mut rest, mut idx) = (KernNote::default(), false, 0);
+      while idx < chars.len() {
+        let sign = chars[idx];
+        match sign {
+          '0'..='9' => {
+            let length = Self::parse_recip(&chars, &mut idx)
+              .ok_or_else(|| format!("Invalid duration in **kern token \"{token}\""))?;
+            event.length.get_or_insert(length);
+            continue;
+          }
+          'a'..='g' | 'A'..='G' => {
+            let count = chars[idx..].iter().take_while(|ch| **ch == sign).count();
+            let count = i32::try_from(count).unwrap_or(i32::MAX);
+            let octave = if sign.is_ascii_lowercase() {
+              3 + count
+            } else {
+              4 - count
+            };
+            note.pitch = Some((sign.to_ascii_uppercase(), octave, 0));
+            idx += count as usize;
+            continue;
+          }
+          'r' => rest = true,
+          '#' | '-' => {
+            if let Some((_, _, alter)) = note.pitch.as_mut() {
+              *alter += if sign == '#' { 1 } else { -1 };
+            }
+          }
+          'n' | 'X' => note.explicit = true,
+          '[' => note.tie_start = true,
+          ']' => note.tie_stop = true,
+          '_' => (note.tie_start, note.tie_stop) = (true, true),
+          '(' => note.slur_starts += 1,
+          ')' => note.slur_stops += 1,
+          'q' => event.grace = Some(true),
+          'Q' => event.grace = Some(false),
+          'y' => event.invisible = true,
+          '^' if chars.get(idx + 1) == Some(&'^') => {
+            note.notations.push((Some("articulations"), "strong-accent"));
+            idx += 1;
+          }
+          _ => note.notations.extend(Self::notation(sign)),
+        }
+        idx += 1;
+      }
+      if rest {
+        note.pitch = None;
+      } else if note.pitch.is_none() {
+        return Err(format!("Invalid **kern token \"{token}\""));
+      }
+      event.notes.push(note);
+    }
+    if event.notes.is_empty() {
+      Err(format!("Invalid **kern token \"{token}\""))
+    } else if event.length.is_none() && event.grace.is_none() {
+      Err(format!("Missing duration in **kern token \"{token}\""))
+    } else {
+      Ok(event)
+    }
+  }
+
+  fn written_length(actual: Fraction) -> (Fraction, Option<(u64, u64)>) {
+    if actual.note_type().is_some() {
+      return (actual, None);
+    }
+
+    // Durations outside the binary hierarchy are written as the next longer binary duration within a tuplet
+    (0..=MAX_DOTS)
+      .find_map(|dots| {
+        let dotted = Fraction::new((1 << (dots + 1)) - 1, 1 << dots);
+        let base = actual / dotted;
+        let normal = 1 << (63 - base.denominator.leading_zeros());
+        let written = Fraction::new(base.numerator, normal) * dotted;
+        let divisor = gcd(base.denominator, normal);
+        written
+          .note_type()
+          .map(|_| (written, Some((base.denominator / divisor, normal / divisor))))
+      })
+      .unwrap_or((actual, None))
+  }
+
+  fn parse_key_signature(signature: &str) -> BTreeMap<char, i32> {
+    let mut key = BTreeMap::new();
+    let mut step = None;
+    for ch in signature.chars() {
+      match ch {
+        'a'..='g' => step = Some(ch),
+        '#' | '-' => {
+          if let Some(step) = step {
+            *key.entry(step).or_default() += if ch == '#' { 1 } else { -1 };
+          }
+        }
+        _ => (),
+      }
+    }
+    key
+  }
+
+  fn parse_clef(clef: &str) -> Option<XmlElement> {
+    let sign = match clef.chars().next()? {
+      'G' => "G",
+      'F' => "F",
+      'C' => "C",
+      _ => return None,
+    };
+    let modifiers = &clef[1..clef.len() - clef.trim_start_matches(|ch: char| !ch.is_ascii_digit()).len()];
+    let octave_change = modifiers.matches('^').count() as i32 - modifiers.matches('v').count() as i32;
+    let line = clef[1 + modifiers.len()..]
+      .parse::<u8>()
+      .ok()
+      .filter(|line| (1..=5).contains(line))?;
+    Some(Self::clef_element(sign, line, octave_change))
+  }
+
+  fn clef_element(sign: &str, line: u8, octave_change: i32) -> XmlElement {
+    let mut elements = vec![xml_text_element("sign", sign), xml_text_element("line", line)];
+    if octave_change != 0 {
+      elements.push(xml_text_element("clef-octave-change", octave_change));
+    }
+    xml_element("clef", &[], elements)
+  }
+
+  fn key_element(fifths: i32, minor: bool) -> XmlElement {
+    xml_element(
+      "key",
+      &[],
+      vec![
+        xml_text_element("fifths", fifths.clamp(-7, 7)),
+        xml_text_element("mode", if minor { "minor" } else { "major" }),
+      ],
+    )
+  }
+
+  fn tempo_direction(beats_per_minute: f64) -> XmlElement {
+    let per_minute = beats_per_minute.to_string();
+    xml_element(
+      "direction",
+      &[("placement", "above")],
+      vec![
+        xml_element(
+          "direction-type",
+          &[],
+          vec![xml_element(
+            "metronome",
+            &[],
+            vec![
+              xml_text_element("beat-unit", "quarter"),
+              xml_text_element("per-minute", &per_minute),
+            ],
+          )],
+        ),
+        xml_element("sound", &[("tempo", &per_minute)], Vec::new()),
+        xml_text_element("staff", 1),
+      ],
+    )
+  }
+
+  fn load_from_kern(data: &[u8]) -> Result<Composition, String> {
+    let reader = KernReader::parse(&String::from_utf8_lossy(data))?;
+    MusicXmlConverter::load_from_musicxml(&ScorePartwise::deserialize(&reader.transcode_score()?)?)
+  }
+
+  fn recip(duration: Fraction) -> String {
+    (0..=MAX_DOTS)
+      .find_map(|dots| {
+        let base = duration / Fraction::new((1 << (dots + 1)) - 1, 1 << dots);
+        let number = match (base.numerator, base.denominator) {
+          (1, denominator) => denominator.to_string(),
+          (2, 1) => String::from("0"),
+          (4, 1) => String::from("00"),
+          (8, 1) => String::from("000"),
+          _ => return None,
+        };
+        Some(number + &".".repeat(dots as usize))
+      })
+      .unwrap_or_else(|| format!("{}%{}", duration.denominator, duration.numerator))
+  }
+
+  fn pitch_text(name: PitchName, octave: u8) -> String {
+    let letter = match name {
+      PitchName::A => 'a',
+      PitchName::B => 'b',
+      PitchName::C | PitchName::Rest => 'c',
+      PitchName::D => 'd',
+      PitchName::E => 'e',
+      PitchName::F => 'f',
+      PitchName::G => 'g',
+    };
+    if octave >= 4 {
+      String::from(letter).repeat(usize::from(octave - 3))
+    } else {
+      String::from(letter.to_ascii_uppercase()).repeat(usize::from(4 - octave))
+    }
+  }
+
+  fn alteration(accidental: Accidental) -> i32 {
+    match accidental {
+      Accidental::Sharp => 1,
+      Accidental::Flat => -1,
+      Accidental::DoubleSharp => 2,
+      Accidental::DoubleFlat => -2,
+      Accidental::Natural | Accidental::None => 0,
+    }
+  }
+
+  fn note_signs(modification: &NoteModificationType) -> &'static str {
+    match modification {
+      NoteModificationType::Accent => "^",
+      NoteModificationType::DownBow => "u",
+      NoteModificationType::Fermata => ";",
+      NoteModificationType::Harmonic { .. } => "o",
+      NoteModificationType::Marcato => "^^",
+      NoteModificationType::Mordent { upper: true } => "M",
+      NoteModificationType::Mordent { upper: false } => "W",
+      NoteModificationType::Staccato => "'",
+      NoteModificationType::Staccatissimo => "`",
+      NoteModificationType::Tenuto => "~",
+      NoteModificationType::Trill { .. } => "T",
+      NoteModificationType::Turn { upper: true, .. } => "S",
+      NoteModificationType::Turn { upper: false, .. } => "$",
+      NoteModificationType::UpBow => "v",
+      _ => "",
+    }
+  }
+
+  fn chord_signs(modification: &ChordModificationType) -> &'static str {
+    match modification {
+      ChordModificationType::Accent => "^",
+      ChordModificationType::Arpeggiate => ":",
+      ChordModificationType::DownBow => "u",
+      ChordModificationType::Fermata => ";",
+      ChordModificationType::Marcato => "^^",
+      ChordModificationType::Staccato => "'",
+      ChordModificationType::Staccatissimo => "`",
+      ChordModificationType::Tenuto => "~",
+      ChordModificationType::UpBow => "v",
+      _ => "",
+    }
+  }
+
+  fn key_interpretations(key: &Key) -> Vec<(u8, String)> {
+    let fifths = i32::from(key.fifths()).clamp(-7, 7);
+    let accidentals = if fifths >= 0 {
+      SHARPS[..fifths.unsigned_abs() as usize]
+        .iter()
+        .map(|step| format!("{step}#"))
+        .collect::<String>()
+    } else {
+      SHARPS
+        .iter()
+        .rev()
+        .take(fifths.unsigned_abs() as usize)
+        .map(|step| format!("{step}-"))
+        .collect::<String>()
+    };
+    let tonic = match key.mode {
+      KeyMode::Major => MAJOR_TONICS[(fifths + 7) as usize],
+      KeyMode::Minor => MINOR_TONICS[(fifths + 7) as usize],
+    };
+    vec![(1, format!("*k[{accidentals}]")), (2, format!("*{tonic}:"))]
+  }
+
+  fn time_interpretations(time_signature: &TimeSignature) -> Vec<(u8, String)> {
+    match time_signature.signature {
+      TimeSignatureType::CommonTime => vec![(3, String::from("*M4/4")), (4, String::from("*met(c)"))],
+      TimeSignatureType::CutTime => vec![(3, String::from("*M2/2")), (4, String::from("*met(c|)"))],
+      TimeSignatureType::None => Vec::new(),
+      TimeSignatureType::Explicit => vec![(
+        3,
+        format!("*M{}/{}", time_signature.numerator, time_signature.denominator),
+      )],
+    }
+  }
+
+  fn tempo_interpretation(tempo: &Tempo) -> String {
+    let beat = Fraction::from_duration(&tempo.base_note);
+    let quarters_per_minute = f64::from(tempo.beats_per_minute) * 4.0 * beat.numerator as f64 / beat.denominator as f64;
+    format!("*MM{quarters_per_minute}")
+  }
+
+  fn clef_interpretation(clef_type: ClefType) -> &'static str {
+    match clef_type {
+      ClefType::Treble => "*clefG2",
+      ClefType::FrenchViolin => "*clefG1",
+      ClefType::Bass => "*clefF4",
+      ClefType::Baritone => "*clefF3",
+      ClefType::Subbass => "*clefF5",
+      ClefType::Alto => "*clefC3",
+      ClefType::Tenor => "*clefC4",
+      ClefType::Soprano => "*clefC1",
+      ClefType::MezzoSoprano => "*clefC2",
+    }
+  }
+
+  fn rests(mut onset: Fraction, end: Fraction, tokens: &mut Vec<(Fraction, bool, String)>) {
+    // Gaps are filled with the fewest invisible rests of undotted durations that add up to them
+    let mut value = Fraction::new(4, 1);
+    while onset < end && value >= Fraction::new(1, 2048) {
+      if end - onset >= value {
+        tokens.push((onset, false, format!("{}ryy", Self::recip(value))));
+        onset = onset + value;
+      } else {
+        value = value * Fraction::new(1, 2);
+      }
+    }
+  }
+
+  fn line(tokens: impl IntoIterator<Item = String>) -> String {
+    tokens.into_iter().collect::<Vec<_>>().join("\t")
+  }
+
+  fn save_to_kern(composition: &Composition) -> String {
+    let mut lines = Vec::new();
+    let title = composition.get_title();
+    if !title.is_empty() {
+      lines.push(format!("!!!OTL: {title}"));
+    }
+    if let Some(movement_title) = composition.get_metadata().get("movement_title") {
+      lines.push(format!("!!!OMD: {movement_title}"));
+    }
+    for (reference, names) in [
+      ("COM", composition.get_composers()),
+      ("LYR", composition.get_lyricists()),
+      ("ARR", composition.get_arrangers()),
+    ] {
+      lines.extend(names.iter().map(|name| format!("!!!{reference}: {name}")));
+    }
+    if let Some(publisher) = composition.get_publisher() {
+      lines.push(format!("!!!PPR: {publisher}"));
+    }
+    if let Some(copyright) = composition.get_copyright() {
+      lines.push(format!("!!!YEC: {copyright}"));
+    }
+
+    // Collect the tokens of every staff, ordering the spines from the lowest staff to the highest
+    let mut staves = Vec::new();
+    for (part_idx, part) in composition.iter().enumerate() {
+      for staff_name in part.get_staff_names() {
+        let mut writer = KernStaffWriter::new(composition);
+        for PartContent::Section(section) in part.iter() {
+          writer.write_section(section, &staff_name);
+        }
+        staves.push((part_idx + 1, staves.len() + 1, part.get_name(), writer));
+      }
+    }
+    staves.reverse();
+    if staves.is_empty() {
+      lines.extend([String::from("**kern"), String::from("*-")]);
+      return lines.join("\n") + "\n";
+    }
+    lines.push(Self::line(staves.iter().map(|_| String::from("**kern"))));
+    lines.push(Self::line(staves.iter().map(|(part, ..)| format!("*part{part}"))));
+    lines.push(Self::line(staves.iter().map(|(_, staff, ..)| format!("*staff{staff}"))));
+    lines.push(Self::line(staves.iter().map(|(_, _, name, _)| format!("*I\"{name}"))));
+
+    // Divide the music into measures according to the time signatures of the first staff
+    let primary = &staves[staves.len() - 1].3;
+    let end = staves
+      .iter()
+      .map(|(.., writer)| writer.end())
+      .max()
+      .unwrap_or(Fraction::ZERO);
+    let starts = measure_starts(&primary.meters, end);
+    let measure_of = |onset: Fraction| starts.partition_point(|start| *start <= onset).saturating_sub(1);
+    let last_measure_of = |end: Fraction| starts.partition_point(|start| *start < end).saturating_sub(1);
+    let repeat_starts = primary
+      .repeats
+      .iter()
+      .map(|(start, _)| measure_of(*start))
+      .collect::<BTreeSet<_>>();
+    let repeat_ends = primary
+      .repeats
+      .iter()
+      .map(|(_, end)| last_measure_of(*end))
+      .collect::<BTreeSet<_>>();
+    let mut layers = vec![1; staves.len()];
+    let mut cursors: BTreeMap<(usize, usize), Fraction> = BTreeMap::new();
+    for (measure, start) in starts.iter().copied().enumerate() {
+      let measure_end = starts.get(measure + 1).copied().unwrap_or(end);
+      let columns_of = |layers: &[usize]| layers.iter().sum::<usize>();
+      let repeat_end = measure > 0 && repeat_ends.contains(&(measure - 1));
+      let barline = match (repeat_end, repeat_starts.contains(&measure)) {
+        (true, true) => format!("={}:|!|:", measure + 1),
+        (true, false) => format!("={}:|!", measure + 1),
+        (false, true) => format!("={}!|:", measure + 1),
+        (false, false) if measure > 0 => format!("={}", measure + 1),
+        (false, false) => String::new(),
+      };
+      if !barline.is_empty() {
+        lines.push(Self::line((0..columns_of(&layers)).map(|_| barline.clone())));
+      }
+
+      // Split and merge the spines of every staff to match the number of voices it contains
+      let mut columns = Vec::new();
+      for (staff, (.., writer)) in staves.iter().enumerate() {
+        let mut tokens: BTreeMap<usize, Vec<(Fraction, bool, String)>> = BTreeMap::new();
+        for token in writer.tokens.iter().filter(|token| measure_of(token.onset) == measure) {
+          tokens.entry(token.layer).or_default().push((
+            token.onset,
+            token.duration == Fraction::ZERO,
+            token.text.clone(),
+          ));
+        }
+        let count = tokens.keys().copied().max().unwrap_or(1);
+        let mut staff_columns = Vec::new();
+        for layer in 1..=count {
+          let mut cursor = cursors.get(&(staff, layer)).copied().unwrap_or(start).max(start);
+          let mut column = Vec::new();
+          for (onset, grace, text) in tokens.remove(&layer).unwrap_or_default() {
+            if onset > cursor {
+              Self::rests(cursor, onset, &mut column);
+            }
+            let duration = writer
+              .tokens
+              .iter()
+              .find(|token| token.onset == onset && token.layer == layer && token.text == text)
+              .map_or(Fraction::ZERO, |token| token.duration);
+            cursor = cursor.max(onset + duration);
+            column.push((onset, grace, text));
+          }
+          if cursor < measure_end {
+            Self::rests(cursor, measure_end, &mut column);
+          }
+          cursors.insert((staff, layer), cursor);
+          staff_columns.push(column);
+        }
+        columns.push(staff_columns);
+      }
+      for staff in 0..staves.len() {
+        let target = columns[staff].len();
+        if layers[staff] > target {
+          lines.push(Self::line(layers.iter().enumerate().flat_map(|(other, count)| {
+            (1..=*count).map(move |layer| String::from(if other == staff && layer >= target { "*v" } else { "*" }))
+          })));
+          layers[staff] = target;
+        }
+      }
+      while layers
+        .iter()
+        .zip(&columns)
+        .any(|(count, staff_columns)| *count < staff_columns.len())
+      {
+        lines.push(Self::line(layers.iter().zip(&columns).flat_map(
+          |(count, staff_columns)| {
+            let split = *count < staff_columns.len();
+            (1..=*count).map(move |layer| String::from(if split && layer == *count { "*^" } else { "*" }))
+          },
+        )));
+        for (count, staff_columns) in layers.iter_mut().zip(&columns) {
+          *count = (*count + 1).min(staff_columns.len()).max(*count);
+        }
+      }
+
+      // Write interpretations, grace notes, and events at every onset within the measure
+      let in_measure = |onset: Fraction| onset >= start && (onset < measure_end || (measure == starts.len() - 1));
+      let mut onsets = columns
+        .iter()
+        .flatten()
+        .flatten()
+        .map(|(onset, ..)| *onset)
+        .chain(
+          staves
+            .iter()
+            .flat_map(|(.., writer)| writer.clefs.iter().map(|(onset, _)| *onset)),
+        )
+        .chain(primary.changes.iter().map(|(onset, ..)| *onset))
+        .filter(|onset| in_measure(*onset))
+        .collect::<Vec<_>>();
+      onsets.sort_unstable();
+      onsets.dedup();
+      for onset in onsets {
+        let clef_line = staves.iter().enumerate().flat_map(|(staff, (.., writer))| {
+          let clef = writer
+            .clefs
+            .iter()
+            .find(|(clef_onset, _)| *clef_onset == onset)
+            .map(|(_, clef)| Self::clef_interpretation(*clef));
+          (0..layers[staff]).map(move |_| String::from(clef.unwrap_or("*")))
+        });
+        let clef_line = clef_line.collect::<Vec<_>>();
+        if clef_line.iter().any(|token| token != "*") {
+          lines.push(Self::line(clef_line));
+        }
+        for order in 1..=5 {
+          if let Some((.., text)) = primary
+            .changes
+            .iter()
+            .find(|(change_onset, change_order, _)| *change_onset == onset && *change_order == order)
+          {
+            lines.push(Self::line((0..columns_of(&layers)).map(|_| text.clone())));
+          }
+        }
+        let tokens_at = |grace: bool| {
+          columns
+            .iter()
+            .flatten()
+            .map(|column| {
+              column
+                .iter()
+                .filter(|(token_onset, token_grace, _)| *token_onset == onset && *token_grace == grace)
+                .map(|(.., text)| text.clone())
+                .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+        };
+        let graces = tokens_at(true);
+        for idx in 0..graces.iter().map(Vec::len).max().unwrap_or_default() {
+          lines.push(Self::line(
+            graces
+              .iter()
+              .map(|tokens| tokens.get(idx).cloned().unwrap_or_else(|| String::from("."))),
+          ));
+        }
+        let events = tokens_at(false);
+        if events.iter().any(|tokens| !tokens.is_empty()) {
+          lines.push(Self::line(
+            events
+              .into_iter()
+              .map(|tokens| tokens.into_iter().next().unwrap_or_else(|| String::from("."))),
+          ));
+        }
+      }
+    }
+    let final_barline = if repeat_ends.contains(&(starts.len() - 1)) {
+      "=:|!"
+    } else {
+      "=="
+    };
+    lines.push(Self::line(
+      layers
+        .iter()
+        .flat_map(|count| (0..*count).map(|_| String::from(final_barline))),
+    ));
+    lines.push(Self::line(
+      layers.iter().flat_map(|count| (0..*count).map(|_| String::from("*-"))),
+    ));
+    lines.join("\n") + "\n"
+  }
+}
+
+impl Load for KernConverter {
+  // Humdrum data is transcoded into an equivalent MusicXML document which is
+  // then loaded by the MusicXML converter, so both formats share the same structure
+  fn load_bytes(data: &[u8]) -> Result<Composition, AmmError> {
+    KernConverter::load_from_kern(data).map_err(AmmError::from)
+  }
+}
+
+impl Store for KernConverter {
+  fn save_bytes(composition: &Composition) -> Result<Vec<u8>, AmmError> {
+    Ok(KernConverter::save_to_kern(composition).into_bytes())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::storage::Storage;
+
+  const DOCUMENT: &str = "!!!OTL: Little Study
+!!!OMD: First Movement
+!!!COM: A. Composer
+!!!YEC: Public Domain
+**kern\t**kern\t**kern
+*part2\t*part2\t*part1
+*staff3\t*staff2\t*staff1
+*I\"Piano\t*I\"Piano\t*I\"Flute
+*clefF4\t*clefG2\t*clefG2
+*k[f#]\t*k[f#]\t*k[f#]
+*G:\t*G:\t*G:
+*M3/4\t*M3/4\t*M3/4
+*MM96\t*MM96\t*MM96
+=1!|:\t=1!|:\t=1!|:
+*^\t*\t*
+2.GG\t4r\t2.g 2.b 2.dd\t(8g'
+.\t.\t.\t8a
+.\t4D\t.\t12b
+.\t.\t.\t12cc#
+.\t.\t.\t12dd)
+.\t4F#\t.\t4r
+=2\t=2\t=2\t=2
+*v\t*v\t*\t*
+2.GG\t2.d\t2.g;
+=:|!\t=:|!\t=:|!
+*-\t*-\t*-
+";
+
+  fn count_notes(composition: &Composition) -> Vec<usize> {
+    composition
+      .iter()
+      .map(|part| {
+        part
+          .iter_timeslices()
+          .flat_map(|slice| slice.content.into_iter())
+          .filter(|content| !content.note.is_rest())
+          .count()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_load_kern() {
+    let composition = KernConverter::load_bytes(DOCUMENT.as_bytes()).unwrap();
+    assert_eq!(composition.get_title(), "Little Study");
+    assert_eq!(composition.get_composers(), ["A. Composer"]);
+    assert_eq!(composition.get_copyright().as_deref(), Some("Public Domain"));
+    assert_eq!(
+      composition.get_metadata().get("movement_title").unwrap(),
+      "First Movement"
+    );
+    assert_eq!(composition.get_part_names(), ["Flute", "Piano"]);
+    assert_eq!(
+      *composition.get_starting_key(),
+      Key::from_fifths(1, Some(KeyMode::Major))
+    );
+    assert_eq!(
+      *composition.get_starting_time_signature(),
+      TimeSignature::new_explicit(3, 4)
+    );
+    assert_eq!(composition.get_tempo().beats_per_minute, 96);
+    assert_eq!(count_notes(&composition), [12, 16]);
+    assert!(KernConverter::load_bytes(b"**text\n*-\n").is_err());
+    assert!(KernConverter::load_bytes(b"**kern\t**kern\n4c\n*-\t*-\n").is_err());
+    assert!(KernConverter::load_bytes(b"**kern\n4x\n*-\n").is_err());
+  }
+
+  #[test]
+  fn test_save_kern() {
+    let composition = KernConverter::load_bytes(DOCUMENT.as_bytes()).unwrap();
+    let kern = KernConverter::save_to_kern(&composition);
+    assert!(kern.starts_with("!!!OTL: Little Study\n"));
+    assert!(kern.contains("!!!COM: A. Composer\n"));
+    assert!(kern.contains("**kern\t**kern\t**kern\n"));
+    assert!(kern.contains("*I\"Piano\t*I\"Piano\t*I\"Flute\n"));
+    assert!(kern.contains("*clefF4\t*clefG2\t*clefG2\n"));
+    assert!(kern.contains("*k[f#]\t*k[f#]\t*k[f#]\n"));
+    assert!(kern.contains("*M3/4\t*M3/4\t*M3/4\n"));
+    assert!(kern.contains("*MM96\t*MM96\t*MM96\n"));
+    assert!(kern.contains("=1!|:\t=1!|:\t=1!|:\n"));
+    assert!(kern.contains("*^\t*\t*\n"));
+    assert!(kern.contains("*v\t*v\t*\t*\n"));
+    assert!(kern.contains("12cc#"));
+    assert!(kern.contains("(8g'"));
+    assert!(kern.contains("2.g;"));
+    assert!(kern.ends_with("=:|!\t=:|!\t=:|!\n*-\t*-\t*-\n"));
+
+    let reloaded = KernConverter::load_bytes(kern.as_bytes()).unwrap();
+    assert_eq!(reloaded.get_title(), "Little Study");
+    assert_eq!(reloaded.get_part_names(), ["Flute", "Piano"]);
+    assert_eq!(count_notes(&reloaded), count_notes(&composition));
+  }
+
+  #[test]
+  fn test_save_kern_example() {
+    let composition = Storage::MusicXML.load("examples/Hymn_to_Freedom.mxl").unwrap();
+    let kern = KernConverter::save_to_kern(&composition);
+    assert!(kern.contains("*^"));
+    let reloaded = KernConverter::load_bytes(kern.as_bytes()).unwrap();
+    assert_eq!(reloaded.get_part_names(), composition.get_part_names());
+    assert_eq!(count_notes(&reloaded), count_notes(&composition));
+  }
+}
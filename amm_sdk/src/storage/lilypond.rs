@@ -0,0 +1,946 @@
+use super::util::{tempo_marking_text, ContextChange, Fraction, StaffContext};
+use crate::context::{ClefType, Dynamic, Key, KeyMode, KeySignature, Tempo, TimeSignature, TimeSignatureType};
+use crate::modification::{
+  ChordModificationType, Direction, DirectionType, NoteModificationType, PedalType, PhraseModificationType,
+  SectionModificationType, TextPlacement,
+};
+use crate::note::{Accidental, Duration, DurationType, Note, PitchName};
+use crate::structure::{
+  Chord, ChordContent, MultiVoice, MultiVoiceContent, PartContent, Phrase, PhraseContent, Section, SectionContent,
+  Staff, StaffContent,
+};
+use crate::Composition;
+use alloc::{
+  collections::BTreeMap,
+  string::{String, ToString},
+  vec::Vec,
+};
+
+const LILYPOND_VERSION: &str = "2.24.0";
+const MIDDLE_C_STEP: i32 = 4 * 7;
+
+struct LilyPondWriter {
+  output: String,
+  relative: bool,
+  reference: Option<i32>,
+  context: StaffContext,
+  tempo: Tempo,
+  write_tempo: bool,
+  position: Fraction,
+  elapsed: Fraction,
+  alterations: BTreeMap<i32, Accidental>,
+  post_events: String,
+  last_event: Option<usize>,
+  grace: Option<bool>,
+  swell: Option<(usize, String)>,
+}
+
+impl LilyPondWriter {
+  fn new(composition: &Composition, relative: bool, write_tempo: bool) -> Self {
+    Self {
+      output: String::new(),
+      relative,
+      reference: None,
+      context: StaffContext::new(composition),
+      tempo: *composition.get_tempo(),
+      write_tempo,
+      position: Fraction::ZERO,
+      elapsed: Fraction::ZERO,
+      alterations: BTreeMap::new(),
+      post_events: String::new(),
+      last_event: None,
+      grace: None,
+      swell: None,
+    }
+  }
+
+  fn voice_writer(&self) -> Self {
+    Self {
+      output: String::new(),
+      relative: self.relative,
+      reference: self.reference,
+      context: self.context,
+      tempo: self.tempo,
+      write_tempo: false,
+      position: self.position,
+      elapsed: Fraction::ZERO,
+      alterations: self.alterations.clone(),
+      post_events: String::new(),
+      last_event: None,
+      grace: None,
+      swell: None,
+    }
+  }
+
+  fn separate(&mut self) {
+    if !self.output.is_empty() && !self.output.ends_with([' ', '\n']) {
+      self.output.push(' ');
+    }
+  }
+
+  fn command(&mut self, command: &str) {
+    self.close_grace();
+    self.separate();
+    self.output.push_str(command);
+  }
+
+  fn close_grace(&mut self) {
+    if self.grace.take().is_some() {
+      self.output.push_str(" }");
+    }
+  }
+
+  fn advance(&mut self, duration: Fraction) -> bool {
+    self.position = self.position + duration;
+    self.elapsed = self.elapsed + duration;
+    let mut barline = false;
+    if let Some(length) = self.context.measure_length.filter(|length| length.numerator > 0) {
+      while self.position >= length {
+        self.position = self.position - length;
+        self.alterations.clear();
+        barline = true;
+      }
+    }
+    barline
+  }
+
+  fn attach(&mut self, events: &str) {
+    // Post-events closing a phrase belong to its last note, even if the phrase has already been closed
+    if let Some(idx) = self.last_event {
+      self.output.insert_str(idx, events);
+      self.last_event = Some(idx + events.len());
+    }
+  }
+
+  fn pitch_text(&mut self, note: &Note) -> String {
+    let step = LilyPondConverter::step(note);
+    let accidental = match note.accidental {
+      Accidental::None => self
+        .alterations
+        .get(&step)
+        .copied()
+        .unwrap_or(self.context.key.accidentals()[note.pitch.name.index()]),
+      accidental => {
+        self.alterations.insert(step, accidental);
+        accidental
+      }
+    };
+    let mut text = LilyPondConverter::pitch_name_text(note.pitch.name, accidental);
+    let octaves = match self.reference.filter(|_| self.relative) {
+      Some(reference) => {
+        // Relative pitches are placed within a fourth of the previous pitch, unless shifted by octave marks
+        let mut base = reference - reference.rem_euclid(7) + step.rem_euclid(7);
+        while base - reference > 3 {
+          base -= 7;
+        }
+        while reference - base > 3 {
+          base += 7;
+        }
+        (step - base) / 7
+      }
+      None => i32::from(note.pitch.octave) - 3,
+    };
+    self.reference = Some(step);
+    text.push_str(&LilyPondConverter::octave_text(octaves));
+    text
+  }
+
+  fn begin(&mut self, grace: Option<bool>) {
+    if self.grace != grace {
+      self.close_grace();
+      if let Some(acciaccatura) = grace {
+        self.separate();
+        self
+          .output
+          .push_str(if acciaccatura { "\\acciaccatura {" } else { "\\grace {" });
+        self.grace = grace;
+      }
+    }
+    self.separate();
+  }
+
+  fn finish_event(&mut self, duration: Fraction, ratio: Fraction, grace: bool) {
+    if !grace {
+      let post_events = core::mem::take(&mut self.post_events);
+      self.output.push_str(&post_events);
+    }
+    self.last_event = Some(self.output.len());
+    if let Some((remaining, events)) = self.swell.as_mut().filter(|_| !grace) {
+      *remaining = remaining.saturating_sub(1);
+      if *remaining == 0 {
+        let events = core::mem::take(events);
+        self.swell = None;
+        self.post_events.push_str(&events);
+      }
+    }
+    if !grace && self.advance(duration * ratio) {
+      // Bar checks keep the source readable and let LilyPond verify that every measure is complete
+      self.output.push_str(" |\n");
+    }
+  }
+
+  fn write_note(&mut self, note: &Note, ratio: Fraction) {
+    let grace = note
+      .iter_modifications()
+      .find_map(|modification| match modification.r#type {
+        NoteModificationType::Grace { acciaccatura } => Some(acciaccatura),
+        _ => None,
+      });
+    self.begin(grace);
+    if note.is_rest() {
+      self.output.push('r');
+    } else {
+      let pitch = self.pitch_text(note);
+      self.output.push_str(&pitch);
+    }
+    self.output.push_str(&LilyPondConverter::duration_text(&note.duration));
+    for modification in note.iter_modifications() {
+      if let NoteModificationType::Tremolo { relative_speed } = modification.r#type {
+        self
+          .output
+          .push_str(&format!(":{}", 8u32 << u32::from(relative_speed.clamp(1, 5) - 1)));
+      }
+    }
+    if note
+      .iter_modifications()
+      .any(|modification| modification.r#type == NoteModificationType::Tie)
+    {
+      self.output.push('~');
+    }
+    for modification in note.iter_modifications() {
+      self
+        .output
+        .push_str(&LilyPondConverter::note_articulation(&modification.r#type).unwrap_or_default());
+    }
+    self.finish_event(Fraction::from_duration(&note.duration), ratio, grace.is_some());
+  }
+
+  fn write_chord(&mut self, chord: &Chord, ratio: Fraction) {
+    // Grace notes cannot appear within a LilyPond chord, so they are written just before it
+    let (grace_notes, notes): (Vec<_>, Vec<_>) = chord
+      .iter()
+      .map(|ChordContent::Note(note)| note)
+      .filter(|note| !note.is_rest())
+      .partition(|note| note.is_grace_note());
+    for note in grace_notes {
+      self.write_note(note, ratio);
+    }
+    let Some(first) = notes.first() else {
+      return;
+    };
+    self.begin(None);
+    self.output.push('<');
+    let mut chord_reference = None;
+    for (idx, note) in notes.iter().enumerate() {
+      if idx > 0 {
+        self.output.push(' ');
+      }
+      let pitch = self.pitch_text(note);
+      self.output.push_str(&pitch);
+      chord_reference = chord_reference.or(self.reference);
+      if note
+        .iter_modifications()
+        .any(|modification| modification.r#type == NoteModificationType::Tie)
+      {
+        self.output.push('~');
+      }
+    }
+    // Within relative mode, the note following a chord is relative to the first note of the chord
+    self.reference = chord_reference;
+    self.output.push('>');
+    self.output.push_str(&LilyPondConverter::duration_text(&first.duration));
+    for modification in chord.iter_modifications() {
+      match modification.r#type {
+        ChordModificationType::Tie => self.output.push('~'),
+        ChordModificationType::Tremolo { relative_speed } => self
+          .output
+          .push_str(&format!(":{}", 8u32 << u32::from(relative_speed.clamp(1, 5) - 1))),
+        _ => (),
+      }
+    }
+    for modification in chord.iter_modifications() {
+      self
+        .output
+        .push_str(&LilyPondConverter::chord_articulation(&modification.r#type).unwrap_or_default());
+    }
+    self.finish_event(Fraction::from_duration(&first.duration), ratio, false);
+  }
+
+  fn write_phrase(&mut self, phrase: &Phrase, mut ratio: Fraction) {
+    if phrase.is_empty() {
+      return;
+    }
+    let (mut opening, mut closing, mut after) = (String::new(), String::new(), Vec::new());
+    let mut tuplet = false;
+    for modification in phrase.iter_modifications() {
+      match &modification.r#type {
+        PhraseModificationType::Tuplet { num_beats, into_beats } => {
+          self.command(&format!("\\tuplet {num_beats}/{into_beats} {{"));
+          ratio = ratio * Fraction::new(u64::from(*into_beats), u64::from(*num_beats));
+          tuplet = true;
+        }
+        PhraseModificationType::Legato => {
+          opening.push('(');
+          closing.push(')');
+        }
+        PhraseModificationType::Crescendo { final_dynamic } | PhraseModificationType::Decrescendo { final_dynamic } => {
+          opening.push_str(
+            if matches!(modification.r#type, PhraseModificationType::Crescendo { .. }) {
+              "\\<"
+            } else {
+              "\\>"
+            },
+          );
+          match final_dynamic.and_then(|dynamic| LilyPondConverter::dynamic_text(&dynamic)) {
+            Some(dynamic) => closing.push_str(&format!("\\{dynamic}")),
+            None => closing.push_str("\\!"),
+          }
+        }
+        PhraseModificationType::Hairpin { maximum_dynamic } => {
+          // A swell turns from a crescendo into a decrescendo at the middle of the phrase
+          let mut turn = maximum_dynamic
+            .and_then(|dynamic| LilyPondConverter::dynamic_text(&dynamic))
+            .map(|dynamic| format!("\\{dynamic}"))
+            .unwrap_or_default();
+          turn.push_str("\\>");
+          self.swell = Some((LilyPondConverter::count_events(phrase.iter()).div_ceil(2), turn));
+          opening.push_str("\\<");
+          closing.push_str("\\!");
+        }
+        PhraseModificationType::Glissando | PhraseModificationType::Portamento => opening.push_str("\\glissando"),
+        PhraseModificationType::OctaveShift { num_octaves } => {
+          self.command(&format!("\\ottava #{num_octaves}"));
+          after.push(String::from("\\ottava #0"));
+        }
+        PhraseModificationType::Pedal { pedal_type } => {
+          let (on, off) = match pedal_type {
+            PedalType::Sustain => ("\\sustainOn", "\\sustainOff"),
+            PedalType::Sostenuto => ("\\sostenutoOn", "\\sostenutoOff"),
+            PedalType::Soft => ("\\unaCorda", "\\treCorde"),
+          };
+          opening.push_str(on);
+          closing.push_str(off);
+        }
+        PhraseModificationType::Tremolo { .. } => (),
+      }
+    }
+    self.post_events.push_str(&opening);
+    for item in phrase.iter() {
+      match item {
+        PhraseContent::Note(note) => self.write_note(note, ratio),
+        PhraseContent::Chord(chord) => self.write_chord(chord, ratio),
+        PhraseContent::Phrase(phrase) => self.write_phrase(phrase, ratio),
+        PhraseContent::MultiVoice(multivoice) => self.write_multivoice(multivoice, ratio),
+      }
+    }
+    self.close_grace();
+    self.attach(&closing);
+    if tuplet {
+      let bar_check = self.output.ends_with(" |\n");
+      if bar_check {
+        self.output.truncate(self.output.len() - 3);
+      }
+      self.output.push_str(" }");
+      if bar_check {
+        self.output.push_str(" |\n");
+      }
+    }
+    for command in after {
+      self.command(&command);
+    }
+  }
+
+  fn write_multivoice(&mut self, multivoice: &MultiVoice, ratio: Fraction) {
+    let phrases = multivoice
+      .iter()
+      .map(|MultiVoiceContent::Phrase(phrase)| phrase)
+      .filter(|phrase| !phrase.is_empty())
+      .collect::<Vec<_>>();
+    self.close_grace();
+    let mut voices = Vec::new();
+    for phrase in phrases {
+      let mut voice = self.voice_writer();
+      if self.relative && !voices.is_empty() {
+        // Every voice restarts from the same pitch so that LilyPond resolves its octaves exactly as written here
+        voice.reference = voice.reference.or(Some(MIDDLE_C_STEP));
+        let reference = LilyPondConverter::absolute_text(voice.reference.unwrap_or(MIDDLE_C_STEP));
+        voice.output.push_str(&format!("\\resetRelativeOctave {reference}"));
+      }
+      if voices.is_empty() {
+        voice.post_events = core::mem::take(&mut self.post_events);
+      }
+      voice.write_phrase(phrase, ratio);
+      voice.close_grace();
+      voices.push(voice);
+    }
+    let Some(duration) = voices.iter().map(|voice| voice.elapsed).max() else {
+      return;
+    };
+    self.separate();
+    self.output.push_str("<<");
+    for (idx, voice) in voices.iter().enumerate() {
+      if idx > 0 {
+        self.output.push_str(" \\\\");
+      }
+      self.output.push_str(" { ");
+      if idx == 0 {
+        self.last_event = voice.last_event.map(|event| event + self.output.len());
+      }
+      self.output.push_str(voice.output.trim_end());
+      self.output.push_str(" }");
+    }
+    self.output.push_str(" >>");
+    if let Some(first) = voices.first() {
+      self.alterations.clone_from(&first.alterations);
+      if self.relative {
+        self.reference = first.reference;
+        let reference = LilyPondConverter::absolute_text(first.reference.unwrap_or(MIDDLE_C_STEP));
+        self.output.push_str(&format!(" \\resetRelativeOctave {reference}"));
+      }
+    }
+    self.advance(duration);
+  }
+
+  fn write_direction(&mut self, direction: &Direction) {
+    match &direction.r#type {
+      DirectionType::Dynamic { dynamic } => {
+        if let Some(dynamic) = LilyPondConverter::dynamic_text(dynamic) {
+          let dynamic = format!("\\{dynamic}");
+          if !self.post_events.ends_with(&dynamic) {
+            self.post_events.push_str(&dynamic);
+          }
+        }
+      }
+      DirectionType::KeyChange { .. }
+      | DirectionType::TimeSignatureChange { .. }
+      | DirectionType::ClefChange { .. } => match self.context.apply(&direction.r#type) {
+        Some(ContextChange::Key(key)) => self.command(&LilyPondConverter::key_text(&key)),
+        Some(ContextChange::TimeSignature(time_signature)) => {
+          self.command(&LilyPondConverter::time_text(&time_signature));
+        }
+        Some(ContextChange::Clef(clef_type)) => {
+          self.command(&format!("\\clef {}", LilyPondConverter::clef_text(clef_type)));
+        }
+        None => (),
+      },
+      DirectionType::Rehearsal { mark, .. } => {
+        self.command(&format!("\\mark {}", LilyPondConverter::string_text(mark)));
+      }
+      DirectionType::Text { text, style } | DirectionType::Expression { text, style } => {
+        let placement = if style.placement == Some(TextPlacement::Below) {
+          '_'
+        } else {
+          '^'
+        };
+        self
+          .post_events
+          .push_str(&format!("{placement}{}", LilyPondConverter::string_text(text)));
+      }
+      DirectionType::BreathMark => self.post_events.push_str("\\breathe"),
+      DirectionType::Caesura => self.post_events.push_str("\\caesura"),
+      _ => (),
+    }
+  }
+
+  fn write_staff(&mut self, staff: &Staff) {
+    for item in staff.iter() {
+      match item {
+        StaffContent::Note(note) => self.write_note(note, Fraction::ONE),
+        StaffContent::Chord(chord) => self.write_chord(chord, Fraction::ONE),
+        StaffContent::Phrase(phrase) => self.write_phrase(phrase, Fraction::ONE),
+        StaffContent::MultiVoice(multivoice) => self.write_multivoice(multivoice, Fraction::ONE),
+        StaffContent::Direction(direction) => self.write_direction(direction),
+      }
+    }
+  }
+
+  fn write_tempo_text(&mut self, text: &str) {
+    if self.write_tempo {
+      self.post_events.push_str(&format!(
+        "^\\markup {{ \\italic {} }}",
+        LilyPondConverter::string_text(text)
+      ));
+    }
+  }
+
+  fn write_section(&mut self, section: &Section, staff_name: &str, trailing_endings: Vec<&Section>) {
+    let mut repeat = None;
+    for modification in section.iter_modifications() {
+      match &modification.r#type {
+        SectionModificationType::Repeat { num_times } => repeat = Some(u16::from(*num_times) + 1),
+        SectionModificationType::TempoExplicit { tempo } => {
+          if *tempo != self.tempo {
+            self.tempo = *tempo;
+            if self.write_tempo {
+              self.command(&LilyPondConverter::tempo_text(tempo));
+            }
+          }
+        }
+        SectionModificationType::TempoImplicit { tempo } => {
+          if self.write_tempo {
+            let marking = tempo_marking_text(tempo.marking);
+            self.command(&format!("\\tempo {}", LilyPondConverter::string_text(&marking)));
+          }
+        }
+        SectionModificationType::Accelerando => self.write_tempo_text("accel."),
+        SectionModificationType::ATempo => self.write_tempo_text("a tempo"),
+        SectionModificationType::TempoPrimo => self.write_tempo_text("Tempo I"),
+        SectionModificationType::Rallentando => self.write_tempo_text("rall."),
+        SectionModificationType::Ritardando => self.write_tempo_text("rit."),
+        SectionModificationType::Ritenuto => self.write_tempo_text("riten."),
+        SectionModificationType::Stringendo => self.write_tempo_text("string."),
+        SectionModificationType::OnlyPlay { .. } => (),
+      }
+    }
+    let Some(times) = repeat else {
+      self.write_contents(section.iter(), staff_name);
+      return;
+    };
+
+    // Endings within or just after a repeated section become alternatives tied to the passes they are played on
+    self.command(&format!("\\repeat volta {times} {{"));
+    let (endings, body): (Vec<_>, Vec<_>) = section.iter().partition(|content| {
+      matches!(content, SectionContent::Section(ending) if LilyPondConverter::ending_iterations(ending).is_some())
+    });
+    self.write_contents(body.into_iter(), staff_name);
+    self.command("}");
+    let alternatives = endings
+      .into_iter()
+      .filter_map(|content| match content {
+        SectionContent::Section(ending) => Some(ending),
+        SectionContent::Staff(_) => None,
+      })
+      .chain(trailing_endings)
+      .collect::<Vec<_>>();
+    if !alternatives.is_empty() {
+      self.command("\\alternative {");
+      for ending in alternatives {
+        let iterations = LilyPondConverter::ending_iterations(ending)
+          .unwrap_or_default()
+          .iter()
+          .map(|iteration| (u16::from(*iteration) + 1).to_string())
+          .collect::<Vec<_>>()
+          .join(",");
+        self.command(&format!("\\volta {iterations} {{"));
+        self.write_contents(ending.iter(), staff_name);
+        self.command("}");
+      }
+      self.command("}");
+    }
+  }
+
+  fn write_contents<'a>(&mut self, contents: impl Iterator<Item = &'a SectionContent>, staff_name: &str) {
+    let mut contents = contents.peekable();
+    while let Some(content) = contents.next() {
+      match content {
+        SectionContent::Staff(staff) if staff.get_name() == staff_name => self.write_staff(staff),
+        SectionContent::Section(section) => {
+          let mut trailing_endings = Vec::new();
+          if LilyPondConverter::is_repeat(section) {
+            while let Some(SectionContent::Section(ending)) = contents.next_if(|content| {
+              matches!(content, SectionContent::Section(ending) if LilyPondConverter::ending_iterations(ending).is_some())
+            }) {
+              trailing_endings.push(ending);
+            }
+          }
+          self.write_section(section, staff_name, trailing_endings);
+        }
+        SectionContent::Staff(_) => (),
+      }
+    }
+  }
+
+  fn finish(mut self, indent: &str) -> String {
+    self.close_grace();
+    self.command("\\bar \"|.\"");
+    self
+      .output
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty())
+      .map(|line| format!("{indent}{line}\n"))
+      .collect()
+  }
+}
+
+/// Converter from AMM compositions to LilyPond input files.
+///
+/// Every part becomes a `Staff`, or a `StaffGroup` of staves if it contains
+/// more than one. Pitches are written in either absolute or `\relative`
+/// octave entry. LilyPond input cannot be imported.
+pub struct LilyPondConverter;
+
+impl LilyPondConverter {
+  fn count_events(items: core::slice::Iter<'_, PhraseContent>) -> usize {
+    items
+      .map(|item| match item {
+        PhraseContent::Note(note) => usize::from(!note.is_grace_note()),
+        PhraseContent::Chord(_) => 1,
+        PhraseContent::Phrase(phrase) => Self::count_events(phrase.iter()),
+        PhraseContent::MultiVoice(multivoice) => multivoice
+          .iter()
+          .next()
+          .map_or(0, |MultiVoiceContent::Phrase(phrase)| Self::count_events(phrase.iter())),
+      })
+      .sum()
+  }
+
+  fn step(note: &Note) -> i32 {
+    let step = match note.pitch.name {
+      PitchName::C | PitchName::Rest => 0,
+      PitchName::D => 1,
+      PitchName::E => 2,
+      PitchName::F => 3,
+      PitchName::G => 4,
+      PitchName::A => 5,
+      PitchName::B => 6,
+    };
+    i32::from(note.pitch.octave) * 7 + step
+  }
+
+  fn octave_text(octaves: i32) -> String {
+    if octaves >= 0 {
+      "'".repeat(octaves.unsigned_abs() as usize)
+    } else {
+      ",".repeat(octaves.unsigned_abs() as usize)
+    }
+  }
+
+  fn absolute_text(step: i32) -> String {
+    let name = ["c", "d", "e", "f", "g", "a", "b"][step.rem_euclid(7) as usize];
+    format!("{name}{}", Self::octave_text(step.div_euclid(7) - 3))
+  }
+
+  fn pitch_name_text(name: PitchName, accidental: Accidental) -> String {
+    let mut text = String::from(match name {
+      PitchName::A => "a",
+      PitchName::B => "b",
+      PitchName::C | PitchName::Rest => "c",
+      PitchName::D => "d",
+      PitchName::E => "e",
+      PitchName::F => "f",
+      PitchName::G => "g",
+    });
+    text.push_str(match accidental {
+      Accidental::Sharp => "is",
+      Accidental::DoubleSharp => "isis",
+      Accidental::Flat => "es",
+      Accidental::DoubleFlat => "eses",
+      Accidental::Natural | Accidental::None => "",
+    });
+    text
+  }
+
+  fn duration_text(duration: &Duration) -> String {
+    let mut text = String::from(match duration.value {
+      DurationType::Maxima => "\\maxima",
+      DurationType::Long => "\\longa",
+      DurationType::Breve => "\\breve",
+      DurationType::Whole => "1",
+      DurationType::Half => "2",
+      DurationType::Quarter => "4",
+      DurationType::Eighth => "8",
+      DurationType::Sixteenth => "16",
+      DurationType::ThirtySecond => "32",
+      DurationType::SixtyFourth => "64",
+      DurationType::OneHundredTwentyEighth => "128",
+      DurationType::TwoHundredFiftySixth => "256",
+      DurationType::FiveHundredTwelfth => "512",
+      DurationType::OneThousandTwentyFourth => "1024",
+      DurationType::TwoThousandFortyEighth => "2048",
+    });
+    text.extend((0..duration.dots).map(|_| '.'));
+    text
+  }
+
+  fn string_text(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+  }
+
+  fn key_text(key: &Key) -> String {
+    let tonic = match key.signature {
+      KeySignature::A => "a",
+      KeySignature::ASharp => "ais",
+      KeySignature::AFlat => "aes",
+      KeySignature::B => "b",
+      KeySignature::BFlat => "bes",
+      KeySignature::C => "c",
+      KeySignature::CSharp => "cis",
+      KeySignature::CFlat => "ces",
+      KeySignature::D => "d",
+      KeySignature::DSharp => "dis",
+      KeySignature::DFlat => "des",
+      KeySignature::E => "e",
+      KeySignature::EFlat => "ees",
+      KeySignature::F => "f",
+      KeySignature::FSharp => "fis",
+      KeySignature::G => "g",
+      KeySignature::GSharp => "gis",
+      KeySignature::GFlat => "ges",
+    };
+    match key.mode {
+      KeyMode::Major => format!("\\key {tonic} \\major"),
+      KeyMode::Minor => format!("\\key {tonic} \\minor"),
+    }
+  }
+
+  fn time_text(time_signature: &TimeSignature) -> String {
+    match time_signature.signature {
+      TimeSignatureType::CommonTime => String::from("\\defaultTimeSignature \\time 4/4"),
+      TimeSignatureType::CutTime => String::from("\\defaultTimeSignature \\time 2/2"),
+      TimeSignatureType::None => String::from("\\cadenzaOn"),
+      TimeSignatureType::Explicit => format!(
+        "\\numericTimeSignature \\time {}/{}",
+        time_signature.numerator, time_signature.denominator
+      ),
+    }
+  }
+
+  fn clef_text(clef_type: ClefType) -> &'static str {
+    match clef_type {
+      ClefType::Treble => "treble",
+      ClefType::FrenchViolin => "french",
+      ClefType::Bass => "bass",
+      ClefType::Baritone => "varbaritone",
+      ClefType::Subbass => "subbass",
+      ClefType::Alto => "alto",
+      ClefType::Tenor => "tenor",
+      ClefType::Soprano => "soprano",
+      ClefType::MezzoSoprano => "mezzosoprano",
+    }
+  }
+
+  fn tempo_text(tempo: &Tempo) -> String {
+    format!(
+      "\\tempo {} = {}",
+      Self::duration_text(&tempo.base_note),
+      tempo.beats_per_minute
+    )
+  }
+
+  fn dynamic_text(dynamic: &Dynamic) -> Option<String> {
+    match dynamic {
+      Dynamic::Piano(count @ 1..=5) => Some("p".repeat(usize::from(*count))),
+      Dynamic::Forte(count @ 1..=5) => Some("f".repeat(usize::from(*count))),
+      Dynamic::MezzoPiano => Some(String::from("mp")),
+      Dynamic::MezzoForte => Some(String::from("mf")),
+      Dynamic::FortePiano => Some(String::from("fp")),
+      Dynamic::Niente => Some(String::from("n")),
+      Dynamic::Rinforzando | Dynamic::Rinforzato => Some(String::from("rfz")),
+      Dynamic::Sforzando(1) => Some(String::from("sf")),
+      Dynamic::Sforzando(2) => Some(String::from("sff")),
+      Dynamic::Sforzato(1) | Dynamic::Forzando => Some(String::from("sfz")),
+      _ => None,
+    }
+  }
+
+  fn note_articulation(modification: &NoteModificationType) -> Option<String> {
+    let articulation = match modification {
+      NoteModificationType::Accent => "->",
+      NoteModificationType::DetachedLegato => "-_",
+      NoteModificationType::DownBow => "\\downbow",
+      NoteModificationType::Dynamic { dynamic } => return Self::dynamic_text(dynamic).map(|text| format!("\\{text}")),
+      NoteModificationType::Fermata => "\\fermata",
+      NoteModificationType::Fingering { finger, substitution } => {
+        return finger.or(*substitution).map(|finger| format!("-{finger}"))
+      }
+      NoteModificationType::Glissando { .. } | NoteModificationType::Portamento { .. } => "\\glissando",
+      NoteModificationType::Harmonic { .. } => "\\flageolet",
+      NoteModificationType::Haydn => "\\haydnturn",
+      NoteModificationType::Heel => "\\lheel",
+      NoteModificationType::Marcato => "-^",
+      NoteModificationType::Mordent { upper: true } => "\\prall",
+      NoteModificationType::Mordent { upper: false } => "\\mordent",
+      NoteModificationType::Open => "\\open",
+      NoteModificationType::Sforzando => "\\sfz",
+      NoteModificationType::Staccato => "-.",
+      NoteModificationType::Staccatissimo => "-!",
+      NoteModificationType::Stopped => "-+",
+      NoteModificationType::StringNumber { string } => return Some(format!("\\{string}")),
+      NoteModificationType::Tenuto => "--",
+      NoteModificationType::ThumbPosition => "\\thumb",
+      NoteModificationType::Toe => "\\ltoe",
+      NoteModificationType::Trill { .. } => "\\trill",
+      NoteModificationType::Turn { upper: true, .. } => "\\turn",
+      NoteModificationType::Turn { upper: false, .. } => "\\reverseturn",
+      NoteModificationType::UpBow => "\\upbow",
+      _ => return None,
+    };
+    Some(String::from(articulation))
+  }
+
+  fn chord_articulation(modification: &ChordModificationType) -> Option<String> {
+    let articulation = match modification {
+      ChordModificationType::Accent => "->",
+      ChordModificationType::Arpeggiate => "\\arpeggio",
+      ChordModificationType::DetachedLegato => "-_",
+      ChordModificationType::DownBow => "\\downbow",
+      ChordModificationType::Dynamic { dynamic } => return Self::dynamic_text(dynamic).map(|text| format!("\\{text}")),
+      ChordModificationType::Fermata => "\\fermata",
+      ChordModificationType::Heel => "\\lheel",
+      ChordModificationType::Marcato => "-^",
+      ChordModificationType::Open => "\\open",
+      ChordModificationType::Sforzando => "\\sfz",
+      ChordModificationType::Staccato => "-.",
+      ChordModificationType::Staccatissimo => "-!",
+      ChordModificationType::Tenuto => "--",
+      ChordModificationType::Toe => "\\ltoe",
+      ChordModificationType::UpBow => "\\upbow",
+      _ => return None,
+    };
+    Some(String::from(articulation))
+  }
+
+  fn is_repeat(section: &Section) -> bool {
+    section
+      .iter_modifications()
+      .any(|modification| matches!(modification.r#type, SectionModificationType::Repeat { .. }))
+  }
+
+  fn ending_iterations(section: &Section) -> Option<&[u8]> {
+    section
+      .iter_modifications()
+      .find_map(|modification| match &modification.r#type {
+        SectionModificationType::OnlyPlay { iterations } => Some(iterations.as_slice()),
+        _ => None,
+      })
+  }
+
+  fn header_text(composition: &Composition) -> String {
+    let metadata = composition.get_metadata();
+    let mut fields = vec![("title", String::from(composition.get_title()))];
+    if let Some(movement_title) = metadata.get("movement_title") {
+      fields.push(("subtitle", movement_title.clone()));
+    }
+    if !composition.get_composers().is_empty() {
+      fields.push(("composer", composition.get_composers().join(", ")));
+    }
+    if !composition.get_lyricists().is_empty() {
+      fields.push(("poet", composition.get_lyricists().join(", ")));
+    }
+    if !composition.get_arrangers().is_empty() {
+      fields.push(("arranger", composition.get_arrangers().join(", ")));
+    }
+    if let Some(publisher) = composition.get_publisher() {
+      fields.push(("publisher", publisher.clone()));
+    }
+    if let Some(copyright) = composition.get_copyright() {
+      fields.push(("copyright", copyright.clone()));
+    }
+    let mut header = String::from("\\header {\n");
+    for (field, value) in fields {
+      header.push_str(&format!("  {field} = {}\n", Self::string_text(&value)));
+    }
+    header.push_str("}\n");
+    header
+  }
+
+  pub(super) fn save_to_lilypond(composition: &Composition, relative: bool) -> String {
+    let mut lilypond = format!(
+      "\\version \"{LILYPOND_VERSION}\"\n\n{}\n\\score {{\n  <<\n",
+      Self::header_text(composition)
+    );
+
+    // Write every staff of every part, grouping the staves of parts that have more than one
+    let mut write_tempo = true;
+    for part in composition.iter() {
+      let staff_names = part.get_staff_names();
+      let name = Self::string_text(part.get_name());
+      let grouped = staff_names.len() > 1;
+      if grouped {
+        lilypond.push_str(&format!(
+          "    \\new StaffGroup \\with {{ instrumentName = {name} }} <<\n"
+        ));
+      }
+      let indent = if grouped { "      " } else { "    " };
+      for staff_name in staff_names {
+        let mut writer = LilyPondWriter::new(composition, relative, write_tempo);
+        writer.command(&Self::key_text(&writer.context.key));
+        writer.command(&Self::time_text(&writer.context.time_signature));
+        if write_tempo {
+          writer.command(&Self::tempo_text(&writer.tempo));
+        }
+        writer.output.push('\n');
+        for PartContent::Section(section) in part.iter() {
+          writer.write_section(section, &staff_name, Vec::new());
+        }
+        if grouped {
+          lilypond.push_str(&format!(
+            "{indent}\\new Staff = {} {{\n",
+            Self::string_text(&staff_name)
+          ));
+        } else {
+          lilypond.push_str(&format!(
+            "{indent}\\new Staff \\with {{ instrumentName = {name} }} {{\n"
+          ));
+        }
+        if relative {
+          lilypond.push_str(&format!("{indent}  \\relative {{\n"));
+          lilypond.push_str(&writer.finish(&format!("{indent}    ")));
+          lilypond.push_str(&format!("{indent}  }}\n"));
+        } else {
+          lilypond.push_str(&writer.finish(&format!("{indent}  ")));
+        }
+        lilypond.push_str(&format!("{indent}}}\n"));
+        write_tempo = false;
+      }
+      if grouped {
+        lilypond.push_str("    >>\n");
+      }
+    }
+    lilypond.push_str("  >>\n  \\layout { }\n}\n");
+    lilypond
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::storage::Storage;
+
+  #[test]
+  fn test_save_lilypond() {
+    let composition = Storage::ABC
+      .load_data(
+        b"X:1\nT:Scale \"Test\"\nC:Trad.\nM:3/4\nL:1/8\nQ:1/4=90\nK:F\n\
+          |:!p!(3CDE F2 (G2|A2) _B2 ^c2|1 [CEG]6:|2 {/g}f6!<(!|!<)!!f!G,2 c'2 z2|]\n"
+          .to_vec(),
+      )
+      .unwrap();
+    let absolute = LilyPondConverter::save_to_lilypond(&composition, false);
+    assert!(absolute.starts_with("\\version \"2.24.0\""));
+    assert!(absolute.contains("  title = \"Scale \\\"Test\\\"\"\n  composer = \"Trad.\"\n"));
+    assert!(absolute.contains("\\new Staff \\with { instrumentName = "));
+    assert!(absolute.contains("\\key f \\major \\numericTimeSignature \\time 3/4 \\tempo 4 = 90"));
+    assert!(absolute.contains("\\repeat volta 2 {"));
+    assert!(absolute.contains("\\tuplet 3/2 { c'8\\p d'8 e'8 }"));
+    assert!(absolute.contains("bes'4"));
+    assert!(absolute.contains("cis''4"));
+    assert!(absolute.contains("<c' e' g'>2."));
+    assert!(absolute.contains("\\alternative {"));
+    assert!(absolute.contains("\\volta 1 {"));
+    assert!(absolute.contains("\\acciaccatura { g''8 }"));
+    assert!(absolute.contains("g4\\f"));
+    assert!(absolute.contains("c'''4"));
+    assert!(absolute.contains("r4"));
+    assert_eq!(absolute.matches(" |\n").count(), 5);
+    assert_eq!(absolute.matches('{').count(), absolute.matches('}').count());
+
+    let relative = LilyPondConverter::save_to_lilypond(&composition, true);
+    assert!(relative.contains("\\relative {"));
+    assert!(relative.contains("\\tuplet 3/2 { c'8\\p d8 e8 }"));
+    assert!(relative.contains("g,,4\\f\\< c''4"));
+  }
+
+  #[test]
+  fn test_save_lilypond_example() {
+    let composition = Storage::MusicXML.load("examples/Hymn_to_Freedom.mxl").unwrap();
+    let lilypond = LilyPondConverter::save_to_lilypond(&composition, false);
+    assert!(lilypond.contains("\\new StaffGroup \\with { instrumentName = \"Piano\" } <<"));
+    assert_eq!(lilypond.matches("\\new Staff =").count(), 2);
+    assert!(lilypond.contains("<< { "));
+    assert!(lilypond.contains(" \\\\ { "));
+    assert_eq!(lilypond.matches('{').count(), lilypond.matches('}').count());
+    assert_eq!(lilypond.matches("<<").count(), lilypond.matches(">>").count());
+  }
+}
@@ -0,0 +1,2749 @@
+use super::musicxml::MusicXmlConverter;
+use super::util::{
+  dynamic_text, gcd, measure_starts, tempo_marking_text, ContextChange, Fraction, StaffContext, DYNAMICS, MAX_DOTS,
+};
+use super::xml::{add_notation, parse_xml, write_xml, xml_element, xml_text_element, XmlElementExt};
+use super::{AmmError, Load, Store};
+use crate::context::{ClefType, Key, KeyMode, Tempo, TimeSignature, TimeSignatureType};
+use crate::modification::{
+  ChordModificationType, Direction, DirectionType, NoteModificationType, PedalType, PhraseModificationType,
+  SectionModificationType, TextPlacement,
+};
+use crate::note::{Accidental, Duration, DurationType, Note, Pitch, PitchName};
+use crate::structure::{
+  Chord, ChordContent, MultiVoice, MultiVoiceContent, PartContent, Phrase, PhraseContent, Section, SectionContent,
+  Staff, StaffContent,
+};
+use crate::Composition;
+use alloc::{
+  collections::BTreeMap,
+  string::{String, ToString},
+  vec::Vec,
+};
+use musicxml::elements::ScorePartwise;
+use musicxml_internal::{ElementDeserializer, XmlElement};
+
+const VOICES_PER_STAFF: usize = 4;
+const MAX_WHOLE_DIVISIONS: u64 = 15_360;
+const MEI_NAMESPACE: &str = "http://www.music-encoding.org/ns/mei";
+const MEI_VERSION: &str = "5.0";
+const ARTICULATIONS: [(&str, &str, &str); 24] = [
+  ("acc", "articulations", "accent"),
+  ("stacc", "articulations", "staccato"),
+  ("ten", "articulations", "tenuto"),
+  ("stacciss", "articulations", "staccatissimo"),
+  ("marc", "articulations", "strong-accent"),
+  ("spicc", "articulations", "spiccato"),
+  ("ten-stacc", "articulations", "detached-legato"),
+  ("acc-soft", "articulations", "soft-accent"),
+  ("scoop", "articulations", "scoop"),
+  ("doit", "articulations", "doit"),
+  ("plop", "articulations", "plop"),
+  ("fall", "articulations", "falloff"),
+  ("dnbow", "technical", "down-bow"),
+  ("upbow", "technical", "up-bow"),
+  ("harm", "technical", "harmonic"),
+  ("open", "technical", "open-string"),
+  ("stop", "technical", "stopped"),
+  ("snap", "technical", "snap-pizzicato"),
+  ("dbltongue", "technical", "double-tongue"),
+  ("trpltongue", "technical", "triple-tongue"),
+  ("heel", "technical", "heel"),
+  ("toe", "technical", "toe"),
+  ("tap", "technical", "tap"),
+  ("fingernail", "technical", "fingernails"),
+];
+
+enum MeiItem {
+  Element(XmlElement),
+  Timed {
+    element: XmlElement,
+    written: Fraction,
+    actual: Fraction,
+  },
+  Backup(Fraction),
+}
+
+/// Location of a transcoded event, used to resolve the control events referring to it.
+#[derive(Copy, Clone)]
+struct MeiTarget {
+  part: usize,
+  measure: usize,
+  staff: usize,
+  note: usize,
+  first: usize,
+  last: usize,
+  onset: Fraction,
+}
+
+struct MeiMeasure {
+  beat: Fraction,
+  forward_repeat: bool,
+  backward_repeat: bool,
+  ending_start: Option<String>,
+  ending_stop: Option<(String, bool)>,
+  bar_style: Option<&'static str>,
+}
+
+struct MeiPart {
+  name: String,
+  staves: Vec<String>,
+  clefs: Vec<XmlElement>,
+  changes: Vec<XmlElement>,
+  measures: Vec<Vec<MeiItem>>,
+}
+
+struct MeiLayer {
+  part: usize,
+  measure: usize,
+  staff: usize,
+  voice: usize,
+  onset: Fraction,
+  ratio: Fraction,
+  grace: Option<String>,
+  tuplets: usize,
+}
+
+struct MeiReader {
+  metadata: Vec<XmlElement>,
+  parts: Vec<MeiPart>,
+  staves: BTreeMap<String, (usize, usize)>,
+  key: XmlElement,
+  time: XmlElement,
+  measure_length: Fraction,
+  beat: Fraction,
+  tempo: Option<XmlElement>,
+  measures: Vec<MeiMeasure>,
+  repeat_next: bool,
+  ids: BTreeMap<String, MeiTarget>,
+  events: Vec<MeiTarget>,
+  controls: Vec<(usize, XmlElement)>,
+  insertions: BTreeMap<(usize, usize, usize), Vec<XmlElement>>,
+}
+
+impl MeiReader {
+  fn parse(text: &str) -> Result<Self, String> {
+    let mei = parse_xml(text)?;
+    if mei.name != "mei" {
+      return Err(format!("Expected an <mei> root element but found <{}>", mei.name));
+    }
+    let score = mei
+      .child("music")
+      .and_then(|music| music.child("body"))
+      .and_then(|body| body.child("mdiv"))
+      .and_then(|mdiv| mdiv.child("score"))
+      .ok_or("No score found in the MEI document")?;
+    let mut reader = Self {
+      metadata: mei.child("meiHead").map(Self::read_head).unwrap_or_default(),
+      parts: Vec::new(),
+      staves: BTreeMap::new(),
+      key: MeiConverter::key_element(0, false),
+      time: xml_element("time", &[], vec![xml_element("senza-misura", &[], Vec::new())]),
+      measure_length: Fraction::ONE,
+      beat: Fraction::new(1, 4),
+      tempo: None,
+      measures: Vec::new(),
+      repeat_next: false,
+      ids: BTreeMap::new(),
+      events: Vec::new(),
+      controls: Vec::new(),
+      insertions: BTreeMap::new(),
+    };
+    reader.read_section(score)?;
+    reader.resolve_controls();
+    Ok(reader)
+  }
+
+  fn read_head(head: &XmlElement) -> Vec<XmlElement> {
+    let file_description = head.child("fileDesc");
+    let title_statement = file_description.and_then(|description| description.child("titleStmt"));
+    let titles = title_statement
+      .map(|statement| {
+        statement
+          .elements
+          .iter()
+          .filter(|element| element.name == "title")
+          .collect::<Vec<_>>()
+      })
+      .unwrap_or_default();
+    let mut metadata = Vec::new();
+    if let Some(title) = titles
+      .iter()
+      .find(|title| title.attribute("type") != Some("subordinate"))
+    {
+      metadata.push(xml_element(
+        "work",
+        &[],
+        vec![xml_text_element("work-title", MeiConverter::text_content(title))],
+      ));
+    }
+    if let Some(movement_title) = titles
+      .iter()
+      .find(|title| title.attribute("type") == Some("subordinate"))
+    {
+      metadata.push(xml_text_element(
+        "movement-title",
+        MeiConverter::text_content(movement_title),
+      ));
+    }
+
+    // Creators are either listed by role within responsibility statements or as dedicated elements
+    let mut creators = Vec::new();
+    for element in title_statement.iter().flat_map(|statement| statement.elements.iter()) {
+      match element.name.as_str() {
+        "composer" | "lyricist" | "librettist" | "arranger" => creators.push((element.name.as_str(), element)),
+        "respStmt" => creators.extend(
+          element
+            .elements
+            .iter()
+            .filter_map(|name| name.attribute("role").map(|role| (role, name))),
+        ),
+        _ => (),
+      }
+    }
+    let mut identification = creators
+      .into_iter()
+      .filter_map(|(role, name)| {
+        let creator = match role {
+          "librettist" | "author" => "lyricist",
+          role => role,
+        };
+        let name = MeiConverter::text_content(name);
+        (!name.is_empty()).then(|| XmlElement {
+          text: name,
+          ..xml_element("creator", &[("type", creator)], Vec::new())
+        })
+      })
+      .collect::<Vec<_>>();
+    if let Some(publication) = file_description.and_then(|description| description.child("pubStmt")) {
+      if let Some(publisher) = publication
+        .child("publisher")
+        .map(MeiConverter::text_content)
+        .filter(|publisher| !publisher.is_empty())
+      {
+        identification.push(XmlElement {
+          text: publisher,
+          ..xml_element("creator", &[("type", "publisher")], Vec::new())
+        });
+      }
+      if let Some(rights) = publication
+        .child("availability")
+        .map(|availability| availability.child("useRestrict").unwrap_or(availability))
+        .map(MeiConverter::text_content)
+        .filter(|rights| !rights.is_empty())
+      {
+        identification.push(xml_text_element("rights", rights));
+      }
+    }
+    if !identification.is_empty() {
+      metadata.push(xml_element("identification", &[], identification));
+    }
+    metadata
+  }
+
+  fn read_section(&mut self, section: &XmlElement) -> Result<(), String> {
+    for element in &section.elements {
+      match element.name.as_str() {
+        "scoreDef" => self.read_score_definition(element),
+        "staffDef" => self.read_staff_definition(element),
+        "section" => self.read_section(element)?,
+        "ending" => {
+          let start = self.measures.len();
+          self.read_section(element)?;
+          if self.measures.len() > start {
+            let numbers = MeiConverter::ending_numbers(element);
+            self.measures[start].ending_start = Some(numbers.clone());
+            if let Some(last) = self.measures.last_mut() {
+              last.ending_stop = Some((numbers, !last.backward_repeat));
+            }
+          }
+        }
+        "measure" => self.read_measure(element)?,
+        _ => (),
+      }
+    }
+    Ok(())
+  }
+
+  fn read_score_definition(&mut self, definition: &XmlElement) {
+    // Context changes before the first measure simply redefine the initial context
+    let initial = self.measures.is_empty();
+    if let Some(key) = MeiConverter::key_signature(definition) {
+      if initial {
+        self.key = key;
+      } else {
+        for part in &mut self.parts {
+          part.changes.push(key.deep_copy());
+        }
+      }
+    }
+    if let Some((time, length, beat)) = MeiConverter::meter_signature(definition) {
+      self.measure_length = length;
+      self.beat = beat;
+      if initial {
+        self.time = time;
+      } else {
+        for part in &mut self.parts {
+          part.changes.push(time.deep_copy());
+        }
+      }
+    }
+    if initial {
+      if let Some(tempo) = definition
+        .attribute("midi.bpm")
+        .and_then(|bpm| bpm.trim().parse::<f64>().ok())
+        .filter(|bpm| *bpm > 0.0)
+      {
+        self.tempo = Some(MeiConverter::direction(
+          Vec::new(),
+          Some(xml_element("sound", &[("tempo", &tempo.to_string())], Vec::new())),
+          None,
+          1,
+        ));
+      }
+    }
+    if self.parts.is_empty() {
+      self.read_staff_group(definition, None);
+    } else {
+      Self::for_each_staff_definition(definition, &mut |staff_definition| {
+        self.read_staff_definition(staff_definition);
+      });
+    }
+  }
+
+  fn for_each_staff_definition(group: &XmlElement, callback: &mut impl FnMut(&XmlElement)) {
+    for element in &group.elements {
+      match element.name.as_str() {
+        "staffDef" => callback(element),
+        "staffGrp" => Self::for_each_staff_definition(element, callback),
+        _ => (),
+      }
+    }
+  }
+
+  fn read_staff_group(&mut self, group: &XmlElement, part: Option<usize>) {
+    for element in &group.elements {
+      match element.name.as_str() {
+        "staffDef" => {
+          let number = element
+            .attribute("n")
+            .map_or_else(|| (self.staves.len() + 1).to_string(), String::from);
+          let part = part.unwrap_or_else(|| {
+            let name = MeiConverter::label(element).unwrap_or_else(|| format!("Staff {number}"));
+            self.add_part(name)
+          });
+          let clef = MeiConverter::clef(element).unwrap_or_else(|| MeiConverter::clef_element("G", 2, 0));
+          self.parts[part].staves.push(number.clone());
+          self.parts[part].clefs.push(clef);
+          self.staves.insert(number, (part, self.parts[part].staves.len()));
+        }
+        "staffGrp" => {
+          // Braced or labelled groups of unlabelled staves belong to a single instrument
+          let part = part.or_else(|| {
+            let staff_definitions = element
+              .elements
+              .iter()
+              .filter(|child| child.name == "staffDef")
+              .collect::<Vec<_>>();
+            let instrument = staff_definitions.len() > 1
+              && !element.has_child("staffGrp")
+              && (element.attribute("symbol") == Some("brace")
+                || (MeiConverter::label(element).is_some()
+                  && staff_definitions
+                    .iter()
+                    .all(|definition| MeiConverter::label(definition).is_none())));
+            instrument.then(|| {
+              let name = MeiConverter::label(element)
+                .or_else(|| {
+                  staff_definitions
+                    .iter()
+                    .find_map(|definition| MeiConverter::label(definition))
+                })
+                .unwrap_or_else(|| format!("Part {}", self.parts.len() + 1));
+              self.add_part(name)
+            })
+          });
+          self.read_staff_group(element, part);
+        }
+        _ => (),
+      }
+    }
+  }
+
+  fn add_part(&mut self, name: String) -> usize {
+    self.parts.push(MeiPart {
+      name,
+      staves: Vec::new(),
+      clefs: Vec::new(),
+      changes: Vec::new(),
+      measures: Vec::new(),
+    });
+    self.parts.len() - 1
+  }
+
+  fn read_staff_definition(&mut self, definition: &XmlElement) {
+    let staff = definition
+      .attribute("n")
+      .and_then(|number| self.staves.get(number))
+      .copied();
+    if let (Some((part, staff)), Some(mut clef)) = (staff, MeiConverter::clef(definition)) {
+      if self.measures.is_empty() {
+        self.parts[part].clefs[staff - 1] = clef;
+      } else {
+        clef.attributes.push((String::from("number"), staff.to_string()));
+        self.parts[part].changes.push(clef);
+      }
+    }
+  }
+
+  fn read_measure(&mut self, measure: &XmlElement) -> Result<(), String> {
+    if self.parts.is_empty() {
+      return Err(String::from("MEI measure found before any staff definition"));
+    }
+    let measure_idx = self.measures.len();
+    let mut barlines = MeiMeasure {
+      beat: self.beat,
+      forward_repeat: core::mem::take(&mut self.repeat_next) || measure.attribute("left") == Some("rptstart"),
+      backward_repeat: false,
+      ending_start: None,
+      ending_stop: None,
+      bar_style: None,
+    };
+    match measure.attribute("right") {
+      Some("rptend") => barlines.backward_repeat = true,
+      Some("rptboth") => {
+        barlines.backward_repeat = true;
+        self.repeat_next = true;
+      }
+      Some("end") => barlines.bar_style = Some("light-heavy"),
+      Some("dbl") => barlines.bar_style = Some("light-light"),
+      _ => (),
+    }
+    self.measures.push(barlines);
+
+    // Transcode every layer of every staff, backing up to the start of the measure between them
+    for part in 0..self.parts.len() {
+      let mut items = Vec::new();
+      let changes = core::mem::take(&mut self.parts[part].changes);
+      if !changes.is_empty() {
+        items.push(MeiItem::Element(xml_element("attributes", &[], changes)));
+      }
+      let mut cursor = Fraction::ZERO;
+      let staves = self.parts[part].staves.clone();
+      for (idx, number) in staves.iter().enumerate() {
+        let layers = measure
+          .elements
+          .iter()
+          .filter(|staff| staff.name == "staff" && staff.attribute("n") == Some(number))
+          .flat_map(|staff| staff.elements.iter().filter(|layer| layer.name == "layer"))
+          .collect::<Vec<_>>();
+        if cursor > Fraction::ZERO {
+          items.push(MeiItem::Backup(cursor));
+          cursor = Fraction::ZERO;
+        }
+        if layers.is_empty() {
+          let voice = idx * VOICES_PER_STAFF + 1;
+          items.push(MeiItem::Timed {
+            element: xml_element(
+              "forward",
+              &[],
+              vec![xml_text_element("voice", voice), xml_text_element("staff", idx + 1)],
+            ),
+            written: self.measure_length,
+            actual: self.measure_length,
+          });
+          cursor = self.measure_length;
+        }
+        for (layer_idx, layer) in layers.into_iter().enumerate() {
+          if cursor > Fraction::ZERO {
+            items.push(MeiItem::Backup(cursor));
+          }
+          let mut state = MeiLayer {
+            part,
+            measure: measure_idx,
+            staff: idx + 1,
+            voice: idx * VOICES_PER_STAFF + layer_idx.min(VOICES_PER_STAFF - 1) + 1,
+            onset: Fraction::ZERO,
+            ratio: Fraction::ONE,
+            grace: None,
+            tuplets: 0,
+          };
+          for element in &layer.elements {
+            self.read_event(element, &mut state, &mut items)?;
+          }
+          cursor = state.onset;
+        }
+      }
+      self.parts[part].measures.push(items);
+    }
+    self.controls.extend(
+      measure
+        .elements
+        .iter()
+        .filter(|element| element.name != "staff")
+        .map(|element| (measure_idx, element.deep_copy())),
+    );
+    Ok(())
+  }
+
+  fn read_event(&mut self, element: &XmlElement, state: &mut MeiLayer, items: &mut Vec<MeiItem>) -> Result<(), String> {
+    match element.name.as_str() {
+      "note" | "chord" | "rest" => self.push_event(element, state, items)?,
+      "mRest" | "space" | "mSpace" => {
+        let duration = if element.name.starts_with('m') {
+          self.measure_length
+        } else {
+          MeiConverter::duration(element).ok_or("Invalid MEI space duration")? * state.ratio
+        };
+        let element = if element.name == "mRest" {
+          xml_element(
+            "note",
+            &[],
+            vec![
+              xml_element("rest", &[("measure", "yes")], Vec::new()),
+              xml_text_element("voice", state.voice),
+              xml_text_element("staff", state.staff),
+            ],
+          )
+        } else {
+          xml_element(
+            "forward",
+            &[],
+            vec![
+              xml_text_element("voice", state.voice),
+              xml_text_element("staff", state.staff),
+            ],
+          )
+        };
+        items.push(MeiItem::Timed {
+          element,
+          written: duration,
+          actual: duration,
+        });
+        state.onset = state.onset + duration;
+      }
+      "beam" | "bTrem" | "fTrem" | "ligature" => {
+        for child in &element.elements {
+          self.read_event(child, state, items)?;
+        }
+      }
+      "graceGrp" => {
+        let grace = state
+          .grace
+          .replace(String::from(element.attribute("grace").unwrap_or("acc")));
+        for child in &element.elements {
+          self.read_event(child, state, items)?;
+        }
+        state.grace = grace;
+      }
+      "tuplet" => {
+        let num = element.attribute("num").and_then(|num| num.parse::<u64>().ok());
+        let numbase = element
+          .attribute("numbase")
+          .and_then(|numbase| numbase.parse::<u64>().ok());
+        let (Some(num @ 1..), Some(numbase @ 1..)) = (num, numbase) else {
+          return Err(String::from("Invalid MEI tuplet ratio"));
+        };
+        let (start, ratio) = (items.len(), state.ratio);
+        state.ratio = state.ratio * Fraction::new(numbase, num);
+        state.tuplets += 1;
+        for child in &element.elements {
+          self.read_event(child, state, items)?;
+        }
+        let notes = (start..items.len())
+          .filter(|idx| {
+            matches!(&items[*idx], MeiItem::Timed { element, .. } if element.name == "note" && !element.has_child("chord"))
+          })
+          .collect::<Vec<_>>();
+        // Tuplets that leave the duration of their notes unchanged carry no time modification to attach to
+        if let (Some(first), Some(last), true) = (notes.first(), notes.last(), state.ratio != Fraction::ONE) {
+          for (idx, tuplet_type) in [(*first, "start"), (*last, "stop")] {
+            if let MeiItem::Timed { element, .. } = &mut items[idx] {
+              add_notation(
+                element,
+                None,
+                xml_element(
+                  "tuplet",
+                  &[("type", tuplet_type), ("number", &state.tuplets.to_string())],
+                  Vec::new(),
+                ),
+              );
+            }
+          }
+        }
+        state.tuplets -= 1;
+        state.ratio = ratio;
+      }
+      "clef" => {
+        if let Some(mut clef) = MeiConverter::clef(element) {
+          clef.attributes.push((String::from("number"), state.staff.to_string()));
+          items.push(MeiItem::Element(xml_element("attributes", &[], vec![clef])));
+        }
+      }
+      _ => (),
+    }
+    Ok(())
+  }
+
+  fn push_event(&mut self, event: &XmlElement, state: &mut MeiLayer, items: &mut Vec<MeiItem>) -> Result<(), String> {
+    let written = MeiConverter::duration(event)
+      .or_else(|| {
+        event
+          .elements
+          .iter()
+          .filter(|note| note.name == "note")
+          .find_map(MeiConverter::duration)
+      })
+      .ok_or_else(|| format!("Missing duration of MEI <{}>", event.name))?;
+    let actual = written * state.ratio;
+    let grace = event
+      .attribute("grace")
+      .map(String::from)
+      .or_else(|| state.grace.clone());
+    let notes = match event.name.as_str() {
+      "chord" => event
+        .elements
+        .iter()
+        .filter(|note| note.name == "note")
+        .collect::<Vec<_>>(),
+      _ => vec![event],
+    };
+    let first = items.len();
+    for (idx, note) in notes.iter().enumerate() {
+      let mut element = if note.name == "rest" {
+        xml_element("note", &[], vec![xml_element("rest", &[], Vec::new())])
+      } else {
+        MeiConverter::note_element(note, idx > 0)
+      };
+      if let Some(grace) = &grace {
+        element.elements.insert(
+          0,
+          xml_element(
+            "grace",
+            if grace == "unacc" { &[] } else { &[("slash", "yes")] },
+            Vec::new(),
+          ),
+        );
+      }
+      element.elements.push(xml_text_element("voice", state.voice));
+      element.elements.push(xml_text_element("staff", state.staff));
+      if state.ratio != Fraction::ONE {
+        element.elements.push(xml_element(
+          "time-modification",
+          &[],
+          vec![
+            xml_text_element("actual-notes", state.ratio.denominator),
+            xml_text_element("normal-notes", state.ratio.numerator),
+          ],
+        ));
+      }
+      if idx == 0 || note.name == "note" {
+        MeiConverter::add_articulations(&mut element, event);
+      }
+      if grace.is_some() {
+        if let Some((note_type, dots)) = written.note_type() {
+          element.elements.push(xml_text_element("type", note_type));
+          element
+            .elements
+            .extend((0..dots).map(|_| xml_element("dot", &[], Vec::new())));
+        }
+        items.push(MeiItem::Element(element));
+      } else {
+        items.push(MeiItem::Timed {
+          element,
+          written,
+          actual,
+        });
+      }
+    }
+
+    // Register the event and its notes so that control events can refer to them
+    let last = items.len().saturating_sub(1).max(first);
+    let target = MeiTarget {
+      part: state.part,
+      measure: state.measure,
+      staff: state.staff,
+      note: first,
+      first,
+      last,
+      onset: state.onset,
+    };
+    if let Some(id) = event.attribute("xml:id") {
+      self.ids.insert(String::from(id), target);
+    }
+    if event.name == "chord" {
+      for (idx, note) in notes.iter().enumerate() {
+        if let Some(id) = note.attribute("xml:id") {
+          self.ids.insert(
+            String::from(id),
+            MeiTarget {
+              note: first + idx,
+              ..target
+            },
+          );
+        }
+      }
+    }
+    if grace.is_none() && items.len() > first {
+      self.events.push(target);
+      state.onset = state.onset + actual;
+    }
+    Ok(())
+  }
+
+  fn find_target(&self, control: &XmlElement, measure: usize, end: bool) -> Option<MeiTarget> {
+    let (id, timestamp) = if end {
+      ("endid", "tstamp2")
+    } else {
+      ("startid", "tstamp")
+    };
+    if let Some(id) = control.attribute(id) {
+      let id = id.split_whitespace().next().unwrap_or_default().trim_start_matches('#');
+      return self.ids.get(id).copied();
+    }
+
+    // Timestamps count beats from one, optionally preceded by a number of measures to skip
+    let timestamp = control.attribute(timestamp)?;
+    let (measures, beats) = match timestamp.split_once("m+") {
+      Some((measures, beats)) => (measures.trim().parse::<usize>().ok()?, beats),
+      None => (0, timestamp),
+    };
+    let measure = measure + measures;
+    let beats = MeiConverter::parse_beats(beats)?;
+    let onset = self.measures.get(measure)?.beat * beats;
+    let (part, staff) = control
+      .attribute("staff")
+      .and_then(|staff| staff.split_whitespace().next())
+      .and_then(|staff| self.staves.get(staff))
+      .copied()
+      .unwrap_or((0, 1));
+    let mut candidates = self
+      .events
+      .iter()
+      .filter(|event| event.part == part && event.staff == staff && event.measure == measure);
+    if end {
+      candidates.rfind(|event| event.onset <= onset).copied()
+    } else {
+      let events = candidates.collect::<Vec<_>>();
+      events
+        .iter()
+        .find(|event| event.onset >= onset)
+        .or(events.last())
+        .copied()
+        .copied()
+    }
+  }
+
+  fn insert(&mut self, target: &MeiTarget, after: bool, element: XmlElement) {
+    let idx = if after { target.last + 1 } else { target.first };
+    self
+      .insertions
+      .entry((target.part, target.measure, idx))
+      .or_default()
+      .push(element);
+  }
+
+  fn notate(&mut self, target: &MeiTarget, group: Option<&str>, notation: XmlElement) {
+    if let Some(MeiItem::Timed { element, .. } | MeiItem::Element(element)) = self.parts[target.part]
+      .measures
+      .get_mut(target.measure)
+      .and_then(|items| items.get_mut(target.note))
+    {
+      if element.name == "note" {
+        add_notation(element, group, notation);
+      }
+    }
+  }
+
+  fn resolve_controls(&mut self) {
+    let mut slurs = 0;
+    for (measure, control) in core::mem::take(&mut self.controls) {
+      let Some(start) = self.find_target(&control, measure, false) else {
+        continue;
+      };
+      let end = self.find_target(&control, measure, true).unwrap_or(start);
+      let placement = control
+        .attribute("place")
+        .filter(|place| matches!(*place, "above" | "below"));
+      let text = MeiConverter::text_content(&control);
+      match control.name.as_str() {
+        "dynam" => {
+          let direction_type = if DYNAMICS.contains(&text.as_str()) {
+            xml_element("dynamics", &[], vec![xml_element(&text, &[], Vec::new())])
+          } else {
+            xml_text_element("words", &text)
+          };
+          let direction = MeiConverter::direction(
+            vec![direction_type],
+            None,
+            Some(placement.unwrap_or("below")),
+            start.staff,
+          );
+          self.insert(&start, false, direction);
+        }
+        "hairpin" => {
+          let wedge = match control.attribute("form") {
+            Some("cres") => "crescendo",
+            Some("dim") => "diminuendo",
+            _ => continue,
+          };
+          let placement = Some(placement.unwrap_or("below"));
+          let direction = |wedge_type| {
+            MeiConverter::direction(
+              vec![xml_element("wedge", &[("type", wedge_type)], Vec::new())],
+              None,
+              placement,
+              start.staff,
+            )
+          };
+          self.insert(&start, false, direction(wedge));
+          self.insert(&end, true, direction("stop"));
+        }
+        "slur" | "phrase" => {
+          slurs = slurs % 6 + 1;
+          let number = slurs.to_string();
+          self.notate(
+            &start,
+            None,
+            xml_element("slur", &[("type", "start"), ("number", &number)], Vec::new()),
+          );
+          self.notate(
+            &end,
+            None,
+            xml_element("slur", &[("type", "stop"), ("number", &number)], Vec::new()),
+          );
+        }
+        "tie" => {
+          for (target, tie_type) in [(start, "start"), (end, "stop")] {
+            if let Some(MeiItem::Timed { element, .. }) = self.parts[target.part]
+              .measures
+              .get_mut(target.measure)
+              .and_then(|items| items.get_mut(target.note))
+            {
+              element
+                .elements
+                .push(xml_element("tie", &[("type", tie_type)], Vec::new()));
+            }
+            self.notate(&target, None, xml_element("tied", &[("type", tie_type)], Vec::new()));
+          }
+        }
+        "pedal" => {
+          let pedal_type = match control.attribute("dir") {
+            Some("down") => "start",
+            Some("up") => "stop",
+            Some("half" | "bounce") => "change",
+            _ => continue,
+          };
+          let direction = MeiConverter::direction(
+            vec![xml_element("pedal", &[("type", pedal_type)], Vec::new())],
+            None,
+            Some("below"),
+            start.staff,
+          );
+          self.insert(&start, false, direction);
+        }
+        "octave" => {
+          let size = match control.attribute("dis") {
+            Some("15") => "15",
+            Some("22") => "22",
+            _ => "8",
+          };
+          let below = control.attribute("dis.place") == Some("below");
+          let direction = |shift_type| {
+            MeiConverter::direction(
+              vec![xml_element(
+                "octave-shift",
+                &[("type", shift_type), ("size", size)],
+                Vec::new(),
+              )],
+              None,
+              Some(if below { "below" } else { "above" }),
+              start.staff,
+            )
+          };
+          self.insert(&start, false, direction(if below { "up" } else { "down" }));
+          self.insert(&end, true, direction("stop"));
+        }
+        "tempo" => {
+          let mut direction_types = Vec::new();
+          if !text.is_empty() {
+            direction_types.push(xml_text_element("words", &text));
+          }
+          let beat = control
+            .attribute("mm.unit")
+            .and_then(MeiConverter::duration_value)
+            .map(|unit| {
+              let dots = control
+                .attribute("mm.dots")
+                .and_then(|dots| dots.parse::<u32>().ok())
+                .unwrap_or_default()
+                .min(MAX_DOTS);
+              unit * Fraction::new((1 << (dots + 1)) - 1, 1 << dots)
+            })
+            .unwrap_or(Fraction::new(1, 4));
+          let beats_per_minute = control
+            .attribute("mm")
+            .and_then(|mm| mm.trim().parse::<f64>().ok())
+            .filter(|mm| *mm > 0.0);
+          if let (Some(beats_per_minute), Some((beat_unit, dots))) = (beats_per_minute, beat.note_type()) {
+            let mut metronome = vec![xml_text_element("beat-unit", beat_unit)];
+            metronome.extend((0..dots).map(|_| xml_element("beat-unit-dot", &[], Vec::new())));
+            metronome.push(xml_text_element("per-minute", beats_per_minute));
+            direction_types.push(xml_element("metronome", &[], metronome));
+          }
+          let quarters_per_minute = control
+            .attribute("midi.bpm")
+            .and_then(|bpm| bpm.trim().parse::<f64>().ok())
+            .or_else(|| beats_per_minute.map(|bpm| bpm * 4.0 * beat.numerator as f64 / beat.denominator as f64))
+            .filter(|bpm| *bpm > 0.0);
+          let sound = quarters_per_minute.map(|bpm| xml_element("sound", &[("tempo", &bpm.to_string())], Vec::new()));
+          if !direction_types.is_empty() || sound.is_some() {
+            let direction = MeiConverter::direction(direction_types, sound, Some("above"), start.staff);
+            self.insert(&start, false, direction);
+          }
+        }
+        "dir" | "reh" if !text.is_empty() => {
+          let direction_type = if control.name == "reh" {
+            xml_text_element("rehearsal", &text)
+          } else {
+            xml_text_element("words", &text)
+          };
+          let direction = MeiConverter::direction(
+            vec![direction_type],
+            None,
+            Some(placement.unwrap_or("above")),
+            start.staff,
+          );
+          self.insert(&start, false, direction);
+        }
+        "fermata" => self.notate(&start, None, xml_element("fermata", &[], Vec::new())),
+        "arpeg" => self.notate(&start, None, xml_element("arpeggiate", &[], Vec::new())),
+        "breath" => self.notate(
+          &start,
+          Some("articulations"),
+          xml_element("breath-mark", &[], Vec::new()),
+        ),
+        "caesura" => self.notate(&start, Some("articulations"), xml_element("caesura", &[], Vec::new())),
+        "trill" => self.notate(&start, Some("ornaments"), xml_element("trill-mark", &[], Vec::new())),
+        "mordent" => {
+          let ornament = if control.attribute("form") == Some("upper") {
+            "inverted-mordent"
+          } else {
+            "mordent"
+          };
+          self.notate(&start, Some("ornaments"), xml_element(ornament, &[], Vec::new()));
+        }
+        "turn" => {
+          let ornament = if control.attribute("form") == Some("lower") {
+            "inverted-turn"
+          } else {
+            "turn"
+          };
+          self.notate(&start, Some("ornaments"), xml_element(ornament, &[], Vec::new()));
+        }
+        "gliss" => {
+          self.notate(&start, None, xml_element("glissando", &[("type", "start")], Vec::new()));
+          self.notate(&end, None, xml_element("glissando", &[("type", "stop")], Vec::new()));
+        }
+        _ => (),
+      }
+    }
+  }
+
+  fn transcode_item(item: &MeiItem, whole_divisions: u64) -> XmlElement {
+    match item {
+      MeiItem::Element(element) => element.deep_copy(),
+      MeiItem::Backup(duration) => xml_element(
+        "backup",
+        &[],
+        vec![xml_text_element("duration", duration.to_divisions(whole_divisions))],
+      ),
+      MeiItem::Timed {
+        element,
+        written,
+        actual,
+      } => {
+        let mut element = element.deep_copy();
+        element
+          .elements
+          .push(xml_text_element("duration", actual.to_divisions(whole_divisions)));
+        let is_measure_rest = element
+          .child("rest")
+          .is_some_and(|rest| rest.attribute("measure") == Some("yes"));
+        if element.name == "note" && !is_measure_rest {
+          if let Some((note_type, dots)) = written.note_type() {
+            element.elements.push(xml_text_element("type", note_type));
+            element
+              .elements
+              .extend((0..dots).map(|_| xml_element("dot", &[], Vec::new())));
+          }
+        }
+        element
+      }
+    }
+  }
+
+  fn transcode_part(&self, part_idx: usize, whole_divisions: u64) -> Vec<XmlElement> {
+    let part = &self.parts[part_idx];
+    part
+      .measures
+      .iter()
+      .enumerate()
+      .map(|(measure_idx, items)| {
+        let mut elements = Vec::new();
+        if measure_idx == 0 {
+          let mut attributes = vec![
+            xml_text_element("divisions", whole_divisions / 4),
+            self.key.deep_copy(),
+            self.time.deep_copy(),
+          ];
+          if part.staves.len() > 1 {
+            attributes.push(xml_text_element("staves", part.staves.len()));
+          }
+          for (idx, clef) in part.clefs.iter().enumerate() {
+            let mut clef = clef.deep_copy();
+            clef.attributes.push((String::from("number"), (idx + 1).to_string()));
+            attributes.push(clef);
+          }
+          elements.push(xml_element("attributes", &[], attributes));
+          if let Some(tempo) = self.tempo.as_ref().filter(|_| part_idx == 0) {
+            elements.push(tempo.deep_copy());
+          }
+        }
+        let barlines = &self.measures[measure_idx];
+        if barlines.forward_repeat || barlines.ending_start.is_some() {
+          let mut barline = Vec::new();
+          if barlines.forward_repeat {
+            barline.push(xml_text_element("bar-style", "heavy-light"));
+          }
+          if let Some(numbers) = &barlines.ending_start {
+            barline.push(xml_element(
+              "ending",
+              &[("number", numbers), ("type", "start")],
+              Vec::new(),
+            ));
+          }
+          if barlines.forward_repeat {
+            barline.push(xml_element("repeat", &[("direction", "forward")], Vec::new()));
+          }
+          elements.push(xml_element("barline", &[("location", "left")], barline));
+        }
+        elements.extend(items.iter().map(|item| Self::transcode_item(item, whole_divisions)));
+        if barlines.backward_repeat || barlines.ending_stop.is_some() || barlines.bar_style.is_some() {
+          let mut barline = Vec::new();
+          if let Some(style) = barlines.bar_style {
+            barline.push(xml_text_element("bar-style", style));
+          } else if barlines.backward_repeat {
+            barline.push(xml_text_element("bar-style", "light-heavy"));
+          }
+          if let Some((numbers, discontinue)) = &barlines.ending_stop {
+            barline.push(xml_element(
+              "ending",
+              &[
+                ("number", numbers),
+                ("type", if *discontinue { "discontinue" } else { "stop" }),
+              ],
+              Vec::new(),
+            ));
+          }
+          if barlines.backward_repeat {
+            barline.push(xml_element("repeat", &[("direction", "backward")], Vec::new()));
+          }
+          elements.push(xml_element("barline", &[("location", "right")], barline));
+        }
+        xml_element("measure", &[("number", &(measure_idx + 1).to_string())], elements)
+      })
+      .collect()
+  }
+
+  fn transcode_score(mut self) -> Result<XmlElement, String> {
+    if self.parts.iter().all(|part| part.measures.is_empty()) {
+      return Err(String::from("No music found in the MEI document"));
+    }
+
+    // Place every direction attached by a control event just before or after its event
+    for ((part, measure, idx), directions) in core::mem::take(&mut self.insertions).into_iter().rev() {
+      let items = &mut self.parts[part].measures[measure];
+      let idx = idx.min(items.len());
+      items.splice(idx..idx, directions.into_iter().map(MeiItem::Element));
+    }
+
+    // Choose a number of divisions able to represent every duration exactly
+    let whole_divisions = self
+      .parts
+      .iter()
+      .flat_map(|part| part.measures.iter())
+      .flat_map(|items| items.iter())
+      .try_fold(4, |divisions: u64, item| match item {
+        MeiItem::Timed { actual: duration, .. } | MeiItem::Backup(duration) => {
+          let divisions = divisions / gcd(divisions, duration.denominator);
+          divisions
+            .checked_mul(duration.denominator)
+            .filter(|divisions| *divisions <= MAX_WHOLE_DIVISIONS)
+        }
+        MeiItem::Element(_) => Some(divisions),
+      })
+      .ok_or("Note durations in the MEI document are too fine to be represented")?;
+
+    // Transcode the score into an equivalent partwise MusicXML document
+    let mut contents = core::mem::take(&mut self.metadata);
+    contents.push(xml_element(
+      "part-list",
+      &[],
+      self
+        .parts
+        .iter()
+        .enumerate()
+        .map(|(idx, part)| {
+          xml_element(
+            "score-part",
+            &[("id", &format!("P{}", idx + 1))],
+            vec![xml_text_element("part-name", &part.name)],
+          )
+        })
+        .collect(),
+    ));
+    for idx in 0..self.parts.len() {
+      contents.push(xml_element(
+        "part",
+        &[("id", &format!("P{}", idx + 1))],
+        self.transcode_part(idx, whole_divisions),
+      ));
+    }
+    Ok(xml_element("score-partwise", &[("version", "4.0")], contents))
+  }
+}
+
+/// Tuplet group number, container element, and children of a layer container along with their beats.
+type MeiContainer = (usize, XmlElement, Vec<(XmlElement, Option<u64>)>);
+
+struct MeiEvent {
+  layer: usize,
+  onset: Fraction,
+  duration: Fraction,
+  beamable: bool,
+  tuplets: Vec<(usize, u8, u8)>,
+  element: XmlElement,
+}
+
+struct MeiControl {
+  onset: Fraction,
+  element: XmlElement,
+}
+
+struct MeiStaffWriter {
+  staff: usize,
+  primary: bool,
+  context: StaffContext,
+  tempo: Tempo,
+  onset: Fraction,
+  position: Fraction,
+  alterations: BTreeMap<i32, Accidental>,
+  layer: usize,
+  max_layer: usize,
+  tuplets: Vec<(usize, u8, u8)>,
+  num_tuplets: usize,
+  tied: BTreeMap<usize, Vec<Pitch>>,
+  pending: Vec<XmlElement>,
+  events: Vec<MeiEvent>,
+  controls: Vec<MeiControl>,
+  meters: Vec<(Fraction, Option<Fraction>)>,
+  changes: Vec<(Fraction, XmlElement)>,
+  repeats: Vec<(Fraction, Fraction)>,
+  endings: Vec<(Fraction, Fraction, String)>,
+}
+
+impl MeiStaffWriter {
+  fn new(composition: &Composition, staff: usize) -> Self {
+    let context = StaffContext::new(composition);
+    Self {
+      staff,
+      primary: staff == 1,
+      context,
+      tempo: *composition.get_tempo(),
+      onset: Fraction::ZERO,
+      position: Fraction::ZERO,
+      alterations: BTreeMap::new(),
+      layer: 1,
+      max_layer: 1,
+      tuplets: Vec::new(),
+      num_tuplets: 0,
+      tied: BTreeMap::new(),
+      pending: Vec::new(),
+      events: Vec::new(),
+      controls: Vec::new(),
+      meters: vec![(Fraction::ZERO, context.measure_length)],
+      changes: Vec::new(),
+      repeats: Vec::new(),
+      endings: Vec::new(),
+    }
+  }
+
+  fn advance(&mut self, duration: Fraction) {
+    self.onset = self.onset + duration;
+    self.position = self.position + duration;
+    if let Some(length) = self.context.measure_length.filter(|length| length.numerator > 0) {
+      while self.position >= length {
+        self.position = self.position - length;
+        self.alterations.clear();
+      }
+    }
+  }
+
+  fn control(&mut self, name: &str, onset: Fraction, attributes: &[(&str, &str)], text: &str) {
+    let mut element = xml_element(name, &[("staff", &self.staff.to_string())], Vec::new());
+    element.attributes.extend(
+      attributes
+        .iter()
+        .map(|(key, value)| (String::from(*key), String::from(*value))),
+    );
+    element.text = String::from(text);
+    self.controls.push(MeiControl { onset, element });
+  }
+
+  fn pending_control(&mut self, name: &str, attributes: &[(&str, &str)], text: &str) {
+    let mut element = xml_element(name, attributes, Vec::new());
+    element.text = String::from(text);
+    self.pending.push(element);
+  }
+
+  fn push_event(&mut self, element: XmlElement, written: Fraction, ratio: Fraction, grace: bool) {
+    // Control events preceding an event are anchored to it
+    let id = format!("#{}", element.attribute("xml:id").unwrap_or_default());
+    for mut control in core::mem::take(&mut self.pending) {
+      control
+        .attributes
+        .insert(0, (String::from("staff"), self.staff.to_string()));
+      control.attributes.push((String::from("startid"), id.clone()));
+      self.controls.push(MeiControl {
+        onset: self.onset,
+        element: control,
+      });
+    }
+    let duration = if grace { Fraction::ZERO } else { written * ratio };
+    self.events.push(MeiEvent {
+      layer: self.layer,
+      onset: self.onset,
+      duration,
+      beamable: !grace && element.name != "rest" && written < Fraction::new(1, 4),
+      tuplets: self.tuplets.clone(),
+      element,
+    });
+    self.advance(duration);
+  }
+
+  fn note_attributes(&mut self, note: &Note, element: &mut XmlElement, tied: &[Pitch], tie: bool) {
+    let step = i32::from(note.pitch.octave) * 7 + MeiConverter::step(note.pitch.name);
+    let key_accidental = self.context.key.accidentals()[note.pitch.name.index()];
+    let accidental = match note.accidental {
+      // Notes following an altered note within the same measure must cancel the alteration explicitly
+      Accidental::None => {
+        let altered = self
+          .alterations
+          .get(&step)
+          .is_some_and(|accidental| *accidental != key_accidental);
+        altered.then(|| {
+          self.alterations.insert(step, key_accidental);
+          match key_accidental {
+            Accidental::None => Accidental::Natural,
+            accidental => accidental,
+          }
+        })
+      }
+      accidental => {
+        self.alterations.insert(step, accidental);
+        Some(accidental)
+      }
+    };
+    MeiConverter::set_attribute(element, "pname", MeiConverter::pitch_name(note.pitch.name));
+    MeiConverter::set_attribute(element, "oct", note.pitch.octave);
+    if let Some(accidental) = accidental.and_then(MeiConverter::accidental_text) {
+      MeiConverter::set_attribute(element, "accid", accidental);
+    }
+    let tie = match (tied.contains(&note.pitch), tie) {
+      (true, true) => Some("m"),
+      (true, false) => Some("t"),
+      (false, true) => Some("i"),
+      (false, false) => None,
+    };
+    if let Some(tie) = tie {
+      MeiConverter::set_attribute(element, "tie", tie);
+    }
+  }
+
+  fn write_note(&mut self, note: &Note, ratio: Fraction) {
+    let grace = note
+      .iter_modifications()
+      .find_map(|modification| match modification.r#type {
+        NoteModificationType::Grace { acciaccatura } => Some(acciaccatura),
+        _ => None,
+      });
+    let id = format!("n{}", note.get_id());
+    let mut element = xml_element(
+      if note.is_rest() { "rest" } else { "note" },
+      &[("xml:id", &id)],
+      Vec::new(),
+    );
+    MeiConverter::duration_attributes(&mut element, &note.duration);
+    let tied = if grace.is_some() {
+      Vec::new()
+    } else {
+      self.tied.remove(&self.layer).unwrap_or_default()
+    };
+    let tie = note
+      .iter_modifications()
+      .any(|modification| modification.r#type == NoteModificationType::Tie);
+    if !note.is_rest() {
+      self.note_attributes(note, &mut element, &tied, tie);
+      if tie {
+        self.tied.insert(self.layer, vec![note.pitch]);
+      }
+    }
+    if let Some(acciaccatura) = grace {
+      MeiConverter::set_attribute(&mut element, "grace", if acciaccatura { "acc" } else { "unacc" });
+    }
+    let articulations = note
+      .iter_modifications()
+      .filter_map(|modification| MeiConverter::note_articulation(&modification.r#type))
+      .collect::<Vec<_>>();
+    if !articulations.is_empty() {
+      MeiConverter::set_attribute(&mut element, "artic", articulations.join(" "));
+    }
+    for modification in note.iter_modifications() {
+      match modification.r#type {
+        NoteModificationType::Fermata => MeiConverter::set_attribute(&mut element, "fermata", "above"),
+        NoteModificationType::Tremolo { relative_speed } => {
+          MeiConverter::set_attribute(&mut element, "stem.mod", format!("{}slash", relative_speed.clamp(1, 6)));
+        }
+        NoteModificationType::Dynamic { dynamic } => {
+          if let Some(dynamic) = dynamic_text(&dynamic) {
+            self.pending_control("dynam", &[], &dynamic);
+          }
+        }
+        NoteModificationType::Trill { .. } => self.pending_control("trill", &[], ""),
+        NoteModificationType::Mordent { upper } => {
+          self.pending_control("mordent", &[("form", if upper { "upper" } else { "lower" })], "");
+        }
+        NoteModificationType::Turn { upper, delayed, .. } => {
+          let mut attributes = vec![("form", if upper { "upper" } else { "lower" })];
+          if delayed {
+            attributes.push(("delayed", "true"));
+          }
+          self.pending_control("turn", &attributes, "");
+        }
+        _ => (),
+      }
+    }
+    self.push_event(element, Fraction::from_duration(&note.duration), ratio, grace.is_some());
+  }
+
+  fn write_chord(&mut self, chord: &Chord, ratio: Fraction) {
+    // Grace notes are written just before the chord they belong to
+    let (grace_notes, notes): (Vec<_>, Vec<_>) = chord
+      .iter()
+      .map(|ChordContent::Note(note)| note)
+      .filter(|note| !note.is_rest())
+      .partition(|note| note.is_grace_note());
+    for note in grace_notes {
+      self.write_note(note, ratio);
+    }
+    let Some(first) = notes.first().copied() else {
+      return;
+    };
+    let mut element = xml_element("chord", &[("xml:id", &format!("c{}", chord.get_id()))], Vec::new());
+    MeiConverter::duration_attributes(&mut element, &first.duration);
+    let chord_tie = chord
+      .iter_modifications()
+      .any(|modification| modification.r#type == ChordModificationType::Tie);
+    let tied = self.tied.remove(&self.layer).unwrap_or_default();
+    let mut tied_pitches = Vec::new();
+    for note in notes {
+      let mut note_element = xml_element("note", &[("xml:id", &format!("n{}", note.get_id()))], Vec::new());
+      let tie = chord_tie
+        || note
+          .iter_modifications()
+          .any(|modification| modification.r#type == NoteModificationType::Tie);
+      self.note_attributes(note, &mut note_element, &tied, tie);
+      if tie {
+        tied_pitches.push(note.pitch);
+      }
+      element.elements.push(note_element);
+    }
+    if !tied_pitches.is_empty() {
+      self.tied.insert(self.layer, tied_pitches);
+    }
+    let articulations = chord
+      .iter_modifications()
+      .filter_map(|modification| MeiConverter::chord_articulation(&modification.r#type))
+      .collect::<Vec<_>>();
+    if !articulations.is_empty() {
+      MeiConverter::set_attribute(&mut element, "artic", articulations.join(" "));
+    }
+    for modification in chord.iter_modifications() {
+      match modification.r#type {
+        ChordModificationType::Fermata => MeiConverter::set_attribute(&mut element, "fermata", "above"),
+        ChordModificationType::Tremolo { relative_speed } => {
+          MeiConverter::set_attribute(&mut element, "stem.mod", format!("{}slash", relative_speed.clamp(1, 6)));
+        }
+        ChordModificationType::Arpeggiate => self.pending_control("arpeg", &[], ""),
+        ChordModificationType::Dynamic { dynamic } => {
+          if let Some(dynamic) = dynamic_text(&dynamic) {
+            self.pending_control("dynam", &[], &dynamic);
+          }
+        }
+        _ => (),
+      }
+    }
+    self.push_event(element, Fraction::from_duration(&first.duration), ratio, false);
+  }
+
+  fn write_phrase(&mut self, phrase: &Phrase, mut ratio: Fraction) {
+    let (layer, start) = (self.layer, self.events.len());
+    let mut tuplet = false;
+    for modification in phrase.iter_modifications() {
+      if let PhraseModificationType::Tuplet { num_beats, into_beats } = modification.r#type {
+        if num_beats > 0 && into_beats > 0 && num_beats != into_beats {
+          self.num_tuplets += 1;
+          self.tuplets.push((self.num_tuplets, num_beats, into_beats));
+          ratio = ratio * Fraction::new(u64::from(into_beats), u64::from(num_beats));
+          tuplet = true;
+        }
+      }
+    }
+    for item in phrase.iter() {
+      match item {
+        PhraseContent::Note(note) => self.write_note(note, ratio),
+        PhraseContent::Chord(chord) => self.write_chord(chord, ratio),
+        PhraseContent::Phrase(phrase) => self.write_phrase(phrase, ratio),
+        PhraseContent::MultiVoice(multivoice) => self.write_multivoice(multivoice, ratio),
+      }
+    }
+    if tuplet {
+      self.tuplets.pop();
+    }
+
+    // Spanners run from the first to the last sounding event of the phrase within its own layer
+    let events = self.events[start..]
+      .iter()
+      .filter(|event| event.layer == layer && event.duration > Fraction::ZERO)
+      .filter_map(|event| {
+        event
+          .element
+          .attribute("xml:id")
+          .map(|id| (format!("#{id}"), event.onset))
+      })
+      .collect::<Vec<_>>();
+    let (Some((first, onset)), Some((last, last_onset))) = (events.first().cloned(), events.last().cloned()) else {
+      return;
+    };
+    for modification in phrase.iter_modifications() {
+      match modification.r#type {
+        PhraseModificationType::Legato => {
+          self.control("slur", onset, &[("startid", &first), ("endid", &last)], "");
+        }
+        PhraseModificationType::Crescendo { final_dynamic } | PhraseModificationType::Decrescendo { final_dynamic } => {
+          let form = if matches!(modification.r#type, PhraseModificationType::Crescendo { .. }) {
+            "cres"
+          } else {
+            "dim"
+          };
+          self.control(
+            "hairpin",
+            onset,
+            &[("form", form), ("startid", &first), ("endid", &last)],
+            "",
+          );
+          if let Some(dynamic) = final_dynamic.and_then(|dynamic| dynamic_text(&dynamic)) {
+            self.control("dynam", last_onset, &[("startid", &last)], &dynamic);
+          }
+        }
+        PhraseModificationType::Hairpin { maximum_dynamic } => {
+          // A swell turns from a crescendo into a decrescendo at the middle of the phrase
+          let (middle, middle_onset) = events[(events.len() - 1) / 2].clone();
+          self.control(
+            "hairpin",
+            onset,
+            &[("form", "cres"), ("startid", &first), ("endid", &middle)],
+            "",
+          );
+          self.control(
+            "hairpin",
+            middle_onset,
+            &[("form", "dim"), ("startid", &middle), ("endid", &last)],
+            "",
+          );
+          if let Some(dynamic) = maximum_dynamic.and_then(|dynamic| dynamic_text(&dynamic)) {
+            self.control("dynam", middle_onset, &[("startid", &middle)], &dynamic);
+          }
+        }
+        PhraseModificationType::Glissando | PhraseModificationType::Portamento => {
+          self.control("gliss", onset, &[("startid", &first), ("endid", &last)], "");
+        }
+        PhraseModificationType::OctaveShift { num_octaves } if num_octaves != 0 => {
+          let distance = match num_octaves.unsigned_abs() {
+            1 => "8",
+            2 => "15",
+            _ => "22",
+          };
+          let place = if num_octaves > 0 { "above" } else { "below" };
+          self.control(
+            "octave",
+            onset,
+            &[
+              ("dis", distance),
+              ("dis.place", place),
+              ("startid", &first),
+              ("endid", &last),
+            ],
+            "",
+          );
+        }
+        PhraseModificationType::Pedal { pedal_type } => {
+          let function = match pedal_type {
+            PedalType::Sustain => "sustain",
+            PedalType::Sostenuto => "sostenuto",
+            PedalType::Soft => "soft",
+          };
+          self.control(
+            "pedal",
+            onset,
+            &[("dir", "down"), ("func", function), ("startid", &first)],
+            "",
+          );
+          self.control(
+            "pedal",
+            last_onset,
+            &[("dir", "up"), ("func", function), ("startid", &last)],
+            "",
+          );
+        }
+        _ => (),
+      }
+    }
+  }
+
+  fn write_multivoice(&mut self, multivoice: &MultiVoice, ratio: Fraction) {
+    let phrases = multivoice
+      .iter()
+      .map(|MultiVoiceContent::Phrase(phrase)| phrase)
+      .filter(|phrase| !phrase.is_empty())
+      .collect::<Vec<_>>();
+    let (layer, max_layer, onset, position) = (self.layer, self.max_layer, self.onset, self.position);
+    let alterations = self.alterations.clone();
+
+    // Additional voices are written into layers that no enclosing voice is using
+    let layers = (0..phrases.len())
+      .map(|idx| {
+        if idx == 0 {
+          layer
+        } else {
+          self.max_layer += 1;
+          self.max_layer
+        }
+      })
+      .collect::<Vec<_>>();
+    let (mut end, mut end_position, mut first_alterations) = (onset, position, None);
+    for (phrase, voice_layer) in phrases.into_iter().zip(layers) {
+      self.onset = onset;
+      self.position = position;
+      self.alterations.clone_from(&alterations);
+      self.layer = voice_layer;
+      self.write_phrase(phrase, ratio);
+      if self.onset > end {
+        end = self.onset;
+        end_position = self.position;
+      }
+      first_alterations.get_or_insert_with(|| self.alterations.clone());
+    }
+    self.layer = layer;
+    self.max_layer = max_layer;
+    self.onset = end;
+    self.position = end_position;
+    self.alterations = first_alterations.unwrap_or(alterations);
+  }
+
+  fn write_direction(&mut self, direction: &Direction) {
+    match &direction.r#type {
+      DirectionType::Dynamic { dynamic } => {
+        if let Some(dynamic) = dynamic_text(dynamic) {
+          self.pending_control("dynam", &[], &dynamic);
+        }
+      }
+      DirectionType::KeyChange { .. }
+      | DirectionType::TimeSignatureChange { .. }
+      | DirectionType::ClefChange { .. } => match self.context.apply(&direction.r#type) {
+        Some(ContextChange::Key(key)) if self.primary => {
+          self
+            .changes
+            .push((self.onset, MeiConverter::key_signature_element(&key)));
+        }
+        Some(ContextChange::TimeSignature(time_signature)) if self.primary => {
+          self.meters.push((self.onset, self.context.measure_length));
+          if let Some(meter) = MeiConverter::meter_signature_element(&time_signature) {
+            self.changes.push((self.onset, meter));
+          }
+        }
+        Some(ContextChange::Clef(clef_type)) if !self.events.is_empty() => {
+          let element = MeiConverter::clef_element_for(clef_type);
+          self.events.push(MeiEvent {
+            layer: self.layer,
+            onset: self.onset,
+            duration: Fraction::ZERO,
+            beamable: false,
+            tuplets: self.tuplets.clone(),
+            element,
+          });
+        }
+        _ => (),
+      },
+      DirectionType::Rehearsal { mark, .. } => self.pending_control("reh", &[], mark),
+      DirectionType::Text { text, style } | DirectionType::Expression { text, style } => {
+        let place = if style.placement == Some(TextPlacement::Below) {
+          "below"
+        } else {
+          "above"
+        };
+        self.pending_control("dir", &[("place", place)], text);
+      }
+      DirectionType::BreathMark | DirectionType::Caesura => {
+        let name = if direction.r#type == DirectionType::BreathMark {
+          "breath"
+        } else {
+          "caesura"
+        };
+        let last = self
+          .events
+          .iter()
+          .rev()
+          .find(|event| event.layer == self.layer && event.duration > Fraction::ZERO)
+          .and_then(|event| {
+            event
+              .element
+              .attribute("xml:id")
+              .map(|id| (format!("#{id}"), event.onset))
+          });
+        match last {
+          Some((id, onset)) => self.control(name, onset, &[("startid", &id)], ""),
+          None => self.pending_control(name, &[], ""),
+        }
+      }
+      _ => (),
+    }
+  }
+
+  fn write_staff(&mut self, staff: &Staff) {
+    for item in staff.iter() {
+      match item {
+        StaffContent::Note(note) => self.write_note(note, Fraction::ONE),
+        StaffContent::Chord(chord) => self.write_chord(chord, Fraction::ONE),
+        StaffContent::Phrase(phrase) => self.write_phrase(phrase, Fraction::ONE),
+        StaffContent::MultiVoice(multivoice) => self.write_multivoice(multivoice, Fraction::ONE),
+        StaffContent::Direction(direction) => self.write_direction(direction),
+      }
+    }
+  }
+
+  fn write_section(&mut self, section: &Section, staff_name: &str) {
+    let (start, primary) = (self.onset, self.primary);
+    for modification in section.iter_modifications().filter(|_| primary) {
+      match &modification.r#type {
+        SectionModificationType::TempoExplicit { tempo } => {
+          if *tempo != self.tempo {
+            self.tempo = *tempo;
+            let attributes = MeiConverter::tempo_attributes(tempo);
+            let attributes = attributes
+              .iter()
+              .map(|(key, value)| (*key, value.as_str()))
+              .collect::<Vec<_>>();
+            self.pending_control("tempo", &attributes, "");
+          }
+        }
+        SectionModificationType::TempoImplicit { tempo } => {
+          self.pending_control("tempo", &[], &tempo_marking_text(tempo.marking));
+        }
+        SectionModificationType::Accelerando => self.pending_control("tempo", &[], "accel."),
+        SectionModificationType::ATempo => self.pending_control("tempo", &[], "a tempo"),
+        SectionModificationType::TempoPrimo => self.pending_control("tempo", &[], "Tempo I"),
+        SectionModificationType::Rallentando => self.pending_control("tempo", &[], "rall."),
+        SectionModificationType::Ritardando => self.pending_control("tempo", &[], "rit."),
+        SectionModificationType::Ritenuto => self.pending_control("tempo", &[], "riten."),
+        SectionModificationType::Stringendo => self.pending_control("tempo", &[], "string."),
+        SectionModificationType::Repeat { .. } | SectionModificationType::OnlyPlay { .. } => (),
+      }
+    }
+    for content in section.iter() {
+      match content {
+        SectionContent::Staff(staff) if staff.get_name() == staff_name => self.write_staff(staff),
+        SectionContent::Section(section) => self.write_section(section, staff_name),
+        SectionContent::Staff(_) => (),
+      }
+    }
+    if self.primary && self.onset > start {
+      for modification in section.iter_modifications() {
+        match &modification.r#type {
+          SectionModificationType::Repeat { .. } => self.repeats.push((start, self.onset)),
+          SectionModificationType::OnlyPlay { iterations } => {
+            let numbers = iterations
+              .iter()
+              .map(|iteration| (u16::from(*iteration) + 1).to_string())
+              .collect::<Vec<_>>();
+            self.endings.push((start, self.onset, numbers.join(", ")));
+          }
+          _ => (),
+        }
+      }
+    }
+  }
+
+  fn finish(&mut self) {
+    // Control events at the very end of a staff are anchored to its last event
+    let last = self
+      .events
+      .iter()
+      .rev()
+      .find(|event| event.duration > Fraction::ZERO)
+      .and_then(|event| {
+        event
+          .element
+          .attribute("xml:id")
+          .map(|id| (format!("#{id}"), event.onset))
+      });
+    let pending = core::mem::take(&mut self.pending);
+    if let Some((id, onset)) = last {
+      for mut control in pending {
+        control
+          .attributes
+          .insert(0, (String::from("staff"), self.staff.to_string()));
+        control.attributes.push((String::from("startid"), id.clone()));
+        self.controls.push(MeiControl {
+          onset,
+          element: control,
+        });
+      }
+    }
+  }
+
+  fn end(&self) -> Fraction {
+    self
+      .events
+      .iter()
+      .map(|event| event.onset + event.duration)
+      .max()
+      .unwrap_or(Fraction::ZERO)
+  }
+}
+
+/// Converter between AMM compositions and MEI (Music Encoding Initiative) documents.
+///
+/// MEI documents are imported by transcoding them into an equivalent MusicXML
+/// document which is then loaded by the MusicXML converter. Compositions are
+/// exported as MEI 5 documents with one staff per AMM staff, grouping the staves
+/// of parts that have more than one.
+pub struct MeiConverter;
+
+impl MeiConverter {
+  fn text_content(element: &XmlElement) -> String {
+    let mut text = element.text.clone();
+    for child in &element.elements {
+      let child_text = Self::text_content(child);
+      if !child_text.is_empty() {
+        if !text.is_empty() {
+          text.push(' ');
+        }
+        text.push_str(&child_text);
+      }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+  }
+
+  fn label(element: &XmlElement) -> Option<String> {
+    element
+      .attribute("label")
+      .map(String::from)
+      .or_else(|| element.child("label").map(Self::text_content))
+      .map(|label| String::from(label.trim()))
+      .filter(|label| !label.is_empty())
+  }
+
+  fn ending_numbers(ending: &XmlElement) -> String {
+    let text = ending.attribute("label").or(ending.attribute("n")).unwrap_or("1");
+    let numbers = text
+      .split(|ch: char| !ch.is_ascii_digit())
+      .filter(|number| !number.is_empty())
+      .collect::<Vec<_>>();
+    if numbers.is_empty() {
+      String::from("1")
+    } else {
+      numbers.join(", ")
+    }
+  }
+
+  fn parse_beats(text: &str) -> Option<Fraction> {
+    let (whole, fraction) = text.trim().split_once('.').unwrap_or((text.trim(), ""));
+    let fraction = &fraction[..fraction.len().min(6)];
+    let denominator = 10u64.pow(fraction.len() as u32);
+    let beats = whole.parse::<u64>().ok()?;
+    let fraction = if fraction.is_empty() {
+      0
+    } else {
+      fraction.parse::<u64>().ok()?
+    };
+    (beats >= 1).then(|| Fraction::new((beats - 1) * denominator + fraction, denominator))
+  }
+
+  fn duration_value(text: &str) -> Option<Fraction> {
+    match text.trim() {
+      "maxima" => Some(Fraction::new(8, 1)),
+      "long" => Some(Fraction::new(4, 1)),
+      "breve" => Some(Fraction::new(2, 1)),
+      value => value
+        .parse::<u64>()
+        .ok()
+        .filter(|value| value.is_power_of_two() && *value <= 2048)
+        .map(|value| Fraction::new(1, value)),
+    }
+  }
+
+  fn duration(element: &XmlElement) -> Option<Fraction> {
+    let value = Self::duration_value(element.attribute("dur")?)?;
+    let dots = element
+      .attribute("dots")
+      .and_then(|dots| dots.parse::<u32>().ok())
+      .unwrap_or_default()
+      .min(MAX_DOTS);
+    Some(value * Fraction::new((1 << (dots + 1)) - 1, 1 << dots))
+  }
+
+  fn key_element(fifths: i32, minor: bool) -> XmlElement {
+    xml_element(
+      "key",
+      &[],
+      vec![
+        xml_text_element("fifths", fifths.clamp(-7, 7)),
+        xml_text_element("mode", if minor { "minor" } else { "major" }),
+      ],
+    )
+  }
+
+  fn key_signature(definition: &XmlElement) -> Option<XmlElement> {
+    let key_signature = definition.child("keySig");
+    let signature = key_signature
+      .and_then(|key_signature| key_signature.attribute("sig"))
+      .or_else(|| definition.attribute("keysig"))
+      .or_else(|| definition.attribute("key.sig"))?;
+    let mode = key_signature
+      .and_then(|key_signature| key_signature.attribute("mode"))
+      .or_else(|| definition.attribute("key.mode"));
+    let fifths = match signature.trim() {
+      "0" => 0,
+      signature => {
+        let count = signature[..signature.len() - 1].parse::<i32>().ok()?;
+        match signature.chars().last()? {
+          's' => count,
+          'f' => -count,
+          _ => return None,
+        }
+      }
+    };
+    Some(Self::key_element(fifths, mode == Some("minor")))
+  }
+
+  fn meter_signature(definition: &XmlElement) -> Option<(XmlElement, Fraction, Fraction)> {
+    let meter = definition.child("meterSig");
+    let attribute = |name: &str, fallback: &str| {
+      meter
+        .and_then(|meter| meter.attribute(name))
+        .or_else(|| definition.attribute(fallback))
+    };
+    let symbol = attribute("sym", "meter.sym");
+    let (count, unit) = match (attribute("count", "meter.count"), attribute("unit", "meter.unit")) {
+      (Some(count), Some(unit)) => (String::from(count), unit.trim().parse::<u64>().ok()?),
+      _ => match symbol? {
+        "common" => (String::from("4"), 4),
+        "cut" => (String::from("2"), 2),
+        _ => return None,
+      },
+    };
+    let beats = count
+      .split('+')
+      .map(|beats| beats.trim().parse::<u64>().ok())
+      .sum::<Option<u64>>()?;
+    if beats == 0 || unit == 0 {
+      return None;
+    }
+    let elements = vec![xml_text_element("beats", count), xml_text_element("beat-type", unit)];
+    let time = match symbol {
+      Some(symbol @ ("common" | "cut")) => xml_element("time", &[("symbol", symbol)], elements),
+      _ => xml_element("time", &[], elements),
+    };
+    Some((time, Fraction::new(beats, unit), Fraction::new(1, unit)))
+  }
+
+  fn clef_element(sign: &str, line: u8, octave_change: i32) -> XmlElement {
+    let mut elements = vec![xml_text_element("sign", sign), xml_text_element("line", line)];
+    if octave_change != 0 {
+      elements.push(xml_text_element("clef-octave-change", octave_change));
+    }
+    xml_element("clef", &[], elements)
+  }
+
+  fn clef(element: &XmlElement) -> Option<XmlElement> {
+    let (clef, prefix) = if element.name == "clef" {
+      (element, "")
+    } else if let Some(clef) = element.child("clef") {
+      (clef, "")
+    } else {
+      (element, "clef.")
+    };
+    let attribute = |name: &str| clef.attribute(&format!("{prefix}{name}"));
+    let (sign, default_line) = match attribute("shape")? {
+      "G" | "GG" => ("G", 2),
+      "F" => ("F", 4),
+      "C" => ("C", 3),
+      _ => return None,
+    };
+    let line = attribute("line")
+      .and_then(|line| line.parse::<u8>().ok())
+      .filter(|line| (1..=5).contains(line))
+      .unwrap_or(default_line);
+    let octaves = match attribute("dis") {
+      Some("8") => 1,
+      Some("15") => 2,
+      _ => 0,
+    };
+    let octave_change = if attribute("dis.place") == Some("below") {
+      -octaves
+    } else {
+      octaves
+    };
+    Some(Self::clef_element(sign, line, octave_change))
+  }
+
+  fn direction(
+    direction_types: Vec<XmlElement>,
+    sound: Option<XmlElement>,
+    placement: Option<&str>,
+    staff: usize,
+  ) -> XmlElement {
+    let mut elements = direction_types
+      .into_iter()
+      .map(|direction_type| xml_element("direction-type", &[], vec![direction_type]))
+      .collect::<Vec<_>>();
+    elements.extend(sound);
+    elements.push(xml_text_element("staff", staff));
+    match placement {
+      Some(placement) => xml_element("direction", &[("placement", placement)], elements),
+      None => xml_element("direction", &[], elements),
+    }
+  }
+
+  fn note_element(note: &XmlElement, in_chord: bool) -> XmlElement {
+    let step = note
+      .attribute("pname")
+      .map(str::to_ascii_uppercase)
+      .filter(|step| matches!(step.as_str(), "A" | "B" | "C" | "D" | "E" | "F" | "G"));
+    let octave = note
+      .attribute("oct")
+      .and_then(|octave| octave.trim().parse::<i32>().ok());
+    let (Some(step), Some(octave)) = (step, octave) else {
+      return xml_element("note", &[], vec![xml_element("rest", &[], Vec::new())]);
+    };
+    let mut elements = Vec::new();
+    if in_chord {
+      elements.push(xml_element("chord", &[], Vec::new()));
+    }
+    elements.push(xml_element(
+      "pitch",
+      &[],
+      vec![
+        xml_text_element("step", step),
+        xml_text_element("octave", octave.clamp(0, 9)),
+      ],
+    ));
+    let accidental = note
+      .attribute("accid")
+      .or_else(|| note.child("accid").and_then(|accid| accid.attribute("accid")));
+    if let Some(accidental) = accidental.and_then(|accidental| match accidental {
+      "s" => Some("sharp"),
+      "f" => Some("flat"),
+      "ss" | "x" => Some("double-sharp"),
+      "ff" => Some("flat-flat"),
+      "n" => Some("natural"),
+      _ => None,
+    }) {
+      elements.push(xml_text_element("accidental", accidental));
+    }
+    let mut element = xml_element("note", &[], elements);
+    let tie = note.attribute("tie").unwrap_or_default();
+    for (marker, tie_type) in [('t', "stop"), ('m', "stop"), ('i', "start"), ('m', "start")] {
+      if tie.contains(marker) {
+        element
+          .elements
+          .push(xml_element("tie", &[("type", tie_type)], Vec::new()));
+        add_notation(
+          &mut element,
+          None,
+          xml_element("tied", &[("type", tie_type)], Vec::new()),
+        );
+      }
+    }
+    element
+  }
+
+  fn add_articulations(element: &mut XmlElement, event: &XmlElement) {
+    let articulations = event
+      .attribute("artic")
+      .into_iter()
+      .chain(
+        event
+          .elements
+          .iter()
+          .filter(|child| child.name == "artic")
+          .filter_map(|child| child.attribute("artic")),
+      )
+      .flat_map(str::split_whitespace)
+      .collect::<Vec<_>>();
+    for articulation in articulations {
+      if let Some((_, group, name)) = ARTICULATIONS.iter().find(|(artic, ..)| *artic == articulation) {
+        add_notation(element, Some(group), xml_element(name, &[], Vec::new()));
+      }
+    }
+    if event.attribute("fermata").is_some() {
+      add_notation(element, None, xml_element("fermata", &[], Vec::new()));
+    }
+    if let Some(strokes) = event
+      .attribute("stem.mod")
+      .and_then(|modifier| modifier.strip_suffix("slash"))
+      .filter(|strokes| matches!(*strokes, "1" | "2" | "3" | "4" | "5" | "6"))
+    {
+      add_notation(
+        element,
+        Some("ornaments"),
+        XmlElement {
+          text: String::from(strokes),
+          ..xml_element("tremolo", &[("type", "single")], Vec::new())
+        },
+      );
+    }
+  }
+
+  fn load_from_mei(data: &[u8]) -> Result<Composition, String> {
+    let reader = MeiReader::parse(&String::from_utf8_lossy(data))?;
+    MusicXmlConverter::load_from_musicxml(&ScorePartwise::deserialize(&reader.transcode_score()?)?)
+  }
+
+  fn set_attribute(element: &mut XmlElement, name: &str, value: impl ToString) {
+    element.attributes.push((String::from(name), value.to_string()));
+  }
+
+  fn step(name: PitchName) -> i32 {
+    match name {
+      PitchName::C | PitchName::Rest => 0,
+      PitchName::D => 1,
+      PitchName::E => 2,
+      PitchName::F => 3,
+      PitchName::G => 4,
+      PitchName::A => 5,
+      PitchName::B => 6,
+    }
+  }
+
+  fn pitch_name(name: PitchName) -> &'static str {
+    match name {
+      PitchName::A => "a",
+      PitchName::B => "b",
+      PitchName::C | PitchName::Rest => "c",
+      PitchName::D => "d",
+      PitchName::E => "e",
+      PitchName::F => "f",
+      PitchName::G => "g",
+    }
+  }
+
+  fn accidental_text(accidental: Accidental) -> Option<&'static str> {
+    match accidental {
+      Accidental::Sharp => Some("s"),
+      Accidental::Flat => Some("f"),
+      Accidental::DoubleSharp => Some("x"),
+      Accidental::DoubleFlat => Some("ff"),
+      Accidental::Natural => Some("n"),
+      Accidental::None => None,
+    }
+  }
+
+  fn duration_text(value: DurationType) -> &'static str {
+    match value {
+      DurationType::Maxima => "maxima",
+      DurationType::Long => "long",
+      DurationType::Breve => "breve",
+      DurationType::Whole => "1",
+      DurationType::Half => "2",
+      DurationType::Quarter => "4",
+      DurationType::Eighth => "8",
+      DurationType::Sixteenth => "16",
+      DurationType::ThirtySecond => "32",
+      DurationType::SixtyFourth => "64",
+      DurationType::OneHundredTwentyEighth => "128",
+      DurationType::TwoHundredFiftySixth => "256",
+      DurationType::FiveHundredTwelfth => "512",
+      DurationType::OneThousandTwentyFourth => "1024",
+      DurationType::TwoThousandFortyEighth => "2048",
+    }
+  }
+
+  fn duration_attributes(element: &mut XmlElement, duration: &Duration) {
+    Self::set_attribute(element, "dur", Self::duration_text(duration.value));
+    if duration.dots > 0 {
+      Self::set_attribute(element, "dots", duration.dots);
+    }
+  }
+
+  fn space_elements(mut duration: Fraction) -> Vec<XmlElement> {
+    // Gaps are filled with the fewest spaces of undotted durations that add up to them
+    let mut spaces = Vec::new();
+    let mut value = Fraction::new(4, 1);
+    while duration > Fraction::ZERO && value >= Fraction::new(1, 2048) {
+      if duration >= value {
+        let text = match value.numerator {
+          4 => String::from("long"),
+          2 => String::from("breve"),
+          _ => value.denominator.to_string(),
+        };
+        spaces.push(xml_element("space", &[("dur", &text)], Vec::new()));
+        duration = duration - value;
+      } else {
+        value = value * Fraction::new(1, 2);
+      }
+    }
+    spaces
+  }
+
+  fn note_articulation(modification: &NoteModificationType) -> Option<&'static str> {
+    match modification {
+      NoteModificationType::Accent => Some("acc"),
+      NoteModificationType::DetachedLegato => Some("ten-stacc"),
+      NoteModificationType::Doit => Some("doit"),
+      NoteModificationType::DoubleTongue => Some("dbltongue"),
+      NoteModificationType::DownBow => Some("dnbow"),
+      NoteModificationType::Falloff => Some("fall"),
+      NoteModificationType::Fingernails => Some("fingernail"),
+      NoteModificationType::Harmonic { .. } => Some("harm"),
+      NoteModificationType::Heel => Some("heel"),
+      NoteModificationType::Marcato => Some("marc"),
+      NoteModificationType::Open => Some("open"),
+      NoteModificationType::Plop => Some("plop"),
+      NoteModificationType::Scoop => Some("scoop"),
+      NoteModificationType::SoftAccent => Some("acc-soft"),
+      NoteModificationType::Spiccato => Some("spicc"),
+      NoteModificationType::Staccato => Some("stacc"),
+      NoteModificationType::Staccatissimo => Some("stacciss"),
+      NoteModificationType::Stopped => Some("stop"),
+      NoteModificationType::Tap => Some("tap"),
+      NoteModificationType::Tenuto => Some("ten"),
+      NoteModificationType::Toe => Some("toe"),
+      NoteModificationType::TripleTongue => Some("trpltongue"),
+      NoteModificationType::UpBow => Some("upbow"),
+      _ => None,
+    }
+  }
+
+  fn chord_articulation(modification: &ChordModificationType) -> Option<&'static str> {
+    match modification {
+      ChordModificationType::Accent => Some("acc"),
+      ChordModificationType::DetachedLegato => Some("ten-stacc"),
+      ChordModificationType::DownBow => Some("dnbow"),
+      ChordModificationType::Fingernails => Some("fingernail"),
+      ChordModificationType::Heel => Some("heel"),
+      ChordModificationType::Marcato => Some("marc"),
+      ChordModificationType::Open => Some("open"),
+      ChordModificationType::SoftAccent => Some("acc-soft"),
+      ChordModificationType::Spiccato => Some("spicc"),
+      ChordModificationType::Staccato => Some("stacc"),
+      ChordModificationType::Staccatissimo => Some("stacciss"),
+      ChordModificationType::Tenuto => Some("ten"),
+      ChordModificationType::Toe => Some("toe"),
+      ChordModificationType::UpBow => Some("upbow"),
+      _ => None,
+    }
+  }
+
+  fn key_signature_element(key: &Key) -> XmlElement {
+    let fifths = key.fifths();
+    let signature = match fifths {
+      0 => String::from("0"),
+      fifths if fifths > 0 => format!("{fifths}s"),
+      fifths => format!("{}f", fifths.unsigned_abs()),
+    };
+    let mode = match key.mode {
+      KeyMode::Major => "major",
+      KeyMode::Minor => "minor",
+    };
+    xml_element("keySig", &[("sig", &signature), ("mode", mode)], Vec::new())
+  }
+
+  fn meter_signature_element(time_signature: &TimeSignature) -> Option<XmlElement> {
+    match time_signature.signature {
+      TimeSignatureType::CommonTime => Some(xml_element(
+        "meterSig",
+        &[("count", "4"), ("unit", "4"), ("sym", "common")],
+        Vec::new(),
+      )),
+      TimeSignatureType::CutTime => Some(xml_element(
+        "meterSig",
+        &[("count", "2"), ("unit", "2"), ("sym", "cut")],
+        Vec::new(),
+      )),
+      TimeSignatureType::None => None,
+      TimeSignatureType::Explicit => Some(xml_element(
+        "meterSig",
+        &[
+          ("count", &time_signature.numerator.to_string()),
+          ("unit", &time_signature.denominator.to_string()),
+        ],
+        Vec::new(),
+      )),
+    }
+  }
+
+  fn clef_element_for(clef_type: ClefType) -> XmlElement {
+    let (shape, line) = match clef_type {
+      ClefType::Treble => ("G", "2"),
+      ClefType::FrenchViolin => ("G", "1"),
+      ClefType::Bass => ("F", "4"),
+      ClefType::Baritone => ("F", "3"),
+      ClefType::Subbass => ("F", "5"),
+      ClefType::Alto => ("C", "3"),
+      ClefType::Tenor => ("C", "4"),
+      ClefType::Soprano => ("C", "1"),
+      ClefType::MezzoSoprano => ("C", "2"),
+    };
+    xml_element("clef", &[("shape", shape), ("line", line)], Vec::new())
+  }
+
+  fn tempo_attributes(tempo: &Tempo) -> Vec<(&'static str, String)> {
+    let beat = Fraction::from_duration(&tempo.base_note);
+    let quarters_per_minute = f64::from(tempo.beats_per_minute) * 4.0 * beat.numerator as f64 / beat.denominator as f64;
+    let mut attributes = vec![
+      ("mm", tempo.beats_per_minute.to_string()),
+      ("mm.unit", String::from(Self::duration_text(tempo.base_note.value))),
+    ];
+    if tempo.base_note.dots > 0 {
+      attributes.push(("mm.dots", tempo.base_note.dots.to_string()));
+    }
+    attributes.push(("midi.bpm", quarters_per_minute.to_string()));
+    attributes
+  }
+
+  fn head_element(composition: &Composition) -> XmlElement {
+    let mut title_statement = vec![xml_text_element("title", composition.get_title())];
+    if let Some(movement_title) = composition.get_metadata().get("movement_title") {
+      title_statement.push(XmlElement {
+        text: movement_title.clone(),
+        ..xml_element("title", &[("type", "subordinate")], Vec::new())
+      });
+    }
+    let responsibilities = [
+      ("composer", composition.get_composers()),
+      ("lyricist", composition.get_lyricists()),
+      ("arranger", composition.get_arrangers()),
+    ]
+    .into_iter()
+    .flat_map(|(role, names)| {
+      names.iter().map(move |name| XmlElement {
+        text: name.clone(),
+        ..xml_element("persName", &[("role", role)], Vec::new())
+      })
+    })
+    .collect::<Vec<_>>();
+    if !responsibilities.is_empty() {
+      title_statement.push(xml_element("respStmt", &[], responsibilities));
+    }
+    let mut publication = Vec::new();
+    if let Some(publisher) = composition.get_publisher() {
+      publication.push(xml_text_element("publisher", publisher));
+    }
+    if let Some(copyright) = composition.get_copyright() {
+      publication.push(xml_element(
+        "availability",
+        &[],
+        vec![xml_text_element("useRestrict", copyright)],
+      ));
+    }
+    xml_element(
+      "meiHead",
+      &[],
+      vec![xml_element(
+        "fileDesc",
+        &[],
+        vec![
+          xml_element("titleStmt", &[], title_statement),
+          xml_element("pubStmt", &[], publication),
+        ],
+      )],
+    )
+  }
+
+  fn beam_groups(children: Vec<(XmlElement, Option<u64>)>) -> Vec<XmlElement> {
+    let mut elements = Vec::new();
+    let mut group: Vec<XmlElement> = Vec::new();
+    let mut group_beat = None;
+    let flush = |group: &mut Vec<XmlElement>, elements: &mut Vec<XmlElement>| {
+      if group.len() > 1 {
+        elements.push(xml_element("beam", &[], core::mem::take(group)));
+      } else {
+        elements.append(group);
+      }
+    };
+    for (element, beat) in children {
+      if beat.is_none() || beat != group_beat {
+        flush(&mut group, &mut elements);
+      }
+      group_beat = beat;
+      if beat.is_some() {
+        group.push(element);
+      } else {
+        elements.push(element);
+      }
+    }
+    flush(&mut group, &mut elements);
+    elements
+  }
+
+  fn layer_element(events: &[&MeiEvent], layer: usize, start: Fraction, beat: Fraction) -> XmlElement {
+    // Open tuplets are kept on a stack of containers, each collecting its children along with their beats
+    let mut stack: Vec<MeiContainer> = vec![(
+      0,
+      xml_element("layer", &[("n", &layer.to_string())], Vec::new()),
+      Vec::new(),
+    )];
+    let close = |stack: &mut Vec<MeiContainer>| {
+      if let Some((_, mut container, children)) = stack.pop() {
+        container.elements = Self::beam_groups(children);
+        if let Some((_, _, parent)) = stack.last_mut() {
+          parent.push((container, None));
+        } else {
+          stack.push((0, container, Vec::new()));
+        }
+      }
+    };
+    let mut cursor = start;
+    for event in events {
+      let gap = event.onset > cursor;
+      let depth = if gap {
+        0
+      } else {
+        stack[1..]
+          .iter()
+          .zip(&event.tuplets)
+          .take_while(|((group, ..), (tuplet, ..))| group == tuplet)
+          .count()
+      };
+      while stack.len() > depth + 1 {
+        close(&mut stack);
+      }
+      if gap {
+        stack[0].2.extend(
+          Self::space_elements(event.onset - cursor)
+            .into_iter()
+            .map(|space| (space, None)),
+        );
+      }
+      for (group, num, numbase) in &event.tuplets[depth..] {
+        stack.push((
+          *group,
+          xml_element(
+            "tuplet",
+            &[("num", &num.to_string()), ("numbase", &numbase.to_string())],
+            Vec::new(),
+          ),
+          Vec::new(),
+        ));
+      }
+      let offset = (event.onset - start) / beat;
+      let beat = event.beamable.then(|| offset.numerator / offset.denominator);
+      if let Some((_, _, children)) = stack.last_mut() {
+        children.push((event.element.deep_copy(), beat));
+      }
+      cursor = cursor.max(event.onset + event.duration);
+    }
+    while stack.len() > 1 {
+      close(&mut stack);
+    }
+    let (_, mut layer, children) = stack.pop().unwrap_or_default();
+    layer.elements = Self::beam_groups(children);
+    layer
+  }
+
+  fn save_to_mei(composition: &Composition) -> String {
+    // Collect the events of every staff, grouping the staves of parts that have more than one
+    let mut writers: Vec<MeiStaffWriter> = Vec::new();
+    let mut staff_group = Vec::new();
+    for part in composition.iter() {
+      let staff_names = part.get_staff_names();
+      let mut definitions = Vec::new();
+      for staff_name in &staff_names {
+        let mut writer = MeiStaffWriter::new(composition, writers.len() + 1);
+        for PartContent::Section(section) in part.iter() {
+          writer.write_section(section, staff_name);
+        }
+        writer.finish();
+        let initial_clef = writer
+          .events
+          .iter()
+          .find(|event| event.element.name == "clef")
+          .map_or(writer.context.clef, |_| ClefType::Treble);
+        let mut definition = xml_element(
+          "staffDef",
+          &[("n", &writer.staff.to_string()), ("lines", "5")],
+          vec![Self::clef_element_for(initial_clef)],
+        );
+        if staff_names.len() == 1 {
+          definition
+            .elements
+            .insert(0, xml_text_element("label", part.get_name()));
+        }
+        definitions.push(definition);
+        writers.push(writer);
+      }
+      if definitions.len() > 1 {
+        definitions.insert(0, xml_text_element("label", part.get_name()));
+        staff_group.push(xml_element(
+          "staffGrp",
+          &[("symbol", "brace"), ("bar.thru", "true")],
+          definitions,
+        ));
+      } else {
+        staff_group.extend(definitions);
+      }
+    }
+
+    // Divide the music into measures according to the time signatures of the first staff
+    let end = writers.iter().map(MeiStaffWriter::end).max().unwrap_or(Fraction::ZERO);
+    let starts = writers
+      .first()
+      .map_or_else(|| vec![Fraction::ZERO], |primary| measure_starts(&primary.meters, end));
+    let measure_of = |onset: Fraction| starts.partition_point(|start| *start <= onset).saturating_sub(1);
+    let last_measure_of = |end: Fraction| starts.partition_point(|start| *start < end).saturating_sub(1);
+    let mut measures = (0..starts.len())
+      .map(|idx| xml_element("measure", &[("n", &(idx + 1).to_string())], Vec::new()))
+      .collect::<Vec<_>>();
+    for writer in &writers {
+      let mut buckets: Vec<Vec<&MeiEvent>> = (0..starts.len()).map(|_| Vec::new()).collect();
+      for event in &writer.events {
+        buckets[measure_of(event.onset)].push(event);
+      }
+      for (idx, events) in buckets.into_iter().enumerate() {
+        let start = starts[idx];
+        let meter = writer
+          .meters
+          .iter()
+          .rev()
+          .find(|(onset, _)| *onset <= start)
+          .and_then(|(_, length)| *length);
+        let beat = match meter {
+          Some(length) if length.numerator % 3 == 0 && length.numerator > 3 && length.denominator >= 8 => {
+            Fraction::new(3, length.denominator)
+          }
+          _ => Fraction::new(1, 4),
+        };
+        let mut layers = events.iter().map(|event| event.layer).collect::<Vec<_>>();
+        layers.sort_unstable();
+        layers.dedup();
+        let mut staff = xml_element("staff", &[("n", &writer.staff.to_string())], Vec::new());
+        for layer in layers {
+          let layer_events = events
+            .iter()
+            .filter(|event| event.layer == layer)
+            .copied()
+            .collect::<Vec<_>>();
+          staff
+            .elements
+            .push(Self::layer_element(&layer_events, layer, start, beat));
+        }
+        if staff.elements.is_empty() {
+          staff.elements.push(xml_element(
+            "layer",
+            &[("n", "1")],
+            vec![xml_element("mSpace", &[], Vec::new())],
+          ));
+        }
+        measures[idx].elements.push(staff);
+      }
+    }
+    for writer in &writers {
+      for control in &writer.controls {
+        measures[measure_of(control.onset)]
+          .elements
+          .push(control.element.deep_copy());
+      }
+    }
+
+    // Add the initial tempo along with any repeats, endings, and changes of context
+    let tempo_attributes = Self::tempo_attributes(composition.get_tempo());
+    let mut tempo = xml_element("tempo", &[("tstamp", "1"), ("staff", "1")], Vec::new());
+    tempo.attributes.extend(
+      tempo_attributes
+        .into_iter()
+        .map(|(key, value)| (String::from(key), value)),
+    );
+    let first_control = measures[0]
+      .elements
+      .iter()
+      .position(|element| element.name != "staff")
+      .unwrap_or(measures[0].elements.len());
+    measures[0].elements.insert(first_control, tempo);
+    let mut score_definition = xml_element("scoreDef", &[], Vec::new());
+    score_definition
+      .elements
+      .push(Self::key_signature_element(composition.get_starting_key()));
+    score_definition
+      .elements
+      .extend(Self::meter_signature_element(composition.get_starting_time_signature()));
+    let mut changes: BTreeMap<usize, XmlElement> = BTreeMap::new();
+    let mut endings = Vec::new();
+    if let Some(primary) = writers.first() {
+      for (start, end) in &primary.repeats {
+        Self::set_attribute(&mut measures[measure_of(*start)], "left", "rptstart");
+        Self::set_attribute(&mut measures[last_measure_of(*end)], "right", "rptend");
+      }
+      for (start, end, numbers) in &primary.endings {
+        endings.push((measure_of(*start), last_measure_of(*end), numbers.clone()));
+      }
+      for (onset, change) in &primary.changes {
+        let idx = measure_of(*onset);
+        let definition = if idx == 0 {
+          &mut score_definition
+        } else {
+          changes
+            .entry(idx)
+            .or_insert_with(|| xml_element("scoreDef", &[], Vec::new()))
+        };
+        definition.elements.retain(|element| element.name != change.name);
+        definition.elements.push(change.deep_copy());
+      }
+    }
+    score_definition
+      .elements
+      .push(xml_element("staffGrp", &[], staff_group));
+
+    // Gather the measures into a section, wrapping the measures of every ending
+    let mut section = xml_element("section", &[], Vec::new());
+    let mut ending: Option<(usize, XmlElement)> = None;
+    for (idx, measure) in measures.into_iter().enumerate() {
+      if ending.is_none() {
+        ending = endings
+          .iter()
+          .find(|(start, ..)| *start == idx)
+          .map(|(_, end, numbers)| {
+            let number = numbers.split(", ").next().unwrap_or("1");
+            (
+              *end,
+              xml_element("ending", &[("n", number), ("label", numbers)], Vec::new()),
+            )
+          });
+      }
+      let container = match ending.as_mut() {
+        Some((_, ending)) => ending,
+        None => &mut section,
+      };
+      if let Some(change) = changes.remove(&idx) {
+        container.elements.push(change);
+      }
+      container.elements.push(measure);
+      if ending.as_ref().is_some_and(|(end, _)| *end <= idx) {
+        if let Some((_, ending)) = ending.take() {
+          section.elements.push(ending);
+        }
+      }
+    }
+    if let Some((_, ending)) = ending {
+      section.elements.push(ending);
+    }
+
+    let mei = xml_element(
+      "mei",
+      &[("xmlns", MEI_NAMESPACE), ("meiversion", MEI_VERSION)],
+      vec![
+        Self::head_element(composition),
+        xml_element(
+          "music",
+          &[],
+          vec![xml_element(
+            "body",
+            &[],
+            vec![xml_element(
+              "mdiv",
+              &[],
+              vec![xml_element("score", &[], vec![score_definition, section])],
+            )],
+          )],
+        ),
+      ],
+    );
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    write_xml(&mei, 0, &mut output);
+    output
+  }
+}
+
+impl Load for MeiConverter {
+  // MEI documents are transcoded into an equivalent MusicXML document which is
+  // then loaded by the MusicXML converter, so both formats share the same structure
+  fn load_bytes(data: &[u8]) -> Result<Composition, AmmError> {
+    MeiConverter::load_from_mei(data).map_err(AmmError::from)
+  }
+}
+
+impl Store for MeiConverter {
+  fn save_bytes(composition: &Composition) -> Result<Vec<u8>, AmmError> {
+    Ok(MeiConverter::save_to_mei(composition).into_bytes())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::storage::Storage;
+
+  const DOCUMENT: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<mei xmlns="http://www.music-encoding.org/ns/mei" meiversion="5.0">
+  <meiHead>
+    <fileDesc>
+      <titleStmt>
+        <title>Little Study</title>
+        <title type="subordinate">First Movement</title>
+        <respStmt><persName role="composer">A. Composer</persName></respStmt>
+      </titleStmt>
+      <pubStmt><availability><useRestrict>Public Domain</useRestrict></availability></pubStmt>
+    </fileDesc>
+  </meiHead>
+  <music><body><mdiv><score>
+    <scoreDef keysig="1s" meter.count="3" meter.unit="4" midi.bpm="96">
+      <staffGrp>
+        <staffDef n="1" lines="5" label="Flute" clef.shape="G" clef.line="2"/>
+        <staffGrp symbol="brace" label="Piano">
+          <staffDef n="2" lines="5" clef.shape="G" clef.line="2"/>
+          <staffDef n="3" lines="5" clef.shape="F" clef.line="4"/>
+        </staffGrp>
+      </staffGrp>
+    </scoreDef>
+    <section>
+      <measure n="1" left="rptstart">
+        <staff n="1"><layer n="1">
+          <beam><note xml:id="a" pname="g" oct="4" dur="8" artic="stacc"/><note pname="a" oct="4" dur="8"/></beam>
+          <tuplet num="3" numbase="2"><note pname="b" oct="4" dur="8"/><note pname="c" oct="5" dur="8" accid="s"/><note xml:id="b" pname="d" oct="5" dur="8"/></tuplet>
+          <rest dur="4"/>
+        </layer></staff>
+        <staff n="2"><layer n="1"><chord dur="2" dots="1"><note pname="g" oct="4"/><note pname="b" oct="4"/><note pname="d" oct="5"/></chord></layer></staff>
+        <staff n="3"><layer n="1"><mRest/></layer></staff>
+        <dynam staff="1" startid="#a">p</dynam>
+        <hairpin staff="1" form="cres" startid="#a" endid="#b"/>
+        <slur staff="1" startid="#a" endid="#b"/>
+        <pedal staff="3" tstamp="1" dir="down"/>
+      </measure>
+      <measure n="2" right="rptend">
+        <staff n="1"><layer n="1"><note pname="g" oct="4" dur="2" dots="1" fermata="above"/></layer></staff>
+        <staff n="2"><layer n="1"><note pname="d" oct="4" dur="2" dots="1"/></layer></staff>
+        <staff n="3"><layer n="1"><note pname="g" oct="2" dur="2" dots="1"/></layer></staff>
+      </measure>
+    </section>
+  </score></mdiv></body></music>
+</mei>"##;
+
+  fn count_notes(composition: &Composition) -> Vec<usize> {
+    composition
+      .iter()
+      .map(|part| {
+        part
+          .iter_timeslices()
+          .flat_map(|slice| slice.content.into_iter())
+          .filter(|content| !content.note.is_rest())
+          .count()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_load_mei() {
+    let composition = MeiConverter::load_bytes(DOCUMENT.as_bytes()).unwrap();
+    assert_eq!(composition.get_title(), "Little Study");
+    assert_eq!(composition.get_composers(), ["A. Composer"]);
+    assert_eq!(composition.get_copyright().as_deref(), Some("Public Domain"));
+    assert_eq!(
+      composition.get_metadata().get("movement_title").unwrap(),
+      "First Movement"
+    );
+    assert_eq!(composition.get_part_names(), ["Flute", "Piano"]);
+    assert_eq!(
+      *composition.get_starting_key(),
+      Key::from_fifths(1, Some(KeyMode::Major))
+    );
+    assert_eq!(
+      *composition.get_starting_time_signature(),
+      TimeSignature::new_explicit(3, 4)
+    );
+    assert_eq!(composition.get_tempo().beats_per_minute, 96);
+    assert_eq!(count_notes(&composition), [12, 10]);
+    assert!(MeiConverter::load_bytes(b"<mei><music/></mei>").is_err());
+    assert!(MeiConverter::load_bytes(b"<score-partwise/>").is_err());
+  }
+
+  #[test]
+  fn test_save_mei() {
+    let composition = MeiConverter::load_bytes(DOCUMENT.as_bytes()).unwrap();
+    let mei = MeiConverter::save_to_mei(&composition);
+    assert!(mei.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<mei xmlns="));
+    assert!(mei.contains("<title>Little Study</title>"));
+    assert!(mei.contains("<persName role=\"composer\">A. Composer</persName>"));
+    assert!(mei.contains("<keySig sig=\"1s\" mode=\"major\"/>"));
+    assert!(mei.contains("<meterSig count=\"3\" unit=\"4\"/>"));
+    assert!(mei.contains("<staffGrp symbol=\"brace\" bar.thru=\"true\">"));
+    assert!(mei.contains("<clef shape=\"F\" line=\"4\"/>"));
+    assert!(mei.contains("<tuplet num=\"3\" numbase=\"2\">"));
+    assert!(mei.contains("<beam>"));
+    assert!(mei.contains("artic=\"stacc\""));
+    assert!(mei.contains("accid=\"s\""));
+    assert!(mei.contains("<chord xml:id="));
+    assert!(mei.contains(">p</dynam>"));
+    assert!(mei.contains("<hairpin staff=\"1\" form=\"cres\""));
+    assert!(mei.contains("<slur staff=\"1\""));
+    assert!(mei.contains("left=\"rptstart\""));
+    assert!(mei.contains("right=\"rptend\""));
+    assert_eq!(mei.matches("<measure ").count(), 2);
+
+    let reloaded = MeiConverter::load_bytes(mei.as_bytes()).unwrap();
+    assert_eq!(reloaded.get_title(), "Little Study");
+    assert_eq!(reloaded.get_part_names(), ["Flute", "Piano"]);
+    assert_eq!(count_notes(&reloaded), count_notes(&composition));
+  }
+
+  #[test]
+  fn test_save_mei_example() {
+    let composition = Storage::MusicXML.load("examples/Hymn_to_Freedom.mxl").unwrap();
+    let mei = MeiConverter::save_to_mei(&composition);
+    assert_eq!(mei.matches("<staffDef ").count(), 2);
+    assert!(mei.contains("<layer n=\"2\">"));
+    let reloaded = MeiConverter::load_bytes(mei.as_bytes()).unwrap();
+    assert_eq!(reloaded.get_part_names(), composition.get_part_names());
+    assert_eq!(count_notes(&reloaded), count_notes(&composition));
+  }
+
+  #[test]
+  fn test_mei_unit_tuplets() {
+    let composition = Storage::MusicXML
+      .load("examples/Grande Valse Brillante2.musicxml")
+      .unwrap();
+    let mei = MeiConverter::save_to_mei(&composition);
+    let tuplets = mei.split("<tuplet num=\"").skip(1).map(|tuplet| {
+      let (num, rest) = tuplet.split_once('"').unwrap();
+      (num, rest.trim_start_matches(" numbase=\"").split('"').next().unwrap())
+    });
+    assert!(tuplets.into_iter().all(|(num, numbase)| num != numbase));
+    let reloaded = MeiConverter::load_bytes(mei.as_bytes()).unwrap();
+    assert_eq!(reloaded.get_part_names(), composition.get_part_names());
+
+    let unit = DOCUMENT.replace("<tuplet num=\"3\" numbase=\"2\">", "<tuplet num=\"3\" numbase=\"3\">");
+    assert!(unit.contains("numbase=\"3\""));
+    assert!(MeiConverter::load_bytes(unit.as_bytes()).is_ok());
+  }
+}
@@ -1,4 +1,4 @@
-use super::Load;
+use super::{AmmError, Load};
 use crate::context::{Key, KeyMode, Tempo, TimeSignature};
 use crate::modification::{Direction, DirectionType, NoteModificationType};
 use crate::note::{Duration, DurationType, Note};
@@ -6,7 +6,6 @@ use crate::structure::{PartContent, Staff, StaffContent};
 use crate::Composition;
 use alloc::{collections::VecDeque, string::String};
 use midly::{MetaMessage, Smf, Track};
-use std::fs;
 
 type TimeStamp = u32;
 
@@ -600,13 +599,8 @@ impl MidiConverter {
 }
 
 impl Load for MidiConverter {
-  fn load(path: &str) -> Result<Composition, String> {
-    let data = fs::read(path).map_err(|err| err.to_string())?;
-    MidiConverter::load_from_midi(data.as_slice())
-  }
-
-  fn load_data(data: Vec<u8>) -> Result<Composition, String> {
-    MidiConverter::load_from_midi(data.as_slice())
+  fn load_bytes(data: &[u8]) -> Result<Composition, AmmError> {
+    MidiConverter::load_from_midi(data).map_err(AmmError::from)
   }
 }
 
@@ -0,0 +1,2103 @@
+use super::musicxml::MusicXmlConverter;
+use super::util::{dynamic_text, gcd, measure_starts, ContextChange, Fraction, StaffContext, DYNAMICS};
+use super::xml::{add_notation, xml_element, xml_text_element, XmlElementExt};
+use super::{AmmError, Load, Store};
+use crate::context::{ClefType, Key, Tempo, TimeSignature, TimeSignatureType};
+use crate::modification::{
+  ChordModificationType, DirectionType, NoteModificationType, PhraseModificationType, SectionModificationType,
+};
+use crate::note::{Accidental, Duration, Note, Pitch, PitchName};
+use crate::structure::{
+  Chord, ChordContent, MultiVoice, MultiVoiceContent, PartContent, Phrase, PhraseContent, Section, SectionContent,
+  Staff, StaffContent,
+};
+use crate::Composition;
+use alloc::{
+  collections::{BTreeMap, BTreeSet},
+  string::{String, ToString},
+  vec::Vec,
+};
+use amm_internal::{JsonOptions, JsonSerializer, JsonValue};
+use musicxml::elements::ScorePartwise;
+use musicxml_internal::{ElementDeserializer, XmlElement};
+
+const VOICES_PER_STAFF: usize = 4;
+const MAX_WHOLE_DIVISIONS: u64 = 15_360;
+const MNX_VERSION: i64 = 1;
+const SHARPS: [char; 7] = ['F', 'C', 'G', 'D', 'A', 'E', 'B'];
+const DURATION_BASES: [(&str, u64, u64); 17] = [
+  ("duplexMaxima", 16, 1),
+  ("maxima", 8, 1),
+  ("longa", 4, 1),
+  ("breve", 2, 1),
+  ("whole", 1, 1),
+  ("half", 1, 2),
+  ("quarter", 1, 4),
+  ("eighth", 1, 8),
+  ("16th", 1, 16),
+  ("32nd", 1, 32),
+  ("64th", 1, 64),
+  ("128th", 1, 128),
+  ("256th", 1, 256),
+  ("512th", 1, 512),
+  ("1024th", 1, 1024),
+  ("2048th", 1, 2048),
+  ("4096th", 1, 4096),
+];
+const MARKINGS: [(&str, &str); 10] = [
+  ("accent", "accent"),
+  ("breath", "breath-mark"),
+  ("softAccent", "soft-accent"),
+  ("spiccato", "spiccato"),
+  ("staccatissimo", "staccatissimo"),
+  ("staccato", "staccato"),
+  ("stress", "stress"),
+  ("strongAccent", "strong-accent"),
+  ("tenuto", "tenuto"),
+  ("unstress", "unstress"),
+];
+
+enum MnxItem {
+  Element(XmlElement),
+  Timed {
+    element: XmlElement,
+    written: Fraction,
+    actual: Fraction,
+  },
+  Backup(Fraction),
+}
+
+/// Part, measure, and item index of a transcoded element.
+type MnxLocation = (usize, usize, usize);
+
+struct MnxMeasure {
+  length: Fraction,
+  fifths: i32,
+  changes: Vec<XmlElement>,
+  tempos: Vec<(Fraction, XmlElement)>,
+  forward_repeat: bool,
+  backward_repeat: Option<Option<i64>>,
+  ending_start: Option<String>,
+  ending_stop: Option<(String, bool)>,
+  bar_style: Option<&'static str>,
+}
+
+struct MnxPart {
+  name: String,
+  staves: usize,
+  clefs: Vec<XmlElement>,
+  voices: BTreeMap<(usize, String), usize>,
+  alterations: BTreeMap<(usize, char, i64), i64>,
+  measures: Vec<Vec<MnxItem>>,
+}
+
+struct MnxSequence {
+  part: usize,
+  measure: usize,
+  staff: usize,
+  voice: usize,
+  onset: Fraction,
+  ratio: Fraction,
+  grace: Option<bool>,
+  tuplets: usize,
+}
+
+struct MnxReader {
+  parts: Vec<MnxPart>,
+  measures: Vec<MnxMeasure>,
+  key: XmlElement,
+  time: XmlElement,
+  notes: BTreeMap<String, MnxLocation>,
+  events: BTreeMap<String, MnxLocation>,
+  ties: Vec<(MnxLocation, String)>,
+  tie_targets: BTreeSet<String>,
+  slurs: Vec<(MnxLocation, String)>,
+  positions: BTreeMap<(usize, usize, usize), Vec<(Fraction, usize)>>,
+  insertions: BTreeMap<MnxLocation, Vec<XmlElement>>,
+}
+
+impl MnxReader {
+  fn parse(text: &str) -> Result<Self, String> {
+    let document = JsonValue::parse(text)?;
+    let version = document
+      .get("mnx")
+      .and_then(|mnx| mnx.get("version"))
+      .and_then(JsonValue::as_i64)
+      .ok_or("Missing MNX version information")?;
+    if version > MNX_VERSION {
+      return Err(format!("Unsupported MNX version {version}"));
+    }
+    let globals = document
+      .get("global")
+      .and_then(|global| global.get("measures"))
+      .and_then(JsonValue::as_array)
+      .filter(|measures| !measures.is_empty())
+      .ok_or("No global measures found in the MNX document")?;
+    let parts = document
+      .get("parts")
+      .and_then(JsonValue::as_array)
+      .filter(|parts| !parts.is_empty())
+      .ok_or("No parts found in the MNX document")?;
+    let mut reader = Self {
+      parts: Vec::new(),
+      measures: Vec::new(),
+      key: MnxConverter::key_element(0),
+      time: xml_element("time", &[], vec![xml_element("senza-misura", &[], Vec::new())]),
+      notes: BTreeMap::new(),
+      events: BTreeMap::new(),
+      ties: Vec::new(),
+      tie_targets: BTreeSet::new(),
+      slurs: Vec::new(),
+      positions: BTreeMap::new(),
+      insertions: BTreeMap::new(),
+    };
+    reader.read_global_measures(globals)?;
+    for (idx, part) in parts.iter().enumerate() {
+      let staves = part
+        .get("staves")
+        .and_then(JsonValue::as_i64)
+        .and_then(|staves| usize::try_from(staves).ok())
+        .filter(|staves| (1..=VOICES_PER_STAFF * 4).contains(staves))
+        .unwrap_or(1);
+      reader.parts.push(MnxPart {
+        name: part
+          .get("name")
+          .and_then(JsonValue::as_str)
+          .map_or_else(|| format!("Part {}", idx + 1), String::from),
+        staves,
+        clefs: (0..staves).map(|_| MnxConverter::clef_element("G", 2, 0)).collect(),
+        voices: BTreeMap::new(),
+        alterations: BTreeMap::new(),
+        measures: Vec::new(),
+      });
+    }
+    for measure_idx in 0..reader.measures.len() {
+      for (part_idx, part) in parts.iter().enumerate() {
+        let measure = part
+          .get("measures")
+          .and_then(JsonValue::as_array)
+          .and_then(|measures| measures.get(measure_idx));
+        reader
+          .read_measure(part_idx, measure_idx, measure)
+          .map_err(|err| format!("Measure {}: {err}", measure_idx + 1))?;
+      }
+    }
+    reader.resolve_spanners();
+    Ok(reader)
+  }
+
+  fn read_global_measures(&mut self, globals: &[JsonValue]) -> Result<(), String> {
+    let (mut length, mut fifths) = (Fraction::ZERO, 0);
+    for (idx, global) in globals.iter().enumerate() {
+      let mut changes = Vec::new();
+      if let Some(key) = global.get("key") {
+        fifths = key
+          .get("fifths")
+          .and_then(JsonValue::as_i64)
+          .filter(|fifths| (-7..=7).contains(fifths))
+          .ok_or("Invalid MNX key signature")? as i32;
+        changes.push(MnxConverter::key_element(fifths));
+      }
+      if let Some(time) = global.get("time") {
+        let (element, measure_length) = MnxConverter::time(time).ok_or("Invalid MNX time signature")?;
+        length = measure_length;
+        changes.push(element);
+      }
+      if idx == 0 {
+        for change in changes.drain(..) {
+          match change.name.as_str() {
+            "key" => self.key = change,
+            _ => self.time = change,
+          }
+        }
+      }
+      let tempos = global
+        .get("tempos")
+        .and_then(JsonValue::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|tempo| Some((MnxConverter::position(tempo), MnxConverter::tempo_direction(tempo)?)))
+        .collect();
+      self.measures.push(MnxMeasure {
+        length,
+        fifths,
+        changes,
+        tempos,
+        forward_repeat: global.get("repeatStart").is_some(),
+        backward_repeat: global
+          .get("repeatEnd")
+          .map(|repeat| repeat.get("times").and_then(JsonValue::as_i64)),
+        ending_start: None,
+        ending_stop: None,
+        bar_style: global
+          .get("barline")
+          .and_then(|barline| barline.get("type"))
+          .and_then(JsonValue::as_str)
+          .and_then(MnxConverter::bar_style),
+      });
+    }
+
+    // Endings span a number of measures starting with the one they are declared in
+    for (idx, global) in globals.iter().enumerate() {
+      let Some(ending) = global.get("ending") else {
+        continue;
+      };
+      let numbers = ending
+        .get("numbers")
+        .and_then(JsonValue::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(JsonValue::as_i64)
+        .map(|number| number.to_string())
+        .collect::<Vec<_>>();
+      let numbers = if numbers.is_empty() {
+        String::from("1")
+      } else {
+        numbers.join(", ")
+      };
+      let duration = ending
+        .get("duration")
+        .and_then(JsonValue::as_i64)
+        .and_then(|duration| usize::try_from(duration).ok())
+        .unwrap_or(1)
+        .max(1);
+      let end = (idx + duration - 1).min(self.measures.len() - 1);
+      let open = ending.get("open").and_then(JsonValue::as_bool).unwrap_or(false);
+      self.measures[idx].ending_start = Some(numbers.clone());
+      self.measures[end].ending_stop = Some((numbers, open));
+    }
+    Ok(())
+  }
+
+  fn read_measure(&mut self, part: usize, measure_idx: usize, measure: Option<&JsonValue>) -> Result<(), String> {
+    let mut items = Vec::new();
+    let changes = &self.measures[measure_idx].changes;
+    if !changes.is_empty() {
+      let changes = changes.iter().map(XmlElementExt::deep_copy).collect();
+      items.push(MnxItem::Element(xml_element("attributes", &[], changes)));
+    }
+    self.parts[part].alterations.clear();
+
+    // Transcode every sequence of every staff, backing up to the start of the measure between them
+    let (staves, length) = (self.parts[part].staves, self.measures[measure_idx].length);
+    let sequences = measure
+      .and_then(|measure| measure.get("sequences"))
+      .and_then(JsonValue::as_array)
+      .unwrap_or_default();
+    let mut cursor = Fraction::ZERO;
+    for staff in 1..=staves {
+      let staff_sequences = sequences
+        .iter()
+        .filter(|sequence| MnxConverter::staff(sequence, staves) == staff)
+        .collect::<Vec<_>>();
+      if staff_sequences.is_empty() && length > Fraction::ZERO {
+        if cursor > Fraction::ZERO {
+          items.push(MnxItem::Backup(cursor));
+        }
+        items.push(MnxItem::Timed {
+          element: MnxConverter::forward((staff - 1) * VOICES_PER_STAFF + 1, staff),
+          written: length,
+          actual: length,
+        });
+        cursor = length;
+      }
+      for (idx, sequence) in staff_sequences.into_iter().enumerate() {
+        if cursor > Fraction::ZERO {
+          items.push(MnxItem::Backup(cursor));
+        }
+        let name = sequence
+          .get("voice")
+          .and_then(JsonValue::as_str)
+          .map_or_else(|| format!("#{idx}"), String::from);
+        let voices = &mut self.parts[part].voices;
+        let count = voices.keys().filter(|(voice_staff, _)| *voice_staff == staff).count();
+        let voice = *voices.entry((staff, name)).or_insert(count);
+        let mut state = MnxSequence {
+          part,
+          measure: measure_idx,
+          staff,
+          voice: (staff - 1) * VOICES_PER_STAFF + voice.min(VOICES_PER_STAFF - 1) + 1,
+          onset: Fraction::ZERO,
+          ratio: Fraction::ONE,
+          grace: None,
+          tuplets: 0,
+        };
+        let content = sequence
+          .get("content")
+          .and_then(JsonValue::as_array)
+          .unwrap_or_default();
+        self.read_content(content, &mut state, &mut items)?;
+        cursor = state.onset;
+      }
+    }
+
+    // Clefs, dynamics, and tempos are placed before the first event at or after their position
+    let end = items.len();
+    if let Some(measure) = measure {
+      for clef in measure.get("clefs").and_then(JsonValue::as_array).unwrap_or_default() {
+        let staff = MnxConverter::staff(clef, staves);
+        let Some(mut element) = clef.get("clef").and_then(MnxConverter::clef) else {
+          continue;
+        };
+        let position = MnxConverter::position(clef);
+        if measure_idx == 0 && position == Fraction::ZERO {
+          self.parts[part].clefs[staff - 1] = element;
+        } else {
+          element.attributes.push((String::from("number"), staff.to_string()));
+          let attributes = xml_element("attributes", &[], vec![element]);
+          self.place((part, measure_idx, staff), position, end, attributes);
+        }
+      }
+      for dynamic in measure
+        .get("dynamics")
+        .and_then(JsonValue::as_array)
+        .unwrap_or_default()
+      {
+        let Some(text) = dynamic.get("value").and_then(JsonValue::as_str) else {
+          continue;
+        };
+        let direction_type = if DYNAMICS.contains(&text) {
+          xml_element("dynamics", &[], vec![xml_element(text, &[], Vec::new())])
+        } else {
+          xml_text_element("words", text)
+        };
+        let staff = MnxConverter::staff(dynamic, staves);
+        let direction = MnxConverter::direction(vec![direction_type], None, Some("below"), staff);
+        self.place(
+          (part, measure_idx, staff),
+          MnxConverter::position(dynamic),
+          end,
+          direction,
+        );
+      }
+    }
+    if part == 0 {
+      let tempos = self.measures[measure_idx]
+        .tempos
+        .iter()
+        .map(|(position, tempo)| (*position, tempo.deep_copy()))
+        .collect::<Vec<_>>();
+      for (position, tempo) in tempos {
+        self.place((part, measure_idx, 1), position, end, tempo);
+      }
+    }
+    self.parts[part].measures.push(items);
+    Ok(())
+  }
+
+  fn place(&mut self, (part, measure, staff): MnxLocation, position: Fraction, end: usize, element: XmlElement) {
+    let idx = self
+      .positions
+      .get(&(part, measure, staff))
+      .and_then(|starts| {
+        starts
+          .iter()
+          .filter(|(onset, _)| *onset >= position)
+          .min()
+          .map(|(_, idx)| *idx)
+      })
+      .unwrap_or(end);
+    self.insertions.entry((part, measure, idx)).or_default().push(element);
+  }
+
+  fn read_content(
+    &mut self,
+    content: &[JsonValue],
+    state: &mut MnxSequence,
+    items: &mut Vec<MnxItem>,
+  ) -> Result<(), String> {
+    for item in content {
+      match item.get("type").and_then(JsonValue::as_str).unwrap_or("event") {
+        "event" => self.push_event(item, state, items)?,
+        "space" => {
+          let duration = item
+            .get("duration")
+            .and_then(MnxConverter::duration)
+            .ok_or("Invalid MNX space duration")?
+            * state.ratio;
+          items.push(MnxItem::Timed {
+            element: MnxConverter::forward(state.voice, state.staff),
+            written: duration,
+            actual: duration,
+          });
+          state.onset = state.onset + duration;
+        }
+        "grace" => {
+          let slash = item.get("slash").and_then(JsonValue::as_bool).unwrap_or(false);
+          let grace = state.grace.replace(slash);
+          let content = item.get("content").and_then(JsonValue::as_array).unwrap_or_default();
+          self.read_content(content, state, items)?;
+          state.grace = grace;
+        }
+        "tuplet" => {
+          let total = |side: &str| {
+            let side = item.get(side)?;
+            let multiple = side
+              .get("multiple")
+              .and_then(JsonValue::as_i64)
+              .and_then(|multiple| u64::try_from(multiple).ok())
+              .filter(|multiple| *multiple > 0)?;
+            Some(MnxConverter::duration(side.get("duration")?)? * Fraction::new(multiple, 1))
+          };
+          let (Some(inner), Some(outer)) = (total("inner"), total("outer")) else {
+            return Err(String::from("Invalid MNX tuplet ratio"));
+          };
+          let (start, ratio) = (items.len(), state.ratio);
+          state.ratio = state.ratio * (outer / inner);
+          state.tuplets += 1;
+          let content = item.get("content").and_then(JsonValue::as_array).unwrap_or_default();
+          self.read_content(content, state, items)?;
+          let notes = (start..items.len())
+            .filter(|idx| {
+              matches!(&items[*idx], MnxItem::Timed { element, .. } if element.name == "note" && !element.has_child("chord"))
+            })
+            .collect::<Vec<_>>();
+          // Tuplets that leave the duration of their notes unchanged carry no time modification to attach to
+          if let (Some(first), Some(last), true) = (notes.first(), notes.last(), state.ratio != Fraction::ONE) {
+            for (idx, tuplet_type) in [(*first, "start"), (*last, "stop")] {
+              if let MnxItem::Timed { element, .. } = &mut items[idx] {
+                add_notation(
+                  element,
+                  None,
+                  xml_element(
+                    "tuplet",
+                    &[("type", tuplet_type), ("number", &state.tuplets.to_string())],
+                    Vec::new(),
+                  ),
+                );
+              }
+            }
+          }
+          state.tuplets -= 1;
+          state.ratio = ratio;
+        }
+        _ => (),
+      }
+    }
+    Ok(())
+  }
+
+  fn note_element(&mut self, note: &JsonValue, state: &MnxSequence, in_chord: bool) -> Result<XmlElement, String> {
+    let pitch = note.get("pitch").ok_or("Missing pitch of MNX note")?;
+    let step = pitch
+      .get("step")
+      .and_then(JsonValue::as_str)
+      .and_then(|step| step.chars().next())
+      .map(|step| step.to_ascii_uppercase())
+      .filter(|step| ('A'..='G').contains(step))
+      .ok_or("Invalid MNX pitch step")?;
+    let octave = pitch
+      .get("octave")
+      .and_then(JsonValue::as_i64)
+      .ok_or("Invalid MNX pitch octave")?
+      .clamp(0, 9);
+    let alter = pitch.get("alter").and_then(JsonValue::as_i64).unwrap_or(0).clamp(-2, 2);
+
+    // Written accidentals are only shown where the key or an earlier note of the measure implies another alteration
+    let tie_stop = note
+      .get("id")
+      .and_then(JsonValue::as_str)
+      .is_some_and(|id| self.tie_targets.contains(id));
+    let fifths = self.measures[state.measure].fifths;
+    let alterations = &mut self.parts[state.part].alterations;
+    let implied = alterations
+      .get(&(state.staff, step, octave))
+      .copied()
+      .unwrap_or_else(|| MnxConverter::key_alteration(fifths, step));
+    alterations.insert((state.staff, step, octave), alter);
+    let show = note
+      .get("accidentalDisplay")
+      .and_then(|display| display.get("show"))
+      .and_then(JsonValue::as_bool)
+      .unwrap_or(alter != implied && !tie_stop);
+    let mut elements = Vec::new();
+    if in_chord {
+      elements.push(xml_element("chord", &[], Vec::new()));
+    }
+    let mut pitch = vec![xml_text_element("step", step)];
+    if alter != 0 {
+      pitch.push(xml_text_element("alter", alter));
+    }
+    pitch.push(xml_text_element("octave", octave));
+    elements.push(xml_element("pitch", &[], pitch));
+    if show {
+      let accidental = match alter {
+        -2 => "flat-flat",
+        -1 => "flat",
+        1 => "sharp",
+        2 => "double-sharp",
+        _ => "natural",
+      };
+      elements.push(xml_text_element("accidental", accidental));
+    }
+    Ok(xml_element("note", &[], elements))
+  }
+
+  fn push_event(&mut self, event: &JsonValue, state: &mut MnxSequence, items: &mut Vec<MnxItem>) -> Result<(), String> {
+    let full_measure = event.get("measure").and_then(JsonValue::as_bool) == Some(true);
+    let (written, actual) = if full_measure {
+      let length = self.measures[state.measure].length;
+      (length, length)
+    } else {
+      let written = event
+        .get("duration")
+        .and_then(MnxConverter::duration)
+        .ok_or("Missing duration of MNX event")?;
+      (written, written * state.ratio)
+    };
+    let notes = event
+      .get("notes")
+      .and_then(JsonValue::as_array)
+      .filter(|notes| !notes.is_empty() && event.get("rest").is_none());
+    let first = items.len();
+    let mut elements = Vec::new();
+    match notes {
+      Some(notes) => {
+        for (idx, note) in notes.iter().enumerate() {
+          let element = self.note_element(note, state, idx > 0)?;
+          let location = (state.part, state.measure, first + idx);
+          if let Some(id) = note.get("id").and_then(JsonValue::as_str) {
+            self.notes.insert(String::from(id), location);
+          }
+          for tie in note.get("ties").and_then(JsonValue::as_array).unwrap_or_default() {
+            if let Some(target) = tie.get("target").and_then(JsonValue::as_str) {
+              self.ties.push((location, String::from(target)));
+              self.tie_targets.insert(String::from(target));
+            }
+          }
+          elements.push(element);
+        }
+      }
+      None if state.grace.is_some() || actual == Fraction::ZERO => return Ok(()),
+      None => elements.push(xml_element(
+        "note",
+        &[],
+        vec![xml_element(
+          "rest",
+          if full_measure { &[("measure", "yes")] } else { &[] },
+          Vec::new(),
+        )],
+      )),
+    }
+    for (idx, mut element) in elements.into_iter().enumerate() {
+      if let Some(slash) = state.grace {
+        element.elements.insert(
+          0,
+          xml_element("grace", if slash { &[("slash", "yes")] } else { &[] }, Vec::new()),
+        );
+      }
+      element.elements.push(xml_text_element("voice", state.voice));
+      element.elements.push(xml_text_element("staff", state.staff));
+      if state.ratio != Fraction::ONE && !full_measure {
+        element.elements.push(xml_element(
+          "time-modification",
+          &[],
+          vec![
+            xml_text_element("actual-notes", state.ratio.denominator),
+            xml_text_element("normal-notes", state.ratio.numerator),
+          ],
+        ));
+      }
+      if idx == 0 {
+        MnxConverter::add_markings(&mut element, event);
+      }
+      if state.grace.is_some() {
+        if let Some((note_type, dots)) = written.note_type() {
+          element.elements.push(xml_text_element("type", note_type));
+          element
+            .elements
+            .extend((0..dots).map(|_| xml_element("dot", &[], Vec::new())));
+        }
+        items.push(MnxItem::Element(element));
+      } else {
+        items.push(MnxItem::Timed {
+          element,
+          written,
+          actual,
+        });
+      }
+    }
+
+    // Register the event so that slurs and directions can refer to it
+    let location = (state.part, state.measure, first);
+    if let Some(id) = event.get("id").and_then(JsonValue::as_str) {
+      self.events.insert(String::from(id), location);
+    }
+    for slur in event.get("slurs").and_then(JsonValue::as_array).unwrap_or_default() {
+      if let Some(target) = slur.get("target").and_then(JsonValue::as_str) {
+        self.slurs.push((location, String::from(target)));
+      }
+    }
+    self
+      .positions
+      .entry((state.part, state.measure, state.staff))
+      .or_default()
+      .push((state.onset, first));
+    if state.grace.is_none() {
+      state.onset = state.onset + actual;
+    }
+    Ok(())
+  }
+
+  fn notate(&mut self, (part, measure, idx): MnxLocation, notation: XmlElement, tie: Option<&str>) {
+    if let Some(MnxItem::Timed { element, .. } | MnxItem::Element(element)) = self.parts[part]
+      .measures
+      .get_mut(measure)
+      .and_then(|items| items.get_mut(idx))
+    {
+      if let Some(tie_type) = tie {
+        element
+          .elements
+          .push(xml_element("tie", &[("type", tie_type)], Vec::new()));
+      }
+      add_notation(element, None, notation);
+    }
+  }
+
+  fn resolve_spanners(&mut self) {
+    for (source, target) in core::mem::take(&mut self.ties) {
+      if let Some(target) = self.notes.get(&target).copied() {
+        for (location, tie_type) in [(source, "start"), (target, "stop")] {
+          let tied = xml_element("tied", &[("type", tie_type)], Vec::new());
+          self.notate(location, tied, Some(tie_type));
+        }
+      }
+    }
+    let mut slurs = 0;
+    for (source, target) in core::mem::take(&mut self.slurs) {
+      let Some(target) = self.events.get(&target).copied() else {
+        continue;
+      };
+      slurs = slurs % 6 + 1;
+      let number = slurs.to_string();
+      for (location, slur_type) in [(source, "start"), (target, "stop")] {
+        let slur = xml_element("slur", &[("type", slur_type), ("number", &number)], Vec::new());
+        self.notate(location, slur, None);
+      }
+    }
+  }
+
+  fn transcode_item(item: &MnxItem, whole_divisions: u64) -> XmlElement {
+    match item {
+      MnxItem::Element(element) => element.deep_copy(),
+      MnxItem::Backup(duration) => xml_element(
+        "backup",
+        &[],
+        vec![xml_text_element("duration", duration.to_divisions(whole_divisions))],
+      ),
+      MnxItem::Timed {
+        element,
+        written,
+        actual,
+      } => {
+        let mut element = element.deep_copy();
+        element
+          .elements
+          .push(xml_text_element("duration", actual.to_divisions(whole_divisions)));
+        let is_measure_rest = element
+          .child("rest")
+          .is_some_and(|rest| rest.attribute("measure") == Some("yes"));
+        if element.name == "note" && !is_measure_rest {
+          if let Some((note_type, dots)) = written.note_type() {
+            element.elements.push(xml_text_element("type", note_type));
+            element
+              .elements
+              .extend((0..dots).map(|_| xml_element("dot", &[], Vec::new())));
+          }
+        }
+        element
+      }
+    }
+  }
+
+  fn transcode_part(&self, part_idx: usize, whole_divisions: u64) -> Vec<XmlElement> {
+    let part = &self.parts[part_idx];
+    part
+      .measures
+      .iter()
+      .enumerate()
+      .map(|(measure_idx, items)| {
+        let mut elements = Vec::new();
+        if measure_idx == 0 {
+          let mut attributes = vec![
+            xml_text_element("divisions", whole_divisions / 4),
+            self.key.deep_copy(),
+            self.time.deep_copy(),
+          ];
+          if part.staves > 1 {
+            attributes.push(xml_text_element("staves", part.staves));
+          }
+          for (idx, clef) in part.clefs.iter().enumerate() {
+            let mut clef = clef.deep_copy();
+            clef.attributes.push((String::from("number"), (idx + 1).to_string()));
+            attributes.push(clef);
+          }
+          elements.push(xml_element("attributes", &[], attributes));
+        }
+        let barlines = &self.measures[measure_idx];
+        if barlines.forward_repeat || barlines.ending_start.is_some() {
+          let mut barline = Vec::new();
+          if barlines.forward_repeat {
+            barline.push(xml_text_element("bar-style", "heavy-light"));
+          }
+          if let Some(numbers) = &barlines.ending_start {
+            barline.push(xml_element(
+              "ending",
+              &[("number", numbers), ("type", "start")],
+              Vec::new(),
+            ));
+          }
+          if barlines.forward_repeat {
+            barline.push(xml_element("repeat", &[("direction", "forward")], Vec::new()));
+          }
+          elements.push(xml_element("barline", &[("location", "left")], barline));
+        }
+        elements.extend(items.iter().map(|item| Self::transcode_item(item, whole_divisions)));
+        if barlines.backward_repeat.is_some() || barlines.ending_stop.is_some() || barlines.bar_style.is_some() {
+          let mut barline = Vec::new();
+          if let Some(style) = barlines.bar_style {
+            barline.push(xml_text_element("bar-style", style));
+          } else if barlines.backward_repeat.is_some() {
+            barline.push(xml_text_element("bar-style", "light-heavy"));
+          }
+          if let Some((numbers, discontinue)) = &barlines.ending_stop {
+            barline.push(xml_element(
+              "ending",
+              &[
+                ("number", numbers),
+                ("type", if *discontinue { "discontinue" } else { "stop" }),
+              ],
+              Vec::new(),
+            ));
+          }
+          match barlines.backward_repeat {
+            Some(Some(times)) => barline.push(xml_element(
+              "repeat",
+              &[("direction", "backward"), ("times", &times.clamp(1, 255).to_string())],
+              Vec::new(),
+            )),
+            Some(None) => barline.push(xml_element("repeat", &[("direction", "backward")], Vec::new())),
+            None => (),
+          }
+          elements.push(xml_element("barline", &[("location", "right")], barline));
+        }
+        xml_element("measure", &[("number", &(measure_idx + 1).to_string())], elements)
+      })
+      .collect()
+  }
+
+  fn transcode_score(mut self) -> Result<XmlElement, String> {
+    // Place every clef, dynamic, and tempo just before the event it belongs to
+    for ((part, measure, idx), directions) in core::mem::take(&mut self.insertions).into_iter().rev() {
+      let items = &mut self.parts[part].measures[measure];
+      let idx = idx.min(items.len());
+      items.splice(idx..idx, directions.into_iter().map(MnxItem::Element));
+    }
+
+    // Choose a number of divisions able to represent every duration exactly
+    let whole_divisions = self
+      .parts
+      .iter()
+      .flat_map(|part| part.measures.iter())
+      .flat_map(|items| items.iter())
+      .try_fold(4, |divisions: u64, item| match item {
+        MnxItem::Timed { actual: duration, .. } | MnxItem::Backup(duration) => {
+          let divisions = divisions / gcd(divisions, duration.denominator);
+          divisions
+            .checked_mul(duration.denominator)
+            .filter(|divisions| *divisions <= MAX_WHOLE_DIVISIONS)
+        }
+        MnxItem::Element(_) => Some(divisions),
+      })
+      .ok_or("Note durations in the MNX document are too fine to be represented")?;
+
+    // Transcode the score into an equivalent partwise MusicXML document
+    let mut contents = vec![xml_element(
+      "part-list",
+      &[],
+      self
+        .parts
+        .iter()
+        .enumerate()
+        .map(|(idx, part)| {
+          xml_element(
+            "score-part",
+            &[("id", &format!("P{}", idx + 1))],
+            vec![xml_text_element("part-name", &part.name)],
+          )
+        })
+        .collect(),
+    )];
+    for idx in 0..self.parts.len() {
+      contents.push(xml_element(
+        "part",
+        &[("id", &format!("P{}", idx + 1))],
+        self.transcode_part(idx, whole_divisions),
+      ));
+    }
+    Ok(xml_element("score-partwise", &[("version", "4.0")], contents))
+  }
+}
+
+/// Tuplet group number, number of beats, number of beats it fits into, and written beat duration.
+type MnxTuplet = (usize, u8, u8, Fraction);
+
+struct MnxEvent {
+  voice: usize,
+  onset: Fraction,
+  duration: Fraction,
+  grace: Option<bool>,
+  tuplets: Vec<MnxTuplet>,
+  value: JsonValue,
+}
+
+struct MnxStaffWriter {
+  primary: bool,
+  context: StaffContext,
+  tempo: Tempo,
+  onset: Fraction,
+  position: Fraction,
+  alterations: BTreeMap<(u8, usize), i64>,
+  voice: usize,
+  max_voice: usize,
+  tuplets: Vec<(usize, u8, u8, Option<Fraction>)>,
+  num_tuplets: usize,
+  tied: BTreeMap<usize, Vec<(Pitch, usize, usize)>>,
+  events: Vec<MnxEvent>,
+  dynamics: Vec<(Fraction, String)>,
+  clefs: Vec<(Fraction, ClefType)>,
+  meters: Vec<(Fraction, Option<Fraction>)>,
+  keys: Vec<(Fraction, Key)>,
+  times: Vec<(Fraction, TimeSignature)>,
+  tempos: Vec<(Fraction, Tempo)>,
+  repeats: Vec<(Fraction, Fraction)>,
+  endings: Vec<(Fraction, Fraction, Vec<u16>)>,
+}
+
+impl MnxStaffWriter {
+  fn new(composition: &Composition, primary: bool) -> Self {
+    let context = StaffContext::new(composition);
+    Self {
+      primary,
+      context,
+      tempo: *composition.get_tempo(),
+      onset: Fraction::ZERO,
+      position: Fraction::ZERO,
+      alterations: BTreeMap::new(),
+      voice: 1,
+      max_voice: 1,
+      tuplets: Vec::new(),
+      num_tuplets: 0,
+      tied: BTreeMap::new(),
+      events: Vec::new(),
+      dynamics: Vec::new(),
+      clefs: vec![(Fraction::ZERO, ClefType::Treble)],
+      meters: vec![(Fraction::ZERO, context.measure_length)],
+      keys: Vec::new(),
+      times: Vec::new(),
+      tempos: Vec::new(),
+      repeats: Vec::new(),
+      endings: Vec::new(),
+    }
+  }
+
+  fn advance(&mut self, duration: Fraction) {
+    self.onset = self.onset + duration;
+    self.position = self.position + duration;
+    if let Some(length) = self.context.measure_length.filter(|length| length.numerator > 0) {
+      while self.position >= length {
+        self.position = self.position - length;
+        self.alterations.clear();
+      }
+    }
+  }
+
+  fn push_event(&mut self, value: JsonValue, written: Fraction, ratio: Fraction, grace: Option<bool>) {
+    let duration = if grace.is_some() {
+      Fraction::ZERO
+    } else {
+      written * ratio
+    };
+    for (.., unit) in &mut self.tuplets {
+      unit.get_or_insert(written);
+    }
+    self.events.push(MnxEvent {
+      voice: self.voice,
+      onset: self.onset,
+      duration,
+      grace,
+      tuplets: self
+        .tuplets
+        .iter()
+        .map(|(group, num, into, unit)| (*group, *num, *into, unit.unwrap_or(written)))
+        .collect(),
+      value,
+    });
+    self.advance(duration);
+  }
+
+  fn note_value(&mut self, note: &Note) -> JsonValue {
+    // MNX spells out the sounding alteration of every note
+    let key = (note.pitch.octave, note.pitch.name.index());
+    let alter = match note.accidental {
+      Accidental::None => {
+        let key_accidental = self.context.key.accidentals()[note.pitch.name.index()];
+        self
+          .alterations
+          .get(&key)
+          .copied()
+          .unwrap_or_else(|| MnxConverter::alteration(key_accidental))
+      }
+      accidental => {
+        let alter = MnxConverter::alteration(accidental);
+        self.alterations.insert(key, alter);
+        alter
+      }
+    };
+    let mut pitch = vec![
+      (
+        "step",
+        JsonValue::String(String::from(MnxConverter::step(note.pitch.name))),
+      ),
+      ("octave", JsonValue::Number(f64::from(note.pitch.octave))),
+    ];
+    if alter != 0 {
+      pitch.push(("alter", JsonValue::Number(alter as f64)));
+    }
+    let mut members = vec![
+      ("id", JsonValue::String(format!("n{}", note.get_id()))),
+      ("pitch", MnxConverter::object(pitch)),
+    ];
+    if note.accidental != Accidental::None {
+      members.push((
+        "accidentalDisplay",
+        MnxConverter::object(vec![("show", JsonValue::Bool(true))]),
+      ));
+    }
+    MnxConverter::object(members)
+  }
+
+  fn link_tie(&mut self, tied: &[(Pitch, usize, usize)], pitch: Pitch, target: &str) {
+    let Some((_, event, note)) = tied.iter().find(|(tied_pitch, ..)| *tied_pitch == pitch) else {
+      return;
+    };
+    if let Some(JsonValue::Array(notes)) = MnxConverter::member_mut(&mut self.events[*event].value, "notes") {
+      if let Some(note) = notes.get_mut(*note) {
+        let tie = MnxConverter::object(vec![("target", JsonValue::String(String::from(target)))]);
+        MnxConverter::push_member(note, "ties", tie);
+      }
+    }
+  }
+
+  fn write_note(&mut self, note: &Note, ratio: Fraction) {
+    let grace = note
+      .iter_modifications()
+      .find_map(|modification| match modification.r#type {
+        NoteModificationType::Grace { acciaccatura } => Some(acciaccatura),
+        _ => None,
+      });
+    let mut event = MnxConverter::object(vec![
+      ("id", JsonValue::String(format!("e{}", note.get_id()))),
+      ("duration", MnxConverter::duration_value(&note.duration)),
+    ]);
+    if note.is_rest() {
+      MnxConverter::set_member(&mut event, "rest", MnxConverter::object(Vec::new()));
+    } else {
+      let tied = if grace.is_some() {
+        Vec::new()
+      } else {
+        self.tied.remove(&self.voice).unwrap_or_default()
+      };
+      let value = self.note_value(note);
+      self.link_tie(&tied, note.pitch, &format!("n{}", note.get_id()));
+      MnxConverter::set_member(&mut event, "notes", JsonValue::Array(vec![value]));
+      if note
+        .iter_modifications()
+        .any(|modification| modification.r#type == NoteModificationType::Tie)
+      {
+        self.tied.insert(self.voice, vec![(note.pitch, self.events.len(), 0)]);
+      }
+    }
+    let mut markings = Vec::new();
+    for modification in note.iter_modifications() {
+      match modification.r#type {
+        NoteModificationType::Dynamic { dynamic } => {
+          self.dynamics.extend(
+            dynamic_text(&dynamic)
+              .map(|dynamic| (self.onset, dynamic))
+              .filter(|_| grace.is_none()),
+          );
+        }
+        NoteModificationType::Tremolo { relative_speed } => markings.push(MnxConverter::tremolo(relative_speed)),
+        ref modification => markings.extend(
+          MnxConverter::note_markings(modification)
+            .iter()
+            .map(|name| (*name, MnxConverter::object(Vec::new()))),
+        ),
+      }
+    }
+    if !markings.is_empty() {
+      MnxConverter::set_member(&mut event, "markings", MnxConverter::object(markings));
+    }
+    self.push_event(event, Fraction::from_duration(&note.duration), ratio, grace);
+  }
+
+  fn write_chord(&mut self, chord: &Chord, ratio: Fraction) {
+    // Grace notes are written just before the chord they belong to
+    let (grace_notes, notes): (Vec<_>, Vec<_>) = chord
+      .iter()
+      .map(|ChordContent::Note(note)| note)
+      .filter(|note| !note.is_rest())
+      .partition(|note| note.is_grace_note());
+    for note in grace_notes {
+      self.write_note(note, ratio);
+    }
+    let Some(first) = notes.first().copied() else {
+      return;
+    };
+    let chord_tie = chord
+      .iter_modifications()
+      .any(|modification| modification.r#type == ChordModificationType::Tie);
+    let tied = self.tied.remove(&self.voice).unwrap_or_default();
+    let (mut values, mut tied_pitches) = (Vec::new(), Vec::new());
+    for (idx, note) in notes.into_iter().enumerate() {
+      let value = self.note_value(note);
+      self.link_tie(&tied, note.pitch, &format!("n{}", note.get_id()));
+      if chord_tie
+        || note
+          .iter_modifications()
+          .any(|modification| modification.r#type == NoteModificationType::Tie)
+      {
+        tied_pitches.push((note.pitch, self.events.len(), idx));
+      }
+      values.push(value);
+    }
+    if !tied_pitches.is_empty() {
+      self.tied.insert(self.voice, tied_pitches);
+    }
+    let mut markings = Vec::new();
+    for modification in chord.iter_modifications() {
+      match modification.r#type {
+        ChordModificationType::Dynamic { dynamic } => {
+          self
+            .dynamics
+            .extend(dynamic_text(&dynamic).map(|dynamic| (self.onset, dynamic)));
+        }
+        ChordModificationType::Tremolo { relative_speed } => markings.push(MnxConverter::tremolo(relative_speed)),
+        ref modification => markings.extend(
+          MnxConverter::chord_markings(modification)
+            .iter()
+            .map(|name| (*name, MnxConverter::object(Vec::new()))),
+        ),
+      }
+    }
+    let mut event = MnxConverter::object(vec![
+      ("id", JsonValue::String(format!("c{}", chord.get_id()))),
+      ("duration", MnxConverter::duration_value(&first.duration)),
+      ("notes", JsonValue::Array(values)),
+    ]);
+    if !markings.is_empty() {
+      MnxConverter::set_member(&mut event, "markings", MnxConverter::object(markings));
+    }
+    self.push_event(event, Fraction::from_duration(&first.duration), ratio, None);
+  }
+
+  fn write_phrase(&mut self, phrase: &Phrase, mut ratio: Fraction) {
+    let (voice, start) = (self.voice, self.events.len());
+    let mut tuplet = false;
+    for modification in phrase.iter_modifications() {
+      if let PhraseModificationType::Tuplet { num_beats, into_beats } = modification.r#type {
+        if num_beats > 0 && into_beats > 0 && num_beats != into_beats {
+          self.num_tuplets += 1;
+          self.tuplets.push((self.num_tuplets, num_beats, into_beats, None));
+          ratio = ratio * Fraction::new(u64::from(into_beats), u64::from(num_beats));
+          tuplet = true;
+        }
+      }
+    }
+    for item in phrase.iter() {
+      match item {
+        PhraseContent::Note(note) => self.write_note(note, ratio),
+        PhraseContent::Chord(chord) => self.write_chord(chord, ratio),
+        PhraseContent::Phrase(phrase) => self.write_phrase(phrase, ratio),
+        PhraseContent::MultiVoice(multivoice) => self.write_multivoice(multivoice, ratio),
+      }
+    }
+    if tuplet {
+      self.tuplets.pop();
+    }
+
+    // Slurs run from the first to the last sounding event of the phrase within its own voice
+    let legato = phrase
+      .iter_modifications()
+      .any(|modification| modification.r#type == PhraseModificationType::Legato);
+    let events = (start..self.events.len())
+      .filter(|idx| self.events[*idx].voice == voice && self.events[*idx].duration > Fraction::ZERO)
+      .collect::<Vec<_>>();
+    if let (true, Some(first), Some(last)) = (legato, events.first(), events.last()) {
+      if first != last {
+        let target = self.events[*last].value.get("id").cloned().unwrap_or(JsonValue::Null);
+        let slur = MnxConverter::object(vec![("target", target)]);
+        MnxConverter::push_member(&mut self.events[*first].value, "slurs", slur);
+      }
+    }
+  }
+
+  fn write_multivoice(&mut self, multivoice: &MultiVoice, ratio: Fraction) {
+    let phrases = multivoice
+      .iter()
+      .map(|MultiVoiceContent::Phrase(phrase)| phrase)
+      .filter(|phrase| !phrase.is_empty())
+      .collect::<Vec<_>>();
+    let (voice, max_voice, onset, position) = (self.voice, self.max_voice, self.onset, self.position);
+    let alterations = self.alterations.clone();
+
+    // Additional voices are written into sequences that no enclosing voice is using
+    let voices = (0..phrases.len())
+      .map(|idx| {
+        if idx == 0 {
+          voice
+        } else {
+          self.max_voice += 1;
+          self.max_voice
+        }
+      })
+      .collect::<Vec<_>>();
+    let (mut end, mut end_position, mut first_alterations) = (onset, position, None);
+    for (phrase, phrase_voice) in phrases.into_iter().zip(voices) {
+      self.onset = onset;
+      self.position = position;
+      self.alterations.clone_from(&alterations);
+      self.voice = phrase_voice;
+      self.write_phrase(phrase, ratio);
+      if self.onset > end {
+        end = self.onset;
+        end_position = self.position;
+      }
+      first_alterations.get_or_insert_with(|| self.alterations.clone());
+    }
+    self.voice = voice;
+    self.max_voice = max_voice;
+    self.onset = end;
+    self.position = end_position;
+    self.alterations = first_alterations.unwrap_or(alterations);
+  }
+
+  fn write_staff(&mut self, staff: &Staff) {
+    for item in staff.iter() {
+      match item {
+        StaffContent::Note(note) => self.write_note(note, Fraction::ONE),
+        StaffContent::Chord(chord) => self.write_chord(chord, Fraction::ONE),
+        StaffContent::Phrase(phrase) => self.write_phrase(phrase, Fraction::ONE),
+        StaffContent::MultiVoice(multivoice) => self.write_multivoice(multivoice, Fraction::ONE),
+        StaffContent::Direction(direction) => match &direction.r#type {
+          DirectionType::Dynamic { dynamic } => {
+            self
+              .dynamics
+              .extend(dynamic_text(dynamic).map(|dynamic| (self.onset, dynamic)));
+          }
+          DirectionType::KeyChange { .. }
+          | DirectionType::TimeSignatureChange { .. }
+          | DirectionType::ClefChange { .. } => match self.context.apply(&direction.r#type) {
+            Some(ContextChange::Key(key)) => self.keys.push((self.onset, key)),
+            Some(ContextChange::TimeSignature(time_signature)) => {
+              self.meters.push((self.onset, self.context.measure_length));
+              self.times.push((self.onset, time_signature));
+            }
+            Some(ContextChange::Clef(clef_type)) => {
+              let onset = self.onset;
+              self.clefs.retain(|(clef_onset, _)| *clef_onset != onset);
+              self.clefs.push((onset, clef_type));
+            }
+            None => (),
+          },
+          DirectionType::BreathMark => {
+            let voice = self.voice;
+            if let Some(event) = self
+              .events
+              .iter_mut()
+              .rev()
+              .find(|event| event.voice == voice && event.duration > Fraction::ZERO)
+            {
+              if MnxConverter::member_mut(&mut event.value, "markings").is_none() {
+                MnxConverter::set_member(&mut event.value, "markings", MnxConverter::object(Vec::new()));
+              }
+              if let Some(markings) = MnxConverter::member_mut(&mut event.value, "markings") {
+                MnxConverter::set_member(markings, "breath", MnxConverter::object(Vec::new()));
+              }
+            }
+          }
+          _ => (),
+        },
+      }
+    }
+  }
+
+  fn write_section(&mut self, section: &Section, staff_name: &str) {
+    let start = self.onset;
+    for modification in section.iter_modifications() {
+      if let SectionModificationType::TempoExplicit { tempo } = &modification.r#type {
+        if *tempo != self.tempo {
+          self.tempo = *tempo;
+          self.tempos.push((self.onset, *tempo));
+        }
+      }
+    }
+    for content in section.iter() {
+      match content {
+        SectionContent::Staff(staff) if staff.get_name() == staff_name => self.write_staff(staff),
+        SectionContent::Section(section) => self.write_section(section, staff_name),
+        SectionContent::Staff(_) => (),
+      }
+    }
+    if self.primary && self.onset > start {
+      for modification in section.iter_modifications() {
+        match &modification.r#type {
+          SectionModificationType::Repeat { .. } => self.repeats.push((start, self.onset)),
+          SectionModificationType::OnlyPlay { iterations } => {
+            let numbers = iterations.iter().map(|iteration| u16::from(*iteration) + 1).collect();
+            self.endings.push((start, self.onset, numbers));
+          }
+          _ => (),
+        }
+      }
+    }
+  }
+
+  fn end(&self) -> Fraction {
+    self
+      .events
+      .iter()
+      .map(|event| event.onset + event.duration)
+      .max()
+      .unwrap_or(Fraction::ZERO)
+  }
+}
+
+/// Converter between AMM compositions and MNX (W3C Music Notation) documents.
+///
+/// MNX documents are imported by transcoding their global measures and part
+/// sequences into an equivalent MusicXML document which is then loaded by the
+/// MusicXML converter. Compositions are exported with one sequence per voice
+/// of every staff, measure by measure.
+pub struct MnxConverter;
+
+impl MnxConverter {
+  fn object(members: Vec<(&str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(
+      members
+        .into_iter()
+        .map(|(name, value)| (String::from(name), value))
+        .collect(),
+    )
+  }
+
+  fn member_mut<'a>(value: &'a mut JsonValue, key: &str) -> Option<&'a mut JsonValue> {
+    match value {
+      JsonValue::Object(members) => members
+        .iter_mut()
+        .find_map(|(name, value)| (name == key).then_some(value)),
+      _ => None,
+    }
+  }
+
+  fn set_member(value: &mut JsonValue, key: &str, member: JsonValue) {
+    if let JsonValue::Object(members) = value {
+      match members.iter_mut().find(|(name, _)| name == key) {
+        Some((_, value)) => *value = member,
+        None => members.push((String::from(key), member)),
+      }
+    }
+  }
+
+  fn push_member(value: &mut JsonValue, key: &str, item: JsonValue) {
+    match Self::member_mut(value, key) {
+      Some(JsonValue::Array(items)) => items.push(item),
+      _ => Self::set_member(value, key, JsonValue::Array(vec![item])),
+    }
+  }
+
+  fn number(value: u64) -> JsonValue {
+    JsonValue::Number(value as f64)
+  }
+
+  fn staff(value: &JsonValue, staves: usize) -> usize {
+    value
+      .get("staff")
+      .and_then(JsonValue::as_i64)
+      .and_then(|staff| usize::try_from(staff).ok())
+      .filter(|staff| (1..=staves).contains(staff))
+      .unwrap_or(1)
+  }
+
+  fn position(value: &JsonValue) -> Fraction {
+    let fraction = value
+      .get("position")
+      .or_else(|| value.get("location"))
+      .and_then(|position| position.get("fraction"))
+      .and_then(JsonValue::as_array)
+      .unwrap_or_default();
+    match fraction {
+      [numerator, denominator] => {
+        let numerator = numerator.as_i64().and_then(|numerator| u64::try_from(numerator).ok());
+        let denominator = denominator
+          .as_i64()
+          .and_then(|denominator| u64::try_from(denominator).ok())
+          .filter(|denominator| *denominator > 0);
+        match (numerator, denominator) {
+          (Some(numerator), Some(denominator)) => Fraction::new(numerator, denominator),
+          _ => Fraction::ZERO,
+        }
+      }
+      _ => Fraction::ZERO,
+    }
+  }
+
+  fn duration(value: &JsonValue) -> Option<Fraction> {
+    let base = value.get("base").and_then(JsonValue::as_str)?;
+    let (_, numerator, denominator) = DURATION_BASES.iter().find(|(name, ..)| *name == base)?;
+    let dots = value.get("dots").and_then(JsonValue::as_i64).unwrap_or(0);
+    let dots = u32::try_from(dots).ok().filter(|dots| *dots <= 4)?;
+    Some(Fraction::new(*numerator, *denominator) * Fraction::new((1 << (dots + 1)) - 1, 1 << dots))
+  }
+
+  fn duration_value(duration: &Duration) -> JsonValue {
+    Self::fraction_value(Fraction::from_duration(duration))
+  }
+
+  fn fraction_value(written: Fraction) -> JsonValue {
+    let (base, dots) = written.note_type().unwrap_or(("quarter", 0));
+    let base = match base {
+      "long" => "longa",
+      base => base,
+    };
+    let mut members = vec![("base", JsonValue::String(String::from(base)))];
+    if dots > 0 {
+      members.push(("dots", Self::number(u64::from(dots))));
+    }
+    Self::object(members)
+  }
+
+  fn key_element(fifths: i32) -> XmlElement {
+    xml_element("key", &[], vec![xml_text_element("fifths", fifths)])
+  }
+
+  fn key_alteration(fifths: i32, step: char) -> i64 {
+    let idx = SHARPS.iter().position(|sharp| *sharp == step).unwrap_or_default() as i32;
+    if fifths > idx {
+      1
+    } else if fifths < idx - 6 {
+      -1
+    } else {
+      0
+    }
+  }
+
+  fn time(time: &JsonValue) -> Option<(XmlElement, Fraction)> {
+    let count = time
+      .get("count")
+      .and_then(JsonValue::as_i64)
+      .and_then(|count| u64::try_from(count).ok())
+      .filter(|count| *count > 0)?;
+    let unit = time
+      .get("unit")
+      .and_then(JsonValue::as_i64)
+      .and_then(|unit| u64::try_from(unit).ok())
+      .filter(|unit| *unit > 0)?;
+    let elements = vec![xml_text_element("beats", count), xml_text_element("beat-type", unit)];
+    let element = match time.get("symbol").and_then(JsonValue::as_str) {
+      Some(symbol @ ("common" | "cut")) => xml_element("time", &[("symbol", symbol)], elements),
+      _ => xml_element("time", &[], elements),
+    };
+    Some((element, Fraction::new(count, unit)))
+  }
+
+  fn clef_element(sign: &str, line: u8, octave_change: i64) -> XmlElement {
+    let mut elements = vec![xml_text_element("sign", sign), xml_text_element("line", line)];
+    if octave_change != 0 {
+      elements.push(xml_text_element("clef-octave-change", octave_change));
+    }
+    xml_element("clef", &[], elements)
+  }
+
+  fn clef(clef: &JsonValue) -> Option<XmlElement> {
+    let sign = match clef.get("sign").and_then(JsonValue::as_str)? {
+      "G" => "G",
+      "F" => "F",
+      "C" => "C",
+      _ => return None,
+    };
+    // Staff positions count the lines and spaces away from the middle line of the staff
+    let position = clef.get("staffPosition").and_then(JsonValue::as_i64).unwrap_or(0);
+    let line = u8::try_from(3 + position.div_euclid(2))
+      .ok()
+      .filter(|line| (1..=5).contains(line))?;
+    let octave = clef.get("octave").and_then(JsonValue::as_i64).unwrap_or(0).clamp(-2, 2);
+    Some(Self::clef_element(sign, line, octave))
+  }
+
+  fn bar_style(barline: &str) -> Option<&'static str> {
+    match barline {
+      "double" => Some("light-light"),
+      "final" => Some("light-heavy"),
+      "heavy" => Some("heavy"),
+      "heavyLight" => Some("heavy-light"),
+      "heavyHeavy" => Some("heavy-heavy"),
+      "dashed" => Some("dashed"),
+      "dotted" => Some("dotted"),
+      "noBarline" => Some("none"),
+      "short" => Some("short"),
+      "tick" => Some("tick"),
+      _ => None,
+    }
+  }
+
+  fn direction(
+    direction_types: Vec<XmlElement>,
+    sound: Option<XmlElement>,
+    placement: Option<&str>,
+    staff: usize,
+  ) -> XmlElement {
+    let mut elements = direction_types
+      .into_iter()
+      .map(|direction_type| xml_element("direction-type", &[], vec![direction_type]))
+      .collect::<Vec<_>>();
+    elements.extend(sound);
+    elements.push(xml_text_element("staff", staff));
+    match placement {
+      Some(placement) => xml_element("direction", &[("placement", placement)], elements),
+      None => xml_element("direction", &[], elements),
+    }
+  }
+
+  fn tempo_direction(tempo: &JsonValue) -> Option<XmlElement> {
+    let beats_per_minute = tempo.get("bpm").and_then(JsonValue::as_f64).filter(|bpm| *bpm > 0.0)?;
+    let beat = tempo
+      .get("value")
+      .and_then(Self::duration)
+      .unwrap_or(Fraction::new(1, 4));
+    let (beat_unit, dots) = beat.note_type()?;
+    let mut metronome = vec![xml_text_element("beat-unit", beat_unit)];
+    metronome.extend((0..dots).map(|_| xml_element("beat-unit-dot", &[], Vec::new())));
+    metronome.push(xml_text_element("per-minute", beats_per_minute));
+    let quarters_per_minute = beats_per_minute * 4.0 * beat.numerator as f64 / beat.denominator as f64;
+    Some(Self::direction(
+      vec![xml_element("metronome", &[], metronome)],
+      Some(xml_element(
+        "sound",
+        &[("tempo", &quarters_per_minute.to_string())],
+        Vec::new(),
+      )),
+      Some("above"),
+      1,
+    ))
+  }
+
+  fn forward(voice: usize, staff: usize) -> XmlElement {
+    xml_element(
+      "forward",
+      &[],
+      vec![xml_text_element("voice", voice), xml_text_element("staff", staff)],
+    )
+  }
+
+  fn add_markings(element: &mut XmlElement, event: &JsonValue) {
+    let Some(markings) = event.get("markings") else {
+      return;
+    };
+    for (marking, articulation) in MARKINGS {
+      if markings.get(marking).is_some() {
+        add_notation(
+          element,
+          Some("articulations"),
+          xml_element(articulation, &[], Vec::new()),
+        );
+      }
+    }
+    if let Some(tremolo) = markings.get("tremolo") {
+      let marks = tremolo
+        .get("marks")
+        .and_then(JsonValue::as_i64)
+        .unwrap_or(3)
+        .clamp(1, 8);
+      let tremolo = XmlElement {
+        text: marks.to_string(),
+        ..xml_element("tremolo", &[("type", "single")], Vec::new())
+      };
+      add_notation(element, Some("ornaments"), tremolo);
+    }
+  }
+
+  fn load_from_mnx(data: &[u8]) -> Result<Composition, String> {
+    let text = core::str::from_utf8(data).map_err(|err| err.to_string())?;
+    let reader = MnxReader::parse(text)?;
+    MusicXmlConverter::load_from_musicxml(&ScorePartwise::deserialize(&reader.transcode_score()?)?)
+  }
+
+  fn step(name: PitchName) -> &'static str {
+    match name {
+      PitchName::A => "A",
+      PitchName::B => "B",
+      PitchName::C | PitchName::Rest => "C",
+      PitchName::D => "D",
+      PitchName::E => "E",
+      PitchName::F => "F",
+      PitchName::G => "G",
+    }
+  }
+
+  fn alteration(accidental: Accidental) -> i64 {
+    match accidental {
+      Accidental::Sharp => 1,
+      Accidental::Flat => -1,
+      Accidental::DoubleSharp => 2,
+      Accidental::DoubleFlat => -2,
+      Accidental::Natural | Accidental::None => 0,
+    }
+  }
+
+  fn tremolo(relative_speed: u8) -> (&'static str, JsonValue) {
+    let marks = Self::number(u64::from(relative_speed.clamp(1, 8)));
+    ("tremolo", Self::object(vec![("marks", marks)]))
+  }
+
+  fn note_markings(modification: &NoteModificationType) -> &'static [&'static str] {
+    match modification {
+      NoteModificationType::Accent => &["accent"],
+      NoteModificationType::DetachedLegato => &["staccato", "tenuto"],
+      NoteModificationType::Marcato => &["strongAccent"],
+      NoteModificationType::SoftAccent => &["softAccent"],
+      NoteModificationType::Spiccato => &["spiccato"],
+      NoteModificationType::Staccatissimo => &["staccatissimo"],
+      NoteModificationType::Staccato => &["staccato"],
+      NoteModificationType::Stress => &["stress"],
+      NoteModificationType::Tenuto => &["tenuto"],
+      NoteModificationType::Unstress => &["unstress"],
+      _ => &[],
+    }
+  }
+
+  fn chord_markings(modification: &ChordModificationType) -> &'static [&'static str] {
+    match modification {
+      ChordModificationType::Accent => &["accent"],
+      ChordModificationType::DetachedLegato => &["staccato", "tenuto"],
+      ChordModificationType::Marcato => &["strongAccent"],
+      ChordModificationType::SoftAccent => &["softAccent"],
+      ChordModificationType::Spiccato => &["spiccato"],
+      ChordModificationType::Staccatissimo => &["staccatissimo"],
+      ChordModificationType::Staccato => &["staccato"],
+      ChordModificationType::Stress => &["stress"],
+      ChordModificationType::Tenuto => &["tenuto"],
+      ChordModificationType::Unstress => &["unstress"],
+      _ => &[],
+    }
+  }
+
+  fn key_value(key: &Key) -> JsonValue {
+    Self::object(vec![("fifths", JsonValue::Number(f64::from(key.fifths())))])
+  }
+
+  fn time_value(time_signature: &TimeSignature) -> Option<JsonValue> {
+    let (count, unit, symbol) = match time_signature.signature {
+      TimeSignatureType::CommonTime => (4, 4, Some("common")),
+      TimeSignatureType::CutTime => (2, 2, Some("cut")),
+      TimeSignatureType::None => return None,
+      TimeSignatureType::Explicit => (time_signature.numerator, time_signature.denominator, None),
+    };
+    let mut members = vec![
+      ("count", Self::number(u64::from(count))),
+      ("unit", Self::number(u64::from(unit))),
+    ];
+    if let Some(symbol) = symbol {
+      members.push(("symbol", JsonValue::String(String::from(symbol))));
+    }
+    Some(Self::object(members))
+  }
+
+  fn tempo_value(tempo: &Tempo, position: Option<Fraction>) -> JsonValue {
+    let mut members = vec![
+      ("bpm", Self::number(u64::from(tempo.beats_per_minute))),
+      ("value", Self::duration_value(&tempo.base_note)),
+    ];
+    members.extend(position.map(|position| ("location", Self::position_value(position))));
+    Self::object(members)
+  }
+
+  fn position_value(position: Fraction) -> JsonValue {
+    Self::object(vec![(
+      "fraction",
+      JsonValue::Array(vec![
+        Self::number(position.numerator),
+        Self::number(position.denominator),
+      ]),
+    )])
+  }
+
+  fn clef_value(clef_type: ClefType) -> JsonValue {
+    let (sign, position) = match clef_type {
+      ClefType::Treble => ("G", -2),
+      ClefType::FrenchViolin => ("G", -4),
+      ClefType::Bass => ("F", 2),
+      ClefType::Baritone => ("F", 0),
+      ClefType::Subbass => ("F", 4),
+      ClefType::Alto => ("C", 0),
+      ClefType::Tenor => ("C", 2),
+      ClefType::Soprano => ("C", -4),
+      ClefType::MezzoSoprano => ("C", -2),
+    };
+    Self::object(vec![
+      ("sign", JsonValue::String(String::from(sign))),
+      ("staffPosition", JsonValue::Number(f64::from(position))),
+    ])
+  }
+
+  fn space_values(mut duration: Fraction) -> Vec<JsonValue> {
+    // Gaps are filled with the fewest spaces of undotted durations that add up to them
+    let mut spaces = Vec::new();
+    let mut value = Fraction::new(4, 1);
+    while duration > Fraction::ZERO && value >= Fraction::new(1, 2048) {
+      if duration >= value {
+        spaces.push(Self::object(vec![
+          ("type", JsonValue::String(String::from("space"))),
+          ("duration", Self::fraction_value(value)),
+        ]));
+        duration = duration - value;
+      } else {
+        value = value * Fraction::new(1, 2);
+      }
+    }
+    spaces
+  }
+
+  fn sequence_content(events: &[&MnxEvent], start: Fraction) -> Vec<JsonValue> {
+    // Open tuplets are kept on a stack of containers, each collecting its children
+    let mut stack: Vec<(usize, JsonValue, Vec<JsonValue>)> = vec![(0, JsonValue::Null, Vec::new())];
+    let close = |stack: &mut Vec<(usize, JsonValue, Vec<JsonValue>)>| {
+      if let Some((_, mut container, children)) = stack.pop() {
+        Self::set_member(&mut container, "content", JsonValue::Array(children));
+        if let Some((_, _, parent)) = stack.last_mut() {
+          parent.push(container);
+        }
+      }
+    };
+    let (mut cursor, mut grace_open) = (start, false);
+    for event in events {
+      let gap = event.onset > cursor;
+      let depth = if gap {
+        0
+      } else {
+        stack[1..]
+          .iter()
+          .zip(&event.tuplets)
+          .take_while(|((group, ..), (tuplet, ..))| group == tuplet)
+          .count()
+      };
+      if stack.len() > depth + 1 {
+        grace_open = false;
+      }
+      while stack.len() > depth + 1 {
+        close(&mut stack);
+      }
+      if gap {
+        stack[0].2.extend(Self::space_values(event.onset - cursor));
+        grace_open = false;
+      }
+      for (group, num, into, unit) in &event.tuplets[depth..] {
+        let side = |multiple: u8| {
+          Self::object(vec![
+            ("multiple", Self::number(u64::from(multiple))),
+            ("duration", Self::fraction_value(*unit)),
+          ])
+        };
+        let tuplet = Self::object(vec![
+          ("type", JsonValue::String(String::from("tuplet"))),
+          ("inner", side(*num)),
+          ("outer", side(*into)),
+        ]);
+        stack.push((*group, tuplet, Vec::new()));
+        grace_open = false;
+      }
+      let Some((_, _, children)) = stack.last_mut() else {
+        continue;
+      };
+      match event.grace {
+        Some(slash) => {
+          let same_group = grace_open
+            && children
+              .last()
+              .and_then(|group| group.get("slash"))
+              .and_then(JsonValue::as_bool)
+              == Some(slash);
+          match children.last_mut().filter(|_| same_group) {
+            Some(group) => Self::push_member(group, "content", event.value.clone()),
+            None => children.push(Self::object(vec![
+              ("type", JsonValue::String(String::from("grace"))),
+              ("slash", JsonValue::Bool(slash)),
+              ("content", JsonValue::Array(vec![event.value.clone()])),
+            ])),
+          }
+          grace_open = true;
+        }
+        None => {
+          children.push(event.value.clone());
+          grace_open = false;
+        }
+      }
+      cursor = cursor.max(event.onset + event.duration);
+    }
+    while stack.len() > 1 {
+      close(&mut stack);
+    }
+    stack.pop().map(|(.., children)| children).unwrap_or_default()
+  }
+
+  pub(super) fn save_to_mnx(composition: &Composition, options: &JsonOptions) -> String {
+    // Collect the events of every staff of every part
+    let mut parts = Vec::new();
+    let mut num_staves = 0;
+    for part in composition.iter() {
+      let mut writers = Vec::new();
+      for staff_name in part.get_staff_names() {
+        let mut writer = MnxStaffWriter::new(composition, num_staves == 0);
+        for PartContent::Section(section) in part.iter() {
+          writer.write_section(section, &staff_name);
+        }
+        writers.push(writer);
+        num_staves += 1;
+      }
+      parts.push((part.get_name(), writers));
+    }
+
+    // Divide the music into measures according to the time signatures of the first staff
+    let writers = parts.iter().flat_map(|(_, writers)| writers.iter()).collect::<Vec<_>>();
+    let end = writers
+      .iter()
+      .map(|writer| writer.end())
+      .max()
+      .unwrap_or(Fraction::ZERO);
+    let starts = writers
+      .first()
+      .map_or_else(|| vec![Fraction::ZERO], |primary| measure_starts(&primary.meters, end));
+    let measure_of = |onset: Fraction| starts.partition_point(|start| *start <= onset).saturating_sub(1);
+    let last_measure_of = |end: Fraction| starts.partition_point(|start| *start < end).saturating_sub(1);
+
+    // Describe the key, time signature, tempo, repeats, and endings of every measure
+    let mut globals = (0..starts.len()).map(|_| Self::object(Vec::new())).collect::<Vec<_>>();
+    Self::set_member(&mut globals[0], "key", Self::key_value(composition.get_starting_key()));
+    if let Some(time) = Self::time_value(composition.get_starting_time_signature()) {
+      Self::set_member(&mut globals[0], "time", time);
+    }
+    Self::push_member(
+      &mut globals[0],
+      "tempos",
+      Self::tempo_value(composition.get_tempo(), None),
+    );
+    if let Some(primary) = writers.first() {
+      for (onset, key) in &primary.keys {
+        Self::set_member(&mut globals[measure_of(*onset)], "key", Self::key_value(key));
+      }
+      for (onset, time_signature) in &primary.times {
+        if let Some(time) = Self::time_value(time_signature) {
+          Self::set_member(&mut globals[measure_of(*onset)], "time", time);
+        }
+      }
+      for (onset, tempo) in &primary.tempos {
+        let idx = measure_of(*onset);
+        let position = Some(*onset - starts[idx]).filter(|position| *position > Fraction::ZERO);
+        Self::push_member(&mut globals[idx], "tempos", Self::tempo_value(tempo, position));
+      }
+      for (start, end) in &primary.repeats {
+        Self::set_member(
+          &mut globals[measure_of(*start)],
+          "repeatStart",
+          Self::object(Vec::new()),
+        );
+        Self::set_member(
+          &mut globals[last_measure_of(*end)],
+          "repeatEnd",
+          Self::object(Vec::new()),
+        );
+      }
+      for (start, end, numbers) in &primary.endings {
+        let (first, last) = (measure_of(*start), last_measure_of(*end));
+        let ending = Self::object(vec![
+          ("duration", Self::number((last.max(first) - first + 1) as u64)),
+          (
+            "numbers",
+            JsonValue::Array(numbers.iter().map(|number| Self::number(u64::from(*number))).collect()),
+          ),
+        ]);
+        Self::set_member(&mut globals[first], "ending", ending);
+      }
+    }
+
+    // Write the clefs, dynamics, and sequences of every part measure by measure
+    let mut part_values = Vec::new();
+    for (name, writers) in &parts {
+      let mut measures = (0..starts.len())
+        .map(|_| (Vec::new(), Vec::new(), Vec::new()))
+        .collect::<Vec<_>>();
+      for (staff_idx, writer) in writers.iter().enumerate() {
+        let staff = Self::number(staff_idx as u64 + 1);
+        for (onset, clef_type) in &writer.clefs {
+          let idx = measure_of(*onset);
+          measures[idx].0.push(Self::object(vec![
+            ("clef", Self::clef_value(*clef_type)),
+            ("position", Self::position_value(*onset - starts[idx])),
+            ("staff", staff.clone()),
+          ]));
+        }
+        for (onset, dynamic) in &writer.dynamics {
+          let idx = measure_of(*onset);
+          measures[idx].1.push(Self::object(vec![
+            ("value", JsonValue::String(dynamic.clone())),
+            ("position", Self::position_value(*onset - starts[idx])),
+            ("staff", staff.clone()),
+          ]));
+        }
+        let mut buckets: Vec<BTreeMap<usize, Vec<&MnxEvent>>> = (0..starts.len()).map(|_| BTreeMap::new()).collect();
+        for event in &writer.events {
+          buckets[measure_of(event.onset)]
+            .entry(event.voice)
+            .or_default()
+            .push(event);
+        }
+        for (idx, voices) in buckets.into_iter().enumerate() {
+          for (voice, events) in voices {
+            measures[idx].2.push(Self::object(vec![
+              ("staff", staff.clone()),
+              ("voice", JsonValue::String(format!("v{voice}"))),
+              (
+                "content",
+                JsonValue::Array(Self::sequence_content(&events, starts[idx])),
+              ),
+            ]));
+          }
+        }
+      }
+      let measures = measures
+        .into_iter()
+        .map(|(clefs, dynamics, sequences)| {
+          let mut members = Vec::new();
+          if !clefs.is_empty() {
+            members.push(("clefs", JsonValue::Array(clefs)));
+          }
+          if !dynamics.is_empty() {
+            members.push(("dynamics", JsonValue::Array(dynamics)));
+          }
+          members.push(("sequences", JsonValue::Array(sequences)));
+          Self::object(members)
+        })
+        .collect();
+      let mut members = vec![("name", JsonValue::String(name.to_string()))];
+      if writers.len() > 1 {
+        members.push(("staves", Self::number(writers.len() as u64)));
+      }
+      members.push(("measures", JsonValue::Array(measures)));
+      part_values.push(Self::object(members));
+    }
+    Self::object(vec![
+      ("mnx", Self::object(vec![("version", Self::number(MNX_VERSION as u64))])),
+      ("global", Self::object(vec![("measures", JsonValue::Array(globals))])),
+      ("parts", JsonValue::Array(part_values)),
+    ])
+    .serialize_json_with(options)
+  }
+}
+
+impl Load for MnxConverter {
+  // MNX documents are transcoded into an equivalent MusicXML document which is
+  // then loaded by the MusicXML converter, so both formats share the same structure
+  fn load_bytes(data: &[u8]) -> Result<Composition, AmmError> {
+    MnxConverter::load_from_mnx(data).map_err(AmmError::from)
+  }
+}
+
+impl Store for MnxConverter {
+  fn save_bytes(composition: &Composition) -> Result<Vec<u8>, AmmError> {
+    Ok(MnxConverter::save_to_mnx(composition, &JsonOptions::default()).into_bytes())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::context::KeyMode;
+  use crate::storage::Storage;
+
+  const DOCUMENT: &str = r#"{
+  "mnx": {"version": 1},
+  "global": {
+    "measures": [
+      {
+        "key": {"fifths": 1},
+        "time": {"count": 3, "unit": 4},
+        "tempos": [{"bpm": 96, "value": {"base": "quarter"}}],
+        "repeatStart": {}
+      },
+      {"repeatEnd": {}, "barline": {"type": "final"}}
+    ]
+  },
+  "parts": [
+    {
+      "name": "Flute",
+      "measures": [
+        {
+          "dynamics": [{"value": "p", "position": {"fraction": [0, 1]}}],
+          "sequences": [
+            {
+              "voice": "v1",
+              "content": [
+                {"id": "a", "duration": {"base": "eighth"}, "notes": [{"pitch": {"step": "G", "octave": 4}}], "markings": {"staccato": {}}, "slurs": [{"target": "b"}]},
+                {"duration": {"base": "eighth"}, "notes": [{"pitch": {"step": "A", "octave": 4}}]},
+                {
+                  "type": "tuplet",
+                  "inner": {"multiple": 3, "duration": {"base": "eighth"}},
+                  "outer": {"multiple": 2, "duration": {"base": "eighth"}},
+                  "content": [
+                    {"duration": {"base": "eighth"}, "notes": [{"pitch": {"step": "B", "octave": 4}}]},
+                    {"duration": {"base": "eighth"}, "notes": [{"pitch": {"step": "C", "octave": 5, "alter": 1}}]},
+                    {"id": "b", "duration": {"base": "eighth"}, "notes": [{"id": "t", "pitch": {"step": "D", "octave": 5}, "ties": [{"target": "u"}]}]}
+                  ]
+                },
+                {"duration": {"base": "quarter"}, "rest": {}}
+              ]
+            }
+          ]
+        },
+        {
+          "sequences": [
+            {"content": [{"duration": {"base": "half", "dots": 1}, "notes": [{"id": "u", "pitch": {"step": "D", "octave": 5}}], "markings": {"tenuto": {}}}]}
+          ]
+        }
+      ]
+    },
+    {
+      "name": "Piano",
+      "staves": 2,
+      "measures": [
+        {
+          "clefs": [{"clef": {"sign": "F", "staffPosition": 2}, "staff": 2}],
+          "sequences": [
+            {"staff": 1, "content": [{"duration": {"base": "half", "dots": 1}, "notes": [{"pitch": {"step": "G", "octave": 4}}, {"pitch": {"step": "B", "octave": 4}}, {"pitch": {"step": "D", "octave": 5}}]}]},
+            {"staff": 2, "content": [{"duration": {"base": "whole"}, "measure": true, "rest": {}}]}
+          ]
+        },
+        {
+          "sequences": [
+            {"staff": 1, "content": [{"duration": {"base": "half", "dots": 1}, "notes": [{"pitch": {"step": "D", "octave": 4}}]}]},
+            {"staff": 2, "content": [{"duration": {"base": "half", "dots": 1}, "notes": [{"pitch": {"step": "G", "octave": 2}}]}]}
+          ]
+        }
+      ]
+    }
+  ]
+}"#;
+
+  fn count_notes(composition: &Composition) -> Vec<usize> {
+    composition
+      .iter()
+      .map(|part| {
+        part
+          .iter_timeslices()
+          .flat_map(|slice| slice.content.into_iter())
+          .filter(|content| !content.note.is_rest())
+          .count()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_load_mnx() {
+    let composition = MnxConverter::load_bytes(DOCUMENT.as_bytes()).unwrap();
+    assert_eq!(composition.get_part_names(), ["Flute", "Piano"]);
+    assert_eq!(
+      *composition.get_starting_key(),
+      Key::from_fifths(1, Some(KeyMode::Major))
+    );
+    assert_eq!(
+      *composition.get_starting_time_signature(),
+      TimeSignature::new_explicit(3, 4)
+    );
+    assert_eq!(composition.get_tempo().beats_per_minute, 96);
+    assert_eq!(count_notes(&composition), [12, 10]);
+    assert!(MnxConverter::load_bytes(b"{\"mnx\": {\"version\": 2}}").is_err());
+    assert!(MnxConverter::load_bytes(b"{\"mnx\": {\"version\": 1}, \"parts\": []}").is_err());
+    assert!(MnxConverter::load_bytes(b"{\"mnx\": ").is_err());
+  }
+
+  #[test]
+  fn test_save_mnx() {
+    let composition = MnxConverter::load_bytes(DOCUMENT.as_bytes()).unwrap();
+    let mnx = MnxConverter::save_to_mnx(&composition, &JsonOptions::default());
+    let document = JsonValue::parse(&mnx).unwrap();
+    assert_eq!(
+      document.get("mnx").and_then(|mnx| mnx.get("version")),
+      Some(&JsonValue::Number(1.0))
+    );
+    assert!(mnx.contains("\"key\":{\"fifths\":1}"));
+    assert!(mnx.contains("\"time\":{\"count\":3,\"unit\":4}"));
+    assert!(mnx.contains("\"tempos\":[{\"bpm\":96,\"value\":{\"base\":\"quarter\"}}]"));
+    assert!(mnx.contains("\"repeatStart\":{}"));
+    assert!(mnx.contains("\"repeatEnd\":{}"));
+    assert!(mnx.contains("\"type\":\"tuplet\",\"inner\":{\"multiple\":3,\"duration\":{\"base\":\"eighth\"}}"));
+    assert!(mnx.contains("\"markings\":{\"staccato\":{}}"));
+    assert!(mnx.contains("\"slurs\":[{\"target\":"));
+    assert!(mnx.contains("\"ties\":[{\"target\":"));
+    assert!(mnx.contains("\"accidentalDisplay\":{\"show\":true}"));
+    assert!(mnx.contains("\"clef\":{\"sign\":\"F\",\"staffPosition\":2}"));
+    assert!(mnx.contains("\"value\":\"p\""));
+    assert!(mnx.contains("\"staves\":2"));
+    let measures = document
+      .get("global")
+      .and_then(|global| global.get("measures"))
+      .and_then(JsonValue::as_array)
+      .unwrap();
+    assert_eq!(measures.len(), 2);
+
+    let reloaded = MnxConverter::load_bytes(mnx.as_bytes()).unwrap();
+    assert_eq!(reloaded.get_part_names(), ["Flute", "Piano"]);
+    assert_eq!(count_notes(&reloaded), count_notes(&composition));
+  }
+
+  #[test]
+  fn test_save_mnx_example() {
+    let composition = Storage::MusicXML.load("examples/Hymn_to_Freedom.mxl").unwrap();
+    let mnx = MnxConverter::save_to_mnx(&composition, &JsonOptions::default());
+    assert!(mnx.contains("\"voice\":\"v2\""));
+    let reloaded = MnxConverter::load_bytes(mnx.as_bytes()).unwrap();
+    assert_eq!(reloaded.get_part_names(), composition.get_part_names());
+    assert_eq!(count_notes(&reloaded), count_notes(&composition));
+  }
+
+  #[test]
+  fn test_mnx_unit_tuplets() {
+    let composition = Storage::MusicXML
+      .load("examples/Grande Valse Brillante2.musicxml")
+      .unwrap();
+    let mnx = MnxConverter::save_to_mnx(&composition, &JsonOptions::default());
+    let tuplets = mnx
+      .split("\"type\":\"tuplet\",\"inner\":{\"multiple\":")
+      .skip(1)
+      .map(|tuplet| {
+        let (inner, rest) = tuplet.split_once(',').unwrap();
+        (
+          inner,
+          rest
+            .split("\"outer\":{\"multiple\":")
+            .nth(1)
+            .unwrap()
+            .split(',')
+            .next()
+            .unwrap(),
+        )
+      });
+    assert!(tuplets.into_iter().all(|(inner, outer)| inner != outer));
+    let reloaded = MnxConverter::load_bytes(mnx.as_bytes()).unwrap();
+    assert_eq!(reloaded.get_part_names(), composition.get_part_names());
+
+    let unit = DOCUMENT.replace("\"outer\": {\"multiple\": 2", "\"outer\": {\"multiple\": 3");
+    assert_ne!(unit, DOCUMENT);
+    assert!(MnxConverter::load_bytes(unit.as_bytes()).is_ok());
+  }
+}
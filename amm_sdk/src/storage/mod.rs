@@ -3,71 +3,523 @@
 
 use crate::Composition;
 
-use alloc::string::String;
-use amm::AmmStorage;
+use abc::AbcConverter;
+use alloc::{string::String, vec::Vec};
+use amm::{AmmBinaryStorage, AmmStorage};
+use amm_internal::{JsonReader, JsonSerializer, JsonValue};
+use kern::KernConverter;
+use lilypond::LilyPondConverter;
+use mei::MeiConverter;
 use midi::MidiConverter;
+use mnx::MnxConverter;
+use musescore::MuseScoreConverter;
 use musicxml::MusicXmlConverter;
+use xml::{parse_xml, XmlElementExt};
+use zip::ZipArchive;
 
+pub use amm_internal::{AmmError, AmmErrorKind, JsonOptions};
+pub use custom::{DetectFormat, StorageFormat};
+
+mod abc;
 mod amm;
+mod custom;
+mod kern;
+mod lilypond;
+mod mei;
 mod midi;
+mod mnx;
+mod musescore;
 mod musicxml;
+mod util;
+mod xml;
+mod zip;
+
+/// A storage format that compositions can be loaded from.
+///
+/// Implement this trait to import a custom format described by a
+/// [`StorageFormat`].
+pub trait Load {
+  /// Loads a composition from the raw `data` of a document in this format.
+  ///
+  /// # Errors
+  /// Returns an error if the contents of the data cannot be parsed.
+  fn load_bytes(data: &[u8]) -> Result<Composition, AmmError>;
+}
+
+/// A storage format that compositions can be saved to.
+///
+/// Implement this trait to export a custom format described by a
+/// [`StorageFormat`].
+pub trait Store {
+  /// Saves a composition as the raw data of a document in this format.
+  ///
+  /// # Errors
+  /// Returns an error if the composition cannot be represented in this format.
+  fn save_bytes(composition: &Composition) -> Result<Vec<u8>, AmmError>;
+}
+
+#[cfg(feature = "std")]
+fn io_error(err: std::io::Error) -> AmmError {
+  AmmError::new(AmmErrorKind::Io, err.to_string())
+}
+
+/// Adapts an [`std::io::Write`] to [`core::fmt::Write`], keeping the first
+/// I/O error and counting the bytes written.
+#[cfg(feature = "std")]
+struct IoWriter<W> {
+  writer: W,
+  written: usize,
+  error: Option<std::io::Error>,
+}
 
-pub(crate) trait Load {
-  fn load(path: &str) -> Result<Composition, String>;
-  fn load_data(data: Vec<u8>) -> Result<Composition, String>;
+#[cfg(feature = "std")]
+impl<W: std::io::Write> core::fmt::Write for IoWriter<W> {
+  fn write_str(&mut self, text: &str) -> core::fmt::Result {
+    match self.writer.write_all(text.as_bytes()) {
+      Ok(()) => {
+        self.written += text.len();
+        Ok(())
+      }
+      Err(err) => {
+        self.error = Some(err);
+        Err(core::fmt::Error)
+      }
+    }
+  }
 }
 
-pub(crate) trait Store {
-  fn save(path: &str, composition: &Composition) -> Result<usize, String>;
+#[cfg(feature = "std")]
+fn read_all(mut reader: impl std::io::Read) -> Result<Vec<u8>, AmmError> {
+  let mut data = Vec::new();
+  reader.read_to_end(&mut data).map_err(io_error)?;
+  Ok(data)
 }
 
 /// Represents the various storage formats supported by the SDK.
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub enum Storage {
+  /// The native AMM JSON format.
   #[default]
   AMM,
+  /// The compact, versioned binary encoding of the native AMM format.
+  AMMBinary,
+  /// MusicXML in either partwise or timewise form, compressed (`.mxl`) or uncompressed.
   MusicXML,
+  /// Standard MIDI files.
   MIDI,
+  /// MuseScore native scores, compressed (`.mscz`) or uncompressed (`.mscx`).
+  MuseScore,
+  /// ABC text notation, commonly used for folk and traditional tunes.
+  ABC,
+  /// LilyPond input files for typesetting, with pitches entered in either
+  /// absolute or `\relative` octave mode. Export only.
+  LilyPond { relative: bool },
+  /// MEI (Music Encoding Initiative) 5 documents.
+  MEI,
+  /// Humdrum `**kern` data, with one spine per staff.
+  Kern,
+  /// MNX (W3C Music Notation) 1.0 JSON documents.
+  MNX,
+  /// A format provided by the application, identified by the name it was
+  /// registered under with [`Storage::register`].
+  Custom(&'static str),
 }
 
 impl Storage {
+  /// Detects the storage format of the given raw `data` by inspecting its
+  /// contents, without relying on any file extension.
+  ///
+  /// AMM JSON, binary AMM, partwise and timewise MusicXML, compressed MusicXML (`.mxl`)
+  /// archives, compressed (`.mscz`) and uncompressed (`.mscx`) MuseScore
+  /// scores, MEI documents, MNX documents, Humdrum `**kern` data, Standard MIDI
+  /// files, and ABC notation are recognized.
+  ///
+  /// Registered custom formats are tried in the order they were registered
+  /// when the data does not match any of the built-in formats.
+  ///
+  /// # Errors
+  /// Returns an [`AmmErrorKind::UnsupportedFormat`] error describing the
+  /// contents if the format of the data cannot be recognized.
+  pub fn detect(data: &[u8]) -> Result<Self, AmmError> {
+    Self::detect_builtin(data).or_else(|err| match custom::detect(data) {
+      Some(format) => Ok(Self::Custom(format.name())),
+      None => Err(err),
+    })
+  }
+
+  fn detect_builtin(data: &[u8]) -> Result<Self, AmmError> {
+    if data.starts_with(b"MThd") {
+      return Ok(Self::MIDI);
+    } else if AmmBinaryStorage::is_amm_binary(data) {
+      return Ok(Self::AMMBinary);
+    } else if data.starts_with(b"PK\x03\x04") {
+      return Self::detect_archive(data);
+    }
+    let text = match core::str::from_utf8(data) {
+      Ok(text) => text,
+      Err(err) => core::str::from_utf8(&data[..err.valid_up_to()]).unwrap_or_default(),
+    };
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    let unsupported = |message: String| Err(AmmError::new(AmmErrorKind::UnsupportedFormat, message));
+    if text.is_empty() {
+      unsupported(String::from(
+        "Unable to detect the storage format of empty or binary data",
+      ))
+    } else if text.starts_with('{') {
+      if JsonReader::new(text).peek_type().ok().flatten().as_deref() == Some("Composition") {
+        Ok(Self::AMM)
+      } else if JsonValue::parse(text).is_ok_and(|value| value.get("mnx").is_some()) {
+        Ok(Self::MNX)
+      } else {
+        unsupported(String::from(
+          "JSON data does not contain an AMM composition or MNX document",
+        ))
+      }
+    } else if text.starts_with('<') {
+      match Self::find_xml_root_element(text) {
+        Some("score-partwise" | "score-timewise") => Ok(Self::MusicXML),
+        Some("museScore") => Ok(Self::MuseScore),
+        Some("mei") => Ok(Self::MEI),
+        Some(root) => unsupported(format!("Unsupported XML document with root element <{root}>")),
+        None => unsupported(String::from("Unable to locate the root element of the XML document")),
+      }
+    } else if text
+      .lines()
+      .find(|line| !line.starts_with('!'))
+      .is_some_and(|line| line.split('\t').any(|spine| spine == "**kern"))
+    {
+      Ok(Self::Kern)
+    } else if text.starts_with("%abc")
+      || (text.lines().any(|line| line.starts_with("X:")) && text.lines().any(|line| line.starts_with("K:")))
+    {
+      Ok(Self::ABC)
+    } else {
+      unsupported(String::from("Unrecognized storage format"))
+    }
+  }
+
+  /// Returns the format associated with the given file `extension`, with or
+  /// without its leading dot, ignoring case.
+  ///
+  /// Extensions of the built-in formats take precedence over those of
+  /// registered custom formats.
+  #[must_use]
+  pub fn from_extension(extension: &str) -> Option<Self> {
+    let extension = extension.strip_prefix('.').unwrap_or(extension).to_ascii_lowercase();
+    match extension.as_str() {
+      "amm" => Some(Self::AMM),
+      "ammb" => Some(Self::AMMBinary),
+      "musicxml" | "mxl" | "xml" => Some(Self::MusicXML),
+      "mid" | "midi" => Some(Self::MIDI),
+      "mscz" | "mscx" => Some(Self::MuseScore),
+      "abc" => Some(Self::ABC),
+      "ly" => Some(Self::LilyPond { relative: false }),
+      "mei" => Some(Self::MEI),
+      "krn" => Some(Self::Kern),
+      "mnx" => Some(Self::MNX),
+      _ => custom::formats()
+        .into_iter()
+        .find(|format| {
+          format
+            .extensions()
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(&extension))
+        })
+        .map(|format| Self::Custom(format.name())),
+    }
+  }
+
+  /// Registers a custom storage `format`, making it available as
+  /// [`Storage::Custom`] under its name for loading, saving, detection and
+  /// extension lookups.
+  ///
+  /// # Errors
+  /// Returns an error if a format with the same name is already registered.
+  #[cfg(feature = "std")]
+  pub fn register(format: StorageFormat) -> Result<Self, AmmError> {
+    custom::register(format).map(|()| Self::Custom(format.name()))
+  }
+
+  /// Returns all custom storage formats registered with [`Storage::register`].
+  #[must_use]
+  pub fn custom_formats() -> Vec<StorageFormat> {
+    custom::formats()
+  }
+
+  fn detect_archive(data: &[u8]) -> Result<Self, AmmError> {
+    let archive = ZipArchive::new(data)?;
+    if archive.file_names().any(|name| name.ends_with(".mscx")) {
+      return Ok(Self::MuseScore);
+    }
+    // Compressed MusicXML archives must declare their score as the first rootfile of the container
+    let root_file = archive
+      .read_file("META-INF/container.xml")
+      .ok()
+      .and_then(|container| parse_xml(core::str::from_utf8(&container).ok()?).ok())
+      .and_then(|container| {
+        container
+          .child("rootfiles")?
+          .child("rootfile")?
+          .attribute("full-path")
+          .map(String::from)
+      });
+    match root_file {
+      Some(path)
+        if (path.ends_with(".musicxml") || path.ends_with(".xml")) && archive.file_names().any(|name| name == path) =>
+      {
+        Ok(Self::MusicXML)
+      }
+      _ => Err(AmmError::new(
+        AmmErrorKind::UnsupportedFormat,
+        "ZIP archive does not contain a MusicXML or MuseScore score",
+      )),
+    }
+  }
+
+  fn find_xml_root_element(text: &str) -> Option<&str> {
+    let mut remaining = text;
+    loop {
+      remaining = remaining.trim_start();
+      if let Some(declaration) = remaining.strip_prefix("<?") {
+        remaining = declaration.split_once("?>")?.1;
+      } else if let Some(comment) = remaining.strip_prefix("<!--") {
+        remaining = comment.split_once("-->")?.1;
+      } else if let Some(doctype) = remaining.strip_prefix("<!") {
+        remaining = if doctype.split_once('>')?.0.contains('[') {
+          doctype.split_once("]>")?.1
+        } else {
+          doctype.split_once('>')?.1
+        };
+      } else {
+        return remaining
+          .strip_prefix('<')?
+          .split(|ch: char| ch.is_whitespace() || ch == '>' || ch == '/')
+          .next()
+          .filter(|name| !name.is_empty());
+      }
+    }
+  }
+
+  /// Loads a composition from a file at the specified `path`, automatically
+  /// detecting its storage format from the file contents.
+  ///
+  /// # Errors
+  /// Returns an error if the file cannot be read, its format cannot be
+  /// detected, or its contents cannot be parsed.
+  #[cfg(feature = "std")]
+  pub fn load_auto(path: &str) -> Result<Composition, AmmError> {
+    let data = std::fs::read(path).map_err(io_error)?;
+    Self::load_auto_bytes(&data)
+  }
+
+  /// Loads a composition from the given raw `data`, automatically detecting
+  /// its storage format from the data contents.
+  ///
+  /// # Errors
+  /// Returns an error if the format of the data cannot be detected or its
+  /// contents cannot be parsed.
+  pub fn load_auto_data(data: Vec<u8>) -> Result<Composition, AmmError> {
+    Self::load_auto_bytes(&data)
+  }
+
+  /// Loads a composition from the given byte slice, automatically detecting
+  /// its storage format from the data contents.
+  ///
+  /// # Errors
+  /// Returns an error if the format of the data cannot be detected or its
+  /// contents cannot be parsed.
+  pub fn load_auto_bytes(data: &[u8]) -> Result<Composition, AmmError> {
+    Self::detect(data)?.load_bytes(data)
+  }
+
+  /// Loads a composition from everything remaining in `reader`, automatically
+  /// detecting its storage format from the data contents.
+  ///
+  /// # Errors
+  /// Returns an error if the reader fails, the format of the data cannot be
+  /// detected, or its contents cannot be parsed.
+  #[cfg(feature = "std")]
+  pub fn load_auto_reader(reader: impl std::io::Read) -> Result<Composition, AmmError> {
+    Self::load_auto_bytes(&read_all(reader)?)
+  }
+
   /// Loads a composition from a file at the specified `path`.
   ///
   /// # Errors
-  /// TODO
-  pub fn load(&self, path: &str) -> Result<Composition, String> {
+  /// Returns an error if the file cannot be read or its contents cannot be
+  /// parsed. Errors raised while deserializing AMM documents carry the path
+  /// and byte offset of the value that failed.
+  #[cfg(feature = "std")]
+  pub fn load(&self, path: &str) -> Result<Composition, AmmError> {
+    let data = std::fs::read(path).map_err(io_error)?;
+    self.load_bytes(&data)
+  }
+
+  /// Loads a composition from the given raw `data`.
+  ///
+  /// # Errors
+  /// Returns an error if the contents of the data cannot be parsed. Errors
+  /// raised while deserializing AMM documents carry the path and byte offset
+  /// of the value that failed.
+  pub fn load_data(&self, data: Vec<u8>) -> Result<Composition, AmmError> {
+    self.load_bytes(&data)
+  }
+
+  /// Loads a composition from the given byte slice.
+  ///
+  /// # Errors
+  /// Returns an error if the contents of the data cannot be parsed. Errors
+  /// raised while deserializing AMM documents carry the path and byte offset
+  /// of the value that failed.
+  pub fn load_bytes(&self, data: &[u8]) -> Result<Composition, AmmError> {
     match self {
-      Self::AMM => AmmStorage::load(path),
-      Self::MusicXML => MusicXmlConverter::load(path),
-      Self::MIDI => MidiConverter::load(path),
+      Self::AMM => AmmStorage::load_bytes(data),
+      Self::AMMBinary => AmmBinaryStorage::load_bytes(data),
+      Self::MusicXML => MusicXmlConverter::load_bytes(data),
+      Self::MIDI => MidiConverter::load_bytes(data),
+      Self::MuseScore => MuseScoreConverter::load_bytes(data),
+      Self::ABC => AbcConverter::load_bytes(data),
+      Self::MEI => MeiConverter::load_bytes(data),
+      Self::Kern => KernConverter::load_bytes(data),
+      Self::MNX => MnxConverter::load_bytes(data),
+      Self::LilyPond { .. } => Err(AmmError::new(
+        AmmErrorKind::UnsupportedFormat,
+        "Cannot import from LilyPond",
+      )),
+      Self::Custom(name) => custom::find(name)?.load_bytes(data),
     }
   }
 
-  /// TODO
+  /// Loads a composition from everything remaining in `reader`.
+  ///
+  /// # Errors
+  /// Returns an error if the reader fails or the contents of the data cannot
+  /// be parsed.
+  #[cfg(feature = "std")]
+  pub fn load_reader(&self, reader: impl std::io::Read) -> Result<Composition, AmmError> {
+    self.load_bytes(&read_all(reader)?)
+  }
+
+  /// Saves a composition to a file at the specified `path`, returning the
+  /// number of bytes written.
+  ///
+  /// # Errors
+  /// Returns an error if the format does not support export or the file
+  /// cannot be written.
+  #[cfg(feature = "std")]
+  pub fn save(&self, path: &str, composition: &Composition) -> Result<usize, AmmError> {
+    self.save_with(path, composition, &JsonOptions::default())
+  }
+
+  /// Saves a composition to a file at the specified `path` with the given
+  /// JSON layout `options`, returning the number of bytes written.
+  ///
+  /// The options apply to the JSON-based AMM and MNX formats, which can be
+  /// pretty-printed for review or written in canonical form so that saving
+  /// equal compositions always produces identical files. All other formats
+  /// are saved exactly as by [`Storage::save`].
+  ///
+  /// # Errors
+  /// Returns an error if the format does not support export or the file
+  /// cannot be written.
+  #[cfg(feature = "std")]
+  pub fn save_with(&self, path: &str, composition: &Composition, options: &JsonOptions) -> Result<usize, AmmError> {
+    let data = self.save_bytes_with(composition, options)?;
+    std::fs::write(path, &data).map_err(io_error)?;
+    Ok(data.len())
+  }
+
+  /// Saves a composition into a new byte vector.
+  ///
+  /// # Errors
+  /// Returns an error if the format does not support export.
+  pub fn save_bytes(&self, composition: &Composition) -> Result<Vec<u8>, AmmError> {
+    self.save_bytes_with(composition, &JsonOptions::default())
+  }
+
+  /// Saves a composition into a new byte vector with the given JSON layout
+  /// `options`, which apply as described for [`Storage::save_with`].
   ///
   /// # Errors
-  /// TODO
-  pub fn load_data(&self, data: Vec<u8>) -> Result<Composition, String> {
+  /// Returns an error if the format does not support export.
+  pub fn save_bytes_with(&self, composition: &Composition, options: &JsonOptions) -> Result<Vec<u8>, AmmError> {
     match self {
-      Self::AMM => AmmStorage::load_data(data),
-      Self::MusicXML => MusicXmlConverter::load_data(data),
-      Self::MIDI => MidiConverter::load_data(data),
+      Self::AMM => Ok(AmmStorage::save_to_amm(composition, options).into_bytes()),
+      Self::AMMBinary => AmmBinaryStorage::save_bytes(composition),
+      Self::MusicXML => Err(AmmError::new(
+        AmmErrorKind::UnsupportedFormat,
+        "Cannot export to MusicXML",
+      )),
+      Self::MIDI => Err(AmmError::new(AmmErrorKind::UnsupportedFormat, "Cannot export to MIDI")),
+      Self::MuseScore => Err(AmmError::new(
+        AmmErrorKind::UnsupportedFormat,
+        "Cannot export to MuseScore",
+      )),
+      Self::ABC => AbcConverter::save_bytes(composition),
+      Self::MEI => MeiConverter::save_bytes(composition),
+      Self::Kern => KernConverter::save_bytes(composition),
+      Self::MNX => Ok(MnxConverter::save_to_mnx(composition, options).into_bytes()),
+      Self::LilyPond { relative } => Ok(LilyPondConverter::save_to_lilypond(composition, *relative).into_bytes()),
+      Self::Custom(name) => custom::find(name)?.save_bytes(composition),
     }
   }
 
-  /// TODO
+  /// Saves a composition into `writer`, returning the number of bytes written.
   ///
   /// # Errors
-  /// TODO
-  pub fn save(&self, path: &str, composition: &Composition) -> Result<usize, String> {
-    match self {
-      Self::AMM => AmmStorage::save(path, composition),
-      Self::MusicXML => Err(String::from("Cannot export to MusicXML")),
-      Self::MIDI => Err(String::from("Cannot export to MIDI")),
+  /// Returns an error if the format does not support export or writing fails.
+  #[cfg(feature = "std")]
+  pub fn save_writer(&self, writer: impl std::io::Write, composition: &Composition) -> Result<usize, AmmError> {
+    self.save_writer_with(writer, composition, &JsonOptions::default())
+  }
+
+  /// Saves a composition into `writer` with the given JSON layout `options`,
+  /// which apply as described for [`Storage::save_with`], returning the number
+  /// of bytes written.
+  ///
+  /// # Errors
+  /// Returns an error if the format does not support export or writing fails.
+  #[cfg(feature = "std")]
+  pub fn save_writer_with(
+    &self,
+    mut writer: impl std::io::Write,
+    composition: &Composition,
+    options: &JsonOptions,
+  ) -> Result<usize, AmmError> {
+    if *self == Self::AMM {
+      let mut out = IoWriter {
+        writer: &mut writer,
+        written: 0,
+        error: None,
+      };
+      if AmmStorage::write_amm(&mut out, composition, options).is_err() {
+        return Err(io_error(
+          out.error.unwrap_or_else(|| std::io::Error::other("Formatting failed")),
+        ));
+      }
+      let written = out.written;
+      writer.flush().map_err(io_error)?;
+      return Ok(written);
     }
+    let data = self.save_bytes_with(composition, options)?;
+    writer
+      .write_all(&data)
+      .and_then(|()| writer.flush())
+      .map_err(io_error)?;
+    Ok(data.len())
   }
 }
 
+/// Returns the [JSON Schema](https://json-schema.org) of native AMM documents
+/// as written by [`Storage::AMM`], laid out according to `options`.
+///
+/// The schema is generated from the composition model itself, so it always
+/// describes the documents written by this version of the SDK.
+#[must_use]
+pub fn amm_json_schema(options: &JsonOptions) -> String {
+  AmmStorage::json_schema().serialize_json_with(options)
+}
+
 impl core::fmt::Display for Storage {
   fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
     write!(
@@ -75,9 +527,219 @@ impl core::fmt::Display for Storage {
       "{}",
       match self {
         Self::AMM => "AMM (Abstract Music Manipulation)",
+        Self::AMMBinary => "AMM Binary (Abstract Music Manipulation Binary Encoding)",
         Self::MusicXML => "MusicXML (Music Extensible Markup Language)",
         Self::MIDI => "MIDI (Musical Instrument Digital Interface)",
+        Self::MuseScore => "MuseScore (MuseScore Native Score Format)",
+        Self::ABC => "ABC (ABC Music Notation)",
+        Self::LilyPond { .. } => "LilyPond (GNU LilyPond Music Engraving)",
+        Self::MEI => "MEI (Music Encoding Initiative)",
+        Self::Kern => "Humdrum **kern (Humdrum Kern Notation)",
+        Self::MNX => "MNX (W3C Music Notation JSON)",
+        Self::Custom(name) => name,
       }
     )
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use amm_internal::JsonSerializer;
+
+  #[test]
+  fn test_detect() {
+    let midi = std::fs::read("tests/test_midi_files/test-1.mid").unwrap();
+    assert_eq!(Storage::detect(&midi), Ok(Storage::MIDI));
+    let mxl = std::fs::read("examples/Hymn_to_Freedom.mxl").unwrap();
+    assert_eq!(Storage::detect(&mxl), Ok(Storage::MusicXML));
+    let musicxml = std::fs::read("examples/Telemann.musicxml").unwrap();
+    assert_eq!(Storage::detect(&musicxml), Ok(Storage::MusicXML));
+    let timewise = b"<?xml version=\"1.0\"?>\n<!-- Timewise -->\n<score-timewise version=\"4.0\"></score-timewise>";
+    assert_eq!(Storage::detect(timewise), Ok(Storage::MusicXML));
+    let mscz = std::fs::read("examples/Billie Jean.mscz").unwrap();
+    assert_eq!(Storage::detect(&mscz), Ok(Storage::MuseScore));
+    let mscx = b"<?xml version=\"1.0\"?>\n<museScore version=\"4.40\"><Score></Score></museScore>";
+    assert_eq!(Storage::detect(mscx), Ok(Storage::MuseScore));
+    let amm = Composition::new("Test", None, None, None).serialize_json();
+    assert_eq!(Storage::detect(amm.as_bytes()), Ok(Storage::AMM));
+    assert_eq!(Storage::detect(b"AMMB\x01\x00"), Ok(Storage::AMMBinary));
+    assert_eq!(Storage::detect(b"%abc-2.1\nX:1\nK:D\nDEFG|"), Ok(Storage::ABC));
+    assert_eq!(Storage::detect(b"X:1\nT:Tune\nK:G\nGABc|"), Ok(Storage::ABC));
+    let mei = b"<?xml version=\"1.0\"?>\n<mei xmlns=\"http://www.music-encoding.org/ns/mei\" meiversion=\"5.0\"/>";
+    assert_eq!(Storage::detect(mei), Ok(Storage::MEI));
+    let kern = b"!!!OTL: Tune\n**kern\t**kern\n*M4/4\t*M4/4\n1C\t1c\n*-\t*-\n";
+    assert_eq!(Storage::detect(kern), Ok(Storage::Kern));
+    let mnx = b"{\"mnx\": {\"version\": 1}, \"global\": {\"measures\": [{}]}, \"parts\": [{}]}";
+    assert_eq!(Storage::detect(mnx), Ok(Storage::MNX));
+    assert!(Storage::detect(b"<html><body></body></html>").is_err());
+    assert!(Storage::detect(b"{\"key\":\"value\"}").is_err());
+    assert!(Storage::detect(br#"{"name":"_type","child":{"_type":"Composition"}}"#).is_err());
+    assert!(Storage::detect(b"").is_err());
+    assert!(Storage::detect(&[0xff, 0x00, 0x13]).is_err());
+  }
+
+  fn stored_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let (mut archive, mut directory) = (Vec::new(), Vec::new());
+    for (name, contents) in files {
+      let (offset, size) = (archive.len() as u32, contents.len() as u32);
+      archive.extend_from_slice(b"PK\x03\x04");
+      archive.extend_from_slice(&[0; 14]);
+      archive.extend_from_slice(&size.to_le_bytes());
+      archive.extend_from_slice(&size.to_le_bytes());
+      archive.extend_from_slice(&(name.len() as u16).to_le_bytes());
+      archive.extend_from_slice(&[0; 2]);
+      archive.extend_from_slice(name.as_bytes());
+      archive.extend_from_slice(contents);
+      directory.extend_from_slice(b"PK\x01\x02");
+      directory.extend_from_slice(&[0; 16]);
+      directory.extend_from_slice(&size.to_le_bytes());
+      directory.extend_from_slice(&size.to_le_bytes());
+      directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+      directory.extend_from_slice(&[0; 12]);
+      directory.extend_from_slice(&offset.to_le_bytes());
+      directory.extend_from_slice(name.as_bytes());
+    }
+    let (directory_offset, directory_size) = (archive.len() as u32, directory.len() as u32);
+    archive.append(&mut directory);
+    archive.extend_from_slice(b"PK\x05\x06");
+    archive.extend_from_slice(&[0; 4]);
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&directory_size.to_le_bytes());
+    archive.extend_from_slice(&directory_offset.to_le_bytes());
+    archive.extend_from_slice(&[0; 2]);
+    archive
+  }
+
+  #[test]
+  fn test_detect_archive() {
+    let container = b"<container><rootfiles><rootfile full-path=\"score.musicxml\"/></rootfiles></container>";
+    let mxl = stored_zip(&[
+      ("META-INF/container.xml", container),
+      ("score.musicxml", b"<score-partwise/>"),
+    ]);
+    assert_eq!(Storage::detect(&mxl), Ok(Storage::MusicXML));
+    let docx = stored_zip(&[
+      ("[Content_Types].xml", b"<Types/>"),
+      ("word/document.xml", b"<document/>"),
+    ]);
+    assert!(Storage::detect(&docx).is_err());
+    let epub = stored_zip(&[
+      (
+        "META-INF/container.xml",
+        b"<container><rootfiles><rootfile full-path=\"book.opf\"/></rootfiles></container>",
+      ),
+      ("book.opf", b"<package/>"),
+    ]);
+    assert!(Storage::detect(&epub).is_err());
+  }
+
+  #[test]
+  fn test_load_auto() {
+    let composition = Storage::load_auto("examples/Hymn_to_Freedom.mxl");
+    assert!(composition.is_ok());
+    assert_eq!(composition, Storage::MusicXML.load("examples/Hymn_to_Freedom.mxl"));
+  }
+
+  #[test]
+  fn test_memory_storage() {
+    let mxl = std::fs::read("examples/Hymn_to_Freedom.mxl").unwrap();
+    let composition = Storage::load_auto_reader(mxl.as_slice()).unwrap();
+    assert_eq!(Storage::MusicXML.load_bytes(&mxl).as_ref(), Ok(&composition));
+
+    for storage in [Storage::AMM, Storage::AMMBinary, Storage::MNX, Storage::Kern] {
+      let data = storage.save_bytes(&composition).unwrap();
+      let mut written = Vec::new();
+      assert_eq!(storage.save_writer(&mut written, &composition), Ok(data.len()));
+      assert_eq!(written, data);
+      assert_eq!(Storage::detect(&data), Ok(storage));
+      assert!(storage.load_reader(std::io::Cursor::new(&data)).is_ok());
+    }
+    let amm = Storage::AMM.save_bytes(&composition).unwrap();
+    assert_eq!(Storage::load_auto_bytes(&amm).as_ref(), Ok(&composition));
+    let options = JsonOptions::pretty(2);
+    let pretty = Storage::AMM.save_bytes_with(&composition, &options).unwrap();
+    assert!(pretty.len() > amm.len());
+    assert_eq!(Storage::AMM.load_bytes(&pretty).as_ref(), Ok(&composition));
+    let mut written = Vec::new();
+    assert_eq!(
+      Storage::AMM.save_writer_with(&mut written, &composition, &options),
+      Ok(pretty.len())
+    );
+    assert_eq!(written, pretty);
+    let mut full = [0; 16];
+    let error = Storage::AMM.save_writer(full.as_mut_slice(), &composition).unwrap_err();
+    assert_eq!(error.kind, AmmErrorKind::Io);
+    let lilypond = Storage::LilyPond { relative: true }.save_bytes(&composition).unwrap();
+    assert!(lilypond.starts_with(b"\\version"));
+    assert_eq!(
+      Storage::MIDI.save_bytes(&composition).unwrap_err().kind,
+      AmmErrorKind::UnsupportedFormat
+    );
+    let error = Storage::AMM.load_reader(FailingReader).unwrap_err();
+    assert_eq!(error.kind, AmmErrorKind::Io);
+  }
+
+  struct TitleFormat;
+
+  impl Load for TitleFormat {
+    fn load_bytes(data: &[u8]) -> Result<Composition, AmmError> {
+      let title = data
+        .strip_prefix(b"TITLE:")
+        .and_then(|title| core::str::from_utf8(title).ok())
+        .ok_or_else(|| AmmError::new(AmmErrorKind::Syntax, "Missing title"))?;
+      Ok(Composition::new(title, None, None, None))
+    }
+  }
+
+  impl Store for TitleFormat {
+    fn save_bytes(composition: &Composition) -> Result<Vec<u8>, AmmError> {
+      Ok(format!("TITLE:{}", composition.get_title()).into_bytes())
+    }
+  }
+
+  #[test]
+  fn test_custom_format() {
+    let format = StorageFormat::new("Title", &["title", "ttl"])
+      .with_detection(|data| data.starts_with(b"TITLE:"))
+      .with_load::<TitleFormat>()
+      .with_store::<TitleFormat>();
+    let storage = Storage::register(format).unwrap();
+    assert_eq!(storage, Storage::Custom("Title"));
+    assert_eq!(storage.to_string(), "Title");
+    assert!(Storage::register(format).is_err());
+    assert!(Storage::custom_formats().iter().any(|format| format.name() == "Title"));
+
+    let composition = Composition::new("Custom", None, None, None);
+    let data = storage.save_bytes(&composition).unwrap();
+    assert_eq!(data, b"TITLE:Custom");
+    assert_eq!(Storage::detect(&data), Ok(storage));
+    assert_eq!(Storage::load_auto_bytes(&data).unwrap().get_title(), "Custom");
+    assert_eq!(storage.load_bytes(b"Custom").unwrap_err().kind, AmmErrorKind::Syntax);
+    let amm = Storage::AMM.save_bytes(&composition).unwrap();
+    assert_eq!(Storage::detect(&amm), Ok(Storage::AMM));
+
+    assert_eq!(Storage::from_extension(".TTL"), Some(storage));
+    assert_eq!(Storage::from_extension("mxl"), Some(Storage::MusicXML));
+    assert_eq!(Storage::from_extension("unknown"), None);
+
+    let import_only = Storage::register(StorageFormat::new("Title Import", &[]).with_load::<TitleFormat>()).unwrap();
+    assert_eq!(
+      import_only.save_bytes(&composition).unwrap_err().kind,
+      AmmErrorKind::UnsupportedFormat
+    );
+    assert_eq!(
+      Storage::Custom("Missing").load_bytes(&data).unwrap_err().kind,
+      AmmErrorKind::UnsupportedFormat
+    );
+  }
+
+  struct FailingReader;
+
+  impl std::io::Read for FailingReader {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+      Err(std::io::Error::other("Connection reset"))
+    }
+  }
+}
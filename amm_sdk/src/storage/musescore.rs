@@ -0,0 +1,1608 @@
+use super::musicxml::MusicXmlConverter;
+use super::xml::{add_notation, parse_xml, xml_element, xml_text_element, XmlElementExt};
+use super::zip::ZipArchive;
+use super::{AmmError, Load};
+use crate::Composition;
+use alloc::{
+  collections::BTreeMap,
+  string::{String, ToString},
+  vec::Vec,
+};
+use musicxml::elements::ScorePartwise;
+use musicxml_internal::{bytes_to_string, ElementDeserializer, XmlElement};
+
+const MIN_SUPPORTED_VERSION: u32 = 3;
+const DEFAULT_DIVISIONS: usize = 480;
+const VOICES_PER_STAFF: usize = 4;
+const MAX_DOTS: u32 = 4;
+const MAX_DURATION_DIVISIONS: usize = 1 << 16;
+const TPC_STEPS: [&str; 7] = ["F", "C", "G", "D", "A", "E", "B"];
+const DEFAULT_TPC: [i32; 12] = [14, 21, 16, 11, 18, 13, 20, 15, 10, 17, 12, 19];
+const GRACE_ELEMENTS: [&str; 8] = [
+  "acciaccatura",
+  "appoggiatura",
+  "grace4",
+  "grace16",
+  "grace32",
+  "grace8after",
+  "grace16after",
+  "grace32after",
+];
+const DYNAMICS: [&str; 26] = [
+  "pppppp", "ppppp", "pppp", "ppp", "pp", "p", "mp", "mf", "f", "ff", "fff", "ffff", "fffff", "ffffff", "fp", "pf",
+  "sf", "sfp", "sfpp", "sfz", "sffz", "sfzp", "fz", "rf", "rfz", "n",
+];
+
+#[derive(Default)]
+struct VoiceState {
+  tuplets: Vec<(usize, usize, bool)>,
+  open_slurs: Vec<usize>,
+  pending_slur_starts: usize,
+  pending_slur_stops: usize,
+  pending_fermata: bool,
+  pending_trill: bool,
+  last_note_idx: Option<usize>,
+}
+
+#[derive(Default)]
+struct StaffState {
+  octave_shift: Option<(i32, u8)>,
+  open_wedges: Vec<usize>,
+  voices: [VoiceState; VOICES_PER_STAFF],
+}
+
+struct StaffContext {
+  number: usize,
+  is_first_in_part: bool,
+  divisions: usize,
+  measure_length: usize,
+}
+
+#[derive(Default)]
+struct MeasureBuilder {
+  elements: Vec<XmlElement>,
+  cursor: usize,
+  key: Option<XmlElement>,
+  time: Option<XmlElement>,
+  clefs: BTreeMap<usize, XmlElement>,
+}
+
+#[derive(Default)]
+struct MeasureDetails<'a> {
+  length: usize,
+  start_repeat: bool,
+  end_repeat: Option<usize>,
+  ending_start: Option<String>,
+  ending_stop: Option<(String, bool)>,
+  markers: Vec<&'a XmlElement>,
+  jumps: Vec<&'a XmlElement>,
+}
+
+struct PartDetails<'a> {
+  id: String,
+  name: String,
+  definition: &'a XmlElement,
+  staves: Vec<(&'a XmlElement, Vec<&'a XmlElement>)>,
+}
+
+pub struct MuseScoreConverter;
+
+impl MuseScoreConverter {
+  fn parse_fraction(text: &str) -> Option<(usize, usize)> {
+    let (numerator, denominator) = text.trim().split_once('/')?;
+    let (numerator, denominator) = (numerator.trim().parse().ok()?, denominator.trim().parse().ok()?);
+    if denominator == 0 {
+      None
+    } else {
+      Some((numerator, denominator))
+    }
+  }
+
+  fn fraction_to_divisions((numerator, denominator): (usize, usize), divisions: usize) -> Result<usize, String> {
+    4usize
+      .checked_mul(divisions)
+      .and_then(|total| total.checked_mul(numerator))
+      .and_then(|total| total.checked_add(denominator / 2))
+      .map(|total| total / denominator)
+      .filter(|total| *total <= MAX_DURATION_DIVISIONS)
+      .ok_or_else(|| format!("Duration of {numerator}/{denominator} is too long to be represented"))
+  }
+
+  fn duration_type_fraction(duration_type: &str) -> Option<(usize, usize)> {
+    match duration_type {
+      "long" => Some((4, 1)),
+      "breve" => Some((2, 1)),
+      "whole" => Some((1, 1)),
+      "half" => Some((1, 2)),
+      "quarter" => Some((1, 4)),
+      "eighth" => Some((1, 8)),
+      "16th" => Some((1, 16)),
+      "32nd" => Some((1, 32)),
+      "64th" => Some((1, 64)),
+      "128th" => Some((1, 128)),
+      "256th" => Some((1, 256)),
+      "512th" => Some((1, 512)),
+      "1024th" => Some((1, 1024)),
+      _ => None,
+    }
+  }
+
+  fn collect_text(element: &XmlElement) -> String {
+    let mut text = element.text.clone();
+    for child in element.elements.iter().filter(|child| child.name != "sym") {
+      let child_text = Self::collect_text(child);
+      if !child_text.is_empty() {
+        if !text.is_empty() {
+          text.push(' ');
+        }
+        text.push_str(&child_text);
+      }
+    }
+    text
+  }
+
+  fn contains_element(element: &XmlElement, name: &str) -> bool {
+    element
+      .elements
+      .iter()
+      .any(|child| child.name == name || Self::contains_element(child, name))
+  }
+
+  fn spell_midi_number(midi_number: i32, tpc: Option<i32>) -> (&'static str, i32, i32) {
+    let tpc = tpc.unwrap_or(DEFAULT_TPC[midi_number.rem_euclid(12) as usize]);
+    let alter = (tpc + 1).div_euclid(7) - 2;
+    (
+      TPC_STEPS[(tpc + 1).rem_euclid(7) as usize],
+      alter,
+      ((midi_number - alter).div_euclid(12) - 1).clamp(0, 9),
+    )
+  }
+
+  fn transcode_pitch(midi_number: i32, tpc: Option<i32>) -> XmlElement {
+    let (step, alter, octave) = Self::spell_midi_number(midi_number, tpc);
+    let mut elements = vec![xml_text_element("step", step)];
+    if alter != 0 {
+      elements.push(xml_text_element("alter", alter));
+    }
+    elements.push(xml_text_element("octave", octave));
+    xml_element("pitch", &[], elements)
+  }
+
+  fn transcode_accidental(subtype: &str) -> Option<&'static str> {
+    let subtype = subtype.to_ascii_lowercase();
+    if subtype.contains("doublesharp") || subtype.contains("sharpsharp") || subtype.ends_with("sharp2") {
+      Some("double-sharp")
+    } else if subtype.contains("doubleflat") || subtype.contains("flatflat") || subtype.ends_with("flat2") {
+      Some("flat-flat")
+    } else if subtype.contains("natural") && subtype.contains("sharp") {
+      Some("natural-sharp")
+    } else if subtype.contains("natural") && subtype.contains("flat") {
+      Some("natural-flat")
+    } else if subtype.contains("sharp") {
+      Some("sharp")
+    } else if subtype.contains("flat") {
+      Some("flat")
+    } else if subtype.contains("natural") {
+      Some("natural")
+    } else {
+      None
+    }
+  }
+
+  fn transcode_articulation(subtype: &str) -> Vec<(&'static str, &'static str)> {
+    let subtype = subtype
+      .strip_suffix("Above")
+      .or_else(|| subtype.strip_suffix("Below"))
+      .unwrap_or(subtype);
+    match subtype {
+      "articAccent" => vec![("articulations", "accent")],
+      "articStaccato" => vec![("articulations", "staccato")],
+      "articStaccatissimo" | "articStaccatissimoWedge" | "articStaccatissimoStroke" => {
+        vec![("articulations", "staccatissimo")]
+      }
+      "articTenuto" => vec![("articulations", "tenuto")],
+      "articTenutoStaccato" => vec![("articulations", "detached-legato")],
+      "articMarcato" => vec![("articulations", "strong-accent")],
+      "articAccentStaccato" => vec![("articulations", "accent"), ("articulations", "staccato")],
+      "articMarcatoStaccato" => vec![("articulations", "strong-accent"), ("articulations", "staccato")],
+      "articMarcatoTenuto" => vec![("articulations", "strong-accent"), ("articulations", "tenuto")],
+      "articTenutoAccent" => vec![("articulations", "tenuto"), ("articulations", "accent")],
+      "articSoftAccent" => vec![("articulations", "soft-accent")],
+      "articStress" => vec![("articulations", "stress")],
+      "articUnstress" => vec![("articulations", "unstress")],
+      "ornamentTrill" => vec![("ornaments", "trill-mark")],
+      "ornamentTurn" => vec![("ornaments", "turn")],
+      "ornamentTurnInverted" => vec![("ornaments", "inverted-turn")],
+      "ornamentMordent" => vec![("ornaments", "mordent")],
+      "ornamentShortTrill" | "ornamentMordentInverted" => vec![("ornaments", "inverted-mordent")],
+      "ornamentHaydn" => vec![("ornaments", "haydn")],
+      "ornamentShake" | "ornamentShake3" | "ornamentShakeMuffat1" => vec![("ornaments", "shake")],
+      "stringsUpBow" => vec![("technical", "up-bow")],
+      "stringsDownBow" => vec![("technical", "down-bow")],
+      "stringsHarmonic" => vec![("technical", "harmonic")],
+      "stringsThumbPosition" => vec![("technical", "thumb-position")],
+      "pluckedSnapPizzicato" => vec![("technical", "snap-pizzicato")],
+      "brassMuteClosed" => vec![("technical", "stopped")],
+      "brassMuteOpen" => vec![("technical", "open")],
+      fermata if fermata.starts_with("fermata") => vec![("notations", "fermata")],
+      _ => Vec::new(),
+    }
+  }
+
+  fn transcode_clef(clef_type: &str, staff_number: usize) -> XmlElement {
+    let octave_change = if clef_type.contains("15ma") {
+      2
+    } else if clef_type.contains("8va") {
+      1
+    } else if clef_type.contains("15mb") {
+      -2
+    } else if clef_type.contains("8vb") {
+      -1
+    } else {
+      0
+    };
+    let (sign, line) = match clef_type.chars().next() {
+      Some('G') if clef_type.starts_with("G1") => ("G", Some(1)),
+      Some('G') => ("G", Some(2)),
+      Some('F') if clef_type.starts_with("F3") || clef_type.starts_with("F_B") => ("F", Some(3)),
+      Some('F') if clef_type.starts_with("F5") || clef_type.starts_with("F_C") => ("F", Some(5)),
+      Some('F') => ("F", Some(4)),
+      Some('C') => (
+        "C",
+        Some(
+          clef_type
+            .chars()
+            .nth(1)
+            .and_then(|line| line.to_digit(10))
+            .filter(|line| (1..=5).contains(line))
+            .unwrap_or(3),
+        ),
+      ),
+      Some('T') => ("TAB", Some(5)),
+      Some('P') => ("percussion", None),
+      _ => ("G", Some(2)),
+    };
+    let mut elements = vec![xml_text_element("sign", sign)];
+    if let Some(line) = line {
+      elements.push(xml_text_element("line", line));
+    }
+    if octave_change != 0 {
+      elements.push(xml_text_element("clef-octave-change", octave_change));
+    }
+    xml_element("clef", &[("number", &staff_number.to_string())], elements)
+  }
+
+  fn find_initial_clef(part: &XmlElement, staff_definition: &XmlElement, staff_number: usize) -> String {
+    let instrument = part.child("Instrument");
+    match staff_definition
+      .child("StaffType")
+      .and_then(|staff_type| staff_type.attribute("group"))
+    {
+      Some("tablature") => String::from("TAB"),
+      Some("percussion") => String::from("PERC"),
+      _ => staff_definition
+        .child_text("defaultConcertClef")
+        .or_else(|| staff_definition.child_text("defaultClef"))
+        .or_else(|| {
+          instrument.and_then(|instrument| {
+            instrument
+              .elements
+              .iter()
+              .find(|element| {
+                element.name == "clef"
+                  && element.attribute("staff").map_or(Some(1), |staff| staff.parse().ok()) == Some(staff_number)
+              })
+              .map(|clef| clef.text.as_str())
+          })
+        })
+        .or_else(|| instrument.and_then(|instrument| instrument.child_text("concertClef")))
+        .map_or_else(|| String::from("G"), String::from),
+    }
+  }
+
+  fn transcode_staff_tuning(part: &XmlElement, staff_number: usize) -> Option<XmlElement> {
+    let strings = part
+      .child("Instrument")?
+      .child("StringData")?
+      .elements
+      .iter()
+      .filter(|element| element.name == "string")
+      .filter_map(|string| string.text.parse::<i32>().ok())
+      .collect::<Vec<_>>();
+    if strings.is_empty() {
+      return None;
+    }
+    let mut elements = vec![xml_text_element("staff-lines", strings.len())];
+    for (idx, midi_number) in strings.into_iter().enumerate() {
+      let (step, alter, octave) = Self::spell_midi_number(midi_number, None);
+      let mut tuning = vec![xml_text_element("tuning-step", step)];
+      if alter != 0 {
+        tuning.push(xml_text_element("tuning-alter", alter));
+      }
+      tuning.push(xml_text_element("tuning-octave", octave));
+      elements.push(xml_element("staff-tuning", &[("line", &(idx + 1).to_string())], tuning));
+    }
+    Some(xml_element(
+      "staff-details",
+      &[("number", &staff_number.to_string())],
+      elements,
+    ))
+  }
+
+  fn direction(
+    direction_types: Vec<XmlElement>,
+    sound: Option<XmlElement>,
+    staff_number: usize,
+    attributes: &[(&str, &str)],
+  ) -> XmlElement {
+    let mut elements = direction_types
+      .into_iter()
+      .map(|direction_type| xml_element("direction-type", &[], vec![direction_type]))
+      .collect::<Vec<_>>();
+    elements.push(xml_text_element("staff", staff_number));
+    elements.extend(sound);
+    xml_element("direction", attributes, elements)
+  }
+
+  fn transcode_text(element: &XmlElement, italic: bool, staff_number: usize) -> Option<XmlElement> {
+    let text = element.child("text").map(Self::collect_text).unwrap_or_default();
+    if text.is_empty() {
+      None
+    } else {
+      let italic = italic
+        || element
+          .child("text")
+          .is_some_and(|text| Self::contains_element(text, "i"));
+      let words = XmlElement {
+        text,
+        ..xml_element(
+          "words",
+          if italic { &[("font-style", "italic")] } else { &[] },
+          Vec::new(),
+        )
+      };
+      Some(Self::direction(vec![words], None, staff_number, &[]))
+    }
+  }
+
+  fn transcode_marker(marker: &XmlElement) -> Option<(bool, XmlElement)> {
+    let subtype = marker.child_text("subtype").unwrap_or_default();
+    let text = marker.child("text").map(Self::collect_text).unwrap_or_default();
+    let words = |default: &str| xml_text_element("words", if text.is_empty() { default } else { &text });
+    match subtype {
+      "segno" | "varsegno" => Some((
+        true,
+        Self::direction(
+          vec![xml_element("segno", &[], Vec::new())],
+          Some(xml_element("sound", &[("segno", "segno")], Vec::new())),
+          1,
+          &[],
+        ),
+      )),
+      "coda" | "varcoda" | "codab" => Some((
+        true,
+        Self::direction(
+          vec![xml_element("coda", &[], Vec::new())],
+          Some(xml_element("sound", &[("coda", "coda")], Vec::new())),
+          1,
+          &[],
+        ),
+      )),
+      "fine" => Some((
+        false,
+        Self::direction(
+          vec![words("Fine")],
+          Some(xml_element("sound", &[("fine", "yes")], Vec::new())),
+          1,
+          &[],
+        ),
+      )),
+      to_coda if to_coda.starts_with("toCoda") || to_coda.starts_with("da_coda") => Some((
+        false,
+        Self::direction(
+          vec![words("To Coda")],
+          Some(xml_element("sound", &[("tocoda", "coda")], Vec::new())),
+          1,
+          &[],
+        ),
+      )),
+      _ => None,
+    }
+  }
+
+  fn transcode_jump(jump: &XmlElement) -> XmlElement {
+    let text = jump.child("text").map(Self::collect_text).unwrap_or_default();
+    let (default_text, sound) = if jump.child_text("jumpTo") == Some("start") {
+      ("D.C.", ("dacapo", "yes"))
+    } else {
+      ("D.S.", ("dalsegno", "segno"))
+    };
+    Self::direction(
+      vec![xml_text_element(
+        "words",
+        if text.is_empty() { default_text } else { &text },
+      )],
+      Some(xml_element("sound", &[sound], Vec::new())),
+      1,
+      &[],
+    )
+  }
+
+  fn transcode_volta_endings(volta: &XmlElement) -> Result<String, String> {
+    let numbers = volta
+      .child_text("endings")
+      .or_else(|| volta.child_text("beginText"))
+      .unwrap_or_default()
+      .split(|ch: char| !ch.is_ascii_digit())
+      .filter(|number| !number.is_empty())
+      .map(|number| {
+        number
+          .parse::<u8>()
+          .ok()
+          .filter(|number| *number > 0)
+          .ok_or_else(|| format!("Invalid volta ending number '{number}'"))
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(if numbers.is_empty() {
+      String::from("1")
+    } else {
+      numbers.iter().map(ToString::to_string).collect::<Vec<_>>().join(",")
+    })
+  }
+
+  fn parse_repeat_times(times: &str) -> Result<usize, String> {
+    // Repeats are played at most as many times as an ending number can address
+    match times.trim().parse::<usize>() {
+      Ok(times) if times > 0 => Ok(times.min(usize::from(u8::MAX))),
+      _ => Err(format!("Invalid repeat count '{times}'")),
+    }
+  }
+
+  fn gather_measure_details<'a>(
+    measures: &[&'a XmlElement],
+    divisions: usize,
+  ) -> Result<Vec<MeasureDetails<'a>>, String> {
+    let mut details = (0..measures.len())
+      .map(|_| MeasureDetails::default())
+      .collect::<Vec<_>>();
+    let mut time_signature = (4, 4);
+    for (idx, measure) in measures.iter().enumerate() {
+      details[idx].start_repeat = measure.has_child("startRepeat");
+      details[idx].end_repeat = measure
+        .child_text("endRepeat")
+        .map(Self::parse_repeat_times)
+        .transpose()?;
+      let measure_items = measure.elements.iter().chain(
+        measure
+          .elements
+          .iter()
+          .filter(|element| element.name == "voice")
+          .flat_map(|voice| voice.elements.iter()),
+      );
+      for item in measure_items {
+        match item.name.as_str() {
+          "TimeSig" => {
+            if let (Some(numerator), Some(denominator)) = (
+              item.child_text("sigN").and_then(|value| value.parse().ok()),
+              item.child_text("sigD").and_then(|value| value.parse().ok()),
+            ) {
+              if denominator > 0 {
+                time_signature = (numerator, denominator);
+              }
+            }
+          }
+          "Marker" => details[idx].markers.push(item),
+          "Jump" => details[idx].jumps.push(item),
+          "Spanner" if item.attribute("type") == Some("Volta") => {
+            if let (Some(volta), Some(next)) = (item.child("Volta"), item.child("next")) {
+              let endings = Self::transcode_volta_endings(volta)?;
+              let num_measures = next
+                .child("location")
+                .and_then(|location| location.child_text("measures"))
+                .and_then(|num_measures| num_measures.parse::<usize>().ok())
+                .unwrap_or(1)
+                .max(1);
+              let end_idx = (idx + num_measures - 1).min(measures.len() - 1);
+              details[end_idx].ending_stop = Some((endings.clone(), volta.child_text("endHookType") == Some("0")));
+              details[idx].ending_start = Some(endings);
+            }
+          }
+          _ => (),
+        }
+      }
+      details[idx].length = Self::fraction_to_divisions(
+        measure
+          .attribute("len")
+          .and_then(Self::parse_fraction)
+          .unwrap_or(time_signature),
+        divisions,
+      )?;
+    }
+    Ok(details)
+  }
+
+  fn transcode_chord_rest(
+    element: &XmlElement,
+    staff: &StaffContext,
+    voice_number: usize,
+    state: &mut StaffState,
+    voice_idx: usize,
+    measure: &mut MeasureBuilder,
+  ) -> Result<(), String> {
+    // Determine the timing details of the chord or rest
+    let grace = GRACE_ELEMENTS.iter().find(|grace| element.has_child(grace));
+    let duration_type = element.child_text("durationType").unwrap_or("quarter");
+    let num_dots = element
+      .child_text("dots")
+      .and_then(|dots| dots.parse::<u32>().ok())
+      .unwrap_or(0)
+      .min(MAX_DOTS);
+    let voice = &mut state.voices[voice_idx];
+    let (normal_notes, actual_notes) = voice
+      .tuplets
+      .iter()
+      .try_fold((1usize, 1usize), |(normal, actual), tuplet| {
+        Some((normal.checked_mul(tuplet.0)?, actual.checked_mul(tuplet.1)?))
+      })
+      .ok_or("Nested tuplet ratios are too large to be represented")?;
+    let (note_type, divisions) = if let Some((numerator, denominator)) = Self::duration_type_fraction(duration_type) {
+      let fraction = numerator
+        .checked_mul((1 << (num_dots + 1)) - 1)
+        .and_then(|numerator| numerator.checked_mul(normal_notes))
+        .zip(
+          denominator
+            .checked_mul(1 << num_dots)
+            .and_then(|denominator| denominator.checked_mul(actual_notes)),
+        )
+        .ok_or("Tuplet note durations are too short to be represented")?;
+      (
+        Some(duration_type),
+        Self::fraction_to_divisions(fraction, staff.divisions)?,
+      )
+    } else {
+      (
+        None,
+        match element.child_text("duration").and_then(Self::parse_fraction) {
+          Some(fraction) => Self::fraction_to_divisions(fraction, staff.divisions)?,
+          None => staff.measure_length,
+        },
+      )
+    };
+    if element.name == "Rest" && element.child_text("visible") == Some("0") {
+      measure.elements.push(xml_element(
+        "forward",
+        &[],
+        vec![
+          xml_text_element("duration", divisions),
+          xml_text_element("voice", voice_number),
+          xml_text_element("staff", staff.number),
+        ],
+      ));
+      measure.cursor += divisions;
+      return Ok(());
+    }
+
+    // Gather all notations which apply to the chord as a whole
+    let mut chord_notations = Vec::new();
+    for _ in 0..voice.pending_slur_stops {
+      if let Some(number) = voice.open_slurs.pop() {
+        chord_notations.push((
+          None,
+          xml_element("slur", &[("type", "stop"), ("number", &number.to_string())], Vec::new()),
+        ));
+      }
+    }
+    for _ in 0..voice.pending_slur_starts {
+      let number = voice.open_slurs.iter().max().map_or(1, |number| number + 1);
+      voice.open_slurs.push(number);
+      chord_notations.push((
+        None,
+        xml_element(
+          "slur",
+          &[("type", "start"), ("number", &number.to_string())],
+          Vec::new(),
+        ),
+      ));
+    }
+    (voice.pending_slur_starts, voice.pending_slur_stops) = (0, 0);
+    for (idx, tuplet) in voice.tuplets.iter_mut().enumerate() {
+      if !tuplet.2 {
+        tuplet.2 = true;
+        chord_notations.push((
+          None,
+          xml_element(
+            "tuplet",
+            &[("type", "start"), ("number", &(idx + 1).to_string())],
+            Vec::new(),
+          ),
+        ));
+      }
+    }
+    if voice.pending_fermata {
+      voice.pending_fermata = false;
+      chord_notations.push((None, xml_element("fermata", &[], Vec::new())));
+    }
+    if voice.pending_trill {
+      voice.pending_trill = false;
+      chord_notations.push((Some("ornaments"), xml_element("trill-mark", &[], Vec::new())));
+    }
+    for child in &element.elements {
+      match child.name.as_str() {
+        "Articulation" | "Ornament" => {
+          for (group, name) in Self::transcode_articulation(child.child_text("subtype").unwrap_or_default()) {
+            chord_notations.push((
+              Some(group).filter(|group| *group != "notations"),
+              xml_element(name, &[], Vec::new()),
+            ));
+          }
+        }
+        "Tremolo" | "TremoloSingleChord" => {
+          let marks = match child.child_text("subtype") {
+            Some("r8") => 1,
+            Some("r16") => 2,
+            Some("r32") => 3,
+            Some("r64") => 4,
+            _ => 0,
+          };
+          if marks > 0 {
+            chord_notations.push((
+              Some("ornaments"),
+              XmlElement {
+                text: marks.to_string(),
+                ..xml_element("tremolo", &[("type", "single")], Vec::new())
+              },
+            ));
+          }
+        }
+        _ => (),
+      }
+    }
+    let lyrics = element
+      .elements
+      .iter()
+      .filter(|child| child.name == "Lyrics")
+      .map(|lyric| {
+        let number = lyric
+          .child_text("no")
+          .and_then(|number| number.parse::<usize>().ok())
+          .unwrap_or(0)
+          + 1;
+        xml_element(
+          "lyric",
+          &[("number", &number.to_string())],
+          vec![
+            xml_text_element("syllabic", lyric.child_text("syllabic").unwrap_or("single")),
+            xml_text_element("text", lyric.child("text").map(Self::collect_text).unwrap_or_default()),
+          ],
+        )
+      })
+      .collect::<Vec<_>>();
+
+    // Transcode each note in the chord, or the rest itself
+    let notes = element
+      .elements
+      .iter()
+      .filter(|child| child.name == "Note")
+      .map(Some)
+      .collect::<Vec<_>>();
+    let first_note_idx = measure.elements.len();
+    let notes = if notes.is_empty() { vec![None] } else { notes };
+    for (idx, note) in notes.into_iter().enumerate() {
+      let mut contents = Vec::new();
+      if let Some(grace) = grace {
+        contents.push(xml_element(
+          "grace",
+          if *grace == "acciaccatura" {
+            &[("slash", "yes")]
+          } else {
+            &[]
+          },
+          Vec::new(),
+        ));
+      }
+      if idx > 0 {
+        contents.push(xml_element("chord", &[], Vec::new()));
+      }
+      let (mut tie_start, mut tie_stop) = (false, false);
+      if let Some(note) = note {
+        let midi_number = note
+          .child_text("pitch")
+          .and_then(|pitch| pitch.parse().ok())
+          .unwrap_or(60);
+        let tpc = note.child_text("tpc").and_then(|tpc| tpc.parse().ok());
+        let octave_shift = state.octave_shift.map_or(0, |(semitones, _)| semitones);
+        contents.push(Self::transcode_pitch(midi_number - octave_shift, tpc));
+        tie_start = note.has_child("Tie");
+        tie_stop = note.has_child("endSpanner");
+        for spanner in note.elements.iter().filter(|child| child.name == "Spanner") {
+          if spanner.attribute("type") == Some("Tie") {
+            tie_start |= spanner.has_child("next");
+            tie_stop |= spanner.has_child("prev");
+          }
+        }
+      } else {
+        contents.push(xml_element(
+          "rest",
+          if note_type.is_none() {
+            &[("measure", "yes")]
+          } else {
+            &[]
+          },
+          Vec::new(),
+        ));
+      }
+      if grace.is_none() {
+        contents.push(xml_text_element("duration", divisions));
+      }
+      if tie_stop {
+        contents.push(xml_element("tie", &[("type", "stop")], Vec::new()));
+      }
+      if tie_start {
+        contents.push(xml_element("tie", &[("type", "start")], Vec::new()));
+      }
+      contents.push(xml_text_element("voice", voice_number));
+      if let Some(note_type) = note_type {
+        contents.push(xml_text_element("type", note_type));
+        for _ in 0..num_dots {
+          contents.push(xml_element("dot", &[], Vec::new()));
+        }
+      }
+      if let Some(accidental) = note
+        .and_then(|note| note.child("Accidental"))
+        .and_then(|accidental| accidental.child_text("subtype"))
+        .and_then(Self::transcode_accidental)
+      {
+        contents.push(xml_text_element("accidental", accidental));
+      }
+      if actual_notes != normal_notes {
+        contents.push(xml_element(
+          "time-modification",
+          &[],
+          vec![
+            xml_text_element("actual-notes", actual_notes),
+            xml_text_element("normal-notes", normal_notes),
+          ],
+        ));
+      }
+      contents.push(xml_text_element("staff", staff.number));
+      if idx == 0 {
+        contents.extend(lyrics.iter().map(|lyric| {
+          XmlElement {
+            name: lyric.name.clone(),
+            attributes: lyric.attributes.clone(),
+            elements: lyric
+              .elements
+              .iter()
+              .map(|element| xml_text_element(&element.name, &element.text))
+              .collect(),
+            text: String::new(),
+          }
+        }));
+      }
+      let mut note_element = xml_element("note", &[], contents);
+      if tie_stop {
+        add_notation(
+          &mut note_element,
+          None,
+          xml_element("tied", &[("type", "stop")], Vec::new()),
+        );
+      }
+      if tie_start {
+        add_notation(
+          &mut note_element,
+          None,
+          xml_element("tied", &[("type", "start")], Vec::new()),
+        );
+      }
+      if idx == 0 {
+        for (group, notation) in chord_notations.drain(..) {
+          add_notation(&mut note_element, group, notation);
+        }
+      }
+      if element.has_child("Arpeggio") {
+        add_notation(&mut note_element, None, xml_element("arpeggiate", &[], Vec::new()));
+      }
+      if let Some(note) = note {
+        if let Some(string) = note
+          .child_text("string")
+          .and_then(|string| string.parse::<usize>().ok())
+        {
+          add_notation(
+            &mut note_element,
+            Some("technical"),
+            xml_text_element("string", string + 1),
+          );
+        }
+        if let Some(fret) = note.child_text("fret") {
+          add_notation(&mut note_element, Some("technical"), xml_text_element("fret", fret));
+        }
+      }
+      measure.elements.push(note_element);
+    }
+    voice.last_note_idx = Some(first_note_idx);
+    if grace.is_none() {
+      measure.cursor += divisions;
+    }
+    Ok(())
+  }
+
+  fn transcode_spanner(
+    spanner: &XmlElement,
+    staff: &StaffContext,
+    state: &mut StaffState,
+    voice_idx: usize,
+    measure: &mut MeasureBuilder,
+  ) {
+    let (is_start, is_end) = (spanner.has_child("next"), spanner.has_child("prev"));
+    match spanner.attribute("type") {
+      Some("Slur") if is_start => state.voices[voice_idx].pending_slur_starts += 1,
+      Some("Slur") if is_end => state.voices[voice_idx].pending_slur_stops += 1,
+      Some("Trill") if is_start => state.voices[voice_idx].pending_trill = true,
+      Some("HairPin") if is_start => {
+        let number = state.open_wedges.iter().max().map_or(1, |number| number + 1);
+        let subtype = spanner
+          .child("HairPin")
+          .and_then(|hairpin| hairpin.child_text("subtype"))
+          .and_then(|subtype| subtype.parse::<u8>().ok())
+          .unwrap_or(0);
+        state.open_wedges.push(number);
+        measure.elements.push(Self::direction(
+          vec![xml_element(
+            "wedge",
+            &[
+              (
+                "type",
+                if subtype.is_multiple_of(2) {
+                  "crescendo"
+                } else {
+                  "diminuendo"
+                },
+              ),
+              ("number", &number.to_string()),
+            ],
+            Vec::new(),
+          )],
+          None,
+          staff.number,
+          &[("placement", "below")],
+        ));
+      }
+      Some("HairPin") if is_end => {
+        if let Some(number) = state.open_wedges.pop() {
+          measure.elements.push(Self::direction(
+            vec![xml_element(
+              "wedge",
+              &[("type", "stop"), ("number", &number.to_string())],
+              Vec::new(),
+            )],
+            None,
+            staff.number,
+            &[("placement", "below")],
+          ));
+        }
+      }
+      Some("Pedal") if is_start || is_end => measure.elements.push(Self::direction(
+        vec![xml_element(
+          "pedal",
+          &[("type", if is_start { "start" } else { "stop" }), ("line", "yes")],
+          Vec::new(),
+        )],
+        None,
+        staff.number,
+        &[("placement", "below")],
+      )),
+      Some("Ottava") if is_start => {
+        let (semitones, size) = match spanner
+          .child("Ottava")
+          .and_then(|ottava| ottava.child_text("subtype"))
+          .unwrap_or_default()
+        {
+          "8vb" | "1" => (-12, 8),
+          "15ma" | "2" => (24, 15),
+          "15mb" | "3" => (-24, 15),
+          "22ma" | "4" => (36, 22),
+          "22mb" | "5" => (-36, 22),
+          _ => (12, 8),
+        };
+        state.octave_shift = Some((semitones, size));
+        measure.elements.push(Self::direction(
+          vec![xml_element(
+            "octave-shift",
+            &[
+              ("type", if semitones > 0 { "down" } else { "up" }),
+              ("size", &size.to_string()),
+            ],
+            Vec::new(),
+          )],
+          None,
+          staff.number,
+          &[],
+        ));
+      }
+      Some("Ottava") if is_end => {
+        if let Some((_, size)) = state.octave_shift.take() {
+          measure.elements.push(Self::direction(
+            vec![xml_element(
+              "octave-shift",
+              &[("type", "stop"), ("size", &size.to_string())],
+              Vec::new(),
+            )],
+            None,
+            staff.number,
+            &[],
+          ));
+        }
+      }
+      _ => (),
+    }
+  }
+
+  fn transcode_voice(
+    voice: &XmlElement,
+    staff: &StaffContext,
+    state: &mut StaffState,
+    voice_idx: usize,
+    measure: &mut MeasureBuilder,
+  ) -> Result<(), String> {
+    let voice_number = (staff.number - 1) * VOICES_PER_STAFF + voice_idx + 1;
+    for element in &voice.elements {
+      match element.name.as_str() {
+        "Chord" | "Rest" => Self::transcode_chord_rest(element, staff, voice_number, state, voice_idx, measure)?,
+        "Tuplet" => {
+          let normal_notes = element
+            .child_text("normalNotes")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(1);
+          let actual_notes = element
+            .child_text("actualNotes")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(1);
+          if normal_notes > 0 && actual_notes > 0 {
+            state.voices[voice_idx]
+              .tuplets
+              .push((normal_notes, actual_notes, false));
+          }
+        }
+        "endTuplet" => {
+          let voice = &mut state.voices[voice_idx];
+          let number = voice.tuplets.len();
+          if let (Some((_, _, true)), Some(note_idx)) = (voice.tuplets.pop(), voice.last_note_idx) {
+            add_notation(
+              &mut measure.elements[note_idx],
+              None,
+              xml_element(
+                "tuplet",
+                &[("type", "stop"), ("number", &number.to_string())],
+                Vec::new(),
+              ),
+            );
+          }
+        }
+        "Breath" => {
+          if let Some(note_idx) = state.voices[voice_idx].last_note_idx {
+            add_notation(
+              &mut measure.elements[note_idx],
+              Some("articulations"),
+              xml_element("breath-mark", &[], Vec::new()),
+            );
+          }
+        }
+        "Fermata" => state.voices[voice_idx].pending_fermata = true,
+        "KeySig" if staff.is_first_in_part => {
+          let fifths = element
+            .child_text("concertKey")
+            .or_else(|| element.child_text("accidental"))
+            .unwrap_or("0");
+          let mut contents = vec![xml_text_element("fifths", fifths)];
+          if let Some(mode) = element
+            .child_text("mode")
+            .filter(|mode| *mode == "major" || *mode == "minor")
+          {
+            contents.push(xml_text_element("mode", mode));
+          }
+          let key = xml_element("key", &[], contents);
+          if measure.cursor == 0 {
+            measure.key = Some(key);
+          } else {
+            measure.elements.push(xml_element("attributes", &[], vec![key]));
+          }
+        }
+        "TimeSig" if staff.is_first_in_part => {
+          let time = xml_element(
+            "time",
+            &[],
+            vec![
+              xml_text_element("beats", element.child_text("sigN").unwrap_or("4")),
+              xml_text_element("beat-type", element.child_text("sigD").unwrap_or("4")),
+            ],
+          );
+          if measure.cursor == 0 {
+            measure.time = Some(time);
+          } else {
+            measure.elements.push(xml_element("attributes", &[], vec![time]));
+          }
+        }
+        "Clef" => {
+          if let Some(clef_type) = element
+            .child_text("concertClefType")
+            .or_else(|| element.child_text("subtype"))
+          {
+            let clef = Self::transcode_clef(clef_type, staff.number);
+            if measure.cursor == 0 {
+              measure.clefs.insert(staff.number, clef);
+            } else {
+              measure.elements.push(xml_element("attributes", &[], vec![clef]));
+            }
+          }
+        }
+        "Dynamic" => {
+          if let Some(dynamic) = element
+            .child_text("subtype")
+            .filter(|dynamic| DYNAMICS.contains(dynamic))
+          {
+            measure.elements.push(Self::direction(
+              vec![xml_element(
+                "dynamics",
+                &[],
+                vec![xml_element(dynamic, &[], Vec::new())],
+              )],
+              None,
+              staff.number,
+              &[("placement", "below")],
+            ));
+          }
+        }
+        "Tempo" => {
+          #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+          if let Some(bpm) = element
+            .child_text("tempo")
+            .and_then(|tempo| tempo.parse::<f64>().ok())
+            .map(|beats_per_second| (beats_per_second * 60.0).round() as u32)
+            .filter(|bpm| *bpm > 0)
+          {
+            measure.elements.push(Self::direction(
+              vec![xml_element(
+                "metronome",
+                &[],
+                vec![
+                  xml_text_element("beat-unit", "quarter"),
+                  xml_text_element("per-minute", bpm),
+                ],
+              )],
+              Some(xml_element("sound", &[("tempo", &bpm.to_string())], Vec::new())),
+              staff.number,
+              &[("placement", "above")],
+            ));
+          }
+        }
+        "RehearsalMark" => {
+          let mark = element.child("text").map(Self::collect_text).unwrap_or_default();
+          if !mark.is_empty() {
+            measure.elements.push(Self::direction(
+              vec![xml_text_element("rehearsal", mark)],
+              None,
+              staff.number,
+              &[("placement", "above")],
+            ));
+          }
+        }
+        "StaffText" | "SystemText" | "Expression" => {
+          if let Some(direction) = Self::transcode_text(element, element.name == "Expression", staff.number) {
+            measure.elements.push(direction);
+          }
+        }
+        "Spanner" => Self::transcode_spanner(element, staff, state, voice_idx, measure),
+        "location" => {
+          if let Some(divisions) = element
+            .child_text("fractions")
+            .and_then(Self::parse_fraction)
+            .map(|fraction| Self::fraction_to_divisions(fraction, staff.divisions))
+            .transpose()?
+            .filter(|divisions| *divisions > 0)
+          {
+            measure.elements.push(xml_element(
+              "forward",
+              &[],
+              vec![
+                xml_text_element("duration", divisions),
+                xml_text_element("voice", voice_number),
+                xml_text_element("staff", staff.number),
+              ],
+            ));
+            measure.cursor += divisions;
+          }
+        }
+        _ => (),
+      }
+    }
+    Ok(())
+  }
+
+  fn transcode_measure(
+    part: &PartDetails,
+    measure_idx: usize,
+    details: &MeasureDetails,
+    divisions: usize,
+    staff_states: &mut [StaffState],
+  ) -> Result<XmlElement, String> {
+    // Add any initial context to the first measure and any left barline
+    let mut measure = MeasureBuilder::default();
+    if measure_idx == 0 {
+      for (idx, (staff_definition, _)) in part.staves.iter().enumerate() {
+        let clef = Self::find_initial_clef(part.definition, staff_definition, idx + 1);
+        measure.clefs.insert(idx + 1, Self::transcode_clef(&clef, idx + 1));
+      }
+    }
+    if details.start_repeat || details.ending_start.is_some() {
+      let mut barline = Vec::new();
+      if let Some(numbers) = &details.ending_start {
+        barline.push(xml_element(
+          "ending",
+          &[("number", numbers), ("type", "start")],
+          Vec::new(),
+        ));
+      }
+      if details.start_repeat {
+        barline.push(xml_element("repeat", &[("direction", "forward")], Vec::new()));
+      }
+      measure
+        .elements
+        .push(xml_element("barline", &[("location", "left")], barline));
+    }
+    let mut end_directions = details
+      .jumps
+      .iter()
+      .map(|jump| Self::transcode_jump(jump))
+      .collect::<Vec<_>>();
+    for (at_start, direction) in details
+      .markers
+      .iter()
+      .filter_map(|marker| Self::transcode_marker(marker))
+    {
+      if at_start {
+        measure.elements.push(direction);
+      } else {
+        end_directions.push(direction);
+      }
+    }
+
+    // Transcode all voices in all staves of the part
+    for (staff_idx, (_, measures)) in part.staves.iter().enumerate() {
+      let staff = StaffContext {
+        number: staff_idx + 1,
+        is_first_in_part: staff_idx == 0,
+        divisions,
+        measure_length: details.length,
+      };
+      let state = &mut staff_states[staff_idx];
+      for voice in state.voices.iter_mut() {
+        voice.last_note_idx = None;
+      }
+      if let Some(staff_measure) = measures.get(measure_idx) {
+        for (voice_idx, voice) in staff_measure
+          .elements
+          .iter()
+          .filter(|element| element.name == "voice")
+          .take(VOICES_PER_STAFF)
+          .enumerate()
+        {
+          if !voice.elements.is_empty() {
+            if measure.cursor > 0 {
+              measure.elements.push(xml_element(
+                "backup",
+                &[],
+                vec![xml_text_element("duration", measure.cursor)],
+              ));
+              measure.cursor = 0;
+            }
+            Self::transcode_voice(voice, &staff, state, voice_idx, &mut measure)?;
+          }
+        }
+      }
+    }
+
+    // Complete the measure and add any right barline
+    if measure.cursor < details.length {
+      measure.elements.push(xml_element(
+        "forward",
+        &[],
+        vec![xml_text_element("duration", details.length - measure.cursor)],
+      ));
+    }
+    measure.elements.append(&mut end_directions);
+    if details.end_repeat.is_some() || details.ending_stop.is_some() {
+      let mut barline = vec![xml_text_element("bar-style", "light-heavy")];
+      if let Some((numbers, discontinue)) = &details.ending_stop {
+        barline.push(xml_element(
+          "ending",
+          &[
+            ("number", numbers),
+            ("type", if *discontinue { "discontinue" } else { "stop" }),
+          ],
+          Vec::new(),
+        ));
+      }
+      if let Some(times) = details.end_repeat {
+        barline.push(xml_element(
+          "repeat",
+          &[("direction", "backward"), ("times", &times.to_string())],
+          Vec::new(),
+        ));
+      }
+      measure
+        .elements
+        .push(xml_element("barline", &[("location", "right")], barline));
+    }
+
+    // Insert all context changes occurring at the start of the measure
+    let mut attributes = Vec::new();
+    if measure_idx == 0 {
+      attributes.push(xml_text_element("divisions", divisions));
+    }
+    attributes.extend(measure.key.take());
+    attributes.extend(measure.time.take());
+    if measure_idx == 0 && part.staves.len() > 1 {
+      attributes.push(xml_text_element("staves", part.staves.len()));
+    }
+    attributes.extend(core::mem::take(&mut measure.clefs).into_values());
+    if measure_idx == 0 {
+      for (idx, (staff_definition, _)) in part.staves.iter().enumerate() {
+        if staff_definition
+          .child("StaffType")
+          .and_then(|staff_type| staff_type.attribute("group"))
+          == Some("tablature")
+        {
+          attributes.extend(Self::transcode_staff_tuning(part.definition, idx + 1));
+        }
+      }
+    }
+    if !attributes.is_empty() {
+      measure.elements.insert(0, xml_element("attributes", &[], attributes));
+    }
+    Ok(xml_element(
+      "measure",
+      &[("number", &(measure_idx + 1).to_string())],
+      measure.elements,
+    ))
+  }
+
+  fn transcode_metadata(score: &XmlElement, first_staff: Option<&XmlElement>) -> Vec<XmlElement> {
+    let meta_tags = score
+      .elements
+      .iter()
+      .filter(|element| element.name == "metaTag")
+      .filter_map(|tag| Some((tag.attribute("name")?, tag.text.as_str())))
+      .filter(|(_, value)| !value.is_empty())
+      .collect::<BTreeMap<_, _>>();
+    let title = meta_tags
+      .get("workTitle")
+      .map(|title| String::from(*title))
+      .or_else(|| {
+        first_staff?
+          .elements
+          .iter()
+          .filter(|element| element.name == "VBox")
+          .flat_map(|frame| frame.elements.iter())
+          .find(|text| {
+            text.name == "Text"
+              && text
+                .child_text("style")
+                .is_some_and(|style| style.eq_ignore_ascii_case("title"))
+          })
+          .and_then(|text| text.child("text"))
+          .map(Self::collect_text)
+          .filter(|title| !title.is_empty())
+      });
+    let mut metadata = Vec::new();
+    let mut work = Vec::new();
+    if let Some(work_number) = meta_tags.get("workNumber") {
+      work.push(xml_text_element("work-number", work_number));
+    }
+    if let Some(title) = title {
+      work.push(xml_text_element("work-title", title));
+    }
+    if !work.is_empty() {
+      metadata.push(xml_element("work", &[], work));
+    }
+    if let Some(movement_number) = meta_tags.get("movementNumber") {
+      metadata.push(xml_text_element("movement-number", movement_number));
+    }
+    if let Some(movement_title) = meta_tags.get("movementTitle") {
+      metadata.push(xml_text_element("movement-title", movement_title));
+    }
+    let mut identification = ["composer", "lyricist", "arranger"]
+      .iter()
+      .filter_map(|creator| {
+        meta_tags.get(creator).map(|name| XmlElement {
+          text: String::from(*name),
+          ..xml_element("creator", &[("type", creator)], Vec::new())
+        })
+      })
+      .collect::<Vec<_>>();
+    if let Some(copyright) = meta_tags.get("copyright") {
+      identification.push(xml_text_element("rights", copyright));
+    }
+    if !identification.is_empty() {
+      metadata.push(xml_element("identification", &[], identification));
+    }
+    metadata
+  }
+
+  fn transcode_score(score: &XmlElement) -> Result<XmlElement, String> {
+    // Locate the musical contents of every staff in the score
+    let divisions = score
+      .child_text("Division")
+      .and_then(|divisions| divisions.parse().ok())
+      .filter(|divisions| *divisions > 0)
+      .unwrap_or(DEFAULT_DIVISIONS);
+    let staff_contents = score
+      .elements
+      .iter()
+      .filter(|element| element.name == "Staff")
+      .filter_map(|staff| {
+        Some((
+          staff.attribute("id")?,
+          staff
+            .elements
+            .iter()
+            .filter(|element| element.name == "Measure")
+            .collect::<Vec<_>>(),
+        ))
+      })
+      .collect::<BTreeMap<_, _>>();
+
+    // Find all parts and their associated staves
+    let mut part_names: BTreeMap<String, usize> = BTreeMap::new();
+    let parts = score
+      .elements
+      .iter()
+      .filter(|element| element.name == "Part")
+      .filter_map(|part| {
+        let staves = part
+          .elements
+          .iter()
+          .filter(|element| element.name == "Staff")
+          .filter_map(|staff| Some((staff, staff_contents.get(staff.attribute("id")?)?.clone())))
+          .collect::<Vec<_>>();
+        if staves.iter().all(|(_, measures)| measures.is_empty()) {
+          return None;
+        }
+        let name = part
+          .child_text("trackName")
+          .filter(|name| !name.is_empty())
+          .or_else(|| {
+            part
+              .child("Instrument")
+              .and_then(|instrument| instrument.child_text("longName"))
+          })
+          .filter(|name| !name.is_empty())
+          .unwrap_or("Part");
+        let count = part_names.entry(String::from(name)).or_default();
+        *count += 1;
+        Some((name, *count, part, staves))
+      })
+      .collect::<Vec<_>>();
+    let parts = parts
+      .into_iter()
+      .enumerate()
+      .map(|(idx, (name, count, definition, staves))| PartDetails {
+        id: format!("P{}", idx + 1),
+        name: if count > 1 || part_names.get(name).is_some_and(|total| *total > 1) {
+          format!("{name} {count}")
+        } else {
+          String::from(name)
+        },
+        definition,
+        staves,
+      })
+      .collect::<Vec<_>>();
+    if parts.is_empty() {
+      return Err(String::from("No parts found in the MuseScore score"));
+    }
+
+    // Gather system-wide measure details from the topmost staff
+    let first_staff = score.elements.iter().find(|element| element.name == "Staff");
+    let measure_details = Self::gather_measure_details(
+      first_staff
+        .and_then(|staff| staff_contents.get(staff.attribute("id")?))
+        .map_or(&[], Vec::as_slice),
+      divisions,
+    )?;
+
+    // Transcode the score into an equivalent partwise MusicXML document
+    let mut contents = Self::transcode_metadata(score, first_staff);
+    contents.push(xml_element(
+      "part-list",
+      &[],
+      parts
+        .iter()
+        .map(|part| {
+          xml_element(
+            "score-part",
+            &[("id", &part.id)],
+            vec![xml_text_element("part-name", &part.name)],
+          )
+        })
+        .collect(),
+    ));
+    for part in &parts {
+      let mut staff_states = (0..part.staves.len())
+        .map(|_| StaffState::default())
+        .collect::<Vec<_>>();
+      let measures = measure_details
+        .iter()
+        .enumerate()
+        .map(|(idx, details)| Self::transcode_measure(part, idx, details, divisions, &mut staff_states))
+        .collect::<Result<_, _>>()?;
+      contents.push(xml_element("part", &[("id", &part.id)], measures));
+    }
+    Ok(xml_element("score-partwise", &[("version", "4.0")], contents))
+  }
+
+  fn extract_score_contents(data: &[u8]) -> Result<String, String> {
+    if data.starts_with(b"PK\x03\x04") {
+      let archive = ZipArchive::new(data)?;
+      let score_path = archive
+        .read_file("META-INF/container.xml")
+        .ok()
+        .and_then(|container| parse_xml(&bytes_to_string(&container).ok()?).ok())
+        .and_then(|container| {
+          container
+            .child("rootfiles")?
+            .elements
+            .iter()
+            .filter_map(|rootfile| rootfile.attribute("full-path"))
+            .find(|path| path.ends_with(".mscx"))
+            .map(String::from)
+        })
+        .or_else(|| {
+          archive
+            .file_names()
+            .find(|name| name.ends_with(".mscx") && !name.contains('/'))
+            .map(String::from)
+        })
+        .ok_or("Cannot find a MuseScore score in the compressed archive")?;
+      bytes_to_string(&archive.read_file(&score_path)?)
+    } else {
+      bytes_to_string(data)
+    }
+  }
+
+  fn load_from_musescore(data: &[u8]) -> Result<Composition, String> {
+    let root = parse_xml(&Self::extract_score_contents(data)?)?;
+    if root.name != "museScore" {
+      return Err(String::from("Data does not contain a MuseScore score"));
+    }
+    let version = root.attribute("version").unwrap_or_default();
+    if version
+      .split('.')
+      .next()
+      .and_then(|major| major.parse::<u32>().ok())
+      .is_none_or(|major| major < MIN_SUPPORTED_VERSION)
+    {
+      return Err(format!(
+        "Unsupported MuseScore file version '{version}'...only MuseScore 3 and later are supported"
+      ));
+    }
+    let score = root.child("Score").ok_or("Missing score contents in MuseScore file")?;
+    MusicXmlConverter::load_from_musicxml(&ScorePartwise::deserialize(&Self::transcode_score(score)?)?)
+  }
+}
+
+impl Load for MuseScoreConverter {
+  // MuseScore scores are transcoded into an equivalent MusicXML document which is
+  // then loaded by the MusicXML converter, so both formats share the same structure
+  // Note: lyrics are transcoded but then dropped, since the AMM model cannot yet represent them
+  fn load_bytes(data: &[u8]) -> Result<Composition, AmmError> {
+    MuseScoreConverter::load_from_musescore(data).map_err(AmmError::from)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::modification::{NoteModificationType, PhraseModificationType};
+  use crate::note::PitchName;
+  use crate::storage::Storage;
+
+  #[test]
+  fn test_invalid_repeats_and_endings() {
+    let mscx = |attributes: &str, contents: &str| {
+      format!(
+        r#"<museScore version="3.02"><Score><Part><Staff id="1"/><trackName>Flute</trackName></Part>
+          <Staff id="1"><Measure{attributes}>{contents}<voice><Rest><durationType>measure</durationType><duration>4/4</duration></Rest></voice></Measure></Staff>
+        </Score></museScore>"#
+      )
+    };
+    let volta = |endings: &str| {
+      format!(
+        r#"<voice><Spanner type="Volta"><Volta><endings>{endings}</endings></Volta><next><location><measures>1</measures></location></next></Spanner></voice>"#
+      )
+    };
+    assert!(MuseScoreConverter::load_bytes(mscx("", &volta("1, 2")).as_bytes()).is_ok());
+    assert!(MuseScoreConverter::load_bytes(mscx("", &volta("300")).as_bytes()).is_err());
+    assert!(MuseScoreConverter::load_bytes(mscx("", &volta("0")).as_bytes()).is_err());
+    assert!(MuseScoreConverter::load_bytes(mscx("", "<endRepeat>0</endRepeat>").as_bytes()).is_err());
+    assert!(MuseScoreConverter::load_bytes(mscx("", "<endRepeat>many</endRepeat>").as_bytes()).is_err());
+    assert!(MuseScoreConverter::load_bytes(mscx("", "<endRepeat>1000</endRepeat>").as_bytes()).is_ok());
+    assert!(MuseScoreConverter::load_bytes(mscx(r#" len="18446744073709551615/1""#, "").as_bytes()).is_err());
+  }
+
+  #[test]
+  fn test_load_musescore() {
+    let composition = Storage::MuseScore.load("examples/Billie Jean.mscz").unwrap();
+    let reference = Storage::MusicXML.load("examples/Billie Jean.mxl").unwrap();
+    assert_eq!(composition.get_title(), reference.get_title());
+    assert_eq!(composition.get_part_names(), ["Voice", "Piano", "Electric Bass"]);
+    assert_eq!(
+      composition.iter_timeslices().count(),
+      reference.iter_timeslices().count()
+    );
+    let mscx = r#"<museScore version="3.02"><Score><Division>480</Division>
+      <metaTag name="workTitle">Sketch</metaTag><metaTag name="composer">Anon</metaTag>
+      <Part><Staff id="1"><StaffType group="pitched"/></Staff><trackName>Flute</trackName></Part>
+      <Staff id="1">
+        <Measure><startRepeat/><voice><KeySig><accidental>-1</accidental></KeySig>
+          <TimeSig><sigN>2</sigN><sigD>4</sigD></TimeSig><Dynamic><subtype>mf</subtype></Dynamic>
+          <Spanner type="Volta"><Volta><endHookType>1</endHookType><endings>1, 2</endings></Volta>
+            <next><location><measures>1</measures></location></next></Spanner>
+          <Tuplet><normalNotes>2</normalNotes><actualNotes>3</actualNotes><baseNote>eighth</baseNote></Tuplet>
+          <Chord><durationType>eighth</durationType><Lyrics><text>la</text></Lyrics>
+            <Note><pitch>65</pitch><tpc>13</tpc></Note></Chord>
+          <Chord><durationType>eighth</durationType><Articulation><subtype>articStaccatoAbove</subtype></Articulation>
+            <Note><pitch>67</pitch><tpc>15</tpc></Note><Note><pitch>70</pitch><tpc>12</tpc></Note></Chord>
+          <Chord><durationType>eighth</durationType><Note><pitch>69</pitch><tpc>17</tpc></Note></Chord>
+          <endTuplet/><Rest><durationType>quarter</durationType></Rest></voice>
+          <voice><Rest><durationType>half</durationType></Rest></voice>
+          <endRepeat>2</endRepeat></Measure>
+        <Measure><voice><Rest><durationType>measure</durationType><duration>2/4</duration></Rest></voice></Measure>
+      </Staff></Score></museScore>"#;
+    let composition = MuseScoreConverter::load_bytes(mscx.as_bytes()).unwrap();
+    assert_eq!(composition.get_title(), "Sketch");
+    assert_eq!(composition.get_part_names(), ["Flute"]);
+    let timeslices = composition
+      .get_part_by_name("Flute")
+      .unwrap()
+      .iter_timeslices()
+      .collect::<Vec<_>>();
+    assert_eq!(timeslices[0].content.len(), 2);
+    assert!(timeslices[0].content.iter().any(|content| content.note.is_rest()));
+    let notes = timeslices
+      .iter()
+      .flat_map(|timeslice| timeslice.content.iter())
+      .filter(|content| !content.note.is_rest())
+      .take(4)
+      .collect::<Vec<_>>();
+    assert_eq!(
+      notes
+        .iter()
+        .map(|content| (content.note.pitch.name, content.note.pitch.octave))
+        .collect::<Vec<_>>(),
+      [
+        (PitchName::F, 4),
+        (PitchName::G, 4),
+        (PitchName::B, 4),
+        (PitchName::A, 4)
+      ]
+    );
+    assert!(notes.iter().all(|content| content
+      .phrase_details
+      .iter()
+      .any(
+        |details| details.modifications.contains(&PhraseModificationType::Tuplet {
+          num_beats: 3,
+          into_beats: 2
+        })
+      )));
+    assert!(notes[1..3].iter().all(|content| content
+      .note
+      .iter_modifications()
+      .any(|modification| modification.r#type == NoteModificationType::Staccato)));
+    assert!(!notes[0]
+      .note
+      .iter_modifications()
+      .any(|modification| modification.r#type == NoteModificationType::Staccato));
+    assert!(MuseScoreConverter::load_bytes(b"<score-partwise></score-partwise>").is_err());
+    assert!(MuseScoreConverter::load_bytes(b"<museScore version=\"2.06\"><Score/></museScore>").is_err());
+  }
+}
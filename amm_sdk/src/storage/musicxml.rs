@@ -1,8 +1,8 @@
-use super::Load;
+use super::{AmmError, Load};
 #[allow(clippy::wildcard_imports)]
 use crate::{context::*, modification::*, note::*, structure::*, Composition};
 use alloc::{
-  collections::BTreeMap,
+  collections::{BTreeMap, BTreeSet},
   string::{String, ToString},
   vec::Vec,
 };
@@ -53,6 +53,7 @@ struct NoteDetails {
   pub note_modifications: Vec<NoteModificationType>,
   pub phrase_modifications_start: Vec<PhraseModDetails>,
   pub phrase_modifications_end: Vec<PhraseModDetails>,
+  pub time_only: Option<Vec<u8>>,
 }
 
 #[cfg(feature = "print")]
@@ -106,6 +107,10 @@ impl core::fmt::Display for NoteDetails {
   }
 }
 
+type PhraseModIndices = BTreeSet<(usize, usize)>;
+
+const PASS_SPECIFIC_SECTION_NAME: &str = "Pass-Specific Section";
+
 fn section_generate_id() -> usize {
   static COUNTER: AtomicUsize = AtomicUsize::new(1);
   COUNTER.fetch_add(1, Ordering::Relaxed)
@@ -164,6 +169,11 @@ struct TimeSliceContainer {
   pub repeat: Vec<(bool, u8)>,
   pub tempo_change_explicit: Option<Tempo>,
   pub tempo_change_implicit: Option<TempoSuggestion>,
+  pub tempo_modification: Option<SectionModificationType>,
+  pub tempo_resume: bool,
+  pub tempo_primo: bool,
+  pub time_only: Vec<(Vec<u8>, TimeSliceContainer)>,
+  pub pass: Vec<(bool, Vec<u8>)>,
   pub notes: Vec<NoteDetails>,
 }
 
@@ -179,6 +189,11 @@ impl TimeSliceContainer {
       && self.repeat.is_empty()
       && self.tempo_change_explicit.is_none()
       && self.tempo_change_implicit.is_none()
+      && self.tempo_modification.is_none()
+      && !self.tempo_resume
+      && !self.tempo_primo
+      && self.time_only.is_empty()
+      && self.pass.is_empty()
       && self.notes.is_empty()
   }
 }
@@ -244,6 +259,38 @@ impl core::fmt::Display for TimeSliceContainer {
         .iter()
         .map(|item| format!("\"Tempo Change: {item}\"")),
     );
+    description.extend(
+      self
+        .tempo_modification
+        .iter()
+        .map(|item| format!("\"Tempo Modification: {item}\"")),
+    );
+    if self.tempo_resume {
+      description.push(String::from("\"Tempo Resume: A Tempo\""));
+    }
+    if self.tempo_primo {
+      description.push(String::from("\"Tempo Resume: Tempo Primo\""));
+    }
+    description.extend(self.time_only.iter().map(|(iterations, content)| {
+      format!(
+        "\"Only Play: [{}]\": [ {content} ]",
+        iterations
+          .iter()
+          .map(ToString::to_string)
+          .collect::<Vec<String>>()
+          .join(", ")
+      )
+    }));
+    description.extend(self.pass.iter().map(|(start, iterations)| {
+      format!(
+        "\"Pass-Specific: Start={start} Iterations=[{}]\"",
+        iterations
+          .iter()
+          .map(ToString::to_string)
+          .collect::<Vec<String>>()
+          .join(", ")
+      )
+    }));
     description.extend(self.notes.iter().map(|item| format!("\"{item}\"")));
     let desc = description.join(", ");
     write!(f, "{desc}")
@@ -494,6 +541,69 @@ impl MusicXmlConverter {
     vec![String::from("1")]
   }
 
+  fn parse_plucking_finger(text: &str) -> Option<PluckingFinger> {
+    match text.trim() {
+      "p" | "P" => Some(PluckingFinger::Thumb),
+      "i" | "I" => Some(PluckingFinger::Index),
+      "m" | "M" => Some(PluckingFinger::Middle),
+      "a" | "A" => Some(PluckingFinger::Ring),
+      "c" | "C" | "ch" | "x" => Some(PluckingFinger::Little),
+      _ => None,
+    }
+  }
+
+  #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+  fn find_tuning(part_elements: &Vec<musicxml::elements::PartElement>) -> Option<Tuning> {
+    for element in part_elements {
+      if let musicxml::elements::PartElement::Measure(measure) = element {
+        for measure_element in &measure.content {
+          if let musicxml::elements::MeasureElement::Attributes(attributes) = measure_element {
+            for staff_details in &attributes.content.staff_details {
+              if !staff_details.content.staff_tuning.is_empty() {
+                let mut strings = staff_details
+                  .content
+                  .staff_tuning
+                  .iter()
+                  .map(|staff_tuning| {
+                    let semitones = match staff_tuning.content.tuning_step.content {
+                      musicxml::datatypes::Step::C => 0,
+                      musicxml::datatypes::Step::D => 2,
+                      musicxml::datatypes::Step::E => 4,
+                      musicxml::datatypes::Step::F => 5,
+                      musicxml::datatypes::Step::G => 7,
+                      musicxml::datatypes::Step::A => 9,
+                      musicxml::datatypes::Step::B => 11,
+                    } + staff_tuning
+                      .content
+                      .tuning_alter
+                      .as_ref()
+                      .map_or(0, |alter| *alter.content)
+                      + 12 * (i16::from(*staff_tuning.content.tuning_octave.content) + 1);
+                    (*staff_tuning.attributes.line, semitones.clamp(0, 127) as u8)
+                  })
+                  .collect::<Vec<_>>();
+                strings.sort_by(|(line, _), (other_line, _)| other_line.cmp(line));
+                let capo = staff_details
+                  .content
+                  .capo
+                  .as_ref()
+                  .map_or(0, |capo| *capo.content as u8);
+                return Some(Tuning::new(
+                  &strings
+                    .into_iter()
+                    .map(|(_, midi_number)| midi_number)
+                    .collect::<Vec<_>>(),
+                  capo,
+                ));
+              }
+            }
+          }
+        }
+      }
+    }
+    None
+  }
+
   fn find_divisions_per_quarter_note(part_elements: &Vec<musicxml::elements::PartElement>) -> usize {
     for element in part_elements {
       if let musicxml::elements::PartElement::Measure(measure) = element {
@@ -736,6 +846,77 @@ impl MusicXmlConverter {
     *element.duration.content as isize
   }
 
+  fn parse_dynamics_type(dynamics: &musicxml::elements::DynamicsType) -> Option<Dynamic> {
+    match dynamics {
+      musicxml::elements::DynamicsType::P(_) => Some(Dynamic::Piano(1)),
+      musicxml::elements::DynamicsType::Pp(_) => Some(Dynamic::Piano(2)),
+      musicxml::elements::DynamicsType::Ppp(_) => Some(Dynamic::Piano(3)),
+      musicxml::elements::DynamicsType::Pppp(_) => Some(Dynamic::Piano(4)),
+      musicxml::elements::DynamicsType::Ppppp(_) => Some(Dynamic::Piano(5)),
+      musicxml::elements::DynamicsType::Pppppp(_) => Some(Dynamic::Piano(6)),
+      musicxml::elements::DynamicsType::F(_) => Some(Dynamic::Forte(1)),
+      musicxml::elements::DynamicsType::Ff(_) => Some(Dynamic::Forte(2)),
+      musicxml::elements::DynamicsType::Fff(_) => Some(Dynamic::Forte(3)),
+      musicxml::elements::DynamicsType::Ffff(_) => Some(Dynamic::Forte(4)),
+      musicxml::elements::DynamicsType::Fffff(_) => Some(Dynamic::Forte(5)),
+      musicxml::elements::DynamicsType::Ffffff(_) => Some(Dynamic::Forte(6)),
+      musicxml::elements::DynamicsType::Mp(_) => Some(Dynamic::MezzoPiano),
+      musicxml::elements::DynamicsType::Mf(_) => Some(Dynamic::MezzoForte),
+      musicxml::elements::DynamicsType::Sf(_) => Some(Dynamic::Sforzando(1)),
+      musicxml::elements::DynamicsType::Sfp(_) => Some(Dynamic::SforzandoPiano(1)),
+      musicxml::elements::DynamicsType::Sfpp(_) => Some(Dynamic::SforzandoPiano(2)),
+      musicxml::elements::DynamicsType::Fp(_) => Some(Dynamic::FortePiano),
+      musicxml::elements::DynamicsType::Rf(_) => Some(Dynamic::Rinforzando),
+      musicxml::elements::DynamicsType::Rfz(_) => Some(Dynamic::Rinforzato),
+      musicxml::elements::DynamicsType::Sfz(_) => Some(Dynamic::Sforzato(1)),
+      musicxml::elements::DynamicsType::Sffz(_) => Some(Dynamic::Sforzato(2)),
+      musicxml::elements::DynamicsType::Fz(_) => Some(Dynamic::Forzando),
+      musicxml::elements::DynamicsType::N(_) => Some(Dynamic::Niente),
+      musicxml::elements::DynamicsType::Pf(_) => Some(Dynamic::PianoForte),
+      musicxml::elements::DynamicsType::Sfzp(_) => Some(Dynamic::SforzatoPiano),
+      musicxml::elements::DynamicsType::OtherDynamics(other_dynamics) => Dynamic::from_text(&other_dynamics.content),
+    }
+  }
+
+  fn parse_dynamics_text(dynamics: &musicxml::elements::DynamicsType) -> Option<DirectionType> {
+    // Dynamics without an equivalent marking (e.g., "più f" or "pp sub.") are kept as expressive text
+    match dynamics {
+      musicxml::elements::DynamicsType::OtherDynamics(other_dynamics) if !other_dynamics.content.trim().is_empty() => {
+        Some(DirectionType::Expression {
+          text: other_dynamics.content.trim().to_string(),
+          style: TextStyle {
+            italic: true,
+            ..TextStyle::default()
+          },
+        })
+      }
+      _ => None,
+    }
+  }
+
+  #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+  fn parse_text_style(
+    font_family: Option<&musicxml::datatypes::FontFamily>,
+    font_size: Option<&musicxml::datatypes::FontSize>,
+    font_style: Option<&musicxml::datatypes::FontStyle>,
+    font_weight: Option<&musicxml::datatypes::FontWeight>,
+    placement: Option<&musicxml::datatypes::AboveBelow>,
+  ) -> TextStyle {
+    TextStyle {
+      font_family: font_family.and_then(|family| family.first().cloned()),
+      font_size: font_size.and_then(|size| match size {
+        musicxml::datatypes::FontSize::Decimal(points) if *points > 0.0 => Some(points.round().min(255.0) as u8),
+        _ => None,
+      }),
+      bold: font_weight == Some(&musicxml::datatypes::FontWeight::Bold),
+      italic: font_style == Some(&musicxml::datatypes::FontStyle::Italic),
+      placement: placement.map(|placement| match placement {
+        musicxml::datatypes::AboveBelow::Above => TextPlacement::Above,
+        musicxml::datatypes::AboveBelow::Below => TextPlacement::Below,
+      }),
+    }
+  }
+
   fn parse_direction_element(
     element: &musicxml::elements::Direction,
     time_slice: &mut BTreeMap<String, Vec<TimeSliceContainer>>,
@@ -754,6 +935,51 @@ impl MusicXmlConverter {
       .for_each(|item| match &item.content {
         musicxml::elements::DirectionTypeContents::Rehearsal(rehearsal) => {
           time_slice.get_mut(&staff_name).unwrap()[cursor].section_start = Some(rehearsal[0].content.clone());
+          let item = DirectionType::Rehearsal {
+            mark: rehearsal[0].content.trim().to_string(),
+            style: Self::parse_text_style(
+              rehearsal[0].attributes.font_family.as_ref(),
+              rehearsal[0].attributes.font_size.as_ref(),
+              rehearsal[0].attributes.font_style.as_ref(),
+              rehearsal[0].attributes.font_weight.as_ref(),
+              element.attributes.placement.as_ref(),
+            ),
+          };
+          time_slice.get_mut(&staff_name).unwrap()[cursor].direction.push(item);
+        }
+        musicxml::elements::DirectionTypeContents::Words(words) => {
+          let text = words
+            .iter()
+            .map(|words| words.content.as_str())
+            .collect::<String>()
+            .trim()
+            .to_string();
+          let slice = &mut time_slice.get_mut(&staff_name).unwrap()[cursor];
+          match SectionModificationType::from_text(&text) {
+            Some(SectionModificationType::TempoPrimo) => slice.tempo_primo = true,
+            Some(SectionModificationType::ATempo) => slice.tempo_resume = true,
+            Some(SectionModificationType::TempoImplicit { tempo }) => slice.tempo_change_implicit = Some(tempo),
+            Some(modification) => slice.tempo_modification = Some(modification),
+            None => (),
+          }
+
+          // The words are always kept since they may contain more than a tempo indication
+          if let Some(first) = words.first().filter(|_| !text.is_empty()) {
+            let style = Self::parse_text_style(
+              first.attributes.font_family.as_ref(),
+              first.attributes.font_size.as_ref(),
+              first.attributes.font_style.as_ref(),
+              first.attributes.font_weight.as_ref(),
+              element.attributes.placement.as_ref(),
+            );
+            // Expressive text is conventionally engraved in italics, unlike other free-form words
+            let item = if style.italic {
+              DirectionType::Expression { text, style }
+            } else {
+              DirectionType::Text { text, style }
+            };
+            slice.direction.push(item);
+          }
         }
         musicxml::elements::DirectionTypeContents::Segno(_segno) => {
           time_slice.get_mut(&staff_name).unwrap()[cursor].section_start = Some(String::from("Segno"));
@@ -816,36 +1042,19 @@ impl MusicXmlConverter {
           }
         }
         musicxml::elements::DirectionTypeContents::Dynamics(dynamics) => {
-          let mut chord_mod = false;
-          let dynamic_type = match &dynamics[0].content[0] {
-            musicxml::elements::DynamicsType::P(_) => Some(Dynamic::Piano(1)),
-            musicxml::elements::DynamicsType::Pp(_) => Some(Dynamic::Piano(2)),
-            musicxml::elements::DynamicsType::Ppp(_) => Some(Dynamic::Piano(3)),
-            musicxml::elements::DynamicsType::Pppp(_) => Some(Dynamic::Piano(4)),
-            musicxml::elements::DynamicsType::Ppppp(_) => Some(Dynamic::Piano(5)),
-            musicxml::elements::DynamicsType::Pppppp(_) => Some(Dynamic::Piano(6)),
-            musicxml::elements::DynamicsType::F(_) => Some(Dynamic::Forte(1)),
-            musicxml::elements::DynamicsType::Ff(_) => Some(Dynamic::Forte(2)),
-            musicxml::elements::DynamicsType::Fff(_) => Some(Dynamic::Forte(3)),
-            musicxml::elements::DynamicsType::Ffff(_) => Some(Dynamic::Forte(4)),
-            musicxml::elements::DynamicsType::Fffff(_) => Some(Dynamic::Forte(5)),
-            musicxml::elements::DynamicsType::Ffffff(_) => Some(Dynamic::Forte(6)),
-            musicxml::elements::DynamicsType::Mp(_) => Some(Dynamic::MezzoPiano),
-            musicxml::elements::DynamicsType::Mf(_) => Some(Dynamic::MezzoForte),
-            musicxml::elements::DynamicsType::N(_) | musicxml::elements::DynamicsType::OtherDynamics(_) => None,
-            _ => {
-              chord_mod = true;
-              None
+          let dynamics_type = dynamics.first().and_then(|dynamics| dynamics.content.first());
+          if let Some(dynamic) = dynamics_type.and_then(Self::parse_dynamics_type) {
+            if dynamic.is_accent() {
+              time_slice.get_mut(&staff_name).unwrap()[cursor]
+                .chord_modification
+                .push(ChordModificationType::Dynamic { dynamic });
+            } else {
+              time_slice.get_mut(&staff_name).unwrap()[cursor]
+                .direction
+                .push(DirectionType::Dynamic { dynamic });
             }
-          };
-          if let Some(dynamic_type) = dynamic_type {
-            time_slice.get_mut(&staff_name).unwrap()[cursor]
-              .direction
-              .push(DirectionType::Dynamic { dynamic: dynamic_type });
-          } else if chord_mod {
-            time_slice.get_mut(&staff_name).unwrap()[cursor]
-              .chord_modification
-              .push(ChordModificationType::Accent);
+          } else if let Some(item) = dynamics_type.and_then(Self::parse_dynamics_text) {
+            time_slice.get_mut(&staff_name).unwrap()[cursor].direction.push(item);
           }
         }
         musicxml::elements::DirectionTypeContents::Pedal(pedal) => match &pedal.attributes.r#type {
@@ -974,9 +1183,97 @@ impl MusicXmlConverter {
         }
         _ => (),
       });
+    let slice = &mut time_slice.get_mut(&staff_name).unwrap()[cursor];
+    if let Some(sound) = &element.content.sound {
+      Self::parse_sound_element(sound, &element.content.direction_type, slice);
+    }
+    if slice.tempo_change_explicit.is_some() {
+      slice.tempo_change_implicit = None;
+    }
     0
   }
 
+  #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+  fn parse_sound_element(
+    element: &musicxml::elements::Sound,
+    notated: &[musicxml::elements::DirectionType],
+    slice: &mut TimeSliceContainer,
+  ) {
+    // Sounds restricted to certain passes are stored separately until the enclosing measure is expanded
+    let mut pass_specific_slice = TimeSliceContainer::default();
+    let target = if element.attributes.time_only.is_some() {
+      &mut pass_specific_slice
+    } else {
+      &mut *slice
+    };
+    let is_notated = |condition: fn(&musicxml::elements::DirectionTypeContents) -> bool| {
+      notated.iter().any(|direction_type| condition(&direction_type.content))
+    };
+    if target.tempo_change_explicit.is_none() {
+      target.tempo_change_explicit = Self::parse_tempo_from_sound(element);
+    }
+    if let Some(dynamics) = &element.attributes.dynamics {
+      if !is_notated(|item| matches!(item, musicxml::elements::DirectionTypeContents::Dynamics(_))) {
+        target.direction.push(DirectionType::Dynamic {
+          dynamic: Self::parse_dynamics_from_sound(**dynamics),
+        });
+      }
+    }
+    if !is_notated(|item| matches!(item, musicxml::elements::DirectionTypeContents::Pedal(_))) {
+      for (pedal, pedal_type) in [
+        (&element.attributes.damper_pedal, PedalType::Sustain),
+        (&element.attributes.sostenuto_pedal, PedalType::Sostenuto),
+        (&element.attributes.soft_pedal, PedalType::Soft),
+      ] {
+        if let Some(pedal) = pedal {
+          let item = PhraseModDetails {
+            modification: PhraseModificationType::Pedal { pedal_type },
+            is_start: match pedal {
+              musicxml::datatypes::YesNoNumber::Yes => true,
+              musicxml::datatypes::YesNoNumber::No => false,
+              musicxml::datatypes::YesNoNumber::Decimal(depth) => *depth > 0.0,
+            },
+            number: None,
+            for_voice: None,
+            combine_with_next: false,
+          };
+          if item.is_start {
+            target.phrase_modification_start.push(item);
+          } else {
+            target.phrase_modification_end.push(item);
+          }
+        }
+      }
+    }
+    if target.section_start.is_none() {
+      if element.attributes.segno.is_some() {
+        target.section_start = Some(String::from("Segno"));
+      } else if element.attributes.coda.is_some() {
+        target.section_start = Some(String::from("Coda"));
+      }
+    }
+    if let Some(time_only) = &element.attributes.time_only {
+      if !pass_specific_slice.is_empty() {
+        let iterations = time_only.iter().map(|pass| pass.saturating_sub(1)).collect();
+        slice.time_only.push((iterations, pass_specific_slice));
+      }
+    }
+  }
+
+  fn parse_dynamics_from_sound(percentage: f64) -> Dynamic {
+    // Sound dynamics are expressed as a percentage of the default forte velocity (90)
+    match percentage {
+      p if p < 27.0 => Dynamic::Piano(3),
+      p if p < 46.0 => Dynamic::Piano(2),
+      p if p < 63.0 => Dynamic::Piano(1),
+      p if p < 80.0 => Dynamic::MezzoPiano,
+      p if p < 98.0 => Dynamic::MezzoForte,
+      p if p < 116.0 => Dynamic::Forte(1),
+      p if p < 132.0 => Dynamic::Forte(2),
+      _ => Dynamic::Forte(3),
+    }
+  }
+
   fn parse_barline_element(
     element: &musicxml::elements::Barline,
     time_slice: &mut BTreeMap<String, Vec<TimeSliceContainer>>,
@@ -1037,7 +1334,7 @@ impl MusicXmlConverter {
     divisions_per_quarter_note: usize,
     previous_cursor: usize,
     cursor: usize,
-  ) -> isize {
+  ) -> Result<isize, String> {
     let staff_name = if let Some(staff) = &note.content.staff {
       staff.content.to_string()
     } else {
@@ -1111,7 +1408,7 @@ impl MusicXmlConverter {
     };
     if let Some(print) = &note.attributes.print_object {
       if *print == musicxml::datatypes::YesNo::No {
-        return divisions as isize;
+        return Ok(divisions as isize);
       }
     }
     let (duration, extra_rests, altered_divisions) = if let Some(note_type) = &note.content.r#type {
@@ -1205,14 +1502,13 @@ impl MusicXmlConverter {
           into_beats: *time_modification.content.normal_notes.content as u8,
         });
     let (mut arpeggiate, mut non_arpeggiate) = (false, false);
+    let (mut string_number, mut fret_number) = (None, None);
+    let (mut finger_number, mut substitution_finger) = (None, None);
     let mut note_modifications: Vec<NoteModificationType> = Vec::new();
     let (mut phrase_modifications_start, mut phrase_modifications_end) = (Vec::new(), Vec::new());
-    note.content.notations.iter().for_each(|notation| {
-      notation
-        .content
-        .notations
-        .iter()
-        .for_each(|notation_type| match notation_type {
+    note.content.notations.iter().try_for_each(|notation| {
+      notation.content.notations.iter().try_for_each(|notation_type| {
+        match notation_type {
           musicxml::elements::NotationContentTypes::Tied(tie) => {
             tied = (tie.attributes.r#type == musicxml::datatypes::StartStopContinue::Start)
               || (tie.attributes.r#type == musicxml::datatypes::StartStopContinue::Continue);
@@ -1238,8 +1534,11 @@ impl MusicXmlConverter {
             }
           }
           musicxml::elements::NotationContentTypes::Tuplet(tuplet) => {
+            let Some(modification) = tuplet_details else {
+              return Err(String::from("Tuplet notation on a note without a time modification"));
+            };
             let item = PhraseModDetails {
-              modification: tuplet_details.unwrap(),
+              modification,
               is_start: tuplet.attributes.r#type == musicxml::datatypes::StartStop::Start,
               number: tuplet.attributes.number.as_ref().map(|number| **number),
               for_voice: voice.clone(),
@@ -1376,13 +1675,28 @@ impl MusicXmlConverter {
             note_modifications.extend(technicals.content.iter().filter_map(|technical| match technical {
               musicxml::elements::TechnicalContents::UpBow(_up_bow) => Some(NoteModificationType::UpBow),
               musicxml::elements::TechnicalContents::DownBow(_down_bow) => Some(NoteModificationType::DownBow),
-              musicxml::elements::TechnicalContents::Harmonic(_harmonic) => None,
+              musicxml::elements::TechnicalContents::Harmonic(harmonic) => Some(NoteModificationType::Harmonic {
+                natural: harmonic.content.artificial.is_none(),
+              }),
               musicxml::elements::TechnicalContents::OpenString(_open_string) => Some(NoteModificationType::Open),
               musicxml::elements::TechnicalContents::ThumbPosition(_thumb_position) => {
                 Some(NoteModificationType::ThumbPosition)
               }
-              musicxml::elements::TechnicalContents::Fingering(_fingering) => None,
-              musicxml::elements::TechnicalContents::Pluck(_pluck) => None,
+              musicxml::elements::TechnicalContents::Fingering(fingering) => {
+                if fingering.attributes.alternate != Some(musicxml::datatypes::YesNo::Yes) {
+                  if let Ok(finger) = fingering.content.trim().parse::<u8>() {
+                    if fingering.attributes.substitution == Some(musicxml::datatypes::YesNo::Yes) {
+                      substitution_finger = Some(finger);
+                    } else if finger_number.is_none() {
+                      finger_number = Some(finger);
+                    }
+                  }
+                }
+                None
+              }
+              musicxml::elements::TechnicalContents::Pluck(pluck) => {
+                Self::parse_plucking_finger(&pluck.content).map(|finger| NoteModificationType::Pluck { finger })
+              }
               musicxml::elements::TechnicalContents::DoubleTongue(_double_tongue) => {
                 Some(NoteModificationType::DoubleTongue)
               }
@@ -1393,11 +1707,33 @@ impl MusicXmlConverter {
               musicxml::elements::TechnicalContents::SnapPizzicato(_snap_pizzicato) => {
                 Some(NoteModificationType::Pizzicato)
               }
-              musicxml::elements::TechnicalContents::Fret(_fret) => None,
-              musicxml::elements::TechnicalContents::StringNumber(_string) => None,
-              musicxml::elements::TechnicalContents::HammerOn(_hammer_on) => None,
-              musicxml::elements::TechnicalContents::PullOff(_pull_off) => None,
-              musicxml::elements::TechnicalContents::Bend(_bend) => None,
+              musicxml::elements::TechnicalContents::Fret(fret) => {
+                fret_number = Some(*fret.content as u8);
+                None
+              }
+              musicxml::elements::TechnicalContents::StringNumber(string) => {
+                string_number = Some(*string.content);
+                None
+              }
+              musicxml::elements::TechnicalContents::HammerOn(hammer_on) => {
+                if hammer_on.attributes.r#type == musicxml::datatypes::StartStop::Start {
+                  Some(NoteModificationType::HammerOn)
+                } else {
+                  None
+                }
+              }
+              musicxml::elements::TechnicalContents::PullOff(pull_off) => {
+                if pull_off.attributes.r#type == musicxml::datatypes::StartStop::Start {
+                  Some(NoteModificationType::PullOff)
+                } else {
+                  None
+                }
+              }
+              musicxml::elements::TechnicalContents::Bend(bend) => Some(NoteModificationType::Bend {
+                semitones: (*bend.content.bend_alter.content).clamp(-128, 127) as i8,
+                pre_bend: bend.content.pre_bend.is_some(),
+                release: bend.content.release.is_some(),
+              }),
               musicxml::elements::TechnicalContents::Tap(_tap) => Some(NoteModificationType::Tap),
               musicxml::elements::TechnicalContents::Heel(_heel) => Some(NoteModificationType::Heel),
               musicxml::elements::TechnicalContents::Toe(_toe) => Some(NoteModificationType::Toe),
@@ -1500,68 +1836,23 @@ impl MusicXmlConverter {
                 }),
             );
           }
-          musicxml::elements::NotationContentTypes::Dynamics(dynamics) => match &dynamics.content[0] {
-            musicxml::elements::DynamicsType::P(_p) => note_modifications.push(NoteModificationType::Dynamic {
-              dynamic: Dynamic::Piano(1),
-            }),
-            musicxml::elements::DynamicsType::Pp(_pp) => note_modifications.push(NoteModificationType::Dynamic {
-              dynamic: Dynamic::Piano(2),
-            }),
-            musicxml::elements::DynamicsType::Ppp(_ppp) => note_modifications.push(NoteModificationType::Dynamic {
-              dynamic: Dynamic::Piano(3),
-            }),
-            musicxml::elements::DynamicsType::Pppp(_pppp) => note_modifications.push(NoteModificationType::Dynamic {
-              dynamic: Dynamic::Piano(4),
-            }),
-            musicxml::elements::DynamicsType::Ppppp(_pppp_it) => {
-              note_modifications.push(NoteModificationType::Dynamic {
-                dynamic: Dynamic::Piano(5),
-              });
-            }
-            musicxml::elements::DynamicsType::Pppppp(_ppp_it) => {
-              note_modifications.push(NoteModificationType::Dynamic {
-                dynamic: Dynamic::Piano(6),
-              });
-            }
-            musicxml::elements::DynamicsType::F(_f) => note_modifications.push(NoteModificationType::Dynamic {
-              dynamic: Dynamic::Forte(1),
-            }),
-            musicxml::elements::DynamicsType::Ff(_ff) => note_modifications.push(NoteModificationType::Dynamic {
-              dynamic: Dynamic::Forte(2),
-            }),
-            musicxml::elements::DynamicsType::Fff(_fff) => note_modifications.push(NoteModificationType::Dynamic {
-              dynamic: Dynamic::Forte(3),
-            }),
-            musicxml::elements::DynamicsType::Ffff(_ffff) => note_modifications.push(NoteModificationType::Dynamic {
-              dynamic: Dynamic::Forte(4),
-            }),
-            musicxml::elements::DynamicsType::Fffff(_ffff_it) => {
-              note_modifications.push(NoteModificationType::Dynamic {
-                dynamic: Dynamic::Forte(5),
-              });
-            }
-            musicxml::elements::DynamicsType::Ffffff(_fff_it) => {
-              note_modifications.push(NoteModificationType::Dynamic {
-                dynamic: Dynamic::Forte(6),
-              });
+          musicxml::elements::NotationContentTypes::Dynamics(dynamics) => {
+            if let Some(dynamic) = dynamics.content.first().and_then(Self::parse_dynamics_type) {
+              note_modifications.push(NoteModificationType::Dynamic { dynamic });
+            } else if let Some(item) = dynamics.content.first().and_then(Self::parse_dynamics_text) {
+              time_slices.get_mut(&staff_name).unwrap()[cursor].direction.push(item);
             }
-            musicxml::elements::DynamicsType::Mp(_mp) => note_modifications.push(NoteModificationType::Dynamic {
-              dynamic: Dynamic::MezzoPiano,
-            }),
-            musicxml::elements::DynamicsType::Mf(_mf) => note_modifications.push(NoteModificationType::Dynamic {
-              dynamic: Dynamic::MezzoForte,
-            }),
-            musicxml::elements::DynamicsType::N(_) | musicxml::elements::DynamicsType::OtherDynamics(_) => (),
-            _ => note_modifications.push(NoteModificationType::Accent),
-          },
+          }
           musicxml::elements::NotationContentTypes::Fermata(_fermata) => {
             note_modifications.push(NoteModificationType::Fermata);
           }
           musicxml::elements::NotationContentTypes::Arpeggiate(_arpeggiate) => arpeggiate = true,
           musicxml::elements::NotationContentTypes::NonArpeggiate(_non_arpeggiate) => non_arpeggiate = true,
           _ => {}
-        });
-    });
+        }
+        Ok(())
+      })
+    })?;
     if let Some(pizzicato) = &note.attributes.pizzicato {
       if *pizzicato == musicxml::datatypes::YesNo::Yes
         && !note_modifications
@@ -1571,6 +1862,17 @@ impl MusicXmlConverter {
         note_modifications.push(NoteModificationType::Pizzicato);
       }
     }
+    match (string_number, fret_number) {
+      (Some(string), Some(fret)) => note_modifications.push(NoteModificationType::FretPosition { string, fret }),
+      (Some(string), None) => note_modifications.push(NoteModificationType::StringNumber { string }),
+      _ => (),
+    }
+    if finger_number.is_some() || substitution_finger.is_some() {
+      note_modifications.push(NoteModificationType::Fingering {
+        finger: finger_number,
+        substitution: substitution_finger,
+      });
+    }
     if tied {
       note_modifications.push(NoteModificationType::Tie);
     }
@@ -1590,23 +1892,213 @@ impl MusicXmlConverter {
       note_modifications,
       phrase_modifications_start,
       phrase_modifications_end,
+      time_only: note
+        .attributes
+        .time_only
+        .as_ref()
+        .map(|time_only| time_only.iter().map(|pass| pass.saturating_sub(1)).collect()),
     };
+    let item_time_only = item.time_only.clone();
     if chord {
       time_slices.get_mut(&staff_name).unwrap()[previous_cursor]
         .notes
         .push(item);
-      0
+      Ok(0)
     } else {
       time_slices.get_mut(&staff_name).unwrap()[cursor].notes.push(item);
       let mut implicit_cursor = cursor + altered_divisions;
-      for extra_rest in extra_rests {
+      for mut extra_rest in extra_rests {
         let implicit_divisions = extra_rest.divisions;
+        extra_rest.time_only.clone_from(&item_time_only);
         time_slices.get_mut(&staff_name).unwrap()[implicit_cursor]
           .notes
           .push(extra_rest);
         implicit_cursor += implicit_divisions;
       }
-      divisions as isize
+      Ok(divisions as isize)
+    }
+  }
+
+  fn filter_time_slice_for_passes(
+    time_slice: &TimeSliceContainer,
+    iterations: &[u8],
+    keep_structure: bool,
+  ) -> TimeSliceContainer {
+    let plays_during = |time_only: &[u8]| iterations.iter().any(|pass| time_only.contains(pass));
+    let mut filtered = TimeSliceContainer {
+      direction: time_slice.direction.clone(),
+      chord_modification: time_slice.chord_modification.clone(),
+      phrase_modification_start: time_slice.phrase_modification_start.clone(),
+      phrase_modification_end: time_slice.phrase_modification_end.clone(),
+      notes: time_slice
+        .notes
+        .iter()
+        .filter(|note| note.time_only.as_ref().is_none_or(|time_only| plays_during(time_only)))
+        .cloned()
+        .collect(),
+      ..Default::default()
+    };
+    if keep_structure {
+      filtered.jump_to.clone_from(&time_slice.jump_to);
+      filtered.section_start.clone_from(&time_slice.section_start);
+      filtered.ending.clone_from(&time_slice.ending);
+      filtered.repeat.clone_from(&time_slice.repeat);
+      filtered.tempo_change_explicit = time_slice.tempo_change_explicit;
+      filtered.tempo_change_implicit = time_slice.tempo_change_implicit;
+      filtered.tempo_modification.clone_from(&time_slice.tempo_modification);
+      filtered.tempo_resume = time_slice.tempo_resume;
+      filtered.tempo_primo = time_slice.tempo_primo;
+      filtered.pass.clone_from(&time_slice.pass);
+    }
+    for (time_only, content) in &time_slice.time_only {
+      if plays_during(time_only) {
+        filtered.direction.extend(content.direction.iter().cloned());
+        filtered
+          .chord_modification
+          .extend(content.chord_modification.iter().cloned());
+        filtered
+          .phrase_modification_start
+          .extend(content.phrase_modification_start.iter().cloned());
+        filtered
+          .phrase_modification_end
+          .extend(content.phrase_modification_end.iter().cloned());
+      }
+    }
+    filtered
+  }
+
+  fn enclosing_repeat_passes(
+    time_slices: &BTreeMap<String, Vec<TimeSliceContainer>>,
+    measure_start: usize,
+    measure_end: usize,
+  ) -> u8 {
+    // Repeat barlines are recorded on every staff, so use whichever staff holds them at each time slice
+    let mut open_repeats = Vec::new();
+    let num_slices = time_slices.values().map(Vec::len).max().unwrap_or_default();
+    for slice_idx in 0..num_slices {
+      let repeats = time_slices
+        .values()
+        .filter_map(|slices| slices.get(slice_idx))
+        .find(|slice| !slice.repeat.is_empty())
+        .map(|slice| slice.repeat.as_slice())
+        .unwrap_or_default();
+      for &(is_start, num_times) in repeats {
+        if is_start {
+          open_repeats.push(slice_idx);
+        } else {
+          // A backward repeat without a matching forward repeat repeats from the beginning of the piece
+          let repeat_start = open_repeats.pop().unwrap_or_default();
+          if repeat_start <= measure_start && measure_end <= slice_idx {
+            return num_times.saturating_add(1);
+          }
+        }
+      }
+    }
+    1
+  }
+
+  fn find_unpaired_phrase_mods(slices: &[TimeSliceContainer]) -> (PhraseModIndices, PhraseModIndices) {
+    // Returns the (slice, modification) indices of all phrase starts and ends whose partner lies outside the slices
+    let (mut unpaired_starts, mut unpaired_ends) = (BTreeSet::new(), BTreeSet::new());
+    let mut open_mods: BTreeMap<_, Vec<(usize, usize)>> = BTreeMap::new();
+    for (slice_idx, slice) in slices.iter().enumerate() {
+      for (mod_idx, details) in slice.phrase_modification_end.iter().enumerate() {
+        let key = (details.modification, details.number, details.for_voice.clone());
+        if open_mods.get_mut(&key).and_then(Vec::pop).is_none() {
+          unpaired_ends.insert((slice_idx, mod_idx));
+        }
+      }
+      for (mod_idx, details) in slice.phrase_modification_start.iter().enumerate() {
+        let key = (details.modification, details.number, details.for_voice.clone());
+        open_mods.entry(key).or_default().push((slice_idx, mod_idx));
+      }
+    }
+    unpaired_starts.extend(open_mods.into_values().flatten());
+    (unpaired_starts, unpaired_ends)
+  }
+
+  fn expand_time_only_measures(
+    time_slices: &mut BTreeMap<String, Vec<TimeSliceContainer>>,
+    measure_bounds: &[(usize, usize)],
+  ) {
+    // Measures containing pass-specific content are duplicated once for each distinct set of passes
+    for &(measure_start, measure_end) in measure_bounds.iter().rev() {
+      let time_only_iterations = time_slices
+        .values()
+        .flat_map(|slices| slices[measure_start..measure_end].iter())
+        .flat_map(|slice| {
+          slice
+            .notes
+            .iter()
+            .filter_map(|note| note.time_only.clone())
+            .chain(slice.time_only.iter().map(|(time_only, _)| time_only.clone()))
+        })
+        .collect::<BTreeSet<_>>();
+      if time_only_iterations.is_empty() {
+        continue;
+      }
+
+      // Group together all reachable passes during which the exact same content is played
+      let num_passes = Self::enclosing_repeat_passes(time_slices, measure_start, measure_end);
+      let mut pass_groups: BTreeMap<Vec<bool>, Vec<u8>> = BTreeMap::new();
+      for pass in 0..num_passes {
+        pass_groups
+          .entry(
+            time_only_iterations
+              .iter()
+              .map(|time_only| time_only.contains(&pass))
+              .collect(),
+          )
+          .or_default()
+          .push(pass);
+      }
+      let mut pass_groups = pass_groups.into_values().collect::<Vec<_>>();
+      pass_groups.sort_unstable();
+
+      // A measure that sounds the same on every pass only needs its unreachable content removed
+      if let [iterations] = pass_groups.as_slice() {
+        for slices in time_slices.values_mut() {
+          for slice in &mut slices[measure_start..measure_end] {
+            *slice = Self::filter_time_slice_for_passes(slice, iterations, true);
+          }
+        }
+        continue;
+      }
+
+      // Replace the original measure with its pass-specific variants, keeping phrases that cross the measure
+      // boundaries attached only to the variants adjacent to their partners
+      let last_group_idx = pass_groups.len() - 1;
+      for slices in time_slices.values_mut() {
+        let (unpaired_starts, unpaired_ends) = Self::find_unpaired_phrase_mods(&slices[measure_start..measure_end]);
+        let mut expanded_measure = Vec::new();
+        for (group_idx, iterations) in pass_groups.iter().enumerate() {
+          let mut variant = slices[measure_start..measure_end]
+            .iter()
+            .map(|slice| Self::filter_time_slice_for_passes(slice, iterations, group_idx == 0))
+            .collect::<Vec<_>>();
+          for (slice_idx, slice) in variant.iter_mut().enumerate() {
+            let mut mod_idx = 0;
+            slice.phrase_modification_start.retain(|_| {
+              mod_idx += 1;
+              group_idx == last_group_idx || !unpaired_starts.contains(&(slice_idx, mod_idx - 1))
+            });
+            mod_idx = 0;
+            slice.phrase_modification_end.retain(|_| {
+              mod_idx += 1;
+              group_idx == 0 || !unpaired_ends.contains(&(slice_idx, mod_idx - 1))
+            });
+          }
+          if group_idx > 0 {
+            variant[0].pass.push((false, pass_groups[group_idx - 1].clone()));
+          }
+          variant[0].pass.push((true, iterations.clone()));
+          expanded_measure.append(&mut variant);
+        }
+        slices.splice(measure_start..measure_end, expanded_measure);
+        slices[measure_start + pass_groups.len() * (measure_end - measure_start)]
+          .pass
+          .insert(0, (false, pass_groups[last_group_idx].clone()));
+      }
     }
   }
 
@@ -1614,6 +2106,7 @@ impl MusicXmlConverter {
     let mut section_details = BTreeMap::new();
     let (mut open_endings, mut open_repeats) = (Vec::new(), Vec::new());
     let (mut open_sections, mut open_tempos) = (Vec::new(), Vec::new());
+    let (mut open_tempo_modifications, mut open_passes) = (Vec::new(), Vec::new());
 
     // Check for implicit repeats
     let add_implicit_repeat = Cell::new(false);
@@ -1636,6 +2129,10 @@ impl MusicXmlConverter {
         || !slice.repeat.is_empty()
         || slice.tempo_change_explicit.is_some()
         || slice.tempo_change_implicit.is_some()
+        || slice.tempo_modification.is_some()
+        || slice.tempo_resume
+        || slice.tempo_primo
+        || !slice.pass.is_empty()
     }) {
       // Priority: Ending end, Repeat end, Tempo Change, Section start, Repeat start, Ending start
       // TODO: If multiple sections start at the same time and one ends before the others (out of the order we placed them), then the one that ended needs to become the leaf-most section (i.e., everything in current section should move to that section), and all other sections should shift up by one
@@ -1648,6 +2145,11 @@ impl MusicXmlConverter {
         open_repeats.push(new_section_id);
         add_implicit_repeat.set(false);
       }
+      for (pass_start, _) in &time_slice.pass {
+        if !*pass_start {
+          details.ending_sections.push(open_passes.pop().unwrap_or_default());
+        }
+      }
       for (ending_start, _) in &time_slice.ending {
         if !*ending_start {
           details.ending_sections.push(open_endings.pop().unwrap_or_default());
@@ -1658,13 +2160,38 @@ impl MusicXmlConverter {
           details.ending_sections.push(open_repeats.pop().unwrap_or_default());
         }
       }
+      if time_slice.tempo_resume || time_slice.tempo_primo {
+        for section in open_passes.drain(..) {
+          details.ending_sections.push(section);
+        }
+        for section in open_endings.drain(..) {
+          details.ending_sections.push(section);
+        }
+        for section in open_repeats.drain(..) {
+          details.ending_sections.push(section);
+        }
+        for section in open_tempo_modifications.drain(..) {
+          details.ending_sections.push(section);
+        }
+        if time_slice.tempo_primo {
+          for section in open_tempos.drain(..) {
+            details.ending_sections.push(section);
+          }
+        }
+      }
       if let Some(section_name) = &time_slice.section_start {
+        for section in open_passes.drain(..) {
+          details.ending_sections.push(section);
+        }
         for section in open_endings.drain(..) {
           details.ending_sections.push(section);
         }
         for section in open_repeats.drain(..) {
           details.ending_sections.push(section);
         }
+        for section in open_tempo_modifications.drain(..) {
+          details.ending_sections.push(section);
+        }
         for section in open_tempos.drain(..) {
           details.ending_sections.push(section);
         }
@@ -1675,12 +2202,18 @@ impl MusicXmlConverter {
         open_sections.push(new_section_id);
       }
       if let Some(tempo) = &time_slice.tempo_change_explicit {
+        for section in open_passes.drain(..) {
+          details.ending_sections.push(section);
+        }
         for section in open_endings.drain(..) {
           details.ending_sections.push(section);
         }
         for section in open_repeats.drain(..) {
           details.ending_sections.push(section);
         }
+        for section in open_tempo_modifications.drain(..) {
+          details.ending_sections.push(section);
+        }
         for section in open_tempos.drain(..) {
           details.ending_sections.push(section);
         }
@@ -1689,12 +2222,18 @@ impl MusicXmlConverter {
         open_tempos.push(new_section_id);
       }
       if let Some(tempo) = &time_slice.tempo_change_implicit {
+        for section in open_passes.drain(..) {
+          details.ending_sections.push(section);
+        }
         for section in open_endings.drain(..) {
           details.ending_sections.push(section);
         }
         for section in open_repeats.drain(..) {
           details.ending_sections.push(section);
         }
+        for section in open_tempo_modifications.drain(..) {
+          details.ending_sections.push(section);
+        }
         for section in open_tempos.drain(..) {
           details.ending_sections.push(section);
         }
@@ -1702,6 +2241,23 @@ impl MusicXmlConverter {
         new_section.add_modification(SectionModificationType::TempoImplicit { tempo: *tempo });
         open_tempos.push(new_section_id);
       }
+      if let Some(modification) = &time_slice.tempo_modification {
+        for section in open_passes.drain(..) {
+          details.ending_sections.push(section);
+        }
+        for section in open_endings.drain(..) {
+          details.ending_sections.push(section);
+        }
+        for section in open_repeats.drain(..) {
+          details.ending_sections.push(section);
+        }
+        for section in open_tempo_modifications.drain(..) {
+          details.ending_sections.push(section);
+        }
+        let (new_section_id, new_section) = details.new_section("Tempo Modification Section");
+        new_section.add_modification(modification.clone());
+        open_tempo_modifications.push(new_section_id);
+      }
       for (repeat_start, times) in &time_slice.repeat {
         if *repeat_start {
           let (new_section_id, new_section) = details.new_section("Repeated Section");
@@ -1718,6 +2274,15 @@ impl MusicXmlConverter {
           open_endings.push(new_section_id);
         }
       }
+      for (pass_start, iterations) in &time_slice.pass {
+        if *pass_start {
+          let (new_section_id, new_section) = details.new_section(PASS_SPECIFIC_SECTION_NAME);
+          new_section.add_modification(SectionModificationType::OnlyPlay {
+            iterations: iterations.clone(),
+          });
+          open_passes.push(new_section_id);
+        }
+      }
       // TODO: How handle "Jump To"?? time_slice.jump_to.iter().for_each(|item| println!("[{time_slice_idx}]: JumpTo: {item}"));
     }
     section_details
@@ -1746,9 +2311,10 @@ impl MusicXmlConverter {
           }
         }
         for (&new_section_number, new_section) in &details.starting_sections {
-          let is_ending_section = new_section
-            .iter_modifications()
-            .any(|modification| matches!(modification.r#type, SectionModificationType::OnlyPlay { .. }));
+          let is_ending_section = new_section.get_name() != PASS_SPECIFIC_SECTION_NAME
+            && new_section
+              .iter_modifications()
+              .any(|modification| matches!(modification.r#type, SectionModificationType::OnlyPlay { .. }));
           if last_closed_index != index {
             section_structure.insert(
               last_closed_index,
@@ -2165,7 +2731,7 @@ impl MusicXmlConverter {
     items_by_voice
   }
 
-  fn load_from_musicxml(score: &ScorePartwise) -> Result<Composition, String> {
+  pub(crate) fn load_from_musicxml(score: &ScorePartwise) -> Result<Composition, String> {
     // Generate the initial composition structure and search for known metadata
     let mut composition = Composition::new(
       match &score.content.work {
@@ -2205,6 +2771,12 @@ impl MusicXmlConverter {
           .get(&*part.attributes.id)
           .expect("Unknown Part ID encountered");
         let max_divisions = MusicXmlConverter::find_max_num_divisions(&part.content);
+        if let Some(tuning) = MusicXmlConverter::find_tuning(&part.content) {
+          composition
+            .get_part_mut_by_name(part_name)
+            .expect("Unknown part name encountered")
+            .set_tuning(Some(tuning));
+        }
         part_data.data.insert(part_name.clone(), BTreeMap::new());
         let part_staves = unsafe { part_data.data.get_mut(part_name).unwrap_unchecked() };
         for staff in MusicXmlConverter::find_staves(&part.content) {
@@ -2220,6 +2792,7 @@ impl MusicXmlConverter {
         composition.remove_part_by_name(unsafe { parts_map.get(&*part.attributes.id).unwrap_unchecked() });
       } else {
         let mut open_wedges = BTreeMap::new();
+        let mut measure_bounds = Vec::new();
         let (mut cursor, mut previous_cursor): (usize, usize) = (0, 0);
         let divisions_per_quarter_note = MusicXmlConverter::find_divisions_per_quarter_note(&part.content);
         part_divisions_per_quarter_note.insert(
@@ -2234,6 +2807,7 @@ impl MusicXmlConverter {
         };
         for element in &part.content {
           if let musicxml::elements::PartElement::Measure(measure) = element {
+            let measure_start = cursor;
             let mut latest_cursor_reached = cursor;
             let mut accidental_context = BTreeMap::new();
             for measure_element in &measure.content {
@@ -2248,7 +2822,7 @@ impl MusicXmlConverter {
                   divisions_per_quarter_note,
                   previous_cursor,
                   cursor,
-                ),
+                )?,
                 musicxml::elements::MeasureElement::Backup(backup) => {
                   MusicXmlConverter::parse_backup_element(&backup.content)
                 }
@@ -2261,6 +2835,12 @@ impl MusicXmlConverter {
                 musicxml::elements::MeasureElement::Barline(barline) => {
                   MusicXmlConverter::parse_barline_element(barline, time_slices, cursor)
                 }
+                musicxml::elements::MeasureElement::Sound(sound) => {
+                  for slice in time_slices.values_mut() {
+                    MusicXmlConverter::parse_sound_element(sound, &[], &mut slice[cursor]);
+                  }
+                  0
+                }
                 _ => 0,
               };
               if cursor_change != 0 {
@@ -2270,8 +2850,10 @@ impl MusicXmlConverter {
               }
             }
             cursor = latest_cursor_reached;
+            measure_bounds.push((measure_start, cursor));
           }
         }
+        MusicXmlConverter::expand_time_only_measures(time_slices, &measure_bounds);
       }
     }
 
@@ -2292,6 +2874,7 @@ impl MusicXmlConverter {
       .collect();
 
     // Use the temporally ordered time slices for each part to construct a final composition structure
+    let starting_key = *composition.get_starting_key();
     for (part_name, staves) in part_data.data {
       let part = composition
         .get_part_mut_by_name(&part_name)
@@ -2359,6 +2942,10 @@ impl MusicXmlConverter {
                     };
                     let new_divisions =
                       Self::convert_duration_to_divisions(implicit_rest.duration, divisions_per_quarter_note);
+                    if new_divisions == 0 {
+                      // Gaps too short to be notated cannot be filled by an implicit rest
+                      break;
+                    }
                     implicit_rest.divisions = new_divisions;
                     divisions_remaining -= new_divisions;
                     time_slices[last_valid_idx].notes.push(implicit_rest);
@@ -2606,6 +3193,11 @@ impl MusicXmlConverter {
 
       // Simplify the part to remove any unnecessary nesting structures
       part.simplify();
+
+      // Choose playable fret positions for fretted parts whose source specifies none
+      if part.get_tuning().is_some() && !part.has_fret_positions() {
+        part.assign_fret_positions(Some(starting_key));
+      }
     }
 
     Ok(composition)
@@ -2613,13 +3205,377 @@ impl MusicXmlConverter {
 }
 
 impl Load for MusicXmlConverter {
-  fn load(path: &str) -> Result<Composition, String> {
-    let score = musicxml::read_score_partwise(path)?;
-    MusicXmlConverter::load_from_musicxml(&score)
+  // Both readers detect the root element of the score, transparently converting
+  // timewise scores into their partwise equivalents before they are parsed
+  fn load_bytes(data: &[u8]) -> Result<Composition, AmmError> {
+    let score = musicxml::read_score_data_partwise(data.to_vec())?;
+    MusicXmlConverter::load_from_musicxml(&score).map_err(AmmError::from)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::storage::Storage;
+
+  const TIMEWISE_SCORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<!DOCTYPE score-timewise PUBLIC "-//Recordare//DTD MusicXML 4.0 Timewise//EN" "http://www.musicxml.org/dtds/timewise.dtd">
+<score-timewise version="4.0">
+  <work><work-title>Timewise Test</work-title></work>
+  <part-list>
+    <score-part id="P1"><part-name>Flute</part-name></score-part>
+    <score-part id="P2"><part-name>Cello</part-name></score-part>
+  </part-list>
+  <measure number="1">
+    <part id="P1">
+      <attributes><divisions>1</divisions><clef><sign>G</sign><line>2</line></clef></attributes>
+      <note><pitch><step>C</step><octave>5</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </part>
+    <part id="P2">
+      <attributes><divisions>1</divisions><clef><sign>F</sign><line>4</line></clef></attributes>
+      <note><pitch><step>C</step><octave>3</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </part>
+  </measure>
+  <measure number="2">
+    <part id="P1">
+      <note><pitch><step>D</step><octave>5</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </part>
+    <part id="P2">
+      <note><pitch><step>G</step><octave>2</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </part>
+  </measure>
+</score-timewise>"#;
+
+  const PARTWISE_SCORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<!DOCTYPE score-partwise PUBLIC "-//Recordare//DTD MusicXML 4.0 Partwise//EN" "http://www.musicxml.org/dtds/partwise.dtd">
+<score-partwise version="4.0">
+  <work><work-title>Timewise Test</work-title></work>
+  <part-list>
+    <score-part id="P1"><part-name>Flute</part-name></score-part>
+    <score-part id="P2"><part-name>Cello</part-name></score-part>
+  </part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>1</divisions><clef><sign>G</sign><line>2</line></clef></attributes>
+      <note><pitch><step>C</step><octave>5</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </measure>
+    <measure number="2">
+      <note><pitch><step>D</step><octave>5</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </measure>
+  </part>
+  <part id="P2">
+    <measure number="1">
+      <attributes><divisions>1</divisions><clef><sign>F</sign><line>4</line></clef></attributes>
+      <note><pitch><step>C</step><octave>3</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </measure>
+    <measure number="2">
+      <note><pitch><step>G</step><octave>2</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </measure>
+  </part>
+</score-partwise>"#;
+
+  const TABLATURE_SCORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<score-partwise version="4.0">
+  <part-list><score-part id="P1"><part-name>Guitar</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>1</divisions>
+        <clef><sign>G</sign><line>2</line><clef-octave-change>-1</clef-octave-change></clef>
+        <staff-details>
+          <staff-lines>6</staff-lines>
+          <staff-tuning line="1"><tuning-step>E</tuning-step><tuning-octave>2</tuning-octave></staff-tuning>
+          <staff-tuning line="2"><tuning-step>A</tuning-step><tuning-octave>2</tuning-octave></staff-tuning>
+          <staff-tuning line="3"><tuning-step>D</tuning-step><tuning-octave>3</tuning-octave></staff-tuning>
+          <staff-tuning line="4"><tuning-step>G</tuning-step><tuning-octave>3</tuning-octave></staff-tuning>
+          <staff-tuning line="5"><tuning-step>B</tuning-step><tuning-octave>3</tuning-octave></staff-tuning>
+          <staff-tuning line="6"><tuning-step>E</tuning-step><tuning-octave>4</tuning-octave></staff-tuning>
+        </staff-details>
+      </attributes>
+      <note><pitch><step>E</step><octave>2</octave></pitch><duration>2</duration><voice>1</voice><type>half</type></note>
+      <note><pitch><step>C</step><octave>3</octave></pitch><duration>2</duration><voice>1</voice><type>half</type></note>
+    </measure>
+  </part>
+</score-partwise>"#;
+
+  #[test]
+  fn test_fret_assignment_on_import() {
+    let composition = Storage::MusicXML
+      .load_data(TABLATURE_SCORE.as_bytes().to_vec())
+      .unwrap();
+    let part = composition.get_part_by_name("Guitar").unwrap();
+    assert!(part.get_tuning().is_some());
+    let positions = part
+      .iter_timeslices()
+      .flat_map(|timeslice| timeslice.content)
+      .filter_map(|content| {
+        content
+          .note
+          .iter_modifications()
+          .find_map(|modification| match modification.r#type {
+            NoteModificationType::FretPosition { string, fret } => Some((string, fret)),
+            _ => None,
+          })
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(positions.len(), 2);
+    assert_eq!(positions[0].1, 0);
+  }
+
+  const TECHNICAL_SCORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<score-partwise version="4.0">
+  <part-list><score-part id="P1"><part-name>Violin</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>1</divisions><clef><sign>G</sign><line>2</line></clef></attributes>
+      <note>
+        <pitch><step>A</step><octave>4</octave></pitch><duration>1</duration><voice>1</voice><type>quarter</type>
+        <notations><technical><fingering>3</fingering><fingering substitution="yes">4</fingering><string>2</string></technical></notations>
+      </note>
+      <note>
+        <pitch><step>B</step><octave>4</octave></pitch><duration>1</duration><voice>1</voice><type>quarter</type>
+        <notations><technical><fingering substitution="yes">2</fingering><fingering alternate="yes">1</fingering></technical></notations>
+      </note>
+      <note>
+        <pitch><step>E</step><octave>5</octave></pitch><duration>1</duration><voice>1</voice><type>quarter</type>
+        <notations><technical><harmonic><natural/></harmonic><pluck>i</pluck></technical></notations>
+      </note>
+      <note>
+        <pitch><step>A</step><octave>5</octave></pitch><duration>1</duration><voice>1</voice><type>quarter</type>
+        <notations><technical><harmonic><artificial/></harmonic><pluck>a</pluck></technical></notations>
+      </note>
+    </measure>
+  </part>
+</score-partwise>"#;
+
+  #[test]
+  fn test_technical_import() {
+    let composition = Storage::MusicXML
+      .load_data(TECHNICAL_SCORE.as_bytes().to_vec())
+      .unwrap();
+    let modifications = composition
+      .get_part_by_name("Violin")
+      .unwrap()
+      .iter_timeslices()
+      .flat_map(|timeslice| timeslice.content)
+      .map(|content| {
+        content
+          .note
+          .iter_modifications()
+          .map(|modification| modification.r#type)
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(modifications.len(), 4);
+    assert!(modifications[0].contains(&NoteModificationType::Fingering {
+      finger: Some(3),
+      substitution: Some(4)
+    }));
+    assert!(modifications[0].contains(&NoteModificationType::StringNumber { string: 2 }));
+    assert!(modifications[1].contains(&NoteModificationType::Fingering {
+      finger: None,
+      substitution: Some(2)
+    }));
+    assert!(modifications[2].contains(&NoteModificationType::Harmonic { natural: true }));
+    assert!(modifications[2].contains(&NoteModificationType::Pluck {
+      finger: PluckingFinger::Index
+    }));
+    assert!(modifications[3].contains(&NoteModificationType::Harmonic { natural: false }));
+    assert!(modifications[3].contains(&NoteModificationType::Pluck {
+      finger: PluckingFinger::Ring
+    }));
+  }
+
+  const DYNAMICS_SCORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<score-partwise version="4.0">
+  <part-list><score-part id="P1"><part-name>Piano</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>1</divisions><clef><sign>G</sign><line>2</line></clef></attributes>
+      <direction><direction-type><dynamics><other-dynamics>più f</other-dynamics></dynamics></direction-type></direction>
+      <note><pitch><step>C</step><octave>5</octave></pitch><duration>2</duration><voice>1</voice><type>half</type></note>
+      <direction><direction-type><dynamics><other-dynamics>sfpp</other-dynamics></dynamics></direction-type></direction>
+      <note>
+        <pitch><step>D</step><octave>5</octave></pitch><duration>2</duration><voice>1</voice><type>half</type>
+        <notations><dynamics><other-dynamics>pp sub.</other-dynamics></dynamics></notations>
+      </note>
+    </measure>
+  </part>
+</score-partwise>"#;
+
+  #[test]
+  fn test_text_dynamics_import() {
+    let composition = Storage::MusicXML.load_data(DYNAMICS_SCORE.as_bytes().to_vec()).unwrap();
+    let directions = composition
+      .get_part_by_name("Piano")
+      .unwrap()
+      .iter_timeslices()
+      .flat_map(|timeslice| timeslice.directions)
+      .map(|direction| direction.r#type)
+      .collect::<Vec<_>>();
+    let texts = directions
+      .iter()
+      .filter_map(|direction| match direction {
+        DirectionType::Expression { text, style } if style.italic => Some(text.as_str()),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(texts, ["più f", "pp sub."]);
+  }
+
+  #[test]
+  fn test_tuplet_without_time_modification() {
+    let score = DYNAMICS_SCORE.replace("<notations><dynamics>", "<notations><tuplet type=\"start\"/><dynamics>");
+    assert!(Storage::MusicXML.load_data(score.into_bytes()).is_err());
+  }
+
+  const WORDS_SCORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<score-partwise version="4.0">
+  <part-list><score-part id="P1"><part-name>Flute</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>1</divisions><clef><sign>G</sign><line>2</line></clef></attributes>
+      <direction><direction-type><words font-style="italic">rit. e dim.</words></direction-type></direction>
+      <note><pitch><step>C</step><octave>5</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </measure>
+  </part>
+</score-partwise>"#;
+
+  #[test]
+  fn test_tempo_words_keep_text() {
+    let composition = Storage::MusicXML.load_data(WORDS_SCORE.as_bytes().to_vec()).unwrap();
+    let timeslices = composition
+      .get_part_by_name("Flute")
+      .unwrap()
+      .iter_timeslices()
+      .collect::<Vec<_>>();
+    assert!(timeslices
+      .iter()
+      .any(|timeslice| timeslice.tempo_details.contains(&SectionModificationType::Ritardando)));
+    assert!(timeslices
+      .iter()
+      .flat_map(|timeslice| timeslice.directions.iter())
+      .any(|direction| matches!(&direction.r#type, DirectionType::Expression { text, .. } if text == "rit. e dim.")));
   }
 
-  fn load_data(data: Vec<u8>) -> Result<Composition, String> {
-    let score = musicxml::read_score_data_partwise(data)?;
-    MusicXmlConverter::load_from_musicxml(&score)
+  const TIME_ONLY_REPEAT_SCORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<score-partwise version="4.0">
+  <part-list><score-part id="P1"><part-name>Flute</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>1</divisions><clef><sign>G</sign><line>2</line></clef></attributes>
+      <barline location="left"><repeat direction="forward"/></barline>
+      <note><pitch><step>C</step><octave>5</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </measure>
+    <measure number="2">
+      <note time-only="1"><pitch><step>D</step><octave>5</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+      <backup><duration>4</duration></backup>
+      <note time-only="2"><pitch><step>E</step><octave>5</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+      <barline location="right"><repeat direction="backward"/></barline>
+    </measure>
+  </part>
+</score-partwise>"#;
+
+  const TIME_ONLY_SCORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<score-partwise version="4.0">
+  <part-list><score-part id="P1"><part-name>Flute</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>1</divisions><clef><sign>G</sign><line>2</line></clef></attributes>
+      <note><pitch><step>C</step><octave>5</octave></pitch><duration>2</duration><voice>1</voice><type>half</type></note>
+      <note time-only="1"><pitch><step>D</step><octave>5</octave></pitch><duration>2</duration><voice>1</voice><type>half</type></note>
+    </measure>
+    <measure number="2">
+      <sound time-only="2" dynamics="40"/>
+      <note><pitch><step>E</step><octave>5</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </measure>
+  </part>
+</score-partwise>"#;
+
+  const SOUND_SCORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<score-partwise version="4.0">
+  <part-list><score-part id="P1"><part-name>Piano</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>1</divisions><clef><sign>G</sign><line>2</line></clef></attributes>
+      <direction><direction-type><words>Allegro</words></direction-type><sound tempo="132" dynamics="106.67"/></direction>
+      <note><pitch><step>C</step><octave>5</octave></pitch><duration>4</duration><voice>1</voice><type>whole</type></note>
+    </measure>
+  </part>
+</score-partwise>"#;
+
+  fn played_steps(composition: &Composition, part_name: &str) -> Vec<PitchName> {
+    composition
+      .get_part_by_name(part_name)
+      .unwrap()
+      .iter_timeslices()
+      .flat_map(|timeslice| timeslice.content)
+      .filter(|content| !content.note.is_rest())
+      .map(|content| content.note.pitch.name)
+      .collect()
+  }
+
+  #[test]
+  fn test_time_only_in_repeat() {
+    let composition = Storage::MusicXML
+      .load_data(TIME_ONLY_REPEAT_SCORE.as_bytes().to_vec())
+      .unwrap();
+    assert_eq!(
+      played_steps(&composition, "Flute"),
+      [PitchName::C, PitchName::D, PitchName::C, PitchName::E]
+    );
+  }
+
+  #[test]
+  fn test_time_only_outside_repeat() {
+    let composition = Storage::MusicXML
+      .load_data(TIME_ONLY_SCORE.as_bytes().to_vec())
+      .unwrap();
+    assert_eq!(
+      played_steps(&composition, "Flute"),
+      [PitchName::C, PitchName::D, PitchName::E]
+    );
+    assert!(!composition
+      .get_part_by_name("Flute")
+      .unwrap()
+      .iter_timeslices()
+      .flat_map(|timeslice| timeslice.directions)
+      .any(|direction| matches!(direction.r#type, DirectionType::Dynamic { .. })));
+  }
+
+  #[test]
+  fn test_sound_dynamics_and_tempo() {
+    let composition = Storage::MusicXML.load_data(SOUND_SCORE.as_bytes().to_vec()).unwrap();
+    let timeslices = composition
+      .get_part_by_name("Piano")
+      .unwrap()
+      .iter_timeslices()
+      .collect::<Vec<_>>();
+    assert!(timeslices
+      .iter()
+      .flat_map(|timeslice| timeslice.directions.iter())
+      .any(|direction| direction.r#type
+        == DirectionType::Dynamic {
+          dynamic: Dynamic::Forte(1)
+        }));
+    assert!(timeslices.iter().any(|timeslice| timeslice.tempo_details.contains(
+      &SectionModificationType::TempoExplicit {
+        tempo: Tempo {
+          base_note: Duration::new(DurationType::Quarter, 0),
+          beats_per_minute: 132,
+        }
+      }
+    )));
+  }
+
+  #[test]
+  fn test_timewise_import() {
+    let timewise = Storage::MusicXML.load_data(TIMEWISE_SCORE.as_bytes().to_vec());
+    let partwise = Storage::MusicXML.load_data(PARTWISE_SCORE.as_bytes().to_vec());
+    assert!(timewise.is_ok());
+    assert_eq!(timewise, partwise);
+    if let Ok(composition) = timewise {
+      assert_eq!(composition.get_part_names(), vec!["Flute", "Cello"]);
+    }
   }
 }
@@ -0,0 +1,270 @@
+use crate::context::{ClefType, Dynamic, Key, TempoMarking, TimeSignature, TimeSignatureType};
+use crate::modification::DirectionType;
+use crate::note::{Duration, DurationType};
+use crate::Composition;
+use alloc::{string::String, vec, vec::Vec};
+use core::ops::{Add, Div, Mul, Sub};
+
+pub(super) const MAX_DOTS: u32 = 3;
+pub(super) const DYNAMICS: [&str; 26] = [
+  "pppppp", "ppppp", "pppp", "ppp", "pp", "p", "mp", "mf", "f", "ff", "fff", "ffff", "fffff", "ffffff", "fp", "fz",
+  "n", "pf", "rf", "rfz", "sf", "sffz", "sfp", "sfpp", "sfz", "sfzp",
+];
+const NOTE_TYPES: [&str; 14] = [
+  "maxima", "long", "breve", "whole", "half", "quarter", "eighth", "16th", "32nd", "64th", "128th", "256th", "512th",
+  "1024th",
+];
+
+pub(super) const fn gcd(mut a: u64, mut b: u64) -> u64 {
+  while b != 0 {
+    (a, b) = (b, a % b);
+  }
+  a
+}
+
+/// Exact rational duration measured in whole notes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(super) struct Fraction {
+  pub(super) numerator: u64,
+  pub(super) denominator: u64,
+}
+
+impl Fraction {
+  pub(super) const ZERO: Self = Self {
+    numerator: 0,
+    denominator: 1,
+  };
+  pub(super) const ONE: Self = Self {
+    numerator: 1,
+    denominator: 1,
+  };
+
+  pub(super) fn new(numerator: u64, denominator: u64) -> Self {
+    let divisor = gcd(numerator, denominator).max(1);
+    Self {
+      numerator: numerator / divisor,
+      denominator: (denominator / divisor).max(1),
+    }
+  }
+
+  pub(super) fn parse(text: &str) -> Option<Self> {
+    let (numerator, denominator) = text.trim().split_once('/').unwrap_or((text.trim(), "1"));
+    let numerator = numerator.trim().parse().ok()?;
+    let denominator = denominator.trim().parse().ok().filter(|denominator| *denominator > 0)?;
+    Some(Self::new(numerator, denominator))
+  }
+
+  pub(super) fn from_duration(duration: &Duration) -> Self {
+    let (numerator, denominator) = match duration.value {
+      DurationType::Maxima => (8, 1),
+      DurationType::Long => (4, 1),
+      DurationType::Breve => (2, 1),
+      DurationType::Whole => (1, 1),
+      DurationType::Half => (1, 2),
+      DurationType::Quarter => (1, 4),
+      DurationType::Eighth => (1, 8),
+      DurationType::Sixteenth => (1, 16),
+      DurationType::ThirtySecond => (1, 32),
+      DurationType::SixtyFourth => (1, 64),
+      DurationType::OneHundredTwentyEighth => (1, 128),
+      DurationType::TwoHundredFiftySixth => (1, 256),
+      DurationType::FiveHundredTwelfth => (1, 512),
+      DurationType::OneThousandTwentyFourth => (1, 1024),
+      DurationType::TwoThousandFortyEighth => (1, 2048),
+    };
+    let dots = u32::from(duration.dots.min(8));
+    Self::new(numerator, denominator) * Self::new((1 << (dots + 1)) - 1, 1 << dots)
+  }
+
+  pub(super) fn note_type(self) -> Option<(&'static str, u32)> {
+    (0..=MAX_DOTS).find_map(|dots| {
+      let base = self * Self::new(1 << dots, (1 << (dots + 1)) - 1);
+      let idx = match (base.numerator, base.denominator) {
+        (1, denominator) if denominator.is_power_of_two() => Some(denominator.trailing_zeros() as usize + 3),
+        (numerator, 1) if numerator.is_power_of_two() && numerator <= 8 => {
+          Some(3 - numerator.trailing_zeros() as usize)
+        }
+        _ => None,
+      };
+      idx
+        .and_then(|idx| NOTE_TYPES.get(idx))
+        .map(|note_type| (*note_type, dots))
+    })
+  }
+
+  pub(super) fn to_divisions(self, whole_divisions: u64) -> u64 {
+    self.numerator.saturating_mul(whole_divisions) / self.denominator
+  }
+}
+
+impl Add for Fraction {
+  type Output = Self;
+  fn add(self, other: Self) -> Self {
+    Self::new(
+      (self.numerator.saturating_mul(other.denominator))
+        .saturating_add(other.numerator.saturating_mul(self.denominator)),
+      self.denominator.saturating_mul(other.denominator),
+    )
+  }
+}
+
+impl Sub for Fraction {
+  type Output = Self;
+  fn sub(self, other: Self) -> Self {
+    Self::new(
+      (self.numerator.saturating_mul(other.denominator))
+        .saturating_sub(other.numerator.saturating_mul(self.denominator)),
+      self.denominator.saturating_mul(other.denominator),
+    )
+  }
+}
+
+impl Mul for Fraction {
+  type Output = Self;
+  fn mul(self, other: Self) -> Self {
+    Self::new(
+      self.numerator.saturating_mul(other.numerator),
+      self.denominator.saturating_mul(other.denominator),
+    )
+  }
+}
+
+impl Div for Fraction {
+  type Output = Self;
+  fn div(self, other: Self) -> Self {
+    Self::new(
+      self.numerator.saturating_mul(other.denominator),
+      self.denominator.saturating_mul(other.numerator),
+    )
+  }
+}
+
+impl PartialOrd for Fraction {
+  fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Fraction {
+  fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+    (u128::from(self.numerator) * u128::from(other.denominator))
+      .cmp(&(u128::from(other.numerator) * u128::from(self.denominator)))
+  }
+}
+
+/// Returns the length of a measure in the given time signature, or `None` for
+/// unmetered music.
+pub(super) fn measure_length(time_signature: &TimeSignature) -> Option<Fraction> {
+  match time_signature.signature {
+    TimeSignatureType::CommonTime | TimeSignatureType::CutTime => Some(Fraction::ONE),
+    TimeSignatureType::None => None,
+    TimeSignatureType::Explicit => Some(Fraction::new(
+      u64::from(time_signature.numerator),
+      u64::from(time_signature.denominator),
+    )),
+  }
+}
+
+/// Formats a tempo marking as its words, e.g. `Allegro Moderato`.
+pub(super) fn tempo_marking_text(marking: TempoMarking) -> String {
+  let mut text = String::new();
+  for ch in format!("{marking:?}").chars() {
+    if ch.is_ascii_uppercase() && !text.is_empty() {
+      text.push(' ');
+    }
+    text.push(ch);
+  }
+  text
+}
+
+/// Key, time signature or clef that a direction changes to.
+pub(super) enum ContextChange {
+  Key(Key),
+  TimeSignature(TimeSignature),
+  Clef(ClefType),
+}
+
+/// Key, time signature and clef in effect at the current position of a staff
+/// being written.
+#[derive(Copy, Clone)]
+pub(super) struct StaffContext {
+  pub(super) key: Key,
+  pub(super) time_signature: TimeSignature,
+  pub(super) clef: ClefType,
+  pub(super) measure_length: Option<Fraction>,
+}
+
+impl StaffContext {
+  pub(super) fn new(composition: &Composition) -> Self {
+    let time_signature = *composition.get_starting_time_signature();
+    Self {
+      key: *composition.get_starting_key(),
+      time_signature,
+      clef: ClefType::Treble,
+      measure_length: measure_length(&time_signature),
+    }
+  }
+
+  /// Applies a key, time signature or clef change direction, returning the
+  /// change only if it differs from the context already in effect.
+  pub(super) fn apply(&mut self, direction: &DirectionType) -> Option<ContextChange> {
+    match direction {
+      DirectionType::KeyChange { key } if *key != self.key => {
+        self.key = *key;
+        Some(ContextChange::Key(*key))
+      }
+      DirectionType::TimeSignatureChange { time_signature } if *time_signature != self.time_signature => {
+        self.time_signature = *time_signature;
+        self.measure_length = measure_length(time_signature);
+        Some(ContextChange::TimeSignature(*time_signature))
+      }
+      DirectionType::ClefChange { clef } if clef.clef_type != self.clef => {
+        self.clef = clef.clef_type;
+        Some(ContextChange::Clef(clef.clef_type))
+      }
+      _ => None,
+    }
+  }
+}
+
+/// Formats a dynamic as the abbreviation shared by MEI and MNX, if it has one.
+pub(super) fn dynamic_text(dynamic: &Dynamic) -> Option<String> {
+  match dynamic {
+    Dynamic::Piano(count @ 1..=6) => Some("p".repeat(usize::from(*count))),
+    Dynamic::Forte(count @ 1..=6) => Some("f".repeat(usize::from(*count))),
+    Dynamic::MezzoPiano => Some(String::from("mp")),
+    Dynamic::MezzoForte => Some(String::from("mf")),
+    Dynamic::FortePiano => Some(String::from("fp")),
+    Dynamic::Niente => Some(String::from("n")),
+    Dynamic::Rinforzando | Dynamic::Rinforzato => Some(String::from("rfz")),
+    Dynamic::Sforzando(1) => Some(String::from("sf")),
+    Dynamic::Sforzato(1) | Dynamic::Forzando => Some(String::from("sfz")),
+    _ => None,
+  }
+}
+
+/// Returns the onsets of all measures before `end`, given the onsets at which
+/// the measure length changes.
+pub(super) fn measure_starts(meters: &[(Fraction, Option<Fraction>)], end: Fraction) -> Vec<Fraction> {
+  let mut starts = vec![Fraction::ZERO];
+  loop {
+    let start = starts[starts.len() - 1];
+    let length = meters
+      .iter()
+      .rev()
+      .find(|(onset, _)| *onset <= start)
+      .and_then(|(_, length)| *length)
+      .filter(|length| length.numerator > 0);
+    let next_change = meters.iter().map(|(onset, _)| *onset).find(|onset| *onset > start);
+    let next = match (length, next_change) {
+      (Some(length), Some(change)) => (start + length).min(change),
+      (Some(length), None) => start + length,
+      (None, Some(change)) => change,
+      (None, None) => end,
+    };
+    if next >= end || next <= start {
+      return starts;
+    }
+    starts.push(next);
+  }
+}
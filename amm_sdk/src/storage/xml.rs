@@ -0,0 +1,313 @@
+use alloc::string::{String, ToString};
+use musicxml_internal::XmlElement;
+
+/// Convenience accessors for navigating and extending XML element trees.
+pub(crate) trait XmlElementExt {
+  fn attribute(&self, name: &str) -> Option<&str>;
+  fn child(&self, name: &str) -> Option<&XmlElement>;
+  fn child_text(&self, name: &str) -> Option<&str>;
+  fn has_child(&self, name: &str) -> bool;
+  fn child_mut_or_insert(&mut self, name: &str) -> &mut XmlElement;
+  fn deep_copy(&self) -> XmlElement;
+}
+
+impl XmlElementExt for XmlElement {
+  fn attribute(&self, name: &str) -> Option<&str> {
+    self
+      .attributes
+      .iter()
+      .find(|(key, _)| key == name)
+      .map(|(_, value)| value.as_str())
+  }
+
+  fn child(&self, name: &str) -> Option<&XmlElement> {
+    self.elements.iter().find(|element| element.name == name)
+  }
+
+  fn child_text(&self, name: &str) -> Option<&str> {
+    self.child(name).map(|element| element.text.as_str())
+  }
+
+  fn has_child(&self, name: &str) -> bool {
+    self.child(name).is_some()
+  }
+
+  fn child_mut_or_insert(&mut self, name: &str) -> &mut XmlElement {
+    let idx = if let Some(idx) = self.elements.iter().position(|element| element.name == name) {
+      idx
+    } else {
+      self.elements.push(xml_element(name, &[], Vec::new()));
+      self.elements.len() - 1
+    };
+    &mut self.elements[idx]
+  }
+
+  fn deep_copy(&self) -> XmlElement {
+    XmlElement {
+      name: self.name.clone(),
+      attributes: self.attributes.clone(),
+      elements: self.elements.iter().map(XmlElementExt::deep_copy).collect(),
+      text: self.text.clone(),
+    }
+  }
+}
+
+/// Creates a new XML element with the given `name`, `attributes`, and child `elements`.
+pub(crate) fn xml_element(name: &str, attributes: &[(&str, &str)], elements: Vec<XmlElement>) -> XmlElement {
+  XmlElement {
+    name: String::from(name),
+    attributes: attributes
+      .iter()
+      .map(|(key, value)| (String::from(*key), String::from(*value)))
+      .collect(),
+    elements,
+    text: String::new(),
+  }
+}
+
+/// Creates a new XML element with the given `name` containing only `text`.
+pub(crate) fn xml_text_element(name: &str, text: impl ToString) -> XmlElement {
+  XmlElement {
+    name: String::from(name),
+    attributes: Vec::new(),
+    elements: Vec::new(),
+    text: text.to_string(),
+  }
+}
+
+/// Adds a MusicXML `notation` to the given `note` element, optionally nested within
+/// a notation `group` such as `articulations` or `ornaments`.
+pub(crate) fn add_notation(note: &mut XmlElement, group: Option<&str>, notation: XmlElement) {
+  let notations = note.child_mut_or_insert("notations");
+  match group {
+    Some(group) => notations.child_mut_or_insert(group).elements.push(notation),
+    None => notations.elements.push(notation),
+  }
+}
+
+fn decode_entities(text: &str, decoded: &mut String) {
+  let mut remaining = text;
+  while let Some(start) = remaining.find('&') {
+    decoded.push_str(&remaining[..start]);
+    remaining = &remaining[start..];
+    let entity = remaining
+      .find(';')
+      .map(|end| &remaining[1..end])
+      .filter(|entity| entity.len() <= 10);
+    let character = entity.and_then(|entity| match entity {
+      "lt" => Some('<'),
+      "gt" => Some('>'),
+      "amp" => Some('&'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      _ => entity
+        .strip_prefix("#x")
+        .or_else(|| entity.strip_prefix("#X"))
+        .map_or_else(
+          || entity.strip_prefix('#').and_then(|number| number.parse().ok()),
+          |hex| u32::from_str_radix(hex, 16).ok(),
+        )
+        .and_then(char::from_u32),
+    });
+    if let (Some(entity), Some(character)) = (entity, character) {
+      decoded.push(character);
+      remaining = &remaining[entity.len() + 2..];
+    } else {
+      decoded.push('&');
+      remaining = &remaining[1..];
+    }
+  }
+  decoded.push_str(remaining);
+}
+
+fn find_tag_end(tag: &str) -> Option<usize> {
+  let mut quote = None;
+  for (idx, ch) in tag.char_indices() {
+    match (ch, quote) {
+      ('"' | '\'', None) => quote = Some(ch),
+      (_, Some(open)) if ch == open => quote = None,
+      ('>', None) => return Some(idx),
+      _ => (),
+    }
+  }
+  None
+}
+
+fn parse_start_tag(tag: &str) -> Result<XmlElement, String> {
+  let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+  let mut element = xml_element(&tag[..name_end], &[], Vec::new());
+  let mut remaining = tag[name_end..].trim_start();
+  while !remaining.is_empty() {
+    let (key, value) = remaining
+      .split_once('=')
+      .ok_or_else(|| format!("Malformed attribute in XML tag <{}>", element.name))?;
+    let value = value.trim_start();
+    let quote = value
+      .chars()
+      .next()
+      .filter(|quote| *quote == '"' || *quote == '\'')
+      .ok_or_else(|| format!("Unquoted attribute value in XML tag <{}>", element.name))?;
+    let (value, rest) = value[1..]
+      .split_once(quote)
+      .ok_or_else(|| format!("Unterminated attribute value in XML tag <{}>", element.name))?;
+    let mut decoded = String::new();
+    decode_entities(value, &mut decoded);
+    element.attributes.push((String::from(key.trim()), decoded));
+    remaining = rest.trim_start();
+  }
+  Ok(element)
+}
+
+/// Parses the given XML document `text` into a tree of elements, returning its root element.
+///
+/// Declarations, processing instructions, comments, and document type definitions
+/// are skipped, character data is collected into the text of its enclosing element,
+/// and all predefined and numeric character references are decoded.
+pub(crate) fn parse_xml(text: &str) -> Result<XmlElement, String> {
+  let mut open_elements: Vec<XmlElement> = Vec::new();
+  let mut remaining = text.trim_start_matches('\u{feff}');
+  while let Some(tag_start) = remaining.find('<') {
+    if let Some(element) = open_elements.last_mut() {
+      decode_entities(&remaining[..tag_start], &mut element.text);
+    }
+    remaining = &remaining[tag_start..];
+    if let Some(comment) = remaining.strip_prefix("<!--") {
+      remaining = comment.split_once("-->").ok_or("Unterminated XML comment")?.1;
+    } else if let Some(data) = remaining.strip_prefix("<![CDATA[") {
+      let (data, rest) = data.split_once("]]>").ok_or("Unterminated XML CDATA section")?;
+      if let Some(element) = open_elements.last_mut() {
+        element.text.push_str(data);
+      }
+      remaining = rest;
+    } else if let Some(instruction) = remaining.strip_prefix("<?") {
+      remaining = instruction.split_once("?>").ok_or("Unterminated XML declaration")?.1;
+    } else if let Some(doctype) = remaining.strip_prefix("<!") {
+      let declaration_end = doctype.find('>').ok_or("Unterminated XML document type")?;
+      remaining = if doctype[..declaration_end].contains('[') {
+        doctype.split_once("]>").ok_or("Unterminated XML document type")?.1
+      } else {
+        &doctype[declaration_end + 1..]
+      };
+    } else if let Some(closing_tag) = remaining.strip_prefix("</") {
+      let (name, rest) = closing_tag.split_once('>').ok_or("Unterminated XML closing tag")?;
+      let mut element = open_elements.pop().ok_or("Unexpected XML closing tag")?;
+      if element.name != name.trim() {
+        return Err(format!(
+          "Mismatched closing tag...expected '{}' but found '{}'",
+          element.name,
+          name.trim()
+        ));
+      }
+      element.text = String::from(element.text.trim());
+      if let Some(parent) = open_elements.last_mut() {
+        parent.elements.push(element);
+      } else {
+        return Ok(element);
+      }
+      remaining = rest;
+    } else {
+      let tag_end = find_tag_end(remaining).ok_or("Unterminated XML tag")?;
+      let (tag, self_closing) = match remaining[1..tag_end].strip_suffix('/') {
+        Some(tag) => (tag, true),
+        None => (&remaining[1..tag_end], false),
+      };
+      let element = parse_start_tag(tag)?;
+      remaining = &remaining[tag_end + 1..];
+      if !self_closing {
+        open_elements.push(element);
+      } else if let Some(parent) = open_elements.last_mut() {
+        parent.elements.push(element);
+      } else {
+        return Ok(element);
+      }
+    }
+  }
+  Err(String::from("Missing one or more matched XML tags"))
+}
+
+fn encode_entities(text: &str, encoded: &mut String) {
+  for ch in text.chars() {
+    match ch {
+      '<' => encoded.push_str("&lt;"),
+      '>' => encoded.push_str("&gt;"),
+      '&' => encoded.push_str("&amp;"),
+      '"' => encoded.push_str("&quot;"),
+      '\'' => encoded.push_str("&apos;"),
+      _ => encoded.push(ch),
+    }
+  }
+}
+
+/// Writes the given `element` and all of its descendants to `output` as indented XML text,
+/// starting at the given nesting `depth`.
+///
+/// Character data and attribute values are escaped, and elements without any
+/// text or children are written as self-closing tags.
+pub(crate) fn write_xml(element: &XmlElement, depth: usize, output: &mut String) {
+  let indent = "  ".repeat(depth);
+  output.push_str(&indent);
+  output.push('<');
+  output.push_str(&element.name);
+  for (key, value) in &element.attributes {
+    output.push(' ');
+    output.push_str(key);
+    output.push_str("=\"");
+    encode_entities(value, output);
+    output.push('"');
+  }
+  if element.elements.is_empty() && element.text.is_empty() {
+    output.push_str("/>\n");
+    return;
+  }
+  output.push('>');
+  encode_entities(&element.text, output);
+  if !element.elements.is_empty() {
+    output.push('\n');
+    for child in &element.elements {
+      write_xml(child, depth + 1, output);
+    }
+    output.push_str(&indent);
+  }
+  output.push_str("</");
+  output.push_str(&element.name);
+  output.push_str(">\n");
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_parse_xml() {
+    let xml = parse_xml(
+      "\u{feff}<?xml version=\"1.0\"?>\n<!DOCTYPE score [<!ENTITY x \"y\">]>\n<!-- <ignored> -->\n\
+       <root a='1 &amp; 2' b=\"&#x41;\">\n  <child>Tom &amp; Jerry &lt;3</child>\n  <empty/>\n\
+       <mixed><font size=\"3\"/>Title<![CDATA[ <raw> ]]></mixed>\n</root>",
+    )
+    .unwrap();
+    assert_eq!(xml.name, "root");
+    assert_eq!(xml.attribute("a"), Some("1 & 2"));
+    assert_eq!(xml.attribute("b"), Some("A"));
+    assert_eq!(xml.child_text("child"), Some("Tom & Jerry <3"));
+    assert!(xml.has_child("empty"));
+    assert_eq!(xml.child_text("mixed"), Some("Title <raw>"));
+    assert!(parse_xml("<root><child></root>").is_err());
+    assert!(parse_xml("<root>").is_err());
+  }
+
+  #[test]
+  fn test_write_xml() {
+    let mut root = xml_element("root", &[("a", "1 & \"2\"")], Vec::new());
+    root.elements.push(xml_text_element("child", "Tom & Jerry <3"));
+    root.elements.push(xml_element("empty", &[], Vec::new()));
+    let mut output = String::new();
+    write_xml(&root, 0, &mut output);
+    assert_eq!(
+      output,
+      "<root a=\"1 &amp; &quot;2&quot;\">\n  <child>Tom &amp; Jerry &lt;3</child>\n  <empty/>\n</root>\n"
+    );
+    let parsed = parse_xml(&output).unwrap();
+    assert_eq!(parsed.attribute("a"), Some("1 & \"2\""));
+    assert_eq!(parsed.child_text("child"), Some("Tom & Jerry <3"));
+  }
+}
@@ -0,0 +1,128 @@
+use alloc::string::String;
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const END_OF_CENTRAL_DIRECTORY_MIN_LEN: usize = 22;
+const COMPRESSION_STORED: u16 = 0;
+const COMPRESSION_DEFLATE: u16 = 8;
+const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+struct ZipEntry {
+  name: String,
+  compression_method: u16,
+  compressed_size: usize,
+  local_header_offset: usize,
+}
+
+/// Read-only view into the files stored within a ZIP archive.
+///
+/// Only the features required by compressed music formats are supported,
+/// namely stored and deflated entries within a single-disk archive.
+pub(crate) struct ZipArchive<'a> {
+  data: &'a [u8],
+  entries: Vec<ZipEntry>,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, String> {
+  data
+    .get(offset..offset + 2)
+    .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+    .ok_or_else(|| String::from("Unexpected end of ZIP archive"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+  data
+    .get(offset..offset + 4)
+    .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    .ok_or_else(|| String::from("Unexpected end of ZIP archive"))
+}
+
+impl<'a> ZipArchive<'a> {
+  /// Reads the central directory of the ZIP archive contained in `data`.
+  ///
+  /// # Errors
+  /// Returns an error if the data does not contain a valid ZIP archive.
+  pub(crate) fn new(data: &'a [u8]) -> Result<Self, String> {
+    if data.len() < END_OF_CENTRAL_DIRECTORY_MIN_LEN || read_u32(data, 0)? != LOCAL_FILE_HEADER_SIGNATURE {
+      return Err(String::from("Data does not contain a ZIP archive"));
+    }
+    let end_of_directory = (0..=data.len() - END_OF_CENTRAL_DIRECTORY_MIN_LEN)
+      .rev()
+      .find(|offset| read_u32(data, *offset) == Ok(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+      .ok_or("Unable to locate the central directory of the ZIP archive")?;
+    let num_entries = read_u16(data, end_of_directory + 10)?;
+    let mut offset = read_u32(data, end_of_directory + 16)? as usize;
+    let mut entries = Vec::with_capacity(usize::from(num_entries));
+    for _ in 0..num_entries {
+      if read_u32(data, offset)? != CENTRAL_DIRECTORY_SIGNATURE {
+        return Err(String::from("Corrupt central directory in ZIP archive"));
+      }
+      let name_length = usize::from(read_u16(data, offset + 28)?);
+      let extra_length = usize::from(read_u16(data, offset + 30)?);
+      let comment_length = usize::from(read_u16(data, offset + 32)?);
+      let name = data
+        .get(offset + 46..offset + 46 + name_length)
+        .ok_or("Unexpected end of ZIP archive")?;
+      entries.push(ZipEntry {
+        name: String::from_utf8_lossy(name).into_owned(),
+        compression_method: read_u16(data, offset + 10)?,
+        compressed_size: read_u32(data, offset + 20)? as usize,
+        local_header_offset: read_u32(data, offset + 42)? as usize,
+      });
+      offset += 46 + name_length + extra_length + comment_length;
+    }
+    Ok(Self { data, entries })
+  }
+
+  /// Returns an iterator over the names of all files in the archive.
+  pub(crate) fn file_names(&self) -> impl Iterator<Item = &str> {
+    self.entries.iter().map(|entry| entry.name.as_str())
+  }
+
+  /// Reads and decompresses the contents of the file with the given `name`.
+  ///
+  /// # Errors
+  /// Returns an error if the file does not exist in the archive or cannot be decompressed
+  /// within the maximum supported file size.
+  pub(crate) fn read_file(&self, name: &str) -> Result<Vec<u8>, String> {
+    let entry = self
+      .entries
+      .iter()
+      .find(|entry| entry.name == name)
+      .ok_or_else(|| format!("File '{name}' not found in ZIP archive"))?;
+    let header = entry.local_header_offset;
+    if read_u32(self.data, header)? != LOCAL_FILE_HEADER_SIGNATURE {
+      return Err(format!("Corrupt local header for '{name}' in ZIP archive"));
+    }
+    let data_start =
+      header + 30 + usize::from(read_u16(self.data, header + 26)?) + usize::from(read_u16(self.data, header + 28)?);
+    let contents = self
+      .data
+      .get(data_start..data_start + entry.compressed_size)
+      .ok_or("Unexpected end of ZIP archive")?;
+    match entry.compression_method {
+      COMPRESSION_STORED => Ok(contents.to_vec()),
+      COMPRESSION_DEFLATE => decompress_to_vec_with_limit(contents, MAX_DECOMPRESSED_SIZE)
+        .map_err(|err| format!("Unable to decompress '{name}' from ZIP archive: {err}")),
+      method => Err(format!("Unsupported ZIP compression method {method} for '{name}'")),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_zip_archive() {
+    let data = std::fs::read("examples/Billie Jean.mscz").unwrap();
+    let archive = ZipArchive::new(&data).unwrap();
+    assert!(archive.file_names().any(|name| name == "Billie Jean.mscx"));
+    let container = archive.read_file("META-INF/container.xml").unwrap();
+    assert!(String::from_utf8(container).unwrap().contains("<rootfiles>"));
+    assert!(archive.read_file("missing.xml").is_err());
+    assert!(ZipArchive::new(b"not a zip archive at all").is_err());
+  }
+}
@@ -1,16 +1,18 @@
-use crate::context::{generate_id, Tempo};
+use crate::context::{generate_id, Key, Tempo, Tuning};
 use crate::modification::{ChordModification, ChordModificationType, NoteModification};
 use crate::note::{Accidental, Duration, Note, Pitch};
 use crate::temporal::Timeslice;
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 
-#[derive(Clone, Debug, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone, Debug, Eq, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+)]
 pub enum ChordContent {
   Note(Note),
 }
 
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct Chord {
   id: usize,
   content: Vec<ChordContent>,
@@ -27,6 +29,14 @@ impl Chord {
     }
   }
 
+  pub(crate) fn assign_fret_positions(&mut self, tuning: &Tuning, key: Key, hand_position: &mut Option<u8>) {
+    tuning.assign_note_positions(
+      self.iter_mut().map(|ChordContent::Note(note)| note).collect(),
+      key,
+      hand_position,
+    );
+  }
+
   #[must_use]
   pub const fn get_id(&self) -> usize {
     self.id
@@ -11,3 +11,19 @@ pub use part::{Part, PartContent};
 pub use phrase::{Phrase, PhraseContent};
 pub use section::{Section, SectionContent};
 pub use staff::{Staff, StaffContent};
+
+#[cfg(feature = "serde")]
+amm_internal::impl_serde!(
+  Chord,
+  ChordContent,
+  MultiVoice,
+  MultiVoiceContent,
+  Part,
+  PartContent,
+  Phrase,
+  PhraseContent,
+  Section,
+  SectionContent,
+  Staff,
+  StaffContent
+);
@@ -2,20 +2,22 @@ use super::{
   chord::{Chord, ChordContent},
   phrase::{Phrase, PhraseContent, PhraseTimesliceIter},
 };
-use crate::context::{generate_id, Tempo};
+use crate::context::{generate_id, Key, Tempo, Tuning};
 use crate::modification::PhraseModificationType;
 use crate::note::{Duration, DurationType, Note};
 use crate::temporal::Timeslice;
 use alloc::collections::VecDeque;
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 
-#[derive(Clone, Debug, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone, Debug, Eq, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+)]
 pub enum MultiVoiceContent {
   Phrase(Phrase),
 }
 
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct MultiVoice {
   id: usize,
   content: Vec<MultiVoiceContent>,
@@ -310,6 +312,12 @@ impl MultiVoice {
     phrase
   }
 
+  pub(crate) fn assign_fret_positions(&mut self, tuning: &Tuning, key: Key, hand_position: &mut Option<u8>) {
+    self
+      .iter_mut()
+      .for_each(|MultiVoiceContent::Phrase(phrase)| phrase.assign_fret_positions(tuning, key, hand_position));
+  }
+
   #[must_use]
   pub const fn get_id(&self) -> usize {
     self.id
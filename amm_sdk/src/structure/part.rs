@@ -1,19 +1,23 @@
 use super::{chord::Chord, multivoice::MultiVoice, phrase::Phrase, section::Section, staff::Staff};
-use crate::context::{generate_id, Tempo};
+use crate::context::{generate_id, Key, Tempo, Tuning};
+use crate::modification::NoteModificationType;
 use crate::note::{Duration, Note};
 use crate::temporal::Timeslice;
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 
-#[derive(Clone, Debug, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone, Debug, Eq, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+)]
 pub enum PartContent {
   Section(Section),
 }
 
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct Part {
   id: usize,
   name: String,
+  tuning: Option<Tuning>,
   content: Vec<PartContent>,
 }
 
@@ -23,6 +27,7 @@ impl Part {
     Self {
       id: generate_id(),
       name: String::from(name),
+      tuning: None,
       content: Vec::new(),
     }
   }
@@ -39,6 +44,7 @@ impl Part {
     Self {
       id: generate_id(),
       name: self.name.clone(),
+      tuning: self.tuning.clone(),
       content: self
         .iter()
         .map(|PartContent::Section(section)| PartContent::Section(section.flatten()))
@@ -52,10 +58,9 @@ impl Part {
       .get_staff_names()
       .iter()
       .map(|staff_name| {
-        (
-          String::from(staff_name),
-          Self::new((self.name.clone() + "_" + staff_name).as_str()),
-        )
+        let mut part = Self::new((self.name.clone() + "_" + staff_name).as_str());
+        part.tuning.clone_from(&self.tuning);
+        (String::from(staff_name), part)
       })
       .collect();
     self.iter().for_each(|PartContent::Section(section)| {
@@ -83,6 +88,39 @@ impl Part {
     self
   }
 
+  #[must_use]
+  pub fn get_tuning(&self) -> Option<&Tuning> {
+    self.tuning.as_ref()
+  }
+
+  pub fn set_tuning(&mut self, tuning: Option<Tuning>) -> &mut Self {
+    self.tuning = tuning;
+    self
+  }
+
+  #[must_use]
+  pub fn has_fret_positions(&self) -> bool {
+    self.iter_timeslices().any(|timeslice| {
+      timeslice.content.iter().any(|content| {
+        content
+          .note
+          .iter_modifications()
+          .any(|modification| matches!(modification.r#type, NoteModificationType::FretPosition { .. }))
+      })
+    })
+  }
+
+  pub fn assign_fret_positions(&mut self, starting_key: Option<Key>) -> &mut Self {
+    if let Some(tuning) = self.tuning.take() {
+      let (mut key, mut hand_position) = (starting_key.unwrap_or_default(), None);
+      self
+        .iter_mut()
+        .for_each(|PartContent::Section(section)| section.assign_fret_positions(&tuning, &mut key, &mut hand_position));
+      self.tuning = Some(tuning);
+    }
+    self
+  }
+
   pub fn add_section(&mut self, name: &str) -> &mut Section {
     self.content.push(PartContent::Section(Section::new(name)));
     match self.content.last_mut() {
@@ -313,6 +351,7 @@ impl Clone for Part {
     Self {
       id: generate_id(),
       name: self.name.clone(),
+      tuning: self.tuning.clone(),
       content: self.content.clone(),
     }
   }
@@ -320,7 +359,7 @@ impl Clone for Part {
 
 impl PartialEq for Part {
   fn eq(&self, other: &Self) -> bool {
-    self.content == other.content && self.name == other.name
+    self.content == other.content && self.name == other.name && self.tuning == other.tuning
   }
 }
 
@@ -2,14 +2,16 @@ use super::{
   chord::Chord,
   multivoice::{MultiVoice, MultiVoiceTimesliceIter},
 };
-use crate::context::{generate_id, Tempo};
+use crate::context::{generate_id, Key, Tempo, Tuning};
 use crate::modification::{PhraseModification, PhraseModificationType};
 use crate::note::{Accidental, Duration, Note, Pitch};
 use crate::temporal::Timeslice;
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 
-#[derive(Clone, Debug, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone, Debug, Eq, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+)]
 pub enum PhraseContent {
   Note(Note),
   Chord(Chord),
@@ -17,7 +19,7 @@ pub enum PhraseContent {
   MultiVoice(MultiVoice),
 }
 
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct Phrase {
   id: usize,
   pub(crate) content: Vec<PhraseContent>,
@@ -101,6 +103,15 @@ impl Phrase {
     flat_phrase
   }
 
+  pub(crate) fn assign_fret_positions(&mut self, tuning: &Tuning, key: Key, hand_position: &mut Option<u8>) {
+    self.iter_mut().for_each(|item| match item {
+      PhraseContent::Note(note) => tuning.assign_note_positions(vec![note], key, hand_position),
+      PhraseContent::Chord(chord) => chord.assign_fret_positions(tuning, key, hand_position),
+      PhraseContent::Phrase(phrase) => phrase.assign_fret_positions(tuning, key, hand_position),
+      PhraseContent::MultiVoice(multivoice) => multivoice.assign_fret_positions(tuning, key, hand_position),
+    });
+  }
+
   #[must_use]
   pub const fn get_id(&self) -> usize {
     self.id
@@ -4,20 +4,22 @@ use super::{
   phrase::Phrase,
   staff::{Staff, StaffTimesliceIter},
 };
-use crate::context::{generate_id, Tempo};
+use crate::context::{generate_id, Key, Tempo, Tuning};
 use crate::modification::{SectionModification, SectionModificationType};
 use crate::note::{Duration, DurationType, Note, Pitch};
 use crate::temporal::Timeslice;
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 
-#[derive(Clone, Debug, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone, Debug, Eq, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+)]
 pub enum SectionContent {
   Staff(Staff),
   Section(Section),
 }
 
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct Section {
   id: usize,
   name: String,
@@ -147,6 +149,17 @@ impl Section {
     }
   }
 
+  pub(crate) fn assign_fret_positions(&mut self, tuning: &Tuning, key: &mut Key, hand_position: &mut Option<u8>) {
+    let starting_key = *key;
+    self.iter_mut().for_each(|item| match item {
+      SectionContent::Staff(staff) => {
+        *key = starting_key;
+        staff.assign_fret_positions(tuning, key, hand_position);
+      }
+      SectionContent::Section(section) => section.assign_fret_positions(tuning, key, hand_position),
+    });
+  }
+
   #[must_use]
   pub const fn get_id(&self) -> usize {
     self.id
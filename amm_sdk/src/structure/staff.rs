@@ -3,14 +3,16 @@ use super::{
   multivoice::{MultiVoice, MultiVoiceTimesliceIter},
   phrase::{Phrase, PhraseContent, PhraseTimesliceIter},
 };
-use crate::context::{generate_id, Tempo};
+use crate::context::{generate_id, Key, Tempo, Tuning};
 use crate::modification::{Direction, DirectionType};
 use crate::note::{Accidental, Duration, Note, Pitch};
 use crate::temporal::Timeslice;
 use amm_internal::amm_prelude::*;
-use amm_macros::{JsonDeserialize, JsonSerialize};
+use amm_macros::{BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize};
 
-#[derive(Clone, Debug, Eq, PartialEq, JsonDeserialize, JsonSerialize)]
+#[derive(
+  Clone, Debug, Eq, PartialEq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize,
+)]
 pub enum StaffContent {
   Note(Note),
   Chord(Chord),
@@ -19,7 +21,7 @@ pub enum StaffContent {
   Direction(Direction),
 }
 
-#[derive(Debug, Default, Eq, JsonDeserialize, JsonSerialize)]
+#[derive(Debug, Default, Eq, BinaryDeserialize, BinarySerialize, JsonDeserialize, JsonSchema, JsonSerialize)]
 pub struct Staff {
   id: usize,
   name: String,
@@ -100,6 +102,20 @@ impl Staff {
     }
   }
 
+  pub(crate) fn assign_fret_positions(&mut self, tuning: &Tuning, key: &mut Key, hand_position: &mut Option<u8>) {
+    self.iter_mut().for_each(|item| match item {
+      StaffContent::Note(note) => tuning.assign_note_positions(vec![note], *key, hand_position),
+      StaffContent::Chord(chord) => chord.assign_fret_positions(tuning, *key, hand_position),
+      StaffContent::Phrase(phrase) => phrase.assign_fret_positions(tuning, *key, hand_position),
+      StaffContent::MultiVoice(multivoice) => multivoice.assign_fret_positions(tuning, *key, hand_position),
+      StaffContent::Direction(direction) => {
+        if let DirectionType::KeyChange { key: new_key } = direction.r#type {
+          *key = new_key;
+        }
+      }
+    });
+  }
+
   #[must_use]
   pub const fn get_id(&self) -> usize {
     self.id
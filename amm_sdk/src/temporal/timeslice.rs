@@ -5,6 +5,7 @@ use alloc::{collections::BTreeMap, vec::Vec};
 use amm_internal::amm_prelude::*;
 
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct TimesliceContext {
   pub key: Key,
   pub original_tempo: Tempo,
@@ -13,6 +14,7 @@ pub struct TimesliceContext {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct TimeslicePhraseDetails {
   pub modifications: Vec<PhraseModificationType>,
   pub index_in_phrase: usize,
@@ -40,6 +42,7 @@ impl core::fmt::Display for TimeslicePhraseDetails {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct TimesliceContent {
   pub note: Note,
   pub phrase_details: Vec<TimeslicePhraseDetails>,
@@ -116,6 +119,7 @@ impl core::fmt::Display for TimesliceContent {
 }
 
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct Timeslice {
   pub arpeggiated: bool,
   pub content: Vec<TimesliceContent>,
@@ -229,6 +233,7 @@ impl core::fmt::Display for Timeslice {
 }
 
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct PartTimeslice {
   pub timeslices: BTreeMap<String, Timeslice>,
 }